@@ -6,4 +6,9 @@ fn main() {
             .file("native/flock-posix.c")
             .compile("libflock-posix.a");
     }
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/skytable.proto");
+        tonic_build::compile_protos("proto/skytable.proto").expect("failed to compile protos");
+    }
 }
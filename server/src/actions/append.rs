@@ -0,0 +1,59 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `APPEND` queries
+//! This module provides functions to work with `APPEND` queries
+
+use crate::{dbnet::prelude::*, util::compiler};
+
+action!(
+    /// Run an `APPEND` query
+    ///
+    /// Syntax: `APPEND <key> <value>`. Appends the value to the current value of the key
+    /// (creating the key if it doesn't exist) and returns the new length of the value
+    fn append(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let kve = handle.get_table_with::<P, KVEBlob>()?;
+        let (key, suffix) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are exactly 2 arguments
+            (act.next_unchecked(), act.next_unchecked())
+        };
+        // since the current value is valid, the result is valid if the suffix is
+        if compiler::unlikely(!kve.get_double_encoder()(key, suffix)) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        if registry::state_okay() {
+            let len = kve.append_unchecked(key, suffix);
+            con.write_usize(len).await?;
+        } else {
+            return util::err(P::RCODE_SERVER_ERR);
+        }
+        Ok(())
+    }
+);
@@ -0,0 +1,70 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `CAS` queries
+//! This module provides functions to work with `CAS` (compare-and-swap) queries
+
+use crate::{corestore::SharedSlice, dbnet::prelude::*};
+
+action!(
+    /// Run a `CAS` query
+    ///
+    /// Syntax: `CAS <key> <expected> <new>`. The value of `key` is set to `new` only if its
+    /// current value is `expected`; the comparison and the swap happen under the same
+    /// write lock
+    fn cas(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 3)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let kve = handle.get_table_with::<P, KVEBlob>()?;
+        let (key, expected, new) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are exactly 3 arguments
+            (
+                act.next_unchecked(),
+                act.next_unchecked(),
+                act.next_unchecked(),
+            )
+        };
+        if !kve.get_double_encoder()(key, new) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        if registry::state_okay() {
+            match kve.compare_and_swap_unchecked(
+                SharedSlice::new(key),
+                expected,
+                SharedSlice::new(new),
+            ) {
+                Some(true) => con._write_raw(P::RCODE_OKAY).await?,
+                Some(false) => con._write_raw(P::RSTRING_CAS_MISMATCH).await?,
+                None => con._write_raw(P::RCODE_NIL).await?,
+            }
+        } else {
+            return util::err(P::RCODE_SERVER_ERR);
+        }
+        Ok(())
+    }
+);
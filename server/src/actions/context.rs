@@ -0,0 +1,57 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use crate::dbnet::prelude::*;
+
+action!(
+    /// Returns what the connection is running as
+    ///
+    /// Syntax: `CONTEXT`. This returns an array of `[user, role, keyspace, protocol]`: the user
+    /// that is logged in on this connection (null if authn is disabled), their role (like in
+    /// `AUTH LISTUSER`), the current keyspace and the version of the protocol that the
+    /// connection speaks
+    fn context(
+        handle: &Corestore,
+        con: &mut Connection<C, P>,
+        auth: &mut AuthProviderHandle,
+        act: ActionIter<'a>,
+    ) {
+        ensure_length::<P>(act.len(), |len| len == 0)?;
+        let provider = auth.provider();
+        con.write_typed_array_header(4, b'+').await?;
+        match provider.current_user() {
+            Some(user) => con.write_typed_array_element(user).await?,
+            None => con.write_typed_array_element_null().await?,
+        }
+        con.write_typed_array_element(provider.role().as_bytes()).await?;
+        match handle.get_ids().0 {
+            Some(keyspace) => con.write_typed_array_element(keyspace).await?,
+            None => con.write_typed_array_element_null().await?,
+        }
+        con.write_typed_array_element(P::PROTOCOL_VERSIONSTRING.as_bytes()).await?;
+        Ok(())
+    }
+);
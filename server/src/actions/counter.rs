@@ -0,0 +1,116 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Counter queries
+//!
+//! This module provides functions to work with `INCR`, `DECR`, `INCRBY` and `DECRBY` queries.
+//! The value is parsed as a 64-bit signed integer and updated under the shard's write lock, so
+//! concurrent increments are never lost
+
+use crate::{
+    actions::ActionResult,
+    dbnet::{prelude::*, BufferedSocketStream},
+    kvengine::CounterError,
+    util::compiler,
+};
+
+action!(
+    /// Run an `INCR` query
+    ///
+    /// Syntax: `INCR <key>`. Returns the new value
+    fn incr(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        self::increment(handle, con, act, 1).await
+    }
+    /// Run a `DECR` query
+    ///
+    /// Syntax: `DECR <key>`. Returns the new value
+    fn decr(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        self::increment(handle, con, act, -1).await
+    }
+);
+
+action!(
+    /// Run an `INCRBY` query
+    ///
+    /// Syntax: `INCRBY <key> <delta>`. Returns the new value
+    fn incrby(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2)?;
+        let delta = self::parse_delta::<P>(act.next_back().unwrap_or_aerr::<P>()?)?;
+        self::increment(handle, con, act, delta).await
+    }
+    /// Run a `DECRBY` query
+    ///
+    /// Syntax: `DECRBY <key> <delta>`. Returns the new value
+    fn decrby(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2)?;
+        let delta = self::parse_delta::<P>(act.next_back().unwrap_or_aerr::<P>()?)?;
+        match delta.checked_neg() {
+            Some(delta) => self::increment(handle, con, act, delta).await,
+            None => util::err(P::RSTRING_INTEGER_OVERFLOW),
+        }
+    }
+);
+
+fn parse_delta<P: ProtocolSpec>(delta: &[u8]) -> ActionResult<i64> {
+    match core::str::from_utf8(delta).map(str::parse::<i64>) {
+        Ok(Ok(delta)) => Ok(delta),
+        _ => util::err(P::RSTRING_NOT_AN_INTEGER),
+    }
+}
+
+/// Add `delta` to the counter in the first key of `act` and write out the new value
+async fn increment<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    mut act: ActionIter<'a>,
+    delta: i64,
+) -> ActionResult<()> {
+    if !registry::memory_okay() {
+        return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+    }
+    let kve = handle.get_table_with::<P, KVEBlob>()?;
+    let key = act.next().unwrap_or_aerr::<P>()?;
+    if compiler::unlikely(!kve.is_key_ok(key)) {
+        return util::err(P::RCODE_ENCODING_ERROR);
+    }
+    if !registry::state_okay() {
+        return util::err(P::RCODE_SERVER_ERR);
+    }
+    match kve.increment_unchecked(key, delta) {
+        Ok(new) => {
+            con.write_mono_length_prefixed_with_tsymbol(
+                new.to_string().as_bytes(),
+                kve.get_value_tsymbol(),
+            )
+            .await?
+        }
+        Err(CounterError::NotAnInteger) => return util::err(P::RSTRING_NOT_AN_INTEGER),
+        Err(CounterError::Overflow) => return util::err(P::RSTRING_INTEGER_OVERFLOW),
+    }
+    Ok(())
+}
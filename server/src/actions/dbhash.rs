@@ -0,0 +1,184 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `DBHASH` and `HASHRANGE` queries
+//!
+//! Both return a digest of the keys in a table (and their values) that is the same on any two
+//! nodes holding exactly the same data, no matter in which order the keys were written. This
+//! makes it cheap to check that two nodes are in sync: only the digests have to be compared,
+//! and `HASHRANGE` can then be used to narrow a difference down to a range of keys.
+//!
+//! Every key is hashed along with its value using 64-bit FNV-1a (with every item prefixed by
+//! its length), and the result is mixed with the finalizer of SplitMix64. Since sets, hashes
+//! and sorted sets are unordered, the hashes of their items are added up instead of being
+//! chained, and so are the hashes of the keys in a table. TTLs are left out since the deadline
+//! of a key can differ slightly from one node to another, but expired keys are skipped
+
+use {
+    crate::{
+        corestore::{
+            table::{DataModel, Table},
+            SharedSlice,
+        },
+        dbnet::prelude::*,
+        kvengine,
+    },
+    std::collections::HashSet,
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A 64-bit FNV-1a hasher
+struct Hasher(u64);
+
+impl Hasher {
+    const fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+    fn write_raw(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+    /// Hash an item, prefixed by its length so that `["ab", "c"]` and `["a", "bc"]` differ
+    fn write(&mut self, item: &[u8]) {
+        self.write_raw(&(item.len() as u64).to_le_bytes());
+        self.write_raw(item);
+    }
+    fn write_u64(&mut self, int: u64) {
+        self.write_raw(&int.to_le_bytes());
+    }
+    /// Returns the (mixed) hash
+    const fn finish(&self) -> u64 {
+        let mut hash = self.0;
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+        hash ^ (hash >> 31)
+    }
+}
+
+/// Returns the hash of an unordered group of items, each of which is hashed by `hash_item`
+fn hash_unordered<I: IntoIterator>(group: I, hash_item: impl Fn(&mut Hasher, I::Item)) -> u64 {
+    group.into_iter().fold(0u64, |sum, item| {
+        let mut hasher = Hasher::new();
+        hash_item(&mut hasher, item);
+        sum.wrapping_add(hasher.finish())
+    })
+}
+
+/// Returns the digest of the live keys in `table` that start with `prefix` (and their values)
+pub fn table_digest(table: &Table, prefix: &[u8]) -> u64 {
+    macro_rules! digest {
+        ($engine:expr, |$hasher:ident, $value:ident| $hash_value:expr) => {{
+            // collect the expired keys up front so that we never hold a lock on the expiry map
+            // while a shard is locked
+            let now = kvengine::now_millis();
+            let expired: HashSet<SharedSlice> = $engine
+                .get_expiry_ref()
+                .iter()
+                .filter(|kv| *kv.value() <= now)
+                .map(|kv| kv.key().clone())
+                .collect();
+            $engine
+                .get_inner_ref()
+                .iter()
+                .filter(|kv| kv.key().starts_with(prefix) && !expired.contains(kv.key()))
+                .fold(0u64, |digest, kv| {
+                    let mut $hasher = Hasher::new();
+                    $hasher.write(kv.key());
+                    let $value = kv.value();
+                    $hash_value;
+                    digest.wrapping_add($hasher.finish())
+                })
+        }};
+    }
+    match table.get_model_ref() {
+        DataModel::KV(kve) => digest!(kve, |hasher, value| hasher.write(value)),
+        DataModel::KVExtListmap(kve) => digest!(kve, |hasher, list| {
+            let list = list.read();
+            hasher.write_u64(list.len() as u64);
+            list.iter().for_each(|item| hasher.write(item))
+        }),
+        DataModel::KVExtSetmap(kve) => digest!(kve, |hasher, set| {
+            let members = hash_unordered(set.read().iter(), |hasher, member| hasher.write(member));
+            hasher.write_u64(members)
+        }),
+        DataModel::KVExtHashmap(kve) => digest!(kve, |hasher, hash| {
+            let fields = hash_unordered(hash.read().iter(), |hasher, (field, value)| {
+                hasher.write(field);
+                hasher.write(value);
+            });
+            hasher.write_u64(fields)
+        }),
+        DataModel::KVExtZSetmap(kve) => digest!(kve, |hasher, zset| {
+            let members = hash_unordered(zset.read().iter(), |hasher, (member, score)| {
+                hasher.write(member);
+                hasher.write(&score.get().to_le_bytes());
+            });
+            hasher.write_u64(members)
+        }),
+    }
+}
+
+action!(
+    /// Run a `DBHASH [<entity>]` query
+    ///
+    /// Returns the digest of every key in the table
+    fn dbhash(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len < 2)?;
+        let digest = match act.next() {
+            Some(raw_entity) => {
+                let entity = handle_entity!(con, raw_entity);
+                self::table_digest(&get_tbl!(&entity, handle, con), b"")
+            }
+            None => self::table_digest(get_tbl_ref!(handle, con), b""),
+        };
+        con.write_string(&format!("{digest:016x}")).await?;
+        Ok(())
+    }
+
+    /// Run a `HASHRANGE <prefix> [<entity>]` query
+    ///
+    /// Returns the digest of the keys in the table that start with `prefix`
+    fn hashrange(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1 || len == 2)?;
+        let prefix = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is at least one argument
+            act.next_unchecked()
+        };
+        let digest = match act.next() {
+            Some(raw_entity) => {
+                let entity = handle_entity!(con, raw_entity);
+                self::table_digest(&get_tbl!(&entity, handle, con), prefix)
+            }
+            None => self::table_digest(get_tbl_ref!(handle, con), prefix),
+        };
+        con.write_string(&format!("{digest:016x}")).await?;
+        Ok(())
+    }
+);
@@ -33,7 +33,7 @@ use crate::{
 };
 
 action!(
-    /// Run a `DEL` query
+    /// Run a `DEL` query (`MDEL` is an alias for the same action)
     ///
     /// Do note that this function is blocking since it acquires a write lock.
     /// It will write an entire datagroup, for this `del` action
@@ -73,6 +73,15 @@ action!(
             DataModel::KVExtListmap(kvlmap) => {
                 remove!(kvlmap)
             }
+            DataModel::KVExtSetmap(kvsmap) => {
+                remove!(kvsmap)
+            }
+            DataModel::KVExtHashmap(kvhmap) => {
+                remove!(kvhmap)
+            }
+            DataModel::KVExtZSetmap(kvzmap) => {
+                remove!(kvzmap)
+            }
             #[allow(unreachable_patterns)]
             _ => return util::err(P::RSTRING_WRONG_MODEL),
         }
@@ -0,0 +1,185 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `DUMP` and `RESTORE` queries
+//!
+//! `DUMP <key>` serializes the value of a key (along with its type and time-to-live) into an
+//! opaque blob, which `RESTORE <key> <blob>` turns back into a key, possibly on another server.
+//! A blob looks like:
+//! ```text
+//! [version: u8][ttl: u64][dump][checksum: u32]
+//! ```
+//! The TTL is the number of milliseconds that the key had left when it was dumped (`0` if it
+//! doesn't expire), so that the clocks of the two servers don't have to agree. The dump holds
+//! the type and the items of the value, just like the dumps used to move slots (see
+//! [`migrate`](crate::cluster::migrate)), and the checksum is the CRC32 of everything before
+//! it. All the numbers are little-endian, so blobs are portable across platforms
+
+use {
+    crate::{
+        cluster::migrate::{self, RestoreError},
+        dbnet::prelude::*,
+        kvengine,
+    },
+    core::mem,
+};
+
+/// The version of the blobs that we write. A blob of any other version is rejected
+const DUMP_VERSION: u8 = 1;
+const REPLACE: &[u8] = b"replace";
+
+/// Wrap a dump of a key with the given TTL (in milliseconds) into a blob
+fn encode_blob(dump: &[u8], ttl: u64) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(1 + mem::size_of::<u64>() + dump.len() + 4);
+    blob.push(DUMP_VERSION);
+    blob.extend_from_slice(&ttl.to_le_bytes());
+    blob.extend_from_slice(dump);
+    let checksum = crc32fast::hash(&blob);
+    blob.extend_from_slice(&checksum.to_le_bytes());
+    blob
+}
+
+/// Returns the dump and the TTL (in milliseconds) in a blob. `None` is returned if the blob is
+/// corrupted or of another version
+fn decode_blob(blob: &[u8]) -> Option<(&[u8], u64)> {
+    const HEADER_LEN: usize = 1 + mem::size_of::<u64>();
+    if blob.len() < HEADER_LEN + mem::size_of::<u32>() {
+        return None;
+    }
+    let (body, checksum) = blob.split_at(blob.len() - mem::size_of::<u32>());
+    if body[0] != DUMP_VERSION || crc32fast::hash(body).to_le_bytes() != checksum {
+        return None;
+    }
+    let ttl = u64::from_le_bytes(body[1..HEADER_LEN].try_into().ok()?);
+    Some((&body[HEADER_LEN..], ttl))
+}
+
+action!(
+    /// Run a `DUMP <key>` query
+    ///
+    /// Returns a blob holding the value of the key (with its type and TTL) or `Nil` if the key
+    /// doesn't exist
+    fn dump(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let key = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is exactly one argument
+            act.next_unchecked()
+        };
+        let table = match handle.get_ctable_ref() {
+            Some(table) => table,
+            None => return util::err(P::RSTRING_DEFAULT_UNSET),
+        };
+        match migrate::dump_key(table, key) {
+            Some((dump, deadline)) => {
+                let ttl = match deadline {
+                    0 => 0,
+                    // a key that is about to expire still has to have a TTL
+                    deadline => deadline.saturating_sub(kvengine::now_millis()).max(1),
+                };
+                con.write_binary(&self::encode_blob(&dump, ttl)).await?
+            }
+            None => con._write_raw(P::RCODE_NIL).await?,
+        }
+        Ok(())
+    }
+
+    /// Run a `RESTORE <key> <blob> [REPLACE]` query
+    ///
+    /// Creates the key from a blob returned by `DUMP`. The table needs to have the model that the
+    /// value was dumped from. Unless `REPLACE` is passed, a key that already exists isn't
+    /// touched and an overwrite error is returned
+    fn restore(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2 || len == 3)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let (key, blob) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are at least two arguments
+            (act.next_unchecked(), act.next_unchecked())
+        };
+        let replace = match act.next() {
+            Some(flag) if flag.eq_ignore_ascii_case(REPLACE) => true,
+            Some(_) => return util::err(P::RCODE_ACTION_ERR),
+            None => false,
+        };
+        let (dump, ttl) = match self::decode_blob(blob) {
+            Some(decoded) => decoded,
+            None => return util::err(P::RSTRING_BAD_DUMP),
+        };
+        let table = match handle.get_ctable_ref() {
+            Some(table) => table,
+            None => return util::err(P::RSTRING_DEFAULT_UNSET),
+        };
+        if !registry::state_okay() {
+            return util::err(P::RCODE_SERVER_ERR);
+        }
+        if !replace && migrate::table_has_key(table, key) {
+            return util::err(P::RCODE_OVERWRITE_ERR);
+        }
+        let deadline = match ttl {
+            0 => 0,
+            // relative to the original write if this is being replayed
+            ttl => kvengine::write_time().saturating_add(ttl),
+        };
+        match migrate::restore_key(table, key, dump, deadline) {
+            Ok(()) => con._write_raw(P::RCODE_OKAY).await?,
+            Err(RestoreError::WrongModel) => return util::err(P::RSTRING_WRONG_MODEL),
+            Err(RestoreError::Corrupted) => return util::err(P::RSTRING_BAD_DUMP),
+            Err(RestoreError::Encoding) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_blob, encode_blob, DUMP_VERSION};
+
+    #[test]
+    fn blob_roundtrip() {
+        let blob = encode_blob(b"dump", 1500);
+        assert_eq!(blob[0], DUMP_VERSION);
+        assert_eq!(decode_blob(&blob), Some((&b"dump"[..], 1500)));
+        assert_eq!(decode_blob(&encode_blob(b"", 0)), Some((&b""[..], 0)));
+    }
+
+    #[test]
+    fn bad_blobs() {
+        let blob = encode_blob(b"dump", 0);
+        // a flipped bit
+        let mut corrupted = blob.clone();
+        corrupted[10] ^= 1;
+        assert_eq!(decode_blob(&corrupted), None);
+        // another version (with a valid checksum)
+        let mut newer = blob[..blob.len() - 4].to_vec();
+        newer[0] = DUMP_VERSION + 1;
+        let checksum = crc32fast::hash(&newer);
+        newer.extend_from_slice(&checksum.to_le_bytes());
+        assert_eq!(decode_blob(&newer), None);
+        // truncated
+        assert_eq!(decode_blob(&blob[..8]), None);
+    }
+}
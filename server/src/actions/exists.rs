@@ -54,6 +54,9 @@ action!(
         match tbl.get_model_ref() {
             DataModel::KV(kve) => exists!(kve),
             DataModel::KVExtListmap(kve) => exists!(kve),
+            DataModel::KVExtSetmap(kve) => exists!(kve),
+            DataModel::KVExtHashmap(kve) => exists!(kve),
+            DataModel::KVExtZSetmap(kve) => exists!(kve),
             #[allow(unreachable_patterns)]
             _ => return util::err(P::RSTRING_WRONG_MODEL),
         }
@@ -0,0 +1,166 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Expiry actions
+//!
+//! This module provides the `EXPIRE`, `TTL`, `PERSIST` and `SETEX` actions that can be used
+//! to manage the time-to-live of keys. Expired keys are purged lazily (on access) and by the
+//! expiry sweeper service
+
+use crate::{
+    corestore::{table::DataModel, SharedSlice},
+    dbnet::prelude::*,
+    kvengine,
+};
+
+/// Run the provided expression against the key/value engine of the current table, returning
+/// an encoding error if the key is not valid for the table
+macro_rules! with_engine {
+    ($handle:expr, $con:expr, $key:expr, |$engine:ident| $ret:expr) => {{
+        let tbl = get_tbl_ref!($handle, $con);
+        match tbl.get_model_ref() {
+            DataModel::KV($engine) if $engine.is_key_ok($key) => $ret,
+            DataModel::KVExtListmap($engine) if $engine.is_key_ok($key) => $ret,
+            DataModel::KVExtSetmap($engine) if $engine.is_key_ok($key) => $ret,
+            DataModel::KVExtHashmap($engine) if $engine.is_key_ok($key) => $ret,
+            DataModel::KVExtZSetmap($engine) if $engine.is_key_ok($key) => $ret,
+            _ => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+    }};
+}
+
+/// Parse the provided number of seconds, returning the deadline (in milliseconds since the
+/// epoch) that is that many seconds after the write (see [`kvengine::write_time`])
+fn deadline_after(seconds: &[u8]) -> Option<u64> {
+    let seconds = String::from_utf8_lossy(seconds).parse::<u64>().ok()?;
+    Some(kvengine::write_time().saturating_add(seconds.saturating_mul(1000)))
+}
+
+action!(
+    /// Run an `EXPIRE` query
+    ///
+    /// Syntax: `EXPIRE <key> <seconds>`. Sets the time-to-live of an existing key
+    fn expire(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2)?;
+        let (key, seconds) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are two arguments
+            (act.next_unchecked(), act.next_unchecked())
+        };
+        let at = match deadline_after(seconds) {
+            Some(at) => at,
+            None => return util::err(P::RCODE_WRONGTYPE_ERR),
+        };
+        if registry::state_okay() {
+            let did = with_engine!(handle, con, key, |kve| kve.expire_at_unchecked(key, at));
+            if did {
+                con._write_raw(P::RCODE_OKAY).await?;
+            } else {
+                con._write_raw(P::RCODE_NIL).await?;
+            }
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+
+    /// Run a `TTL` query
+    ///
+    /// Syntax: `TTL <key>`. Returns the number of seconds after which the key will expire
+    fn ttl(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let key = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is one argument
+            act.next_unchecked()
+        };
+        match with_engine!(handle, con, key, |kve| kve.ttl_unchecked(key)) {
+            Some(Some(millis)) => con.write_int64((millis + 999) / 1000).await?,
+            Some(None) => con._write_raw(P::RSTRING_NO_EXPIRY).await?,
+            None => con._write_raw(P::RCODE_NIL).await?,
+        }
+        Ok(())
+    }
+
+    /// Run a `PERSIST` query
+    ///
+    /// Syntax: `PERSIST <key>`. Removes the time-to-live of an existing key
+    fn persist(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let key = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is one argument
+            act.next_unchecked()
+        };
+        if registry::state_okay() {
+            let did = with_engine!(handle, con, key, |kve| kve.persist_unchecked(key));
+            if did {
+                con._write_raw(P::RCODE_OKAY).await?;
+            } else {
+                con._write_raw(P::RCODE_NIL).await?;
+            }
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+
+    /// Run a `SETEX` query
+    ///
+    /// Syntax: `SETEX <key> <seconds> <value>`. Sets the value of the key (whether it exists or
+    /// not) along with its time-to-live
+    fn setex(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 3)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let (key, seconds, value) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are three arguments
+            (
+                act.next_unchecked(),
+                act.next_unchecked(),
+                act.next_unchecked(),
+            )
+        };
+        let at = match deadline_after(seconds) {
+            Some(at) => at,
+            None => return util::err(P::RCODE_WRONGTYPE_ERR),
+        };
+        if registry::state_okay() {
+            let did = {
+                let writer = handle.get_table_with::<P, KVEBlob>()?;
+                writer
+                    .upsert_with_expiry(SharedSlice::new(key), SharedSlice::new(value), at)
+                    .is_ok()
+            };
+            if did {
+                con._write_raw(P::RCODE_OKAY).await?;
+            } else {
+                con._write_raw(P::RCODE_ENCODING_ERROR).await?;
+            }
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+);
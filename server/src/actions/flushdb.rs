@@ -26,19 +26,47 @@
 
 use crate::{dbnet::prelude::*, queryengine::ActionIter};
 
+const ASYNC: &[u8] = b"ASYNC";
+
 action!(
     /// Delete all the keys in the database
-    fn flushdb(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
-        ensure_length::<P>(act.len(), |len| len < 2)?;
+    ///
+    /// Syntax: `FLUSHDB [<entity>] [ASYNC]`. With `ASYNC`, the data is moved out of the table
+    /// and freed on a background thread, so that clearing a large table doesn't hold up this
+    /// connection. If auth is enabled, only root can run this
+    fn flushdb(
+        handle: &Corestore,
+        con: &mut Connection<C, P>,
+        auth: &mut AuthProviderHandle,
+        act: ActionIter<'a>,
+    ) {
+        let mut act = act;
+        ensure_length::<P>(act.len(), |len| len < 3)?;
+        auth.provider().ensure_root_if_enabled::<P>()?;
+        let is_async = act
+            .as_ref()
+            .last()
+            .map(|arg| arg.eq_ignore_ascii_case(ASYNC))
+            .unwrap_or(false);
+        let has_entity = act.len() - (is_async as usize) == 1;
+        if act.len() == 2 && !is_async {
+            return util::err(P::RCODE_ACTION_ERR);
+        }
         if registry::state_okay() {
-            if act.is_empty() {
-                // flush the current table
-                get_tbl_ref!(handle, con).truncate_table();
-            } else {
+            let table = if has_entity {
                 // flush the entity
                 let raw_entity = unsafe { act.next_unchecked() };
                 let entity = handle_entity!(con, raw_entity);
-                get_tbl!(&entity, handle, con).truncate_table();
+                get_tbl!(&entity, handle, con)
+            } else {
+                // flush the current table
+                get_tbl!(handle, con)
+            };
+            if is_async {
+                let detached = table.detach_data();
+                tokio::task::spawn_blocking(move || drop(detached));
+            } else {
+                table.truncate_table();
             }
             con._write_raw(P::RCODE_OKAY).await?;
         } else {
@@ -0,0 +1,126 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Hash actions
+//!
+//! Actions to work with the hash model, where every key maps to a set of field/value pairs.
+//! Both fields and values follow the value type of the model
+
+use crate::{corestore::SharedSlice, dbnet::prelude::*, util::compiler};
+
+action! {
+    /// Handle an `HSET` query for the hash model
+    /// Syntax: `HSET <hash> <field1> <value1> <field2> <value2> ...`
+    ///
+    /// Creates the hash if it doesn't exist and returns the number of fields that were added
+    /// (fields that already existed are simply updated)
+    fn hset(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1 && len % 2 == 1)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let hashmap = handle.get_table_with::<P, KVEHash>()?;
+        let hashname = unsafe { act.next_unchecked() };
+        let encoding_ok = hashmap.is_key_ok(hashname) && act.as_ref().all(hashmap.get_val_encoder());
+        if compiler::unlikely(!encoding_ok) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        if registry::state_okay() {
+            let mut pairs = Vec::with_capacity(act.len() / 2);
+            while let (Some(field), Some(value)) = (act.next(), act.next()) {
+                pairs.push((SharedSlice::new(field), SharedSlice::new(value)));
+            }
+            let added = hashmap.hash_set_unchecked(hashname, pairs);
+            con.write_usize(added).await?;
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+    /// Handle an `HGET` query for the hash model
+    /// Syntax: `HGET <hash> <field>`
+    ///
+    /// Returns `Nil` if either the hash or the field doesn't exist
+    fn hget(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2)?;
+        let hashmap = handle.get_table_with::<P, KVEHash>()?;
+        let (hashname, field) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are two arguments
+            (act.next_unchecked(), act.next_unchecked())
+        };
+        match hashmap.hash_get(hashname, field) {
+            Ok(Some(value)) => {
+                con.write_mono_length_prefixed_with_tsymbol(&value, hashmap.get_value_tsymbol())
+                    .await?
+            }
+            Ok(None) => return util::err(P::RCODE_NIL),
+            Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+    /// Handle an `HDEL` query for the hash model
+    /// Syntax: `HDEL <hash> <field1> <field2> ...`
+    ///
+    /// Returns the number of fields that were removed
+    fn hdel(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1)?;
+        let hashmap = handle.get_table_with::<P, KVEHash>()?;
+        let hashname = unsafe { act.next_unchecked() };
+        if compiler::unlikely(!hashmap.is_key_ok(hashname)) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        if registry::state_okay() {
+            match hashmap.hash_remove_unchecked(hashname, act) {
+                Some(removed) => con.write_usize(removed).await?,
+                None => con._write_raw(P::RCODE_NIL).await?,
+            }
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+    /// Handle an `HGETALL` query for the hash model
+    /// Syntax: `HGETALL <hash>`
+    ///
+    /// Returns a flat array of alternating fields and values
+    fn hgetall(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let hashmap = handle.get_table_with::<P, KVEHash>()?;
+        match hashmap.hash_get_all(unsafe { act.next_unchecked() }) {
+            Ok(Some(pairs)) => {
+                con.write_typed_non_null_array_header(pairs.len() * 2, hashmap.get_value_tsymbol())
+                    .await?;
+                for (field, value) in pairs {
+                    con.write_typed_non_null_array_element(&field).await?;
+                    con.write_typed_non_null_array_element(&value).await?;
+                }
+            }
+            Ok(None) => return util::err(P::RCODE_NIL),
+            Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+}
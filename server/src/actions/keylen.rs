@@ -29,7 +29,7 @@ use crate::dbnet::prelude::*;
 action!(
     /// Run a `KEYLEN` query
     ///
-    /// At this moment, `keylen` only supports a single key
+    /// At this moment, `keylen` only supports a single key. `STRLEN` is an alias for `KEYLEN`
     fn keylen(handle: &crate::corestore::Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
         ensure_length::<P>(act.len(), |len| len == 1)?;
         let res: Option<usize> = {
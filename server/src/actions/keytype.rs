@@ -0,0 +1,96 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `TYPE` queries
+//! This module provides functions to work with `TYPE` queries. Values are typed by the
+//! model of the table they live in, so this just tells the client what the model enforces
+//! for the key
+
+use crate::{corestore::table::DataModel, dbnet::prelude::*};
+
+const TYPE_BINSTR: &str = "binstr";
+const TYPE_STR: &str = "str";
+const TYPE_LIST_BINSTR: &str = "list<binstr>";
+const TYPE_LIST_STR: &str = "list<str>";
+const TYPE_SET_BINSTR: &str = "set<binstr>";
+const TYPE_SET_STR: &str = "set<str>";
+const TYPE_HASH_BINSTR: &str = "hash<binstr>";
+const TYPE_HASH_STR: &str = "hash<str>";
+const TYPE_ZSET_BINSTR: &str = "zset<binstr>";
+const TYPE_ZSET_STR: &str = "zset<str>";
+
+action!(
+    /// Run a `TYPE` query
+    ///
+    /// Returns the type of the value for the given key (`binstr`, `str`, `list<binstr>`,
+    /// `list<str>`, `set<binstr>`, `set<str>`, `hash<binstr>`, `hash<str>`, `zset<binstr>` or
+    /// `zset<str>`) or `Nil` if the key doesn't exist
+    fn keytype(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let key = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is exactly one argument
+            act.next_unchecked()
+        };
+        let tbl = get_tbl_ref!(handle, con);
+        let ty = match tbl.get_model_ref() {
+            DataModel::KV(kve) => match kve.exists(key) {
+                Ok(true) if kve.is_val_encoded() => Some(TYPE_STR),
+                Ok(true) => Some(TYPE_BINSTR),
+                Ok(false) => None,
+                Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+            },
+            DataModel::KVExtListmap(kve) => match kve.exists(key) {
+                Ok(true) if kve.is_val_encoded() => Some(TYPE_LIST_STR),
+                Ok(true) => Some(TYPE_LIST_BINSTR),
+                Ok(false) => None,
+                Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+            },
+            DataModel::KVExtSetmap(kve) => match kve.exists(key) {
+                Ok(true) if kve.is_val_encoded() => Some(TYPE_SET_STR),
+                Ok(true) => Some(TYPE_SET_BINSTR),
+                Ok(false) => None,
+                Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+            },
+            DataModel::KVExtHashmap(kve) => match kve.exists(key) {
+                Ok(true) if kve.is_val_encoded() => Some(TYPE_HASH_STR),
+                Ok(true) => Some(TYPE_HASH_BINSTR),
+                Ok(false) => None,
+                Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+            },
+            DataModel::KVExtZSetmap(kve) => match kve.exists(key) {
+                Ok(true) if kve.is_val_encoded() => Some(TYPE_ZSET_STR),
+                Ok(true) => Some(TYPE_ZSET_BINSTR),
+                Ok(false) => None,
+                Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+            },
+        };
+        match ty {
+            Some(ty) => con.write_string(ty).await?,
+            None => con._write_raw(P::RCODE_NIL).await?,
+        }
+        Ok(())
+    }
+);
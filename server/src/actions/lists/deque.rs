@@ -0,0 +1,268 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Queue-like list operations
+//!
+//! Actions to push and pop at either end of a list. Unlike `LMOD <list> push`, a push creates
+//! the list if it doesn't already exist
+//!
+//! `BLPOP <list> <timeout>` and `BRPOP <list> <timeout>` pop just like `LPOP` and `RPOP`, but
+//! if the list is empty (or doesn't exist), the connection is parked on the table's change
+//! notifications (see [`notify`](crate::kvengine::notify)) for up to `timeout` milliseconds
+//! (`0` doesn't wait) and the pop is retried whenever the list is pushed to. Nil is returned if
+//! nothing could be popped in time. If several connections wait on the same list, whichever is
+//! woken first gets the element while the others keep waiting. The pops are recorded as `LPOP`s
+//! and `RPOP`s, and since these block the connection, they can't be queued in a transaction
+
+use {
+    crate::{
+        actions::ActionResult,
+        corestore::SharedSlice,
+        dbnet::{prelude::*, BufferedSocketStream},
+        kvengine::{notify::KeyEvent, KVEListmap, LockedVec},
+        queryengine,
+        util::compiler,
+    },
+    core::str,
+    tokio::{
+        sync::broadcast::error::RecvError,
+        time::{self, Duration},
+    },
+};
+
+/// Push `values` to the front (in reverse order, just like pushing them one by one) or the
+/// back of the list `listname`, creating the list if it doesn't exist. Returns the new length
+/// of the list
+fn push<P: ProtocolSpec>(
+    listmap: &KVEListmap,
+    listname: &[u8],
+    values: ActionIter<'_>,
+    front: bool,
+) -> ActionResult<usize> {
+    if !registry::memory_okay() {
+        return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+    }
+    let encoding_ok = listmap.is_key_ok(listname) && values.as_ref().all(listmap.get_val_encoder());
+    if compiler::unlikely(!encoding_ok) {
+        return util::err(P::RCODE_ENCODING_ERROR);
+    }
+    if !registry::state_okay() {
+        return util::err(P::RCODE_SERVER_ERR);
+    }
+    listmap.purge_if_expired(listname);
+    let values: Vec<SharedSlice> = values.map(SharedSlice::new).collect();
+    let lists = listmap.get_inner_ref();
+    let key = SharedSlice::new(listname);
+    let (len, event) = loop {
+        if let Some(list) = lists.get(listname) {
+            let mut wlock = list.write();
+            if front {
+                values.into_iter().for_each(|value| wlock.push_front(value));
+            } else {
+                wlock.extend(values);
+            }
+            break (wlock.len(), KeyEvent::Update);
+        }
+        // the list doesn't exist, so try to create it. If someone else created it in the
+        // meantime, we'll just push to their list
+        if let Some(entry) = lists.fresh_entry(key.clone()) {
+            let len = values.len();
+            let list = if front {
+                values.into_iter().rev().collect()
+            } else {
+                values.into()
+            };
+            entry.insert(LockedVec::new(list));
+            break (len, KeyEvent::Set);
+        }
+    };
+    listmap.touch(listname);
+    listmap.notify(event, listname);
+    Ok(len)
+}
+
+/// Pop an element from the front or the back of the list `listname`
+fn pop<P: ProtocolSpec>(
+    listmap: &KVEListmap,
+    listname: &[u8],
+    front: bool,
+) -> ActionResult<SharedSlice> {
+    match self::try_pop::<P>(listmap, listname, front)? {
+        Some(Some(value)) => Ok(value),
+        Some(None) => util::err(P::RSTRING_LISTMAP_LIST_IS_EMPTY),
+        None => util::err(P::RCODE_NIL),
+    }
+}
+
+/// Pop an element from the front or the back of the list `listname`. Returns `None` if the list
+/// doesn't exist and `Some(None)` if it's empty
+fn try_pop<P: ProtocolSpec>(
+    listmap: &KVEListmap,
+    listname: &[u8],
+    front: bool,
+) -> ActionResult<Option<Option<SharedSlice>>> {
+    if !registry::state_okay() {
+        return util::err(P::RCODE_SERVER_ERR);
+    }
+    let maybe_pop = match listmap.get(listname) {
+        Ok(list) => list.map(|list| {
+            let mut wlock = list.write();
+            if front {
+                wlock.pop_front()
+            } else {
+                wlock.pop_back()
+            }
+        }),
+        Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+    };
+    if let Some(Some(_)) = maybe_pop {
+        listmap.notify(KeyEvent::Update, listname);
+    }
+    Ok(maybe_pop)
+}
+
+/// Pop an element from the front or the back of a list, waiting for one to be pushed if the
+/// list is empty (see the module docs)
+async fn blocking_pop<'a, P: ProtocolSpec, C: 'a + BufferedSocketStream>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    mut act: ActionIter<'a>,
+    front: bool,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 2)?;
+    let listmap = handle.get_table_with::<P, KVEList>()?;
+    let (listname, timeout) = unsafe {
+        // UNSAFE(@ohsayan): We have checked that there are two arguments
+        (act.next_unchecked(), act.next_unchecked())
+    };
+    let timeout = match str::from_utf8(timeout)
+        .ok()
+        .and_then(|timeout| timeout.parse::<u64>().ok())
+    {
+        Some(timeout) => Duration::from_millis(timeout),
+        None => return util::err(P::RCODE_WRONGTYPE_ERR),
+    };
+    let deadline = time::Instant::now() + timeout;
+    // subscribe before the first attempt, so that we don't miss a push that lands right after it
+    let mut changes = listmap.subscribe();
+    loop {
+        let popped = {
+            // a pop is a write, so it's made (and logged) under the lock like any other write
+            let _txn_lock = registry::lock_txn_shared().await;
+            let popped = self::try_pop::<P>(listmap, listname, front)?;
+            if let Some(Some(_)) = popped {
+                if queryengine::is_logging(handle) {
+                    let action: &[u8] = if front { b"LPOP" } else { b"RPOP" };
+                    queryengine::log_record(handle, [action, listname].into_iter());
+                }
+            }
+            popped.flatten()
+        };
+        if let Some(value) = popped {
+            con.write_mono_length_prefixed_with_tsymbol(&value, listmap.get_value_tsymbol())
+                .await?;
+            return Ok(());
+        }
+        // wait till the list is pushed to (or created)
+        loop {
+            match time::timeout_at(deadline, changes.recv()).await {
+                Err(_) => {
+                    con._write_raw(P::RCODE_NIL).await?;
+                    return Ok(());
+                }
+                Ok(Ok(change))
+                    if change.key.as_ref() == listname
+                        && matches!(change.event, KeyEvent::Set | KeyEvent::Update) =>
+                {
+                    break;
+                }
+                // we might have missed a push, so just try again
+                Ok(Err(RecvError::Lagged(_))) => break,
+                Ok(Err(RecvError::Closed)) => {
+                    con._write_raw(P::RCODE_NIL).await?;
+                    return Ok(());
+                }
+                Ok(Ok(_)) => {}
+            }
+        }
+    }
+}
+
+action! {
+    /// Handle an `LPUSH` query for the list model
+    /// Syntax: `LPUSH <listname> <values ...>`
+    fn lpush(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1)?;
+        let listmap = handle.get_table_with::<P, KVEList>()?;
+        let listname = unsafe { act.next_unchecked() };
+        let len = self::push::<P>(listmap, listname, act, true)?;
+        con.write_usize(len).await?;
+        Ok(())
+    }
+    /// Handle an `RPUSH` query for the list model
+    /// Syntax: `RPUSH <listname> <values ...>`
+    fn rpush(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1)?;
+        let listmap = handle.get_table_with::<P, KVEList>()?;
+        let listname = unsafe { act.next_unchecked() };
+        let len = self::push::<P>(listmap, listname, act, false)?;
+        con.write_usize(len).await?;
+        Ok(())
+    }
+    /// Handle an `LPOP` query for the list model
+    /// Syntax: `LPOP <listname>`
+    fn lpop(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let listmap = handle.get_table_with::<P, KVEList>()?;
+        let value = self::pop::<P>(listmap, unsafe { act.next_unchecked() }, true)?;
+        con.write_mono_length_prefixed_with_tsymbol(&value, listmap.get_value_tsymbol())
+            .await?;
+        Ok(())
+    }
+    /// Handle an `RPOP` query for the list model
+    /// Syntax: `RPOP <listname>`
+    fn rpop(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let listmap = handle.get_table_with::<P, KVEList>()?;
+        let value = self::pop::<P>(listmap, unsafe { act.next_unchecked() }, false)?;
+        con.write_mono_length_prefixed_with_tsymbol(&value, listmap.get_value_tsymbol())
+            .await?;
+        Ok(())
+    }
+}
+
+action! {
+    /// Handle a `BLPOP` query for the list model
+    /// Syntax: `BLPOP <listname> <timeout>`
+    fn blpop(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::blocking_pop(handle, con, act, true).await
+    }
+    /// Handle a `BRPOP` query for the list model
+    /// Syntax: `BRPOP <listname> <timeout>`
+    fn brpop(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::blocking_pop(handle, con, act, false).await
+    }
+}
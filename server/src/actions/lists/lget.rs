@@ -24,7 +24,10 @@
  *
 */
 
-use crate::{corestore::SharedSlice, dbnet::prelude::*};
+use {
+    crate::{corestore::SharedSlice, dbnet::prelude::*},
+    std::collections::VecDeque,
+};
 
 const LEN: &[u8] = "LEN".as_bytes();
 const LIMIT: &[u8] = "LIMIT".as_bytes();
@@ -45,10 +48,13 @@ impl Range {
     pub fn set_stop(&mut self, stop: usize) {
         self.stop = Some(stop);
     }
-    pub fn into_vec(self, slice: &[SharedSlice]) -> Option<Vec<SharedSlice>> {
-        slice
-            .get(self.start..self.stop.unwrap_or(slice.len()))
-            .map(|slc| slc.to_vec())
+    pub fn into_vec(self, list: &VecDeque<SharedSlice>) -> Option<Vec<SharedSlice>> {
+        let stop = self.stop.unwrap_or(list.len());
+        if self.start <= stop && stop <= list.len() {
+            Some(list.range(self.start..stop).cloned().collect())
+        } else {
+            None
+        }
     }
 }
 
@@ -133,7 +139,7 @@ action! {
                     LAST => {
                         ensure_length::<P>(act.len(), |len| len == 0)?;
                         let maybe_value = listmap.get(listname).map(|list| {
-                            list.map(|lst| lst.read().last().cloned())
+                            list.map(|lst| lst.read().back().cloned())
                         });
                         match maybe_value {
                             Ok(v) => match v {
@@ -151,7 +157,7 @@ action! {
                     FIRST => {
                         ensure_length::<P>(act.len(), |len| len == 0)?;
                         let maybe_value = listmap.get(listname).map(|list| {
-                            list.map(|lst| lst.read().first().cloned())
+                            list.map(|lst| lst.read().front().cloned())
                         });
                         match maybe_value {
                             Ok(v) => match v {
@@ -204,4 +210,36 @@ action! {
         }
         Ok(())
     }
+    /// Handle an `LRANGE` query for the list model (KVExt). This is a shorthand for
+    /// `LGET <mylist> RANGE <start> <optional stop>`
+    /// ## Syntax
+    /// - `LRANGE <mylist> <start>` will return all the elements from `start`
+    /// - `LRANGE <mylist> <start> <stop>` will return the elements in `start..stop`
+    fn lrange(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2 || len == 3)?;
+        let listmap = handle.get_table_with::<P, KVEList>()?;
+        let listname = unsafe { act.next_unchecked() };
+        let mut range = match unsafe { String::from_utf8_lossy(act.next_unchecked()) }.parse() {
+            Ok(start) => Range::new(start),
+            Err(_) => return util::err(P::RCODE_WRONGTYPE_ERR),
+        };
+        if let Some(stop) = act.next_string_owned() {
+            match stop.parse() {
+                Ok(stop) => range.set_stop(stop),
+                Err(_) => return util::err(P::RCODE_WRONGTYPE_ERR),
+            }
+        }
+        match listmap.get(listname) {
+            Ok(Some(list)) => {
+                let ret = range.into_vec(&list.read());
+                match ret {
+                    Some(ret) => writelist!(con, listmap, ret),
+                    None => return Err(P::RSTRING_LISTMAP_BAD_INDEX.into()),
+                }
+            }
+            Ok(None) => return Err(P::RCODE_NIL.into()),
+            Err(()) => return Err(P::RCODE_ENCODING_ERROR.into()),
+        }
+        Ok(())
+    }
 }
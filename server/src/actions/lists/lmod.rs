@@ -24,7 +24,9 @@
  *
 */
 
-use crate::{corestore::SharedSlice, dbnet::prelude::*, util::compiler};
+use crate::{
+    corestore::SharedSlice, dbnet::prelude::*, kvengine::notify::KeyEvent, util::compiler,
+};
 
 const CLEAR: &[u8] = "CLEAR".as_bytes();
 const PUSH: &[u8] = "PUSH".as_bytes();
@@ -45,6 +47,7 @@ action! {
         let listmap = handle.get_table_with::<P, KVEList>()?;
         // get the list name
         let listname = unsafe { act.next_unchecked() };
+        listmap.purge_if_expired(listname);
         macro_rules! get_numeric_count {
             () => {
                 match unsafe { String::from_utf8_lossy(act.next_unchecked()) }.parse::<usize>() {
@@ -63,6 +66,7 @@ action! {
                 };
                 let okay = if registry::state_okay() {
                     list.write().clear();
+                    listmap.notify(KeyEvent::Update, listname);
                     P::RCODE_OKAY
                 } else {
                     P::RCODE_SERVER_ERR
@@ -71,6 +75,9 @@ action! {
             }
             PUSH => {
                 ensure_boolean_or_aerr::<P>(!act.is_empty())?;
+                if !registry::memory_okay() {
+                    return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+                }
                 let list = match listmap.get_inner_ref().get(listname) {
                     Some(l) => l,
                     _ => return Err(P::RCODE_NIL.into()),
@@ -79,6 +86,7 @@ action! {
                 let ret = if compiler::likely(act.as_ref().all(venc_ok)) {
                     if registry::state_okay() {
                         list.write().extend(act.map(SharedSlice::new));
+                        listmap.notify(KeyEvent::Update, listname);
                         P::RCODE_OKAY
                     } else {
                         P::RCODE_SERVER_ERR
@@ -101,6 +109,9 @@ action! {
                             false
                         }
                     });
+                    if maybe_value == Some(true) {
+                        listmap.notify(KeyEvent::Update, listname);
+                    }
                     con._write_raw(P::OKAY_BADIDX_NIL_NLUT[maybe_value]).await?
                 } else {
                     return Err(P::RCODE_SERVER_ERR.into());
@@ -108,6 +119,9 @@ action! {
             }
             INSERT => {
                 ensure_length::<P>(act.len(), |len| len == 2)?;
+                if !registry::memory_okay() {
+                    return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+                }
                 let idx_to_insert_at = get_numeric_count!();
                 let bts = unsafe { act.next_unchecked() };
                 let ret = if compiler::likely(listmap.is_val_ok(bts)) {
@@ -127,6 +141,9 @@ action! {
                             }),
                             Err(()) => return Err(P::RCODE_ENCODING_ERROR.into()),
                         };
+                        if maybe_insert == Some(true) {
+                            listmap.notify(KeyEvent::Update, listname);
+                        }
                         P::OKAY_BADIDX_NIL_NLUT[maybe_insert]
                     } else {
                         // flush broken; server err
@@ -152,20 +169,17 @@ action! {
                         Ok(lst) => lst.map(|list| {
                             let mut wlock = list.write();
                             if let Some(idx) = idx {
-                                if idx < wlock.len() {
-                                    // so we can pop
-                                    Some(wlock.remove(idx))
-                                } else {
-                                    None
-                                }
+                                // this is `None` if the index is out of bounds
+                                wlock.remove(idx)
                             } else {
-                                wlock.pop()
+                                wlock.pop_back()
                             }
                         }),
                         Err(()) => return Err(P::RCODE_ENCODING_ERROR.into()),
                     };
                     match maybe_pop {
                         Some(Some(val)) => {
+                            listmap.notify(KeyEvent::Update, listname);
                             con.write_mono_length_prefixed_with_tsymbol(
                                 &val, listmap.get_value_tsymbol()
                             ).await?;
@@ -27,27 +27,40 @@
 #[macro_use]
 mod macros;
 // modules
+pub mod deque;
 pub mod lget;
 pub mod lmod;
 
-use crate::{corestore::SharedSlice, dbnet::prelude::*, kvengine::LockedVec};
+use crate::{
+    corestore::SharedSlice,
+    dbnet::prelude::*,
+    kvengine::{notify::KeyEvent, LockedVec},
+};
 
 action! {
     /// Handle an `LSET` query for the list model
     /// Syntax: `LSET <listname> <values ...>`
     fn lset(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
         ensure_length::<P>(act.len(), |len| len > 0)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
         let listmap = handle.get_table_with::<P, KVEList>()?;
         let listname = unsafe { act.next_unchecked_bytes() };
+        listmap.purge_if_expired(&listname);
         let list = listmap.get_inner_ref();
         if registry::state_okay() {
-            let did = if let Some(entry) = list.fresh_entry(listname) {
+            let did = if let Some(entry) = list.fresh_entry(listname.clone()) {
                 let v: Vec<SharedSlice> = act.map(SharedSlice::new).collect();
-                entry.insert(LockedVec::new(v));
+                entry.insert(LockedVec::new(v.into()));
                 true
             } else {
                 false
             };
+            if did {
+                listmap.touch(&listname);
+                listmap.notify(KeyEvent::Set, &listname);
+            }
             con._write_raw(P::OKAY_OVW_BLUT[did]).await?
         } else {
             con._write_raw(P::RCODE_SERVER_ERR).await?
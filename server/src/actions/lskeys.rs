@@ -25,59 +25,106 @@
 */
 
 use crate::{
+    actions::ActionResult,
     corestore::{table::DataModel, SharedSlice},
     dbnet::prelude::*,
+    util::glob::glob_match,
 };
 
 const DEFAULT_COUNT: usize = 10;
 
 action!(
     /// Run an `LSKEYS` query
+    ///
+    /// Apart from the plain `LSKEYS [<entity>] [<count>]` form, the keys can be filtered
+    /// with `MATCH <pattern>` and paginated with `CURSOR <cursor>` (`LIMIT <count>` can be
+    /// used in place of the count). When filtering or paginating, the first element of the
+    /// returned array is the cursor to be passed in the next call (`0` once the scan is done)
+    /// and the count bounds the keys that are examined, not the ones that match
     fn lskeys(handle: &crate::corestore::Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
-        ensure_length::<P>(act.len(), |size| size < 4)?;
-        let (table, count) = if act.is_empty() {
-            (get_tbl!(handle, con), DEFAULT_COUNT)
-        } else if act.len() == 1 {
-            // two args, could either be count or an entity
-            let nextret = unsafe { act.next_unchecked() };
-            if unsafe { ucidx!(nextret, 0) }.is_ascii_digit() {
+        ensure_length::<P>(act.len(), |size| size < 9)?;
+        let mut entity = None;
+        let mut count = None;
+        let mut pattern = None;
+        let mut cursor = None;
+        while let Some(arg) = act.next() {
+            if arg.eq_ignore_ascii_case(b"MATCH") {
+                pattern = Some(act.next().unwrap_or_aerr::<P>()?);
+            } else if arg.eq_ignore_ascii_case(b"CURSOR") {
+                let ret = act.next().unwrap_or_aerr::<P>()?;
+                match String::from_utf8_lossy(ret).parse::<u64>() {
+                    Ok(c) => cursor = Some(c),
+                    Err(_) => return util::err(P::RCODE_WRONGTYPE_ERR),
+                }
+            } else if arg.eq_ignore_ascii_case(b"LIMIT") {
+                let ret = act.next().unwrap_or_aerr::<P>()?;
+                count = Some(self::parse_count::<P>(ret)?);
+            } else if count.is_none()
+                && !arg.is_empty()
+                && unsafe { ucidx!(arg, 0) }.is_ascii_digit()
+            {
                 // noice, this is a number; let's try to parse it
-                let count = if let Ok(cnt) = String::from_utf8_lossy(nextret).parse::<usize>() {
-                    cnt
-                } else {
-                    return util::err(P::RCODE_WRONGTYPE_ERR);
-                };
-                (get_tbl!(handle, con), count)
-            } else {
+                count = Some(self::parse_count::<P>(arg)?);
+            } else if entity.is_none() && count.is_none() && pattern.is_none() && cursor.is_none() {
                 // sigh, an entity
-                let entity = handle_entity!(con, nextret);
-                (get_tbl!(&entity, handle, con), DEFAULT_COUNT)
-            }
-        } else {
-            // an entity and a count, gosh this fella is really trying us
-            let entity_ret = unsafe { act.next().unsafe_unwrap() };
-            let count_ret = unsafe { act.next().unsafe_unwrap() };
-            let entity = handle_entity!(con, entity_ret);
-            let count = if let Ok(cnt) = String::from_utf8_lossy(count_ret).parse::<usize>() {
-                cnt
+                entity = Some(handle_entity!(con, arg));
             } else {
-                return util::err(P::RCODE_WRONGTYPE_ERR);
-            };
-            (get_tbl!(&entity, handle, con), count)
+                return util::err(P::RCODE_ACTION_ERR);
+            }
+        }
+        let table = match entity {
+            Some(entity) => get_tbl!(&entity, handle, con),
+            None => get_tbl!(handle, con),
         };
+        let count = count.unwrap_or(DEFAULT_COUNT);
         let tsymbol = match table.get_model_ref() {
             DataModel::KV(kv) => kv.get_value_tsymbol(),
             DataModel::KVExtListmap(kv) => kv.get_value_tsymbol(),
+            DataModel::KVExtSetmap(kv) => kv.get_value_tsymbol(),
+            DataModel::KVExtHashmap(kv) => kv.get_value_tsymbol(),
+            DataModel::KVExtZSetmap(kv) => kv.get_value_tsymbol(),
         };
-        let items: Vec<SharedSlice> = match table.get_model_ref() {
-            DataModel::KV(kv) => kv.get_inner_ref().get_keys(count),
-            DataModel::KVExtListmap(kv) => kv.get_inner_ref().get_keys(count),
-        };
-        con.write_typed_non_null_array_header(items.len(), tsymbol)
-            .await?;
-        for key in items {
-            con.write_typed_non_null_array_element(&key).await?;
+        if pattern.is_none() && cursor.is_none() {
+            let items: Vec<SharedSlice> = match table.get_model_ref() {
+                DataModel::KV(kv) => kv.live_keys(count),
+                DataModel::KVExtListmap(kv) => kv.live_keys(count),
+                DataModel::KVExtSetmap(kv) => kv.live_keys(count),
+                DataModel::KVExtHashmap(kv) => kv.live_keys(count),
+                DataModel::KVExtZSetmap(kv) => kv.live_keys(count),
+            };
+            con.write_typed_non_null_array_header(items.len(), tsymbol)
+                .await?;
+            for key in items {
+                con.write_typed_non_null_array_element(&key).await?;
+            }
+        } else {
+            if count == 0 {
+                return util::err(P::RCODE_ACTION_ERR);
+            }
+            let filter = |key: &SharedSlice| pattern.map_or(true, |pat| glob_match(pat, key));
+            let cursor = cursor.unwrap_or(0);
+            let (items, next) = match table.get_model_ref() {
+                DataModel::KV(kv) => kv.scan_live_keys(cursor, count, filter),
+                DataModel::KVExtListmap(kv) => kv.scan_live_keys(cursor, count, filter),
+                DataModel::KVExtSetmap(kv) => kv.scan_live_keys(cursor, count, filter),
+                DataModel::KVExtHashmap(kv) => kv.scan_live_keys(cursor, count, filter),
+                DataModel::KVExtZSetmap(kv) => kv.scan_live_keys(cursor, count, filter),
+            };
+            con.write_typed_non_null_array_header(items.len() + 1, tsymbol)
+                .await?;
+            con.write_typed_non_null_array_element(next.to_string().as_bytes())
+                .await?;
+            for key in items {
+                con.write_typed_non_null_array_element(&key).await?;
+            }
         }
         Ok(())
     }
 );
+
+fn parse_count<P: ProtocolSpec>(ret: &[u8]) -> ActionResult<usize> {
+    match String::from_utf8_lossy(ret).parse::<usize>() {
+        Ok(cnt) => Ok(cnt),
+        Err(_) => util::err(P::RCODE_WRONGTYPE_ERR),
+    }
+}
@@ -32,12 +32,21 @@
 
 #[macro_use]
 mod macros;
+pub mod append;
+pub mod cas;
+pub mod context;
+pub mod counter;
+pub mod dbhash;
 pub mod dbsize;
 pub mod del;
+pub mod dump;
 pub mod exists;
+pub mod expire;
 pub mod flushdb;
 pub mod get;
+pub mod hashes;
 pub mod keylen;
+pub mod keytype;
 pub mod lists;
 pub mod lskeys;
 pub mod mget;
@@ -45,11 +54,16 @@ pub mod mpop;
 pub mod mset;
 pub mod mupdate;
 pub mod pop;
+pub mod randomkey;
+pub mod sample;
 pub mod set;
+pub mod sets;
 pub mod strong;
+pub mod typed;
 pub mod update;
 pub mod uset;
 pub mod whereami;
+pub mod zsets;
 use {
     crate::{corestore::memstore::DdlError, protocol::interface::ProtocolSpec, util},
     std::io::Error as IoError,
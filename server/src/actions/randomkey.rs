@@ -0,0 +1,65 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::{
+        corestore::{map::DefaultState, table::DataModel},
+        dbnet::prelude::*,
+    },
+    core::hash::{BuildHasher, Hasher},
+};
+
+/// Returns a random number. Every new hasher state is randomly seeded, so the hash of
+/// nothing is a random number
+pub(crate) fn random_number() -> u64 {
+    DefaultState::new().build_hasher().finish()
+}
+
+action!(
+    /// Run a `RANDOMKEY` query
+    ///
+    /// Syntax: `RANDOMKEY`. Returns a key from the current table, chosen uniformly at random
+    fn randomkey(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 0)?;
+        let table = get_tbl_ref!(handle, con);
+        let random = random_number();
+        let (key, tsymbol) = match table.get_model_ref() {
+            DataModel::KV(kv) => (kv.random_key(random), kv.get_key_tsymbol()),
+            DataModel::KVExtListmap(kv) => (kv.random_key(random), kv.get_key_tsymbol()),
+            DataModel::KVExtSetmap(kv) => (kv.random_key(random), kv.get_key_tsymbol()),
+            DataModel::KVExtHashmap(kv) => (kv.random_key(random), kv.get_key_tsymbol()),
+            DataModel::KVExtZSetmap(kv) => (kv.random_key(random), kv.get_key_tsymbol()),
+        };
+        match key {
+            Some(key) => {
+                con.write_mono_length_prefixed_with_tsymbol(&key, tsymbol)
+                    .await?
+            }
+            None => con._write_raw(P::RCODE_NIL).await?,
+        }
+        Ok(())
+    }
+);
@@ -0,0 +1,83 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Key sampling
+//!
+//! The `SAMPLE` action returns a uniform random sample of the keys in a table, which is handy
+//! for profiling the key naming patterns (and the sizes of the values) of a large table without
+//! having to scan all of it from a client
+
+use crate::{corestore::table::DataModel, dbnet::prelude::*};
+
+const WITHSIZES: &[u8] = b"WITHSIZES";
+
+action!(
+    /// Run a `SAMPLE` query
+    ///
+    /// Syntax: `SAMPLE <count> [WITHSIZES]`. Returns (atmost) `count` keys of the current table,
+    /// picked uniformly at random. With `WITHSIZES`, a flat array of every key followed by the
+    /// approximate number of bytes used by its value is returned instead
+    fn sample(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1 || len == 2)?;
+        let count = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is atleast one argument
+            act.next_unchecked()
+        };
+        let count = match String::from_utf8_lossy(count).parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => return util::err(P::RCODE_WRONGTYPE_ERR),
+        };
+        let with_sizes = match act.next() {
+            Some(arg) if arg.eq_ignore_ascii_case(WITHSIZES) => true,
+            Some(_) => return util::err(P::RCODE_ACTION_ERR),
+            None => false,
+        };
+        let table = get_tbl_ref!(handle, con);
+        let random = super::randomkey::random_number();
+        let (sample, tsymbol) = match table.get_model_ref() {
+            DataModel::KV(kv) => (kv.sample(count, random), kv.get_key_tsymbol()),
+            DataModel::KVExtListmap(kv) => (kv.sample(count, random), kv.get_key_tsymbol()),
+            DataModel::KVExtSetmap(kv) => (kv.sample(count, random), kv.get_key_tsymbol()),
+            DataModel::KVExtHashmap(kv) => (kv.sample(count, random), kv.get_key_tsymbol()),
+            DataModel::KVExtZSetmap(kv) => (kv.sample(count, random), kv.get_key_tsymbol()),
+        };
+        if with_sizes {
+            con.write_array_header(2 * sample.len()).await?;
+            for (key, size) in sample {
+                con.write_mono_length_prefixed_with_tsymbol(&key, tsymbol)
+                    .await?;
+                con.write_usize(size).await?;
+            }
+        } else {
+            con.write_typed_non_null_array_header(sample.len(), tsymbol)
+                .await?;
+            for (key, _) in sample {
+                con.write_typed_non_null_array_element(&key).await?;
+            }
+        }
+        Ok(())
+    }
+);
@@ -29,27 +29,41 @@
 
 use crate::{corestore::SharedSlice, dbnet::prelude::*, queryengine::ActionIter};
 
+/// `SET <key> <value> NX`: only set the key if it doesn't exist (the default)
+const FLAG_NX: &[u8] = b"nx";
+/// `SET <key> <value> XX`: only set the key if it already exists
+const FLAG_XX: &[u8] = b"xx";
+
 action!(
     /// Run a `SET` query
     fn set(handle: &crate::corestore::Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
-        ensure_length::<P>(act.len(), |len| len == 2)?;
+        ensure_length::<P>(act.len(), |len| len == 2 || len == 3)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let (key, value) = unsafe {
+            // UNSAFE(@ohsayan): This is completely safe as we've already checked
+            // that there are atleast 2 arguments
+            (
+                SharedSlice::new(act.next().unsafe_unwrap()),
+                SharedSlice::new(act.next().unsafe_unwrap()),
+            )
+        };
+        let only_existing = match act.next_lowercase().as_deref() {
+            None | Some(FLAG_NX) => false,
+            Some(FLAG_XX) => true,
+            Some(_) => return util::err(P::RCODE_ACTION_ERR),
+        };
         if registry::state_okay() {
-            let did_we = {
+            let response = {
                 let writer = handle.get_table_with::<P, KVEBlob>()?;
-                match unsafe {
-                    // UNSAFE(@ohsayan): This is completely safe as we've already checked
-                    // that there are exactly 2 arguments
-                    writer.set(
-                        SharedSlice::new(act.next().unsafe_unwrap()),
-                        SharedSlice::new(act.next().unsafe_unwrap()),
-                    )
-                } {
-                    Ok(true) => Some(true),
-                    Ok(false) => Some(false),
-                    Err(()) => None,
+                if only_existing {
+                    P::UPDATE_NLUT[writer.update(key, value).ok()]
+                } else {
+                    P::SET_NLUT[writer.set(key, value).ok()]
                 }
             };
-            con._write_raw(P::SET_NLUT[did_we]).await?;
+            con._write_raw(response).await?;
         } else {
             con._write_raw(P::RCODE_SERVER_ERR).await?;
         }
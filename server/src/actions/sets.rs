@@ -0,0 +1,180 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Set actions
+//!
+//! Actions to work with the set model, where every key maps to an unordered set of unique
+//! members
+
+use crate::{
+    actions::ActionResult,
+    corestore::SharedSlice,
+    dbnet::{prelude::*, BufferedSocketStream},
+    kvengine::{encoding::ENCODING_LUT_ITER, SetOperation},
+    util::compiler,
+};
+
+/// Write out the given members as a typed array
+macro_rules! writeset {
+    ($con:expr, $setmap:expr, $members:expr) => {{
+        $con.write_typed_non_null_array_header($members.len(), $setmap.get_value_tsymbol())
+            .await?;
+        for member in $members {
+            $con.write_typed_non_null_array_element(&member).await?;
+        }
+    }};
+}
+
+action! {
+    /// Handle an `SADD` query for the set model
+    /// Syntax: `SADD <set> <members ...>`
+    ///
+    /// Creates the set if it doesn't exist and returns the number of members that were added
+    fn sadd(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let setmap = handle.get_table_with::<P, KVESet>()?;
+        let setname = unsafe { act.next_unchecked() };
+        let encoding_ok = setmap.is_key_ok(setname) && act.as_ref().all(setmap.get_val_encoder());
+        if compiler::unlikely(!encoding_ok) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        if registry::state_okay() {
+            let added = setmap.set_add_unchecked(setname, act.map(SharedSlice::new).collect());
+            con.write_usize(added).await?;
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+    /// Handle an `SREM` query for the set model
+    /// Syntax: `SREM <set> <members ...>`
+    ///
+    /// Returns the number of members that were removed
+    fn srem(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1)?;
+        let setmap = handle.get_table_with::<P, KVESet>()?;
+        let setname = unsafe { act.next_unchecked() };
+        if compiler::unlikely(!setmap.is_key_ok(setname)) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        if registry::state_okay() {
+            match setmap.set_remove_unchecked(setname, act) {
+                Some(removed) => con.write_usize(removed).await?,
+                None => con._write_raw(P::RCODE_NIL).await?,
+            }
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+    /// Handle an `SMEMBERS` query for the set model
+    /// Syntax: `SMEMBERS <set>`
+    fn smembers(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let setmap = handle.get_table_with::<P, KVESet>()?;
+        match setmap.set_members(unsafe { act.next_unchecked() }) {
+            Ok(Some(members)) => writeset!(con, setmap, members),
+            Ok(None) => return util::err(P::RCODE_NIL),
+            Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+    /// Handle an `SISMEMBER` query for the set model
+    /// Syntax: `SISMEMBER <set> <member>`
+    ///
+    /// Returns `1` if the member is present in the set and `0` otherwise
+    fn sismember(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2)?;
+        let setmap = handle.get_table_with::<P, KVESet>()?;
+        let (setname, member) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are two arguments
+            (act.next_unchecked(), act.next_unchecked())
+        };
+        match setmap.set_contains(setname, member) {
+            Ok(Some(present)) => con.write_usize(present as usize).await?,
+            Ok(None) => return util::err(P::RCODE_NIL),
+            Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+    /// Handle an `SCARD` query for the set model
+    /// Syntax: `SCARD <set>`
+    fn scard(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let setmap = handle.get_table_with::<P, KVESet>()?;
+        match setmap.set_len(unsafe { act.next_unchecked() }) {
+            Ok(Some(len)) => con.write_usize(len).await?,
+            Ok(None) => return util::err(P::RCODE_NIL),
+            Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+}
+
+action! {
+    /// Handle an `SINTER` query for the set model
+    /// Syntax: `SINTER <set1> <set2 ...>`
+    ///
+    /// Sets that don't exist are treated as empty sets
+    fn sinter(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::combine(handle, con, act, SetOperation::Intersection).await
+    }
+    /// Handle an `SUNION` query for the set model
+    /// Syntax: `SUNION <set1> <set2 ...>`
+    ///
+    /// Sets that don't exist are treated as empty sets
+    fn sunion(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::combine(handle, con, act, SetOperation::Union).await
+    }
+    /// Handle an `SDIFF` query for the set model
+    /// Syntax: `SDIFF <set1> <set2 ...>`
+    ///
+    /// Returns the members of the first set that aren't present in the others. Sets that
+    /// don't exist are treated as empty sets
+    fn sdiff(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::combine(handle, con, act, SetOperation::Difference).await
+    }
+}
+
+/// Combine the given sets with `op` and write out the resulting members
+async fn combine<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    act: ActionIter<'a>,
+    op: SetOperation,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len != 0)?;
+    let setmap = handle.get_table_with::<P, KVESet>()?;
+    if compiler::unlikely(!ENCODING_LUT_ITER[setmap.is_key_encoded()](act.as_ref())) {
+        return util::err(P::RCODE_ENCODING_ERROR);
+    }
+    let members = setmap.set_combine_unchecked(act, op);
+    writeset!(con, setmap, members);
+    Ok(())
+}
@@ -28,7 +28,7 @@ use {
     crate::{
         actions::strong::StrongActionResult,
         dbnet::prelude::*,
-        kvengine::{KVEStandard, SingleEncoder},
+        kvengine::{notify::KeyEvent, KVEStandard, SingleEncoder},
         protocol::iter::DerefUnsafeSlice,
         util::compiler,
     },
@@ -127,7 +127,9 @@ pub(super) fn snapshot_and_del<'a, T: 'a + DerefUnsafeSlice>(
                 // value after we snapshotted it. In that case, let this key
                 // be whatever the "newer" value is. Since our snapshot is a "happens-before"
                 // thing, this is absolutely fine
-                let _ = lowtable.remove_if(key, |_, val| val.eq(&snapshot));
+                if lowtable.true_remove_if(key, |_, val| val.eq(&snapshot)) {
+                    kve.notify(KeyEvent::Delete, key);
+                }
             });
             StrongActionResult::Okay
         } else {
@@ -29,7 +29,7 @@ use {
         actions::strong::StrongActionResult,
         corestore::SharedSlice,
         dbnet::prelude::*,
-        kvengine::{DoubleEncoder, KVEStandard},
+        kvengine::{notify::KeyEvent, DoubleEncoder, KVEStandard},
         protocol::iter::DerefUnsafeSlice,
         util::compiler,
     },
@@ -44,6 +44,9 @@ action! {
     fn sset(handle: &crate::corestore::Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
         let howmany = act.len();
         ensure_length::<P>(howmany, |size| size & 1 == 0 && size != 0)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
         let kve = handle.get_table_with::<P, KVEBlob>()?;
         if registry::state_okay() {
             let encoder = kve.get_double_encoder();
@@ -103,7 +106,6 @@ pub(super) fn snapshot_and_insert<'a, T: 'a + DerefUnsafeSlice>(
     }
     if registry::state_okay() {
         if key_iter_stat_ok {
-            let _kve = kve;
             let lowtable = lowtable;
             // fine, the keys were non-existent when we looked at them
             while let (Some(key), Some(value)) = (act.next(), act.next()) {
@@ -112,6 +114,7 @@ pub(super) fn snapshot_and_insert<'a, T: 'a + DerefUnsafeSlice>(
                         lowtable.fresh_entry(SharedSlice::new(key.deref_slice()))
                     {
                         fresh.insert(SharedSlice::new(value.deref_slice()));
+                        kve.notify(KeyEvent::Set, key.deref_slice());
                     }
                     // we don't care if some other thread initialized the value we checked
                     // it. We expected a fresh entry, so that's what we'll check and use
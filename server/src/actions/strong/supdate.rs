@@ -29,7 +29,7 @@ use {
         actions::strong::StrongActionResult,
         corestore::SharedSlice,
         dbnet::prelude::*,
-        kvengine::{DoubleEncoder, KVEStandard},
+        kvengine::{notify::KeyEvent, DoubleEncoder, KVEStandard},
         protocol::iter::DerefUnsafeSlice,
         util::compiler,
     },
@@ -44,6 +44,9 @@ action! {
     fn supdate(handle: &crate::corestore::Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
         let howmany = act.len();
         ensure_length::<P>(howmany, |size| size & 1 == 0 && size != 0)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
         let kve = handle.get_table_with::<P, KVEBlob>()?;
         if registry::state_okay() {
             let encoder = kve.get_double_encoder();
@@ -128,6 +131,9 @@ pub(super) fn snapshot_and_update<'a, T: 'a + DerefUnsafeSlice>(
                     {
                         if mutable.value().eq(&snapshot) {
                             mutable.insert(SharedSlice::new(value.deref_slice()));
+                            // like `UPDATE`, the new value doesn't keep the time-to-live
+                            let _ = kve.get_expiry_ref().remove(key.deref_slice());
+                            kve.notify(KeyEvent::Update, key.deref_slice());
                         } else {
                             drop(mutable);
                         }
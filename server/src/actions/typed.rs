@@ -0,0 +1,131 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Typed queries
+//! This module provides functions to work with `STRSET`, `STRGET`, `BINSET` and `BINGET`
+//! queries. They work just like `SET` and `GET`, but only on tables whose values have the type
+//! that the action names (`str` or `binstr`; see `TYPE`). On any other table (including the
+//! ones that hold lists) they fail with `wrong-value-type`, so clients can rely on the server to
+//! enforce the type of the values they read and write
+
+use crate::{
+    actions::ActionResult,
+    corestore::{table::DataModel, SharedSlice},
+    dbnet::{prelude::*, BufferedSocketStream},
+    kvengine::KVEStandard,
+    util::compiler,
+};
+
+action!(
+    /// Run a `STRSET` query
+    ///
+    /// Syntax: `STRSET <key> <value>`. Sets the key if it doesn't exist
+    fn strset(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::typed_set(handle, con, act, true).await
+    }
+    /// Run a `BINSET` query
+    ///
+    /// Syntax: `BINSET <key> <value>`. Sets the key if it doesn't exist
+    fn binset(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::typed_set(handle, con, act, false).await
+    }
+    /// Run a `STRGET` query
+    ///
+    /// Syntax: `STRGET <key>`. Returns the value of the key
+    fn strget(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::typed_get(handle, con, act, true).await
+    }
+    /// Run a `BINGET` query
+    ///
+    /// Syntax: `BINGET <key>`. Returns the value of the key
+    fn binget(handle: &Corestore, con: &mut Connection<C, P>, act: ActionIter<'a>) {
+        self::typed_get(handle, con, act, false).await
+    }
+);
+
+/// Returns the current table if it holds values of the given type (`str` if `is_str` is set
+/// and `binstr` otherwise)
+fn table_of_type<P: ProtocolSpec>(handle: &Corestore, is_str: bool) -> ActionResult<&KVEStandard> {
+    let tbl = match handle.get_ctable_ref() {
+        Some(tbl) => tbl,
+        None => return util::err(P::RSTRING_DEFAULT_UNSET),
+    };
+    match tbl.get_model_ref() {
+        DataModel::KV(kve) if kve.is_val_encoded() == is_str => Ok(kve),
+        _ => util::err(P::RSTRING_WRONG_VALUE_TYPE),
+    }
+}
+
+/// Set the key in `act` (if it doesn't exist) in a table with values of the given type
+async fn typed_set<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    mut act: ActionIter<'a>,
+    is_str: bool,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 2)?;
+    let kve = self::table_of_type::<P>(handle, is_str)?;
+    if !registry::memory_okay() {
+        return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+    }
+    if !registry::state_okay() {
+        return util::err(P::RCODE_SERVER_ERR);
+    }
+    let (key, value) = unsafe {
+        // UNSAFE(@ohsayan): We have checked that there are exactly 2 arguments
+        (
+            SharedSlice::new(act.next_unchecked()),
+            SharedSlice::new(act.next_unchecked()),
+        )
+    };
+    con._write_raw(P::SET_NLUT[kve.set(key, value).ok()])
+        .await?;
+    Ok(())
+}
+
+/// Write out the value of the key in `act` from a table with values of the given type
+async fn typed_get<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    mut act: ActionIter<'a>,
+    is_str: bool,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 1)?;
+    let kve = self::table_of_type::<P>(handle, is_str)?;
+    let key = unsafe {
+        // UNSAFE(@ohsayan): We have checked that there is exactly one argument
+        act.next_unchecked()
+    };
+    match kve.get_cloned(key) {
+        Ok(Some(val)) => {
+            con.write_mono_length_prefixed_with_tsymbol(&val, kve.get_value_tsymbol())
+                .await?
+        }
+        Err(_) => compiler::cold_err(con._write_raw(P::RCODE_ENCODING_ERROR)).await?,
+        Ok(_) => con._write_raw(P::RCODE_NIL).await?,
+    }
+    Ok(())
+}
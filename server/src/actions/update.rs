@@ -34,6 +34,9 @@ action!(
     /// Run an `UPDATE` query
     fn update(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
         ensure_length::<P>(act.len(), |len| len == 2)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
         if registry::state_okay() {
             let did_we = {
                 let writer = handle.get_table_with::<P, KVEBlob>()?;
@@ -0,0 +1,142 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Sorted set actions
+//!
+//! Actions to work with the sorted set model, where every key maps to a set of unique members
+//! ordered by their scores
+
+use crate::{
+    corestore::{zset::Score, SharedSlice},
+    dbnet::prelude::*,
+    util::compiler,
+};
+
+action! {
+    /// Handle a `ZADD` query for the sorted set model
+    /// Syntax: `ZADD <zset> <score1> <member1> <score2> <member2> ...`
+    ///
+    /// Creates the sorted set if it doesn't exist and returns the number of members that were
+    /// added (members that already existed just have their scores updated)
+    fn zadd(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1 && len % 2 == 1)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let zsetmap = handle.get_table_with::<P, KVEZSet>()?;
+        let zsetname = unsafe { act.next_unchecked() };
+        if compiler::unlikely(!zsetmap.is_key_ok(zsetname)) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        let mut members = Vec::with_capacity(act.len() / 2);
+        while let (Some(score), Some(member)) = (act.next(), act.next()) {
+            let score = match Score::parse(score) {
+                Some(score) => score,
+                None => return util::err(P::RSTRING_ZSET_BAD_SCORE),
+            };
+            if compiler::unlikely(!zsetmap.is_val_ok(member)) {
+                return util::err(P::RCODE_ENCODING_ERROR);
+            }
+            members.push((SharedSlice::new(member), score));
+        }
+        if registry::state_okay() {
+            let added = zsetmap.zset_add_unchecked(zsetname, members);
+            con.write_usize(added).await?;
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+    /// Handle a `ZREM` query for the sorted set model
+    /// Syntax: `ZREM <zset> <member1> <member2> ...`
+    ///
+    /// Returns the number of members that were removed
+    fn zrem(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1)?;
+        let zsetmap = handle.get_table_with::<P, KVEZSet>()?;
+        let zsetname = unsafe { act.next_unchecked() };
+        if compiler::unlikely(!zsetmap.is_key_ok(zsetname)) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        if registry::state_okay() {
+            match zsetmap.zset_remove_unchecked(zsetname, act) {
+                Some(removed) => con.write_usize(removed).await?,
+                None => con._write_raw(P::RCODE_NIL).await?,
+            }
+        } else {
+            con._write_raw(P::RCODE_SERVER_ERR).await?;
+        }
+        Ok(())
+    }
+    /// Handle a `ZRANGEBYSCORE` query for the sorted set model
+    /// Syntax: `ZRANGEBYSCORE <zset> <min> <max>`
+    ///
+    /// Returns the members with a score in `min..=max`, ordered by their scores. `-inf` and
+    /// `+inf` can be used for unbounded ranges
+    fn zrangebyscore(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 3)?;
+        let zsetmap = handle.get_table_with::<P, KVEZSet>()?;
+        let (zsetname, min, max) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are three arguments
+            (act.next_unchecked(), act.next_unchecked(), act.next_unchecked())
+        };
+        let (min, max) = match (Score::parse(min), Score::parse(max)) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return util::err(P::RSTRING_ZSET_BAD_SCORE),
+        };
+        match zsetmap.zset_range_by_score(zsetname, min, max) {
+            Ok(Some(members)) => {
+                con.write_typed_non_null_array_header(members.len(), zsetmap.get_value_tsymbol())
+                    .await?;
+                for (member, _) in members {
+                    con.write_typed_non_null_array_element(&member).await?;
+                }
+            }
+            Ok(None) => return util::err(P::RCODE_NIL),
+            Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+    /// Handle a `ZRANK` query for the sorted set model
+    /// Syntax: `ZRANK <zset> <member>`
+    ///
+    /// Returns the zero-based rank of the member (ordered by ascending score) or `Nil` if
+    /// either the sorted set or the member doesn't exist
+    fn zrank(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2)?;
+        let zsetmap = handle.get_table_with::<P, KVEZSet>()?;
+        let (zsetname, member) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are two arguments
+            (act.next_unchecked(), act.next_unchecked())
+        };
+        match zsetmap.zset_rank(zsetname, member) {
+            Ok(Some(rank)) => con.write_usize(rank).await?,
+            Ok(None) => return util::err(P::RCODE_NIL),
+            Err(()) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+}
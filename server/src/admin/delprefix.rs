@@ -0,0 +1,163 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::{
+        corestore::table::{DataModel, Table},
+        dbnet::{
+            loopback::{Element, Loopback},
+            prelude::*,
+        },
+        services::jobs::{Job, JobKind},
+        util::glob::glob_match,
+        IoResult,
+    },
+    std::{
+        io::{Error as IoError, ErrorKind},
+        sync::Arc,
+    },
+};
+
+const MATCH: &[u8] = b"match";
+/// The number of keys that are deleted at a time
+const DELETE_BATCH: usize = 1000;
+
+/// The keys that a `DELPREFIX` deletes
+enum KeyFilter {
+    /// the keys that start with the prefix
+    Prefix(Vec<u8>),
+    /// the keys that match the glob pattern
+    Glob(Vec<u8>),
+}
+
+impl KeyFilter {
+    fn matches(&self, key: &[u8]) -> bool {
+        match self {
+            Self::Prefix(prefix) => key.starts_with(prefix),
+            Self::Glob(pattern) => glob_match(pattern, key),
+        }
+    }
+}
+
+action!(
+    /// Run a `DELPREFIX <prefix>` or a `DELPREFIX MATCH <pattern>` query, which deletes the keys
+    /// in the current table that start with `prefix` (or match `pattern`) in the background.
+    /// The ID of the job is returned right away and its progress can be checked with `SYS JOB`
+    fn delprefix(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1 || len == 2)?;
+        let filter = if act.len() == 2 {
+            let (keyword, pattern) = unsafe {
+                // UNSAFE(@ohsayan): We have already checked the length
+                (act.next_lowercase_unchecked(), act.next_unchecked())
+            };
+            if keyword.as_ref() != MATCH {
+                return util::err(P::RCODE_ACTION_ERR);
+            }
+            KeyFilter::Glob(pattern.to_vec())
+        } else {
+            let prefix = unsafe {
+                // UNSAFE(@ohsayan): We have already checked the length
+                act.next_unchecked()
+            };
+            KeyFilter::Prefix(prefix.to_vec())
+        };
+        if !registry::state_okay() {
+            return util::err(P::RCODE_SERVER_ERR);
+        }
+        let table = get_tbl!(handle, con);
+        let (id, job) = handle.get_jobs().start(JobKind::DelPrefix);
+        tokio::spawn(self::delete_matching(handle.clone(), table, filter, job));
+        con.write_int64(id).await?;
+        Ok(())
+    }
+);
+
+/// Delete the keys in `table` that match `filter`, recording the progress in `job` (in keys).
+/// `handle` must be using `table`
+///
+/// The keys are scanned [`DELETE_BATCH`] at a time and the ones that match are deleted with
+/// ordinary `DEL`s (so that they're logged and replicated). Other queries run in between the
+/// batches, so keys that are added while the job is running may or may not be deleted
+async fn delete_matching(handle: Corestore, table: Arc<Table>, filter: KeyFilter, job: Arc<Job>) {
+    let ret = self::delete_batches(&handle, &table, &filter, &job).await;
+    job.finish(ret.is_ok());
+    match ret {
+        Ok(()) => log::info!("Deleted {} keys in the background", job.done()),
+        Err(_) if job.is_killed() => {
+            log::info!("Stopped deleting keys after deleting {}", job.done())
+        }
+        Err(e) => log::error!("Failed to delete keys in the background: {e}"),
+    }
+}
+
+async fn delete_batches(
+    handle: &Corestore,
+    table: &Table,
+    filter: &KeyFilter,
+    job: &Job,
+) -> IoResult<()> {
+    macro_rules! scan {
+        ($engine:expr, $cursor:expr) => {
+            $engine
+                .get_inner_ref()
+                .scan_keys($cursor, DELETE_BATCH, |key| filter.matches(key))
+        };
+    }
+    let mut loopback = Loopback::new(handle);
+    let mut cursor = 0;
+    loop {
+        job.ensure_alive()?;
+        let (keys, next) = match table.get_model_ref() {
+            DataModel::KV(kve) => scan!(kve, cursor),
+            DataModel::KVExtListmap(kve) => scan!(kve, cursor),
+            DataModel::KVExtSetmap(kve) => scan!(kve, cursor),
+            DataModel::KVExtHashmap(kve) => scan!(kve, cursor),
+            DataModel::KVExtZSetmap(kve) => scan!(kve, cursor),
+        };
+        job.add_total(keys.len() as u64);
+        if !keys.is_empty() {
+            let mut query: Vec<&[u8]> = Vec::with_capacity(keys.len() + 1);
+            query.push(b"DEL");
+            query.extend(keys.iter().map(|key| &**key));
+            // keys that were deleted by someone else in the meantime aren't counted
+            match loopback.run(&query).await? {
+                Element::Int(deleted) => job.add_done(deleted),
+                response => {
+                    return Err(IoError::new(
+                        ErrorKind::Other,
+                        format!("DEL failed with {response:?}"),
+                    ))
+                }
+            }
+        }
+        if next == 0 {
+            return Ok(());
+        }
+        cursor = next;
+        // let the other queries in
+        tokio::task::yield_now().await;
+    }
+}
@@ -0,0 +1,93 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::{
+        dbnet::prelude::*,
+        logging::{self, LogModule},
+        queryengine::ActionIter,
+    },
+    core::str,
+    log::LevelFilter,
+};
+
+const ERR_UNKNOWN_MODULE: &[u8] = b"!14\nunknown-module\n";
+const ERR_UNKNOWN_LEVEL: &[u8] = b"!13\nunknown-level\n";
+
+/// Returns the name of a level, the way it's written in the configuration
+fn level_name(level: LevelFilter) -> String {
+    level.to_string().to_ascii_lowercase()
+}
+
+action!(
+    /// Get or set the log levels
+    ///
+    /// Syntax: `LOGLEVEL [<module> [<level>]]`. Without any arguments, this returns the level
+    /// of every module as a flat array of name/level pairs. Setting a level takes effect
+    /// immediately, but isn't persisted. If auth is enabled, only root can set a level
+    fn loglevel(con: &mut Connection<C, P>, auth: &mut AuthProviderHandle, act: ActionIter<'a>) {
+        let mut act = act;
+        ensure_length::<P>(act.len(), |len| len < 3)?;
+        if act.len() == 0 {
+            con.write_array_header(2 * LogModule::COUNT).await?;
+            for module in LogModule::ALL {
+                con.write_string(module.name()).await?;
+                con.write_string(&level_name(logging::level(module)))
+                    .await?;
+            }
+            return Ok(());
+        }
+        let module = match LogModule::from_name(unsafe { act.next_unchecked() }) {
+            Some(module) => module,
+            None => return util::err(ERR_UNKNOWN_MODULE),
+        };
+        match act.next() {
+            Some(level) => {
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                let level = str::from_utf8(level)
+                    .ok()
+                    .and_then(|level| level.parse::<LevelFilter>().ok());
+                match level {
+                    Some(level) => {
+                        log::info!(
+                            "Log level of `{}` set to `{}`",
+                            module.name(),
+                            level_name(level)
+                        );
+                        logging::set_level(module, level);
+                        con._write_raw(P::RCODE_OKAY).await?;
+                    }
+                    None => return util::err(ERR_UNKNOWN_LEVEL),
+                }
+            }
+            None => {
+                con.write_string(&level_name(logging::level(module)))
+                    .await?
+            }
+        }
+        Ok(())
+    }
+);
@@ -26,7 +26,7 @@
 
 use {
     crate::{
-        dbnet::prelude::*, kvengine::encoding,
+        dbnet::prelude::*, kvengine::encoding, services::jobs::JobKind,
         storage::v1::sengine::SnapshotActionResult,
     },
     core::str,
@@ -40,7 +40,10 @@ action!(
         let engine = handle.get_engine();
         if act.is_empty() {
             // traditional mksnap
-            match engine.mksnap(handle.clone_store()).await {
+            let (_, job) = handle.get_jobs().start(JobKind::Snapshot);
+            let ret = engine.mksnap(handle.clone_store()).await;
+            job.finish(ret == SnapshotActionResult::Ok);
+            match ret {
                 SnapshotActionResult::Ok => con._write_raw(P::RCODE_OKAY).await?,
                 SnapshotActionResult::Failure => return util::err(P::RCODE_SERVER_ERR),
                 SnapshotActionResult::Disabled => return util::err(P::RSTRING_SNAPSHOT_DISABLED),
@@ -79,7 +82,10 @@ action!(
             }
 
             // now make the snapshot
-            match engine.mkrsnap(&name, handle.clone_store()).await {
+            let (_, job) = handle.get_jobs().start(JobKind::Snapshot);
+            let ret = engine.mkrsnap(&name, handle.clone_store()).await;
+            job.finish(ret == SnapshotActionResult::Ok);
+            match ret {
                 SnapshotActionResult::Ok => con._write_raw(P::RCODE_OKAY).await?,
                 SnapshotActionResult::Failure => return util::err(P::RCODE_SERVER_ERR),
                 SnapshotActionResult::Busy => return util::err(P::RSTRING_SNAPSHOT_BUSY),
@@ -26,5 +26,9 @@
 
 //! Modules for administration of Skytable
 
+pub mod delprefix;
+pub mod loglevel;
 pub mod mksnap;
+pub mod quota;
+pub mod snapshot;
 pub mod sys;
@@ -0,0 +1,157 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Quotas
+//!
+//! `QUOTA` lets root see and change the quotas of the keyspaces and the users (see
+//! [`quota`](crate::queryengine::quota)) while the server is running:
+//! - `QUOTA SET KEYSPACE|USER <name> <maxkeys> <maxbytes>`: set a quota, where a limit of zero
+//! means that there is no limit (and a quota without any limit is removed)
+//! - `QUOTA GET KEYSPACE|USER <name>`: the limits and the usage as a flat array of name/value
+//! pairs (or nil if there is no quota)
+//! - `QUOTA LIST`: everything that has a quota as a flat array of kind/name pairs
+
+use {
+    crate::{
+        actions::ActionResult,
+        auth::AuthProvider,
+        corestore::memstore::ObjectID,
+        dbnet::prelude::*,
+        queryengine::quota::{Quota, Usage},
+    },
+    core::str,
+};
+
+const SET: &[u8] = b"set";
+const GET: &[u8] = b"get";
+const LIST: &[u8] = b"list";
+const KIND_KEYSPACE: &[u8] = b"keyspace";
+const KIND_USER: &[u8] = b"user";
+
+fn parse_limit<P: ProtocolSpec>(limit: &[u8]) -> ActionResult<u64> {
+    match str::from_utf8(limit)
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+    {
+        Some(limit) => Ok(limit),
+        None => util::err(P::RCODE_WRONGTYPE_ERR),
+    }
+}
+
+action! {
+    /// Get or set the quotas. If auth is enabled, only root can run this
+    fn quota(
+        handle: &Corestore,
+        con: &mut Connection<C, P>,
+        auth: &mut AuthProviderHandle,
+        iter: ActionIter<'_>,
+    ) {
+        let mut iter = iter;
+        auth.provider().ensure_root_if_enabled::<P>()?;
+        match unsafe { iter.next_lowercase_unchecked() }.as_ref() {
+            LIST => {
+                ensure_length::<P>(iter.len(), |len| len == 0)?;
+                quota_list(handle, con).await
+            }
+            GET => {
+                ensure_length::<P>(iter.len(), |len| len == 2)?;
+                quota_get(handle, con, &mut iter).await
+            }
+            SET => {
+                ensure_length::<P>(iter.len(), |len| len == 4)?;
+                quota_set(handle, con, &mut iter).await
+            }
+            _ => util::err(P::RCODE_UNKNOWN_ACTION),
+        }
+    }
+    fn quota_list(handle: &Corestore, con: &mut Connection<C, P>) {
+        let quotas = handle.get_quotas();
+        let (keyspaces, users) = (quotas.keyspaces(), quotas.users());
+        con.write_array_header(2 * (keyspaces.len() + users.len())).await?;
+        for keyspace in keyspaces {
+            con.write_string("keyspace").await?;
+            con.write_string(&String::from_utf8_lossy(&keyspace)).await?;
+        }
+        for user in users {
+            con.write_string("user").await?;
+            con.write_string(&String::from_utf8_lossy(&user)).await?;
+        }
+        Ok(())
+    }
+    fn quota_get(handle: &Corestore, con: &mut Connection<C, P>, iter: &mut ActionIter<'_>) {
+        let quotas = handle.get_quotas();
+        let kind = unsafe { iter.next_lowercase_unchecked() };
+        let name = unsafe { iter.next_unchecked() };
+        let quota = match kind.as_ref() {
+            KIND_KEYSPACE => quotas.keyspace(name),
+            KIND_USER => quotas.user(name),
+            _ => return util::err(P::RCODE_UNKNOWN_ACTION),
+        };
+        match quota {
+            Some((quota, Usage { keys, bytes })) => {
+                let fields = [
+                    ("maxkeys", quota.maxkeys()),
+                    ("maxbytes", quota.maxbytes()),
+                    ("keys", keys),
+                    ("bytes", bytes),
+                ];
+                con.write_array_header(2 * fields.len()).await?;
+                for (name, value) in fields {
+                    con.write_string(name).await?;
+                    con.write_int64(value).await?;
+                }
+            }
+            None => con._write_raw(P::RCODE_NIL).await?,
+        }
+        Ok(())
+    }
+    fn quota_set(handle: &Corestore, con: &mut Connection<C, P>, iter: &mut ActionIter<'_>) {
+        let quotas = handle.get_quotas();
+        let kind = unsafe { iter.next_lowercase_unchecked() };
+        let name = unsafe { iter.next_unchecked() };
+        let maxkeys = parse_limit::<P>(unsafe { iter.next_unchecked() })?;
+        let maxbytes = parse_limit::<P>(unsafe { iter.next_unchecked() })?;
+        let quota = Quota::new(maxkeys, maxbytes);
+        match kind.as_ref() {
+            KIND_KEYSPACE => {
+                let keyspace = ObjectID::try_from_slice(name)
+                    .unwrap_or_custom_aerr(P::RSTRING_BAD_CONTAINER_NAME)?;
+                quotas.set_keyspace(keyspace, quota);
+            }
+            KIND_USER => quotas.set_user(AuthProvider::try_auth_id::<P>(name)?, quota),
+            _ => return util::err(P::RCODE_UNKNOWN_ACTION),
+        }
+        log::info!(
+            "Quota of {} `{}` set to {} keys and {} bytes",
+            String::from_utf8_lossy(kind.as_ref()),
+            String::from_utf8_lossy(name),
+            maxkeys,
+            maxbytes
+        );
+        con._write_raw(P::RCODE_OKAY).await?;
+        Ok(())
+    }
+}
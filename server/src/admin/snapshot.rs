@@ -0,0 +1,125 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use crate::{
+    corestore::{memstore::Memstore, Corestore},
+    dbnet::prelude::*,
+    kvengine::encoding,
+    services,
+    storage::v1::{interface::DataDir, sengine::SnapshotActionResult},
+};
+
+action!(
+    /// List all the local and remote snapshots
+    fn lssnap(
+        handle: &crate::corestore::Corestore,
+        con: &mut Connection<C, P>,
+        act: ActionIter<'a>,
+    ) {
+        ensure_length::<P>(act.len(), |len| len == 0)?;
+        let snapshots = handle.get_engine().list_snapshots();
+        con.write_typed_non_null_array_header(snapshots.len(), b'+')
+            .await?;
+        for snapshot in snapshots {
+            con.write_typed_non_null_array_element(snapshot.as_bytes())
+                .await?;
+        }
+        Ok(())
+    }
+);
+
+action!(
+    /// Restore the data from a local or remote snapshot without restarting the server
+    fn restoresnap(
+        handle: &crate::corestore::Corestore,
+        con: &mut Connection<C, P>,
+        mut act: ActionIter<'a>,
+    ) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let name = unsafe {
+            // SAFETY: We have already checked that there is one item
+            act.next_unchecked_bytes()
+        };
+        if !encoding::is_utf8(&name) {
+            return util::err(P::RCODE_ENCODING_ERROR);
+        }
+        if !registry::state_okay() {
+            return util::err(P::RCODE_SERVER_ERR);
+        }
+        // no need to sanitize the name; we only accept the names of snapshots that we know of
+        let restore_handle = handle.clone();
+        match handle
+            .get_engine()
+            .restore(&name, move |snapshot| {
+                self::restore(&restore_handle, snapshot)
+            })
+            .await
+        {
+            SnapshotActionResult::Ok => con._write_raw(P::RCODE_OKAY).await?,
+            SnapshotActionResult::Failure => return util::err(P::RCODE_SERVER_ERR),
+            SnapshotActionResult::Busy => return util::err(P::RSTRING_SNAPSHOT_BUSY),
+            SnapshotActionResult::NotFound => return util::err(P::RSTRING_SNAPSHOT_NOT_FOUND),
+            SnapshotActionResult::Replicated => return util::err(P::RSTRING_SNAPSHOT_REPLICATED),
+            _ => unsafe { impossible!() },
+        }
+        Ok(())
+    }
+);
+
+/// Restore the data from `snapshot` and save it (see [`services::bgsave::restore_in`]). This
+/// blocks, so it has to be run on a thread that doesn't run async tasks
+fn restore(handle: &Corestore, snapshot: Memstore) -> SnapshotActionResult {
+    // no query can run while we hold this, so the check below can't race with a replica
+    // that's syncing and no query sees a half-restored store
+    let txn_lock = registry::lock_txn_exclusive_blocking();
+    let replication = handle.get_replication();
+    if replication.is_streaming() || replication.peers().is_enabled() {
+        // the restore isn't a write that can be sent to the replicas (or the peers), so they
+        // would silently diverge from us
+        return SnapshotActionResult::Replicated;
+    }
+    match services::bgsave::restore_in(&DataDir::current(), handle, snapshot, txn_lock) {
+        Ok(skipped) => {
+            if skipped != 0 {
+                log::warn!(
+                    "Skipped restoring {} tables that changed their model and are in use",
+                    skipped
+                );
+            }
+            log::info!("Successfully restored data from snapshot");
+            SnapshotActionResult::Ok
+        }
+        Err(e) => {
+            // the restored data is live, but a restart would bring back the old data
+            log::error!(
+                "Failed to save the data restored from a snapshot with error: {}",
+                e
+            );
+            registry::poison();
+            SnapshotActionResult::Failure
+        }
+    }
+}
@@ -26,44 +26,375 @@
 
 use {
     crate::{
-        corestore::booltable::BoolTable, dbnet::prelude::*,
-        storage::v1::interface::DIR_ROOT,
+        actions::{randomkey, ActionResult},
+        corestore::{booltable::BoolTable, slab, spill},
+        dbnet::{compression::Codec, prelude::*},
+        queryengine,
+        replication::ReplicationStatus,
+        services::{jobs::KillError, reload},
+        storage::v1::{compress, interface::DIR_ROOT},
     },
     libsky::VERSION,
 };
 
 const INFO: &[u8] = b"info";
 const METRIC: &[u8] = b"metric";
+const STATS: &[u8] = b"stats";
+const RELOADCONF: &[u8] = b"reloadconf";
+const COMMANDS: &[u8] = b"commands";
+const MEMORY: &[u8] = b"memory";
+const JOB: &[u8] = b"job";
+const JOBS: &[u8] = b"jobs";
+const PING: &[u8] = b"ping";
 const INFO_PROTOCOL: &[u8] = b"protocol";
 const INFO_PROTOVER: &[u8] = b"protover";
 const INFO_VERSION: &[u8] = b"version";
+const INFO_REPLICATION: &[u8] = b"replication";
+const INFO_COMPRESSION: &[u8] = b"compression";
+const INFO_NETWORK: &[u8] = b"network";
 const METRIC_HEALTH: &[u8] = b"health";
 const METRIC_STORAGE_USAGE: &[u8] = b"storage";
+const METRIC_TABLE_STATS: &[u8] = b"tablestats";
+const METRIC_UPTIME: &[u8] = b"uptime";
+const METRIC_CLIENTS: &[u8] = b"clients";
+const METRIC_COMMANDS: &[u8] = b"commands";
+const METRIC_KEYS: &[u8] = b"keys";
+const METRIC_MEMORY: &[u8] = b"memory";
+const METRIC_SPILL: &[u8] = b"spill";
+const METRIC_SLAB: &[u8] = b"slab";
+const MEMORY_USAGE: &[u8] = b"usage";
+const MEMORY_KEYSPACES: &[u8] = b"keyspaces";
+const JOBS_LIST: &[u8] = b"list";
+const JOBS_KILL: &[u8] = b"kill";
 const ERR_UNKNOWN_PROPERTY: &[u8] = b"!16\nunknown-property\n";
 const ERR_UNKNOWN_METRIC: &[u8] = b"!14\nunknown-metric\n";
+const ERR_BAD_CONFIG: &[u8] = b"!10\nbad-config\n";
+const ERR_NOT_KILLABLE: &[u8] = b"!12\nnot-killable\n";
 
 const HEALTH_TABLE: BoolTable<&str> = BoolTable::new("good", "critical");
+const AOF_TABLE: BoolTable<&str> = BoolTable::new("enabled", "disabled");
+
+/// The number of entries sampled per table to estimate the memory usage
+const MEMORY_SAMPLES: usize = 256;
+
+/// Returns an estimate of the number of bytes used by the data in all the tables. Every table
+/// is sampled instead of walked (see
+/// [`Table::estimate_memory_usage`](crate::corestore::table::Table::estimate_memory_usage)),
+/// so polling this doesn't get slower as the dataset grows
+fn estimate_memory_usage(handle: &Corestore) -> u64 {
+    handle
+        .get_store()
+        .list_all_tables()
+        .iter()
+        .map(|tbl| tbl.estimate_memory_usage(MEMORY_SAMPLES, randomkey::random_number()) as u64)
+        .sum()
+}
 
 action! {
-    fn sys(_handle: &Corestore, con: &mut Connection<C, P>, iter: ActionIter<'_>) {
+    fn sys(
+        handle: &Corestore,
+        con: &mut Connection<C, P>,
+        auth: &mut AuthProviderHandle,
+        iter: ActionIter<'_>,
+    ) {
         let mut iter = iter;
-        ensure_boolean_or_aerr::<P>(iter.len() == 2)?;
+        ensure_length::<P>(iter.len(), |len| (1..=3).contains(&len))?;
+        let len = iter.len();
+        let single = len == 1;
         match unsafe { iter.next_lowercase_unchecked() }.as_ref() {
-            INFO => sys_info(con, &mut iter).await,
-            METRIC => sys_metric(con, &mut iter).await,
+            PING if single => sys_ping(con).await,
+            STATS if single => sys_stats(handle, con).await,
+            COMMANDS if single => sys_commands(con).await,
+            RELOADCONF if single => {
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                sys_reloadconf(handle, con).await
+            }
+            _ if single => util::err(P::RCODE_ACTION_ERR),
+            MEMORY => sys_memory(handle, con, &mut iter).await,
+            INFO if len == 2 => sys_info(handle, con, &mut iter).await,
+            METRIC if len == 2 => sys_metric(handle, con, &mut iter).await,
+            JOB if len == 2 => sys_job(handle, con, &mut iter).await,
+            JOBS => sys_jobs(handle, con, auth, &mut iter).await,
+            INFO | METRIC | JOB => util::err(P::RCODE_ACTION_ERR),
             _ => util::err(P::RCODE_UNKNOWN_ACTION),
         }
     }
-    fn sys_info(con: &mut Connection<C, P>, iter: &mut ActionIter<'_>) {
+    /// Run `SYS PING` for a connection that hasn't logged in, since health checks shouldn't
+    /// need credentials. Any other query is refused as usual
+    fn sys_noauth(con: &mut Connection<C, P>, iter: ActionIter<'_>) {
+        let mut iter = iter;
+        match iter.next_lowercase() {
+            Some(sub) if iter.is_empty() && sub.as_ref() == PING => sys_ping(con).await,
+            _ => util::err(P::AUTH_CODE_BAD_CREDENTIALS),
+        }
+    }
+    /// Reply to `SYS PING` with the smallest possible response
+    fn sys_ping(con: &mut Connection<C, P>) {
+        con.write_string("PONG").await?;
+        Ok(())
+    }
+    /// Reload the settings that can be changed at runtime (see [`reload::reload_config`]).
+    /// If auth is enabled, only root can run this
+    fn sys_reloadconf(handle: &Corestore, con: &mut Connection<C, P>) {
+        match reload::reload_config(handle) {
+            Ok(()) => con._write_raw(P::RCODE_OKAY).await?,
+            Err(e) => {
+                log::error!("Failed to reload the configuration: {e}");
+                return util::err(ERR_BAD_CONFIG);
+            }
+        }
+        Ok(())
+    }
+    /// Write out all the server statistics as a flat array of name/value pairs
+    fn sys_stats(handle: &Corestore, con: &mut Connection<C, P>) {
+        let stats = handle.get_stats();
+        let tables = handle.get_store().list_all_tables();
+        let (hits, misses) = tables
+            .iter()
+            .map(|tbl| tbl.lookup_stats())
+            .fold((0, 0), |(h, m), (th, tm)| (h + th, m + tm));
+        let keyspaces: Vec<(String, u64)> = handle
+            .get_store()
+            .keyspaces
+            .iter()
+            .map(|ks| {
+                let name = String::from_utf8_lossy(ks.key().as_slice()).into_owned();
+                let keys = ks.value().tables.iter().map(|tbl| tbl.count() as u64).sum();
+                (format!("keyspace.{name}"), keys)
+            })
+            .collect();
+        let counters = [
+            ("uptime", stats.uptime()),
+            ("clients", stats.clients()),
+            ("connections", stats.connections()),
+            ("rejected", stats.rejected()),
+            ("commands", stats.commands()),
+            ("hits", hits),
+            ("misses", misses),
+            ("keys", keyspaces.iter().map(|(_, keys)| keys).sum()),
+            ("memory", estimate_memory_usage(handle)),
+            ("lastsave", stats.last_save()),
+        ];
+        con.write_array_header(2 * (counters.len() + keyspaces.len() + 3)).await?;
+        con.write_string("version").await?;
+        con.write_string(VERSION).await?;
+        con.write_string("health").await?;
+        con.write_string(HEALTH_TABLE[registry::state_okay()]).await?;
+        con.write_string("aof").await?;
+        con.write_string(AOF_TABLE[handle.get_aof().is_some()]).await?;
+        for (name, value) in counters {
+            con.write_string(name).await?;
+            con.write_int64(value).await?;
+        }
+        for (name, keys) in keyspaces {
+            con.write_string(&name).await?;
+            con.write_int64(keys).await?;
+        }
+        Ok(())
+    }
+    /// Write out the command table as a flat array with the name, the (least) number of
+    /// arguments and the flags of every action
+    fn sys_commands(con: &mut Connection<C, P>) {
+        let actions: Vec<_> = queryengine::all_actions().collect();
+        con.write_array_header(3 * actions.len()).await?;
+        for spec in actions {
+            con.write_string(spec.name).await?;
+            con.write_int64(spec.arity.min() as u64).await?;
+            con.write_string(&spec.describe_flags()).await?;
+        }
+        Ok(())
+    }
+    fn sys_info(handle: &Corestore, con: &mut Connection<C, P>, iter: &mut ActionIter<'_>) {
         match unsafe { iter.next_lowercase_unchecked() }.as_ref() {
             INFO_PROTOCOL => con.write_string(P::PROTOCOL_VERSIONSTRING).await?,
             INFO_PROTOVER => con.write_float(P::PROTOCOL_VERSION).await?,
             INFO_VERSION => con.write_string(VERSION).await?,
+            INFO_REPLICATION => sys_info_replication(handle, con).await?,
+            INFO_COMPRESSION => sys_info_compression(con).await?,
+            INFO_NETWORK => sys_info_network(handle, con).await?,
             _ => return util::err(ERR_UNKNOWN_PROPERTY),
         }
         Ok(())
     }
-    fn sys_metric(con: &mut Connection<C, P>, iter: &mut ActionIter<'_>) {
+    /// Write out the replication status as a flat array of name/value pairs
+    fn sys_info_replication(handle: &Corestore, con: &mut Connection<C, P>) {
+        match handle.get_replication().status() {
+            ReplicationStatus::Primary { replicas } => {
+                con.write_array_header(4).await?;
+                con.write_string("role").await?;
+                con.write_string("primary").await?;
+                con.write_string("replicas").await?;
+                con.write_usize(replicas).await?;
+            }
+            ReplicationStatus::Replica {
+                primary,
+                link,
+                applied,
+                last_sync,
+                lag,
+            } => {
+                con.write_array_header(12).await?;
+                con.write_string("role").await?;
+                con.write_string("replica").await?;
+                con.write_string("primary").await?;
+                con.write_string(&primary).await?;
+                con.write_string("link").await?;
+                con.write_string(link.as_str()).await?;
+                con.write_string("applied").await?;
+                con.write_int64(applied).await?;
+                con.write_string("lastsync").await?;
+                con.write_int64(last_sync).await?;
+                con.write_string("lag").await?;
+                match lag {
+                    Some(lag) => con.write_int64(lag).await?,
+                    None => con.write_string("unknown").await?,
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Write out the compression settings and what compression has saved since startup, as a
+    /// flat array of name/value pairs
+    fn sys_info_compression(con: &mut Connection<C, P>) {
+        let (codec, min) = match compress::settings() {
+            Some((Codec::Lz4, min)) => ("lz4", min),
+            Some((Codec::Zstd, min)) => ("zstd", min),
+            None => ("none", 0),
+        };
+        let stats = compress::stats();
+        con.write_array_header(12).await?;
+        con.write_string("codec").await?;
+        con.write_string(codec).await?;
+        con.write_string("compressmin").await?;
+        con.write_usize(min).await?;
+        con.write_string("values").await?;
+        con.write_int64(stats.values).await?;
+        con.write_string("rawbytes").await?;
+        con.write_int64(stats.raw).await?;
+        con.write_string("storedbytes").await?;
+        con.write_int64(stats.stored).await?;
+        con.write_string("saved").await?;
+        con.write_int64(stats.saved()).await?;
+        Ok(())
+    }
+    /// Write out the number of IP rules and the clients they turned away since startup, as a
+    /// flat array of name/value pairs
+    fn sys_info_network(handle: &Corestore, con: &mut Connection<C, P>) {
+        let (allow, deny) = {
+            let runtime = handle.get_runtime_config().load();
+            (runtime.ipfilter.allow.len(), runtime.ipfilter.deny.len())
+        };
+        let stats = handle.get_stats();
+        con.write_array_header(8).await?;
+        con.write_string("allow").await?;
+        con.write_usize(allow).await?;
+        con.write_string("deny").await?;
+        con.write_usize(deny).await?;
+        con.write_string("denied").await?;
+        con.write_int64(stats.denied()).await?;
+        con.write_string("notallowed").await?;
+        con.write_int64(stats.not_allowed()).await?;
+        Ok(())
+    }
+    /// Run `SYS MEMORY USAGE <key>`, which returns the approximate number of bytes used by a
+    /// key in the current table (or `Nil` if it doesn't exist), or `SYS MEMORY KEYSPACES`, which
+    /// returns the approximate number of bytes used by every keyspace as a flat array of
+    /// name/value pairs
+    fn sys_memory(handle: &Corestore, con: &mut Connection<C, P>, iter: &mut ActionIter<'_>) {
+        match unsafe { iter.next_lowercase_unchecked() }.as_ref() {
+            MEMORY_USAGE if iter.len() == 1 => {
+                let key = unsafe {
+                    // UNSAFE(@ohsayan): We just checked that there is exactly one argument left
+                    iter.next_unchecked()
+                };
+                let tbl = get_tbl_ref!(handle, con);
+                match tbl.memory_usage_of(key) {
+                    Some(bytes) => con.write_usize(bytes).await?,
+                    None => con._write_raw(P::RCODE_NIL).await?,
+                }
+            }
+            MEMORY_KEYSPACES if iter.is_empty() => {
+                let keyspaces: Vec<(String, usize)> = handle
+                    .get_store()
+                    .keyspaces
+                    .iter()
+                    .map(|ks| {
+                        let name = String::from_utf8_lossy(ks.key().as_slice()).into_owned();
+                        let bytes = ks
+                            .value()
+                            .estimate_memory_usage(MEMORY_SAMPLES, randomkey::random_number);
+                        (name, bytes)
+                    })
+                    .collect();
+                con.write_array_header(2 * keyspaces.len()).await?;
+                for (name, bytes) in keyspaces {
+                    con.write_string(&name).await?;
+                    con.write_usize(bytes).await?;
+                }
+            }
+            MEMORY_USAGE | MEMORY_KEYSPACES => return util::err(P::RCODE_ACTION_ERR),
+            _ => return util::err(ERR_UNKNOWN_PROPERTY),
+        }
+        Ok(())
+    }
+    /// Run `SYS JOB <id>`, which returns the state of a background job (`running`, `done`,
+    /// `failed` or `killed`), the amount of work that it found and the amount of work that it
+    /// did (or `Nil` if there's no such job)
+    fn sys_job(handle: &Corestore, con: &mut Connection<C, P>, iter: &mut ActionIter<'_>) {
+        let id = unsafe {
+            // UNSAFE(@ohsayan): We have already checked the length
+            iter.next_unchecked()
+        };
+        match handle.get_jobs().get(parse_job_id::<P>(id)?) {
+            Some(job) => {
+                con.write_array_header(3).await?;
+                con.write_string(job.state().as_str()).await?;
+                con.write_int64(job.total()).await?;
+                con.write_int64(job.done()).await?;
+            }
+            None => con._write_raw(P::RCODE_NIL).await?,
+        }
+        Ok(())
+    }
+    /// Run `SYS JOBS LIST`, which returns the ID, the kind, the state and the progress of every
+    /// job as a flat array, or `SYS JOBS KILL <id>`, which asks a running job to stop. If auth
+    /// is enabled, only root can kill a job
+    fn sys_jobs(
+        handle: &Corestore,
+        con: &mut Connection<C, P>,
+        auth: &mut AuthProviderHandle,
+        iter: &mut ActionIter<'_>,
+    ) {
+        match unsafe { iter.next_lowercase_unchecked() }.as_ref() {
+            JOBS_LIST if iter.is_empty() => {
+                let jobs = handle.get_jobs().list();
+                con.write_array_header(5 * jobs.len()).await?;
+                for (id, job) in jobs {
+                    con.write_int64(id).await?;
+                    con.write_string(job.kind().as_str()).await?;
+                    con.write_string(job.state().as_str()).await?;
+                    con.write_int64(job.total()).await?;
+                    con.write_int64(job.done()).await?;
+                }
+            }
+            JOBS_KILL if iter.len() == 1 => {
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                let id = unsafe {
+                    // UNSAFE(@ohsayan): We just checked that there is exactly one argument left
+                    iter.next_unchecked()
+                };
+                match handle.get_jobs().kill(parse_job_id::<P>(id)?) {
+                    Ok(()) => con._write_raw(P::RCODE_OKAY).await?,
+                    Err(KillError::NotRunning) => con._write_raw(P::RCODE_NIL).await?,
+                    Err(KillError::NotKillable) => return util::err(ERR_NOT_KILLABLE),
+                }
+            }
+            JOBS_LIST | JOBS_KILL => return util::err(P::RCODE_ACTION_ERR),
+            _ => return util::err(ERR_UNKNOWN_PROPERTY),
+        }
+        Ok(())
+    }
+    fn sys_metric(handle: &Corestore, con: &mut Connection<C, P>, iter: &mut ActionIter<'_>) {
         match unsafe { iter.next_lowercase_unchecked() }.as_ref() {
             METRIC_HEALTH => {
                 con.write_string(HEALTH_TABLE[registry::state_okay()]).await?
@@ -77,8 +408,54 @@ action! {
                     },
                 }
             }
+            METRIC_TABLE_STATS => {
+                let stats = match handle.get_ctable_ref() {
+                    Some(tbl) => tbl.map_stats(),
+                    None => return util::err(P::RSTRING_DEFAULT_UNSET),
+                };
+                con.write_array_header(5).await?;
+                con.write_usize(stats.buckets).await?;
+                con.write_usize(stats.occupied).await?;
+                con.write_usize(stats.tombstones).await?;
+                con.write_usize(stats.longest_probe).await?;
+                con.write_float(stats.load_factor()).await?;
+            }
+            METRIC_UPTIME => con.write_int64(handle.get_stats().uptime()).await?,
+            METRIC_CLIENTS => con.write_int64(handle.get_stats().clients()).await?,
+            METRIC_COMMANDS => con.write_int64(handle.get_stats().commands()).await?,
+            METRIC_KEYS => {
+                let keys: usize = handle
+                    .get_store()
+                    .list_all_tables()
+                    .iter()
+                    .map(|tbl| tbl.count())
+                    .sum();
+                con.write_usize(keys).await?
+            }
+            METRIC_MEMORY => con.write_int64(estimate_memory_usage(handle)).await?,
+            METRIC_SPILL => {
+                let (values, bytes) = spill::stats();
+                con.write_array_header(2).await?;
+                con.write_int64(values).await?;
+                con.write_int64(bytes).await?;
+            }
+            METRIC_SLAB => {
+                let stats = slab::stats();
+                con.write_array_header(4).await?;
+                con.write_int64(stats.slices).await?;
+                con.write_int64(stats.used).await?;
+                con.write_int64(stats.reserved).await?;
+                con.write_float(stats.fragmentation()).await?;
+            }
             _ => return util::err(ERR_UNKNOWN_METRIC),
         }
         Ok(())
     }
 }
+
+fn parse_job_id<P: ProtocolSpec>(id: &[u8]) -> ActionResult<u64> {
+    match String::from_utf8_lossy(id).parse::<u64>() {
+        Ok(id) => Ok(id),
+        Err(_) => util::err(P::RCODE_WRONGTYPE_ERR),
+    }
+}
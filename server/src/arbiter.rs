@@ -26,19 +26,30 @@
 
 use {
     crate::{
-        auth::AuthProvider,
-        config::{ConfigurationSet, SnapshotConfig, SnapshotPref},
-        corestore::Corestore,
-        dbnet,
+        auth::{audit::AuditLog, export, AuthProvider, OriginKey},
+        config::{
+            AofConfig, ConfigurationSet, FsyncPolicy, RuntimeConfig, SnapshotConfig, SnapshotPref,
+            StartupActions,
+        },
+        corestore::{spill, Corestore},
+        dbnet::{self, governor::Governor, ConnectionOptions, StatusListener},
         diskstore::flock::FileLock,
-        services,
-        storage::v1::sengine::SnapshotEngine,
+        queryengine::ratelimit::RateLimiter,
+        registry, replication, services,
+        storage::v1::{
+            aof::{self, AppendOnlyLog},
+            compress, crypt,
+            interface::DataDir,
+            sengine::SnapshotEngine,
+            ttl,
+        },
+        streams::{self, Streams},
         util::{
             error::{Error, SkyResult},
             os::TerminationSignal,
         },
     },
-    std::{sync::Arc, thread::sleep},
+    std::{path::Path, sync::Arc, thread::sleep},
     tokio::{
         sync::{
             broadcast,
@@ -55,33 +66,150 @@ const TERMSIG_THRESHOLD: usize = 3;
 pub async fn run(
     ConfigurationSet {
         ports,
+        unixsocket,
+        httpport,
+        grpcport,
+        statusport,
+        compressmin,
+        maxinflight,
+        maxoutbuf,
+        keepalive,
+        limits,
         bgsave,
         snapshot,
         maxcon,
+        timeouts,
+        maxlag,
+        ratelimits,
+        ipfilter,
         auth,
         protocol,
+        eviction,
+        aof: aof_cfg,
+        storage,
+        spillmin,
+        keepexpired,
+        encryption,
+        logging,
         ..
     }: ConfigurationSet,
-    restore_filepath: Option<String>,
+    StartupActions { restore, recover }: StartupActions,
 ) -> SkyResult<Corestore> {
     // Intialize the broadcast channel
     let (signal, _) = broadcast::channel(1);
+    // the probes are answered while the data is loaded, so this goes first
+    let status = match statusport {
+        Some(port) => {
+            let host = ports.get_host();
+            let status = StatusListener::init(host, port, &signal).await?;
+            log::info!("Health and readiness endpoints started on http://{host}:{port}");
+            Some(status)
+        }
+        None => None,
+    };
     let engine = match &snapshot {
         SnapshotConfig::Enabled(SnapshotPref { atmost, .. }) => SnapshotEngine::new(*atmost),
         SnapshotConfig::Disabled => SnapshotEngine::new_disabled(),
     };
     let engine = Arc::new(engine);
+    compress::configure(storage);
+    crypt::configure(&encryption)
+        .map_err(|e| Error::ioerror_extra(e, "loading the encryption keys"))?;
+    ttl::configure(keepexpired);
     // restore data
-    services::restore_data(restore_filepath)
+    services::restore_data(restore)
         .map_err(|e| Error::ioerror_extra(e, "restoring data from backup"))?;
+    // roll the data back to a snapshot; the writes made since are replayed once it's loaded
+    let recovered = match recover {
+        Some(target) => Some(
+            services::aof::prepare_recovery(target)
+                .map_err(|e| Error::ioerror_extra(e, "preparing the recovery"))?,
+        ),
+        None => None,
+    };
+    // values are spilled as they're loaded, so this has to be set up before the store
+    spill::configure(&DataDir::current(), spillmin)
+        .map_err(|e| Error::ioerror_extra(e, "preparing the spill directory"))?;
     // init the store
-    let db = Corestore::init_with_snapcfg(engine.clone())?;
+    let mut db = Corestore::init_with_snapcfg(&DataDir::current(), engine.clone())?;
+    // the settings that can be reloaded while the server is running
+    let runtime = RuntimeConfig::new(
+        logging.levels,
+        bgsave,
+        maxcon,
+        timeouts,
+        ratelimits,
+        ipfilter,
+        maxlag,
+    )
+    .into_shared();
+    db.set_runtime_config(runtime.clone());
+    db.get_hooks()
+        .register(Arc::new(RateLimiter::new(runtime.clone())));
+    db.get_hooks().register(db.clone_quotas());
     // refresh the snapshotengine state
     engine.parse_dir()?;
-    let auth_provider = match auth.origin_key {
-        Some(key) => {
+    // replay the append-only log (if enabled) and start recording writes
+    if let AofConfig::Enabled(fsync) = aof_cfg {
+        let replayed = match recovered {
+            Some(records) => services::aof::replay_records(&db, &records).await,
+            None => services::aof::replay_log(&db).await,
+        }
+        .map_err(|e| Error::ioerror_extra(e, "replaying the append-only log"))?;
+        if replayed != 0 {
+            log::info!("Replayed {} writes from the append-only log", replayed);
+            // everything we replayed is now on disk, so the logs aren't needed anymore
+            let cloned_db = db.clone();
+            tokio::task::spawn_blocking(move || services::bgsave::run_bgsave(&cloned_db))
+                .await
+                .expect("Something caused the background service to panic")
+                .map_err(|e| Error::ioerror_extra(e, "flushing the replayed writes"))?;
+        }
+        // the older logs are archived for recoveries if there are snapshots to recover from
+        let archive = matches!(snapshot, SnapshotConfig::Enabled(_));
+        if archive {
+            aof::archive_logs()
+        } else {
+            aof::remove_logs()
+        }
+        .map_err(|e| Error::ioerror_extra(e, "removing the older logs"))?;
+        let log = AppendOnlyLog::open(&DataDir::current(), fsync, archive)
+            .map_err(|e| Error::ioerror_extra(e, "opening the append-only log"))?;
+        db.set_aof(Arc::new(log));
+    } else if Path::new(aof::AOF_PATH).exists() {
+        log::warn!("Found an append-only log, but the AOF is disabled. Ignoring it");
+    }
+    // the streams are synced just like the append-only log (or left to the OS without one)
+    let streams_fsync = match aof_cfg {
+        AofConfig::Enabled(fsync) => fsync,
+        AofConfig::Disabled => FsyncPolicy::No,
+    };
+    let streams = Streams::open(streams::STREAMS_PATH, streams_fsync)
+        .map_err(|e| Error::ioerror_extra(e, "loading the streams"))?;
+    db.set_streams(Arc::new(streams));
+    let origin = match (auth.origin_key, auth.origin_file.as_ref()) {
+        (Some(key), _) => Some(OriginKey::new(key.into_inner())),
+        (None, Some(path)) => Some(
+            OriginKey::bootstrap(path)
+                .map_err(|e| Error::ioerror_extra(e, "loading the origin key file"))?,
+        ),
+        (None, None) => None,
+    };
+    let auth_provider = match origin {
+        Some(origin) => {
             let authref = db.get_store().setup_auth();
-            AuthProvider::new(authref, Some(key.into_inner()))
+            let audit = match auth.audit_log.as_ref() {
+                Some(path) => AuditLog::open(path)
+                    .map_err(|e| Error::ioerror_extra(e, "opening the audit log"))?,
+                None => AuditLog::new_in_memory(),
+            };
+            let provider = AuthProvider::new(authref, origin, &auth, audit);
+            if let Some(path) = auth.import.as_ref() {
+                let count = export::import(provider.authmap(), path)
+                    .map_err(|e| Error::ioerror_extra(e, "importing the users"))?;
+                log::info!("Imported {count} users from {path}");
+            }
+            provider
         }
         None => AuthProvider::new_disabled(),
     };
@@ -89,7 +217,6 @@ pub async fn run(
     // initialize the background services
     let bgsave_handle = tokio::spawn(services::bgsave::bgsave_scheduler(
         db.clone(),
-        bgsave,
         signal.subscribe(),
     ));
     let snapshot_handle = tokio::spawn(services::snapshot::snapshot_service(
@@ -98,15 +225,69 @@ pub async fn run(
         snapshot,
         signal.subscribe(),
     ));
+    let expiry_handle = tokio::spawn(services::expiry::expiry_sweeper(
+        db.clone(),
+        signal.subscribe(),
+    ));
+    let heartbeat_handle = tokio::spawn(replication::heartbeat_service(
+        db.clone(),
+        signal.subscribe(),
+    ));
+    let quota_handle = tokio::spawn(services::quota::quota_service(
+        db.clone(),
+        auth_provider.authmap().clone(),
+        signal.subscribe(),
+    ));
+    let aof_syncer_handle = if aof_cfg == AofConfig::Enabled(FsyncPolicy::EverySec) {
+        Some(tokio::spawn(services::aof::aof_syncer(
+            db.clone(),
+            signal.subscribe(),
+        )))
+    } else {
+        None
+    };
+    let governor = Arc::new(Governor::new(runtime));
+    let reaper_handle = tokio::spawn(services::reaper::idle_reaper(
+        governor.clone(),
+        signal.subscribe(),
+    ));
+    #[cfg(unix)]
+    let reload_handle = tokio::spawn(services::reload::reload_service(
+        db.clone(),
+        signal.subscribe(),
+    ));
+    registry::set_eviction_policy(eviction.policy);
+    let eviction_handle = if eviction.is_enabled() {
+        Some(tokio::spawn(services::eviction::eviction_service(
+            db.clone(),
+            eviction,
+            signal.subscribe(),
+        )))
+    } else {
+        None
+    };
 
     // bind to signals
     let termsig =
         TerminationSignal::init().map_err(|e| Error::ioerror_extra(e, "binding to signals"))?;
+    if let Some(ref status) = status {
+        status.set_loaded(&db);
+    }
     // start the server (single or multiple listeners)
     let mut server = dbnet::connect(
         ports,
+        unixsocket,
+        httpport,
+        grpcport,
         protocol,
-        maxcon,
+        ConnectionOptions {
+            compressmin,
+            maxinflight,
+            maxoutbuf,
+            keepalive,
+            limits,
+        },
+        governor,
         db.clone(),
         auth_provider,
         signal.clone(),
@@ -122,10 +303,25 @@ pub async fn run(
     // drop the signal and let others exit
     drop(signal);
     server.finish_with_termsig().await;
+    if let Some(status) = status {
+        status.release_self().await;
+    }
 
     // wait for the background services to terminate
     let _ = snapshot_handle.await;
     let _ = bgsave_handle.await;
+    let _ = expiry_handle.await;
+    let _ = heartbeat_handle.await;
+    let _ = quota_handle.await;
+    if let Some(eviction_handle) = eviction_handle {
+        let _ = eviction_handle.await;
+    }
+    if let Some(aof_syncer_handle) = aof_syncer_handle {
+        let _ = aof_syncer_handle.await;
+    }
+    let _ = reaper_handle.await;
+    #[cfg(unix)]
+    let _ = reload_handle.await;
     Ok(db)
 }
 
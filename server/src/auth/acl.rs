@@ -0,0 +1,125 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Keyspace ACLs
+//!
+//! Root can restrict a user to a set of keyspaces, so that multiple tenants can share a server
+//! without seeing each other's data. A restricted user can only run actions on the tables of the
+//! keyspaces they are allowed to use, and can't run any action that works on the whole server
+//! (like taking a snapshot). BlueQL statements are checked against the keyspace that they work
+//! on, when they're executed
+
+use crate::{
+    corestore::memstore::ObjectID,
+    queryengine::{self, commands::ActionFlags},
+};
+
+/// What a query has access to
+pub enum Scope {
+    /// the query doesn't touch any keyspace
+    Free,
+    /// the query works on the current keyspace
+    CurrentKeyspace,
+    /// the query is a BlueQL statement (which knows what keyspace it works on)
+    Statement,
+    /// the query works on the whole server
+    Server,
+}
+
+impl Scope {
+    /// Returns the scope of a query starting with `action` (see [`ActionFlags`])
+    pub fn of(action: &[u8]) -> Self {
+        let flags = queryengine::action_flags(action);
+        if flags.contains(ActionFlags::KEYSPACE_FREE) {
+            Self::Free
+        } else if flags.contains(ActionFlags::SERVER) {
+            Self::Server
+        } else if action.iter().any(u8::is_ascii_whitespace) {
+            // actions are a single word, while statements are followed by their arguments
+            Self::Statement
+        } else {
+            Self::CurrentKeyspace
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+/// The keyspaces that a user is allowed to use (`None` if they can use any keyspace)
+pub struct KeyspaceAcl(Option<Vec<ObjectID>>);
+
+impl KeyspaceAcl {
+    /// The maximum number of keyspaces that a user can be restricted to
+    pub const MAX_KEYSPACES: usize = u8::MAX as usize;
+    pub const fn unrestricted() -> Self {
+        Self(None)
+    }
+    pub fn restricted(keyspaces: Vec<ObjectID>) -> Self {
+        Self(Some(keyspaces))
+    }
+    pub const fn is_restricted(&self) -> bool {
+        self.0.is_some()
+    }
+    /// Returns the keyspaces that the user is restricted to (`None` if they can use any)
+    pub fn keyspaces(&self) -> Option<&[ObjectID]> {
+        self.0.as_deref()
+    }
+    /// Returns true if the keyspace can be used. If `keyspace` is `None` (no keyspace or the
+    /// whole server), only unrestricted users have access
+    pub fn allows(&self, keyspace: Option<&[u8]>) -> bool {
+        match (&self.0, keyspace) {
+            (None, _) => true,
+            (Some(keyspaces), Some(keyspace)) => keyspaces.iter().any(|ks| ks.eq(keyspace)),
+            (Some(_), None) => false,
+        }
+    }
+    /// Encode the ACL: `[count: 1B]` followed by `[len: 1B][keyspace]` for each keyspace. A
+    /// count of zero means that the user is unrestricted
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        let keyspaces = self.0.as_deref().unwrap_or_default();
+        buf.push(keyspaces.len() as u8);
+        for keyspace in keyspaces {
+            buf.push(keyspace.len() as u8);
+            buf.extend_from_slice(keyspace);
+        }
+    }
+    /// Decode an ACL from the start of `buf`, returning it along with the number of bytes read
+    pub fn decode(buf: &[u8]) -> Option<(Self, usize)> {
+        let (&count, mut rest) = buf.split_first()?;
+        if count == 0 {
+            return Some((Self::unrestricted(), 1));
+        }
+        let mut keyspaces = Vec::with_capacity(count as usize);
+        let mut read = 1;
+        for _ in 0..count {
+            let (&len, tail) = rest.split_first()?;
+            let keyspace = ObjectID::try_from_slice(tail.get(..len as usize)?)?;
+            keyspaces.push(keyspace);
+            rest = &tail[len as usize..];
+            read += 1 + len as usize;
+        }
+        Some((Self::restricted(keyspaces), read))
+    }
+}
@@ -0,0 +1,139 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Audit log
+//!
+//! Authentication events (claims, new and deleted users, regenerated keys and failed logins)
+//! and privileged actions are recorded in the audit log, one line per event. The most recent
+//! events are kept in memory (so that root can look at them with `AUTH AUDIT`) and, if an
+//! audit log file is configured, every event is also appended to it. The audit log file is
+//! never rotated or truncated by the server
+
+use {
+    crate::IoResult,
+    chrono::prelude::Utc,
+    core::fmt,
+    parking_lot::Mutex,
+    std::{
+        collections::VecDeque,
+        fs::{File, OpenOptions},
+        io::Write,
+        net::IpAddr,
+        path::Path,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kinds of events that are audited
+pub enum AuditEvent {
+    ClaimRoot,
+    ClaimUser,
+    DeleteUser,
+    Regenerate,
+    RotateOrigin,
+    LoginFailed,
+    /// an action that needs the `admin` permission, or changes another user
+    Privileged,
+}
+
+impl AuditEvent {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::ClaimRoot => "claim-root",
+            Self::ClaimUser => "claim-user",
+            Self::DeleteUser => "delete-user",
+            Self::Regenerate => "regenerate",
+            Self::RotateOrigin => "rotate-origin",
+            Self::LoginFailed => "login-failed",
+            Self::Privileged => "privileged",
+        }
+    }
+}
+
+/// The audit log, shared by all connections
+pub struct AuditLog {
+    /// the most recent events (oldest first)
+    recent: Mutex<VecDeque<String>>,
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLog {
+    /// The number of events that are kept in memory
+    pub const CAPACITY: usize = 1024;
+    /// An audit log that only keeps the recent events in memory
+    pub fn new_in_memory() -> Self {
+        Self {
+            recent: Mutex::new(VecDeque::with_capacity(Self::CAPACITY)),
+            file: None,
+        }
+    }
+    /// An audit log that also appends every event to the file at `path`
+    pub fn open(path: impl AsRef<Path>) -> IoResult<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+            ..Self::new_in_memory()
+        })
+    }
+    /// Record an event. `user` is the user that caused it (if anyone is logged in)
+    pub fn record(
+        &self,
+        event: AuditEvent,
+        user: Option<&[u8]>,
+        client: Option<IpAddr>,
+        detail: impl fmt::Display,
+    ) {
+        let mut line = format!(
+            "[{}] {} user={} client={}",
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            event.name(),
+            user.map(String::from_utf8_lossy).as_deref().unwrap_or("-"),
+            client.map_or_else(|| "-".to_owned(), |ip| ip.to_string()),
+        );
+        let detail = detail.to_string();
+        if !detail.is_empty() {
+            line.push(' ');
+            line.push_str(&detail);
+        }
+        if let Some(file) = self.file.as_ref() {
+            let mut file = file.lock();
+            if let Err(e) = writeln!(file, "{line}").and_then(|_| file.flush()) {
+                log::error!("Failed to write to the audit log: {e}");
+            }
+        }
+        let mut recent = self.recent.lock();
+        if recent.len() == Self::CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+    }
+    /// Returns (at most) `count` of the most recent events, oldest first
+    pub fn recent(&self, count: usize) -> Vec<String> {
+        let recent = self.recent.lock();
+        let skip = recent.len().saturating_sub(count);
+        recent.iter().skip(skip).cloned().collect()
+    }
+}
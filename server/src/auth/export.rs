@@ -0,0 +1,185 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Exporting and importing users
+//!
+//! Root can export all the users (with their hashed keys, permissions and keyspace
+//! restrictions) to a file with `AUTH EXPORT`, and import such a file on another instance with
+//! `AUTH IMPORT` (or at startup, with `auth.import`), so that a fleet of servers can be
+//! provisioned with the same users. The file is JSON and looks like:
+//! ```text
+//! {
+//!   "version": 1,
+//!   "users": [
+//!     {
+//!       "name": "tenant",
+//!       "key": "$argon2id$v=19$m=19456,t=2,p=1$...",
+//!       "permissions": ["read", "write"],
+//!       "keyspaces": ["tenant"],
+//!       "created": 1792108800
+//!     }
+//!   ]
+//! }
+//! ```
+//! `keyspaces` is left out for users that can use any keyspace, and `created` is optional. The
+//! keys are the PHC strings that are stored (see [`keys`](super::keys)); keys issued by older
+//! versions aren't PHC strings, so they're written as `$rcrypt$` followed by the key in base64.
+//!
+//! An import adds the users in the file and replaces the users with the same names, leaving
+//! any other users alone. Nothing is imported if any of the users in the file is invalid
+
+use {
+    super::{
+        acl::KeyspaceAcl,
+        keys, origin,
+        provider::{AuthID, Authmap, UserRecord, AUTHID_SIZE},
+        Permissions,
+    },
+    crate::{corestore::memstore::ObjectID, IoResult},
+    serde::{Deserialize, Serialize},
+    std::{
+        fs,
+        io::{Error as IoError, ErrorKind},
+        path::Path,
+    },
+};
+
+/// The version of the files that we write. A file of any other version is rejected
+const EXPORT_VERSION: u8 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct ExportFile {
+    version: u8,
+    users: Vec<ExportedUser>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedUser {
+    name: String,
+    key: String,
+    permissions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keyspaces: Option<Vec<String>>,
+    #[serde(default)]
+    created: u64,
+}
+
+impl ExportedUser {
+    fn new(name: &AuthID, record: &UserRecord) -> Self {
+        Self {
+            name: String::from_utf8_lossy(name).into_owned(),
+            key: keys::export_key(record.key()),
+            permissions: record
+                .permissions()
+                .names()
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+            keyspaces: record.acl().keyspaces().map(|keyspaces| {
+                keyspaces
+                    .iter()
+                    .map(|keyspace| String::from_utf8_lossy(keyspace).into_owned())
+                    .collect()
+            }),
+            created: record.created(),
+        }
+    }
+    fn into_record(self) -> IoResult<(AuthID, UserRecord)> {
+        let Self {
+            name,
+            key,
+            permissions,
+            keyspaces,
+            created,
+        } = self;
+        let invalid = |what: &str| invalid_data(format!("user `{name}` has {what}"));
+        if name.is_empty() || !name.is_ascii() || name.len() > AUTHID_SIZE {
+            return Err(invalid("an illegal name"));
+        }
+        let id = unsafe {
+            // We just verified the length
+            AuthID::from_slice(name.as_bytes())
+        };
+        let key = keys::import_key(&key).ok_or_else(|| invalid("an invalid key"))?;
+        let permissions = Permissions::from_names(permissions.iter().map(|name| name.as_bytes()))
+            .ok_or_else(|| invalid("an unknown permission"))?;
+        let acl = match keyspaces {
+            None => KeyspaceAcl::unrestricted(),
+            Some(keyspaces) if keyspaces.len() <= KeyspaceAcl::MAX_KEYSPACES => keyspaces
+                .iter()
+                .map(|keyspace| ObjectID::try_from_slice(keyspace.as_bytes()))
+                .collect::<Option<Vec<ObjectID>>>()
+                .map(KeyspaceAcl::restricted)
+                .ok_or_else(|| invalid("an illegal keyspace name"))?,
+            Some(_) => return Err(invalid("too many keyspaces")),
+        };
+        Ok((id, UserRecord::imported(key, permissions, acl, created)))
+    }
+}
+
+fn invalid_data(msg: String) -> IoError {
+    IoError::new(ErrorKind::InvalidData, msg)
+}
+
+/// Write all the users to the file at `path` (which only its owner can read), returning the
+/// number of users that were written
+pub fn export(authmap: &Authmap, path: impl AsRef<Path>) -> IoResult<usize> {
+    let mut users: Vec<ExportedUser> = authmap
+        .iter()
+        .map(|kv| ExportedUser::new(kv.key(), kv.value()))
+        .collect();
+    // so that exports of the same users are identical
+    users.sort_by(|a, b| a.name.cmp(&b.name));
+    let count = users.len();
+    let file = ExportFile {
+        version: EXPORT_VERSION,
+        users,
+    };
+    origin::write_private(path.as_ref(), &serde_json::to_vec_pretty(&file)?)?;
+    Ok(count)
+}
+
+/// Add the users in the file at `path` (replacing the users with the same names), returning
+/// the number of users that were imported
+pub fn import(authmap: &Authmap, path: impl AsRef<Path>) -> IoResult<usize> {
+    let file: ExportFile = serde_json::from_slice(&fs::read(path)?)?;
+    if file.version != EXPORT_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported version {} (expected {EXPORT_VERSION})",
+            file.version
+        )));
+    }
+    let users = file
+        .users
+        .into_iter()
+        .map(ExportedUser::into_record)
+        .collect::<IoResult<Vec<_>>>()?;
+    let count = users.len();
+    for (id, record) in users {
+        authmap.upsert(id, record);
+    }
+    Ok(count)
+}
@@ -24,35 +24,131 @@
  *
 */
 
+/*
+ * Keys are hashed with a memory-hard scheme (Argon2id by default, or scrypt) and stored as
+ * PHC strings, whose prefix (`$argon2id$` or `$scrypt$`) tells us how to verify them. Keys
+ * issued by older versions were hashed with rcrypt and stored as 40 raw bytes; these don't
+ * have any prefix and are still accepted, until the key is regenerated
+*/
+
 use {
-    super::provider::{Authkey, AUTHKEY_SIZE},
-    crate::corestore::array::Array,
+    crate::config::{HashScheme, KeyHashing},
+    argon2::{
+        password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+        Algorithm, Argon2, Params, Version,
+    },
+    scrypt::Scrypt,
 };
 
-type AuthkeyArray = Array<u8, { AUTHKEY_SIZE }>;
+/// A hashed key, as stored in the authmap
+pub type KeyHash = Box<[u8]>;
+
 const RAN_BYTES_SIZE: usize = 40;
+const SALT_SIZE: usize = 16;
+const PREFIX_ARGON2ID: &[u8] = b"$argon2id$";
+const PREFIX_SCRYPT: &[u8] = b"$scrypt$";
+/// The prefix of keys from older versions in exported users, since they aren't PHC strings
+const PREFIX_EXPORTED_RCRYPT: &str = "$rcrypt$";
+/// The size of keys from older versions
+const RCRYPT_HASH_SIZE: usize = 40;
 
 /// Return a "human readable key" and the "authbytes" that can be stored
 /// safely. To do this:
-/// - Generate 64 random bytes
+/// - Generate 40 random bytes
 /// - Encode that into base64. This is the client key
-/// - Hash the key using rcrypt. This is the server key that
+/// - Hash the key using the configured scheme. This is the server key that
 /// will be stored
-pub fn generate_full() -> (String, Authkey) {
+pub fn generate_full(hashing: &KeyHashing) -> (String, KeyHash) {
     let mut bytes: [u8; RAN_BYTES_SIZE] = [0u8; RAN_BYTES_SIZE];
     openssl::rand::rand_bytes(&mut bytes).unwrap();
     let ret = base64::encode_config(bytes, base64::BCRYPT);
-    let hash = rcrypt::hash(&ret, rcrypt::DEFAULT_COST).unwrap();
-    let store_in_db = unsafe {
-        let mut array = AuthkeyArray::new();
-        // we guarantee that the size is equal to 40
-        array.extend_from_slice_unchecked(&hash);
-        array.into_array_unchecked()
+    let hash = self::hash_key(ret.as_bytes(), hashing).unwrap();
+    (ret, hash.into_bytes().into_boxed_slice())
+}
+
+/// Hash a key using the configured scheme, returning the PHC string
+fn hash_key(key: &[u8], hashing: &KeyHashing) -> password_hash::Result<String> {
+    let mut salt = [0u8; SALT_SIZE];
+    openssl::rand::rand_bytes(&mut salt).unwrap();
+    let salt = SaltString::b64_encode(&salt)?;
+    let hash = match hashing.scheme {
+        HashScheme::Argon2id => {
+            let params = hashing.argon2;
+            let params = Params::new(params.memory, params.iterations, params.parallelism, None)?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password(key, &salt)?
+        }
+        HashScheme::Scrypt => {
+            let params = hashing.scrypt;
+            let params = scrypt::Params::new(params.log_n, params.r, params.p)
+                .map_err(|_| password_hash::Error::Crypto)?;
+            Scrypt.hash_password_customized(key, None, None, params, salt.as_salt())?
+        }
     };
-    (ret, store_in_db)
+    Ok(hash.to_string())
 }
 
 /// Verify a "human readable key" against the provided "authbytes"
 pub fn verify_key(input: &[u8], hash: &[u8]) -> Option<bool> {
-    rcrypt::verify(input, hash).ok()
+    if hash.starts_with(PREFIX_ARGON2ID) {
+        self::verify_phc(&Argon2::default(), input, hash)
+    } else if hash.starts_with(PREFIX_SCRYPT) {
+        self::verify_phc(&Scrypt, input, hash)
+    } else {
+        // a key from an older version
+        rcrypt::verify(input, hash).ok()
+    }
+}
+
+/// Returns true if `input` is any of the keys with the given `hashes`. The memory-hard
+/// schemes take tens of milliseconds (by design), so the hashes are checked on the blocking
+/// pool instead of holding up the other connections on this worker
+pub async fn verify_any(input: &[u8], hashes: Vec<KeyHash>) -> bool {
+    if hashes.is_empty() {
+        return false;
+    }
+    let input = input.to_vec();
+    tokio::task::spawn_blocking(move || {
+        hashes
+            .iter()
+            .any(|hash| self::verify_key(&input, hash).unwrap_or(false))
+    })
+    .await
+    .expect("Something caused the key verifier to panic")
+}
+
+/// Verify a key against a PHC string (the parameters are read from the string)
+fn verify_phc(verifier: &impl PasswordVerifier, input: &[u8], hash: &[u8]) -> Option<bool> {
+    let hash = PasswordHash::new(core::str::from_utf8(hash).ok()?).ok()?;
+    match verifier.verify_password(input, &hash) {
+        Ok(()) => Some(true),
+        Err(password_hash::Error::Password) => Some(false),
+        Err(_) => None,
+    }
+}
+
+/// Returns a stored key as a string, for exporting users (see [`super::export`])
+pub fn export_key(hash: &[u8]) -> String {
+    match core::str::from_utf8(hash) {
+        Ok(phc) if hash.starts_with(PREFIX_ARGON2ID) || hash.starts_with(PREFIX_SCRYPT) => {
+            phc.to_owned()
+        }
+        _ => format!("{PREFIX_EXPORTED_RCRYPT}{}", base64::encode(hash)),
+    }
+}
+
+/// Returns the key to store for an exported key, or `None` if it isn't valid
+pub fn import_key(exported: &str) -> Option<KeyHash> {
+    if let Some(legacy) = exported.strip_prefix(PREFIX_EXPORTED_RCRYPT) {
+        return base64::decode(legacy)
+            .ok()
+            .filter(|hash| hash.len() == RCRYPT_HASH_SIZE)
+            .map(Vec::into_boxed_slice);
+    }
+    let is_phc = exported.as_bytes().starts_with(PREFIX_ARGON2ID)
+        || exported.as_bytes().starts_with(PREFIX_SCRYPT);
+    if is_phc && PasswordHash::new(exported).is_ok() {
+        Some(exported.as_bytes().into())
+    } else {
+        None
+    }
 }
@@ -33,17 +33,32 @@
  * accounts. On claiming the root account, this key is issued
  *
  * When the root account is claimed, it can be used to create "standard users". standard
- * users have access to everything but the ability to create/revoke other users
+ * users can never create/revoke other users, and can only run the actions that their
+ * permissions (see `perms`) allow. Root can change these with `AUTH GRANT`/`AUTH REVOKE`, and
+ * can restrict a user to a few keyspaces (see `acl`) with `AUTH RESTRICT`/`AUTH UNRESTRICT`
 */
 
+pub mod acl;
+pub mod audit;
+pub mod export;
 mod keys;
+pub mod origin;
+pub mod perms;
 pub mod provider;
-pub use provider::{AuthProvider, Authmap};
+mod throttle;
+pub use {
+    origin::OriginKey,
+    perms::Permissions,
+    provider::{AuthProvider, Authmap},
+};
 
 #[cfg(test)]
 mod tests;
 
-use crate::dbnet::prelude::*;
+use {
+    self::acl::KeyspaceAcl,
+    crate::{corestore::memstore::ObjectID, dbnet::prelude::*},
+};
 
 const AUTH_CLAIM: &[u8] = b"claim";
 const AUTH_LOGIN: &[u8] = b"login";
@@ -53,6 +68,16 @@ const AUTH_DELUSER: &[u8] = b"deluser";
 const AUTH_RESTORE: &[u8] = b"restore";
 const AUTH_LISTUSER: &[u8] = b"listuser";
 const AUTH_WHOAMI: &[u8] = b"whoami";
+const AUTH_GRANT: &[u8] = b"grant";
+const AUTH_REVOKE: &[u8] = b"revoke";
+const AUTH_RESTRICT: &[u8] = b"restrict";
+const AUTH_UNRESTRICT: &[u8] = b"unrestrict";
+const AUTH_AUDIT: &[u8] = b"audit";
+const AUTH_ROTATEORIGIN: &[u8] = b"rotateorigin";
+const AUTH_EXPORT: &[u8] = b"export";
+const AUTH_IMPORT: &[u8] = b"import";
+/// The number of audit log entries returned by `AUTH AUDIT` if no count is given
+const AUDIT_DEFAULT_COUNT: usize = 10;
 
 action! {
     /// Handle auth. Should have passed the `auth` token
@@ -66,9 +91,14 @@ action! {
             AUTH_LOGIN => self::_auth_login(con, auth, &mut iter).await,
             AUTH_CLAIM => self::_auth_claim(con, auth, &mut iter).await,
             AUTH_ADDUSER => {
-                ensure_boolean_or_aerr::<P>(iter.len() == 1)?; // just the username
+                ensure_boolean_or_aerr::<P>(!iter.is_empty())?; // the username and perms (if any)
                 let username = unsafe { iter.next_unchecked() };
-                let key = auth.provider_mut().claim_user::<P>(username)?;
+                let permissions = if iter.is_empty() {
+                    Permissions::DEFAULT
+                } else {
+                    self::parse_permissions::<P>(&mut iter)?
+                };
+                let key = auth.provider_mut().claim_user::<P>(username, permissions)?;
                 con.write_string(&key).await?;
                 Ok(())
             }
@@ -88,6 +118,60 @@ action! {
             AUTH_RESTORE => self::auth_restore(con, auth, &mut iter).await,
             AUTH_LISTUSER => self::auth_listuser(con, auth, &mut iter).await,
             AUTH_WHOAMI => self::auth_whoami(con, auth, &mut iter).await,
+            AUTH_GRANT => {
+                ensure_boolean_or_aerr::<P>(iter.len() > 1)?; // the username and perms
+                let username = unsafe { iter.next_unchecked() };
+                let permissions = self::parse_permissions::<P>(&mut iter)?;
+                auth.provider().grant::<P>(username, permissions)?;
+                con._write_raw(P::RCODE_OKAY).await?;
+                Ok(())
+            }
+            AUTH_REVOKE => {
+                ensure_boolean_or_aerr::<P>(iter.len() > 1)?; // the username and perms
+                let username = unsafe { iter.next_unchecked() };
+                let permissions = self::parse_permissions::<P>(&mut iter)?;
+                auth.provider().revoke::<P>(username, permissions)?;
+                con._write_raw(P::RCODE_OKAY).await?;
+                Ok(())
+            }
+            AUTH_RESTRICT => {
+                // the username and keyspaces
+                let len = iter.len();
+                ensure_boolean_or_aerr::<P>(len > 1 && len <= KeyspaceAcl::MAX_KEYSPACES + 1)?;
+                let username = unsafe { iter.next_unchecked() };
+                let keyspaces = iter
+                    .map(ObjectID::try_from_slice)
+                    .collect::<Option<Vec<ObjectID>>>()
+                    .unwrap_or_custom_aerr(P::RSTRING_BAD_CONTAINER_NAME)?;
+                auth.provider().restrict::<P>(username, keyspaces)?;
+                con._write_raw(P::RCODE_OKAY).await?;
+                Ok(())
+            }
+            AUTH_AUDIT => self::auth_audit(con, auth, &mut iter).await,
+            AUTH_ROTATEORIGIN => {
+                ensure_boolean_or_aerr::<P>(iter.is_empty())?; // nothing else
+                let key = auth.provider().rotate_origin::<P>()?;
+                con.write_string(&key).await?;
+                Ok(())
+            }
+            AUTH_EXPORT => {
+                ensure_boolean_or_aerr::<P>(iter.len() == 1)?; // just the path
+                let count = auth.provider().export_users::<P>(unsafe { iter.next_unchecked() })?;
+                con.write_usize(count).await?;
+                Ok(())
+            }
+            AUTH_IMPORT => {
+                ensure_boolean_or_aerr::<P>(iter.len() == 1)?; // just the path
+                let count = auth.provider().import_users::<P>(unsafe { iter.next_unchecked() })?;
+                con.write_usize(count).await?;
+                Ok(())
+            }
+            AUTH_UNRESTRICT => {
+                ensure_boolean_or_aerr::<P>(iter.len() == 1)?; // just the username
+                auth.provider().unrestrict::<P>(unsafe { iter.next_unchecked() })?;
+                con._write_raw(P::RCODE_OKAY).await?;
+                Ok(())
+            }
             _ => util::err(P::RCODE_UNKNOWN_ACTION),
         }
     }
@@ -98,10 +182,31 @@ action! {
     }
     fn auth_listuser(con: &mut Connection<C, P>, auth: &mut AuthProviderHandle, iter: &mut ActionIter<'_>) {
         ensure_boolean_or_aerr::<P>(ActionIter::is_empty(iter))?;
-        let usernames = auth.provider().collect_usernames::<P>()?;
-        con.write_typed_non_null_array_header(usernames.len(), b'+').await?;
-        for username in usernames {
-            con.write_typed_non_null_array_element(username.as_bytes()).await?;
+        let users = auth.provider().collect_users::<P>()?;
+        con.write_array_header(users.len()).await?;
+        for user in users {
+            // [name, created, last login, role]
+            con.write_array_header(4).await?;
+            con.write_string(&user.name).await?;
+            con.write_int64(user.created).await?;
+            con.write_int64(user.last_login).await?;
+            con.write_string(&user.role).await?;
+        }
+        Ok(())
+    }
+    fn auth_audit(con: &mut Connection<C, P>, auth: &mut AuthProviderHandle, iter: &mut ActionIter<'_>) {
+        ensure_boolean_or_aerr::<P>(iter.len() < 2)?; // an optional count
+        let count = match iter.next() {
+            Some(count) => match String::from_utf8_lossy(count).parse::<usize>() {
+                Ok(count) => count,
+                Err(_) => return util::err(P::RCODE_WRONGTYPE_ERR),
+            },
+            None => AUDIT_DEFAULT_COUNT,
+        };
+        let entries = auth.provider().audit_entries::<P>(count)?;
+        con.write_typed_non_null_array_header(entries.len(), b'+').await?;
+        for entry in entries {
+            con.write_typed_non_null_array_element(entry.as_bytes()).await?;
         }
         Ok(())
     }
@@ -151,9 +256,17 @@ action! {
         // sweet, where's our username and password
         ensure_boolean_or_aerr::<P>(iter.len() == 2)?; // just the uname and pass
         let (username, password) = unsafe { (iter.next_unchecked(), iter.next_unchecked()) };
-        auth.provider_mut().login::<P>(username, password)?;
+        auth.provider_mut().login::<P>(username, password).await?;
         auth.set_auth();
         con._write_raw(P::RCODE_OKAY).await?;
         Ok(())
     }
 }
+
+/// Parse the remaining arguments as permission names
+fn parse_permissions<P: ProtocolSpec>(iter: &mut ActionIter<'_>) -> ActionResult<Permissions> {
+    match Permissions::from_names(iter) {
+        Some(permissions) => Ok(permissions),
+        None => util::err(P::AUTH_ERROR_UNKNOWN_PERMISSION),
+    }
+}
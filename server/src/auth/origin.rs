@@ -0,0 +1,162 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Origin key
+//!
+//! The origin key is either set in the configuration (`auth.origin_key`) or kept in the origin
+//! key file (`auth.origin_file`). When the server starts with an origin key file that doesn't
+//! exist yet (like on the first boot of a container), a random key is generated, written to
+//! the file (which only its owner can read) and printed once to stdout. After that, the key is
+//! read from the file.
+//!
+//! Root can rotate the origin key with `AUTH ROTATEORIGIN`. This rewrites the origin key file,
+//! so a key that is set in the configuration can't be rotated: the next start would bring the
+//! old key back
+
+use {
+    super::provider::{Authkey, AUTHKEY_SIZE},
+    crate::{config::AuthkeyWrapper, IoResult},
+    parking_lot::RwLock,
+    std::{
+        fs::{self, OpenOptions},
+        io::{Error as IoError, ErrorKind, Write},
+        path::{Path, PathBuf},
+    },
+};
+
+/// The characters that a generated key is made of
+const ALPHABET: &[u8; 62] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+/// Random bytes at or above this are thrown away, so that every character is equally likely
+const UNBIASED_MAX: u8 = (ALPHABET.len() * 4) as u8;
+
+/// The origin key (shared by all connections)
+pub struct OriginKey {
+    key: RwLock<Authkey>,
+    /// the origin key file, if the key is kept in one
+    file: Option<PathBuf>,
+}
+
+impl OriginKey {
+    /// An origin key that is set in the configuration
+    pub fn new(key: Authkey) -> Self {
+        Self {
+            key: RwLock::new(key),
+            file: None,
+        }
+    }
+    /// Read the origin key from the file at `path`. If the file doesn't exist, a new key is
+    /// generated, written to it and printed to stdout
+    pub fn bootstrap(path: impl Into<PathBuf>) -> IoResult<Self> {
+        let path = path.into();
+        let key = match fs::read_to_string(&path) {
+            Ok(stored) => AuthkeyWrapper::try_new(stored.trim())
+                .ok_or_else(|| {
+                    IoError::new(
+                        ErrorKind::InvalidData,
+                        "expected a key with 40 ASCII alphanumeric characters",
+                    )
+                })?
+                .into_inner(),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let key = self::generate();
+                write_private(&path, &key)?;
+                log::info!("Generated an origin key in {}", path.display());
+                // this is the only time the key is shown; it's only in the file from now on
+                println!("Origin key: {}", String::from_utf8_lossy(&key));
+                key
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            key: RwLock::new(key),
+            file: Some(path),
+        })
+    }
+    /// Returns true if `key` is the origin key
+    pub fn verify(&self, key: &[u8]) -> bool {
+        self.key.read().eq(key)
+    }
+    /// Returns true if the key is kept in an origin key file (and can hence be rotated)
+    pub const fn is_rotatable(&self) -> bool {
+        self.file.is_some()
+    }
+    /// Replace the origin key with a new one, which is written to the origin key file and
+    /// returned. The current key is kept if the file can't be written
+    pub fn rotate(&self) -> IoResult<Authkey> {
+        let path = self.file.as_ref().ok_or_else(|| {
+            IoError::new(
+                ErrorKind::Unsupported,
+                "the origin key isn't kept in a file",
+            )
+        })?;
+        // hold the lock while writing, so that concurrent rotations don't race on the file
+        let mut current = self.key.write();
+        let key = self::generate();
+        write_private(path, &key)?;
+        *current = key;
+        Ok(key)
+    }
+}
+
+/// Generate a random origin key of 40 ASCII alphanumeric characters
+pub fn generate() -> Authkey {
+    let mut key = [0u8; AUTHKEY_SIZE];
+    let mut filled = 0;
+    let mut bytes = [0u8; AUTHKEY_SIZE];
+    while filled < AUTHKEY_SIZE {
+        openssl::rand::rand_bytes(&mut bytes).unwrap();
+        for byte in bytes.iter().filter(|byte| **byte < UNBIASED_MAX) {
+            if filled == AUTHKEY_SIZE {
+                break;
+            }
+            key[filled] = ALPHABET[*byte as usize % ALPHABET.len()];
+            filled += 1;
+        }
+    }
+    key
+}
+
+/// Write `contents` (and a trailing newline) to the file at `path`, which only its owner can
+/// read and write. The contents are first written to a temporary file which then replaces the
+/// file, so that a crash never leaves a half-written file behind
+pub(super) fn write_private(path: &Path, contents: &[u8]) -> IoResult<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    // a leftover from an earlier crash might be readable by others
+    let _ = fs::remove_file(&tmp);
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp)?;
+    file.write_all(contents)?;
+    file.write_all(b"\n")?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
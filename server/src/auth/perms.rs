@@ -0,0 +1,123 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Permissions
+//!
+//! Every user (except root, who can do anything) has a set of permissions that decides which
+//! actions they can run:
+//! - `read`: actions that only read data
+//! - `write`: actions that modify data (including scripts)
+//! - `admin`: DDL, snapshots and bulk loads
+//!
+//! A read-only user only has `read`, a read-write user has `read` and `write` and an admin
+//! user has all of them. New users are read-write unless root says otherwise. The permissions
+//! are stored as a bitmap alongside the user's key in the authmap
+
+use crate::{
+    queryengine::{self, commands::ActionFlags},
+    storage::v1::aof,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A bitmap of permissions
+pub struct Permissions(u8);
+
+impl Permissions {
+    pub const NONE: Self = Self(0);
+    pub const READ: Self = Self(1 << 0);
+    pub const WRITE: Self = Self(1 << 1);
+    pub const ADMIN: Self = Self(1 << 2);
+    pub const ALL: Self = Self(Self::READ.0 | Self::WRITE.0 | Self::ADMIN.0);
+    /// The permissions of new users
+    pub const DEFAULT: Self = Self(Self::READ.0 | Self::WRITE.0);
+    const NAMES: [(&'static str, Self); 3] = [
+        ("read", Self::READ),
+        ("write", Self::WRITE),
+        ("admin", Self::ADMIN),
+    ];
+    /// Create a bitmap from its raw value, ignoring any unknown bits
+    pub const fn from_raw(raw: u8) -> Self {
+        Self(raw & Self::ALL.0)
+    }
+    pub const fn raw(&self) -> u8 {
+        self.0
+    }
+    /// Returns true if all the permissions in `other` are set
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+    /// A short name for the permissions: `admin` (all of them), `readwrite` (the default),
+    /// `readonly`, `none` or the names of the permissions (like `write+admin`)
+    pub fn role(&self) -> String {
+        match *self {
+            Self::ALL => "admin".to_owned(),
+            Self::DEFAULT => "readwrite".to_owned(),
+            Self::READ => "readonly".to_owned(),
+            Self::NONE => "none".to_owned(),
+            _ => self.names().join("+"),
+        }
+    }
+    /// The names of the permissions (like `read` and `write`)
+    pub fn names(&self) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(_, perm)| self.contains(*perm))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+    /// Parse a list of permission names (ignoring case). Returns `None` if any of the names is
+    /// unknown
+    pub fn from_names<'a>(names: impl Iterator<Item = &'a [u8]>) -> Option<Self> {
+        names
+            .map(|name| {
+                Self::NAMES
+                    .iter()
+                    .find(|(pname, _)| pname.as_bytes().eq_ignore_ascii_case(name))
+                    .map(|(_, perm)| *perm)
+            })
+            .try_fold(Self::NONE, |perms, perm| Some(perms.union(perm?)))
+    }
+    /// Returns the permissions that are needed to run a query starting with `action` (see
+    /// [`ActionFlags`]). DDL statements are administrative
+    pub fn required_for(action: &[u8]) -> Self {
+        let flags = queryengine::action_flags(action);
+        if flags.contains(ActionFlags::UNRESTRICTED) {
+            Self::NONE
+        } else if flags.contains(ActionFlags::ADMIN) || aof::is_ddl(action) {
+            Self::ADMIN
+        } else if flags.contains(ActionFlags::WRITE) {
+            Self::WRITE
+        } else {
+            Self::READ
+        }
+    }
+}
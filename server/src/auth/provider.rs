@@ -25,14 +25,29 @@
 */
 
 use {
-    super::keys,
+    super::{
+        acl::KeyspaceAcl,
+        audit::{AuditEvent, AuditLog},
+        export,
+        keys::{self, KeyHash},
+        origin::OriginKey,
+        perms::Permissions,
+        throttle::LoginThrottle,
+    },
     crate::{
         actions::{ActionError, ActionResult},
-        corestore::{array::Array, htable::Coremap},
+        config::{AuthSettings, KeyHashing},
+        corestore::{array::Array, htable::Coremap, memstore::ObjectID},
+        kvengine,
         protocol::interface::ProtocolSpec,
         util::err,
     },
-    std::sync::Arc,
+    core::{fmt, mem},
+    std::{
+        net::IpAddr,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
 };
 
 // constants
@@ -57,65 +72,350 @@ uninit_array! {
 const USER_ROOT: AuthID = unsafe { AuthID::from_const(USER_ROOT_ARRAY, 4) };
 
 /// An authn ID
-type AuthID = Array<u8, AUTHID_SIZE>;
+pub type AuthID = Array<u8, AUTHID_SIZE>;
 /// An authn key
 pub type Authkey = [u8; AUTHKEY_SIZE];
 /// Authmap
-pub type Authmap = Arc<Coremap<AuthID, Authkey>>;
+pub type Authmap = Arc<Coremap<AuthID, UserRecord>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A key that was replaced by a new one, but is still accepted until its grace period ends
+pub struct RetiredKey {
+    key: KeyHash,
+    /// when the grace period ends (in milliseconds since the UNIX epoch)
+    expires: u64,
+}
+
+impl RetiredKey {
+    pub const fn new(key: KeyHash, expires: u64) -> Self {
+        Self { key, expires }
+    }
+    fn is_expired(&self) -> bool {
+        kvengine::now_millis() >= self.expires
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A user's entry in the authmap
+pub struct UserRecord {
+    /// the hash of the user's key
+    key: KeyHash,
+    /// what the user is allowed to do
+    permissions: Permissions,
+    /// the keyspaces that the user can use
+    acl: KeyspaceAcl,
+    /// the previous key, if it was regenerated with a grace period
+    retired: Option<RetiredKey>,
+    /// when the user was created (in seconds since the UNIX epoch; zero if unknown)
+    created: u64,
+    /// when the user last logged in (in seconds since the UNIX epoch; zero if never)
+    last_login: u64,
+}
+
+impl UserRecord {
+    pub const fn new(key: KeyHash, permissions: Permissions) -> Self {
+        Self {
+            key,
+            permissions,
+            acl: KeyspaceAcl::unrestricted(),
+            retired: None,
+            created: 0,
+            last_login: 0,
+        }
+    }
+    /// A record for a user that is created just now
+    pub fn claimed(key: KeyHash, permissions: Permissions) -> Self {
+        Self {
+            created: now_secs(),
+            ..Self::new(key, permissions)
+        }
+    }
+    /// A record for a user that was imported from another instance (see [`super::export`])
+    pub fn imported(
+        key: KeyHash,
+        permissions: Permissions,
+        acl: KeyspaceAcl,
+        created: u64,
+    ) -> Self {
+        Self {
+            acl,
+            created,
+            ..Self::new(key, permissions)
+        }
+    }
+    /// Returns the hash of the user's key
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+    pub const fn created(&self) -> u64 {
+        self.created
+    }
+    pub const fn last_login(&self) -> u64 {
+        self.last_login
+    }
+    /// Returns the keys that the user can log in with: their key, and their previous key
+    /// (while it's still in its grace period)
+    pub fn login_keys(&self) -> Vec<KeyHash> {
+        let retired = self
+            .retired
+            .as_ref()
+            .filter(|retired| !retired.is_expired())
+            .map(|retired| retired.key.clone());
+        [Some(self.key.clone()), retired]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+    /// Replace the user's key. If a grace period is provided, the current key is still
+    /// accepted until it ends
+    pub fn rotate(&mut self, key: KeyHash, grace: Option<Duration>) {
+        let previous = mem::replace(&mut self.key, key);
+        self.retired = grace.map(|grace| {
+            RetiredKey::new(previous, kvengine::now_millis() + grace.as_millis() as u64)
+        });
+    }
+    pub const fn permissions(&self) -> Permissions {
+        self.permissions
+    }
+    pub const fn acl(&self) -> &KeyspaceAcl {
+        &self.acl
+    }
+    /// Encode the record for the storage engine:
+    /// `[key length: 1B][key][permissions: 1B][acl][retired key][created: 8B][last login: 8B]`,
+    /// where the retired key is `[key length: 1B][key][expiry: 8B]` (or just a zero length if
+    /// there is none)
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(self.key.len() + 20);
+        // a PHC string is well under 255 bytes
+        encoded.push(self.key.len() as u8);
+        encoded.extend_from_slice(&self.key);
+        encoded.push(self.permissions.raw());
+        self.acl.encode_into(&mut encoded);
+        match self
+            .retired
+            .as_ref()
+            .filter(|retired| !retired.is_expired())
+        {
+            Some(retired) => {
+                encoded.push(retired.key.len() as u8);
+                encoded.extend_from_slice(&retired.key);
+                encoded.extend_from_slice(&retired.expires.to_le_bytes());
+            }
+            None => encoded.push(0),
+        }
+        encoded.extend_from_slice(&self.created.to_le_bytes());
+        encoded.extend_from_slice(&self.last_login.to_le_bytes());
+        encoded
+    }
+    /// Decode a record. Older versions only stored the (rcrypt) key, so such users are
+    /// given what they could do back then: everything, everywhere. Any other missing fields
+    /// take their defaults
+    pub fn decode(encoded: &[u8]) -> Option<Self> {
+        if encoded.len() == AUTHKEY_SIZE {
+            return Some(Self::new(encoded.into(), Permissions::ALL));
+        }
+        let (keylen, rest) = encoded.split_first()?;
+        let keylen = *keylen as usize;
+        let key = rest.get(..keylen)?.into();
+        let rest = &rest[keylen..];
+        let (permissions, rest) = match rest.split_first() {
+            Some((raw, rest)) => (Permissions::from_raw(*raw), rest),
+            None => (Permissions::ALL, rest),
+        };
+        let (acl, rest) = if rest.is_empty() {
+            (KeyspaceAcl::unrestricted(), rest)
+        } else {
+            let (acl, read) = KeyspaceAcl::decode(rest)?;
+            (acl, &rest[read..])
+        };
+        let (retired, rest) = match rest.split_first() {
+            None | Some((&0, _)) => (None, rest.get(1..).unwrap_or_default()),
+            Some((&keylen, rest)) => {
+                let keylen = keylen as usize;
+                let key = rest.get(..keylen)?.into();
+                let expires = rest.get(keylen..keylen + 8)?.try_into().ok()?;
+                let retired = RetiredKey::new(key, u64::from_le_bytes(expires));
+                (Some(retired), &rest[keylen + 8..])
+            }
+        };
+        let (created, last_login) = match rest.len() {
+            0 => (0, 0),
+            16 => (
+                u64::from_le_bytes(rest[..8].try_into().ok()?),
+                u64::from_le_bytes(rest[8..].try_into().ok()?),
+            ),
+            // trailing garbage
+            _ => return None,
+        };
+        Some(Self {
+            key,
+            permissions,
+            acl,
+            retired,
+            created,
+            last_login,
+        })
+    }
+}
+
+/// A user, as listed by `AUTH LISTUSER`
+pub struct UserSummary {
+    pub name: String,
+    /// when the user was created (in seconds since the UNIX epoch; zero if unknown)
+    pub created: u64,
+    /// when the user last logged in (in seconds since the UNIX epoch; zero if never)
+    pub last_login: u64,
+    pub role: String,
+}
+
+/// Returns the current time as seconds since the UNIX epoch
+fn now_secs() -> u64 {
+    kvengine::now_millis() / 1000
+}
 
 /// The authn/authz provider
 ///
 pub struct AuthProvider {
-    origin: Option<Authkey>,
+    /// the origin key (shared by all connections)
+    origin: Option<Arc<OriginKey>>,
     /// the current user
     whoami: Option<AuthID>,
     /// a map of users
     authmap: Authmap,
+    /// how new keys are hashed
+    hashing: KeyHashing,
+    /// the duration of inactivity after which the current user is logged out
+    session_ttl: Option<Duration>,
+    /// how long a regenerated key is still accepted for
+    rotation_grace: Option<Duration>,
+    /// when the current user last did something
+    last_active: Instant,
+    /// the current user was logged in by their client certificate (such sessions don't lapse,
+    /// since the certificate holds for as long as the connection does)
+    by_certificate: bool,
+    /// failed logins (shared by all connections)
+    throttle: Arc<LoginThrottle>,
+    /// the IP of the client, if connected over TCP
+    peer: Option<IpAddr>,
+    /// the audit log (shared by all connections)
+    audit: Arc<AuditLog>,
 }
 
 impl AuthProvider {
-    fn _new(authmap: Authmap, whoami: Option<AuthID>, origin: Option<Authkey>) -> Self {
+    fn _new(
+        authmap: Authmap,
+        whoami: Option<AuthID>,
+        origin: Option<OriginKey>,
+        settings: &AuthSettings,
+        audit: AuditLog,
+    ) -> Self {
         Self {
             authmap,
             whoami,
-            origin,
+            origin: origin.map(Arc::new),
+            hashing: settings.hashing,
+            session_ttl: settings.session_ttl(),
+            rotation_grace: settings.rotation_grace(),
+            last_active: Instant::now(),
+            by_certificate: false,
+            throttle: Arc::new(LoginThrottle::new(settings.throttling)),
+            peer: None,
+            audit: Arc::new(audit),
         }
     }
     /// New provider with no origin-key
     pub fn new_disabled() -> Self {
-        Self::_new(Default::default(), None, None)
+        Self::_new(
+            Default::default(),
+            None,
+            None,
+            &AuthSettings::default(),
+            AuditLog::new_in_memory(),
+        )
     }
     /// New provider with zero users
     #[cfg(test)]
     pub fn new_blank(origin: Option<Authkey>) -> Self {
-        Self::_new(Default::default(), None, origin)
+        Self::_new(
+            Default::default(),
+            None,
+            origin.map(OriginKey::new),
+            &AuthSettings::default(),
+            AuditLog::new_in_memory(),
+        )
+    }
+    /// New provider with zero users, whose origin key can be rotated
+    #[cfg(test)]
+    pub fn new_blank_with_origin(origin: OriginKey) -> Self {
+        Self::_new(
+            Default::default(),
+            None,
+            Some(origin),
+            &AuthSettings::default(),
+            AuditLog::new_in_memory(),
+        )
+    }
+    /// New provider with zero users, that throttles failed logins
+    #[cfg(test)]
+    pub fn new_blank_throttled(
+        origin: Option<Authkey>,
+        throttling: crate::config::LoginThrottling,
+    ) -> Self {
+        let mut settings = AuthSettings::default();
+        settings.throttling = throttling;
+        Self::_new(
+            Default::default(),
+            None,
+            origin.map(OriginKey::new),
+            &settings,
+            AuditLog::new_in_memory(),
+        )
+    }
+    #[cfg(test)]
+    pub fn set_session_ttl(&mut self, session_ttl: Option<Duration>) {
+        self.session_ttl = session_ttl;
+    }
+    #[cfg(test)]
+    pub fn set_rotation_grace(&mut self, rotation_grace: Option<Duration>) {
+        self.rotation_grace = rotation_grace;
     }
     /// New provider with users from the provided map
     ///
     /// ## Test suite
     /// The testsuite creates users `root` and `testuser`; this **does not** apply to
     /// release mode
-    pub fn new(authmap: Arc<Coremap<AuthID, Authkey>>, origin: Option<Authkey>) -> Self {
-        let slf = Self::_new(authmap, None, origin);
+    pub fn new(
+        authmap: Authmap,
+        origin: OriginKey,
+        settings: &AuthSettings,
+        audit: AuditLog,
+    ) -> Self {
+        let slf = Self::_new(authmap, None, Some(origin), settings, audit);
         #[cfg(debug_assertions)]
         {
             // 'root' user in test mode
             slf.authmap.true_if_insert(
                 AuthID::try_from_slice(testsuite_data::TESTSUITE_ROOT_USER).unwrap(),
-                [
-                    172, 143, 117, 169, 158, 156, 33, 106, 139, 107, 20, 106, 91, 219, 34, 157, 98,
-                    147, 142, 91, 222, 238, 205, 120, 72, 171, 90, 218, 147, 2, 75, 67, 44, 108,
-                    185, 124, 55, 40, 156, 252,
-                ],
+                UserRecord::new(
+                    Box::new([
+                        172, 143, 117, 169, 158, 156, 33, 106, 139, 107, 20, 106, 91, 219, 34, 157,
+                        98, 147, 142, 91, 222, 238, 205, 120, 72, 171, 90, 218, 147, 2, 75, 67, 44,
+                        108, 185, 124, 55, 40, 156, 252,
+                    ]),
+                    Permissions::ALL,
+                ),
             );
             // 'testuser' user in test mode
             slf.authmap.true_if_insert(
                 AuthID::try_from_slice(testsuite_data::TESTSUITE_TEST_USER).unwrap(),
-                [
-                    172, 183, 60, 221, 53, 240, 231, 217, 113, 112, 98, 16, 109, 62, 235, 95, 184,
-                    107, 130, 139, 43, 197, 40, 31, 176, 127, 185, 22, 172, 124, 39, 225, 124, 71,
-                    193, 115, 176, 162, 239, 93,
-                ],
+                UserRecord::new(
+                    Box::new([
+                        172, 183, 60, 221, 53, 240, 231, 217, 113, 112, 98, 16, 109, 62, 235, 95,
+                        184, 107, 130, 139, 43, 197, 40, 31, 176, 127, 185, 22, 172, 124, 39, 225,
+                        124, 71, 193, 115, 176, 162, 239, 93,
+                    ]),
+                    Permissions::ALL,
+                ),
             );
         }
         slf
@@ -123,13 +423,37 @@ impl AuthProvider {
     pub const fn is_enabled(&self) -> bool {
         matches!(self.origin, Some(_))
     }
+    /// Returns a provider for a new connection from the provided IP
+    pub fn for_client(&self, peer: Option<IpAddr>) -> Self {
+        let mut slf = self.clone();
+        slf.peer = peer;
+        slf
+    }
+    /// Returns the user that is logged in (if any)
+    pub fn current_user(&self) -> Option<&AuthID> {
+        self.whoami.as_ref()
+    }
+    /// Returns the IP of the client (this is `None` for clients on a Unix domain socket)
+    pub const fn peer(&self) -> Option<IpAddr> {
+        self.peer
+    }
+    /// Returns the users (shared by all connections)
+    pub const fn authmap(&self) -> &Authmap {
+        &self.authmap
+    }
     pub fn claim_root<P: ProtocolSpec>(&mut self, origin_key: &[u8]) -> ActionResult<String> {
         self.verify_origin::<P>(origin_key)?;
         // the origin key was good, let's try claiming root
-        let (key, store) = keys::generate_full();
-        if self.authmap.true_if_insert(USER_ROOT, store) {
+        let (key, store) = keys::generate_full(&self.hashing);
+        if self
+            .authmap
+            .true_if_insert(USER_ROOT, UserRecord::claimed(store, Permissions::ALL))
+        {
             // claimed, sweet, log them in
+            self.touch_last_login(USER_ROOT);
             self.whoami = Some(USER_ROOT);
+            self.last_active = Instant::now();
+            self.audit(AuditEvent::ClaimRoot, "");
             Ok(key)
         } else {
             err(P::AUTH_ERROR_ALREADYCLAIMED)
@@ -142,36 +466,115 @@ impl AuthProvider {
             None => err(P::AUTH_CODE_PERMS),
         }
     }
-    pub fn claim_user<P: ProtocolSpec>(&self, claimant: &[u8]) -> ActionResult<String> {
+    pub fn claim_user<P: ProtocolSpec>(
+        &self,
+        claimant: &[u8],
+        permissions: Permissions,
+    ) -> ActionResult<String> {
         self.ensure_root::<P>()?;
-        self._claim_user::<P>(claimant)
+        self._claim_user::<P>(claimant, permissions)
     }
-    pub fn _claim_user<P: ProtocolSpec>(&self, claimant: &[u8]) -> ActionResult<String> {
-        let (key, store) = keys::generate_full();
-        if self
-            .authmap
-            .true_if_insert(Self::try_auth_id::<P>(claimant)?, store)
-        {
+    pub fn _claim_user<P: ProtocolSpec>(
+        &self,
+        claimant: &[u8],
+        permissions: Permissions,
+    ) -> ActionResult<String> {
+        let (key, store) = keys::generate_full(&self.hashing);
+        if self.authmap.true_if_insert(
+            Self::try_auth_id::<P>(claimant)?,
+            UserRecord::claimed(store, permissions),
+        ) {
+            self.audit_target(AuditEvent::ClaimUser, "", claimant);
             Ok(key)
         } else {
             err(P::AUTH_ERROR_ALREADYCLAIMED)
         }
     }
-    pub fn login<P: ProtocolSpec>(&mut self, account: &[u8], token: &[u8]) -> ActionResult<()> {
-        self.ensure_enabled::<P>()?;
-        match self
+    /// Log in with the user's key (or their previous key, while it's still in its grace
+    /// period). The key is verified on the blocking pool (see [`keys::verify_any`])
+    pub async fn login<P: ProtocolSpec>(
+        &mut self,
+        account: &[u8],
+        token: &[u8],
+    ) -> ActionResult<()> {
+        let id = self.begin_login::<P>(account)?;
+        // don't hold on to the record, since we'll update its last login
+        let login_keys = self
             .authmap
             .get(account)
-            .map(|token_hash| keys::verify_key(token, token_hash.as_slice()))
-        {
-            Some(Some(true)) => {
-                // great, authenticated
-                self.whoami = Some(Self::try_auth_id::<P>(account)?);
-                Ok(())
+            .map(|record| record.login_keys())
+            .unwrap_or_default();
+        let verified = keys::verify_any(token, login_keys).await;
+        self.finish_login::<P>(id, account, verified)
+    }
+    /// Log in as `account` without checking a key, because the caller has verified it
+    /// recently (see [`crate::dbnet::stateless`]). The user must still exist and the client
+    /// must not be throttled
+    pub fn login_verified<P: ProtocolSpec>(&mut self, account: &[u8]) -> ActionResult<()> {
+        let id = self.begin_login::<P>(account)?;
+        let exists = self.authmap.contains_key(account);
+        self.finish_login::<P>(id, account, exists)
+    }
+    fn begin_login<P: ProtocolSpec>(&self, account: &[u8]) -> ActionResult<AuthID> {
+        self.ensure_enabled::<P>()?;
+        let id = match Self::try_auth_id::<P>(account) {
+            Ok(id) => id,
+            // no such user can exist
+            Err(_) => return err(P::AUTH_CODE_BAD_CREDENTIALS),
+        };
+        if self.throttle.is_blocked(&id, self.peer) {
+            return err(P::AUTH_CODE_RATE_LIMITED);
+        }
+        Ok(id)
+    }
+    fn finish_login<P: ProtocolSpec>(
+        &mut self,
+        id: AuthID,
+        account: &[u8],
+        verified: bool,
+    ) -> ActionResult<()> {
+        if verified {
+            // great, authenticated
+            self.throttle.record_success(&id);
+            self.touch_last_login(id);
+            self.whoami = Some(id);
+            self.last_active = Instant::now();
+            self.by_certificate = false;
+            Ok(())
+        } else {
+            // either the password was wrong, or the username was wrong
+            self.audit_target(AuditEvent::LoginFailed, "", account);
+            self.throttle.record_failure(id, self.peer);
+            err(P::AUTH_CODE_BAD_CREDENTIALS)
+        }
+    }
+    /// Log in as the user that a client certificate was issued to, which is the first of
+    /// `names` (see [`crate::dbnet::tls`]) that is the name of a user. If none of them is, this
+    /// returns false and the failure is audited
+    pub fn login_with_certificate(&mut self, names: &[String]) -> bool {
+        if !self.is_enabled() || names.is_empty() {
+            return false;
+        }
+        let id = names
+            .iter()
+            .map(|name| name.as_bytes())
+            .filter(|name| name.is_ascii() && name.len() <= AUTHID_SIZE)
+            .find(|name| self.authmap.contains_key(*name))
+            .map(|name| unsafe {
+                // We just verified the length
+                AuthID::from_slice(name)
+            });
+        match id {
+            Some(id) => {
+                self.touch_last_login(id);
+                self.whoami = Some(id);
+                self.last_active = Instant::now();
+                self.by_certificate = true;
+                true
             }
-            _ => {
-                // either the password was wrong, or the username was wrong
-                err(P::AUTH_CODE_BAD_CREDENTIALS)
+            None => {
+                self.audit_target(AuditEvent::LoginFailed, "certificate", names[0].as_bytes());
+                false
             }
         }
     }
@@ -187,17 +590,236 @@ impl AuthProvider {
         self.ensure_root::<P>()?;
         self._regenerate::<P>(account)
     }
-    /// Regenerate the token for the given user. This returns a new token
+    /// Regenerate the token for the given user (keeping their permissions). This returns a
+    /// new token
     fn _regenerate<P: ProtocolSpec>(&self, account: &[u8]) -> ActionResult<String> {
         let id = Self::try_auth_id::<P>(account)?;
-        let (key, store) = keys::generate_full();
-        if self.authmap.true_if_update(id, store) {
-            Ok(key)
+        let (key, store) = keys::generate_full(&self.hashing);
+        match self.authmap.mut_entry(id) {
+            Some(mut entry) => {
+                entry.value_mut().rotate(store, self.rotation_grace);
+                self.audit_target(AuditEvent::Regenerate, "", account);
+                Ok(key)
+            }
+            None => err(P::AUTH_CODE_BAD_CREDENTIALS),
+        }
+    }
+    /// Replace the origin key with a new one, which is returned. Only root can do this, and
+    /// only if the origin key is kept in a file
+    pub fn rotate_origin<P: ProtocolSpec>(&self) -> ActionResult<String> {
+        self.ensure_root::<P>()?;
+        let origin = self.get_origin::<P>()?;
+        if !origin.is_rotatable() {
+            return err(P::AUTH_ERROR_ORIGIN_NOT_ROTATABLE);
+        }
+        match origin.rotate() {
+            Ok(key) => {
+                self.audit(AuditEvent::RotateOrigin, "");
+                Ok(String::from_utf8_lossy(&key).into_owned())
+            }
+            Err(e) => {
+                log::error!("Failed to write the new origin key: {e}");
+                err(P::RCODE_SERVER_ERR)
+            }
+        }
+    }
+    /// Give the user the provided permissions (on top of the ones they already have)
+    pub fn grant<P: ProtocolSpec>(
+        &self,
+        account: &[u8],
+        permissions: Permissions,
+    ) -> ActionResult<()> {
+        self.update_record::<P>("grant", account, |record| {
+            record.permissions = record.permissions.union(permissions)
+        })
+    }
+    /// Take away the provided permissions from the user
+    pub fn revoke<P: ProtocolSpec>(
+        &self,
+        account: &[u8],
+        permissions: Permissions,
+    ) -> ActionResult<()> {
+        self.update_record::<P>("revoke", account, |record| {
+            record.permissions = record.permissions.difference(permissions)
+        })
+    }
+    /// Only let the user use the provided keyspaces
+    pub fn restrict<P: ProtocolSpec>(
+        &self,
+        account: &[u8],
+        keyspaces: Vec<ObjectID>,
+    ) -> ActionResult<()> {
+        self.update_record::<P>("restrict", account, |record| {
+            record.acl = KeyspaceAcl::restricted(keyspaces)
+        })
+    }
+    /// Let the user use any keyspace
+    pub fn unrestrict<P: ProtocolSpec>(&self, account: &[u8]) -> ActionResult<()> {
+        self.update_record::<P>("unrestrict", account, |record| {
+            record.acl = KeyspaceAcl::unrestricted()
+        })
+    }
+    /// Update the record of a user (`action` is the name of the update, for the audit log)
+    fn update_record<P: ProtocolSpec>(
+        &self,
+        action: &str,
+        account: &[u8],
+        update: impl FnOnce(&mut UserRecord),
+    ) -> ActionResult<()> {
+        self.ensure_root::<P>()?;
+        if account.eq(&USER_ROOT) {
+            // root can do anything, always
+            return err(P::AUTH_ERROR_ROOT_PERMISSIONS);
+        }
+        match self.authmap.mut_entry(Self::try_auth_id::<P>(account)?) {
+            Some(mut entry) => {
+                update(entry.value_mut());
+                self.audit_target(AuditEvent::Privileged, action, account);
+                Ok(())
+            }
+            None => err(P::AUTH_CODE_BAD_CREDENTIALS),
+        }
+    }
+    /// Write all the users (with their hashed keys) to the file at `path`, returning the number
+    /// of users that were written. Only root can do this
+    pub fn export_users<P: ProtocolSpec>(&self, path: &[u8]) -> ActionResult<usize> {
+        self.ensure_root::<P>()?;
+        let path = Self::try_path::<P>(path)?;
+        match export::export(&self.authmap, path) {
+            Ok(count) => {
+                self.audit(AuditEvent::Privileged, format_args!("export path={path}"));
+                Ok(count)
+            }
+            Err(e) => {
+                log::error!("Failed to export the users to {path}: {e}");
+                err(P::RCODE_SERVER_ERR)
+            }
+        }
+    }
+    /// Add the users in the file at `path` (replacing the users with the same names), returning
+    /// the number of users that were imported. Only root can do this
+    pub fn import_users<P: ProtocolSpec>(&self, path: &[u8]) -> ActionResult<usize> {
+        self.ensure_root::<P>()?;
+        let path = Self::try_path::<P>(path)?;
+        match export::import(&self.authmap, path) {
+            Ok(count) => {
+                self.audit(AuditEvent::Privileged, format_args!("import path={path}"));
+                Ok(count)
+            }
+            Err(e) => {
+                log::error!("Failed to import the users from {path}: {e}");
+                err(P::AUTH_ERROR_BAD_IMPORT)
+            }
+        }
+    }
+    fn try_path<P: ProtocolSpec>(path: &[u8]) -> ActionResult<&str> {
+        match core::str::from_utf8(path) {
+            Ok(path) => Ok(path),
+            Err(_) => err(P::RCODE_ENCODING_ERROR),
+        }
+    }
+    /// Record an event caused by the current user in the audit log
+    pub fn audit(&self, event: AuditEvent, detail: impl fmt::Display) {
+        if self.is_enabled() {
+            let user = self.whoami.as_ref().map(|id| id.as_slice());
+            self.audit.record(event, user, self.peer, detail);
+        }
+    }
+    /// Record an event that concerns the `target` user in the audit log
+    fn audit_target(&self, event: AuditEvent, action: &str, target: &[u8]) {
+        let target = String::from_utf8_lossy(target);
+        if action.is_empty() {
+            self.audit(event, format_args!("target={target}"));
         } else {
-            err(P::AUTH_CODE_BAD_CREDENTIALS)
+            self.audit(event, format_args!("{action} target={target}"));
+        }
+    }
+    /// Returns (at most) `count` of the most recent audit log entries
+    pub fn audit_entries<P: ProtocolSpec>(&self, count: usize) -> ActionResult<Vec<String>> {
+        self.ensure_root::<P>()?;
+        Ok(self.audit.recent(count))
+    }
+    /// Mark the current session as active. If the user was inactive for longer than the
+    /// session TTL, they're logged out (until they log in again) and this returns false
+    pub fn refresh_session(&mut self) -> bool {
+        let now = Instant::now();
+        let expired = match (self.whoami.as_ref(), self.session_ttl) {
+            (Some(_), Some(ttl)) if !self.by_certificate => {
+                now.duration_since(self.last_active) > ttl
+            }
+            _ => false,
+        };
+        if expired {
+            self.whoami = None;
+        }
+        self.last_active = now;
+        !expired
+    }
+    /// Returns the permissions of the current user. Everyone can do anything if authn is
+    /// disabled
+    pub fn permissions(&self) -> Permissions {
+        if !self.is_enabled() {
+            return Permissions::ALL;
+        }
+        match self.whoami.as_ref() {
+            Some(id) if id.eq(&USER_ROOT) => Permissions::ALL,
+            Some(id) => self
+                .authmap
+                .get(id)
+                .map(|record| record.permissions())
+                // the user was deleted after they logged in
+                .unwrap_or(Permissions::NONE),
+            None => Permissions::NONE,
+        }
+    }
+    /// Returns the role of the current user, like in [`UserSummary`]. Everyone is an admin if
+    /// authn is disabled
+    pub fn role(&self) -> String {
+        match self.whoami.as_ref() {
+            Some(id) if id.eq(&USER_ROOT) => "root".to_owned(),
+            _ => self.permissions().role(),
+        }
+    }
+    /// Ensure that the current user has the provided permissions
+    pub fn ensure_permissions<P: ProtocolSpec>(
+        &self,
+        permissions: Permissions,
+    ) -> ActionResult<()> {
+        if self.permissions().contains(permissions) {
+            Ok(())
+        } else {
+            err(P::AUTH_CODE_PERMS)
+        }
+    }
+    /// Returns true if the current user can use the provided keyspace. `None` stands for no
+    /// keyspace (or the whole server), which only unrestricted users can use
+    pub fn can_access_keyspace(&self, keyspace: Option<&[u8]>) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        match self.whoami.as_ref() {
+            Some(id) if id.eq(&USER_ROOT) => true,
+            Some(id) => self
+                .authmap
+                .get(id)
+                .map(|record| record.acl().allows(keyspace))
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+    /// Ensure that the current user can use the provided keyspace
+    pub fn ensure_keyspace_access<P: ProtocolSpec>(
+        &self,
+        keyspace: Option<&[u8]>,
+    ) -> ActionResult<()> {
+        if self.can_access_keyspace(keyspace) {
+            Ok(())
+        } else {
+            err(P::AUTH_CODE_PERMS)
         }
     }
-    fn try_auth_id<P: ProtocolSpec>(authid: &[u8]) -> ActionResult<AuthID> {
+    /// Returns the ID of a user, if the name is a valid username
+    pub fn try_auth_id<P: ProtocolSpec>(authid: &[u8]) -> ActionResult<AuthID> {
         if authid.is_ascii() && authid.len() <= AUTHID_SIZE {
             Ok(unsafe {
                 // We just verified the length
@@ -209,6 +831,7 @@ impl AuthProvider {
     }
     pub fn logout<P: ProtocolSpec>(&mut self) -> ActionResult<()> {
         self.ensure_enabled::<P>()?;
+        self.by_certificate = false;
         self.whoami
             .take()
             .map(|_| ())
@@ -221,13 +844,13 @@ impl AuthProvider {
             .ok_or(ActionError::ActionError(P::AUTH_ERROR_DISABLED))
     }
     pub fn verify_origin<P: ProtocolSpec>(&self, origin: &[u8]) -> ActionResult<()> {
-        if self.get_origin::<P>()?.eq(origin) {
+        if self.get_origin::<P>()?.verify(origin) {
             Ok(())
         } else {
             err(P::AUTH_CODE_BAD_CREDENTIALS)
         }
     }
-    fn get_origin<P: ProtocolSpec>(&self) -> ActionResult<&Authkey> {
+    fn get_origin<P: ProtocolSpec>(&self) -> ActionResult<&OriginKey> {
         match self.origin.as_ref() {
             Some(key) => Ok(key),
             None => err(P::AUTH_ERROR_DISABLED),
@@ -240,24 +863,47 @@ impl AuthProvider {
             err(P::AUTH_CODE_PERMS)
         }
     }
+    /// Ensure that the current user is root, if authn is enabled
+    pub fn ensure_root_if_enabled<P: ProtocolSpec>(&self) -> ActionResult<()> {
+        if self.is_enabled() {
+            self.ensure_root::<P>()
+        } else {
+            Ok(())
+        }
+    }
     pub fn delete_user<P: ProtocolSpec>(&self, user: &[u8]) -> ActionResult<()> {
         self.ensure_root::<P>()?;
         if user.eq(&USER_ROOT) {
             // can't delete root!
             err(P::AUTH_ERROR_FAILED_TO_DELETE_USER)
         } else if self.authmap.true_if_removed(user) {
+            self.audit_target(AuditEvent::DeleteUser, "", user);
             Ok(())
         } else {
             err(P::AUTH_CODE_BAD_CREDENTIALS)
         }
     }
-    /// List all the users
-    pub fn collect_usernames<P: ProtocolSpec>(&self) -> ActionResult<Vec<String>> {
+    fn touch_last_login(&self, id: AuthID) {
+        if let Some(mut entry) = self.authmap.mut_entry(id) {
+            entry.value_mut().last_login = now_secs();
+        }
+    }
+    /// List all the users (with their metadata)
+    pub fn collect_users<P: ProtocolSpec>(&self) -> ActionResult<Vec<UserSummary>> {
         self.ensure_root::<P>()?;
         Ok(self
             .authmap
             .iter()
-            .map(|kv| String::from_utf8_lossy(kv.key()).to_string())
+            .map(|kv| UserSummary {
+                name: String::from_utf8_lossy(kv.key()).to_string(),
+                created: kv.value().created(),
+                last_login: kv.value().last_login(),
+                role: if kv.key().eq(&USER_ROOT) {
+                    "root".to_owned()
+                } else {
+                    kv.value().permissions().role()
+                },
+            })
             .collect())
     }
     /// Return the AuthID of the current user
@@ -270,12 +916,23 @@ impl AuthProvider {
     }
 }
 
+/// A clone is a provider for a new session: it shares the users, the origin key, the throttle
+/// and the audit log, but nothing about the session itself (no one is logged in on it). This
+/// is what keeps the logged in user strictly per connection
 impl Clone for AuthProvider {
     fn clone(&self) -> Self {
         Self {
             authmap: self.authmap.clone(),
             whoami: None,
-            origin: self.origin,
+            origin: self.origin.clone(),
+            hashing: self.hashing,
+            session_ttl: self.session_ttl,
+            rotation_grace: self.rotation_grace,
+            last_active: Instant::now(),
+            by_certificate: false,
+            throttle: self.throttle.clone(),
+            peer: None,
+            audit: self.audit.clone(),
         }
     }
 }
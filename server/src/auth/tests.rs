@@ -25,19 +25,62 @@
 */
 
 mod keys {
-    use super::super::keys::{generate_full, verify_key};
+    use {
+        super::super::keys::{generate_full, verify_key},
+        crate::config::{Argon2Params, HashScheme, KeyHashing, ScryptParams},
+    };
+
+    // keep the tests fast
+    const fn cheap(scheme: HashScheme) -> KeyHashing {
+        KeyHashing::new(
+            scheme,
+            Argon2Params::new(64, 1, 1),
+            ScryptParams::new(4, 8, 1),
+        )
+    }
 
     #[test]
     fn test_verify_key() {
-        let (key, store) = generate_full();
+        let (key, store) = generate_full(&KeyHashing::default());
+        assert!(store.starts_with(b"$argon2id$"));
+        assert!(verify_key(key.as_bytes(), &store).unwrap());
+    }
+    #[test]
+    fn test_verify_key_argon2id() {
+        let (key, store) = generate_full(&cheap(HashScheme::Argon2id));
+        assert!(store.starts_with(b"$argon2id$"));
         assert!(verify_key(key.as_bytes(), &store).unwrap());
+        assert!(!verify_key(b"notthekey", &store).unwrap());
+    }
+    #[test]
+    fn test_verify_key_scrypt() {
+        let (key, store) = generate_full(&cheap(HashScheme::Scrypt));
+        assert!(store.starts_with(b"$scrypt$"));
+        assert!(verify_key(key.as_bytes(), &store).unwrap());
+        assert!(!verify_key(b"notthekey", &store).unwrap());
+    }
+    #[test]
+    fn test_verify_key_legacy() {
+        // the testsuite's root user still has a key from the rcrypt days
+        use crate::auth::provider::testsuite_data::TESTSUITE_ROOT_TOKEN;
+        let store = [
+            172, 143, 117, 169, 158, 156, 33, 106, 139, 107, 20, 106, 91, 219, 34, 157, 98, 147,
+            142, 91, 222, 238, 205, 120, 72, 171, 90, 218, 147, 2, 75, 67, 44, 108, 185, 124, 55,
+            40, 156, 252,
+        ];
+        assert!(verify_key(TESTSUITE_ROOT_TOKEN.as_bytes(), &store).unwrap());
+        assert!(!verify_key(b"notthekey", &store).unwrap_or(false));
+    }
+    #[test]
+    fn test_verify_key_bad_hash() {
+        assert!(verify_key(b"key", b"$argon2id$garbage").is_none());
     }
 }
 
 mod authn {
     use crate::{
         actions::ActionError,
-        auth::AuthProvider,
+        auth::{AuthProvider, Permissions},
         protocol::{interface::ProtocolSpec, Skyhash2},
     };
 
@@ -74,37 +117,46 @@ mod authn {
             ActionError::ActionError(Skyhash2::AUTH_ERROR_ALREADYCLAIMED)
         );
     }
-    #[test]
-    fn claim_user_okay_with_login() {
+    #[tokio::test]
+    async fn claim_user_okay_with_login() {
         let mut provider = AuthProvider::new_blank(Some(*ORIG));
         // claim root
         let rootkey = provider.claim_root::<Skyhash2>(ORIG).unwrap();
         // login as root
         provider
             .login::<Skyhash2>(b"root", rootkey.as_bytes())
+            .await
             .unwrap();
         // claim user
-        let _ = provider.claim_user::<Skyhash2>(b"sayan").unwrap();
+        let _ = provider
+            .claim_user::<Skyhash2>(b"sayan", Permissions::DEFAULT)
+            .unwrap();
     }
 
-    #[test]
-    fn claim_user_fail_not_root_with_login() {
+    #[tokio::test]
+    async fn claim_user_fail_not_root_with_login() {
         let mut provider = AuthProvider::new_blank(Some(*ORIG));
         // claim root
         let rootkey = provider.claim_root::<Skyhash2>(ORIG).unwrap();
         // login as root
         provider
             .login::<Skyhash2>(b"root", rootkey.as_bytes())
+            .await
             .unwrap();
         // claim user
-        let userkey = provider.claim_user::<Skyhash2>(b"user").unwrap();
+        let userkey = provider
+            .claim_user::<Skyhash2>(b"user", Permissions::DEFAULT)
+            .unwrap();
         // login as user
         provider
             .login::<Skyhash2>(b"user", userkey.as_bytes())
+            .await
             .unwrap();
         // now try to claim an user being a non-root account
         assert_eq!(
-            provider.claim_user::<Skyhash2>(b"otheruser").unwrap_err(),
+            provider
+                .claim_user::<Skyhash2>(b"otheruser", Permissions::DEFAULT)
+                .unwrap_err(),
             ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
         );
     }
@@ -117,8 +169,915 @@ mod authn {
         provider.logout::<Skyhash2>().unwrap();
         // try to claim as an anonymous user
         assert_eq!(
-            provider.claim_user::<Skyhash2>(b"newuser").unwrap_err(),
+            provider
+                .claim_user::<Skyhash2>(b"newuser", Permissions::DEFAULT)
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+    }
+    #[tokio::test]
+    async fn grant_and_revoke() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let userkey = provider
+            .claim_user::<Skyhash2>(b"reader", Permissions::READ)
+            .unwrap();
+        provider
+            .grant::<Skyhash2>(b"reader", Permissions::WRITE)
+            .unwrap();
+        provider
+            .revoke::<Skyhash2>(b"reader", Permissions::READ)
+            .unwrap();
+        provider
+            .login::<Skyhash2>(b"reader", userkey.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(provider.permissions(), Permissions::WRITE);
+        assert_eq!(
+            provider
+                .ensure_permissions::<Skyhash2>(Permissions::READ)
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+    }
+    #[test]
+    fn grant_fail_root() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        assert_eq!(
+            provider
+                .revoke::<Skyhash2>(b"root", Permissions::WRITE)
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_ERROR_ROOT_PERMISSIONS)
+        );
+    }
+    #[test]
+    fn grant_fail_unknown_user() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        assert_eq!(
+            provider
+                .grant::<Skyhash2>(b"nobody", Permissions::WRITE)
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_BAD_CREDENTIALS)
+        );
+    }
+    #[tokio::test]
+    async fn regenerate_keeps_permissions() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let _ = provider
+            .claim_user::<Skyhash2>(b"reader", Permissions::READ)
+            .unwrap();
+        let newkey = provider.regenerate::<Skyhash2>(b"reader").unwrap();
+        provider
+            .login::<Skyhash2>(b"reader", newkey.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(provider.permissions(), Permissions::READ);
+    }
+}
+
+mod session {
+    use {
+        crate::{
+            actions::ActionError,
+            auth::{AuthProvider, Permissions},
+            protocol::{interface::ProtocolSpec, Skyhash2},
+        },
+        std::{thread, time::Duration},
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+
+    #[tokio::test]
+    async fn session_lapses_after_ttl() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        provider.set_session_ttl(Some(Duration::from_millis(50)));
+        let rootkey = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        assert!(provider.refresh_session());
+        thread::sleep(Duration::from_millis(100));
+        assert!(!provider.refresh_session());
+        assert_eq!(provider.permissions(), Permissions::NONE);
+        assert_eq!(
+            provider.whoami::<Skyhash2>().unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+        // logging in again restores the privileges
+        provider
+            .login::<Skyhash2>(b"root", rootkey.as_bytes())
+            .await
+            .unwrap();
+        assert!(provider.refresh_session());
+        assert_eq!(provider.permissions(), Permissions::ALL);
+    }
+    #[test]
+    fn session_without_ttl_never_lapses() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        assert!(provider.refresh_session());
+        assert_eq!(provider.whoami::<Skyhash2>().unwrap(), "root");
+    }
+    #[tokio::test]
+    async fn sessions_are_per_connection() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let userkey = provider
+            .claim_user::<Skyhash2>(b"sayan", Permissions::READ)
+            .unwrap();
+        // neither a clone nor a provider for another client is logged in
+        let mut other = provider.for_client(Some([127, 0, 0, 1].into()));
+        assert!(provider.clone().current_user().is_none());
+        assert!(other.current_user().is_none());
+        assert_eq!(other.permissions(), Permissions::NONE);
+        assert_eq!(
+            other.whoami::<Skyhash2>().unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+        // logging in or out on one connection doesn't change the other
+        other
+            .login::<Skyhash2>(b"sayan", userkey.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(provider.whoami::<Skyhash2>().unwrap(), "root");
+        assert_eq!(other.role(), "readonly");
+        provider.logout::<Skyhash2>().unwrap();
+        assert_eq!(other.whoami::<Skyhash2>().unwrap(), "sayan");
+        // but they share the users
+        assert!(provider.authmap().contains_key(b"sayan".as_ref()));
+    }
+    #[test]
+    fn role_of_the_current_user() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        assert_eq!(provider.role(), "root");
+        assert_eq!(AuthProvider::new_disabled().role(), "admin");
+    }
+}
+
+mod certificate {
+    use {
+        crate::{
+            auth::{AuthProvider, Permissions},
+            protocol::Skyhash2,
+        },
+        std::{thread, time::Duration},
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn login_as_the_first_user_named() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let _ = provider
+            .claim_user::<Skyhash2>(b"billing", Permissions::DEFAULT)
+            .unwrap();
+        let mut client = provider.for_client(None);
+        assert!(client.login_with_certificate(&names(&["billing.internal", "billing"])));
+        assert_eq!(client.whoami::<Skyhash2>().unwrap(), "billing");
+        assert_eq!(client.permissions(), Permissions::DEFAULT);
+    }
+    #[test]
+    fn unknown_names_are_not_logged_in() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let mut client = provider.for_client(None);
+        assert!(!client.login_with_certificate(&names(&["billing"])));
+        assert!(!client.login_with_certificate(&[]));
+        assert!(client.current_user().is_none());
+        let entries = provider.audit_entries::<Skyhash2>(1).unwrap();
+        assert!(entries[0].contains("login-failed"));
+        assert!(entries[0].ends_with("certificate target=billing"));
+    }
+    #[test]
+    fn certificate_sessions_dont_lapse() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let mut client = provider.for_client(None);
+        client.set_session_ttl(Some(Duration::from_millis(10)));
+        assert!(client.login_with_certificate(&names(&["root"])));
+        thread::sleep(Duration::from_millis(50));
+        assert!(client.refresh_session());
+        assert_eq!(client.whoami::<Skyhash2>().unwrap(), "root");
+    }
+    #[test]
+    fn certificates_are_ignored_without_authn() {
+        let mut provider = AuthProvider::new_blank(None);
+        assert!(!provider.login_with_certificate(&names(&["root"])));
+        assert_eq!(provider.permissions(), Permissions::ALL);
+    }
+}
+
+mod rotation {
+    use {
+        crate::{
+            actions::ActionError,
+            auth::{AuthProvider, Permissions},
+            protocol::{interface::ProtocolSpec, Skyhash2},
+        },
+        std::{thread, time::Duration},
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+
+    fn setup(grace: Option<Duration>) -> (AuthProvider, String, String) {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        provider.set_rotation_grace(grace);
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let oldkey = provider
+            .claim_user::<Skyhash2>(b"sayan", Permissions::DEFAULT)
+            .unwrap();
+        let newkey = provider.regenerate::<Skyhash2>(b"sayan").unwrap();
+        (provider, oldkey, newkey)
+    }
+
+    #[tokio::test]
+    async fn old_key_rejected_without_grace() {
+        let (mut provider, oldkey, newkey) = setup(None);
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", oldkey.as_bytes())
+                .await
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_BAD_CREDENTIALS)
+        );
+        provider
+            .login::<Skyhash2>(b"sayan", newkey.as_bytes())
+            .await
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn old_key_accepted_during_grace() {
+        let (mut provider, oldkey, newkey) = setup(Some(Duration::from_secs(60)));
+        provider
+            .login::<Skyhash2>(b"sayan", oldkey.as_bytes())
+            .await
+            .unwrap();
+        provider
+            .login::<Skyhash2>(b"sayan", newkey.as_bytes())
+            .await
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn old_key_rejected_after_grace() {
+        let (mut provider, oldkey, _) = setup(Some(Duration::from_millis(50)));
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", oldkey.as_bytes())
+                .await
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_BAD_CREDENTIALS)
+        );
+    }
+    #[tokio::test]
+    async fn only_the_last_key_gets_a_grace_period() {
+        let (mut provider, oldkey, newkey) = setup(Some(Duration::from_secs(60)));
+        let newestkey = provider.regenerate::<Skyhash2>(b"sayan").unwrap();
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", oldkey.as_bytes())
+                .await
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_BAD_CREDENTIALS)
+        );
+        provider
+            .login::<Skyhash2>(b"sayan", newkey.as_bytes())
+            .await
+            .unwrap();
+        provider
+            .login::<Skyhash2>(b"sayan", newestkey.as_bytes())
+            .await
+            .unwrap();
+    }
+}
+
+mod throttle {
+    use {
+        crate::{
+            actions::ActionError,
+            auth::{AuthProvider, Permissions},
+            config::LoginThrottling,
+            protocol::{interface::ProtocolSpec, Skyhash2},
+        },
+        std::{
+            net::{IpAddr, Ipv4Addr},
+            thread,
+            time::Duration,
+        },
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+    fn rate_limited() -> ActionError {
+        ActionError::ActionError(Skyhash2::AUTH_CODE_RATE_LIMITED)
+    }
+    fn bad_credentials() -> ActionError {
+        ActionError::ActionError(Skyhash2::AUTH_CODE_BAD_CREDENTIALS)
+    }
+    fn setup(max_failures: u32) -> (AuthProvider, String) {
+        let mut provider =
+            AuthProvider::new_blank_throttled(Some(*ORIG), LoginThrottling::new(max_failures, 1));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let key = provider
+            .claim_user::<Skyhash2>(b"sayan", Permissions::DEFAULT)
+            .unwrap();
+        (provider.for_client(Some(CLIENT)), key)
+    }
+
+    #[tokio::test]
+    async fn backoff_after_failure() {
+        let (mut provider, key) = setup(5);
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", b"badkey")
+                .await
+                .unwrap_err(),
+            bad_credentials()
+        );
+        // even the right key has to wait
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", key.as_bytes())
+                .await
+                .unwrap_err(),
+            rate_limited()
+        );
+        thread::sleep(Duration::from_millis(300));
+        provider
+            .login::<Skyhash2>(b"sayan", key.as_bytes())
+            .await
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn lockout_after_max_failures() {
+        let (mut provider, key) = setup(2);
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", b"badkey")
+                .await
+                .unwrap_err(),
+            bad_credentials()
+        );
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", b"badkey")
+                .await
+                .unwrap_err(),
+            bad_credentials()
+        );
+        // locked out, and the backoff is long over
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", key.as_bytes())
+                .await
+                .unwrap_err(),
+            rate_limited()
+        );
+        thread::sleep(Duration::from_millis(800));
+        provider
+            .login::<Skyhash2>(b"sayan", key.as_bytes())
+            .await
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn client_is_throttled_across_accounts() {
+        let (mut provider, _) = setup(5);
+        let rootkey = provider
+            .regenerate_using_origin::<Skyhash2>(ORIG, b"root")
+            .unwrap();
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"sayan", b"badkey")
+                .await
+                .unwrap_err(),
+            bad_credentials()
+        );
+        // the same client can't try another account right away
+        assert_eq!(
+            provider
+                .login::<Skyhash2>(b"root", rootkey.as_bytes())
+                .await
+                .unwrap_err(),
+            rate_limited()
+        );
+        // but another client can
+        let mut other = provider.for_client(None);
+        other
+            .login::<Skyhash2>(b"root", rootkey.as_bytes())
+            .await
+            .unwrap();
+    }
+    #[tokio::test]
+    async fn throttling_disabled() {
+        let (mut provider, key) = setup(0);
+        for _ in 0..10 {
+            assert_eq!(
+                provider
+                    .login::<Skyhash2>(b"sayan", b"badkey")
+                    .await
+                    .unwrap_err(),
+                bad_credentials()
+            );
+        }
+        provider
+            .login::<Skyhash2>(b"sayan", key.as_bytes())
+            .await
+            .unwrap();
+    }
+}
+
+mod audit {
+    use crate::{
+        actions::ActionError,
+        auth::{
+            audit::{AuditEvent, AuditLog},
+            AuthProvider, Permissions,
+        },
+        protocol::{interface::ProtocolSpec, Skyhash2},
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+
+    #[tokio::test]
+    async fn auth_events_are_audited() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let _ = provider
+            .claim_user::<Skyhash2>(b"sayan", Permissions::DEFAULT)
+            .unwrap();
+        let _ = provider.regenerate::<Skyhash2>(b"sayan").unwrap();
+        provider
+            .grant::<Skyhash2>(b"sayan", Permissions::ADMIN)
+            .unwrap();
+        provider.delete_user::<Skyhash2>(b"sayan").unwrap();
+        let _ = provider
+            .login::<Skyhash2>(b"sayan", b"badkey")
+            .await
+            .unwrap_err();
+        let entries = provider.audit_entries::<Skyhash2>(10).unwrap();
+        let events: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.split(' ').nth(1).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "claim-root",
+                "claim-user",
+                "regenerate",
+                "privileged",
+                "delete-user",
+                "login-failed"
+            ]
+        );
+        assert!(entries[3].ends_with("user=root client=- grant target=sayan"));
+        // only the most recent entries
+        let entries = provider.audit_entries::<Skyhash2>(2).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[1].contains("login-failed"));
+    }
+    #[tokio::test]
+    async fn audit_entries_only_for_root() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let key = provider
+            .claim_user::<Skyhash2>(b"sayan", Permissions::ALL)
+            .unwrap();
+        provider.logout::<Skyhash2>().unwrap();
+        provider
+            .login::<Skyhash2>(b"sayan", key.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(
+            provider.audit_entries::<Skyhash2>(10).unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+    }
+    #[test]
+    fn audit_log_keeps_recent_entries() {
+        let log = AuditLog::new_in_memory();
+        for i in 0..AuditLog::CAPACITY + 10 {
+            log.record(AuditEvent::Privileged, None, None, i);
+        }
+        let entries = log.recent(usize::MAX);
+        assert_eq!(entries.len(), AuditLog::CAPACITY);
+        assert!(entries[0].ends_with(" 10"));
+        assert!(entries
+            .last()
+            .unwrap()
+            .ends_with(&format!(" {}", AuditLog::CAPACITY + 9)));
+    }
+}
+
+mod listing {
+    use crate::{
+        actions::ActionError,
+        auth::{provider::UserSummary, AuthProvider, Permissions},
+        protocol::{interface::ProtocolSpec, Skyhash2},
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+
+    fn find<'a>(users: &'a [UserSummary], name: &str) -> &'a UserSummary {
+        users.iter().find(|user| user.name == name).unwrap()
+    }
+
+    #[tokio::test]
+    async fn list_users_with_metadata() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let rootkey = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let userkey = provider
+            .claim_user::<Skyhash2>(b"sayan", Permissions::READ)
+            .unwrap();
+        let users = provider.collect_users::<Skyhash2>().unwrap();
+        assert_eq!(users.len(), 2);
+        let root = find(&users, "root");
+        assert_eq!(root.role, "root");
+        assert_ne!(root.created, 0);
+        assert_ne!(root.last_login, 0);
+        let sayan = find(&users, "sayan");
+        assert_eq!(sayan.role, "readonly");
+        assert_ne!(sayan.created, 0);
+        assert_eq!(sayan.last_login, 0);
+        // only root can list the users
+        provider
+            .login::<Skyhash2>(b"sayan", userkey.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(
+            provider.collect_users::<Skyhash2>().unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+        // the login is tracked
+        provider
+            .login::<Skyhash2>(b"root", rootkey.as_bytes())
+            .await
+            .unwrap();
+        let users = provider.collect_users::<Skyhash2>().unwrap();
+        assert_ne!(find(&users, "sayan").last_login, 0);
+    }
+}
+
+mod perms {
+    use crate::auth::Permissions;
+
+    #[test]
+    fn required_for() {
+        assert_eq!(Permissions::required_for(b"auth"), Permissions::NONE);
+        assert_eq!(Permissions::required_for(b"get"), Permissions::READ);
+        assert_eq!(Permissions::required_for(b"set"), Permissions::WRITE);
+        assert_eq!(Permissions::required_for(b"mksnap"), Permissions::ADMIN);
+        assert_eq!(
+            Permissions::required_for(b"create model twitter.tweets(string, string)"),
+            Permissions::ADMIN
+        );
+    }
+    #[test]
+    fn from_names() {
+        let names: [&[u8]; 2] = [b"READ", b"write"];
+        assert_eq!(
+            Permissions::from_names(names.into_iter()),
+            Some(Permissions::DEFAULT)
+        );
+        let names: [&[u8]; 2] = [b"read", b"everything"];
+        assert_eq!(Permissions::from_names(names.into_iter()), None);
+    }
+    #[test]
+    fn role() {
+        assert_eq!(Permissions::ALL.role(), "admin");
+        assert_eq!(Permissions::DEFAULT.role(), "readwrite");
+        assert_eq!(Permissions::READ.role(), "readonly");
+        assert_eq!(Permissions::NONE.role(), "none");
+        assert_eq!(
+            Permissions::WRITE.union(Permissions::ADMIN).role(),
+            "write+admin"
+        );
+    }
+    #[test]
+    fn from_raw_ignores_unknown_bits() {
+        assert_eq!(Permissions::from_raw(u8::MAX), Permissions::ALL);
+    }
+}
+
+mod acl {
+    use crate::{
+        actions::ActionError,
+        auth::{
+            acl::{KeyspaceAcl, Scope},
+            AuthProvider, Permissions,
+        },
+        corestore::memstore::ObjectID,
+        protocol::{interface::ProtocolSpec, Skyhash2},
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+
+    fn ks(name: &str) -> ObjectID {
+        ObjectID::try_from_slice(name).unwrap()
+    }
+
+    #[test]
+    fn scope_of() {
+        assert!(matches!(Scope::of(b"heya"), Scope::Free));
+        assert!(matches!(Scope::of(b"set"), Scope::CurrentKeyspace));
+        assert!(matches!(Scope::of(b"mksnap"), Scope::Server));
+        assert!(matches!(Scope::of(b"use tenant"), Scope::Statement));
+    }
+    #[test]
+    fn encode_decode() {
+        let acl = KeyspaceAcl::restricted(vec![ks("tenant"), ks("shared")]);
+        let mut encoded = vec![];
+        acl.encode_into(&mut encoded);
+        assert_eq!(KeyspaceAcl::decode(&encoded), Some((acl, encoded.len())));
+        let mut encoded = vec![];
+        KeyspaceAcl::unrestricted().encode_into(&mut encoded);
+        assert_eq!(
+            KeyspaceAcl::decode(&encoded),
+            Some((KeyspaceAcl::unrestricted(), 1))
+        );
+        // the keyspace is shorter than its length says
+        assert_eq!(KeyspaceAcl::decode(&[1, 6, b'a']), None);
+    }
+    #[test]
+    fn allows() {
+        let acl = KeyspaceAcl::restricted(vec![ks("tenant")]);
+        assert!(acl.allows(Some(b"tenant")));
+        assert!(!acl.allows(Some(b"default")));
+        assert!(!acl.allows(None));
+        assert!(KeyspaceAcl::unrestricted().allows(None));
+    }
+    #[tokio::test]
+    async fn restrict_and_unrestrict() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let userkey = provider
+            .claim_user::<Skyhash2>(b"tenant", Permissions::DEFAULT)
+            .unwrap();
+        provider
+            .restrict::<Skyhash2>(b"tenant", vec![ks("tenant")])
+            .unwrap();
+        assert_eq!(
+            provider
+                .restrict::<Skyhash2>(b"root", vec![ks("tenant")])
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_ERROR_ROOT_PERMISSIONS)
+        );
+        provider
+            .login::<Skyhash2>(b"tenant", userkey.as_bytes())
+            .await
+            .unwrap();
+        assert!(provider.can_access_keyspace(Some(b"tenant")));
+        assert_eq!(
+            provider
+                .ensure_keyspace_access::<Skyhash2>(Some(b"default"))
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+        // only root can lift the restriction
+        assert_eq!(
+            provider.unrestrict::<Skyhash2>(b"tenant").unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+    }
+}
+
+mod origin {
+    use {
+        crate::{
+            actions::ActionError,
+            auth::{
+                origin::{self, OriginKey},
+                AuthProvider,
+            },
+            config::AuthkeyWrapper,
+            protocol::{interface::ProtocolSpec, Skyhash2},
+        },
+        std::{fs, path::PathBuf},
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+
+    fn keyfile(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("skyd-origin-{name}-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn generated_keys_are_valid() {
+        let key = origin::generate();
+        let key = String::from_utf8(key.to_vec()).unwrap();
+        assert!(AuthkeyWrapper::try_new(&key).is_some());
+        assert_ne!(origin::generate(), origin::generate());
+    }
+    #[test]
+    fn bootstrap_generates_once() {
+        let path = keyfile("bootstrap");
+        let first = OriginKey::bootstrap(&path).unwrap();
+        let stored = fs::read_to_string(&path).unwrap();
+        assert!(first.verify(stored.trim().as_bytes()));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // the next start reads the same key
+        let second = OriginKey::bootstrap(&path).unwrap();
+        assert!(second.verify(stored.trim().as_bytes()));
+        fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn bootstrap_rejects_bad_keyfile() {
+        let path = keyfile("bad");
+        fs::write(&path, "tooshort\n").unwrap();
+        assert!(OriginKey::bootstrap(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn rotate_origin() {
+        let path = keyfile("rotate");
+        let mut provider =
+            AuthProvider::new_blank_with_origin(OriginKey::bootstrap(&path).unwrap());
+        let oldkey = fs::read_to_string(&path).unwrap();
+        // only root can rotate the key
+        assert_eq!(
+            provider.rotate_origin::<Skyhash2>().unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+        let _ = provider
+            .claim_root::<Skyhash2>(oldkey.trim().as_bytes())
+            .unwrap();
+        let newkey = provider.rotate_origin::<Skyhash2>().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().trim(), newkey);
+        // the old key is no longer accepted
+        assert_eq!(
+            provider
+                .verify_origin::<Skyhash2>(oldkey.trim().as_bytes())
+                .unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_BAD_CREDENTIALS)
+        );
+        let _ = provider
+            .regenerate_using_origin::<Skyhash2>(newkey.as_bytes(), b"root")
+            .unwrap();
+        // connections share the key
+        let other = provider.for_client(None);
+        other.verify_origin::<Skyhash2>(newkey.as_bytes()).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn configured_origin_cant_be_rotated() {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        assert_eq!(
+            provider.rotate_origin::<Skyhash2>().unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_ERROR_ORIGIN_NOT_ROTATABLE)
+        );
+        provider.verify_origin::<Skyhash2>(ORIG).unwrap();
+    }
+}
+
+mod export {
+    use {
+        crate::{
+            actions::ActionError,
+            auth::{
+                provider::{testsuite_data::TESTSUITE_ROOT_TOKEN, UserRecord},
+                AuthProvider, Permissions,
+            },
+            corestore::memstore::ObjectID,
+            protocol::{interface::ProtocolSpec, Skyhash2},
+        },
+        std::{fs, path::PathBuf},
+    };
+
+    const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+
+    fn exportfile(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("skyd-export-{name}-{}.json", std::process::id()))
+    }
+    fn root() -> (AuthProvider, String) {
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let rootkey = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        (provider, rootkey)
+    }
+
+    #[tokio::test]
+    async fn export_and_import() {
+        let path = exportfile("roundtrip");
+        let (source, rootkey) = root();
+        let userkey = source
+            .claim_user::<Skyhash2>(b"tenant", Permissions::READ)
+            .unwrap();
+        source
+            .restrict::<Skyhash2>(b"tenant", vec![ObjectID::try_from_slice("tenant").unwrap()])
+            .unwrap();
+        let pathstr = path.to_str().unwrap().as_bytes();
+        assert_eq!(source.export_users::<Skyhash2>(pathstr).unwrap(), 2);
+        // another instance, with its own root
+        let (target, _) = root();
+        assert_eq!(target.import_users::<Skyhash2>(pathstr).unwrap(), 2);
+        assert_eq!(
+            target.authmap().get_cloned(b"tenant".as_ref()),
+            source.authmap().get_cloned(b"tenant".as_ref())
+        );
+        // the keys are the same on both instances
+        let mut target = target.for_client(None);
+        target
+            .login::<Skyhash2>(b"root", rootkey.as_bytes())
+            .await
+            .unwrap();
+        target
+            .login::<Skyhash2>(b"tenant", userkey.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(target.permissions(), Permissions::READ);
+        assert!(target.can_access_keyspace(Some(b"tenant")));
+        assert!(!target.can_access_keyspace(Some(b"default")));
+        fs::remove_file(&path).unwrap();
+    }
+    #[tokio::test]
+    async fn legacy_keys_roundtrip() {
+        let path = exportfile("legacy");
+        let (source, _) = root();
+        // the testsuite's root user still has a key from the rcrypt days
+        let legacy = [
+            172, 143, 117, 169, 158, 156, 33, 106, 139, 107, 20, 106, 91, 219, 34, 157, 98, 147,
+            142, 91, 222, 238, 205, 120, 72, 171, 90, 218, 147, 2, 75, 67, 44, 108, 185, 124, 55,
+            40, 156, 252,
+        ];
+        source.authmap().upsert(
+            AuthProvider::try_auth_id::<Skyhash2>(b"legacy").unwrap(),
+            UserRecord::new(Box::new(legacy), Permissions::ALL),
+        );
+        let pathstr = path.to_str().unwrap().as_bytes();
+        source.export_users::<Skyhash2>(pathstr).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("$rcrypt$"));
+        let (target, _) = root();
+        target.import_users::<Skyhash2>(pathstr).unwrap();
+        let mut target = target.for_client(None);
+        target
+            .login::<Skyhash2>(b"legacy", TESTSUITE_ROOT_TOKEN.as_bytes())
+            .await
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn invalid_files_arent_imported() {
+        let path = exportfile("invalid");
+        fs::write(
+            &path,
+            r#"{"version":1,"users":[
+                {"name":"good","key":"$argon2id$v=19$m=19456,t=2,p=1$c29tZXNhbHQ$aGFzaGhhc2hoYXNoaGFzaGhhc2hoYXNoaGFzaGhhc2g","permissions":["read"]},
+                {"name":"bad","key":"notahash","permissions":["read"]}
+            ]}"#,
+        )
+        .unwrap();
+        let (provider, _) = root();
+        let pathstr = path.to_str().unwrap().as_bytes();
+        assert_eq!(
+            provider.import_users::<Skyhash2>(pathstr).unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_ERROR_BAD_IMPORT)
+        );
+        assert!(!provider.authmap().contains_key(b"good".as_ref()));
+        // a missing file
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            provider.import_users::<Skyhash2>(pathstr).unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_ERROR_BAD_IMPORT)
+        );
+    }
+    #[tokio::test]
+    async fn only_root_can_export_and_import() {
+        let (provider, _) = root();
+        let userkey = provider
+            .claim_user::<Skyhash2>(b"sayan", Permissions::ALL)
+            .unwrap();
+        let mut provider = provider.for_client(None);
+        provider
+            .login::<Skyhash2>(b"sayan", userkey.as_bytes())
+            .await
+            .unwrap();
+        let path = exportfile("denied");
+        let pathstr = path.to_str().unwrap().as_bytes();
+        assert_eq!(
+            provider.export_users::<Skyhash2>(pathstr).unwrap_err(),
+            ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
+        );
+        assert_eq!(
+            provider.import_users::<Skyhash2>(pathstr).unwrap_err(),
             ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)
         );
+        assert!(!path.exists());
     }
 }
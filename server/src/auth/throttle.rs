@@ -0,0 +1,142 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Failed login throttling
+//!
+//! Failed logins are tracked per account and per client IP. Every failure makes the next
+//! attempt wait twice as long (starting at [`BACKOFF_BASE`]) and once `max_failures` failures
+//! pile up, the account (or IP) is locked out for the configured duration. Failures are
+//! forgotten after a quiet period as long as the lockout, and a successful login clears the
+//! account's failures (but not the IP's, so that logging into another account doesn't reset
+//! the backoff for a client that's guessing keys)
+
+use {
+    super::provider::AuthID,
+    crate::{config::LoginThrottling, corestore::htable::Coremap},
+    std::{
+        hash::Hash,
+        net::IpAddr,
+        time::{Duration, Instant},
+    },
+};
+
+/// The wait after the first failed login
+const BACKOFF_BASE: Duration = Duration::from_millis(250);
+/// Stale records are pruned once a map holds this many records
+const PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct Failures {
+    /// failures in a row
+    count: u32,
+    /// the last failure
+    last: Instant,
+    /// no attempts are allowed until this instant
+    blocked_until: Instant,
+}
+
+impl Failures {
+    fn new(now: Instant) -> Self {
+        Self {
+            count: 0,
+            last: now,
+            blocked_until: now,
+        }
+    }
+}
+
+/// Tracks failed logins, shared by all connections
+pub struct LoginThrottle {
+    settings: LoginThrottling,
+    accounts: Coremap<AuthID, Failures>,
+    clients: Coremap<IpAddr, Failures>,
+}
+
+impl LoginThrottle {
+    pub fn new(settings: LoginThrottling) -> Self {
+        Self {
+            settings,
+            accounts: Coremap::new(),
+            clients: Coremap::new(),
+        }
+    }
+    /// Returns true if login attempts for the account (or from the client) have to wait
+    pub fn is_blocked(&self, account: &AuthID, client: Option<IpAddr>) -> bool {
+        if !self.settings.is_enabled() {
+            return false;
+        }
+        let now = Instant::now();
+        Self::_is_blocked(&self.accounts, account, now)
+            || client
+                .map(|ip| Self::_is_blocked(&self.clients, &ip, now))
+                .unwrap_or(false)
+    }
+    fn _is_blocked<K: Eq + Hash>(map: &Coremap<K, Failures>, key: &K, now: Instant) -> bool {
+        map.get(key)
+            .map(|failures| failures.blocked_until > now)
+            .unwrap_or(false)
+    }
+    /// Record a failed login for the account (and the client)
+    pub fn record_failure(&self, account: AuthID, client: Option<IpAddr>) {
+        if !self.settings.is_enabled() {
+            return;
+        }
+        let now = Instant::now();
+        self.fail(&self.accounts, account, now);
+        if let Some(ip) = client {
+            self.fail(&self.clients, ip, now);
+        }
+    }
+    fn fail<K: Eq + Hash>(&self, map: &Coremap<K, Failures>, key: K, now: Instant) {
+        let lockout = self.settings.lockout();
+        if map.len() >= PRUNE_THRESHOLD {
+            map.retain(|_, failures| !self.is_stale(failures, now));
+        }
+        let mut failures = map.get_or_insert_with(key, || Failures::new(now));
+        if self.is_stale(&failures, now) {
+            *failures = Failures::new(now);
+        }
+        failures.count += 1;
+        failures.last = now;
+        failures.blocked_until = if failures.count >= self.settings.max_failures() {
+            now + lockout
+        } else {
+            // 2^(count - 1) times the base, but never longer than a lockout
+            let backoff = BACKOFF_BASE.saturating_mul(1_u32 << (failures.count - 1).min(16));
+            now + backoff.min(lockout)
+        };
+    }
+    /// Returns true if the failures were long enough ago to be forgotten
+    fn is_stale(&self, failures: &Failures, now: Instant) -> bool {
+        failures.blocked_until <= now && now.duration_since(failures.last) > self.settings.lockout()
+    }
+    /// Record a successful login for the account
+    pub fn record_success(&self, account: &AuthID) {
+        if self.settings.is_enabled() {
+            self.accounts.true_if_removed(account);
+        }
+    }
+}
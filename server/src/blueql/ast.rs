@@ -104,17 +104,17 @@ impl FieldConfig {
             || types.len() != 2
             // the key type cannot be compound
             || types[0].0.len() != 1
-            // the key type cannot be a list
-            || types[0].0[0] == Type::List
+            // the key type cannot be a collection
+            || types[0].0[0].is_collection()
             // the value cannot have a depth more than two
             || types[1].0.len() > 2
             // if the value is a string or binary, it cannot have a depth more than 1
             || ((types[1].0[0] == Type::Binary || types[1].0[0] == Type::String) && types[1].0.len() != 1)
-            // if the value is a list, it must have a depth of two
-            || (types[1].0[0] == Type::List && types[1].0.len() != 2)
-            // if the value is a list, the type argument cannot be a list (it's stupid, I know; that's exactly
-            // why I'll be ditching this API in the next two PRs)
-            || (types[1].0[0] == Type::List && types[1].0[1] == Type::List)
+            // if the value is a collection, it must have a depth of two
+            || (types[1].0[0].is_collection() && types[1].0.len() != 2)
+            // if the value is a collection, the type argument cannot be a collection (it's stupid, I know; that's
+            // exactly why I'll be ditching this API in the next two PRs)
+            || (types[1].0[0].is_collection() && types[1].0[1].is_collection())
         };
         if compiler::unlikely(invalid_expr) {
             // the value type cannot have a depth more than 2
@@ -122,10 +122,16 @@ impl FieldConfig {
         }
         let key_expr = &types[0].0;
         let value_expr = &types[1].0;
-        if value_expr[0] == Type::List {
+        if value_expr[0].is_collection() {
             let k_enc = key_expr[0] == Type::String;
             let v_enc = value_expr[1] == Type::String;
-            Ok(((k_enc as u8) << 1) + (v_enc as u8) + 4)
+            let base = match value_expr[0] {
+                Type::List => 4,
+                Type::Set => 8,
+                Type::Hash => 12,
+                _ => 16,
+            };
+            Ok(((k_enc as u8) << 1) + (v_enc as u8) + base)
         } else {
             let k_enc = key_expr[0] == Type::String;
             let v_enc = value_expr[0] == Type::String;
@@ -298,7 +304,8 @@ impl<'a> Compiler<'a> {
             Token::Keyword(Keyword::Model) => self.parse_inspect_model0(),
             Token::Keyword(Keyword::Space) => self.parse_inspect_space0(),
             Token::Identifier(spaces)
-                if unsafe { spaces.as_slice() }.eq_ignore_ascii_case(b"spaces") =>
+                if unsafe { spaces.as_slice() }.eq_ignore_ascii_case(b"spaces")
+                    || unsafe { spaces.as_slice() }.eq_ignore_ascii_case(b"keyspaces") =>
             {
                 Ok(Statement::InspectSpaces)
             }
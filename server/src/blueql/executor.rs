@@ -28,11 +28,12 @@ use crate::dbnet::BufferedSocketStream;
 
 use {
     super::{
-        ast::{Statement, StatementLT},
+        ast::{Entity, Statement, StatementLT},
         error,
     },
     crate::{
         actions::{self, ActionError, ActionResult},
+        auth::AuthProvider,
         blueql,
         corestore::memstore::ObjectID,
         dbnet::prelude::*,
@@ -42,6 +43,7 @@ use {
 pub async fn execute<'a, P, C>(
     handle: &'a mut Corestore,
     con: &mut Connection<C, P>,
+    auth: &AuthProvider,
     maybe_statement: &[u8],
     extra: usize,
 ) -> ActionResult<()>
@@ -51,6 +53,11 @@ where
 {
     let statement =
         error::map_ql_err_to_resp::<StatementLT, P>(blueql::compile(maybe_statement, extra))?;
+    if !matches!(statement.as_ref(), Statement::InspectSpaces) {
+        // make sure that the user can use the keyspace (spaces are filtered below)
+        let current = handle.get_ids().0.map(|ks| ks.as_slice());
+        auth.ensure_keyspace_access::<P>(self::keyspace_of(statement.as_ref(), current))?;
+    }
     let system_health_okay = registry::state_okay();
     let result = match statement.as_ref() {
         Statement::Use(entity) => handle.swap_entity(entity),
@@ -84,8 +91,9 @@ where
         }
         Statement::InspectSpaces => {
             // ret directly
-            con.write_typed_non_null_array(&handle.get_store().list_keyspaces(), b'+')
-                .await?;
+            let mut spaces = handle.get_store().list_keyspaces();
+            spaces.retain(|space| auth.can_access_keyspace(Some(space.as_slice())));
+            con.write_typed_non_null_array(&spaces, b'+').await?;
             return Ok(());
         }
         Statement::InspectSpace(space) => {
@@ -113,3 +121,28 @@ where
     con._write_raw(P::RCODE_OKAY).await?;
     Ok(())
 }
+
+/// Returns the keyspace that a statement works on (`current` is the keyspace in use)
+fn keyspace_of<'a>(statement: &'a Statement, current: Option<&'a [u8]>) -> Option<&'a [u8]> {
+    let of_entity = |entity: &'a Entity| match entity {
+        Entity::Full(space, _) => Some(unsafe { space.as_slice() }),
+        Entity::Current(_) => current,
+    };
+    match statement {
+        // this is the only place where a lone entity is a space
+        Statement::Use(Entity::Current(space)) => Some(unsafe { space.as_slice() }),
+        Statement::Use(entity) => of_entity(entity),
+        Statement::CreateSpace(space) | Statement::DropSpace { entity: space, .. } => {
+            Some(unsafe { space.as_slice() })
+        }
+        Statement::CreateModel { entity, .. } | Statement::DropModel { entity, .. } => {
+            of_entity(entity)
+        }
+        Statement::InspectSpace(space) => match space {
+            Some(space) => Some(unsafe { space.as_slice() }),
+            None => current,
+        },
+        Statement::InspectModel(model) => model.as_ref().map_or(current, of_entity),
+        Statement::InspectSpaces => None,
+    }
+}
@@ -97,6 +97,16 @@ pub enum Type {
     String,
     Binary,
     List,
+    Set,
+    Hash,
+    SortedSet,
+}
+
+impl Type {
+    /// Returns true if this type holds other values (like `list`)
+    pub const fn is_collection(&self) -> bool {
+        matches!(self, Self::List | Self::Set | Self::Hash | Self::SortedSet)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -112,11 +122,14 @@ impl Keyword {
             b"drop" => Keyword::Drop,
             b"inspect" => Keyword::Inspect,
             b"model" => Keyword::Model,
-            b"space" => Keyword::Space,
+            b"space" | b"keyspace" => Keyword::Space,
             b"volatile" => Keyword::Volatile,
             b"string" => Keyword::Type(Type::String),
             b"binary" => Keyword::Type(Type::Binary),
             b"list" => Keyword::Type(Type::List),
+            b"set" => Keyword::Type(Type::Set),
+            b"hash" => Keyword::Type(Type::Hash),
+            b"zset" => Keyword::Type(Type::SortedSet),
             b"force" => Keyword::Force,
             b"use" => Keyword::Use,
             _ => return None,
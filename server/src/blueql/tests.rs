@@ -253,6 +253,28 @@ mod ast {
         );
     }
     #[test]
+    fn stmt_keyspace_alias() {
+        assert_eq!(
+            Compiler::compile(b"create keyspace twitter").unwrap(),
+            Statement::CreateSpace("twitter".into())
+        );
+        assert_eq!(
+            Compiler::compile(b"drop keyspace twitter").unwrap(),
+            Statement::DropSpace {
+                entity: "twitter".into(),
+                force: false
+            }
+        );
+        assert_eq!(
+            Compiler::compile(b"inspect keyspace twitter").unwrap(),
+            Statement::InspectSpace(Some("twitter".into()))
+        );
+        assert_eq!(
+            Compiler::compile(b"inspect keyspaces").unwrap(),
+            Statement::InspectSpaces
+        );
+    }
+    #[test]
     fn stmt_drop_model() {
         assert_eq!(
             Compiler::compile(b"drop model twitter.tweet force").unwrap(),
@@ -282,6 +304,57 @@ mod ast {
         assert_eq!(Compiler::compile(&src).unwrap(), stmt)
     }
     #[test]
+    fn set_model_code() {
+        let get_model_code = |src| {
+            let l = Lexer::lex(src).unwrap();
+            let stmt = Compiler::new(&l)
+                .parse_create_model1(Entity::Current("jotsy".into()))
+                .unwrap();
+            match stmt {
+                Statement::CreateModel { model, .. } => model.get_model_code().unwrap(),
+                x => panic!("Expected model found {:?}", x),
+            }
+        };
+        assert_eq!(get_model_code(b"(binary, set<binary>)"), 8);
+        assert_eq!(get_model_code(b"(binary, set<string>)"), 9);
+        assert_eq!(get_model_code(b"(string, set<binary>)"), 10);
+        assert_eq!(get_model_code(b"(string, set<string>)"), 11);
+    }
+    #[test]
+    fn hash_model_code() {
+        let get_model_code = |src| {
+            let l = Lexer::lex(src).unwrap();
+            let stmt = Compiler::new(&l)
+                .parse_create_model1(Entity::Current("jotsy".into()))
+                .unwrap();
+            match stmt {
+                Statement::CreateModel { model, .. } => model.get_model_code().unwrap(),
+                x => panic!("Expected model found {:?}", x),
+            }
+        };
+        assert_eq!(get_model_code(b"(binary, hash<binary>)"), 12);
+        assert_eq!(get_model_code(b"(binary, hash<string>)"), 13);
+        assert_eq!(get_model_code(b"(string, hash<binary>)"), 14);
+        assert_eq!(get_model_code(b"(string, hash<string>)"), 15);
+    }
+    #[test]
+    fn zset_model_code() {
+        let get_model_code = |src| {
+            let l = Lexer::lex(src).unwrap();
+            let stmt = Compiler::new(&l)
+                .parse_create_model1(Entity::Current("jotsy".into()))
+                .unwrap();
+            match stmt {
+                Statement::CreateModel { model, .. } => model.get_model_code().unwrap(),
+                x => panic!("Expected model found {:?}", x),
+            }
+        };
+        assert_eq!(get_model_code(b"(binary, zset<binary>)"), 16);
+        assert_eq!(get_model_code(b"(binary, zset<string>)"), 17);
+        assert_eq!(get_model_code(b"(string, zset<binary>)"), 18);
+        assert_eq!(get_model_code(b"(string, zset<string>)"), 19);
+    }
+    #[test]
     fn bad_model_code() {
         let get_model_code = |src| {
             let l = Lexer::lex(src).unwrap();
@@ -309,7 +382,13 @@ mod ast {
             // rule: fields can't be named
             "(id: string, posts: list<string>)",
             // rule: nested lists are disallowed
-            "(string, list<list<string>>)"
+            "(string, list<list<string>>)",
+            // rule: first cannot be a set
+            "(set<string>, string)",
+            // rule: nested collections are disallowed
+            "(string, set<list<string>>)",
+            "(string, list<set<string>>)",
+            "(string, hash<set<string>>)"
         );
         for src in SRC {
             assert_eq!(
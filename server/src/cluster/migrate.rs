@@ -0,0 +1,458 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Moving slots
+//!
+//! A slot is moved in batches of [`BATCH_SIZE`] keys. The keys of a batch are first marked as
+//! in-flight while holding the transaction lock exclusively, so no write to them is running
+//! once they're marked (and none can start until they're unmarked). Only the marks are set
+//! under the lock, so it's held for a moment once per batch. Then for every key, we:
+//! 1. Take a dump of the key
+//! 2. Send the dump to the new owner with `IMPORTKEY <key> <dump> <deadline>`
+//! 3. Delete the key from this node (as an ordinary `DEL`, so that it's logged and replicated)
+//! 4. Clear the in-flight mark
+//!
+//! Reads of an in-flight key are still served here (we still have it), but writes are rejected
+//! until the key is gone. Once every key is moved, the slot is assigned to the new owner on
+//! both nodes.
+//!
+//! ## Dumps
+//!
+//! A dump has the kind of the value (`0` for a blob, `1` for a list, `2` for a set, `3` for a
+//! hash and `4` for a sorted set), the number of items and then every item (prefixed by its
+//! length). The numbers are 64-bit little-endian integers. Hashes are flattened into fields and
+//! values, while sorted sets are flattened into members and scores (the scores are 64-bit
+//! little-endian floats)
+
+use {
+    super::{key_slot, MigrationState, Node},
+    crate::{
+        corestore::{
+            memstore::ObjectID,
+            table::{DataModel, Table},
+            zset::{Score, SortedSet},
+            SharedSlice,
+        },
+        dbnet::prelude::*,
+        kvengine::{self, KVEngine},
+        replication::{encode_query, expect_okay, Credentials},
+        services::{aof::Replayer, jobs::Job},
+        storage::v1::aof::Record,
+        IoResult,
+    },
+    core::{mem, str},
+    parking_lot::RwLock,
+    std::{collections::HashSet, sync::Arc},
+    tokio::{io::BufReader, net::TcpStream},
+};
+
+/// The number of keys that are marked as in-flight at once
+const BATCH_SIZE: usize = 128;
+
+const KIND_BLOB: u8 = 0;
+const KIND_LIST: u8 = 1;
+const KIND_SET: u8 = 2;
+const KIND_HASH: u8 = 3;
+const KIND_ZSET: u8 = 4;
+
+/// Why a dump couldn't be restored
+#[derive(Debug, PartialEq, Eq)]
+pub enum RestoreError {
+    /// the dump is of a value that the table can't hold
+    WrongModel,
+    /// the dump is malformed
+    Corrupted,
+    /// the key or value doesn't have the table's encoding
+    Encoding,
+}
+
+/// A dump that is being written
+struct DumpWriter {
+    buf: Vec<u8>,
+}
+
+impl DumpWriter {
+    fn new(kind: u8, count: usize) -> Self {
+        let mut buf = vec![kind];
+        buf.extend_from_slice(&(count as u64).to_le_bytes());
+        Self { buf }
+    }
+    fn push(&mut self, item: &[u8]) {
+        self.buf
+            .extend_from_slice(&(item.len() as u64).to_le_bytes());
+        self.buf.extend_from_slice(item);
+    }
+    fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Split a dump into its kind and items
+fn read_dump(mut dump: &[u8]) -> Option<(u8, Vec<&[u8]>)> {
+    fn take<'a>(dump: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        if dump.len() < len {
+            None
+        } else {
+            let (ret, rem) = dump.split_at(len);
+            *dump = rem;
+            Some(ret)
+        }
+    }
+    fn take_u64(dump: &mut &[u8]) -> Option<u64> {
+        let mut bytes = [0u8; mem::size_of::<u64>()];
+        bytes.copy_from_slice(take(dump, mem::size_of::<u64>())?);
+        Some(u64::from_le_bytes(bytes))
+    }
+    let kind = take(&mut dump, 1)?[0];
+    let count = take_u64(&mut dump)?;
+    // don't trust the count for the allocation
+    let mut items = Vec::new();
+    for _ in 0..count {
+        let len = take_u64(&mut dump)? as usize;
+        items.push(take(&mut dump, len)?);
+    }
+    if dump.is_empty() {
+        Some((kind, items))
+    } else {
+        None
+    }
+}
+
+/// Returns the (live) keys in `table` that map to `slot`
+pub fn keys_in_slot(table: &Table, slot: u16) -> Vec<SharedSlice> {
+    macro_rules! scan {
+        ($engine:expr) => {
+            $engine
+                .get_inner_ref()
+                .scan_keys(0, usize::MAX, |key| key_slot(key) == slot)
+                .0
+                .into_iter()
+                .filter(|key| !$engine.purge_if_expired(key))
+                .collect()
+        };
+    }
+    match table.get_model_ref() {
+        DataModel::KV(kve) => scan!(kve),
+        DataModel::KVExtListmap(kve) => scan!(kve),
+        DataModel::KVExtSetmap(kve) => scan!(kve),
+        DataModel::KVExtHashmap(kve) => scan!(kve),
+        DataModel::KVExtZSetmap(kve) => scan!(kve),
+    }
+}
+
+/// Returns true if `key` is in `table`
+pub fn table_has_key(table: &Table, key: &[u8]) -> bool {
+    match table.get_model_ref() {
+        DataModel::KV(kve) => kve.exists_unchecked(key),
+        DataModel::KVExtListmap(kve) => kve.exists_unchecked(key),
+        DataModel::KVExtSetmap(kve) => kve.exists_unchecked(key),
+        DataModel::KVExtHashmap(kve) => kve.exists_unchecked(key),
+        DataModel::KVExtZSetmap(kve) => kve.exists_unchecked(key),
+    }
+}
+
+/// Returns the deadline of a key (`0` if it doesn't expire)
+fn deadline_of<T>(engine: &KVEngine<T>, key: &[u8]) -> u64 {
+    engine.get_expiry_ref().get_cloned(key).unwrap_or(0)
+}
+
+/// Take a dump of `key`, returning the dump and the key's deadline (`0` if it doesn't expire).
+/// `None` is returned if the key doesn't exist
+pub fn dump_key(table: &Table, key: &[u8]) -> Option<(Vec<u8>, u64)> {
+    let dump = match table.get_model_ref() {
+        DataModel::KV(kve) => {
+            let value = kve.get_cloned_unchecked(key)?;
+            let mut dump = DumpWriter::new(KIND_BLOB, 1);
+            dump.push(&value);
+            (dump.finish(), deadline_of(kve, key))
+        }
+        DataModel::KVExtListmap(kve) => {
+            let list = kve.get_unchecked(key)?;
+            let list = list.read();
+            let mut dump = DumpWriter::new(KIND_LIST, list.len());
+            list.iter().for_each(|item| dump.push(item));
+            (dump.finish(), deadline_of(kve, key))
+        }
+        DataModel::KVExtSetmap(kve) => {
+            let set = kve.get_unchecked(key)?;
+            let set = set.read();
+            let mut dump = DumpWriter::new(KIND_SET, set.len());
+            set.iter().for_each(|member| dump.push(member));
+            (dump.finish(), deadline_of(kve, key))
+        }
+        DataModel::KVExtHashmap(kve) => {
+            let hash = kve.get_unchecked(key)?;
+            let hash = hash.read();
+            let mut dump = DumpWriter::new(KIND_HASH, hash.len() * 2);
+            hash.iter().for_each(|(field, value)| {
+                dump.push(field);
+                dump.push(value);
+            });
+            (dump.finish(), deadline_of(kve, key))
+        }
+        DataModel::KVExtZSetmap(kve) => {
+            let zset = kve.get_unchecked(key)?;
+            let zset = zset.read();
+            let mut dump = DumpWriter::new(KIND_ZSET, zset.len() * 2);
+            zset.iter().for_each(|(member, score)| {
+                dump.push(member);
+                dump.push(&score.get().to_le_bytes());
+            });
+            (dump.finish(), deadline_of(kve, key))
+        }
+    };
+    Some(dump)
+}
+
+/// Store `value` under `key`, expiring it at `deadline` (if it isn't `0`). A key whose
+/// deadline has already passed is just removed
+fn restore_into<T: kvengine::KVEValue>(
+    engine: &KVEngine<T>,
+    key: &[u8],
+    value: T,
+    deadline: u64,
+) -> Result<(), RestoreError> {
+    let ret = if deadline == 0 {
+        engine.upsert(SharedSlice::new(key), value)
+    } else if deadline <= kvengine::now_millis() {
+        engine.remove(key).map(|_| ())
+    } else {
+        engine.upsert_with_expiry(SharedSlice::new(key), value, deadline)
+    };
+    ret.map_err(|_| RestoreError::Encoding)
+}
+
+/// Restore a dump (taken with [`dump_key`]) into `table`, replacing any value that `key` has
+pub fn restore_key(
+    table: &Table,
+    key: &[u8],
+    dump: &[u8],
+    deadline: u64,
+) -> Result<(), RestoreError> {
+    let (kind, items) = read_dump(dump).ok_or(RestoreError::Corrupted)?;
+    let slices = || items.iter().map(|item| SharedSlice::new(item));
+    match (table.get_model_ref(), kind) {
+        (DataModel::KV(kve), KIND_BLOB) => match items.as_slice() {
+            [value] => restore_into(kve, key, SharedSlice::new(value), deadline),
+            _ => Err(RestoreError::Corrupted),
+        },
+        (DataModel::KVExtListmap(kve), KIND_LIST) => {
+            restore_into(kve, key, RwLock::new(slices().collect()), deadline)
+        }
+        (DataModel::KVExtSetmap(kve), KIND_SET) => {
+            let set: HashSet<SharedSlice> = slices().collect();
+            restore_into(kve, key, RwLock::new(set), deadline)
+        }
+        (DataModel::KVExtHashmap(kve), KIND_HASH) => {
+            if items.len() % 2 != 0 {
+                return Err(RestoreError::Corrupted);
+            }
+            let hash = items
+                .chunks_exact(2)
+                .map(|pair| (SharedSlice::new(pair[0]), SharedSlice::new(pair[1])))
+                .collect();
+            restore_into(kve, key, RwLock::new(hash), deadline)
+        }
+        (DataModel::KVExtZSetmap(kve), KIND_ZSET) => {
+            if items.len() % 2 != 0 {
+                return Err(RestoreError::Corrupted);
+            }
+            let mut zset = SortedSet::new();
+            for pair in items.chunks_exact(2) {
+                let score = <[u8; 8]>::try_from(pair[1])
+                    .ok()
+                    .and_then(|score| Score::new(f64::from_le_bytes(score)))
+                    .ok_or(RestoreError::Corrupted)?;
+                zset.insert(SharedSlice::new(pair[0]), score);
+            }
+            restore_into(kve, key, RwLock::new(zset), deadline)
+        }
+        (_, KIND_BLOB..=KIND_ZSET) => Err(RestoreError::WrongModel),
+        _ => Err(RestoreError::Corrupted),
+    }
+}
+
+action!(
+    /// Run an `IMPORTKEY <key> <dump> <deadline>` query. This is sent by a node that is moving
+    /// a slot to us, but it's an ordinary write otherwise (it's logged and replicated)
+    fn importkey(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 3)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let (key, dump, deadline) = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there are three arguments
+            (
+                act.next_unchecked(),
+                act.next_unchecked(),
+                act.next_unchecked(),
+            )
+        };
+        let deadline = match str::from_utf8(deadline)
+            .ok()
+            .and_then(|deadline| deadline.parse::<u64>().ok())
+        {
+            Some(deadline) => deadline,
+            None => return util::err(P::RCODE_WRONGTYPE_ERR),
+        };
+        let table = match handle.get_ctable_ref() {
+            Some(table) => table,
+            None => return util::err(P::RSTRING_DEFAULT_UNSET),
+        };
+        if !registry::state_okay() {
+            return util::err(P::RCODE_SERVER_ERR);
+        }
+        match self::restore_key(table, key, dump, deadline) {
+            Ok(()) => con._write_raw(P::RCODE_OKAY).await?,
+            Err(RestoreError::WrongModel) => return util::err(P::RSTRING_WRONG_MODEL),
+            Err(RestoreError::Corrupted) => return util::err(P::RCODE_WRONGTYPE_ERR),
+            Err(RestoreError::Encoding) => return util::err(P::RCODE_ENCODING_ERROR),
+        }
+        Ok(())
+    }
+);
+
+/// Move the keys in `slot` to `target` and then hand the slot over to it. If we fail, the
+/// migration is marked as failed (and can be started again); the keys that were already moved
+/// stay on `target`
+pub(super) async fn run(
+    handle: Corestore,
+    slot: u16,
+    target: Node,
+    credentials: Option<Credentials>,
+    job: Arc<Job>,
+) {
+    let ret = self::migrate(&handle, slot, &target, &credentials, &job).await;
+    job.finish(ret.is_ok());
+    match ret {
+        Ok(moved) => log::info!("Moved slot {slot} ({moved} keys) to {target}"),
+        Err(e) => {
+            handle
+                .get_cluster()
+                .set_migration_state(slot, MigrationState::Failed);
+            log::error!("Failed to move slot {slot} to {target}: {e}");
+        }
+    }
+}
+
+async fn migrate(
+    handle: &Corestore,
+    slot: u16,
+    target: &Node,
+    credentials: &Option<Credentials>,
+    job: &Job,
+) -> IoResult<usize> {
+    let cluster = handle.get_cluster();
+    let mut stream = BufReader::new(TcpStream::connect((target.host.as_str(), target.port)).await?);
+    if let Some((username, token)) = credentials {
+        let login = encode_query(&[b"AUTH", b"LOGIN", username.as_bytes(), token.as_bytes()]);
+        stream.write_all(&login).await?;
+        expect_okay(&mut stream, "target", "log in").await?;
+    }
+    let slot_id = slot.to_string();
+    let importing = encode_query(&[b"CLUSTER", b"IMPORTING", slot_id.as_bytes()]);
+    stream.write_all(&importing).await?;
+    expect_okay(&mut stream, "target", "import the slot").await?;
+    cluster.set_migration_state(slot, MigrationState::Moving);
+    // don't hold on to the maps while we're talking to the target
+    let tables: Vec<(ObjectID, ObjectID, Arc<Table>)> = handle
+        .get_store()
+        .keyspaces
+        .iter()
+        .flat_map(|ks| {
+            ks.value()
+                .tables
+                .iter()
+                .map(|tbl| (ks.key().clone(), tbl.key().clone(), tbl.value().clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let mut replayer = Replayer::new(handle);
+    let mut moved = 0;
+    for (ksid, tblid, table) in tables {
+        let keys = self::keys_in_slot(&table, slot);
+        if keys.is_empty() {
+            continue;
+        }
+        job.add_total(keys.len() as u64);
+        let entity = format!(
+            "USE {}.{}",
+            String::from_utf8_lossy(ksid.as_slice()),
+            String::from_utf8_lossy(tblid.as_slice())
+        );
+        stream
+            .write_all(&encode_query(&[entity.as_bytes()]))
+            .await?;
+        expect_okay(&mut stream, "target", "use the table").await?;
+        for batch in keys.chunks(BATCH_SIZE) {
+            {
+                // the queries that are running hold the lock, so once we have it, none of them
+                // can be writing to these keys (and later writes are routed after the marks)
+                let _txn_lock = registry::lock_txn_exclusive().await;
+                for key in batch {
+                    cluster.mark_inflight(slot, key);
+                }
+            }
+            // if we fail halfway, the migration fails and the marks are all cleared
+            for key in batch {
+                job.ensure_alive()?;
+                if let Some((dump, deadline)) = self::dump_key(&table, key) {
+                    let deadline = deadline.to_string();
+                    let import = encode_query(&[b"IMPORTKEY", key, &dump, deadline.as_bytes()]);
+                    stream.write_all(&import).await?;
+                    expect_okay(&mut stream, "target", "import a key").await?;
+                    let delete = Record {
+                        timestamp: kvengine::now_millis(),
+                        keyspace: Some(ksid.clone()),
+                        table: Some(tblid.clone()),
+                        args: vec![b"DEL".to_vec(), key.to_vec()],
+                    };
+                    replayer.apply(&delete).await?;
+                    moved += 1;
+                }
+                job.add_done(1);
+                cluster.clear_inflight(slot, key);
+            }
+        }
+    }
+    let port = target.port.to_string();
+    let assign = encode_query(&[
+        b"CLUSTER",
+        b"ASSIGN",
+        slot_id.as_bytes(),
+        slot_id.as_bytes(),
+        target.host.as_bytes(),
+        port.as_bytes(),
+    ]);
+    stream.write_all(&assign).await?;
+    expect_okay(&mut stream, "target", "take over the slot").await?;
+    {
+        // queries that are running have already been routed here
+        let _txn_lock = registry::lock_txn_exclusive().await;
+        cluster.finish_migration(slot);
+    }
+    Ok(moved)
+}
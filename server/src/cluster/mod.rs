@@ -0,0 +1,687 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Cluster mode
+//!
+//! In cluster mode, keys are partitioned into [`SLOT_COUNT`] _hash slots_ and every slot is
+//! owned by exactly one node. The slot of a key is the CRC16 (XMODEM) of the key, modulo the
+//! number of slots. If a key has a non-empty `{...}` section (a _hash tag_), only that section
+//! is hashed so that related keys (like `{user1}.name` and `{user1}.email`) end up in the
+//! same slot.
+//!
+//! A node joins a cluster with `CLUSTER MYSELF <host> <port>` (the address that clients use to
+//! reach it) and slots are assigned with `CLUSTER ASSIGN <start> <end> <host> <port>`. Every
+//! node has to be told about every assignment. Each change bumps the node's _epoch_, so that
+//! clients can tell when the slot table has changed.
+//!
+//! A query on a key owned by another node is answered with `moved <slot> <host>:<port>`, and
+//! clients are expected to send it to that node instead (smart clients can use
+//! `CLUSTER SLOTS`, or ask for the slot table in their handshake, to route queries directly).
+//! Queries with keys in different slots are rejected with `cross-slot` and queries on
+//! unassigned slots with `cluster-down`. Queries that don't work on keys (like `DBSIZE` or
+//! `SYS`) always run on the node they're sent to.
+//!
+//! ## Migrating slots
+//!
+//! `CLUSTER MIGRATE <slot> <host> <port>` moves the keys in a slot to another node, one key at
+//! a time (see [`migrate`]). While a slot is being moved, the old owner keeps serving the keys
+//! that it still has. Queries on keys that it no longer has (which were either moved or never
+//! existed) are answered with `ask <slot> <host>:<port>`, and clients are expected to send
+//! just that query to the new owner. Writes to a key that is being moved right then are
+//! answered with `try-again`. Once all the keys are moved, the slot is assigned to the new
+//! owner on both nodes (the other nodes have to be told with `CLUSTER ASSIGN`).
+//!
+//! Note: the topology is only kept in memory, so it has to be set up again after a restart
+
+use {
+    crate::{
+        actions::ActionResult, dbnet::prelude::*, kvengine::encoding, queryengine,
+        replication::Credentials, services::jobs::JobKind,
+    },
+    core::{fmt, str},
+    parking_lot::RwLock,
+    std::collections::{HashMap, HashSet},
+};
+
+pub mod migrate;
+#[cfg(test)]
+mod tests;
+
+pub use self::migrate::importkey;
+
+/// The number of hash slots
+pub const SLOT_COUNT: u16 = 16384;
+
+const INFO: &[u8] = b"info";
+const SLOTS: &[u8] = b"slots";
+const MYSELF: &[u8] = b"myself";
+const ASSIGN: &[u8] = b"assign";
+const RESET: &[u8] = b"reset";
+const MIGRATE: &[u8] = b"migrate";
+const IMPORTING: &[u8] = b"importing";
+
+/// The CRC16 (XMODEM) of `data`
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            }
+        })
+    })
+}
+
+/// Returns the slot of a key (see the module docs for hash tags)
+pub fn key_slot(key: &[u8]) -> u16 {
+    let hashed = key
+        .iter()
+        .position(|byte| *byte == b'{')
+        .and_then(|open| {
+            let tag = &key[open + 1..];
+            tag.iter()
+                .position(|byte| *byte == b'}')
+                .filter(|close| *close != 0)
+                .map(|close| &tag[..close])
+        })
+        .unwrap_or(key);
+    crc16(hashed) % SLOT_COUNT
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The address of a node
+pub struct Node {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Node {
+    pub fn new(host: String, port: u16) -> Self {
+        Self { host, port }
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// Where a query should run
+#[derive(Debug, PartialEq, Eq)]
+pub enum Route {
+    /// on this node
+    Local,
+    /// on another node
+    Moved { slot: u16, node: String },
+    /// nowhere, since its keys are in different slots
+    CrossSlot,
+    /// nowhere, since the slot of its keys isn't assigned
+    Down,
+    /// on the node that its slot is being moved to (just this query)
+    Ask { slot: u16, node: String },
+    /// nowhere right now, since its keys are being moved
+    TryAgain,
+}
+
+/// A range of slots owned by the same node
+#[derive(Debug, PartialEq, Eq)]
+pub struct SlotRange {
+    pub start: u16,
+    pub end: u16,
+    pub node: Node,
+}
+
+/// A consistent view of the slot table, as advertised to clients that ask for it in their
+/// handshake
+#[derive(Debug, PartialEq, Eq)]
+pub struct TopologySnapshot {
+    /// the address of this node, which is how the other nodes (and clients) know it
+    pub myself: Node,
+    pub epoch: u64,
+    pub ranges: Vec<SlotRange>,
+}
+
+/// The cluster status of this node, as reported by `CLUSTER INFO`
+pub struct ClusterInfo {
+    pub myself: String,
+    pub epoch: u64,
+    /// the number of slots that are owned by some node
+    pub assigned: usize,
+    /// the number of slots owned by this node
+    pub owned: usize,
+    /// the number of nodes that we know of (including this one)
+    pub nodes: usize,
+    /// the number of slots that we're moving to other nodes
+    pub migrating: usize,
+    /// the number of slots that other nodes are moving to us
+    pub importing: usize,
+}
+
+/// Why a slot can't be moved
+#[derive(Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    /// this node isn't in cluster mode
+    Disabled,
+    /// this node doesn't own the slot
+    NotOwner,
+    /// the slot is already being moved
+    Busy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MigrationState {
+    /// waiting for the new owner to accept the slot
+    Connecting,
+    /// moving the keys
+    Moving,
+    /// stopped because of an error (some keys may have been moved)
+    Failed,
+}
+
+#[derive(Debug)]
+/// A slot that is being moved to another node
+struct Migration {
+    /// the index (in `nodes`) of the new owner
+    target: u16,
+    state: MigrationState,
+    /// the keys that are being moved right now
+    inflight: HashSet<Vec<u8>>,
+}
+
+#[derive(Debug)]
+/// The slot table
+struct Topology {
+    /// the nodes that we know of. The first one is always this node
+    nodes: Vec<Node>,
+    /// the index (in `nodes`) of the owner of every slot
+    slots: Box<[Option<u16>]>,
+    epoch: u64,
+    /// the slots that we're moving to other nodes
+    migrating: HashMap<u16, Migration>,
+    /// the slots that other nodes are moving to us
+    importing: HashSet<u16>,
+}
+
+impl Topology {
+    fn new(myself: Node, epoch: u64) -> Self {
+        Self {
+            nodes: vec![myself],
+            slots: vec![None; SLOT_COUNT as usize].into_boxed_slice(),
+            epoch,
+            migrating: HashMap::new(),
+            importing: HashSet::new(),
+        }
+    }
+    fn node_index(&mut self, node: Node) -> u16 {
+        match self.nodes.iter().position(|known| *known == node) {
+            Some(idx) => idx as u16,
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u16
+            }
+        }
+    }
+    /// Returns the assigned slots, as ranges of slots owned by the same node
+    fn ranges(&self) -> Vec<SlotRange> {
+        let mut ranges = Vec::new();
+        let mut start = 0;
+        for slot in 1..=self.slots.len() {
+            let owner = self.slots[start];
+            if slot < self.slots.len() && self.slots[slot] == owner {
+                continue;
+            }
+            if let Some(owner) = owner {
+                ranges.push(SlotRange {
+                    start: start as u16,
+                    end: (slot - 1) as u16,
+                    node: self.nodes[owner as usize].clone(),
+                });
+            }
+            start = slot;
+        }
+        ranges
+    }
+}
+
+#[derive(Debug, Default)]
+/// The cluster state of a node. This is `None` unless the node is in cluster mode
+pub struct Cluster {
+    topology: RwLock<Option<Topology>>,
+}
+
+impl Cluster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns true if this node is in cluster mode
+    pub fn is_enabled(&self) -> bool {
+        self.topology.read().is_some()
+    }
+    /// Enter cluster mode (or change this node's address), keeping the slot assignments
+    pub fn set_myself(&self, myself: Node) {
+        let mut topology = self.topology.write();
+        match topology.as_mut() {
+            Some(topology) => {
+                topology.nodes[0] = myself;
+                topology.epoch += 1;
+            }
+            None => *topology = Some(Topology::new(myself, 1)),
+        }
+    }
+    /// Assign the slots in `start..=end` to `node`, ending any migration of these slots.
+    /// Returns false if this node isn't in cluster mode
+    pub fn assign(&self, start: u16, end: u16, node: Node) -> bool {
+        let mut topology = self.topology.write();
+        let topology = match topology.as_mut() {
+            Some(topology) => topology,
+            None => return false,
+        };
+        let owner = topology.node_index(node);
+        topology.slots[start as usize..=end as usize].fill(Some(owner));
+        topology
+            .migrating
+            .retain(|slot, _| !(start..=end).contains(slot));
+        topology
+            .importing
+            .retain(|slot| !(start..=end).contains(slot));
+        topology.epoch += 1;
+        true
+    }
+    /// Start moving `slot` to `target`. A migration that failed can be started again
+    pub fn begin_migration(&self, slot: u16, target: Node) -> Result<(), MigrationError> {
+        let mut topology = self.topology.write();
+        let topology = topology.as_mut().ok_or(MigrationError::Disabled)?;
+        if topology.slots[slot as usize] != Some(0) {
+            return Err(MigrationError::NotOwner);
+        }
+        if let Some(migration) = topology.migrating.get(&slot) {
+            if migration.state != MigrationState::Failed {
+                return Err(MigrationError::Busy);
+            }
+        }
+        let target = topology.node_index(target);
+        topology.migrating.insert(
+            slot,
+            Migration {
+                target,
+                state: MigrationState::Connecting,
+                inflight: HashSet::new(),
+            },
+        );
+        Ok(())
+    }
+    fn set_migration_state(&self, slot: u16, state: MigrationState) {
+        if let Some(topology) = self.topology.write().as_mut() {
+            if let Some(migration) = topology.migrating.get_mut(&slot) {
+                migration.state = state;
+                migration.inflight.clear();
+            }
+        }
+    }
+    /// Mark a key as being moved (writes to it are rejected until it's unmarked)
+    fn mark_inflight(&self, slot: u16, key: &[u8]) {
+        if let Some(topology) = self.topology.write().as_mut() {
+            if let Some(migration) = topology.migrating.get_mut(&slot) {
+                migration.inflight.insert(key.to_owned());
+            }
+        }
+    }
+    fn clear_inflight(&self, slot: u16, key: &[u8]) {
+        if let Some(topology) = self.topology.write().as_mut() {
+            if let Some(migration) = topology.migrating.get_mut(&slot) {
+                migration.inflight.remove(key);
+            }
+        }
+    }
+    /// Hand `slot` over to the node that it was being moved to
+    fn finish_migration(&self, slot: u16) {
+        if let Some(topology) = self.topology.write().as_mut() {
+            if let Some(migration) = topology.migrating.remove(&slot) {
+                topology.slots[slot as usize] = Some(migration.target);
+                topology.epoch += 1;
+            }
+        }
+    }
+    /// Accept the keys of `slot` (which another node is moving to us). Returns false if this
+    /// node isn't in cluster mode
+    pub fn set_importing(&self, slot: u16) -> bool {
+        match self.topology.write().as_mut() {
+            Some(topology) => {
+                topology.importing.insert(slot);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Leave cluster mode, forgetting all the slot assignments
+    pub fn reset(&self) {
+        *self.topology.write() = None;
+    }
+    /// Returns where a query should run. `exists` tells if a key is in the current table; it's
+    /// `None` for queries that are queued in a transaction (which are rejected while their
+    /// slot is being moved, since the keys may be gone by the time they run)
+    pub fn route<'a>(
+        &self,
+        action: &[u8],
+        args: impl Iterator<Item = &'a [u8]>,
+        exists: Option<&dyn Fn(&[u8]) -> bool>,
+    ) -> Route {
+        let topology = self.topology.read();
+        let topology = match topology.as_ref() {
+            Some(topology) => topology,
+            None => return Route::Local,
+        };
+        let keys: Vec<&[u8]> = queryengine::action_flags(action)
+            .key_layout()
+            .keys(args)
+            .collect();
+        let slot = match keys.first() {
+            Some(key) => key_slot(key),
+            None => return Route::Local,
+        };
+        if keys.iter().any(|key| key_slot(key) != slot) {
+            return Route::CrossSlot;
+        }
+        if topology.importing.contains(&slot) {
+            return Route::Local;
+        }
+        match topology.slots[slot as usize] {
+            None => Route::Down,
+            Some(0) => match topology.migrating.get(&slot) {
+                Some(migration) if migration.state != MigrationState::Connecting => {
+                    let exists = match exists {
+                        Some(exists) => exists,
+                        None => return Route::TryAgain,
+                    };
+                    if keys.iter().any(|key| migration.inflight.contains(*key)) {
+                        // the key is still here, so it can be read
+                        return if queryengine::writes(action) {
+                            Route::TryAgain
+                        } else {
+                            Route::Local
+                        };
+                    }
+                    match keys.iter().filter(|key| exists(key)).count() {
+                        present if present == keys.len() => Route::Local,
+                        0 => Route::Ask {
+                            slot,
+                            node: topology.nodes[migration.target as usize].to_string(),
+                        },
+                        // some of the keys were moved
+                        _ => Route::TryAgain,
+                    }
+                }
+                _ => Route::Local,
+            },
+            Some(owner) => Route::Moved {
+                slot,
+                node: topology.nodes[owner as usize].to_string(),
+            },
+        }
+    }
+    /// Returns the cluster status of this node, if it's in cluster mode
+    pub fn info(&self) -> Option<ClusterInfo> {
+        let topology = self.topology.read();
+        topology.as_ref().map(|topology| {
+            let assigned = topology.slots.iter().filter(|owner| owner.is_some());
+            ClusterInfo {
+                myself: topology.nodes[0].to_string(),
+                epoch: topology.epoch,
+                assigned: assigned.clone().count(),
+                owned: assigned.filter(|owner| **owner == Some(0)).count(),
+                nodes: topology.nodes.len(),
+                migrating: topology.migrating.len(),
+                importing: topology.importing.len(),
+            }
+        })
+    }
+    /// Returns the assigned slots, as ranges of slots owned by the same node
+    pub fn slot_ranges(&self) -> Vec<SlotRange> {
+        self.topology
+            .read()
+            .as_ref()
+            .map(Topology::ranges)
+            .unwrap_or_default()
+    }
+    /// Returns this node's address, epoch and slot ranges (read together, so that the ranges
+    /// are the ones of that epoch), if it's in cluster mode
+    pub fn snapshot(&self) -> Option<TopologySnapshot> {
+        self.topology
+            .read()
+            .as_ref()
+            .map(|topology| TopologySnapshot {
+                myself: topology.nodes[0].clone(),
+                epoch: topology.epoch,
+                ranges: topology.ranges(),
+            })
+    }
+}
+
+/// Parse a node's address from a query
+fn parse_node<P: ProtocolSpec>(host: &[u8], port: &[u8]) -> ActionResult<Node> {
+    if !encoding::is_utf8(host) {
+        return util::err(P::RCODE_ENCODING_ERROR);
+    }
+    match str::from_utf8(port)
+        .ok()
+        .and_then(|port| port.parse::<u16>().ok())
+    {
+        Some(port) => Ok(Node::new(String::from_utf8_lossy(host).into_owned(), port)),
+        None => util::err(P::RCODE_WRONGTYPE_ERR),
+    }
+}
+
+/// Parse a slot number from a query
+fn parse_slot<P: ProtocolSpec>(slot: &[u8]) -> ActionResult<u16> {
+    match str::from_utf8(slot)
+        .ok()
+        .and_then(|slot| slot.parse::<u64>().ok())
+    {
+        Some(slot) if slot < SLOT_COUNT as u64 => Ok(slot as u16),
+        Some(_) => util::err(P::RSTRING_BAD_SLOT),
+        None => util::err(P::RCODE_WRONGTYPE_ERR),
+    }
+}
+
+action! {
+    /// Run a `CLUSTER` query:
+    /// - `CLUSTER INFO`: the cluster status of this node
+    /// - `CLUSTER SLOTS`: the assigned slot ranges and their owners
+    /// - `CLUSTER MYSELF <host> <port>`: enter cluster mode with the given address
+    /// - `CLUSTER ASSIGN <start> <end> <host> <port>`: assign a range of slots to a node
+    /// - `CLUSTER RESET`: leave cluster mode
+    /// - `CLUSTER MIGRATE <slot> <host> <port> [<username> <token>]`: move a slot that this
+    /// node owns to another node (logging in with the given credentials)
+    /// - `CLUSTER IMPORTING <slot>`: accept the keys of a slot that another node is moving to us
+    ///
+    /// If auth is enabled, only root can change the topology
+    fn cluster(
+        handle: &Corestore,
+        con: &mut Connection<C, P>,
+        auth: &mut AuthProviderHandle,
+        iter: ActionIter<'_>,
+    ) {
+        let mut iter = iter;
+        ensure_length::<P>(iter.len(), |len| len != 0)?;
+        let cluster = handle.get_cluster();
+        let subaction = unsafe {
+            // SAFETY: We have already checked that there is at least one argument
+            iter.next_lowercase_unchecked()
+        };
+        match subaction.as_ref() {
+            INFO => {
+                ensure_length::<P>(iter.len(), |len| len == 0)?;
+                cluster_info(handle, con).await
+            }
+            SLOTS => {
+                ensure_length::<P>(iter.len(), |len| len == 0)?;
+                cluster_slots(handle, con).await
+            }
+            MYSELF => {
+                ensure_length::<P>(iter.len(), |len| len == 2)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                let (host, port) = unsafe {
+                    // SAFETY: We have already checked the length
+                    (iter.next_unchecked(), iter.next_unchecked())
+                };
+                cluster.set_myself(parse_node::<P>(host, port)?);
+                con._write_raw(P::RCODE_OKAY).await?;
+                Ok(())
+            }
+            ASSIGN => {
+                ensure_length::<P>(iter.len(), |len| len == 4)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                let (start, end, host, port) = unsafe {
+                    // SAFETY: We have already checked the length
+                    (
+                        iter.next_unchecked(),
+                        iter.next_unchecked(),
+                        iter.next_unchecked(),
+                        iter.next_unchecked(),
+                    )
+                };
+                let (start, end) = (parse_slot::<P>(start)?, parse_slot::<P>(end)?);
+                if start > end {
+                    return util::err(P::RSTRING_BAD_SLOT);
+                }
+                if cluster.assign(start, end, parse_node::<P>(host, port)?) {
+                    con._write_raw(P::RCODE_OKAY).await?;
+                } else {
+                    con._write_raw(P::RSTRING_CLUSTER_DISABLED).await?;
+                }
+                Ok(())
+            }
+            RESET => {
+                ensure_length::<P>(iter.len(), |len| len == 0)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                cluster.reset();
+                con._write_raw(P::RCODE_OKAY).await?;
+                Ok(())
+            }
+            MIGRATE => {
+                ensure_length::<P>(iter.len(), |len| len == 3 || len == 5)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                let (slot, host, port) = unsafe {
+                    // SAFETY: We have already checked the length
+                    (
+                        iter.next_unchecked(),
+                        iter.next_unchecked(),
+                        iter.next_unchecked(),
+                    )
+                };
+                let (slot, target) = (parse_slot::<P>(slot)?, parse_node::<P>(host, port)?);
+                let credentials: Option<Credentials> = match (iter.next(), iter.next()) {
+                    (Some(username), Some(token)) => {
+                        if !(encoding::is_utf8(username) && encoding::is_utf8(token)) {
+                            return util::err(P::RCODE_ENCODING_ERROR);
+                        }
+                        Some((
+                            String::from_utf8_lossy(username).into_owned(),
+                            String::from_utf8_lossy(token).into_owned(),
+                        ))
+                    }
+                    _ => None,
+                };
+                match cluster.begin_migration(slot, target.clone()) {
+                    Ok(()) => {
+                        let (_, job) = handle.get_jobs().start(JobKind::Migration);
+                        tokio::spawn(migrate::run(handle.clone(), slot, target, credentials, job));
+                        con._write_raw(P::RCODE_OKAY).await?;
+                        Ok(())
+                    }
+                    Err(MigrationError::Disabled) => util::err(P::RSTRING_CLUSTER_DISABLED),
+                    Err(MigrationError::NotOwner) => util::err(P::RSTRING_NOT_SLOT_OWNER),
+                    Err(MigrationError::Busy) => util::err(P::RSTRING_MIGRATION_BUSY),
+                }
+            }
+            IMPORTING => {
+                ensure_length::<P>(iter.len(), |len| len == 1)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                let slot = unsafe {
+                    // SAFETY: We have already checked the length
+                    iter.next_unchecked()
+                };
+                if cluster.set_importing(parse_slot::<P>(slot)?) {
+                    con._write_raw(P::RCODE_OKAY).await?;
+                } else {
+                    con._write_raw(P::RSTRING_CLUSTER_DISABLED).await?;
+                }
+                Ok(())
+            }
+            _ => util::err(P::RCODE_UNKNOWN_ACTION),
+        }
+    }
+    /// Write out the cluster status as a flat array of name/value pairs
+    fn cluster_info(handle: &Corestore, con: &mut Connection<C, P>) {
+        match handle.get_cluster().info() {
+            Some(info) => {
+                let state = if info.assigned == SLOT_COUNT as usize {
+                    "ok"
+                } else {
+                    "down"
+                };
+                con.write_array_header(16).await?;
+                con.write_string("state").await?;
+                con.write_string(state).await?;
+                con.write_string("myself").await?;
+                con.write_string(&info.myself).await?;
+                con.write_string("epoch").await?;
+                con.write_int64(info.epoch).await?;
+                con.write_string("assigned").await?;
+                con.write_usize(info.assigned).await?;
+                con.write_string("owned").await?;
+                con.write_usize(info.owned).await?;
+                con.write_string("nodes").await?;
+                con.write_usize(info.nodes).await?;
+                con.write_string("migrating").await?;
+                con.write_usize(info.migrating).await?;
+                con.write_string("importing").await?;
+                con.write_usize(info.importing).await?;
+            }
+            None => {
+                con.write_array_header(2).await?;
+                con.write_string("state").await?;
+                con.write_string("disabled").await?;
+            }
+        }
+        Ok(())
+    }
+    /// Write out the assigned slot ranges as arrays of `[start, end, host, port]`
+    fn cluster_slots(handle: &Corestore, con: &mut Connection<C, P>) {
+        let ranges = handle.get_cluster().slot_ranges();
+        con.write_array_header(ranges.len()).await?;
+        for range in ranges {
+            con.write_array_header(4).await?;
+            con.write_int64(range.start as u64).await?;
+            con.write_int64(range.end as u64).await?;
+            con.write_string(&range.node.host).await?;
+            con.write_int64(range.node.port as u64).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    super::{
+        crc16, key_slot,
+        migrate::{self, RestoreError},
+        Cluster, MigrationError, MigrationState, Node, Route, SlotRange, SLOT_COUNT,
+    },
+    crate::{
+        corestore::{table::Table, SharedSlice},
+        kvengine,
+        protocol::{interface::ProtocolSpec, Skyhash1, Skyhash2},
+    },
+};
+
+fn node(port: u16) -> Node {
+    Node::new("10.0.0.1".to_owned(), port)
+}
+
+fn route(cluster: &Cluster, query: &[&[u8]]) -> Route {
+    self::route_with(cluster, query, Some(&|_| true))
+}
+
+fn route_with(cluster: &Cluster, query: &[&[u8]], exists: Option<&dyn Fn(&[u8]) -> bool>) -> Route {
+    cluster.route(query[0], query[1..].iter().copied(), exists)
+}
+
+#[test]
+fn crc16_xmodem() {
+    assert_eq!(crc16(b"123456789"), 0x31C3);
+    assert_eq!(crc16(b""), 0);
+}
+
+#[test]
+fn key_slots() {
+    assert_eq!(key_slot(b"foo"), 12182);
+    assert_eq!(key_slot(b"bar"), 5061);
+    // only the hash tag is hashed
+    assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+    assert_eq!(key_slot(b"{user1000}.followers"), key_slot(b"user1000"));
+    // an empty hash tag doesn't count
+    assert_eq!(key_slot(b"foo{}{bar}"), crc16(b"foo{}{bar}") % SLOT_COUNT);
+    assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+    assert_eq!(key_slot(b"foo{bar"), crc16(b"foo{bar") % SLOT_COUNT);
+}
+
+#[test]
+fn everything_is_local_without_cluster_mode() {
+    let cluster = Cluster::new();
+    assert!(!cluster.is_enabled());
+    assert_eq!(route(&cluster, &[b"get", b"foo"]), Route::Local);
+    assert_eq!(route(&cluster, &[b"mget", b"foo", b"bar"]), Route::Local);
+    assert!(!cluster.assign(0, 10, node(2004)));
+    assert!(cluster.info().is_none());
+    assert!(cluster.slot_ranges().is_empty());
+    assert!(cluster.snapshot().is_none());
+}
+
+#[test]
+fn routing() {
+    let cluster = Cluster::new();
+    cluster.set_myself(node(2003));
+    assert!(cluster.assign(0, 8191, node(2003)));
+    // nothing owns the slot of `foo` yet
+    assert_eq!(route(&cluster, &[b"get", b"foo"]), Route::Down);
+    assert!(cluster.assign(8192, SLOT_COUNT - 1, node(2004)));
+    assert_eq!(route(&cluster, &[b"get", b"bar"]), Route::Local);
+    assert_eq!(
+        route(&cluster, &[b"set", b"foo", b"bar"]),
+        Route::Moved {
+            slot: 12182,
+            node: "10.0.0.1:2004".to_owned()
+        }
+    );
+    // values aren't keys
+    assert_eq!(
+        route(&cluster, &[b"mset", b"bar", b"foo", b"{bar}.x", b"foo"]),
+        Route::Local
+    );
+    assert_eq!(
+        route(&cluster, &[b"mget", b"bar", b"foo"]),
+        Route::CrossSlot
+    );
+    // keyless actions run anywhere
+    assert_eq!(route(&cluster, &[b"dbsize"]), Route::Local);
+    assert_eq!(
+        route(&cluster, &[b"sys", b"info", b"version"]),
+        Route::Local
+    );
+    cluster.reset();
+    assert_eq!(route(&cluster, &[b"get", b"foo"]), Route::Local);
+}
+
+#[test]
+fn info_and_slot_ranges() {
+    let cluster = Cluster::new();
+    cluster.set_myself(node(2003));
+    cluster.assign(0, 99, node(2003));
+    cluster.assign(100, 199, node(2004));
+    cluster.assign(150, 150, node(2003));
+    let info = cluster.info().unwrap();
+    assert_eq!(info.myself, "10.0.0.1:2003");
+    assert_eq!(info.epoch, 4);
+    assert_eq!(info.assigned, 200);
+    assert_eq!(info.owned, 101);
+    assert_eq!(info.nodes, 2);
+    let range = |start, end, port| SlotRange {
+        start,
+        end,
+        node: node(port),
+    };
+    assert_eq!(
+        cluster.slot_ranges(),
+        [
+            range(0, 99, 2003),
+            range(100, 149, 2004),
+            range(150, 150, 2003),
+            range(151, 199, 2004),
+        ]
+    );
+    // the last slot is a range of its own
+    cluster.assign(SLOT_COUNT - 1, SLOT_COUNT - 1, node(2003));
+    assert_eq!(
+        cluster.slot_ranges().last(),
+        Some(&range(SLOT_COUNT - 1, SLOT_COUNT - 1, 2003))
+    );
+    let snapshot = cluster.snapshot().unwrap();
+    assert_eq!(snapshot.myself, node(2003));
+    assert_eq!(snapshot.epoch, 5);
+    assert_eq!(snapshot.ranges, cluster.slot_ranges());
+}
+
+#[test]
+fn migration_routing() {
+    let cluster = Cluster::new();
+    assert_eq!(
+        cluster.begin_migration(5061, node(2004)),
+        Err(MigrationError::Disabled)
+    );
+    assert!(!cluster.set_importing(5061));
+    cluster.set_myself(node(2003));
+    cluster.assign(0, SLOT_COUNT - 1, node(2003));
+    cluster.assign(12182, 12182, node(2004));
+    assert_eq!(
+        cluster.begin_migration(12182, node(2005)),
+        Err(MigrationError::NotOwner)
+    );
+    // `bar` is in slot 5061
+    assert_eq!(cluster.begin_migration(5061, node(2004)), Ok(()));
+    assert_eq!(
+        cluster.begin_migration(5061, node(2005)),
+        Err(MigrationError::Busy)
+    );
+    assert_eq!(cluster.info().unwrap().migrating, 1);
+    let missing: &dyn Fn(&[u8]) -> bool = &|_| false;
+    // nothing has been moved until the target accepts the slot
+    assert_eq!(
+        route_with(&cluster, &[b"get", b"bar"], Some(missing)),
+        Route::Local
+    );
+    cluster.set_migration_state(5061, MigrationState::Moving);
+    assert_eq!(route(&cluster, &[b"get", b"bar"]), Route::Local);
+    let ask = Route::Ask {
+        slot: 5061,
+        node: "10.0.0.1:2004".to_owned(),
+    };
+    assert_eq!(route_with(&cluster, &[b"get", b"bar"], Some(missing)), ask);
+    // a key that is being moved can be read, but not written
+    cluster.mark_inflight(5061, b"bar");
+    assert_eq!(route(&cluster, &[b"get", b"bar"]), Route::Local);
+    assert_eq!(route(&cluster, &[b"set", b"bar", b"foo"]), Route::TryAgain);
+    cluster.clear_inflight(5061, b"bar");
+    assert_eq!(route(&cluster, &[b"set", b"bar", b"foo"]), Route::Local);
+    // some of the keys were moved
+    let only_bar: &dyn Fn(&[u8]) -> bool = &|key| key == b"bar";
+    assert_eq!(
+        route_with(&cluster, &[b"mget", b"bar", b"{bar}.x"], Some(only_bar)),
+        Route::TryAgain
+    );
+    // queued queries may run after their keys are gone
+    assert_eq!(
+        route_with(&cluster, &[b"get", b"bar"], None),
+        Route::TryAgain
+    );
+    // a failed migration can be started again
+    cluster.set_migration_state(5061, MigrationState::Failed);
+    assert_eq!(route(&cluster, &[b"get", b"bar"]), Route::Local);
+    assert_eq!(cluster.begin_migration(5061, node(2004)), Ok(()));
+    cluster.set_migration_state(5061, MigrationState::Moving);
+    let epoch = cluster.info().unwrap().epoch;
+    cluster.finish_migration(5061);
+    let info = cluster.info().unwrap();
+    assert_eq!((info.migrating, info.epoch), (0, epoch + 1));
+    assert_eq!(
+        route(&cluster, &[b"get", b"bar"]),
+        Route::Moved {
+            slot: 5061,
+            node: "10.0.0.1:2004".to_owned()
+        }
+    );
+}
+
+#[test]
+fn importing_routing() {
+    let cluster = Cluster::new();
+    cluster.set_myself(node(2003));
+    cluster.assign(0, SLOT_COUNT - 1, node(2004));
+    assert!(cluster.set_importing(12182));
+    assert_eq!(cluster.info().unwrap().importing, 1);
+    // the keys that are being moved to us are served here
+    assert_eq!(
+        route(&cluster, &[b"importkey", b"foo", b"", b"0"]),
+        Route::Local
+    );
+    assert!(matches!(
+        route(&cluster, &[b"get", b"bar"]),
+        Route::Moved { slot: 5061, .. }
+    ));
+    // once the slot is ours, we're done importing it
+    cluster.assign(12182, 12182, node(2003));
+    assert_eq!(cluster.info().unwrap().importing, 0);
+    assert_eq!(route(&cluster, &[b"get", b"foo"]), Route::Local);
+}
+
+#[test]
+fn dump_and_restore() {
+    // (model code, dump)
+    let dumps: [(u8, &[u8]); 5] = [
+        (0, b"\x00\x01\0\0\0\0\0\0\0\x03\0\0\0\0\0\0\0bar"),
+        (
+            4,
+            b"\x01\x02\0\0\0\0\0\0\0\x01\0\0\0\0\0\0\0a\x01\0\0\0\0\0\0\0b",
+        ),
+        (8, b"\x02\x01\0\0\0\0\0\0\0\x01\0\0\0\0\0\0\0a"),
+        (
+            12,
+            b"\x03\x02\0\0\0\0\0\0\0\x01\0\0\0\0\0\0\0f\x01\0\0\0\0\0\0\0v",
+        ),
+        (
+            16,
+            // 1.5
+            b"\x04\x02\0\0\0\0\0\0\0\x01\0\0\0\0\0\0\0m\
+            \x08\0\0\0\0\0\0\0\0\0\0\0\0\0\xf8\x3f",
+        ),
+    ];
+    for (code, dump) in dumps {
+        let table = Table::from_model_code(code, false).unwrap();
+        assert_eq!(migrate::restore_key(&table, b"foo", dump, 0), Ok(()));
+        assert_eq!(migrate::dump_key(&table, b"foo"), Some((dump.to_vec(), 0)));
+        assert_eq!(
+            migrate::keys_in_slot(&table, 12182),
+            [SharedSlice::new(b"foo")]
+        );
+        assert!(migrate::keys_in_slot(&table, 5061).is_empty());
+    }
+    let table = Table::from_model_code(0, false).unwrap();
+    let blob = dumps[0].1;
+    // the deadline goes along with the value
+    let deadline = kvengine::now_millis() + 60_000;
+    migrate::restore_key(&table, b"foo", blob, deadline).unwrap();
+    assert_eq!(
+        migrate::dump_key(&table, b"foo"),
+        Some((blob.to_vec(), deadline))
+    );
+    // a key that has already expired isn't restored (and replaces the old value)
+    migrate::restore_key(&table, b"foo", blob, 1).unwrap();
+    assert!(!migrate::table_has_key(&table, b"foo"));
+    assert_eq!(migrate::dump_key(&table, b"foo"), None);
+    // a list can't be restored into a blob table
+    assert_eq!(
+        migrate::restore_key(&table, b"foo", dumps[1].1, 0),
+        Err(RestoreError::WrongModel)
+    );
+    for corrupted in [&blob[..blob.len() - 1], b"\x05\0\0\0\0\0\0\0\0", b""] {
+        assert_eq!(
+            migrate::restore_key(&table, b"foo", corrupted, 0),
+            Err(RestoreError::Corrupted)
+        );
+    }
+    // the table only takes UTF-8 values
+    let table = Table::from_model_code(1, false).unwrap();
+    let binary = b"\x00\x01\0\0\0\0\0\0\0\x01\0\0\0\0\0\0\0\xff";
+    assert_eq!(
+        migrate::restore_key(&table, b"foo", binary, 0),
+        Err(RestoreError::Encoding)
+    );
+}
+
+#[test]
+fn runtime_respstrings_match_static_ones() {
+    assert_eq!(
+        Skyhash1::encode_respstring("cross-slot"),
+        Skyhash1::RSTRING_CROSS_SLOT
+    );
+    assert_eq!(
+        Skyhash2::encode_respstring("cross-slot"),
+        Skyhash2::RSTRING_CROSS_SLOT
+    );
+}
@@ -62,8 +62,8 @@ impl<const SWITCH: bool> TryFromConfigSource<bool> for Flag<SWITCH> {
     }
 }
 
-pub(super) fn parse_cli_args(matches: ArgMatches) -> Configset {
-    let mut defset = Configset::new_cli();
+/// Apply the settings that were passed as command-line arguments on top of `defset`
+pub(super) fn parse_cli_args(mut defset: Configset, matches: ArgMatches) -> Configset {
     macro_rules! fcli {
         ($fn:ident, $($source:expr, $key:literal),*) => {
             defset.$fn(
@@ -88,6 +88,47 @@ pub(super) fn parse_cli_args(matches: ArgMatches) -> Configset {
         matches.value_of("port"),
         "--port"
     );
+    fcli!(
+        server_unixsocket,
+        matches.value_of("unixsocket"),
+        "--unixsocket"
+    );
+    fcli!(server_httpport, matches.value_of("httpport"), "--httpport");
+    fcli!(server_grpcport, matches.value_of("grpcport"), "--grpcport");
+    fcli!(
+        server_statusport,
+        matches.value_of("statusport"),
+        "--statusport"
+    );
+    fcli!(
+        server_compressmin,
+        matches.value_of("compressmin"),
+        "--compressmin"
+    );
+    fcli!(
+        server_maxinflight,
+        matches.value_of("maxinflight"),
+        "--maxinflight"
+    );
+    fcli!(
+        server_maxoutbuf,
+        matches.value_of("maxoutbuf"),
+        "--maxoutbuf"
+    );
+    fcli!(
+        server_keepalive,
+        matches.value_of("keepalive"),
+        "--keepalive"
+    );
+    fcli!(
+        server_limits,
+        matches.value_of("maxquerysize"),
+        "--maxquerysize",
+        matches.value_of("maxpipeline"),
+        "--maxpipeline",
+        matches.value_of("maxarglen"),
+        "--maxarglen"
+    );
     fcli!(
         server_noart,
         Flag::<true>::new(matches.is_present("noart")),
@@ -95,6 +136,17 @@ pub(super) fn parse_cli_args(matches: ArgMatches) -> Configset {
     );
     fcli!(server_mode, matches.value_of("mode"), "--mode");
     fcli!(server_maxcon, matches.value_of("maxcon"), "--maxcon");
+    fcli!(
+        server_timeouts,
+        matches.value_of("idletimeout"),
+        "--idletimeout",
+        matches.value_of("readtimeout"),
+        "--readtimeout"
+    );
+    fcli!(server_maxlag, matches.value_of("maxlag"), "--maxlag");
+    // network settings
+    fcli!(network_allow, matches.value_of("allow"), "--allow");
+    fcli!(network_deny, matches.value_of("deny"), "--deny");
     // bgsave settings
     fcli!(
         bgsave_settings,
@@ -127,11 +179,71 @@ pub(super) fn parse_cli_args(matches: ArgMatches) -> Configset {
         matches.value_of("tlspass"),
         "--tlspassin"
     );
+    fcli!(
+        tls_clientca,
+        matches.value_of("sslclientca"),
+        "--sslclientca"
+    );
     // auth settings
     fcli!(
         auth_settings,
         matches.value_of("authkey"),
         "--auth-origin-key"
     );
+    fcli!(
+        auth_origin_file,
+        matches.value_of("authoriginfile"),
+        "--auth-origin-file"
+    );
+    fcli!(auth_import, matches.value_of("authimport"), "--auth-import");
+    fcli!(
+        auth_session_ttl,
+        matches.value_of("sessionttl"),
+        "--auth-session-ttl"
+    );
+    // eviction settings
+    fcli!(
+        eviction_settings,
+        matches.value_of("maxmemory"),
+        "--maxmemory",
+        matches.value_of("evictionpolicy"),
+        "--eviction-policy"
+    );
+    // aof settings
+    fcli!(
+        aof_settings,
+        Flag::<true>::new(matches.is_present("aof")),
+        "--aof",
+        matches.value_of("aoffsync"),
+        "--aof-fsync"
+    );
+    // storage settings
+    fcli!(
+        storage_settings,
+        matches.value_of("storagecompress"),
+        "--storage-compress",
+        matches.value_of("storagecompressmin"),
+        "--storage-compressmin"
+    );
+    fcli!(
+        storage_spillmin,
+        matches.value_of("storagespillmin"),
+        "--storage-spillmin"
+    );
+    fcli!(
+        storage_keepexpired,
+        Flag::<true>::new(matches.is_present("storagekeepexpired")),
+        "--storage-keepexpired"
+    );
+    fcli!(
+        storage_encrypt,
+        matches.value_of("storageencrypt"),
+        "--storage-encrypt"
+    );
+    fcli!(
+        storage_keyfile,
+        matches.value_of("storagekeyfile"),
+        "--storage-keyfile"
+    );
     defset
 }
@@ -26,9 +26,8 @@
 
 use super::Configset;
 
-/// Returns the environment configuration
-pub(super) fn parse_env_config() -> Configset {
-    let mut defset = Configset::new_env();
+/// Apply the settings that were set in the environment on top of `defset`
+pub(super) fn parse_env_config(mut defset: Configset) -> Configset {
     macro_rules! fenv {
         (
             $fn:ident,
@@ -48,9 +47,32 @@ pub(super) fn parse_env_config() -> Configset {
     fenv!(protocol_settings, SKY_PROTOCOL_VERSION);
     // server settings
     fenv!(server_tcp, SKY_SYSTEM_HOST, SKY_SYSTEM_PORT);
+    fenv!(server_unixsocket, SKY_SYSTEM_UNIXSOCKET);
+    fenv!(server_httpport, SKY_SYSTEM_HTTPPORT);
+    fenv!(server_grpcport, SKY_SYSTEM_GRPCPORT);
+    fenv!(server_statusport, SKY_SYSTEM_STATUSPORT);
+    fenv!(server_compressmin, SKY_SYSTEM_COMPRESSMIN);
+    fenv!(server_maxinflight, SKY_SYSTEM_MAXINFLIGHT);
+    fenv!(server_maxoutbuf, SKY_SYSTEM_MAXOUTBUF);
+    fenv!(server_keepalive, SKY_SYSTEM_KEEPALIVE);
+    fenv!(
+        server_limits,
+        SKY_SYSTEM_MAXQUERYSIZE,
+        SKY_SYSTEM_MAXPIPELINE,
+        SKY_SYSTEM_MAXARGLEN
+    );
     fenv!(server_noart, SKY_SYSTEM_NOART);
     fenv!(server_maxcon, SKY_SYSTEM_MAXCON);
+    fenv!(
+        server_timeouts,
+        SKY_SYSTEM_IDLETIMEOUT,
+        SKY_SYSTEM_READTIMEOUT
+    );
+    fenv!(server_maxlag, SKY_SYSTEM_MAXLAG);
     fenv!(server_mode, SKY_DEPLOY_MODE);
+    // network settings
+    fenv!(network_allow, SKY_NETWORK_ALLOW);
+    fenv!(network_deny, SKY_NETWORK_DENY);
     // bgsave settings
     fenv!(bgsave_settings, SKY_BGSAVE_ENABLED, SKY_BGSAVE_DURATION);
     // snapshot settings
@@ -69,6 +91,25 @@ pub(super) fn parse_env_config() -> Configset {
         SKY_TLS_ONLY,
         SKY_TLS_PASSIN
     );
+    fenv!(tls_clientca, SKY_TLS_CLIENTCA);
     fenv!(auth_settings, SKY_AUTH_ORIGIN_KEY);
+    fenv!(auth_origin_file, SKY_AUTH_ORIGIN_FILE);
+    fenv!(auth_import, SKY_AUTH_IMPORT);
+    fenv!(auth_session_ttl, SKY_AUTH_SESSION_TTL);
+    // eviction settings
+    fenv!(eviction_settings, SKY_MEMORY_MAX, SKY_MEMORY_POLICY);
+    // aof settings
+    fenv!(aof_settings, SKY_AOF_ENABLED, SKY_AOF_FSYNC);
+    // storage settings
+    fenv!(
+        storage_settings,
+        SKY_STORAGE_COMPRESS,
+        SKY_STORAGE_COMPRESSMIN
+    );
+    fenv!(storage_spillmin, SKY_STORAGE_SPILLMIN);
+    fenv!(storage_keepexpired, SKY_STORAGE_KEEPEXPIRED);
+    fenv!(storage_encrypt, SKY_STORAGE_ENCRYPT);
+    fenv!(storage_keys, SKY_STORAGE_KEYS);
+    fenv!(storage_keyfile, SKY_STORAGE_KEYFILE);
     defset
 }
@@ -26,9 +26,11 @@
 
 use {
     super::{
-        AuthSettings, ConfigSourceParseResult, Configset, Modeset, OptString, ProtocolVersion,
+        AuthkeyWrapper, ConfigSourceParseResult, Configset, EvictionPolicy, FsyncPolicy,
+        HashScheme, LogFormat, Modeset, OptString, ProtocolVersion, StorageCipher, StorageCodec,
         TryFromConfigSource,
     },
+    crate::logging::LogModule,
     serde::Deserialize,
     std::net::IpAddr,
 };
@@ -45,7 +47,19 @@ pub struct Config {
     /// SSL configuration
     pub(super) ssl: Option<KeySslOpts>,
     /// auth settings
-    pub(super) auth: Option<AuthSettings>,
+    pub(super) auth: Option<ConfigKeyAuth>,
+    /// memory limit and eviction settings
+    pub(super) memory: Option<ConfigKeyMemory>,
+    /// rate limits
+    pub(super) ratelimit: Option<ConfigKeyRateLimit>,
+    /// IP allow/deny rules
+    pub(super) network: Option<ConfigKeyNetwork>,
+    /// append-only log settings
+    pub(super) aof: Option<ConfigKeyAof>,
+    /// storage settings
+    pub(super) storage: Option<ConfigKeyStorage>,
+    /// logging settings
+    pub(super) logging: Option<ConfigKeyLogging>,
 }
 
 /// This struct represents the `server` key in the TOML file
@@ -58,11 +72,40 @@ pub struct ConfigKeyServer {
     /// The noart key is an `Option`al boolean value which is set to true
     /// for secure environments to disable terminal artwork
     pub(super) noart: Option<bool>,
-    /// The maximum number of clients
+    /// The maximum number of clients (`maxcon` is what the documentation has always used)
+    #[serde(alias = "maxcon")]
     pub(super) maxclient: Option<usize>,
+    /// The number of seconds after which idle clients are disconnected
+    pub(super) idletimeout: Option<u64>,
+    /// The number of seconds within which a partially sent query has to be completed
+    pub(super) readtimeout: Option<u64>,
+    /// The milliseconds that a replica can lag behind its primary before it refuses reads
+    pub(super) maxlag: Option<u64>,
     /// The deployment mode
     pub(super) mode: Option<Modeset>,
     pub(super) protocol: Option<ProtocolVersion>,
+    /// The path to the Unix domain socket to listen on
+    pub(super) unixsocket: Option<String>,
+    /// The port for the HTTP gateway
+    pub(super) httpport: Option<u16>,
+    /// The port for the gRPC service
+    pub(super) grpcport: Option<u16>,
+    /// The port for the health and readiness endpoints
+    pub(super) statusport: Option<u16>,
+    /// The size from which responses are compressed
+    pub(super) compressmin: Option<usize>,
+    /// The number of bytes of a response that are written before they're flushed
+    pub(super) maxinflight: Option<usize>,
+    /// The number of bytes that can be queued for a client before it's disconnected
+    pub(super) maxoutbuf: Option<usize>,
+    /// The seconds of silence after which TCP keepalive probes are sent
+    pub(super) keepalive: Option<u64>,
+    /// The maximum number of bytes in a query
+    pub(super) maxquerysize: Option<usize>,
+    /// The maximum number of queries in a pipeline
+    pub(super) maxpipeline: Option<usize>,
+    /// The maximum number of bytes in an argument of a query
+    pub(super) maxarglen: Option<usize>,
 }
 
 /// The BGSAVE section in the config file
@@ -92,6 +135,141 @@ pub struct ConfigKeySnapshot {
     pub(super) failsafe: Option<bool>,
 }
 
+/// The memory section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyMemory {
+    /// The approximate memory limit in bytes
+    pub(super) maxmemory: Option<usize>,
+    /// The eviction policy to use once the limit is reached
+    pub(super) policy: Option<EvictionPolicy>,
+}
+
+/// The rate limit section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyRateLimit {
+    /// The queries per second that every user can run
+    pub(super) user: Option<u64>,
+    /// The queries that every user can run in a burst
+    pub(super) user_burst: Option<u64>,
+    /// The queries per second that every client IP can run
+    pub(super) ip: Option<u64>,
+    /// The queries that every client IP can run in a burst
+    pub(super) ip_burst: Option<u64>,
+}
+
+/// The network section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyNetwork {
+    /// The addresses or networks that may connect
+    pub(super) allow: Option<Vec<String>>,
+    /// The addresses or networks that may never connect
+    pub(super) deny: Option<Vec<String>>,
+}
+
+/// The AOF section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyAof {
+    /// Whether the AOF is enabled or not
+    pub(super) enabled: Option<bool>,
+    /// The fsync policy
+    pub(super) fsync: Option<FsyncPolicy>,
+}
+
+/// The storage section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyStorage {
+    /// The codec that values are compressed with
+    pub(super) compress: Option<StorageCodec>,
+    /// The size from which values are compressed
+    pub(super) compressmin: Option<usize>,
+    /// The size from which values are spilled to disk
+    pub(super) spillmin: Option<usize>,
+    /// Whether keys whose TTL has elapsed are still written to disk
+    pub(super) keepexpired: Option<bool>,
+    /// The cipher that files are encrypted with
+    pub(super) encrypt: Option<StorageCipher>,
+    /// The encryption keys
+    pub(super) keys: Option<String>,
+    /// The file that the encryption keys are read from
+    pub(super) keyfile: Option<String>,
+}
+
+/// The auth section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyAuth {
+    /// The origin key
+    pub(super) origin_key: Option<AuthkeyWrapper>,
+    /// The file that the origin key is kept in
+    pub(super) origin_file: Option<String>,
+    /// The scheme used to hash the keys of new users
+    pub(super) hash: Option<HashScheme>,
+    /// The Argon2id parameters
+    pub(super) argon2: Option<ConfigKeyArgon2>,
+    /// The scrypt parameters
+    pub(super) scrypt: Option<ConfigKeyScrypt>,
+    /// The number of minutes of inactivity after which a login lapses
+    pub(super) session_ttl: Option<u64>,
+    /// The number of seconds for which a regenerated key is still accepted
+    pub(super) rotation_grace: Option<u64>,
+    /// Failed login throttling
+    pub(super) lockout: Option<ConfigKeyLockout>,
+    /// The file that audit log entries are appended to
+    pub(super) audit_log: Option<String>,
+    /// A file with exported users that are imported at startup
+    pub(super) import: Option<String>,
+}
+
+/// The `auth.argon2` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyArgon2 {
+    pub(super) memory: Option<u32>,
+    pub(super) iterations: Option<u32>,
+    pub(super) parallelism: Option<u32>,
+}
+
+/// The `auth.scrypt` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyScrypt {
+    pub(super) log_n: Option<u8>,
+    pub(super) r: Option<u32>,
+    pub(super) p: Option<u32>,
+}
+
+/// The `auth.lockout` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyLockout {
+    pub(super) max_failures: Option<u32>,
+    pub(super) duration: Option<u64>,
+}
+
+/// The logging section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyLogging {
+    /// The format of the log records
+    pub(super) format: Option<LogFormat>,
+    /// The default log level
+    pub(super) level: Option<String>,
+    /// The log levels of specific modules
+    pub(super) modules: Option<ConfigKeyLogModules>,
+    /// The log file (records are written to `stderr` if this isn't set)
+    pub(super) file: Option<String>,
+    /// The size (in bytes) after which the log file is rotated
+    pub(super) maxsize: Option<u64>,
+    /// The number of seconds after which the log file is rotated
+    pub(super) every: Option<u64>,
+    /// The number of rotated log files to keep
+    pub(super) keep: Option<usize>,
+}
+
+/// The `logging.modules` section in the TOML file
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct ConfigKeyLogModules {
+    pub(super) protocol: Option<String>,
+    pub(super) coredb: Option<String>,
+    pub(super) diskstore: Option<String>,
+    pub(super) auth: Option<String>,
+}
+
 #[derive(Deserialize, Debug, PartialEq, Eq)]
 pub struct KeySslOpts {
     pub(super) key: String,
@@ -99,6 +277,7 @@ pub struct KeySslOpts {
     pub(super) port: u16,
     pub(super) only: Option<bool>,
     pub(super) passin: Option<String>,
+    pub(super) clientca: Option<String>,
 }
 
 /// A custom non-null type for config files
@@ -163,14 +342,22 @@ impl<T> TryFromConfigSource<T> for Optional<T> {
 
 type ConfigFile = Config;
 
-pub fn from_file(file: ConfigFile) -> Configset {
-    let mut set = Configset::new_file();
+/// Apply the settings in the configuration file on top of `set`
+pub fn from_file(mut set: Configset, file: ConfigFile) -> Configset {
+    // using a configuration file always counts as a custom configuration
+    set.mutated();
     let ConfigFile {
         server,
         bgsave,
         snapshot,
         ssl,
         auth,
+        memory,
+        ratelimit,
+        network,
+        aof,
+        storage,
+        logging,
     } = file;
     // server settings
     set.server_tcp(
@@ -179,8 +366,31 @@ pub fn from_file(file: ConfigFile) -> Configset {
         Optional::some(server.port),
         "server.port",
     );
+    set.server_unixsocket(OptString::from(server.unixsocket), "server.unixsocket");
+    set.server_httpport(Optional::from(server.httpport), "server.httpport");
+    set.server_grpcport(Optional::from(server.grpcport), "server.grpcport");
+    set.server_statusport(Optional::from(server.statusport), "server.statusport");
+    set.server_compressmin(Optional::from(server.compressmin), "server.compressmin");
+    set.server_maxinflight(Optional::from(server.maxinflight), "server.maxinflight");
+    set.server_maxoutbuf(Optional::from(server.maxoutbuf), "server.maxoutbuf");
+    set.server_keepalive(Optional::from(server.keepalive), "server.keepalive");
+    set.server_limits(
+        Optional::from(server.maxquerysize),
+        "server.maxquerysize",
+        Optional::from(server.maxpipeline),
+        "server.maxpipeline",
+        Optional::from(server.maxarglen),
+        "server.maxarglen",
+    );
     set.protocol_settings(server.protocol, "server.protocol");
     set.server_maxcon(Optional::from(server.maxclient), "server.maxcon");
+    set.server_timeouts(
+        Optional::from(server.idletimeout),
+        "server.idletimeout",
+        Optional::from(server.readtimeout),
+        "server.readtimeout",
+    );
+    set.server_maxlag(Optional::from(server.maxlag), "server.maxlag");
     set.server_noart(Optional::from(server.noart), "server.noart");
     set.server_mode(Optional::from(server.mode), "server.mode");
     // bgsave settings
@@ -217,6 +427,7 @@ pub fn from_file(file: ConfigFile) -> Configset {
             port,
             only,
             passin,
+            clientca,
         } = tls;
         set.tls_settings(
             NonNull::from(key),
@@ -230,10 +441,184 @@ pub fn from_file(file: ConfigFile) -> Configset {
             OptString::from(passin),
             "ssl.passin",
         );
+        set.tls_clientca(OptString::from(clientca), "ssl.clientca");
     }
     if let Some(auth) = auth {
-        let AuthSettings { origin_key } = auth;
-        set.auth_settings(Optional::from(origin_key), "auth.origin")
+        let ConfigKeyAuth {
+            origin_key,
+            origin_file,
+            hash,
+            argon2,
+            scrypt,
+            session_ttl,
+            rotation_grace,
+            lockout,
+            audit_log,
+            import,
+        } = auth;
+        set.auth_settings(Optional::from(origin_key), "auth.origin");
+        set.auth_origin_file(OptString::from(origin_file), "auth.origin_file");
+        set.auth_hash(Optional::from(hash), "auth.hash");
+        set.auth_session_ttl(Optional::from(session_ttl), "auth.session_ttl");
+        set.auth_rotation_grace(Optional::from(rotation_grace), "auth.rotation_grace");
+        set.auth_audit_log(OptString::from(audit_log), "auth.audit_log");
+        set.auth_import(OptString::from(import), "auth.import");
+        if let Some(argon2) = argon2 {
+            let ConfigKeyArgon2 {
+                memory,
+                iterations,
+                parallelism,
+            } = argon2;
+            set.auth_argon2(
+                Optional::from(memory),
+                "auth.argon2.memory",
+                Optional::from(iterations),
+                "auth.argon2.iterations",
+                Optional::from(parallelism),
+                "auth.argon2.parallelism",
+            );
+        }
+        if let Some(scrypt) = scrypt {
+            let ConfigKeyScrypt { log_n, r, p } = scrypt;
+            set.auth_scrypt(
+                Optional::from(log_n),
+                "auth.scrypt.log_n",
+                Optional::from(r),
+                "auth.scrypt.r",
+                Optional::from(p),
+                "auth.scrypt.p",
+            );
+        }
+        if let Some(lockout) = lockout {
+            let ConfigKeyLockout {
+                max_failures,
+                duration,
+            } = lockout;
+            set.auth_lockout(
+                Optional::from(max_failures),
+                "auth.lockout.max_failures",
+                Optional::from(duration),
+                "auth.lockout.duration",
+            );
+        }
+    }
+    // memory settings
+    if let Some(memory) = memory {
+        let ConfigKeyMemory { maxmemory, policy } = memory;
+        set.eviction_settings(
+            Optional::from(maxmemory),
+            "memory.maxmemory",
+            Optional::from(policy),
+            "memory.policy",
+        );
+    }
+    // rate limit settings
+    if let Some(ratelimit) = ratelimit {
+        let ConfigKeyRateLimit {
+            user,
+            user_burst,
+            ip,
+            ip_burst,
+        } = ratelimit;
+        set.ratelimit_settings(
+            Optional::from(user),
+            "ratelimit.user",
+            Optional::from(user_burst),
+            "ratelimit.user_burst",
+            Optional::from(ip),
+            "ratelimit.ip",
+            Optional::from(ip_burst),
+            "ratelimit.ip_burst",
+        );
+    }
+    // network settings
+    if let Some(network) = network {
+        let ConfigKeyNetwork { allow, deny } = network;
+        let allow = allow.map(|rules| rules.join(","));
+        let deny = deny.map(|rules| rules.join(","));
+        set.network_allow(allow.as_deref(), "network.allow");
+        set.network_deny(deny.as_deref(), "network.deny");
+    }
+    // aof settings
+    if let Some(aof) = aof {
+        let ConfigKeyAof { enabled, fsync } = aof;
+        set.aof_settings(
+            Optional::from(enabled),
+            "aof.enabled",
+            Optional::from(fsync),
+            "aof.fsync",
+        );
+    }
+    // storage settings
+    if let Some(storage) = storage {
+        let ConfigKeyStorage {
+            compress,
+            compressmin,
+            spillmin,
+            keepexpired,
+            encrypt,
+            keys,
+            keyfile,
+        } = storage;
+        set.storage_settings(
+            Optional::from(compress),
+            "storage.compress",
+            Optional::from(compressmin),
+            "storage.compressmin",
+        );
+        set.storage_spillmin(Optional::from(spillmin), "storage.spillmin");
+        set.storage_keepexpired(Optional::from(keepexpired), "storage.keepexpired");
+        set.storage_encrypt(Optional::from(encrypt), "storage.encrypt");
+        set.storage_keys(OptString::from(keys), "storage.keys");
+        set.storage_keyfile(OptString::from(keyfile), "storage.keyfile");
+    }
+    // logging settings
+    if let Some(logging) = logging {
+        let ConfigKeyLogging {
+            format,
+            level,
+            modules,
+            file,
+            maxsize,
+            every,
+            keep,
+        } = logging;
+        set.logging_format(Optional::from(format), "logging.format");
+        set.logging_level(LogModule::Default, level.as_deref(), "logging.level");
+        if let Some(modules) = modules {
+            let ConfigKeyLogModules {
+                protocol,
+                coredb,
+                diskstore,
+                auth,
+            } = modules;
+            set.logging_level(
+                LogModule::Protocol,
+                protocol.as_deref(),
+                "logging.modules.protocol",
+            );
+            set.logging_level(
+                LogModule::Coredb,
+                coredb.as_deref(),
+                "logging.modules.coredb",
+            );
+            set.logging_level(
+                LogModule::Diskstore,
+                diskstore.as_deref(),
+                "logging.modules.diskstore",
+            );
+            set.logging_level(LogModule::Auth, auth.as_deref(), "logging.modules.auth");
+        }
+        set.logging_file(
+            OptString::from(file),
+            "logging.file",
+            Optional::from(maxsize),
+            "logging.maxsize",
+            Optional::from(every),
+            "logging.every",
+            Optional::from(keep),
+            "logging.keep",
+        );
     }
     set
 }
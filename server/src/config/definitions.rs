@@ -26,20 +26,31 @@
 
 use {
     super::{feedback::WarningStack, DEFAULT_IPV4, DEFAULT_PORT},
-    crate::{config::AuthkeyWrapper, dbnet::MAXIMUM_CONNECTION_LIMIT},
+    crate::{
+        config::AuthkeyWrapper,
+        dbnet::{
+            compression::Codec, DEFAULT_COMPRESSMIN, DEFAULT_KEEPALIVE, DEFAULT_MAXINFLIGHT,
+            DEFAULT_MAXOUTBUF, MAXIMUM_CONNECTION_LIMIT,
+        },
+        logging::LogModule,
+        protocol::QueryLimits,
+        storage::v1::compress::DEFAULT_COMPRESSMIN as DEFAULT_STORAGE_COMPRESSMIN,
+    },
+    arc_swap::ArcSwap,
     core::{fmt, str::FromStr},
+    log::LevelFilter,
     serde::{
         de::{self, Deserializer, Visitor},
         Deserialize,
     },
-    std::net::IpAddr,
+    std::{net::IpAddr, sync::Arc, time::Duration},
 };
 
 /// The BGSAVE configuration
 ///
 /// If BGSAVE is enabled, then the duration (corresponding to `every`) is wrapped in the `Enabled`
 /// variant. Otherwise, the `Disabled` variant is to be used
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum BGSave {
     Enabled(u64),
     Disabled,
@@ -68,8 +79,688 @@ impl BGSave {
     }
 }
 
+/// The policy used to pick keys for eviction once the memory limit is reached
 #[repr(u8)]
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum EvictionPolicy {
+    /// Don't evict anything; reject new keys instead
+    NoEviction = 0,
+    /// Evict the (approximately) least recently used keys
+    Lru = 1,
+    /// Evict the (approximately) least frequently used keys
+    Lfu = 2,
+    /// Evict random keys
+    Random = 3,
+}
+
+impl EvictionPolicy {
+    pub const fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Self::Lru,
+            2 => Self::Lfu,
+            3 => Self::Random,
+            _ => Self::NoEviction,
+        }
+    }
+    /// Returns true if keys need access metadata for this policy
+    pub const fn needs_access_tracking(&self) -> bool {
+        matches!(self, Self::Lru | Self::Lfu)
+    }
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = ();
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        match st {
+            "noeviction" => Ok(Self::NoEviction),
+            "lru" => Ok(Self::Lru),
+            "lfu" => Ok(Self::Lfu),
+            "random" => Ok(Self::Random),
+            _ => Err(()),
+        }
+    }
+}
+
+struct EvictionPolicyVisitor;
+
+impl<'de> Visitor<'de> for EvictionPolicyVisitor {
+    type Value = EvictionPolicy;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expecting a string with the eviction policy")
+    }
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|_| {
+            E::custom(format!(
+                "Bad value `{value}` for eviction policy. Valid inputs: noeviction, lru, lfu, random"
+            ))
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for EvictionPolicy {
+    fn deserialize<D>(deserializer: D) -> Result<EvictionPolicy, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(EvictionPolicyVisitor)
+    }
+}
+
+/// The memory limit and eviction configuration
+#[derive(Debug, PartialEq, Eq)]
+pub struct EvictionConfig {
+    /// The approximate memory limit in bytes. `0` indicates that there is no limit
+    pub maxmemory: usize,
+    /// The eviction policy
+    pub policy: EvictionPolicy,
+}
+
+impl EvictionConfig {
+    /// Create a new eviction configuration
+    pub const fn new(maxmemory: usize, policy: EvictionPolicy) -> Self {
+        Self { maxmemory, policy }
+    }
+    /// The default eviction configuration
+    ///
+    /// Defaults:
+    /// - `maxmemory`: 0 (no limit)
+    /// - `policy`: noeviction
+    pub const fn default() -> Self {
+        Self::new(0, EvictionPolicy::NoEviction)
+    }
+    /// Check if a memory limit was set
+    pub const fn is_enabled(&self) -> bool {
+        self.maxmemory != 0
+    }
+}
+
+/// How often the append-only log is synced to the disk
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum FsyncPolicy {
+    /// Sync after every write
+    Always,
+    /// Sync once every second
+    EverySec,
+    /// Never sync explicitly and leave it to the OS
+    No,
+}
+
+impl FromStr for FsyncPolicy {
+    type Err = ();
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        match st {
+            "always" => Ok(Self::Always),
+            "everysec" => Ok(Self::EverySec),
+            "no" => Ok(Self::No),
+            _ => Err(()),
+        }
+    }
+}
+
+struct FsyncPolicyVisitor;
+
+impl<'de> Visitor<'de> for FsyncPolicyVisitor {
+    type Value = FsyncPolicy;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expecting a string with the fsync policy")
+    }
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|_| {
+            E::custom(format!(
+                "Bad value `{value}` for fsync policy. Valid inputs: always, everysec, no"
+            ))
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for FsyncPolicy {
+    fn deserialize<D>(deserializer: D) -> Result<FsyncPolicy, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(FsyncPolicyVisitor)
+    }
+}
+
+/// The append-only log configuration
+///
+/// If the AOF is enabled, then the fsync policy is wrapped in the `Enabled` variant. Otherwise,
+/// the `Disabled` variant is to be used
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum AofConfig {
+    Enabled(FsyncPolicy),
+    Disabled,
+}
+
+impl AofConfig {
+    /// Create a new AOF configuration with all the fields
+    pub const fn new(enabled: bool, fsync: FsyncPolicy) -> Self {
+        if enabled {
+            AofConfig::Enabled(fsync)
+        } else {
+            AofConfig::Disabled
+        }
+    }
+    /// The default AOF configuration
+    ///
+    /// Defaults:
+    /// - `enabled`: false
+    /// - `fsync`: everysec
+    pub const fn default() -> Self {
+        AofConfig::new(false, FsyncPolicy::EverySec)
+    }
+    /// Check if the AOF is enabled
+    pub const fn is_enabled(&self) -> bool {
+        matches!(self, Self::Enabled(_))
+    }
+}
+
+/// The codec that values are compressed with when they're written to disk
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum StorageCodec {
+    /// Values are written as they are
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl StorageCodec {
+    /// Returns the codec to compress values with (if any)
+    pub const fn codec(&self) -> Option<Codec> {
+        match self {
+            Self::None => None,
+            Self::Lz4 => Some(Codec::Lz4),
+            Self::Zstd => Some(Codec::Zstd),
+        }
+    }
+}
+
+impl FromStr for StorageCodec {
+    type Err = ();
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        match st {
+            "none" => Ok(Self::None),
+            "lz4" => Ok(Self::Lz4),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(()),
+        }
+    }
+}
+
+struct StorageCodecVisitor;
+
+impl<'de> Visitor<'de> for StorageCodecVisitor {
+    type Value = StorageCodec;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expecting a string with the compression codec")
+    }
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|_| {
+            E::custom(format!(
+                "Bad value `{value}` for compression codec. Valid inputs: none, lz4, zstd"
+            ))
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for StorageCodec {
+    fn deserialize<D>(deserializer: D) -> Result<StorageCodec, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(StorageCodecVisitor)
+    }
+}
+
+/// How values are compressed when they're written to disk. Only the values that are at least
+/// `min` bytes long are compressed
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct StorageCompression {
+    pub codec: StorageCodec,
+    pub min: usize,
+}
+
+impl StorageCompression {
+    pub const fn new(codec: StorageCodec, min: usize) -> Self {
+        Self { codec, min }
+    }
+    /// The default compression settings
+    ///
+    /// Defaults:
+    /// - `compress`: none
+    /// - `compressmin`: 1 KiB
+    pub const fn default() -> Self {
+        Self::new(StorageCodec::None, DEFAULT_STORAGE_COMPRESSMIN)
+    }
+    /// Check if values are compressed
+    pub const fn is_enabled(&self) -> bool {
+        !matches!(self.codec, StorageCodec::None)
+    }
+}
+
+/// The cipher that files are encrypted with when they're written to disk
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum StorageCipher {
+    /// Files are written as they are
+    None,
+    Aes256Gcm,
+    Chacha20Poly1305,
+}
+
+impl FromStr for StorageCipher {
+    type Err = ();
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        match st {
+            "none" => Ok(Self::None),
+            "aes-256-gcm" => Ok(Self::Aes256Gcm),
+            "chacha20-poly1305" => Ok(Self::Chacha20Poly1305),
+            _ => Err(()),
+        }
+    }
+}
+
+struct StorageCipherVisitor;
+
+impl<'de> Visitor<'de> for StorageCipherVisitor {
+    type Value = StorageCipher;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expecting a string with the encryption cipher")
+    }
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|_| {
+            E::custom(format!(
+                "Bad value `{value}` for encryption cipher. Valid inputs: none, aes-256-gcm, chacha20-poly1305"
+            ))
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for StorageCipher {
+    fn deserialize<D>(deserializer: D) -> Result<StorageCipher, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(StorageCipherVisitor)
+    }
+}
+
+/// How files are encrypted when they're written to disk. The keys are given as `<id>:<key>`
+/// entries (see [`crypt`](crate::storage::v1::crypt)), either directly (`keys`) or in a file
+/// (`keyfile`)
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct StorageEncryption {
+    pub cipher: StorageCipher,
+    pub keys: Option<String>,
+    pub keyfile: Option<String>,
+}
+
+impl StorageEncryption {
+    pub const fn new(cipher: StorageCipher, keys: Option<String>, keyfile: Option<String>) -> Self {
+        Self {
+            cipher,
+            keys,
+            keyfile,
+        }
+    }
+    /// The default encryption settings
+    ///
+    /// Defaults:
+    /// - `encrypt`: none
+    /// - `keys`, `keyfile`: none
+    pub const fn default() -> Self {
+        Self::new(StorageCipher::None, None, None)
+    }
+    /// Check if files are encrypted
+    pub const fn is_enabled(&self) -> bool {
+        !matches!(self.cipher, StorageCipher::None)
+    }
+    /// Check if any keys were given
+    pub const fn has_keys(&self) -> bool {
+        self.keys.is_some() || self.keyfile.is_some()
+    }
+}
+
+/// The format in which log records are written
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// A JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        match st {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+struct LogFormatVisitor;
+
+impl<'de> Visitor<'de> for LogFormatVisitor {
+    type Value = LogFormat;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expecting a string with the log format")
+    }
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|_| {
+            E::custom(format!(
+                "Bad value `{value}` for log format. Valid inputs: text, json"
+            ))
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for LogFormat {
+    fn deserialize<D>(deserializer: D) -> Result<LogFormat, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(LogFormatVisitor)
+    }
+}
+
+/// When log files are rotated
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct LogRotation {
+    /// the size (in bytes) beyond which the log file is rotated. `0` disables it
+    maxsize: u64,
+    /// the number of seconds after which the log file is rotated. `0` disables it
+    every: u64,
+    /// the number of rotated log files to keep
+    keep: usize,
+}
+
+impl LogRotation {
+    pub const fn new(maxsize: u64, every: u64, keep: usize) -> Self {
+        Self {
+            maxsize,
+            every,
+            keep,
+        }
+    }
+    /// The default rotation settings
+    ///
+    /// Defaults:
+    /// - `maxsize`: 0 (disabled)
+    /// - `every`: 0 (disabled)
+    /// - `keep`: 5
+    pub const fn default() -> Self {
+        Self::new(0, 0, 5)
+    }
+    pub const fn maxsize(&self) -> u64 {
+        self.maxsize
+    }
+    /// Returns the time after which the log file is rotated, if enabled
+    pub const fn every(&self) -> Option<Duration> {
+        if self.every == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.every))
+        }
+    }
+    pub const fn keep(&self) -> usize {
+        self.keep
+    }
+}
+
+/// The logging configuration
+#[derive(PartialEq, Eq, Debug)]
+pub struct LoggingConfig {
+    /// the format of the records
+    pub format: LogFormat,
+    /// the levels set for the modules (including the default level)
+    pub levels: Vec<(LogModule, LevelFilter)>,
+    /// the log file. If this isn't set, records are written to `stderr`
+    pub file: Option<String>,
+    /// the log file rotation settings
+    pub rotation: LogRotation,
+}
+
+impl LoggingConfig {
+    pub const fn new(
+        format: LogFormat,
+        levels: Vec<(LogModule, LevelFilter)>,
+        file: Option<String>,
+        rotation: LogRotation,
+    ) -> Self {
+        Self {
+            format,
+            levels,
+            file,
+            rotation,
+        }
+    }
+    /// The default logging configuration
+    ///
+    /// Defaults:
+    /// - `format`: text
+    /// - `levels`: none (the levels from `SKY_LOG` or `info` are used)
+    /// - `file`: none (`stderr`)
+    pub const fn default() -> Self {
+        Self::new(LogFormat::Text, Vec::new(), None, LogRotation::default())
+    }
+}
+
+/// The per-connection timeouts (in seconds). A timeout of zero disables it
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct ConnectionTimeouts {
+    /// clients that don't run a query for this long are disconnected by the idle reaper
+    idle: u64,
+    /// clients that don't send the rest of a partially sent query within this long are
+    /// disconnected
+    read: u64,
+}
+
+impl ConnectionTimeouts {
+    pub const fn new(idle: u64, read: u64) -> Self {
+        Self { idle, read }
+    }
+    /// The default timeouts
+    ///
+    /// Defaults:
+    /// - `idle`: 0 (disabled)
+    /// - `read`: 0 (disabled)
+    pub const fn default() -> Self {
+        Self::new(0, 0)
+    }
+    const fn as_duration(secs: u64) -> Option<Duration> {
+        if secs == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(secs))
+        }
+    }
+    /// Returns the idle timeout, if enabled
+    pub const fn idle(&self) -> Option<Duration> {
+        Self::as_duration(self.idle)
+    }
+    /// Returns the read timeout, if enabled
+    pub const fn read(&self) -> Option<Duration> {
+        Self::as_duration(self.read)
+    }
+}
+
+/// A token-bucket rate limit: `rate` queries a second, with bursts of up to `burst` queries
+/// (or `rate` queries if `burst` is zero). A rate of zero disables the limit
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct RateLimit {
+    rate: u64,
+    burst: u64,
+}
+
+impl RateLimit {
+    pub const fn new(rate: u64, burst: u64) -> Self {
+        Self { rate, burst }
+    }
+    pub const fn disabled() -> Self {
+        Self::new(0, 0)
+    }
+    pub const fn is_enabled(&self) -> bool {
+        self.rate != 0
+    }
+    /// Returns the number of queries that are allowed every second
+    pub const fn rate(&self) -> u64 {
+        self.rate
+    }
+    /// Returns the number of queries that are allowed in a burst
+    pub const fn burst(&self) -> u64 {
+        if self.burst == 0 {
+            self.rate
+        } else {
+            self.burst
+        }
+    }
+}
+
+/// The rate limits on the queries that clients run (see
+/// [`ratelimit`](crate::queryengine::ratelimit))
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct RateLimits {
+    /// the limit for every user that is logged in
+    pub user: RateLimit,
+    /// the limit for every client IP
+    pub ip: RateLimit,
+}
+
+impl RateLimits {
+    pub const fn new(user: RateLimit, ip: RateLimit) -> Self {
+        Self { user, ip }
+    }
+    /// The default rate limits
+    ///
+    /// Defaults:
+    /// - `user`: disabled
+    /// - `ip`: disabled
+    pub const fn default() -> Self {
+        Self::new(RateLimit::disabled(), RateLimit::disabled())
+    }
+}
+
+/// An IP network in CIDR notation, like `10.0.0.0/8`. A single address (without a prefix
+/// length) stands for a network with just that address
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Returns the address as an integer, along with its width in bits
+    fn bits(addr: IpAddr) -> (u128, u8) {
+        match addr {
+            IpAddr::V4(addr) => (u32::from(addr) as u128, 32),
+            IpAddr::V6(addr) => (u128::from(addr), 128),
+        }
+    }
+    /// Check if `addr` is in this network. IPv4 clients that connect over IPv6 (with an
+    /// IPv4-mapped address) are matched by their IPv4 address
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let (net, width) = Self::bits(self.addr);
+        let (addr, addr_width) = Self::bits(addr.to_canonical());
+        let host_bits = u32::from(width - self.prefix);
+        width == addr_width
+            && net.checked_shr(host_bits).unwrap_or(0) == addr.checked_shr(host_bits).unwrap_or(0)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ();
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match st.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (st, None),
+        };
+        let addr: IpAddr = addr.trim().parse().map_err(|_| ())?;
+        let width = Self::bits(addr).1;
+        let prefix = match prefix {
+            Some(prefix) => prefix.trim().parse().map_err(|_| ())?,
+            None => width,
+        };
+        if prefix > width {
+            return Err(());
+        }
+        Ok(Self { addr, prefix })
+    }
+}
+
+/// A comma separated list of [`Cidr`]s (an empty string is an empty list)
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct CidrList(pub Vec<Cidr>);
+
+impl FromStr for CidrList {
+    type Err = ();
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        st.split(',')
+            .map(str::trim)
+            .filter(|net| !net.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// What the [`IpFilter`] says about a client
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum IpVerdict {
+    Allowed,
+    /// the client is in a denied network
+    Denied,
+    /// there are allowed networks, but the client isn't in any of them
+    NotAllowed,
+}
+
+/// The networks that clients can connect from. A client is turned away if it's in one of the
+/// `deny` networks, or if there are `allow` networks and it isn't in any of them (so the deny
+/// rules win). Clients on the Unix domain socket are always let in
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IpFilter {
+    pub allow: Vec<Cidr>,
+    pub deny: Vec<Cidr>,
+}
+
+impl IpFilter {
+    pub const fn new(allow: Vec<Cidr>, deny: Vec<Cidr>) -> Self {
+        Self { allow, deny }
+    }
+    /// The default filter lets everyone in
+    pub const fn default() -> Self {
+        Self::new(Vec::new(), Vec::new())
+    }
+    /// Check if a client from `addr` can connect
+    pub fn check(&self, addr: IpAddr) -> IpVerdict {
+        if self.deny.iter().any(|net| net.contains(addr)) {
+            IpVerdict::Denied
+        } else if !self.allow.is_empty() && !self.allow.iter().any(|net| net.contains(addr)) {
+            IpVerdict::NotAllowed
+        } else {
+            IpVerdict::Allowed
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ProtocolVersion {
     V1,
     V2,
@@ -129,14 +820,55 @@ pub struct ConfigurationSet {
     pub snapshot: SnapshotConfig,
     /// Port configuration
     pub ports: PortConfig,
+    /// The path to the Unix domain socket (if any)
+    pub unixsocket: Option<String>,
+    /// The port for the HTTP gateway (if any)
+    pub httpport: Option<u16>,
+    /// The port for the gRPC service (if any)
+    pub grpcport: Option<u16>,
+    /// The port for the health and readiness endpoints (if any)
+    pub statusport: Option<u16>,
+    /// The size from which responses are compressed (zero disables compression)
+    pub compressmin: usize,
+    /// The number of bytes of a response that are written before they're flushed
+    pub maxinflight: usize,
+    /// The number of bytes that can be queued for a client before it's disconnected
+    pub maxoutbuf: usize,
+    /// The seconds of silence after which TCP keepalive probes are sent (zero disables them)
+    pub keepalive: u64,
+    /// The caps on the queries that clients can send
+    pub limits: QueryLimits,
     /// The maximum number of connections
     pub maxcon: usize,
+    /// The connection timeouts
+    pub timeouts: ConnectionTimeouts,
+    /// The milliseconds that a replica can lag behind its primary before it refuses reads
+    /// (zero tolerates any lag)
+    pub maxlag: u64,
+    /// The rate limits
+    pub ratelimits: RateLimits,
+    /// The networks that clients can connect from
+    pub ipfilter: IpFilter,
     /// The deployment mode
     pub mode: Modeset,
     /// The auth settings
     pub auth: AuthSettings,
     /// The protocol version
     pub protocol: ProtocolVersion,
+    /// The memory limit and eviction settings
+    pub eviction: EvictionConfig,
+    /// The append-only log settings
+    pub aof: AofConfig,
+    /// The compression of the values that are written to disk
+    pub storage: StorageCompression,
+    /// The size from which values are spilled to disk (zero disables spilling)
+    pub spillmin: usize,
+    /// Whether keys whose TTL has elapsed are still written to disk
+    pub keepexpired: bool,
+    /// The encryption of the files that are written to disk
+    pub encryption: StorageEncryption,
+    /// The logging settings
+    pub logging: LoggingConfig,
 }
 
 impl ConfigurationSet {
@@ -146,20 +878,60 @@ impl ConfigurationSet {
         bgsave: BGSave,
         snapshot: SnapshotConfig,
         ports: PortConfig,
+        unixsocket: Option<String>,
+        httpport: Option<u16>,
+        grpcport: Option<u16>,
+        statusport: Option<u16>,
+        compressmin: usize,
+        maxinflight: usize,
+        maxoutbuf: usize,
+        keepalive: u64,
+        limits: QueryLimits,
         maxcon: usize,
+        timeouts: ConnectionTimeouts,
+        maxlag: u64,
+        ratelimits: RateLimits,
+        ipfilter: IpFilter,
         mode: Modeset,
         auth: AuthSettings,
         protocol: ProtocolVersion,
+        eviction: EvictionConfig,
+        aof: AofConfig,
+        storage: StorageCompression,
+        spillmin: usize,
+        keepexpired: bool,
+        encryption: StorageEncryption,
+        logging: LoggingConfig,
     ) -> Self {
         Self {
             noart,
             bgsave,
             snapshot,
             ports,
+            unixsocket,
+            httpport,
+            grpcport,
+            statusport,
+            compressmin,
+            maxinflight,
+            maxoutbuf,
+            keepalive,
+            limits,
             maxcon,
+            timeouts,
+            maxlag,
+            ratelimits,
+            ipfilter,
             mode,
             auth,
             protocol,
+            eviction,
+            aof,
+            storage,
+            spillmin,
+            keepexpired,
+            encryption,
+            logging,
         }
     }
     /// Create a default `ConfigurationSet` with the following setup defaults:
@@ -169,16 +941,54 @@ impl ConfigurationSet {
     /// - `bgsave_enabled` : true
     /// - `bgsave_duration` : 120
     /// - `ssl` : disabled
+    /// - `unixsocket` : disabled
+    /// - `httpport`, `grpcport`, `statusport` : disabled
+    /// - `compressmin` : 4096
+    /// - `maxinflight` : 1 MiB
+    /// - `maxoutbuf` : 32 MiB
+    /// - `keepalive` : 300 seconds
+    /// - `maxquerysize`, `maxpipeline`, `maxarglen` : 64 MiB, 65536, 16 MiB
+    /// - `idletimeout`, `readtimeout` : disabled
+    /// - `maxlag` : 0 (any lag is tolerated)
+    /// - `ratelimit` : disabled
+    /// - `network.allow`, `network.deny` : none (everyone is let in)
+    /// - `maxmemory` : 0 (no limit)
+    /// - `aof` : disabled
+    /// - `storage.compress` : none
+    /// - `storage.spillmin` : 0 (no spilling)
+    /// - `storage.keepexpired` : false
+    /// - `storage.encrypt` : none
+    /// - `logging` : text records to `stderr`
     pub const fn default() -> Self {
         Self::new(
             false,
             BGSave::default(),
             SnapshotConfig::default(),
             PortConfig::new_insecure_only(DEFAULT_IPV4, 2003),
+            None,
+            None,
+            None,
+            None,
+            DEFAULT_COMPRESSMIN,
+            DEFAULT_MAXINFLIGHT,
+            DEFAULT_MAXOUTBUF,
+            DEFAULT_KEEPALIVE,
+            QueryLimits::default(),
             MAXIMUM_CONNECTION_LIMIT,
+            ConnectionTimeouts::default(),
+            0,
+            RateLimits::default(),
+            IpFilter::default(),
             Modeset::Dev,
             AuthSettings::default(),
             ProtocolVersion::V2,
+            EvictionConfig::default(),
+            AofConfig::default(),
+            StorageCompression::default(),
+            0,
+            false,
+            StorageEncryption::default(),
+            LoggingConfig::default(),
         )
     }
     /// Returns `false` if `noart` is enabled. Otherwise it returns `true`
@@ -187,6 +997,77 @@ impl ConfigurationSet {
     }
 }
 
+/// The settings that can be changed while the server is running, by reloading the
+/// configuration (with `SYS RELOADCONF` or a `SIGHUP`). The subsystems that use these settings
+/// read them from the shared [`ArcSwap`] every time they need them
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RuntimeConfig {
+    /// The levels set for the logging modules
+    pub levels: Vec<(LogModule, LevelFilter)>,
+    /// The BGSAVE configuration
+    pub bgsave: BGSave,
+    /// The maximum number of connections
+    pub maxcon: usize,
+    /// The connection timeouts
+    pub timeouts: ConnectionTimeouts,
+    /// The rate limits
+    pub ratelimits: RateLimits,
+    /// The networks that clients can connect from
+    pub ipfilter: IpFilter,
+    /// The milliseconds that a replica can lag behind its primary before it refuses reads
+    pub maxlag: u64,
+}
+
+impl RuntimeConfig {
+    pub const fn new(
+        levels: Vec<(LogModule, LevelFilter)>,
+        bgsave: BGSave,
+        maxcon: usize,
+        timeouts: ConnectionTimeouts,
+        ratelimits: RateLimits,
+        ipfilter: IpFilter,
+        maxlag: u64,
+    ) -> Self {
+        Self {
+            levels,
+            bgsave,
+            maxcon,
+            timeouts,
+            ratelimits,
+            ipfilter,
+            maxlag,
+        }
+    }
+    /// Take the settings that can be changed at runtime from a configuration
+    pub fn from_config(cfg: &ConfigurationSet) -> Self {
+        Self::new(
+            cfg.logging.levels.clone(),
+            cfg.bgsave,
+            cfg.maxcon,
+            cfg.timeouts,
+            cfg.ratelimits,
+            cfg.ipfilter.clone(),
+            cfg.maxlag,
+        )
+    }
+    /// The runtime settings of the default configuration
+    pub const fn default() -> Self {
+        Self::new(
+            Vec::new(),
+            BGSave::default(),
+            MAXIMUM_CONNECTION_LIMIT,
+            ConnectionTimeouts::default(),
+            RateLimits::default(),
+            IpFilter::default(),
+            0,
+        )
+    }
+    /// Wrap the settings up so that they can be shared with the subsystems that use them
+    pub fn into_shared(self) -> Arc<ArcSwap<RuntimeConfig>> {
+        Arc::new(ArcSwap::from_pointee(self))
+    }
+}
+
 /// Port configuration
 ///
 /// This enumeration determines whether the ports are:
@@ -236,18 +1117,42 @@ impl PortConfig {
             | Self::Multi { host, .. } => *host,
         }
     }
-    pub fn upgrade_to_tls(&mut self, ssl: SslOpts) {
+    /// Returns the port for plain TCP connections (if there is one)
+    pub const fn insecure_port(&self) -> Option<u16> {
         match self {
-            Self::InsecureOnly { host, port } => {
-                *self = Self::Multi {
-                    host: *host,
-                    port: *port,
-                    ssl,
-                }
-            }
-            Self::SecureOnly { .. } | Self::Multi { .. } => {
-                panic!("Port config is already upgraded to TLS")
+            Self::InsecureOnly { port, .. } | Self::Multi { port, .. } => Some(*port),
+            Self::SecureOnly { .. } => None,
+        }
+    }
+    /// Returns the port for TLS connections (if there is one)
+    pub const fn secure_port(&self) -> Option<u16> {
+        match self {
+            Self::SecureOnly { ssl, .. } | Self::Multi { ssl, .. } => Some(ssl.get_port()),
+            Self::InsecureOnly { .. } => None,
+        }
+    }
+    /// Set the host and the port for plain TCP connections, keeping the TLS settings (if any)
+    pub fn set_tcp(&mut self, nhost: IpAddr, nport: u16) {
+        match self {
+            Self::InsecureOnly { host, port } | Self::Multi { host, port, .. } => {
+                *host = nhost;
+                *port = nport;
             }
+            Self::SecureOnly { host, .. } => *host = nhost,
+        }
+    }
+    /// Accept TLS connections (along with plain TCP connections) with the given settings. If TLS
+    /// was already set up, its settings are replaced
+    pub fn upgrade_to_tls(&mut self, ssl: SslOpts) {
+        let host = self.get_host();
+        let port = self.insecure_port().unwrap_or(DEFAULT_PORT);
+        *self = Self::Multi { host, port, ssl };
+    }
+    /// Returns the TLS settings (if TLS is enabled)
+    pub fn ssl_mut(&mut self) -> Option<&mut SslOpts> {
+        match self {
+            Self::SecureOnly { ssl, .. } | Self::Multi { ssl, .. } => Some(ssl),
+            Self::InsecureOnly { .. } => None,
         }
     }
     pub const fn insecure_only(&self) -> bool {
@@ -279,6 +1184,9 @@ pub struct SslOpts {
     pub chain: String,
     pub port: u16,
     pub passfile: Option<String>,
+    /// The CA that client certificates must be issued by. If set, clients must present a
+    /// certificate and are logged in as the user it was issued to
+    pub clientca: Option<String>,
 }
 
 impl SslOpts {
@@ -288,6 +1196,7 @@ impl SslOpts {
             chain,
             port,
             passfile,
+            clientca: None,
         }
     }
     pub const fn get_port(&self) -> u16 {
@@ -344,7 +1253,35 @@ impl SnapshotConfig {
     }
 }
 
-type RestoreFile = Option<String>;
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// The point up to which the data is recovered from the latest snapshot and the AOF
+pub enum RecoveryTarget {
+    /// Replay every write in the log
+    Latest,
+    /// Replay the writes that were made at or before this time (in milliseconds since the
+    /// epoch)
+    Until(u64),
+}
+
+impl RecoveryTarget {
+    /// Returns the time up to which the writes are replayed
+    pub const fn until(&self) -> u64 {
+        match self {
+            Self::Latest => u64::MAX,
+            Self::Until(until) => *until,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+/// The one-off tasks that were requested on the command line and are run before the server
+/// starts. These aren't a part of the configuration
+pub struct StartupActions {
+    /// restore the data from the snapshot in this directory
+    pub restore: Option<String>,
+    /// recover the data from the latest snapshot and the AOF
+    pub recover: Option<RecoveryTarget>,
+}
 
 #[derive(Debug, PartialEq, Eq)]
 /// The type of configuration:
@@ -352,7 +1289,7 @@ type RestoreFile = Option<String>;
 /// - A custom supplied configuration
 pub struct ConfigType {
     pub(super) config: ConfigurationSet,
-    restore: RestoreFile,
+    pub(super) startup: StartupActions,
     is_custom: bool,
     warnings: Option<WarningStack>,
 }
@@ -360,13 +1297,13 @@ pub struct ConfigType {
 impl ConfigType {
     fn _new(
         config: ConfigurationSet,
-        restore: RestoreFile,
+        startup: StartupActions,
         is_custom: bool,
         warnings: Option<WarningStack>,
     ) -> Self {
         Self {
             config,
-            restore,
+            startup,
             is_custom,
             warnings,
         }
@@ -376,8 +1313,8 @@ impl ConfigType {
             warnings.print_warnings()
         }
     }
-    pub fn finish(self) -> (ConfigurationSet, Option<String>) {
-        (self.config, self.restore)
+    pub fn finish(self) -> (ConfigurationSet, StartupActions) {
+        (self.config, self.startup)
     }
     pub fn is_custom(&self) -> bool {
         self.is_custom
@@ -385,15 +1322,18 @@ impl ConfigType {
     pub fn is_artful(&self) -> bool {
         self.config.is_artful()
     }
+    pub fn logging(&self) -> &LoggingConfig {
+        &self.config.logging
+    }
     pub fn new_custom(
         config: ConfigurationSet,
-        restore: RestoreFile,
+        startup: StartupActions,
         warnings: WarningStack,
     ) -> Self {
-        Self::_new(config, restore, true, Some(warnings))
+        Self::_new(config, startup, true, Some(warnings))
     }
-    pub fn new_default(restore: RestoreFile) -> Self {
-        Self::_new(ConfigurationSet::default(), restore, false, None)
+    pub fn new_default(startup: StartupActions) -> Self {
+        Self::_new(ConfigurationSet::default(), startup, false, None)
     }
     /// Check if the current deploy mode is prod
     pub const fn is_prod_mode(&self) -> bool {
@@ -413,7 +1353,7 @@ impl ConfigType {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Modeset {
     Dev,
     Prod,
@@ -458,21 +1398,241 @@ impl<'de> Deserialize<'de> for Modeset {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct AuthSettings {
     pub origin_key: Option<AuthkeyWrapper>,
+    /// the file that the origin key is kept in (it's generated on first boot if the file
+    /// doesn't exist yet)
+    pub origin_file: Option<String>,
+    /// how the keys of users are hashed
+    pub hashing: KeyHashing,
+    /// the number of minutes of inactivity after which a login lapses (zero disables it)
+    pub session_ttl: u64,
+    /// the number of seconds for which a regenerated key is still accepted (zero disables it)
+    pub rotation_grace: u64,
+    /// failed login throttling
+    pub throttling: LoginThrottling,
+    /// the file that audit log entries are appended to
+    pub audit_log: Option<String>,
+    /// a file with exported users that are imported at startup
+    pub import: Option<String>,
 }
 
 impl AuthSettings {
     pub const fn default() -> Self {
-        Self { origin_key: None }
+        Self {
+            origin_key: None,
+            origin_file: None,
+            hashing: KeyHashing::default(),
+            session_ttl: 0,
+            rotation_grace: 0,
+            throttling: LoginThrottling::default(),
+            audit_log: None,
+            import: None,
+        }
     }
     #[cfg(test)]
-    pub fn new(origin: AuthkeyWrapper) -> Self {
+    pub fn new(
+        origin: AuthkeyWrapper,
+        hashing: KeyHashing,
+        session_ttl: u64,
+        rotation_grace: u64,
+        throttling: LoginThrottling,
+        audit_log: Option<String>,
+    ) -> Self {
         Self {
             origin_key: Some(origin),
+            origin_file: None,
+            hashing,
+            session_ttl,
+            rotation_grace,
+            throttling,
+            audit_log,
+            import: None,
+        }
+    }
+    /// Returns true if authn+authz is enabled (either with an origin key or an origin key file)
+    pub const fn is_enabled(&self) -> bool {
+        self.origin_key.is_some() || self.origin_file.is_some()
+    }
+    /// Returns the session TTL, if enabled
+    pub const fn session_ttl(&self) -> Option<Duration> {
+        if self.session_ttl == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.session_ttl * 60))
+        }
+    }
+    /// Returns the grace period of regenerated keys, if enabled
+    pub const fn rotation_grace(&self) -> Option<Duration> {
+        if self.rotation_grace == 0 {
+            None
+        } else {
+            Some(Duration::from_secs(self.rotation_grace))
+        }
+    }
+}
+
+/// Failed login throttling. Every failed login makes the account (and the client's IP) wait
+/// twice as long before the next attempt, and after `max_failures` failures in a row they're
+/// locked out for `lockout` seconds. Zero `max_failures` disables throttling
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct LoginThrottling {
+    max_failures: u32,
+    lockout: u64,
+}
+
+impl LoginThrottling {
+    /// The default lockout duration (in seconds)
+    pub const DEFAULT_LOCKOUT: u64 = 300;
+    pub const fn new(max_failures: u32, lockout: u64) -> Self {
+        Self {
+            max_failures,
+            lockout,
         }
     }
+    /// The default throttling settings
+    ///
+    /// Defaults:
+    /// - `max_failures`: 0 (disabled)
+    /// - `lockout`: 300 seconds
+    pub const fn default() -> Self {
+        Self::new(0, Self::DEFAULT_LOCKOUT)
+    }
+    pub const fn is_enabled(&self) -> bool {
+        self.max_failures != 0
+    }
+    pub const fn max_failures(&self) -> u32 {
+        self.max_failures
+    }
+    pub const fn lockout(&self) -> Duration {
+        Duration::from_secs(self.lockout)
+    }
+}
+
+/// The scheme used to hash the keys of new users
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum HashScheme {
+    Argon2id,
+    Scrypt,
+}
+
+impl FromStr for HashScheme {
+    type Err = ();
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        match st {
+            "argon2id" => Ok(Self::Argon2id),
+            "scrypt" => Ok(Self::Scrypt),
+            _ => Err(()),
+        }
+    }
+}
+
+struct HashSchemeVisitor;
+
+impl<'de> Visitor<'de> for HashSchemeVisitor {
+    type Value = HashScheme;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expecting a string with the hashing scheme")
+    }
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(|_| {
+            E::custom(format!(
+                "Bad value `{value}` for hashing scheme. Valid inputs: argon2id, scrypt"
+            ))
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for HashScheme {
+    fn deserialize<D>(deserializer: D) -> Result<HashScheme, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(HashSchemeVisitor)
+    }
+}
+
+/// The Argon2id parameters
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Argon2Params {
+    /// memory cost in KiB
+    pub memory: u32,
+    /// number of passes
+    pub iterations: u32,
+    /// degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Argon2Params {
+    pub const fn new(memory: u32, iterations: u32, parallelism: u32) -> Self {
+        Self {
+            memory,
+            iterations,
+            parallelism,
+        }
+    }
+    /// Defaults:
+    /// - `memory`: 19456 (19 MiB)
+    /// - `iterations`: 2
+    /// - `parallelism`: 1
+    pub const fn default() -> Self {
+        Self::new(19456, 2, 1)
+    }
+}
+
+/// The scrypt parameters
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct ScryptParams {
+    /// log2 of the CPU/memory cost
+    pub log_n: u8,
+    /// block size
+    pub r: u32,
+    /// degree of parallelism
+    pub p: u32,
+}
+
+impl ScryptParams {
+    pub const fn new(log_n: u8, r: u32, p: u32) -> Self {
+        Self { log_n, r, p }
+    }
+    /// Defaults:
+    /// - `log_n`: 15
+    /// - `r`: 8
+    /// - `p`: 1
+    pub const fn default() -> Self {
+        Self::new(15, 8, 1)
+    }
+}
+
+/// How the keys of new users are hashed. Keys hashed with another scheme (or older keys) can
+/// still be verified
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct KeyHashing {
+    pub scheme: HashScheme,
+    pub argon2: Argon2Params,
+    pub scrypt: ScryptParams,
+}
+
+impl KeyHashing {
+    pub const fn new(scheme: HashScheme, argon2: Argon2Params, scrypt: ScryptParams) -> Self {
+        Self {
+            scheme,
+            argon2,
+            scrypt,
+        }
+    }
+    /// Defaults to Argon2id with the default parameters
+    pub const fn default() -> Self {
+        Self::new(
+            HashScheme::Argon2id,
+            Argon2Params::default(),
+            ScryptParams::default(),
+        )
+    }
 }
 
 struct AuthSettingsVisitor;
@@ -53,9 +53,6 @@ impl FeedbackStack {
             feedback_source,
         }
     }
-    pub fn source(&self) -> &'static str {
-        self.feedback_source
-    }
     pub fn push(&mut self, f: impl ToString) {
         self.stack.push(f.to_string())
     }
@@ -167,7 +164,6 @@ pub enum ConfigError {
     OSError(IoError),
     CfgError(ErrorStack),
     ConfigFileParseError(TomlError),
-    Conflict,
     ProdError(ErrorStack),
 }
 
@@ -177,7 +173,6 @@ impl PartialEq for ConfigError {
             (Self::OSError(lhs), Self::OSError(rhs)) => lhs.to_string() == rhs.to_string(),
             (Self::CfgError(lhs), Self::CfgError(rhs)) => lhs == rhs,
             (Self::ConfigFileParseError(lhs), Self::ConfigFileParseError(rhs)) => lhs == rhs,
-            (Self::Conflict, Self::Conflict) => true,
             (Self::ProdError(lhs), Self::ProdError(rhs)) => lhs == rhs,
             _ => false,
         }
@@ -202,11 +197,11 @@ impl fmt::Display for ConfigError {
             Self::ConfigFileParseError(e) => write!(f, "Configuration file parse failed: {}", e),
             Self::OSError(e) => write!(f, "OS Error: {}", e),
             Self::CfgError(e) => write!(f, "{}", e),
-            Self::Conflict => write!(
+            Self::ProdError(e) => write!(
                 f,
-                "Conflict error: Either provide CLI args, environment variables or a config file for configuration"
+                "You have invalid configuration for production mode. {}",
+                e
             ),
-            Self::ProdError(e) => write!(f, "You have invalid configuration for production mode. {}", e)
         }
     }
 }
@@ -253,7 +248,7 @@ pub(super) fn evaluate_prod_settings(cfg: &ConfigurationSet) -> Result<(), Confi
     if cfg.ports.insecure_only() {
         estack.push("Either multi-socket (TCP and TLS) or TLS only must be enabled");
     }
-    if cfg.auth.origin_key.is_some() && !cfg.ports.secure_only() {
+    if cfg.auth.is_enabled() && !cfg.ports.secure_only() {
         estack.push("When authn+authz is enabled, TLS-only mode must be enabled");
     }
     check_rlimit_or_err(cfg.maxcon, &mut estack)?;
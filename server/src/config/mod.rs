@@ -25,9 +25,11 @@
 */
 
 use {
-    crate::auth::provider::Authkey,
-    clap::{load_yaml, App},
-    core::str::FromStr,
+    crate::{auth::provider::Authkey, logging::LogModule},
+    chrono::DateTime,
+    clap::{load_yaml, App, ArgMatches},
+    core::{mem, str::FromStr},
+    log::LevelFilter,
     std::{
         env::VarError,
         fs,
@@ -47,8 +49,9 @@ mod tests;
 // internal imports
 use self::cfgfile::Config as ConfigFile;
 pub use self::definitions::*;
-use self::feedback::{ConfigError, ErrorStack, WarningStack};
-use crate::dbnet::MAXIMUM_CONNECTION_LIMIT;
+pub use self::feedback::ConfigError;
+use self::feedback::{ErrorStack, WarningStack};
+use crate::protocol::QueryLimits;
 
 // server defaults
 const DEFAULT_IPV4: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
@@ -62,7 +65,7 @@ const DEFAULT_SSL_PORT: u16 = 2004;
 
 type StaticStr = &'static str;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct AuthkeyWrapper(pub Authkey);
 
 impl AuthkeyWrapper {
@@ -273,6 +276,7 @@ impl Configset {
     const EMSG_ENV: StaticStr = "Environment";
     const EMSG_CLI: StaticStr = "CLI";
     const EMSG_FILE: StaticStr = "Configuration file";
+    const EMSG_LAYERED: StaticStr = "Configuration";
 
     /// Internal ctor for a given feedback source. We do not want to expose this to avoid
     /// erroneous feedback source names
@@ -301,6 +305,11 @@ impl Configset {
             wstack: WarningStack::new(Self::EMSG_FILE),
         }
     }
+    /// Create a new configset that the settings from every source are applied to (the keys in
+    /// the feedback tell which source they came from)
+    pub fn new_layered() -> Self {
+        Self::_new(Self::EMSG_LAYERED)
+    }
     /// Mark the configset mutated
     fn mutated(&mut self) {
         self.did_mutate = true;
@@ -348,8 +357,11 @@ impl Configset {
         match new.try_parse() {
             ConfigSourceParseResult::Okay(ok) => {
                 self.mutated();
-                needs_error = !validation_fn(&ok);
-                *target = ok;
+                if validation_fn(&ok) {
+                    *target = ok;
+                } else {
+                    needs_error = true;
+                }
             }
             ConfigSourceParseResult::ParseFailure => {
                 self.mutated();
@@ -361,36 +373,41 @@ impl Configset {
             self.epush(field_key, expected)
         }
     }
-    /// This method can be used to chain configurations to ultimately return the first modified configuration
-    /// that occurs. For example: `cfg_file.and_then(cfg_cli).and_then(cfg_env)`; it will return the first
-    /// modified Configset
-    ///
-    /// ## Panics
-    /// This method will panic if both the provided sets are mutated. Hence, you need to check beforehand that
-    /// there is no conflict
-    pub fn and_then(self, other: Self) -> Self {
-        if self.is_mutated() {
-            if other.is_mutated() {
-                panic!(
-                    "Double mutation: {env_a} and {env_b}",
-                    env_a = self.estack.source(),
-                    env_b = other.estack.source()
-                );
-            }
-            self
-        } else {
-            other
-        }
-    }
     /// Turns self into a Result that can be used by config::get_config()
-    pub fn into_result(self, restore_file: Option<String>) -> Result<ConfigType, ConfigError> {
+    pub fn into_result(mut self, startup: StartupActions) -> Result<ConfigType, ConfigError> {
+        if startup.recover.is_some()
+            && (matches!(self.cfg.aof, AofConfig::Disabled)
+                || matches!(self.cfg.snapshot, SnapshotConfig::Disabled))
+        {
+            self.estack
+                .push("A recovery needs both the append-only log and snapshots to be enabled");
+        }
+        if self.cfg.encryption.is_enabled() && !self.cfg.encryption.has_keys() {
+            self.estack.push(
+                "Encrypting files needs either `storage.keys` or `storage.keyfile` to be set",
+            );
+        }
+        if self.cfg.encryption.is_enabled() && self.cfg.spillmin != 0 {
+            // the spilled values are mapped into memory as they are, so they can't be encrypted
+            self.estack
+                .push("Spilling values to disk can't be used along with encryption");
+        }
+        if self.cfg.auth.origin_key.is_some() && self.cfg.auth.origin_file.is_some() {
+            self.estack
+                .push("Only one of `auth.origin_key` and `auth.origin_file` can be set");
+        }
+        if self.cfg.auth.import.is_some() && !self.cfg.auth.is_enabled() {
+            self.estack.push(
+                "Importing users needs either `auth.origin_key` or `auth.origin_file` to be set",
+            );
+        }
         let mut target = if self.is_okay() {
             // no errors, sweet
             if self.is_mutated() {
                 let Self { cfg, wstack, .. } = self;
-                ConfigType::new_custom(cfg, restore_file, wstack)
+                ConfigType::new_custom(cfg, startup, wstack)
             } else {
-                ConfigType::new_default(restore_file)
+                ConfigType::new_default(startup)
             }
         } else {
             return Err(ConfigError::CfgError(self.estack));
@@ -418,7 +435,7 @@ impl Configset {
         nproto: impl TryFromConfigSource<ProtocolVersion>,
         nproto_key: StaticStr,
     ) {
-        let mut proto = ProtocolVersion::default();
+        let mut proto = self.cfg.protocol;
         self.try_mutate(
             nproto,
             &mut proto,
@@ -438,14 +455,153 @@ impl Configset {
         nport: impl TryFromConfigSource<u16>,
         nport_key: StaticStr,
     ) {
-        let mut host = DEFAULT_IPV4;
-        let mut port = DEFAULT_PORT;
+        if !nhost.is_present() && !nport.is_present() {
+            return;
+        }
+        let mut host = self.cfg.ports.get_host();
+        let mut port = self.cfg.ports.insecure_port().unwrap_or(DEFAULT_PORT);
         self.try_mutate(nhost, &mut host, nhost_key, "an IPv4/IPv6 address");
         self.try_mutate(nport, &mut port, nport_key, "a 16-bit positive integer");
-        self.cfg.ports = PortConfig::new_insecure_only(host, port);
+        self.cfg.ports.set_tcp(host, port);
+    }
+    pub fn server_unixsocket(
+        &mut self,
+        nsocket: impl TryFromConfigSource<OptString>,
+        nsocket_key: StaticStr,
+    ) {
+        let mut socket = OptString::from(self.cfg.unixsocket.take());
+        self.try_mutate(nsocket, &mut socket, nsocket_key, "path to a Unix socket");
+        self.cfg.unixsocket = socket.base;
+    }
+    pub fn server_httpport(&mut self, nport: impl TryFromConfigSource<u16>, nport_key: StaticStr) {
+        let mut port = self.cfg.httpport.unwrap_or(0);
+        self.try_mutate(
+            nport,
+            &mut port,
+            nport_key,
+            "a 16-bit positive integer (or zero to disable)",
+        );
+        self.cfg.httpport = if port == 0 { None } else { Some(port) };
+    }
+    pub fn server_grpcport(&mut self, nport: impl TryFromConfigSource<u16>, nport_key: StaticStr) {
+        let mut port = self.cfg.grpcport.unwrap_or(0);
+        self.try_mutate(
+            nport,
+            &mut port,
+            nport_key,
+            "a 16-bit positive integer (or zero to disable)",
+        );
+        self.cfg.grpcport = if port == 0 { None } else { Some(port) };
+    }
+    pub fn server_statusport(
+        &mut self,
+        nport: impl TryFromConfigSource<u16>,
+        nport_key: StaticStr,
+    ) {
+        let mut port = self.cfg.statusport.unwrap_or(0);
+        self.try_mutate(
+            nport,
+            &mut port,
+            nport_key,
+            "a 16-bit positive integer (or zero to disable)",
+        );
+        self.cfg.statusport = if port == 0 { None } else { Some(port) };
+    }
+    pub fn server_compressmin(
+        &mut self,
+        ncompressmin: impl TryFromConfigSource<usize>,
+        ncompressmin_key: StaticStr,
+    ) {
+        let mut compressmin = self.cfg.compressmin;
+        self.try_mutate(
+            ncompressmin,
+            &mut compressmin,
+            ncompressmin_key,
+            "a positive integer (or zero to disable compression)",
+        );
+        self.cfg.compressmin = compressmin;
+    }
+    pub fn server_maxinflight(
+        &mut self,
+        nmaxinflight: impl TryFromConfigSource<usize>,
+        nmaxinflight_key: StaticStr,
+    ) {
+        let mut maxinflight = self.cfg.maxinflight;
+        self.try_mutate_with_condcheck(
+            nmaxinflight,
+            &mut maxinflight,
+            nmaxinflight_key,
+            "a positive integer greater than zero",
+            |max| *max > 0,
+        );
+        self.cfg.maxinflight = maxinflight;
+    }
+    pub fn server_maxoutbuf(
+        &mut self,
+        nmaxoutbuf: impl TryFromConfigSource<usize>,
+        nmaxoutbuf_key: StaticStr,
+    ) {
+        let mut maxoutbuf = self.cfg.maxoutbuf;
+        self.try_mutate_with_condcheck(
+            nmaxoutbuf,
+            &mut maxoutbuf,
+            nmaxoutbuf_key,
+            "a positive integer greater than zero",
+            |max| *max > 0,
+        );
+        self.cfg.maxoutbuf = maxoutbuf;
+    }
+    pub fn server_keepalive(
+        &mut self,
+        nkeepalive: impl TryFromConfigSource<u64>,
+        nkeepalive_key: StaticStr,
+    ) {
+        let mut keepalive = self.cfg.keepalive;
+        self.try_mutate(
+            nkeepalive,
+            &mut keepalive,
+            nkeepalive_key,
+            "a positive integer in seconds (or zero to disable keepalive)",
+        );
+        self.cfg.keepalive = keepalive;
+    }
+    pub fn server_limits(
+        &mut self,
+        nquerysize: impl TryFromConfigSource<usize>,
+        nquerysize_key: StaticStr,
+        npipeline: impl TryFromConfigSource<usize>,
+        npipeline_key: StaticStr,
+        narglen: impl TryFromConfigSource<usize>,
+        narglen_key: StaticStr,
+    ) {
+        let mut querysize = self.cfg.limits.max_query_size();
+        let mut pipeline = self.cfg.limits.max_pipeline();
+        let mut arglen = self.cfg.limits.max_arg_len();
+        self.try_mutate_with_condcheck(
+            nquerysize,
+            &mut querysize,
+            nquerysize_key,
+            "a positive integer greater than zero",
+            |max| *max > 0,
+        );
+        self.try_mutate_with_condcheck(
+            npipeline,
+            &mut pipeline,
+            npipeline_key,
+            "a positive integer greater than zero",
+            |max| *max > 0,
+        );
+        self.try_mutate_with_condcheck(
+            narglen,
+            &mut arglen,
+            narglen_key,
+            "a positive integer greater than zero",
+            |max| *max > 0,
+        );
+        self.cfg.limits = QueryLimits::new(querysize, pipeline, arglen);
     }
     pub fn server_noart(&mut self, nart: impl TryFromConfigSource<bool>, nart_key: StaticStr) {
-        let mut noart = false;
+        let mut noart = self.cfg.noart;
         self.try_mutate(nart, &mut noart, nart_key, "true/false");
         self.cfg.noart = noart;
     }
@@ -454,7 +610,7 @@ impl Configset {
         nmaxcon: impl TryFromConfigSource<usize>,
         nmaxcon_key: StaticStr,
     ) {
-        let mut maxcon = MAXIMUM_CONNECTION_LIMIT;
+        let mut maxcon = self.cfg.maxcon;
         self.try_mutate_with_condcheck(
             nmaxcon,
             &mut maxcon,
@@ -464,8 +620,45 @@ impl Configset {
         );
         self.cfg.maxcon = maxcon;
     }
+    pub fn server_timeouts(
+        &mut self,
+        nidle: impl TryFromConfigSource<u64>,
+        nidle_key: StaticStr,
+        nread: impl TryFromConfigSource<u64>,
+        nread_key: StaticStr,
+    ) {
+        let mut idle = self.cfg.timeouts.idle().map_or(0, |idle| idle.as_secs());
+        let mut read = self.cfg.timeouts.read().map_or(0, |read| read.as_secs());
+        self.try_mutate(
+            nidle,
+            &mut idle,
+            nidle_key,
+            "a positive integer (or zero to disable)",
+        );
+        self.try_mutate(
+            nread,
+            &mut read,
+            nread_key,
+            "a positive integer (or zero to disable)",
+        );
+        self.cfg.timeouts = ConnectionTimeouts::new(idle, read);
+    }
+    pub fn server_maxlag(
+        &mut self,
+        nmaxlag: impl TryFromConfigSource<u64>,
+        nmaxlag_key: StaticStr,
+    ) {
+        let mut maxlag = self.cfg.maxlag;
+        self.try_mutate(
+            nmaxlag,
+            &mut maxlag,
+            nmaxlag_key,
+            "a positive integer in milliseconds (or zero to disable)",
+        );
+        self.cfg.maxlag = maxlag;
+    }
     pub fn server_mode(&mut self, nmode: impl TryFromConfigSource<Modeset>, nmode_key: StaticStr) {
-        let mut modeset = Modeset::Dev;
+        let mut modeset = self.cfg.mode;
         self.try_mutate(
             nmode,
             &mut modeset,
@@ -485,8 +678,13 @@ impl Configset {
         nduration: impl TryFromConfigSource<u64>,
         nduration_key: StaticStr,
     ) {
-        let mut enabled = true;
-        let mut duration = DEFAULT_BGSAVE_DURATION;
+        if !nenabled.is_present() && !nduration.is_present() {
+            return;
+        }
+        let (mut enabled, mut duration) = match self.cfg.bgsave {
+            BGSave::Enabled(duration) => (true, duration),
+            BGSave::Disabled => (false, DEFAULT_BGSAVE_DURATION),
+        };
         let has_custom_duration = nduration.is_present();
         self.try_mutate(nenabled, &mut enabled, nenabled_key, "true/false");
         self.try_mutate_with_condcheck(
@@ -496,9 +694,8 @@ impl Configset {
             "a positive integer greater than zero",
             |dur| *dur > 0,
         );
-        if enabled {
-            self.cfg.bgsave = BGSave::Enabled(duration);
-        } else {
+        self.cfg.bgsave = BGSave::new(enabled, duration);
+        if !enabled {
             if has_custom_duration {
                 self.wstack.push(format!(
                     "Specifying `{nduration_key}` is useless when BGSAVE is disabled"
@@ -566,6 +763,248 @@ impl Configset {
     }
 }
 
+// eviction settings
+impl Configset {
+    pub fn eviction_settings(
+        &mut self,
+        nmaxmemory: impl TryFromConfigSource<usize>,
+        nmaxmemory_key: StaticStr,
+        npolicy: impl TryFromConfigSource<EvictionPolicy>,
+        npolicy_key: StaticStr,
+    ) {
+        let mut maxmemory = self.cfg.eviction.maxmemory;
+        let mut policy = self.cfg.eviction.policy;
+        let has_custom_policy = npolicy.is_present();
+        self.try_mutate(
+            nmaxmemory,
+            &mut maxmemory,
+            nmaxmemory_key,
+            "a positive integer (bytes). 0 indicates that there is no limit",
+        );
+        self.try_mutate(
+            npolicy,
+            &mut policy,
+            npolicy_key,
+            "one of 'noeviction', 'lru', 'lfu' or 'random'",
+        );
+        if maxmemory == 0 && has_custom_policy {
+            self.wstack.push(format!(
+                "Specifying `{npolicy_key}` is useless when no memory limit is set"
+            ));
+        }
+        self.cfg.eviction = EvictionConfig::new(maxmemory, policy);
+    }
+}
+
+// rate limit settings
+#[allow(clippy::too_many_arguments)]
+impl Configset {
+    pub fn ratelimit_settings(
+        &mut self,
+        nuser: impl TryFromConfigSource<u64>,
+        nuser_key: StaticStr,
+        nuser_burst: impl TryFromConfigSource<u64>,
+        nuser_burst_key: StaticStr,
+        nip: impl TryFromConfigSource<u64>,
+        nip_key: StaticStr,
+        nip_burst: impl TryFromConfigSource<u64>,
+        nip_burst_key: StaticStr,
+    ) {
+        let RateLimits { user, ip } = self.cfg.ratelimits;
+        let (mut user, mut user_burst) = (user.rate(), user.burst());
+        let (mut ip, mut ip_burst) = (ip.rate(), ip.burst());
+        self.try_mutate(
+            nuser,
+            &mut user,
+            nuser_key,
+            "a positive integer in queries per second (or zero to disable)",
+        );
+        self.try_mutate(
+            nuser_burst,
+            &mut user_burst,
+            nuser_burst_key,
+            "a positive integer (or zero to use the rate)",
+        );
+        self.try_mutate(
+            nip,
+            &mut ip,
+            nip_key,
+            "a positive integer in queries per second (or zero to disable)",
+        );
+        self.try_mutate(
+            nip_burst,
+            &mut ip_burst,
+            nip_burst_key,
+            "a positive integer (or zero to use the rate)",
+        );
+        self.cfg.ratelimits = RateLimits::new(
+            RateLimit::new(user, user_burst),
+            RateLimit::new(ip, ip_burst),
+        );
+    }
+}
+
+// network settings
+impl Configset {
+    pub fn network_allow(
+        &mut self,
+        nallow: impl TryFromConfigSource<CidrList>,
+        nallow_key: StaticStr,
+    ) {
+        let mut allow = CidrList(mem::take(&mut self.cfg.ipfilter.allow));
+        self.try_mutate(
+            nallow,
+            &mut allow,
+            nallow_key,
+            "a comma separated list of IP addresses or networks (like 10.0.0.0/8)",
+        );
+        self.cfg.ipfilter.allow = allow.0;
+    }
+    pub fn network_deny(
+        &mut self,
+        ndeny: impl TryFromConfigSource<CidrList>,
+        ndeny_key: StaticStr,
+    ) {
+        let mut deny = CidrList(mem::take(&mut self.cfg.ipfilter.deny));
+        self.try_mutate(
+            ndeny,
+            &mut deny,
+            ndeny_key,
+            "a comma separated list of IP addresses or networks (like 10.0.0.0/8)",
+        );
+        self.cfg.ipfilter.deny = deny.0;
+    }
+}
+
+// AOF settings
+impl Configset {
+    pub fn aof_settings(
+        &mut self,
+        nenabled: impl TryFromConfigSource<bool>,
+        nenabled_key: StaticStr,
+        nfsync: impl TryFromConfigSource<FsyncPolicy>,
+        nfsync_key: StaticStr,
+    ) {
+        let (mut enabled, mut fsync) = match self.cfg.aof {
+            AofConfig::Enabled(fsync) => (true, fsync),
+            AofConfig::Disabled => (false, FsyncPolicy::EverySec),
+        };
+        let has_custom_fsync = nfsync.is_present();
+        self.try_mutate(nenabled, &mut enabled, nenabled_key, "true/false");
+        self.try_mutate(
+            nfsync,
+            &mut fsync,
+            nfsync_key,
+            "one of 'always', 'everysec' or 'no'",
+        );
+        if !enabled && has_custom_fsync {
+            self.wstack.push(format!(
+                "Specifying `{nfsync_key}` is useless when the AOF is disabled"
+            ));
+        }
+        self.cfg.aof = AofConfig::new(enabled, fsync);
+    }
+}
+
+// storage settings
+impl Configset {
+    pub fn storage_settings(
+        &mut self,
+        ncompress: impl TryFromConfigSource<StorageCodec>,
+        ncompress_key: StaticStr,
+        ncompressmin: impl TryFromConfigSource<usize>,
+        ncompressmin_key: StaticStr,
+    ) {
+        let StorageCompression { mut codec, mut min } = self.cfg.storage;
+        let has_custom_min = ncompressmin.is_present();
+        self.try_mutate(
+            ncompress,
+            &mut codec,
+            ncompress_key,
+            "one of 'none', 'lz4' or 'zstd'",
+        );
+        self.try_mutate_with_condcheck(
+            ncompressmin,
+            &mut min,
+            ncompressmin_key,
+            "a positive integer",
+            |min| *min != 0,
+        );
+        let storage = StorageCompression::new(codec, min);
+        if !storage.is_enabled() && has_custom_min {
+            self.wstack.push(format!(
+                "Specifying `{ncompressmin_key}` is useless when compression is disabled"
+            ));
+        }
+        self.cfg.storage = storage;
+    }
+    pub fn storage_spillmin(
+        &mut self,
+        nspillmin: impl TryFromConfigSource<usize>,
+        nspillmin_key: StaticStr,
+    ) {
+        let mut spillmin = self.cfg.spillmin;
+        self.try_mutate(
+            nspillmin,
+            &mut spillmin,
+            nspillmin_key,
+            "a positive integer (or zero to disable spilling)",
+        );
+        self.cfg.spillmin = spillmin;
+    }
+    pub fn storage_keepexpired(
+        &mut self,
+        nkeepexpired: impl TryFromConfigSource<bool>,
+        nkeepexpired_key: StaticStr,
+    ) {
+        let mut keepexpired = self.cfg.keepexpired;
+        self.try_mutate(
+            nkeepexpired,
+            &mut keepexpired,
+            nkeepexpired_key,
+            "true/false",
+        );
+        self.cfg.keepexpired = keepexpired;
+    }
+    pub fn storage_encrypt(
+        &mut self,
+        ncipher: impl TryFromConfigSource<StorageCipher>,
+        ncipher_key: StaticStr,
+    ) {
+        let mut cipher = self.cfg.encryption.cipher;
+        self.try_mutate(
+            ncipher,
+            &mut cipher,
+            ncipher_key,
+            "one of 'none', 'aes-256-gcm' or 'chacha20-poly1305'",
+        );
+        self.cfg.encryption.cipher = cipher;
+    }
+    pub fn storage_keys(
+        &mut self,
+        nkeys: impl TryFromConfigSource<OptString>,
+        nkeys_key: StaticStr,
+    ) {
+        let mut keys = OptString::from(self.cfg.encryption.keys.take());
+        self.try_mutate(nkeys, &mut keys, nkeys_key, "a list of encryption keys");
+        self.cfg.encryption.keys = keys.base;
+    }
+    pub fn storage_keyfile(
+        &mut self,
+        nkeyfile: impl TryFromConfigSource<OptString>,
+        nkeyfile_key: StaticStr,
+    ) {
+        let mut keyfile = OptString::from(self.cfg.encryption.keyfile.take());
+        self.try_mutate(
+            nkeyfile,
+            &mut keyfile,
+            nkeyfile_key,
+            "a path to the encryption key file",
+        );
+        self.cfg.encryption.keyfile = keyfile.base;
+    }
+}
+
 // TLS settings
 #[allow(clippy::too_many_arguments)]
 impl Configset {
@@ -591,11 +1030,11 @@ impl Configset {
                 self.try_mutate(ncert, &mut cert, ncert_key, "path to TLS certificate file");
 
                 // now get port info
-                let mut port = DEFAULT_SSL_PORT;
+                let mut port = self.cfg.ports.secure_port().unwrap_or(DEFAULT_SSL_PORT);
                 self.try_mutate(nport, &mut port, nport_key, "a positive 16-bit integer");
 
                 // now check if TLS only
-                let mut tls_only = false;
+                let mut tls_only = self.cfg.ports.secure_only();
                 self.try_mutate(nonly, &mut tls_only, nonly_key, "true/false");
 
                 // check if we have a TLS cert
@@ -607,7 +1046,9 @@ impl Configset {
                     "path to TLS cert passphrase",
                 );
 
-                let sslopts = SslOpts::new(key, cert, port, tls_pass.base);
+                let mut sslopts = SslOpts::new(key, cert, port, tls_pass.base);
+                // the client CA is set on its own, so keep it
+                sslopts.clientca = self.cfg.ports.ssl_mut().and_then(|ssl| ssl.clientca.take());
                 // now check if TLS only
                 if tls_only {
                     let host = self.cfg.ports.get_host();
@@ -645,6 +1086,112 @@ impl Configset {
             }
         }
     }
+    pub fn tls_clientca(
+        &mut self,
+        nclientca: impl TryFromConfigSource<OptString>,
+        nclientca_key: StaticStr,
+    ) {
+        if !nclientca.is_present() {
+            return;
+        }
+        match self.cfg.ports.ssl_mut().map(|ssl| ssl.clientca.take()) {
+            Some(clientca) => {
+                let mut clientca = OptString::from(clientca);
+                self.try_mutate(
+                    nclientca,
+                    &mut clientca,
+                    nclientca_key,
+                    "path to the CA certificate for client certificates",
+                );
+                if let Some(ssl) = self.cfg.ports.ssl_mut() {
+                    ssl.clientca = clientca.base;
+                }
+            }
+            None => {
+                self.mutated();
+                self.wstack.push(format!(
+                    "Specifying `{nclientca_key}` is pointless when TLS is disabled"
+                ));
+            }
+        }
+    }
+}
+
+// logging settings
+impl Configset {
+    pub fn logging_format(
+        &mut self,
+        nformat: impl TryFromConfigSource<LogFormat>,
+        nformat_key: StaticStr,
+    ) {
+        let mut format = self.cfg.logging.format;
+        self.try_mutate(nformat, &mut format, nformat_key, "one of 'text' or 'json'");
+        self.cfg.logging.format = format;
+    }
+    pub fn logging_level(
+        &mut self,
+        module: LogModule,
+        nlevel: impl TryFromConfigSource<LevelFilter>,
+        nlevel_key: StaticStr,
+    ) {
+        let mut level = LevelFilter::Info;
+        let has_level = nlevel.is_present();
+        self.try_mutate(
+            nlevel,
+            &mut level,
+            nlevel_key,
+            "one of 'off', 'error', 'warn', 'info', 'debug' or 'trace'",
+        );
+        if has_level {
+            self.cfg.logging.levels.push((module, level));
+        }
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn logging_file(
+        &mut self,
+        nfile: OptString,
+        nfile_key: StaticStr,
+        nmaxsize: impl TryFromConfigSource<u64>,
+        nmaxsize_key: StaticStr,
+        nevery: impl TryFromConfigSource<u64>,
+        nevery_key: StaticStr,
+        nkeep: impl TryFromConfigSource<usize>,
+        nkeep_key: StaticStr,
+    ) {
+        let rotation = self.cfg.logging.rotation;
+        let mut file = OptString::from(self.cfg.logging.file.take());
+        let mut maxsize = rotation.maxsize();
+        let mut every = rotation.every().map_or(0, |every| every.as_secs());
+        let mut keep = rotation.keep();
+        let has_rotation = nmaxsize.is_present() || nevery.is_present() || nkeep.is_present();
+        self.try_mutate(nfile, &mut file, nfile_key, "a path to the log file");
+        self.try_mutate(
+            nmaxsize,
+            &mut maxsize,
+            nmaxsize_key,
+            "a positive integer (bytes). 0 disables size based rotation",
+        );
+        self.try_mutate(
+            nevery,
+            &mut every,
+            nevery_key,
+            "a positive integer (seconds). 0 disables time based rotation",
+        );
+        self.try_mutate_with_condcheck(
+            nkeep,
+            &mut keep,
+            nkeep_key,
+            "a positive integer greater than 0",
+            |keep| *keep != 0,
+        );
+        if file.base.is_none() && has_rotation {
+            self.wstack.push(format!(
+                "Specifying log rotation settings is useless when `{nfile_key}` isn't set"
+            ));
+        }
+        self.cfg.logging.file = file.base;
+        self.cfg.logging.rotation = LogRotation::new(maxsize, every, keep);
+    }
 }
 
 // Auth settings
@@ -657,47 +1204,226 @@ impl Configset {
         let mut def = AuthkeyWrapper::empty();
         self.try_mutate(nauth, &mut def, nauth_key, "A 40-byte long ASCII string");
         if def != AuthkeyWrapper::empty() {
-            self.cfg.auth = AuthSettings {
-                origin_key: Some(def),
-            };
+            self.cfg.auth.origin_key = Some(def);
         }
     }
+    pub fn auth_origin_file(
+        &mut self,
+        norigin_file: impl TryFromConfigSource<OptString>,
+        norigin_file_key: StaticStr,
+    ) {
+        let mut origin_file = OptString::from(self.cfg.auth.origin_file.take());
+        self.try_mutate(
+            norigin_file,
+            &mut origin_file,
+            norigin_file_key,
+            "a path to the origin key file",
+        );
+        self.cfg.auth.origin_file = origin_file.base;
+    }
+    pub fn auth_session_ttl(
+        &mut self,
+        nsession_ttl: impl TryFromConfigSource<u64>,
+        nsession_ttl_key: StaticStr,
+    ) {
+        let mut session_ttl = self.cfg.auth.session_ttl;
+        self.try_mutate(
+            nsession_ttl,
+            &mut session_ttl,
+            nsession_ttl_key,
+            "a positive integer in minutes (or zero to disable)",
+        );
+        self.cfg.auth.session_ttl = session_ttl;
+    }
+    pub fn auth_rotation_grace(
+        &mut self,
+        nrotation_grace: impl TryFromConfigSource<u64>,
+        nrotation_grace_key: StaticStr,
+    ) {
+        let mut rotation_grace = self.cfg.auth.rotation_grace;
+        self.try_mutate(
+            nrotation_grace,
+            &mut rotation_grace,
+            nrotation_grace_key,
+            "a positive integer in seconds (or zero to disable)",
+        );
+        self.cfg.auth.rotation_grace = rotation_grace;
+    }
+    pub fn auth_audit_log(
+        &mut self,
+        naudit_log: impl TryFromConfigSource<OptString>,
+        naudit_log_key: StaticStr,
+    ) {
+        let mut audit_log = OptString::from(self.cfg.auth.audit_log.take());
+        self.try_mutate(
+            naudit_log,
+            &mut audit_log,
+            naudit_log_key,
+            "a path to the audit log file",
+        );
+        self.cfg.auth.audit_log = audit_log.base;
+    }
+    pub fn auth_import(
+        &mut self,
+        nimport: impl TryFromConfigSource<OptString>,
+        nimport_key: StaticStr,
+    ) {
+        let mut import = OptString::from(self.cfg.auth.import.take());
+        self.try_mutate(
+            nimport,
+            &mut import,
+            nimport_key,
+            "a path to a file with exported users",
+        );
+        self.cfg.auth.import = import.base;
+    }
+    pub fn auth_lockout(
+        &mut self,
+        nmax_failures: impl TryFromConfigSource<u32>,
+        nmax_failures_key: StaticStr,
+        nduration: impl TryFromConfigSource<u64>,
+        nduration_key: StaticStr,
+    ) {
+        let throttling = self.cfg.auth.throttling;
+        let mut max_failures = throttling.max_failures();
+        let mut duration = throttling.lockout().as_secs();
+        self.try_mutate(
+            nmax_failures,
+            &mut max_failures,
+            nmax_failures_key,
+            "a positive integer (or zero to disable)",
+        );
+        self.try_mutate_with_condcheck(
+            nduration,
+            &mut duration,
+            nduration_key,
+            "a positive integer in seconds greater than 0",
+            |duration| *duration != 0,
+        );
+        self.cfg.auth.throttling = LoginThrottling::new(max_failures, duration);
+    }
+    pub fn auth_hash(
+        &mut self,
+        nscheme: impl TryFromConfigSource<HashScheme>,
+        nscheme_key: StaticStr,
+    ) {
+        let mut scheme = self.cfg.auth.hashing.scheme;
+        self.try_mutate(nscheme, &mut scheme, nscheme_key, "argon2id or scrypt");
+        self.cfg.auth.hashing.scheme = scheme;
+    }
+    pub fn auth_argon2(
+        &mut self,
+        nmemory: impl TryFromConfigSource<u32>,
+        nmemory_key: StaticStr,
+        niterations: impl TryFromConfigSource<u32>,
+        niterations_key: StaticStr,
+        nparallelism: impl TryFromConfigSource<u32>,
+        nparallelism_key: StaticStr,
+    ) {
+        let Argon2Params {
+            mut memory,
+            mut iterations,
+            mut parallelism,
+        } = self.cfg.auth.hashing.argon2;
+        self.try_mutate_with_condcheck(
+            niterations,
+            &mut iterations,
+            niterations_key,
+            "a positive integer greater than 0",
+            |iterations| *iterations != 0,
+        );
+        self.try_mutate_with_condcheck(
+            nparallelism,
+            &mut parallelism,
+            nparallelism_key,
+            "a positive integer between 1 and 16777215",
+            |parallelism| (1..(1 << 24)).contains(parallelism),
+        );
+        self.try_mutate_with_condcheck(
+            nmemory,
+            &mut memory,
+            nmemory_key,
+            "a positive integer (KiB) that is at least 8 times the parallelism",
+            |memory| *memory >= 8 * parallelism,
+        );
+        self.cfg.auth.hashing.argon2 = Argon2Params::new(memory, iterations, parallelism);
+    }
+    pub fn auth_scrypt(
+        &mut self,
+        nlog_n: impl TryFromConfigSource<u8>,
+        nlog_n_key: StaticStr,
+        nr: impl TryFromConfigSource<u32>,
+        nr_key: StaticStr,
+        np: impl TryFromConfigSource<u32>,
+        np_key: StaticStr,
+    ) {
+        let ScryptParams {
+            mut log_n,
+            mut r,
+            mut p,
+        } = self.cfg.auth.hashing.scrypt;
+        self.try_mutate_with_condcheck(
+            nlog_n,
+            &mut log_n,
+            nlog_n_key,
+            "a positive integer between 1 and 63",
+            |log_n| (1..64).contains(log_n),
+        );
+        self.try_mutate_with_condcheck(
+            nr,
+            &mut r,
+            nr_key,
+            "a positive integer greater than 0",
+            |r| *r != 0,
+        );
+        self.try_mutate_with_condcheck(
+            np,
+            &mut p,
+            np_key,
+            "a positive integer greater than 0",
+            |p| *p != 0,
+        );
+        self.cfg.auth.hashing.scrypt = ScryptParams::new(log_n, r, p);
+    }
 }
 
 pub fn get_config() -> Result<ConfigType, ConfigError> {
     // initialize clap because that will let us check for CLI/file configs
     let cfg_layout = load_yaml!("../cli.yml");
     let matches = App::from_yaml(cfg_layout).get_matches();
-    let restore_file = matches.value_of("restore").map(|v| v.to_string());
+    let startup = self::startup_actions(&matches)?;
 
-    // get config from file
-    let cfg_from_file = if let Some(file) = matches.value_of("config") {
+    // the sources are layered: the defaults are overridden by the config file, which is
+    // overridden by the environment, which is overridden by the CLI args
+    let mut cfg = Configset::new_layered();
+    if let Some(file) = matches.value_of("config") {
         let file = fs::read(file)?;
         let cfg_file: ConfigFile = toml::from_slice(&file)?;
-        Some(cfgfile::from_file(cfg_file))
-    } else {
-        None
-    };
-
-    // get config from CLI
-    let cfg_from_cli = cfgcli::parse_cli_args(matches);
-    // get config from env
-    let cfg_from_env = cfgenv::parse_env_config();
-    // calculate the number of config sources
-    let cfg_degree = cfg_from_cli.is_mutated() as u8
-        + cfg_from_env.is_mutated() as u8
-        + cfg_from_file.is_some() as u8;
-    // if degree is more than 1, there is a conflict
-    let has_conflict = cfg_degree > 1;
-    if has_conflict {
-        return Err(ConfigError::Conflict);
-    }
-    if cfg_degree == 0 {
-        // no configuration, use default
-        Ok(ConfigType::new_default(restore_file))
-    } else {
-        cfg_from_file
-            .unwrap_or_else(|| cfg_from_env.and_then(cfg_from_cli))
-            .into_result(restore_file)
+        cfg = cfgfile::from_file(cfg, cfg_file);
     }
+    let cfg = cfgenv::parse_env_config(cfg);
+    let cfg = cfgcli::parse_cli_args(cfg, matches);
+    cfg.into_result(startup)
+}
+
+/// Get the one-off startup actions from the CLI args
+fn startup_actions(matches: &ArgMatches) -> Result<StartupActions, ConfigError> {
+    let recover = match matches.value_of("recoveruntil") {
+        Some(until) => match DateTime::parse_from_rfc3339(until) {
+            Ok(until) => Some(RecoveryTarget::Until(until.timestamp_millis().max(0) as u64)),
+            Err(_) => {
+                let mut estack = ErrorStack::new(Configset::EMSG_CLI);
+                estack.push(
+                    "Bad value for `--recover-until`. Expected an RFC 3339 timestamp like 2026-10-16T09:30:00Z",
+                );
+                return Err(ConfigError::CfgError(estack));
+            }
+        },
+        None if matches.is_present("recover") => Some(RecoveryTarget::Latest),
+        None => None,
+    };
+    Ok(StartupActions {
+        restore: matches.value_of("restore").map(|v| v.to_string()),
+        recover,
+    })
 }
@@ -25,9 +25,18 @@
 */
 
 use {
-    super::{BGSave, Configset, PortConfig, SnapshotConfig, SnapshotPref, SslOpts, DEFAULT_IPV4},
-    crate::ROOT_DIR,
-    std::fs,
+    super::{
+        AofConfig, Argon2Params, BGSave, Configset, ConnectionTimeouts, EvictionConfig,
+        EvictionPolicy, FsyncPolicy, HashScheme, IpFilter, IpVerdict, KeyHashing, LoginThrottling,
+        PortConfig, RateLimit, RateLimits, ScryptParams, SnapshotConfig, SnapshotPref, SslOpts,
+        StorageCipher, StorageCodec, StorageCompression, DEFAULT_IPV4,
+    },
+    crate::{
+        dbnet::{DEFAULT_COMPRESSMIN, DEFAULT_KEEPALIVE, DEFAULT_MAXINFLIGHT, DEFAULT_MAXOUTBUF},
+        protocol::{QueryLimits, DEFAULT_MAXPIPELINE},
+        ROOT_DIR,
+    },
+    std::{fs, time::Duration},
 };
 
 // server tests
@@ -116,6 +125,39 @@ fn server_tcp_fail_both() {
     );
 }
 
+// timeouts
+#[test]
+fn server_timeouts_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_timeouts(
+        Some("300"),
+        "SKY_SYSTEM_IDLETIMEOUT",
+        Some("0"),
+        "SKY_SYSTEM_READTIMEOUT",
+    );
+    assert_eq!(cfgset.cfg.timeouts.idle(), Some(Duration::from_secs(300)));
+    assert_eq!(cfgset.cfg.timeouts.read(), None);
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_timeouts_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_timeouts(
+        Some("-1"),
+        "SKY_SYSTEM_IDLETIMEOUT",
+        None::<&str>,
+        "SKY_SYSTEM_READTIMEOUT",
+    );
+    assert_eq!(cfgset.cfg.timeouts, ConnectionTimeouts::default());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_SYSTEM_IDLETIMEOUT`. Expected a positive integer (or zero to disable)"
+    );
+}
+
 // noart
 #[test]
 fn server_noart_okay() {
@@ -126,6 +168,220 @@ fn server_noart_okay() {
     assert!(cfgset.is_mutated());
 }
 
+// unixsocket
+#[test]
+fn server_unixsocket_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_unixsocket(Some("/tmp/skyd.sock"), "SKY_SYSTEM_UNIXSOCKET");
+    assert_eq!(cfgset.cfg.unixsocket, Some("/tmp/skyd.sock".to_owned()));
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_unixsocket_absent() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_unixsocket(None::<&str>, "SKY_SYSTEM_UNIXSOCKET");
+    assert_eq!(cfgset.cfg.unixsocket, None);
+    assert!(cfgset.is_okay());
+    assert!(!cfgset.is_mutated());
+}
+
+// httpport
+#[test]
+fn server_httpport_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_httpport(Some("2080"), "SKY_SYSTEM_HTTPPORT");
+    assert_eq!(cfgset.cfg.httpport, Some(2080));
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_httpport_zero_disables() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_httpport(Some("0"), "SKY_SYSTEM_HTTPPORT");
+    assert_eq!(cfgset.cfg.httpport, None);
+    assert!(cfgset.is_okay());
+}
+
+#[test]
+fn server_httpport_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_httpport(Some("65536"), "SKY_SYSTEM_HTTPPORT");
+    assert_eq!(cfgset.cfg.httpport, None);
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_SYSTEM_HTTPPORT`. Expected a 16-bit positive integer (or zero to disable)"
+    );
+}
+
+// statusport
+#[test]
+fn server_statusport_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_statusport(Some("2082"), "SKY_SYSTEM_STATUSPORT");
+    assert_eq!(cfgset.cfg.statusport, Some(2082));
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_statusport_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_statusport(Some("-1"), "SKY_SYSTEM_STATUSPORT");
+    assert_eq!(cfgset.cfg.statusport, None);
+    assert!(!cfgset.is_okay());
+}
+
+// grpcport
+#[test]
+fn server_grpcport_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_grpcport(Some("2081"), "SKY_SYSTEM_GRPCPORT");
+    assert_eq!(cfgset.cfg.grpcport, Some(2081));
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_grpcport_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_grpcport(Some("65536"), "SKY_SYSTEM_GRPCPORT");
+    assert_eq!(cfgset.cfg.grpcport, None);
+    assert!(!cfgset.is_okay());
+}
+
+// compressmin
+#[test]
+fn server_compressmin_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_compressmin(Some("0"), "SKY_SYSTEM_COMPRESSMIN");
+    assert_eq!(cfgset.cfg.compressmin, 0);
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_compressmin_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_compressmin(Some("-1"), "SKY_SYSTEM_COMPRESSMIN");
+    assert_eq!(cfgset.cfg.compressmin, DEFAULT_COMPRESSMIN);
+    assert!(!cfgset.is_okay());
+}
+
+// maxinflight
+#[test]
+fn server_maxinflight_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_maxinflight(Some("65536"), "SKY_SYSTEM_MAXINFLIGHT");
+    assert_eq!(cfgset.cfg.maxinflight, 65536);
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_maxinflight_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_maxinflight(Some("0"), "SKY_SYSTEM_MAXINFLIGHT");
+    assert_eq!(cfgset.cfg.maxinflight, DEFAULT_MAXINFLIGHT);
+    assert!(!cfgset.is_okay());
+}
+
+// maxoutbuf
+#[test]
+fn server_maxoutbuf_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_maxoutbuf(Some("1048576"), "SKY_SYSTEM_MAXOUTBUF");
+    assert_eq!(cfgset.cfg.maxoutbuf, 1048576);
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_maxoutbuf_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_maxoutbuf(Some("0"), "SKY_SYSTEM_MAXOUTBUF");
+    assert_eq!(cfgset.cfg.maxoutbuf, DEFAULT_MAXOUTBUF);
+    assert!(!cfgset.is_okay());
+}
+
+// keepalive
+#[test]
+fn server_keepalive_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_keepalive(Some("0"), "SKY_SYSTEM_KEEPALIVE");
+    assert_eq!(cfgset.cfg.keepalive, 0);
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_keepalive_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_keepalive(Some("-60"), "SKY_SYSTEM_KEEPALIVE");
+    assert_eq!(cfgset.cfg.keepalive, DEFAULT_KEEPALIVE);
+    assert!(!cfgset.is_okay());
+}
+
+// maxlag
+#[test]
+fn server_maxlag_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_maxlag(Some("500"), "SKY_SYSTEM_MAXLAG");
+    assert_eq!(cfgset.cfg.maxlag, 500);
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_maxlag_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_maxlag(Some("1s"), "SKY_SYSTEM_MAXLAG");
+    assert_eq!(cfgset.cfg.maxlag, 0);
+    assert!(!cfgset.is_okay());
+}
+
+// query limits
+#[test]
+fn server_limits_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_limits(
+        Some("1048576"),
+        "SKY_SYSTEM_MAXQUERYSIZE",
+        None::<&str>,
+        "SKY_SYSTEM_MAXPIPELINE",
+        Some("65536"),
+        "SKY_SYSTEM_MAXARGLEN",
+    );
+    assert_eq!(
+        cfgset.cfg.limits,
+        QueryLimits::new(1048576, DEFAULT_MAXPIPELINE, 65536)
+    );
+    assert!(cfgset.is_okay());
+    assert!(cfgset.is_mutated());
+}
+
+#[test]
+fn server_limits_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.server_limits(
+        None::<&str>,
+        "SKY_SYSTEM_MAXQUERYSIZE",
+        Some("0"),
+        "SKY_SYSTEM_MAXPIPELINE",
+        None::<&str>,
+        "SKY_SYSTEM_MAXARGLEN",
+    );
+    assert_eq!(cfgset.cfg.limits, QueryLimits::default());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_SYSTEM_MAXPIPELINE`. Expected a positive integer greater than zero"
+    );
+}
+
 #[test]
 fn server_noart_fail() {
     let mut cfgset = Configset::new_env();
@@ -257,6 +513,425 @@ fn snapshot_fail_with_missing_required_values() {
     assert_eq!(cfgset.cfg.snapshot, SnapshotConfig::Disabled);
 }
 
+// eviction settings
+#[test]
+fn eviction_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.eviction_settings(
+        Some("1048576"),
+        "SKY_MEMORY_MAX",
+        Some("lru"),
+        "SKY_MEMORY_POLICY",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(
+        cfgset.cfg.eviction,
+        EvictionConfig::new(1048576, EvictionPolicy::Lru)
+    );
+}
+
+#[test]
+fn eviction_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.eviction_settings(
+        Some("1048576"),
+        "SKY_MEMORY_MAX",
+        Some("mru"),
+        "SKY_MEMORY_POLICY",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_MEMORY_POLICY`. Expected one of 'noeviction', 'lru', 'lfu' or 'random'"
+    );
+}
+
+// AOF settings
+#[test]
+fn aof_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.aof_settings(
+        Some("true"),
+        "SKY_AOF_ENABLED",
+        Some("always"),
+        "SKY_AOF_FSYNC",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(cfgset.cfg.aof, AofConfig::Enabled(FsyncPolicy::Always));
+}
+
+#[test]
+fn aof_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.aof_settings(
+        Some("true"),
+        "SKY_AOF_ENABLED",
+        Some("sometimes"),
+        "SKY_AOF_FSYNC",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_AOF_FSYNC`. Expected one of 'always', 'everysec' or 'no'"
+    );
+}
+
+// storage settings
+#[test]
+fn storage_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_settings(
+        Some("zstd"),
+        "SKY_STORAGE_COMPRESS",
+        Some("512"),
+        "SKY_STORAGE_COMPRESSMIN",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(
+        cfgset.cfg.storage,
+        StorageCompression::new(StorageCodec::Zstd, 512)
+    );
+}
+
+#[test]
+fn storage_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_settings(
+        Some("gzip"),
+        "SKY_STORAGE_COMPRESS",
+        Some("0"),
+        "SKY_STORAGE_COMPRESSMIN",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_STORAGE_COMPRESS`. Expected one of 'none', 'lz4' or 'zstd'"
+    );
+    assert_eq!(
+        cfgset.estack[1],
+        "Bad value for `SKY_STORAGE_COMPRESSMIN`. Expected a positive integer"
+    );
+}
+
+#[test]
+fn storage_min_without_compression() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_settings(
+        None,
+        "SKY_STORAGE_COMPRESS",
+        Some("512"),
+        "SKY_STORAGE_COMPRESSMIN",
+    );
+    assert!(cfgset.is_okay());
+    assert_eq!(
+        cfgset.wstack[0],
+        "Specifying `SKY_STORAGE_COMPRESSMIN` is useless when compression is disabled"
+    );
+}
+
+#[test]
+fn storage_spillmin_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_spillmin(Some("1048576"), "SKY_STORAGE_SPILLMIN");
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(cfgset.cfg.spillmin, 1048576);
+}
+
+#[test]
+fn storage_spillmin_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_spillmin(Some("1MiB"), "SKY_STORAGE_SPILLMIN");
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_STORAGE_SPILLMIN`. Expected a positive integer (or zero to disable spilling)"
+    );
+}
+
+#[test]
+fn storage_keepexpired_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_keepexpired(Some("true"), "SKY_STORAGE_KEEPEXPIRED");
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert!(cfgset.cfg.keepexpired);
+}
+
+#[test]
+fn storage_keepexpired_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_keepexpired(Some("yes"), "SKY_STORAGE_KEEPEXPIRED");
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_STORAGE_KEEPEXPIRED`. Expected true/false"
+    );
+}
+
+#[test]
+fn storage_encrypt_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_encrypt(Some("chacha20-poly1305"), "SKY_STORAGE_ENCRYPT");
+    cfgset.storage_keyfile(Some("/etc/skyd/keys"), "SKY_STORAGE_KEYFILE");
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(
+        cfgset.cfg.encryption.cipher,
+        StorageCipher::Chacha20Poly1305
+    );
+    assert_eq!(
+        cfgset.cfg.encryption.keyfile.as_deref(),
+        Some("/etc/skyd/keys")
+    );
+    assert!(cfgset.cfg.encryption.keys.is_none());
+}
+
+#[test]
+fn storage_encrypt_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.storage_encrypt(Some("aes"), "SKY_STORAGE_ENCRYPT");
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_STORAGE_ENCRYPT`. Expected one of 'none', 'aes-256-gcm' or 'chacha20-poly1305'"
+    );
+}
+
+// key hashing settings
+#[test]
+fn auth_hashing_scrypt_okay() {
+    let mut cfgset = Configset::new_file();
+    cfgset.auth_hash(Some("scrypt"), "auth.hash");
+    cfgset.auth_scrypt(
+        Some("17"),
+        "auth.scrypt.log_n",
+        Some("8"),
+        "auth.scrypt.r",
+        Some("2"),
+        "auth.scrypt.p",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(
+        cfgset.cfg.auth.hashing,
+        KeyHashing::new(
+            HashScheme::Scrypt,
+            Argon2Params::default(),
+            ScryptParams::new(17, 8, 2)
+        )
+    );
+}
+
+#[test]
+fn auth_hashing_argon2_fail_memory() {
+    let mut cfgset = Configset::new_file();
+    cfgset.auth_argon2(
+        Some("16"),
+        "auth.argon2.memory",
+        Some("2"),
+        "auth.argon2.iterations",
+        Some("4"),
+        "auth.argon2.parallelism",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `auth.argon2.memory`. Expected a positive integer (KiB) that is at least 8 times the parallelism"
+    );
+}
+
+// origin key file
+#[test]
+fn auth_origin_file_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.auth_origin_file(Some("/var/lib/skytable/origin.key"), "SKY_AUTH_ORIGIN_FILE");
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(
+        cfgset.cfg.auth.origin_file.as_deref(),
+        Some("/var/lib/skytable/origin.key")
+    );
+    assert!(cfgset.cfg.auth.origin_key.is_none());
+    assert!(cfgset.cfg.auth.is_enabled());
+}
+
+// importing users at startup
+#[test]
+fn auth_import_okay() {
+    let mut cfgset = Configset::new_file();
+    cfgset.auth_import(Some("/etc/skyd/users.json"), "auth.import");
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(
+        cfgset.cfg.auth.import.as_deref(),
+        Some("/etc/skyd/users.json")
+    );
+}
+
+// session TTL
+#[test]
+fn auth_session_ttl_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.auth_session_ttl(Some("15"), "SKY_AUTH_SESSION_TTL");
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(
+        cfgset.cfg.auth.session_ttl(),
+        Some(std::time::Duration::from_secs(900))
+    );
+}
+
+#[test]
+fn auth_session_ttl_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.auth_session_ttl(Some("-1"), "SKY_AUTH_SESSION_TTL");
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_AUTH_SESSION_TTL`. Expected a positive integer in minutes (or zero to disable)"
+    );
+}
+
+// failed login throttling
+#[test]
+fn auth_lockout_okay() {
+    let mut cfgset = Configset::new_file();
+    cfgset.auth_lockout(
+        Some("3"),
+        "auth.lockout.max_failures",
+        Some("60"),
+        "auth.lockout.duration",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    assert_eq!(cfgset.cfg.auth.throttling, LoginThrottling::new(3, 60));
+    assert!(cfgset.cfg.auth.throttling.is_enabled());
+    assert_eq!(
+        cfgset.cfg.auth.throttling.lockout(),
+        Duration::from_secs(60)
+    );
+}
+
+#[test]
+fn auth_lockout_fail_zero_duration() {
+    let mut cfgset = Configset::new_file();
+    cfgset.auth_lockout(
+        Some("3"),
+        "auth.lockout.max_failures",
+        Some("0"),
+        "auth.lockout.duration",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `auth.lockout.duration`. Expected a positive integer in seconds greater than 0"
+    );
+}
+
+// rate limits
+#[test]
+fn ratelimit_settings_okay() {
+    let mut cfgset = Configset::new_file();
+    cfgset.ratelimit_settings(
+        Some("100"),
+        "ratelimit.user",
+        None::<&str>,
+        "ratelimit.user_burst",
+        Some("50"),
+        "ratelimit.ip",
+        Some("200"),
+        "ratelimit.ip_burst",
+    );
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    let RateLimits { user, ip } = cfgset.cfg.ratelimits;
+    assert!(user.is_enabled() && ip.is_enabled());
+    // the burst is the rate, unless it's set
+    assert_eq!((user.rate(), user.burst()), (100, 100));
+    assert_eq!((ip.rate(), ip.burst()), (50, 200));
+}
+
+#[test]
+fn ratelimit_settings_fail() {
+    let mut cfgset = Configset::new_file();
+    cfgset.ratelimit_settings(
+        Some("-1"),
+        "ratelimit.user",
+        None::<&str>,
+        "ratelimit.user_burst",
+        None::<&str>,
+        "ratelimit.ip",
+        None::<&str>,
+        "ratelimit.ip_burst",
+    );
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `ratelimit.user`. Expected a positive integer in queries per second (or zero to disable)"
+    );
+    assert_eq!(cfgset.cfg.ratelimits, RateLimits::default());
+}
+
+// network settings
+#[test]
+fn network_settings_okay() {
+    let mut cfgset = Configset::new_env();
+    cfgset.network_allow(Some("10.0.0.0/8, 192.168.1.7"), "SKY_NETWORK_ALLOW");
+    cfgset.network_deny(Some("10.1.0.0/16,"), "SKY_NETWORK_DENY");
+    assert!(cfgset.is_mutated());
+    assert!(cfgset.is_okay());
+    let filter = &cfgset.cfg.ipfilter;
+    assert_eq!((filter.allow.len(), filter.deny.len()), (2, 1));
+    let check = |addr: &str| filter.check(addr.parse().unwrap());
+    assert_eq!(check("10.2.3.4"), IpVerdict::Allowed);
+    assert_eq!(check("192.168.1.7"), IpVerdict::Allowed);
+    // the deny rules win
+    assert_eq!(check("10.1.2.3"), IpVerdict::Denied);
+    assert_eq!(check("192.168.1.8"), IpVerdict::NotAllowed);
+    // IPv4 clients connecting over IPv6
+    assert_eq!(check("::ffff:10.2.3.4"), IpVerdict::Allowed);
+    assert_eq!(check("::1"), IpVerdict::NotAllowed);
+}
+
+#[test]
+fn network_settings_ipv6() {
+    let mut cfgset = Configset::new_env();
+    cfgset.network_deny(Some("fd00::/8,::/0"), "SKY_NETWORK_DENY");
+    assert!(cfgset.is_okay());
+    let check = |addr: &str| cfgset.cfg.ipfilter.check(addr.parse().unwrap());
+    assert_eq!(check("fd12::1"), IpVerdict::Denied);
+    assert_eq!(check("2001:db8::1"), IpVerdict::Denied);
+    // a v6 network never matches v4 clients
+    assert_eq!(check("127.0.0.1"), IpVerdict::Allowed);
+}
+
+#[test]
+fn network_settings_fail() {
+    let mut cfgset = Configset::new_env();
+    cfgset.network_allow(Some("10.0.0.0/33"), "SKY_NETWORK_ALLOW");
+    cfgset.network_deny(Some("localhost"), "SKY_NETWORK_DENY");
+    assert!(!cfgset.is_okay());
+    assert_eq!(
+        cfgset.estack[0],
+        "Bad value for `SKY_NETWORK_ALLOW`. Expected a comma separated list of IP addresses or networks (like 10.0.0.0/8)"
+    );
+    assert_eq!(cfgset.estack.len(), 2);
+    assert_eq!(cfgset.cfg.ipfilter, IpFilter::default());
+}
+
 // TLS settings
 #[test]
 fn tls_settings_okay() {
@@ -336,6 +1011,44 @@ fn tls_settings_fail_with_missing_required_values() {
     assert_eq!(cfg.cfg.ports, PortConfig::default());
 }
 
+#[test]
+fn tls_clientca_okay() {
+    let mut cfg = Configset::new_env();
+    cfg.tls_settings(
+        Some("key.pem"),
+        "SKY_TLS_KEY",
+        Some("cert.pem"),
+        "SKY_TLS_CERT",
+        None,
+        "SKY_TLS_PORT",
+        Some("true"),
+        "SKY_TLS_ONLY",
+        None,
+        "SKY_TLS_PASSIN",
+    );
+    cfg.tls_clientca(Some("clientca.pem"), "SKY_TLS_CLIENTCA");
+    assert!(cfg.is_okay());
+    let mut ssl = SslOpts::new("key.pem".to_owned(), "cert.pem".to_owned(), 2004, None);
+    ssl.clientca = Some("clientca.pem".to_owned());
+    assert_eq!(
+        cfg.cfg.ports,
+        PortConfig::new_secure_only(DEFAULT_IPV4, ssl)
+    );
+}
+
+#[test]
+fn tls_clientca_without_tls() {
+    let mut cfg = Configset::new_env();
+    cfg.tls_clientca(Some("clientca.pem"), "SKY_TLS_CLIENTCA");
+    assert!(cfg.is_mutated());
+    assert!(cfg.is_okay());
+    assert_eq!(cfg.cfg.ports, PortConfig::default());
+    assert_eq!(
+        cfg.wstack[0],
+        "Specifying `SKY_TLS_CLIENTCA` is pointless when TLS is disabled"
+    );
+}
+
 /// Gets a `toml` file from `WORKSPACEROOT/examples/config-files`
 fn get_toml_from_examples_dir(filename: &str) -> String {
     let path = format!("{ROOT_DIR}examples/config-files/{filename}");
@@ -346,22 +1059,31 @@ mod cfg_file_tests {
     use super::get_toml_from_examples_dir;
     use crate::config::AuthkeyWrapper;
     use crate::config::{
-        cfgfile, AuthSettings, BGSave, Configset, ConfigurationSet, Modeset, PortConfig,
-        ProtocolVersion, SnapshotConfig, SnapshotPref, SslOpts, DEFAULT_IPV4, DEFAULT_PORT,
+        cfgfile, AofConfig, Argon2Params, AuthSettings, BGSave, Configset, ConfigurationSet,
+        ConnectionTimeouts, EvictionConfig, HashScheme, IpFilter, KeyHashing, LogFormat,
+        LogRotation, LoggingConfig, LoginThrottling, Modeset, PortConfig, ProtocolVersion,
+        RateLimit, RateLimits, ScryptParams, SnapshotConfig, SnapshotPref, SslOpts, StorageCodec,
+        StorageCompression, StorageEncryption, DEFAULT_IPV4, DEFAULT_PORT,
     };
-    use crate::dbnet::MAXIMUM_CONNECTION_LIMIT;
+    use crate::dbnet::{
+        DEFAULT_COMPRESSMIN, DEFAULT_KEEPALIVE, DEFAULT_MAXINFLIGHT, DEFAULT_MAXOUTBUF,
+        MAXIMUM_CONNECTION_LIMIT,
+    };
+    use crate::logging::LogModule;
+    use crate::protocol::QueryLimits;
+    use log::LevelFilter;
     use std::net::{IpAddr, Ipv6Addr};
 
     fn cfgset_from_toml_str(file: String) -> Result<Configset, toml::de::Error> {
         let toml = toml::from_str(&file)?;
-        Ok(cfgfile::from_file(toml))
+        Ok(cfgfile::from_file(Configset::new_file(), toml))
     }
 
     #[test]
     fn config_file_okay() {
         let file = get_toml_from_examples_dir("template.toml");
         let toml = toml::from_str(&file).unwrap();
-        let cfg_from_file = cfgfile::from_file(toml);
+        let cfg_from_file = cfgfile::from_file(Configset::new_file(), toml);
         assert!(cfg_from_file.is_mutated());
         assert!(cfg_from_file.is_okay());
         // expected
@@ -378,6 +1100,25 @@ mod cfg_file_tests {
         );
         expected.auth.origin_key =
             Some(AuthkeyWrapper::try_new(crate::TEST_AUTH_ORIGIN_KEY).unwrap());
+        expected.auth.hashing.argon2 = Argon2Params::new(65536, 3, 1);
+        expected.auth.session_ttl = 30;
+        expected.auth.rotation_grace = 300;
+        expected.auth.throttling = LoginThrottling::new(5, 600);
+        expected.auth.audit_log = Some("/var/log/skyd/audit.log".to_owned());
+        expected.unixsocket = Some("/tmp/skyd.sock".to_owned());
+        expected.httpport = Some(2080);
+        expected.statusport = Some(2082);
+        expected.timeouts = ConnectionTimeouts::new(300, 30);
+        expected.ratelimits = RateLimits::new(RateLimit::new(1000, 2000), RateLimit::disabled());
+        expected.logging = LoggingConfig::new(
+            LogFormat::Json,
+            vec![
+                (LogModule::Default, LevelFilter::Info),
+                (LogModule::Diskstore, LevelFilter::Debug),
+            ],
+            Some("/var/log/skyd.log".to_owned()),
+            LogRotation::new(10485760, 86400, 5),
+        );
         // check
         assert_eq!(cfg_from_file.cfg, expected);
     }
@@ -400,10 +1141,30 @@ mod cfg_file_tests {
                 bgsave: BGSave::default(),
                 snapshot: SnapshotConfig::default(),
                 ports: PortConfig::default(),
+                unixsocket: None,
+                httpport: None,
+                grpcport: None,
+                statusport: None,
+                compressmin: DEFAULT_COMPRESSMIN,
+                maxinflight: DEFAULT_MAXINFLIGHT,
+                maxoutbuf: DEFAULT_MAXOUTBUF,
+                keepalive: DEFAULT_KEEPALIVE,
+                limits: QueryLimits::default(),
                 maxcon: MAXIMUM_CONNECTION_LIMIT,
+                timeouts: ConnectionTimeouts::default(),
+                maxlag: 0,
+                ratelimits: RateLimits::default(),
+                ipfilter: IpFilter::default(),
                 mode: Modeset::Dev,
                 auth: AuthSettings::default(),
                 protocol: ProtocolVersion::default(),
+                eviction: EvictionConfig::default(),
+                aof: AofConfig::default(),
+                storage: StorageCompression::default(),
+                spillmin: 0,
+                keepexpired: false,
+                encryption: StorageEncryption::default(),
+                logging: LoggingConfig::default(),
             }
         );
     }
@@ -422,10 +1183,30 @@ mod cfg_file_tests {
                     IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0x1)),
                     DEFAULT_PORT
                 ),
+                unixsocket: None,
+                httpport: None,
+                grpcport: None,
+                statusport: None,
+                compressmin: DEFAULT_COMPRESSMIN,
+                maxinflight: DEFAULT_MAXINFLIGHT,
+                maxoutbuf: DEFAULT_MAXOUTBUF,
+                keepalive: DEFAULT_KEEPALIVE,
+                limits: QueryLimits::default(),
                 maxcon: MAXIMUM_CONNECTION_LIMIT,
+                timeouts: ConnectionTimeouts::default(),
+                maxlag: 0,
+                ratelimits: RateLimits::default(),
+                ipfilter: IpFilter::default(),
                 mode: Modeset::Dev,
                 auth: AuthSettings::default(),
                 protocol: ProtocolVersion::default(),
+                eviction: EvictionConfig::default(),
+                aof: AofConfig::default(),
+                storage: StorageCompression::default(),
+                spillmin: 0,
+                keepexpired: false,
+                encryption: StorageEncryption::default(),
+                logging: LoggingConfig::default(),
             }
         );
     }
@@ -449,10 +1230,49 @@ mod cfg_file_tests {
                         Some("/path/to/cert/passphrase.txt".to_owned())
                     )
                 ),
+                Some("/tmp/skyd.sock".to_owned()),
+                Some(2080),
+                Some(2081),
+                Some(2082),
+                DEFAULT_COMPRESSMIN,
+                DEFAULT_MAXINFLIGHT,
+                DEFAULT_MAXOUTBUF,
+                DEFAULT_KEEPALIVE,
+                QueryLimits::default(),
                 MAXIMUM_CONNECTION_LIMIT,
+                ConnectionTimeouts::new(300, 30),
+                5000,
+                RateLimits::new(RateLimit::new(1000, 2000), RateLimit::disabled()),
+                IpFilter::default(),
                 Modeset::Dev,
-                AuthSettings::new(AuthkeyWrapper::try_new(crate::TEST_AUTH_ORIGIN_KEY).unwrap()),
-                ProtocolVersion::default()
+                AuthSettings::new(
+                    AuthkeyWrapper::try_new(crate::TEST_AUTH_ORIGIN_KEY).unwrap(),
+                    KeyHashing::new(
+                        HashScheme::Argon2id,
+                        Argon2Params::new(65536, 3, 1),
+                        ScryptParams::default()
+                    ),
+                    30,
+                    300,
+                    LoginThrottling::new(5, 600),
+                    Some("/var/log/skyd/audit.log".to_owned())
+                ),
+                ProtocolVersion::default(),
+                EvictionConfig::default(),
+                AofConfig::default(),
+                StorageCompression::new(StorageCodec::Lz4, 1024),
+                64 * 1024 * 1024,
+                false,
+                StorageEncryption::default(),
+                LoggingConfig::new(
+                    LogFormat::Json,
+                    vec![
+                        (LogModule::Default, LevelFilter::Info),
+                        (LogModule::Diskstore, LevelFilter::Debug),
+                    ],
+                    Some("/var/log/skyd.log".to_owned()),
+                    LogRotation::new(10485760, 86400, 5)
+                )
             )
         );
     }
@@ -475,10 +1295,30 @@ mod cfg_file_tests {
                 bgsave: BGSave::new(true, 600),
                 snapshot: SnapshotConfig::default(),
                 ports: PortConfig::default(),
+                unixsocket: None,
+                httpport: None,
+                grpcport: None,
+                statusport: None,
+                compressmin: DEFAULT_COMPRESSMIN,
+                maxinflight: DEFAULT_MAXINFLIGHT,
+                maxoutbuf: DEFAULT_MAXOUTBUF,
+                keepalive: DEFAULT_KEEPALIVE,
+                limits: QueryLimits::default(),
                 maxcon: MAXIMUM_CONNECTION_LIMIT,
+                timeouts: ConnectionTimeouts::default(),
+                maxlag: 0,
+                ratelimits: RateLimits::default(),
+                ipfilter: IpFilter::default(),
                 mode: Modeset::Dev,
                 auth: AuthSettings::default(),
                 protocol: ProtocolVersion::default(),
+                eviction: EvictionConfig::default(),
+                aof: AofConfig::default(),
+                storage: StorageCompression::default(),
+                spillmin: 0,
+                keepexpired: false,
+                encryption: StorageEncryption::default(),
+                logging: LoggingConfig::default(),
             }
         );
     }
@@ -498,10 +1338,30 @@ mod cfg_file_tests {
                 bgsave: BGSave::default(),
                 snapshot: SnapshotConfig::default(),
                 ports: PortConfig::default(),
+                unixsocket: None,
+                httpport: None,
+                grpcport: None,
+                statusport: None,
+                compressmin: DEFAULT_COMPRESSMIN,
+                maxinflight: DEFAULT_MAXINFLIGHT,
+                maxoutbuf: DEFAULT_MAXOUTBUF,
+                keepalive: DEFAULT_KEEPALIVE,
+                limits: QueryLimits::default(),
                 maxcon: MAXIMUM_CONNECTION_LIMIT,
+                timeouts: ConnectionTimeouts::default(),
+                maxlag: 0,
+                ratelimits: RateLimits::default(),
+                ipfilter: IpFilter::default(),
                 mode: Modeset::Dev,
                 auth: AuthSettings::default(),
                 protocol: ProtocolVersion::default(),
+                eviction: EvictionConfig::default(),
+                aof: AofConfig::default(),
+                storage: StorageCompression::default(),
+                spillmin: 0,
+                keepexpired: false,
+                encryption: StorageEncryption::default(),
+                logging: LoggingConfig::default(),
             }
         )
     }
@@ -521,10 +1381,30 @@ mod cfg_file_tests {
                 bgsave: BGSave::new(true, 600),
                 snapshot: SnapshotConfig::default(),
                 ports: PortConfig::default(),
+                unixsocket: None,
+                httpport: None,
+                grpcport: None,
+                statusport: None,
+                compressmin: DEFAULT_COMPRESSMIN,
+                maxinflight: DEFAULT_MAXINFLIGHT,
+                maxoutbuf: DEFAULT_MAXOUTBUF,
+                keepalive: DEFAULT_KEEPALIVE,
+                limits: QueryLimits::default(),
                 maxcon: MAXIMUM_CONNECTION_LIMIT,
+                timeouts: ConnectionTimeouts::default(),
+                maxlag: 0,
+                ratelimits: RateLimits::default(),
+                ipfilter: IpFilter::default(),
                 mode: Modeset::Dev,
                 auth: AuthSettings::default(),
                 protocol: ProtocolVersion::default(),
+                eviction: EvictionConfig::default(),
+                aof: AofConfig::default(),
+                storage: StorageCompression::default(),
+                spillmin: 0,
+                keepexpired: false,
+                encryption: StorageEncryption::default(),
+                logging: LoggingConfig::default(),
             }
         )
     }
@@ -540,24 +1420,47 @@ mod cfg_file_tests {
                 bgsave: BGSave::default(),
                 noart: false,
                 ports: PortConfig::default(),
+                unixsocket: None,
+                httpport: None,
+                grpcport: None,
+                statusport: None,
+                compressmin: DEFAULT_COMPRESSMIN,
+                maxinflight: DEFAULT_MAXINFLIGHT,
+                maxoutbuf: DEFAULT_MAXOUTBUF,
+                keepalive: DEFAULT_KEEPALIVE,
+                limits: QueryLimits::default(),
                 maxcon: MAXIMUM_CONNECTION_LIMIT,
+                timeouts: ConnectionTimeouts::default(),
+                maxlag: 0,
+                ratelimits: RateLimits::default(),
+                ipfilter: IpFilter::default(),
                 mode: Modeset::Dev,
                 auth: AuthSettings::default(),
                 protocol: ProtocolVersion::default(),
+                eviction: EvictionConfig::default(),
+                aof: AofConfig::default(),
+                storage: StorageCompression::default(),
+                spillmin: 0,
+                keepexpired: false,
+                encryption: StorageEncryption::default(),
+                logging: LoggingConfig::default(),
             }
         );
     }
 }
 
 mod cli_arg_tests {
-    use crate::config::{cfgcli, PortConfig};
+    use crate::config::{
+        cfgcli, cfgfile, startup_actions, Configset, ConnectionTimeouts, PortConfig,
+        RecoveryTarget, StartupActions,
+    };
     use clap::{load_yaml, App};
     #[test]
     fn cli_args_okay() {
         let cfg_layout = load_yaml!("../cli.yml");
         let cli_args = ["skyd", "--host", "127.0.0.2"];
         let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
-        let ret = cfgcli::parse_cli_args(matches);
+        let ret = cfgcli::parse_cli_args(Configset::new_cli(), matches);
         assert_eq!(
             ret.cfg.ports,
             PortConfig::new_insecure_only("127.0.0.2".parse().unwrap(), 2003)
@@ -570,7 +1473,7 @@ mod cli_arg_tests {
         let cfg_layout = load_yaml!("../cli.yml");
         let cli_args = ["skyd", "--restore", "/some/restore/path"];
         let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
-        let ret = cfgcli::parse_cli_args(matches);
+        let ret = cfgcli::parse_cli_args(Configset::new_cli(), matches);
         assert!(!ret.is_mutated());
         assert!(ret.is_okay());
     }
@@ -579,7 +1482,7 @@ mod cli_arg_tests {
         let cfg_layout = load_yaml!("../cli.yml");
         let cli_args = ["skyd", "--port", "port2003"];
         let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
-        let ret = cfgcli::parse_cli_args(matches);
+        let ret = cfgcli::parse_cli_args(Configset::new_cli(), matches);
         assert!(ret.is_mutated());
         assert!(!ret.is_okay());
         assert_eq!(
@@ -587,6 +1490,112 @@ mod cli_arg_tests {
             "Bad value for `--port`. Expected a 16-bit positive integer"
         );
     }
+    #[test]
+    fn cli_args_override_config_file() {
+        let toml = toml::from_str(
+            r#"
+            [server]
+            host = "127.0.0.1"
+            port = 2003
+            noart = true
+            idletimeout = 300
+            "#,
+        )
+        .unwrap();
+        let cfg = cfgfile::from_file(Configset::new_layered(), toml);
+        let cfg_layout = load_yaml!("../cli.yml");
+        let cli_args = ["skyd", "--port", "2010", "--readtimeout", "30"];
+        let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
+        let ret = cfgcli::parse_cli_args(cfg, matches);
+        assert!(ret.is_mutated());
+        assert!(ret.is_okay());
+        // the CLI args win, but everything else in the file is kept
+        assert_eq!(
+            ret.cfg.ports,
+            PortConfig::new_insecure_only("127.0.0.1".parse().unwrap(), 2010)
+        );
+        assert!(ret.cfg.noart);
+        assert_eq!(ret.cfg.timeouts, ConnectionTimeouts::new(300, 30));
+    }
+    #[test]
+    fn cli_args_recovery() {
+        let cfg_layout = load_yaml!("../cli.yml");
+        let matches = App::from_yaml(cfg_layout).get_matches_from(["skyd", "--recover"]);
+        assert_eq!(
+            startup_actions(&matches).unwrap().recover,
+            Some(RecoveryTarget::Latest)
+        );
+        let cli_args = ["skyd", "--recover-until", "2026-01-01T05:30:00+05:30"];
+        let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
+        assert_eq!(
+            startup_actions(&matches).unwrap().recover,
+            Some(RecoveryTarget::Until(1_767_225_600_000))
+        );
+        let cli_args = ["skyd", "--recover-until", "yesterday"];
+        let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
+        assert!(startup_actions(&matches).is_err());
+    }
+    #[test]
+    fn cli_args_recovery_needs_aof_and_snapshots() {
+        let startup = StartupActions {
+            restore: None,
+            recover: Some(RecoveryTarget::Latest),
+        };
+        assert!(Configset::new_cli().into_result(startup).is_err());
+    }
+    #[test]
+    fn cli_args_encryption_needs_keys() {
+        let cfg_layout = load_yaml!("../cli.yml");
+        let cli_args = ["skyd", "--storage-encrypt", "aes-256-gcm"];
+        let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
+        let startup = startup_actions(&matches).unwrap();
+        let cfg = cfgcli::parse_cli_args(Configset::new_cli(), matches);
+        assert!(cfg.into_result(startup).is_err());
+    }
+    #[test]
+    fn cli_args_encryption_and_spilling_conflict() {
+        let cfg_layout = load_yaml!("../cli.yml");
+        let cli_args = [
+            "skyd",
+            "--storage-encrypt",
+            "aes-256-gcm",
+            "--storage-keyfile",
+            "/etc/skyd/keys",
+            "--storage-spillmin",
+            "1048576",
+        ];
+        let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
+        let startup = startup_actions(&matches).unwrap();
+        let cfg = cfgcli::parse_cli_args(Configset::new_cli(), matches);
+        assert!(cfg.is_okay());
+        assert!(cfg.into_result(startup).is_err());
+    }
+    #[test]
+    fn cli_args_origin_key_and_file_conflict() {
+        let cfg_layout = load_yaml!("../cli.yml");
+        let cli_args = [
+            "skyd",
+            "--auth-origin-key",
+            crate::TEST_AUTH_ORIGIN_KEY,
+            "--auth-origin-file",
+            "/var/lib/skytable/origin.key",
+        ];
+        let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
+        let startup = startup_actions(&matches).unwrap();
+        let cfg = cfgcli::parse_cli_args(Configset::new_cli(), matches);
+        assert!(cfg.is_okay());
+        assert!(cfg.into_result(startup).is_err());
+    }
+    #[test]
+    fn cli_args_import_needs_auth() {
+        let cfg_layout = load_yaml!("../cli.yml");
+        let cli_args = ["skyd", "--auth-import", "/etc/skyd/users.json"];
+        let matches = App::from_yaml(cfg_layout).get_matches_from(cli_args);
+        let startup = startup_actions(&matches).unwrap();
+        let cfg = cfgcli::parse_cli_args(Configset::new_cli(), matches);
+        assert!(cfg.is_okay());
+        assert!(cfg.into_result(startup).is_err());
+    }
 }
 
 mod try_from_config_source_impls {
@@ -28,27 +28,30 @@
 
 use {
     crate::corestore::map::{
-        bref::{Entry, OccupiedEntry, Ref, VacantEntry},
+        bref::{Entry, OccupiedEntry, Ref, RefMut, VacantEntry},
         iter::{BorrowedIter, OwnedIter},
-        Skymap,
+        DefaultState, MapStats, Skymap,
+    },
+    std::{
+        borrow::Borrow,
+        hash::{BuildHasher, Hash},
+        iter::FromIterator,
+        ops::Deref,
     },
-    ahash::RandomState,
-    std::{borrow::Borrow, hash::Hash, iter::FromIterator, ops::Deref},
 };
 
-type HashTable<K, V> = Skymap<K, V, RandomState>;
-
 #[derive(Debug)]
 /// The Coremap contains the actual key/value pairs along with additional fields for data safety
-/// and protection
-pub struct Coremap<K, V> {
-    pub(crate) inner: HashTable<K, V>,
+/// and protection. The hasher can be swapped out with `S`, although the default (a fast
+/// non-cryptographic hash) is what the database uses
+pub struct Coremap<K, V, S = DefaultState> {
+    pub(crate) inner: Skymap<K, V, S>,
 }
 
 impl<K, V> Default for Coremap<K, V> {
     fn default() -> Self {
         Coremap {
-            inner: HashTable::new_ahash(),
+            inner: Skymap::new(),
         }
     }
 }
@@ -60,7 +63,7 @@ impl<K: Eq + Hash, V> Coremap<K, V> {
     }
     pub fn with_capacity(cap: usize) -> Self {
         Coremap {
-            inner: HashTable::with_capacity(cap),
+            inner: Skymap::with_capacity(cap),
         }
     }
     pub fn try_with_capacity(cap: usize) -> Result<Self, ()> {
@@ -70,6 +73,15 @@ impl<K: Eq + Hash, V> Coremap<K, V> {
             Ok(Self::with_capacity(cap))
         }
     }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher + Clone> Coremap<K, V, S> {
+    /// Create an empty coremap that uses the provided hasher
+    pub fn with_hasher(hasher: S) -> Self {
+        Coremap {
+            inner: Skymap::with_hasher(hasher),
+        }
+    }
     /// Returns the total number of key value pairs
     pub fn len(&self) -> usize {
         self.inner.len()
@@ -78,11 +90,30 @@ impl<K: Eq + Hash, V> Coremap<K, V> {
     pub fn clear(&self) {
         self.inner.clear()
     }
+    /// Move all the entries into a new map, leaving this one empty
+    pub fn take(&self) -> Self {
+        Coremap {
+            inner: self.inner.take(),
+        }
+    }
+    /// Release any memory that isn't needed to hold the current entries
+    pub fn compact(&self) {
+        self.inner.compact()
+    }
+    /// Make room for at least `additional` more entries
+    pub fn reserve(&self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+    /// Returns the occupancy statistics of the inner table
+    pub fn stats(&self) -> MapStats {
+        self.inner.stats()
+    }
 }
 
-impl<K, V> Coremap<K, V>
+impl<K, V, S> Coremap<K, V, S>
 where
     K: Eq + Hash,
+    S: BuildHasher + Clone,
 {
     /// Returns the removed value for key, it it existed
     pub fn remove<Q>(&self, key: &Q) -> Option<(K, V)>
@@ -109,8 +140,29 @@ where
         self.inner.contains_key(key)
     }
     /// Return a non-consuming iterator
-    pub fn iter(&self) -> BorrowedIter<'_, K, V, RandomState> {
-        self.inner.get_iter()
+    pub fn iter(&self) -> BorrowedIter<'_, K, V, S> {
+        self.inner.iter()
+    }
+    /// Returns a point-in-time copy of the table, mapping every pair through `f`.
+    ///
+    /// Only a single shard is read-locked at any instant and the lock is only held for as
+    /// long as it takes to run `f` on the pairs in that shard. The copy is only point-in-time
+    /// if nothing writes to the table while it's being made
+    pub fn snapshot_with<T>(&self, mut f: impl FnMut(&K, &V) -> T) -> Vec<T> {
+        let mut snapshot = Vec::with_capacity(self.len());
+        self.iter()
+            .for_each(|kv| snapshot.push(f(kv.key(), kv.value())));
+        snapshot
+    }
+    /// Returns a copy of the table with every value mapped through `f`. Unlike
+    /// [`Coremap::snapshot_with`], the copy is a table itself (see [`Skymap::freeze_with`])
+    pub fn freeze_with<U>(&self, f: impl FnMut(&V) -> U) -> Coremap<K, U, S>
+    where
+        K: Clone,
+    {
+        Coremap {
+            inner: self.inner.freeze_with(f),
+        }
     }
     /// Get a reference to the value of a key, if it exists
     pub fn get<Q>(&self, key: &Q) -> Option<Ref<'_, K, V>>
@@ -143,9 +195,29 @@ where
     {
         self.inner.remove_if(key, exec)
     }
-    /// Update or insert
-    pub fn upsert(&self, k: K, v: V) {
-        let _ = self.inner.insert(k, v);
+    /// Only keep the entries for which `f` returns true, returning the number of removed entries
+    pub fn retain(&self, f: impl FnMut(&K, &mut V) -> bool) -> usize {
+        self.inner.retain(f)
+    }
+    /// Remove all the provided keys, returning the number of keys that were removed
+    pub fn remove_many<'k, Q>(&self, keys: impl IntoIterator<Item = &'k Q>) -> usize
+    where
+        K: Borrow<Q>,
+        Q: 'k + Hash + Eq + ?Sized,
+    {
+        self.inner.remove_many(keys)
+    }
+    /// Clears the inner table, handing every removed entry to `f`
+    pub fn clear_with(&self, f: impl FnMut(K, V)) {
+        self.inner.clear_with(f)
+    }
+    /// Update or insert, returning the old value (if any)
+    pub fn upsert(&self, k: K, v: V) -> Option<V> {
+        self.inner.upsert(k, v)
+    }
+    /// Get a r/w ref to the value, inserting the value returned by `f` if the key doesn't exist
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> RefMut<'_, K, V> {
+        self.inner.get_or_insert_with(key, f)
     }
     /// Returns true if the value was updated
     pub fn true_if_update(&self, k: K, v: V) -> bool {
@@ -156,14 +228,18 @@ where
             false
         }
     }
-    pub fn mut_entry(&self, key: K) -> Option<OccupiedEntry<K, V, RandomState>> {
+    /// Get the entry for the key, to atomically get or create the value
+    pub fn entry(&self, key: K) -> Entry<K, V, S> {
+        self.inner.entry(key)
+    }
+    pub fn mut_entry(&self, key: K) -> Option<OccupiedEntry<K, V, S>> {
         if let Entry::Occupied(oe) = self.inner.entry(key) {
             Some(oe)
         } else {
             None
         }
     }
-    pub fn fresh_entry(&self, key: K) -> Option<VacantEntry<K, V, RandomState>> {
+    pub fn fresh_entry(&self, key: K) -> Option<VacantEntry<K, V, S>> {
         if let Entry::Vacant(ve) = self.inner.entry(key) {
             Some(ve)
         } else {
@@ -172,7 +248,7 @@ where
     }
 }
 
-impl<K: Eq + Hash, V: Clone> Coremap<K, V> {
+impl<K: Eq + Hash, V: Clone, S: BuildHasher + Clone> Coremap<K, V, S> {
     pub fn get_cloned<Q>(&self, key: &Q) -> Option<V>
     where
         K: Borrow<Q>,
@@ -182,29 +258,65 @@ impl<K: Eq + Hash, V: Clone> Coremap<K, V> {
     }
 }
 
-impl<K: Eq + Hash + Clone, V> Coremap<K, V> {
+impl<K: Eq + Hash + Clone, V, S: BuildHasher + Clone> Coremap<K, V, S> {
     /// Returns atleast `count` number of keys from the hashtable
     pub fn get_keys(&self, count: usize) -> Vec<K> {
         let mut v = Vec::with_capacity(count);
-        self.iter()
+        self.inner
+            .keys()
             .take(count)
-            .map(|kv| kv.key().clone())
-            .for_each(|key| v.push(key));
+            .for_each(|key| v.push(key.key().clone()));
         v
     }
+    /// Returns a key picked uniformly at random using the provided random number
+    pub fn random_key(&self, random: u64) -> Option<K> {
+        self.inner.random_key(random)
+    }
+    /// Returns a random sample of atmost `count` keys, picked using `seed`
+    pub fn sample_keys(&self, count: usize, seed: u64) -> Vec<K> {
+        self.inner.sample_keys(count, seed)
+    }
+    /// Returns a random sample of atmost `count` entries (mapped through `f`), picked using
+    /// `seed`. This only probes a bounded number of buckets for every entry
+    pub fn random_sample<T>(&self, count: usize, seed: u64, f: impl FnMut(&K, &V) -> T) -> Vec<T> {
+        self.inner.random_sample(count, seed, f)
+    }
+    /// Returns a uniform random sample of atmost `count` entries (mapped through `f`). Unlike
+    /// [`Coremap::random_sample`], every entry is equally likely to be picked
+    pub fn reservoir_sample<T>(
+        &self,
+        count: usize,
+        seed: u64,
+        f: impl FnMut(&K, &V) -> T,
+    ) -> Vec<T> {
+        self.inner.reservoir_sample(count, seed, f)
+    }
+    /// Examines atmost `limit` keys starting at `cursor` and returns the ones satisfying
+    /// `filter`, along with the cursor for the next call (`0` once the scan is complete). Keys
+    /// that are present throughout the scan are returned exactly once, even if the map is
+    /// resized mid-scan
+    pub fn scan_keys(
+        &self,
+        cursor: u64,
+        limit: usize,
+        filter: impl FnMut(&K) -> bool,
+    ) -> (Vec<K>, u64) {
+        self.inner.scan_keys(cursor, limit, filter)
+    }
 }
 
-impl<K: Eq + Hash, V> IntoIterator for Coremap<K, V> {
+impl<K: Eq + Hash, V, S> IntoIterator for Coremap<K, V, S> {
     type Item = (K, V);
-    type IntoIter = OwnedIter<K, V, RandomState>;
+    type IntoIter = OwnedIter<K, V, S>;
     fn into_iter(self) -> Self::IntoIter {
         self.inner.get_owned_iter()
     }
 }
 
-impl<K, V> FromIterator<(K, V)> for Coremap<K, V>
+impl<K, V, S> FromIterator<(K, V)> for Coremap<K, V, S>
 where
     K: Eq + Hash,
+    S: BuildHasher + Default + Clone,
 {
     fn from_iter<T>(iter: T) -> Self
     where
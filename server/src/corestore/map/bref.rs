@@ -25,7 +25,7 @@
 */
 
 use {
-    super::LowMap,
+    super::{DefaultState, LowMap},
     crate::util::{compiler, Unwrappable},
     core::{
         hash::{BuildHasher, Hash},
@@ -33,7 +33,7 @@ use {
         ops::{Deref, DerefMut},
     },
     parking_lot::{RwLockReadGuard, RwLockWriteGuard},
-    std::{collections::hash_map::RandomState, sync::Arc},
+    std::sync::Arc,
 };
 
 /// A read-only reference to a bucket
@@ -133,19 +133,28 @@ impl<'a, K: Hash + Eq, V, S: BuildHasher> OccupiedEntry<'a, K, V, S> {
     pub fn value(&self) -> &V {
         self.elem.1
     }
+    /// Get a mutable ref to the value
+    pub fn value_mut(&mut self) -> &mut V {
+        self.elem.1
+    }
     /// Insert a value into this bucket
     pub fn insert(&mut self, other: V) -> V {
         mem::replace(self.elem.1, other)
     }
+    /// Convert this entry into a r/w ref to the bucket (retaining the lock)
+    pub fn into_ref(self) -> RefMut<'a, K, V> {
+        RefMut::new(self.guard, self.elem.0, self.elem.1)
+    }
     /// Remove this element from the map
     pub fn remove(mut self) -> V {
         let hash = super::make_hash::<K, _, S>(&self.hasher, &self.key);
-        unsafe {
+        let (_, value) = unsafe {
             self.guard
                 .remove_entry(hash, super::ceq(self.elem.0))
                 .unsafe_unwrap()
-        }
-        .1
+        };
+        super::shrink_if_sparse(&mut self.guard, &self.hasher);
+        value
     }
 }
 
@@ -181,11 +190,36 @@ impl<'a, K: Hash + Eq, V, S: BuildHasher> VacantEntry<'a, K, V, S> {
 }
 
 /// An entry, either occupied or vacant
-pub enum Entry<'a, K, V, S = RandomState> {
+pub enum Entry<'a, K, V, S = DefaultState> {
     Occupied(OccupiedEntry<'a, K, V, S>),
     Vacant(VacantEntry<'a, K, V, S>),
 }
 
+impl<'a, K: Hash + Eq, V, S: BuildHasher> Entry<'a, K, V, S> {
+    /// Returns a r/w ref to the value, inserting `default` if the entry is vacant
+    pub fn or_insert(self, default: V) -> RefMut<'a, K, V> {
+        match self {
+            Self::Occupied(oe) => oe.into_ref(),
+            Self::Vacant(ve) => ve.insert(default),
+        }
+    }
+    /// Returns a r/w ref to the value, inserting the value returned by `f` if the entry is
+    /// vacant. `f` is only called if the entry is vacant
+    pub fn or_insert_with(self, f: impl FnOnce() -> V) -> RefMut<'a, K, V> {
+        match self {
+            Self::Occupied(oe) => oe.into_ref(),
+            Self::Vacant(ve) => ve.insert(f()),
+        }
+    }
+    /// Run `f` on the value if the entry is occupied
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Self::Occupied(ref mut oe) = self {
+            f(oe.value_mut());
+        }
+        self
+    }
+}
+
 #[cfg(test)]
 impl<'a, K, V, S> Entry<'a, K, V, S> {
     pub fn is_occupied(&self) -> bool {
@@ -228,6 +262,29 @@ impl<'a, K, V> Deref for RefMulti<'a, K, V> {
 unsafe impl<'a, K: Sync, V: Sync> Sync for RefMulti<'a, K, V> {}
 unsafe impl<'a, K: Send, V: Send> Send for RefMulti<'a, K, V> {}
 
+/// A shared ref to a key. The shard stays read-locked for as long as the ref is alive
+pub struct KeyRef<'a, K, V> {
+    inner: RefMulti<'a, K, V>,
+}
+
+impl<'a, K, V> KeyRef<'a, K, V> {
+    /// Create a new shared key ref
+    pub const fn new(inner: RefMulti<'a, K, V>) -> Self {
+        Self { inner }
+    }
+    /// Get a ref to the key
+    pub const fn key(&self) -> &K {
+        self.inner.key()
+    }
+}
+
+impl<'a, K, V> Deref for KeyRef<'a, K, V> {
+    type Target = K;
+    fn deref(&self) -> &Self::Target {
+        self.key()
+    }
+}
+
 /// A shared r/w ref to a bucket
 pub struct RefMultiMut<'a, K, V> {
     _g: Arc<RwLockWriteGuard<'a, LowMap<K, V>>>,
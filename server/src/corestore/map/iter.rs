@@ -25,15 +25,18 @@
 */
 
 use {
-    super::{bref::RefMulti, LowMap, Skymap},
+    super::{
+        bref::{KeyRef, RefMulti},
+        DefaultState, LowMap, Skymap,
+    },
     core::mem,
     hashbrown::raw::{RawIntoIter, RawIter},
     parking_lot::RwLockReadGuard,
-    std::{collections::hash_map::RandomState, sync::Arc},
+    std::sync::Arc,
 };
 
 /// An owned iterator for a [`Skymap`]
-pub struct OwnedIter<K, V, S = RandomState> {
+pub struct OwnedIter<K, V, S = DefaultState> {
     map: Skymap<K, V, S>,
     cs: usize,
     current: Option<RawIntoIter<(K, V)>>,
@@ -78,7 +81,7 @@ unsafe impl<K: Sync, V: Sync, S> Sync for OwnedIter<K, V, S> {}
 type BorrowedIterGroup<'a, K, V> = (RawIter<(K, V)>, Arc<RwLockReadGuard<'a, LowMap<K, V>>>);
 
 /// A borrowed iterator for a [`Skymap`]
-pub struct BorrowedIter<'a, K, V, S = ahash::RandomState> {
+pub struct BorrowedIter<'a, K, V, S = DefaultState> {
     map: &'a Skymap<K, V, S>,
     cs: usize,
     citer: Option<BorrowedIterGroup<'a, K, V>>,
@@ -129,6 +132,66 @@ impl<'a, K, V, S> Iterator for BorrowedIter<'a, K, V, S> {
 unsafe impl<'a, K: Send, V: Send, S> Send for BorrowedIter<'a, K, V, S> {}
 unsafe impl<'a, K: Sync, V: Sync, S> Sync for BorrowedIter<'a, K, V, S> {}
 
+/// A borrowed iterator over the keys of a [`Skymap`]. Just like [`BorrowedIter`], only one
+/// shard is read-locked at a time
+pub struct Keys<'a, K, V, S = DefaultState> {
+    inner: BorrowedIter<'a, K, V, S>,
+}
+
+impl<'a, K, V, S> Keys<'a, K, V, S> {
+    pub const fn new(map: &'a Skymap<K, V, S>) -> Self {
+        Self {
+            inner: BorrowedIter::new(map),
+        }
+    }
+}
+
+impl<'a, K, V, S> Iterator for Keys<'a, K, V, S> {
+    type Item = KeyRef<'a, K, V>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(KeyRef::new)
+    }
+}
+
+/// A borrowed iterator over the values of a [`Skymap`]. Just like [`BorrowedIter`], only one
+/// shard is read-locked at a time
+pub struct Values<'a, K, V, S = DefaultState> {
+    inner: BorrowedIter<'a, K, V, S>,
+}
+
+impl<'a, K, V, S> Values<'a, K, V, S> {
+    pub const fn new(map: &'a Skymap<K, V, S>) -> Self {
+        Self {
+            inner: BorrowedIter::new(map),
+        }
+    }
+}
+
+impl<'a, K, V, S> Iterator for Values<'a, K, V, S> {
+    type Item = RefMulti<'a, K, V>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+#[test]
+fn test_borrowed_iters() {
+    let map = Skymap::default();
+    map.insert("hello1", "world1");
+    map.insert("hello2", "world2");
+    let mut kv: Vec<(&str, &str)> = map.iter().map(|kv| (*kv.key(), *kv.value())).collect();
+    kv.sort_unstable();
+    assert_eq!(kv, vec![("hello1", "world1"), ("hello2", "world2")]);
+    let mut keys: Vec<&str> = map.keys().map(|k| *k).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, vec!["hello1", "hello2"]);
+    let mut values: Vec<&str> = map.values().map(|v| *v).collect();
+    values.sort_unstable();
+    assert_eq!(values, vec!["world1", "world2"]);
+    // the map is still usable
+    assert_eq!(map.len(), 2);
+}
+
 #[test]
 fn test_iter() {
     let map = Skymap::default();
@@ -30,7 +30,7 @@
 use {
     self::{
         bref::{Entry, OccupiedEntry, Ref, RefMut, VacantEntry},
-        iter::{BorrowedIter, OwnedIter},
+        iter::{BorrowedIter, Keys, OwnedIter, Values},
     },
     crate::util::compiler,
     core::{
@@ -40,20 +40,34 @@ use {
         iter::FromIterator,
         mem,
         num::NonZeroUsize,
+        ops::Deref,
     },
     parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard},
-    std::{collections::hash_map::RandomState, thread::available_parallelism},
+    std::thread::available_parallelism,
 };
 
 pub mod bref;
 pub mod iter;
+#[cfg(feature = "map-serde")]
+mod serialize;
 
+/// The hasher state used by default. This is a fast non-cryptographic hash, with a random
+/// seed for every map to keep collisions hard to predict
+pub type DefaultState = ahash::RandomState;
 type LowMap<K, V> = hashbrown::raw::RawTable<(K, V)>;
-type ShardSlice<K, V> = [RwLock<LowMap<K, V>>];
+type ShardSlice<K, V> = [Shard<K, V>];
 type SRlock<'a, K, V> = RwLockReadGuard<'a, hashbrown::raw::RawTable<(K, V)>>;
 type SWlock<'a, K, V> = RwLockWriteGuard<'a, hashbrown::raw::RawTable<(K, V)>>;
 const BITS_IN_USIZE: usize = mem::size_of::<usize>() * 8;
 const DEFAULT_CAP: usize = 128;
+/// A shard is shrunk once less than `1/SHRINK_RATIO` of its capacity is used
+const SHRINK_RATIO: usize = 4;
+/// Shards with a capacity below this are never shrunk automatically, since it isn't worth it
+const SHRINK_MIN_CAPACITY: usize = 64;
+/// The maximum number of buckets probed for every entry picked by [`Skymap::random_sample`].
+/// Since shards are shrunk once they're mostly empty, a full bucket is almost always found
+/// well before this
+const SAMPLE_MAX_PROBES: usize = 64;
 
 fn make_hash<K, Q, S>(hash_builder: &S, val: &Q) -> u64
 where
@@ -76,6 +90,22 @@ where
     state.finish()
 }
 
+/// Advance `state` and return the next number in its sequence (splitmix64)
+pub(super) fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// The position of a key with the given hash in the order that [`Skymap::scan_keys`] visits
+/// keys in. This is the hash rotated such that the bits that pick the shard of the key (see
+/// [`Skymap::determine_shard`]) come first, so the keys of a shard have contiguous positions
+const fn scan_position(hash: u64) -> u64 {
+    (hash as usize).rotate_left(7) as u64
+}
+
 fn make_hasher<K, Q, V, S>(hash_builder: &S) -> impl Fn(&(Q, V)) -> u64 + '_
 where
     K: Borrow<Q>,
@@ -93,6 +123,55 @@ where
     move |x| k.eq(x.0.borrow())
 }
 
+/// Shrink the shard if it has become sparse (say, after a lot of removals). We leave enough
+/// room for the shard to double in size before it has to grow again, so that a workload that
+/// alternates between inserts and removes doesn't keep reallocating
+fn shrink_if_sparse<K, V, S>(lowtable: &mut LowMap<K, V>, hasher: &S)
+where
+    K: Hash,
+    S: BuildHasher,
+{
+    if lowtable.capacity() > SHRINK_MIN_CAPACITY
+        && lowtable.len() * SHRINK_RATIO < lowtable.capacity()
+    {
+        lowtable.shrink_to(lowtable.len() * 2, make_hasher::<K, _, V, S>(hasher));
+    }
+}
+
+/// Returns the number of usable slots in a shard with the provided number of buckets (the
+/// load factor of a shard is capped at 7/8)
+const fn full_capacity(buckets: usize) -> usize {
+    if buckets <= 8 {
+        buckets - 1
+    } else {
+        buckets / 8 * 7
+    }
+}
+
+/// Occupancy statistics of a [`Skymap`], summed across all its shards
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MapStats {
+    /// The total number of buckets
+    pub buckets: usize,
+    /// The number of buckets holding an entry
+    pub occupied: usize,
+    /// The number of buckets holding a tombstone (a deleted entry that still lengthens probes)
+    pub tombstones: usize,
+    /// The longest distance (in buckets) between an entry and the bucket it hashes to
+    pub longest_probe: usize,
+}
+
+impl MapStats {
+    /// Returns the fraction of buckets that are occupied or tombstoned
+    pub fn load_factor(&self) -> f32 {
+        if self.buckets == 0 {
+            0.0
+        } else {
+            (self.occupied + self.tombstones) as f32 / self.buckets as f32
+        }
+    }
+}
+
 fn get_shard_count() -> usize {
     (available_parallelism().map_or(1, usize::from) * 16).next_power_of_two()
 }
@@ -101,23 +180,40 @@ const fn cttz(amount: usize) -> usize {
     amount.trailing_zeros() as usize
 }
 
+/// A shard of the Skymap. Every shard is aligned to (and hence padded up to) two cache lines so
+/// that locking one shard never invalidates the cache line holding the lock of a neighbouring
+/// shard (adjacent-line prefetchers pull in pairs of lines, hence two)
+#[repr(align(128))]
+struct Shard<K, V>(RwLock<LowMap<K, V>>);
+
+impl<K, V> Deref for Shard<K, V> {
+    type Target = RwLock<LowMap<K, V>>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 /// A striped in-memory map
-pub struct Skymap<K, V, S = RandomState> {
+///
+/// There is no table-wide lock: the map is split into a fixed number of shards (based on
+/// the available parallelism) and each shard has its own lock and is resized independently.
+/// A resize (or a long retain) of one shard only blocks access to the keys in that shard
+pub struct Skymap<K, V, S = DefaultState> {
     shards: Box<ShardSlice<K, V>>,
     hasher: S,
     shift: usize,
 }
 
-impl<K, V> Default for Skymap<K, V, RandomState> {
+impl<K, V> Default for Skymap<K, V, DefaultState> {
     fn default() -> Self {
-        Self::with_hasher(RandomState::default())
+        Self::with_hasher(DefaultState::default())
     }
 }
 
-impl<K: fmt::Debug, V: fmt::Debug, S: BuildHasher + Default> fmt::Debug for Skymap<K, V, S> {
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for Skymap<K, V, S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut map = f.debug_map();
-        for s in self.get_iter() {
+        for s in self.iter() {
             map.entry(s.key(), s.value());
         }
         map.finish()
@@ -141,13 +237,6 @@ where
     }
 }
 
-impl<K, V> Skymap<K, V, ahash::RandomState> {
-    /// Get a Skymap with the ahash hasher
-    pub fn new_ahash() -> Self {
-        Skymap::new()
-    }
-}
-
 // basic impls
 impl<K, V, S> Skymap<K, V, S>
 where
@@ -161,6 +250,9 @@ where
     pub fn with_capacity(cap: usize) -> Self {
         Self::with_capacity_and_hasher(cap, S::default())
     }
+}
+
+impl<K, V, S> Skymap<K, V, S> {
     /// Create a new Skymap with the provided cap and hasher
     pub fn with_capacity_and_hasher(mut cap: usize, hasher: S) -> Self {
         let shard_count = get_shard_count();
@@ -172,7 +264,7 @@ where
         let cap_per_shard = cap / shard_count;
         Self {
             shards: (0..shard_count)
-                .map(|_| RwLock::new(LowMap::with_capacity(cap_per_shard)))
+                .map(|_| Shard(RwLock::new(LowMap::with_capacity(cap_per_shard))))
                 .collect(),
             hasher,
             shift,
@@ -194,10 +286,19 @@ where
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
-    /// Get a borrowed iterator for the Skymap. Bound to the lifetime
-    pub fn get_iter(&self) -> BorrowedIter<K, V, S> {
+    /// Get a borrowed iterator for the Skymap. Bound to the lifetime, and only one shard is
+    /// read-locked at a time
+    pub fn iter(&self) -> BorrowedIter<K, V, S> {
         BorrowedIter::new(self)
     }
+    /// Get a borrowed iterator over the keys of the Skymap
+    pub fn keys(&self) -> Keys<K, V, S> {
+        Keys::new(self)
+    }
+    /// Get a borrowed iterator over the values of the Skymap
+    pub fn values(&self) -> Values<K, V, S> {
+        Values::new(self)
+    }
     /// Get an owned iterator to the Skymap
     pub fn get_owned_iter(self) -> OwnedIter<K, V, S> {
         OwnedIter::new(self)
@@ -244,6 +345,12 @@ where
             // end critical section
         }
     }
+    /// Insert a key/value into the Skymap, overwriting (and returning) the old value if the
+    /// key already existed. Unlike a `contains_key` followed by an insert, this only needs a
+    /// single probe
+    pub fn upsert(&self, k: K, v: V) -> Option<V> {
+        self.insert(k, v)
+    }
     /// Remove a key/value from the Skymap
     pub fn remove<Q>(&self, k: &Q) -> Option<(K, V)>
     where
@@ -256,7 +363,10 @@ where
             // begin critical section
             let mut lowtable = self.get_wshard_unchecked(idx);
             match lowtable.remove_entry(hash, ceq(k)) {
-                Some(kv) => Some(kv),
+                Some(kv) => {
+                    shrink_if_sparse(&mut lowtable, self.h());
+                    Some(kv)
+                }
                 None => None,
             }
             // end critical section
@@ -277,7 +387,9 @@ where
                 Some(bucket) => {
                     let (kptr, vptr) = bucket.as_ref();
                     if f(kptr, vptr) {
-                        Some(lowtable.remove(bucket))
+                        let kv = lowtable.remove(bucket);
+                        shrink_if_sparse(&mut lowtable, self.h());
+                        Some(kv)
                     } else {
                         None
                     }
@@ -287,6 +399,45 @@ where
             // end critical section
         }
     }
+    /// Only keep the entries for which `f` returns true, returning the number of entries that
+    /// were removed. Only one shard is write-locked at a time, so readers and writers of other
+    /// shards are never blocked by a retain
+    pub fn retain(&self, mut f: impl FnMut(&K, &mut V) -> bool) -> usize {
+        let mut removed = 0;
+        self.shards().iter().for_each(|shard| {
+            let mut lowtable = shard.write();
+            unsafe {
+                // UNSAFE(@ohsayan): the write guard keeps the buckets valid and erasing a
+                // bucket doesn't invalidate the iterator (no rehashing happens here)
+                for bucket in lowtable.iter() {
+                    let (kptr, vptr) = bucket.as_mut();
+                    if !f(kptr, vptr) {
+                        lowtable.erase(bucket);
+                        removed += 1;
+                    }
+                }
+            }
+            shrink_if_sparse(&mut lowtable, self.h());
+        });
+        removed
+    }
+    /// Remove all the provided keys, returning the number of keys that were actually removed
+    pub fn remove_many<'k, Q>(&self, keys: impl IntoIterator<Item = &'k Q>) -> usize
+    where
+        K: Borrow<Q>,
+        Q: 'k + Hash + Eq + ?Sized,
+    {
+        keys.into_iter()
+            .filter(|key| self.remove(*key).is_some())
+            .count()
+    }
+    /// Clear out all the entries in the Skymap, handing each removed entry to `f`. Like
+    /// [`Skymap::retain`], only one shard is write-locked at a time
+    pub fn clear_with(&self, mut f: impl FnMut(K, V)) {
+        self.shards()
+            .iter()
+            .for_each(|shard| shard.write().drain().for_each(|(k, v)| f(k, v)))
+    }
 }
 
 // lt impls
@@ -359,6 +510,11 @@ impl<'a, K: 'a + Hash + Eq, V: 'a, S: BuildHasher + Clone> Skymap<K, V, S> {
             // end critical section
         }
     }
+    /// Get a r/w ref to the value of the key, inserting the value returned by `f` if the key
+    /// doesn't exist (`f` isn't called otherwise)
+    pub fn get_or_insert_with(&'a self, key: K, f: impl FnOnce() -> V) -> RefMut<'a, K, V> {
+        self.entry(key).or_insert_with(f)
+    }
     /// Check if the Skymap contains the provided key
     pub fn contains_key<Q>(&self, key: &Q) -> bool
     where
@@ -371,6 +527,293 @@ impl<'a, K: 'a + Hash + Eq, V: 'a, S: BuildHasher + Clone> Skymap<K, V, S> {
     pub fn clear(&self) {
         self.shards().iter().for_each(|shard| shard.write().clear())
     }
+    /// Move all the entries into a new map, leaving this map empty. Each shard is swapped out
+    /// with an empty one while it is write-locked, so this doesn't need to touch any entry
+    pub fn take(&self) -> Self {
+        Self {
+            shards: self
+                .shards()
+                .iter()
+                .map(|shard| {
+                    let lowtable = mem::replace(&mut *shard.write(), LowMap::new());
+                    Shard(RwLock::new(lowtable))
+                })
+                .collect(),
+            hasher: self.hasher.clone(),
+            shift: self.shift,
+        }
+    }
+    /// Returns a copy of the map with every value mapped through `f`. Each shard is copied
+    /// while it is read-locked, so the copy of a shard is consistent, but the copy as a whole
+    /// is only point-in-time if nothing writes to the map while it's being made (saves hold
+    /// the transaction lock exclusively for this). The copy keeps the hasher and the shard
+    /// layout, so no key has to be moved to another shard
+    pub fn freeze_with<U>(&self, mut f: impl FnMut(&V) -> U) -> Skymap<K, U, S>
+    where
+        K: Clone,
+    {
+        Skymap {
+            shards: self
+                .shards()
+                .iter()
+                .map(|shard| {
+                    let lowtable = shard.read();
+                    let mut frozen = LowMap::with_capacity(lowtable.len());
+                    unsafe {
+                        // UNSAFE(@ohsayan): the read guard keeps the buckets valid while we
+                        // copy them
+                        for bucket in lowtable.iter() {
+                            let (k, v) = bucket.as_ref();
+                            let hash = make_insert_hash::<K, S>(self.h(), k);
+                            frozen.insert(
+                                hash,
+                                (k.clone(), f(v)),
+                                make_hasher::<K, _, U, S>(self.h()),
+                            );
+                        }
+                    }
+                    Shard(RwLock::new(frozen))
+                })
+                .collect(),
+            hasher: self.hasher.clone(),
+            shift: self.shift,
+        }
+    }
+    /// Shrink every shard to fit its entries, releasing the memory held by the shards (and
+    /// any leftover tombstones). Only one shard is write-locked at a time
+    pub fn compact(&self) {
+        self.shards().iter().for_each(|shard| {
+            shard
+                .write()
+                .shrink_to(0, make_hasher::<K, _, V, S>(self.h()))
+        })
+    }
+    /// Make room for at least `additional` more entries, so that inserting them doesn't have to
+    /// grow (and rehash) the shards. The room is split evenly across the shards, since the keys
+    /// are spread evenly across them. Only one shard is write-locked at a time
+    pub fn reserve(&self, additional: usize) {
+        let per_shard = additional.div_ceil(self.shards().len());
+        self.shards().iter().for_each(|shard| {
+            shard
+                .write()
+                .reserve(per_shard, make_hasher::<K, _, V, S>(self.h()))
+        })
+    }
+    /// Returns the occupancy statistics of the map. Only one shard is read-locked at a time
+    /// and every entry is rehashed to find its probe distance, so this is O(n)
+    pub fn stats(&self) -> MapStats {
+        let mut stats = MapStats::default();
+        for shard in self.shards().iter() {
+            let lowtable = shard.read();
+            let buckets = lowtable.buckets();
+            stats.buckets += buckets;
+            stats.occupied += lowtable.len();
+            // tombstones eat into the space that is left for growth
+            stats.tombstones += full_capacity(buckets) - lowtable.capacity();
+            unsafe {
+                // UNSAFE(@ohsayan): the read guard keeps the buckets valid while we look at them
+                for bucket in lowtable.iter() {
+                    let hash = make_insert_hash::<K, S>(self.h(), &bucket.as_ref().0) as usize;
+                    // the bucket count is always a power of two
+                    let probe = lowtable.bucket_index(&bucket).wrapping_sub(hash) & (buckets - 1);
+                    stats.longest_probe = stats.longest_probe.max(probe);
+                }
+            }
+        }
+        stats
+    }
+    /// Returns a random sample of atmost `count` keys (a key can be picked more than once),
+    /// picked using `seed`. This is meant to be used by approximate algorithms (like eviction)
+    /// that only need a sample of the keys; see [`Skymap::random_sample`] for the cost
+    pub fn sample_keys(&self, count: usize, seed: u64) -> Vec<K>
+    where
+        K: Clone,
+    {
+        self.random_sample(count, seed, |key, _| key.clone())
+    }
+    /// Returns a random sample of atmost `count` entries (mapped through `f`), picked using
+    /// `seed`. Every entry is picked by choosing a shard in proportion to the number of entries
+    /// it has and then probing for a full bucket from a random bucket of that shard, giving up
+    /// after [`SAMPLE_MAX_PROBES`] buckets. So unlike [`Skymap::reservoir_sample`], the cost
+    /// doesn't depend on the size of the map, but entries that follow a run of empty buckets
+    /// are more likely to be picked
+    pub fn random_sample<T>(
+        &self,
+        count: usize,
+        seed: u64,
+        mut f: impl FnMut(&K, &V) -> T,
+    ) -> Vec<T> {
+        let lens: Vec<usize> = self
+            .shards()
+            .iter()
+            .map(|shard| shard.read().len())
+            .collect();
+        let total: usize = lens.iter().sum();
+        let mut sample = Vec::with_capacity(count.min(total));
+        if total == 0 {
+            return sample;
+        }
+        let mut state = seed;
+        for _ in 0..count {
+            let mut pick = (splitmix64(&mut state) % total as u64) as usize;
+            let idx = lens
+                .iter()
+                .position(|&len| {
+                    if pick < len {
+                        true
+                    } else {
+                        pick -= len;
+                        false
+                    }
+                })
+                .unwrap_or(0);
+            let shard = unsafe { self.get_rshard_unchecked(idx) };
+            // the bucket count is always a power of two
+            let mask = shard.buckets() - 1;
+            let first = splitmix64(&mut state) as usize & mask;
+            unsafe {
+                // UNSAFE(@ohsayan): the read guard keeps the buckets valid while we map the
+                // entry, and masking keeps the indices below the bucket count
+                let full = (0..SAMPLE_MAX_PROBES.min(mask + 1))
+                    .map(|offset| (first + offset) & mask)
+                    .find(|&idx| shard.is_bucket_full(idx));
+                if let Some(idx) = full {
+                    let (key, value) = shard.bucket(idx).as_ref();
+                    sample.push(f(key, value));
+                }
+            }
+        }
+        sample
+    }
+    /// Returns a uniform random sample of atmost `count` entries (mapped through `f`), picked
+    /// with reservoir sampling seeded with `seed`. Every bucket is visited, but only one shard is
+    /// read-locked at a time and only the entries that make it into the sample are mapped
+    pub fn reservoir_sample<T>(
+        &self,
+        count: usize,
+        seed: u64,
+        mut f: impl FnMut(&K, &V) -> T,
+    ) -> Vec<T> {
+        let mut sample = Vec::with_capacity(count.min(DEFAULT_CAP));
+        if count == 0 {
+            return sample;
+        }
+        let mut state = seed;
+        let mut seen = 0u64;
+        for shard in self.shards().iter() {
+            let shard = shard.read();
+            unsafe {
+                // UNSAFE(@ohsayan): the read guard keeps the buckets valid while we map them
+                for bucket in shard.iter() {
+                    let (key, value) = bucket.as_ref();
+                    seen += 1;
+                    if sample.len() < count {
+                        sample.push(f(key, value));
+                    } else {
+                        // the entry replaces one in the sample with a probability of count/seen
+                        let pick = (splitmix64(&mut state) % seen) as usize;
+                        if pick < count {
+                            sample[pick] = f(key, value);
+                        }
+                    }
+                }
+            }
+        }
+        sample
+    }
+    /// Returns a key chosen uniformly at random using the provided random number, or `None` if
+    /// the map is empty. The shard is picked in proportion to the number of entries it has and
+    /// then the key is picked from that shard (with only that shard read-locked)
+    pub fn random_key(&self, random: u64) -> Option<K>
+    where
+        K: Clone,
+    {
+        let lens: Vec<usize> = self
+            .shards()
+            .iter()
+            .map(|shard| shard.read().len())
+            .collect();
+        let total: usize = lens.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = (random % total as u64) as usize;
+        let (idx, _) = lens.iter().enumerate().find(|&(_, &len)| {
+            if pick < len {
+                true
+            } else {
+                pick -= len;
+                false
+            }
+        })?;
+        let shard = unsafe { self.get_rshard_unchecked(idx) };
+        unsafe {
+            // UNSAFE(@ohsayan): the read guard keeps the buckets valid while we clone the key.
+            // The shard might have shrunk since we looked at its length, in which case we fall
+            // back to its last entry
+            let bucket = match shard.iter().nth(pick) {
+                Some(bucket) => Some(bucket),
+                None => shard.iter().last(),
+            };
+            bucket.map(|bucket| bucket.as_ref().0.clone())
+        }
+    }
+    /// Examines atmost `limit` keys, resuming from `cursor`, and returns the ones that satisfy
+    /// `filter` along with the cursor to resume from in the next call. A cursor of `0` starts
+    /// a fresh scan and a returned cursor of `0` means that the scan is complete. Since the
+    /// limit bounds the keys that are examined (and not the ones that are returned), a call can
+    /// return no keys at all while the scan isn't complete.
+    ///
+    /// Keys are visited in the order of their [scan positions](scan_position), which only
+    /// depend on their hashes, and the cursor is the position to resume from. So a key that is
+    /// present throughout the scan is returned exactly once even if shards are resized (or
+    /// compacted) mid-scan. Keys that share a position are always examined together, so a call
+    /// can examine a few more than `limit` keys if they happen to. Only one shard is read-locked
+    /// at a time, but the keys of the shard that the scan is in are hashed on every call
+    pub fn scan_keys(
+        &self,
+        cursor: u64,
+        limit: usize,
+        mut filter: impl FnMut(&K) -> bool,
+    ) -> (Vec<K>, u64)
+    where
+        K: Clone,
+    {
+        let mut shard_idx = (cursor as usize) >> self.shift;
+        let mut from = cursor;
+        let mut keys = Vec::with_capacity(limit.min(DEFAULT_CAP));
+        let mut examined = 0;
+        while shard_idx < self.shards().len() {
+            let shard = unsafe { self.get_rshard_unchecked(shard_idx) };
+            unsafe {
+                // UNSAFE(@ohsayan): the read guard keeps the buckets valid while we look at
+                // them and clone the keys
+                let mut pending: Vec<(u64, &K)> = shard
+                    .iter()
+                    .map(|bucket| &bucket.as_ref().0)
+                    .map(|key| (scan_position(make_hash::<K, K, S>(self.h(), key)), key))
+                    .filter(|(position, _)| *position >= from)
+                    .collect();
+                pending.sort_unstable_by_key(|(position, _)| *position);
+                let mut last = None;
+                for (position, key) in pending {
+                    if examined >= limit && last.is_some() && last != Some(position) {
+                        // we've done enough; resume at this key the next time
+                        return (keys, position);
+                    }
+                    last = Some(position);
+                    examined += 1;
+                    if filter(key) {
+                        keys.push(key.clone());
+                    }
+                }
+            }
+            shard_idx += 1;
+            // every position in the next shard is after the ones in this shard
+            from = 0;
+        }
+        (keys, 0)
+    }
 }
 
 // cloned impls
@@ -437,3 +880,333 @@ fn test_entry() {
     assert!(map.entry("hello").is_occupied());
     assert!(map.entry("world").is_vacant());
 }
+
+#[test]
+fn test_entry_or_insert() {
+    let map = Skymap::default();
+    assert_eq!(*map.entry("hello").or_insert(1), 1);
+    // the entry is occupied, so nothing is inserted
+    assert_eq!(*map.entry("hello").or_insert(2), 1);
+    assert_eq!(*map.entry("world").or_insert_with(|| 3), 3);
+    assert_eq!(*map.entry("world").or_insert_with(|| unreachable!()), 3);
+}
+
+#[test]
+fn test_entry_and_modify() {
+    let map = Skymap::default();
+    // the entry is vacant, so nothing is modified
+    assert_eq!(*map.entry("hits").and_modify(|v| *v += 1).or_insert(1), 1);
+    assert_eq!(*map.entry("hits").and_modify(|v| *v += 1).or_insert(1), 2);
+}
+
+#[test]
+fn test_shrink_on_remove() {
+    let map = Skymap::default();
+    // enough keys for every shard to grow past the minimum capacity
+    let (count, keep) = (map.shards().len() * 256, map.shards().len());
+    (0..count).for_each(|i| {
+        map.insert(i, i);
+    });
+    let grown = map.capacity();
+    (keep..count).for_each(|i| {
+        map.remove(&i);
+    });
+    assert_eq!(map.len(), keep);
+    assert!(map.capacity() < grown / SHRINK_RATIO);
+    (0..keep).for_each(|i| assert_eq!(*map.get(&i).unwrap(), i));
+}
+
+#[test]
+fn test_compact() {
+    let map: Skymap<&str, &str> = Skymap::with_capacity(10_000);
+    map.insert("hello", "world");
+    let before = map.capacity();
+    map.compact();
+    assert!(map.capacity() < before);
+    assert_eq!(*map.get("hello").unwrap(), "world");
+    map.clear();
+    map.compact();
+    assert_eq!(map.capacity(), 0);
+}
+
+#[test]
+fn test_reserve() {
+    let map = Skymap::default();
+    map.insert(0, 0);
+    let count = map.shards().len() * 1024;
+    map.reserve(count);
+    assert!(map.capacity() >= count);
+    // the entries are still there
+    assert_eq!(map.len(), 1);
+    assert_eq!(*map.get(&0).unwrap(), 0);
+}
+
+#[test]
+fn test_sample_keys() {
+    let map = Skymap::default();
+    map.insert("hello1", "world");
+    map.insert("hello2", "world");
+    map.insert("hello3", "world");
+    let empty: Skymap<&str, &str> = Skymap::default();
+    assert!(empty.sample_keys(10, 42).is_empty());
+    assert_eq!(map.sample_keys(2, 7).len(), 2);
+    let mut sample = map.sample_keys(64, 42);
+    assert_eq!(sample.len(), 64);
+    // the keys are picked at random, so all of them turn up in a large enough sample
+    sample.sort_unstable();
+    sample.dedup();
+    assert_eq!(sample, vec!["hello1", "hello2", "hello3"]);
+}
+
+#[test]
+fn test_reservoir_sample() {
+    let map = Skymap::default();
+    assert!(map.reservoir_sample(10, 42, |k, _| *k).is_empty());
+    for i in 0..100 {
+        map.insert(i, i * 10);
+    }
+    let sample = map.reservoir_sample(10, 42, |k, v| (*k, *v));
+    assert_eq!(sample.len(), 10);
+    assert!(sample.iter().all(|(k, v)| *v == k * 10));
+    let mut keys: Vec<i32> = sample.into_iter().map(|(k, _)| k).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    assert_eq!(keys.len(), 10);
+    // asking for more than there is returns everything
+    assert_eq!(map.reservoir_sample(1000, 42, |k, _| *k).len(), 100);
+    // and every key is about as likely to be picked
+    let mut picks = vec![0; 100];
+    for seed in 0..1000 {
+        for k in map.reservoir_sample(10, seed, |k, _| *k) {
+            picks[k as usize] += 1;
+        }
+    }
+    assert!(picks.iter().all(|count| (30..=200).contains(count)));
+}
+
+#[test]
+fn test_scan_keys() {
+    let map = Skymap::default();
+    for i in 0..100 {
+        map.insert(format!("user:{}", i), i);
+        map.insert(format!("post:{}", i), i);
+    }
+    let mut cursor = 0;
+    let mut seen = Vec::new();
+    let mut calls = 0;
+    loop {
+        let (keys, next) = map.scan_keys(cursor, 7, |k| k.starts_with("user:"));
+        assert!(keys.len() <= 7);
+        calls += 1;
+        seen.extend(keys);
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    seen.sort_unstable();
+    let mut expected: Vec<String> = (0..100).map(|i| format!("user:{}", i)).collect();
+    expected.sort_unstable();
+    assert_eq!(seen, expected);
+    // the limit bounds the keys that are examined, so the posts need calls of their own
+    assert!(calls >= 200 / 7);
+}
+
+#[test]
+fn test_scan_keys_examines_atmost_limit() {
+    let map = Skymap::default();
+    for i in 0..100 {
+        map.insert(format!("key:{}", i), i);
+    }
+    let mut examined = 0;
+    let (keys, next) = map.scan_keys(0, 10, |_| {
+        examined += 1;
+        false
+    });
+    // nothing matched, but the scan still stops and hands back a cursor
+    assert!(keys.is_empty());
+    assert_ne!(next, 0);
+    assert_eq!(examined, 10);
+}
+
+#[test]
+fn test_scan_keys_across_resizes() {
+    let map = Skymap::default();
+    for i in 0..1000 {
+        map.insert(format!("key:{}", i), i);
+    }
+    let mut seen = std::collections::HashMap::new();
+    let mut cursor = 0;
+    let mut calls = 0;
+    loop {
+        let (keys, next) = map.scan_keys(cursor, 50, |_| true);
+        for key in keys {
+            *seen.entry(key).or_insert(0) += 1;
+        }
+        calls += 1;
+        if calls == 3 {
+            // grow every shard mid-scan
+            for i in 0..20_000 {
+                map.insert(format!("extra:{}", i), i);
+            }
+        } else if calls == 6 {
+            // and then shrink them back
+            for i in 0..20_000 {
+                map.remove(&format!("extra:{}", i));
+            }
+            map.compact();
+        }
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    assert!(calls > 6);
+    assert!((0..1000).all(|i| seen.get(&format!("key:{}", i)) == Some(&1)));
+    assert!(seen.values().all(|count| *count == 1));
+}
+
+#[test]
+fn test_custom_hasher() {
+    use std::collections::hash_map::RandomState;
+    let map: Skymap<String, usize, RandomState> = Skymap::with_hasher(RandomState::new());
+    for i in 0..100 {
+        map.insert(i.to_string(), i);
+    }
+    assert_eq!(map.len(), 100);
+    assert!((0..100).all(|i| *map.get(&i.to_string()).unwrap() == i));
+}
+
+#[test]
+fn test_retain() {
+    let map = Skymap::default();
+    for i in 0..100 {
+        map.insert(i, i * 10);
+    }
+    let removed = map.retain(|k, v| {
+        *v += 1;
+        k % 2 == 0
+    });
+    assert_eq!(removed, 50);
+    assert_eq!(map.len(), 50);
+    assert!((0..100).all(|i| match map.get(&i) {
+        Some(v) => i % 2 == 0 && *v == i * 10 + 1,
+        None => i % 2 == 1,
+    }));
+}
+
+#[test]
+fn test_remove_many() {
+    let map = Skymap::default();
+    for i in 0..10 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.remove_many(&[1, 2, 3, 42]), 3);
+    assert_eq!(map.len(), 7);
+}
+
+#[test]
+fn test_clear_with() {
+    let map = Skymap::default();
+    for i in 0..10 {
+        map.insert(i, i);
+    }
+    let mut cleared = Vec::new();
+    map.clear_with(|k, v| cleared.push((k, v)));
+    cleared.sort_unstable();
+    assert!(map.is_empty());
+    assert_eq!(cleared, (0..10).map(|i| (i, i)).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_shards_are_independent() {
+    let map: Skymap<usize, usize> = Skymap::default();
+    let shard_of =
+        |key: &usize| map.determine_shard(make_hash::<usize, usize, _>(map.h(), key) as usize);
+    let other = (1..).find(|key| shard_of(key) != shard_of(&0)).unwrap();
+    map.insert(0, 0);
+    map.insert(other, other);
+    // holding a write lock on one shard must not block access to any other shard (this would
+    // deadlock otherwise)
+    let entry = map.get_mut(&0).unwrap();
+    map.insert(other, 42);
+    assert_eq!(*map.get(&other).unwrap(), 42);
+    drop(entry);
+}
+
+#[test]
+fn test_upsert() {
+    let map = Skymap::default();
+    assert_eq!(map.upsert("sayan", 1), None);
+    assert_eq!(map.upsert("sayan", 2), Some(1));
+    assert_eq!(*map.get("sayan").unwrap(), 2);
+}
+
+#[test]
+fn test_get_or_insert_with() {
+    let map = Skymap::default();
+    *map.get_or_insert_with("hits", || 0) += 1;
+    *map.get_or_insert_with("hits", || unreachable!()) += 1;
+    assert_eq!(*map.get("hits").unwrap(), 2);
+}
+
+#[test]
+fn test_stats() {
+    let map = Skymap::default();
+    assert_eq!(map.stats().occupied, 0);
+    for i in 0..1000 {
+        map.insert(i, i);
+    }
+    let stats = map.stats();
+    assert_eq!(stats.occupied, 1000);
+    assert!(stats.buckets >= 1000);
+    // nothing was removed
+    assert_eq!(stats.tombstones, 0);
+    assert!(stats.load_factor() > 0.0 && stats.load_factor() <= 0.875);
+}
+
+#[test]
+fn test_random_key() {
+    let map = Skymap::default();
+    assert_eq!(map.random_key(42), None);
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+    let mut seen = std::collections::HashSet::new();
+    for random in 0..100 {
+        seen.insert(map.random_key(random).unwrap());
+    }
+    // every key maps to a distinct position, so consecutive numbers return every key
+    assert_eq!(seen.len(), 100);
+}
+
+#[test]
+fn test_take() {
+    let map = Skymap::default();
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+    let taken = map.take();
+    assert!(map.is_empty());
+    assert_eq!(taken.len(), 100);
+    assert!((0..100).all(|i| *taken.get(&i).unwrap() == i));
+    // the old map is still usable
+    map.insert(1, 2);
+    assert_eq!(*map.get(&1).unwrap(), 2);
+}
+
+#[test]
+fn test_freeze_with() {
+    let map = Skymap::default();
+    for i in 0..100 {
+        map.insert(i, i);
+    }
+    let frozen = map.freeze_with(|v| v * 2);
+    map.clear();
+    map.insert(1, 1);
+    assert_eq!(frozen.len(), 100);
+    assert!((0..100).all(|i| *frozen.get(&i).unwrap() == i * 2));
+    // the copy is a map like any other
+    frozen.insert(1000, 0);
+    assert_eq!(frozen.len(), 101);
+}
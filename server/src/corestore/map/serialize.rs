@@ -0,0 +1,145 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Serde support
+//!
+//! Whole-map (de)serialization for the [`Skymap`] that works on the shards directly, without
+//! first copying the entries into a `std` map. Serialization only read-locks one shard at a
+//! time, so the map must not be modified while it is being serialized: if it is, the length
+//! that we announced won't match and serialization fails (instead of emitting a corrupted map)
+
+use {
+    super::Skymap,
+    crate::corestore::htable::Coremap,
+    core::{
+        fmt,
+        hash::{BuildHasher, Hash},
+        marker::PhantomData,
+    },
+    serde::{
+        de::{Deserialize, Deserializer, MapAccess, Visitor},
+        ser::{Error, Serialize, SerializeMap, Serializer},
+    },
+};
+
+impl<K, V, S> Serialize for Skymap<K, V, S>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let len = self.len();
+        let mut map = serializer.serialize_map(Some(len))?;
+        let mut written = 0;
+        for kv in self.iter() {
+            written += 1;
+            if written > len {
+                break;
+            }
+            map.serialize_entry(kv.key(), kv.value())?;
+        }
+        if written != len {
+            return Err(Ser::Error::custom("map was modified during serialization"));
+        }
+        map.end()
+    }
+}
+
+struct SkymapVisitor<K, V, S> {
+    _m: PhantomData<fn() -> Skymap<K, V, S>>,
+}
+
+impl<'de, K, V, S> Visitor<'de> for SkymapVisitor<K, V, S>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default + Clone,
+{
+    type Value = Skymap<K, V, S>;
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let map = Skymap::with_capacity_and_hasher(access.size_hint().unwrap_or(0), S::default());
+        while let Some((k, v)) = access.next_entry()? {
+            map.insert(k, v);
+        }
+        Ok(map)
+    }
+}
+
+impl<'de, K, V, S> Deserialize<'de> for Skymap<K, V, S>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default + Clone,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(SkymapVisitor { _m: PhantomData })
+    }
+}
+
+impl<K, V, S> Serialize for Coremap<K, V, S>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<'de, K, V, S> Deserialize<'de> for Coremap<K, V, S>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default + Clone,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Skymap::deserialize(deserializer).map(|inner| Coremap { inner })
+    }
+}
+
+#[test]
+fn test_roundtrip() {
+    let map: Skymap<String, u64> = Skymap::default();
+    for i in 0..1000 {
+        map.insert(i.to_string(), i);
+    }
+    let encoded = bincode::serialize(&map).unwrap();
+    let decoded: Skymap<String, u64> = bincode::deserialize(&encoded).unwrap();
+    assert_eq!(decoded.len(), 1000);
+    assert!((0..1000).all(|i| *decoded.get(&i.to_string()).unwrap() == i));
+}
+
+#[test]
+fn test_coremap_roundtrip() {
+    let map: Coremap<u64, Vec<u8>> = (0..100).map(|i| (i, vec![i as u8; 4])).collect();
+    let encoded = bincode::serialize(&map).unwrap();
+    let decoded: Coremap<u64, Vec<u8>> = bincode::deserialize(&encoded).unwrap();
+    assert_eq!(decoded.len(), 100);
+    assert!((0..100).all(|i| *decoded.get(&i).unwrap() == vec![i as u8; 4]));
+}
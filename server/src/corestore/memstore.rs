@@ -333,6 +333,138 @@ impl Memstore {
     pub fn list_keyspaces(&self) -> Vec<ObjectID> {
         self.keyspaces.iter().map(|kv| kv.key().clone()).collect()
     }
+    /// Purge all the expired keys in every table across all keyspaces, returning the
+    /// number of purged keys
+    pub fn sweep_expired(&self) -> usize {
+        self.keyspaces
+            .iter()
+            .map(|ks| {
+                ks.value()
+                    .tables
+                    .iter()
+                    .map(|tbl| tbl.value().sweep_expired())
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+    /// Restore the user keyspaces from a `snapshot` store, returning the number of tables that
+    /// couldn't be restored
+    ///
+    /// The data of every table in the snapshot replaces the data of the live table (which is
+    /// created if it doesn't exist). If the live table has a different model and is currently
+    /// in use, it is skipped to avoid "ghost tables". Keyspaces and tables that aren't a part of
+    /// the snapshot are left untouched and so is the system keyspace
+    ///
+    /// **Trip switch handled:** Yes
+    pub fn restore_from(&self, snapshot: Memstore) -> usize {
+        let mut skipped = 0;
+        let mut tree_changed = false;
+        for (ksid, snap_ks) in snapshot.keyspaces {
+            if ksid.eq(&SYSTEM) {
+                continue;
+            }
+            let live_ks = match self.keyspaces.get_cloned(&ksid) {
+                Some(ks) => ks,
+                None => {
+                    // this keyspace was dropped after the snapshot was taken
+                    self.keyspaces.upsert(ksid, snap_ks);
+                    tree_changed = true;
+                    continue;
+                }
+            };
+            let snap_tables = match Arc::try_unwrap(snap_ks) {
+                Ok(ks) => ks.tables,
+                Err(_) => unsafe { impossible!() },
+            };
+            for (tblid, snap_tbl) in snap_tables {
+                match live_ks.tables.get_cloned(&tblid) {
+                    None => {
+                        live_ks.tables.upsert(tblid, snap_tbl);
+                        tree_changed = true;
+                    }
+                    // volatile tables never have data in a snapshot, so keep what we have
+                    Some(live_tbl) if live_tbl.is_volatile() && snap_tbl.is_volatile() => {}
+                    Some(live_tbl) => {
+                        let snap_tbl = match Arc::try_unwrap(snap_tbl) {
+                            Ok(tbl) => tbl,
+                            Err(_) => unsafe { impossible!() },
+                        };
+                        if let Err(snap_tbl) = live_tbl.replace_with(snap_tbl) {
+                            // the model has changed since the snapshot was taken; one ref is
+                            // held by the keyspace and the other one is ours
+                            let no_one_is_using_table = Arc::strong_count(&live_tbl) == 2;
+                            if no_one_is_using_table {
+                                live_ks.tables.upsert(tblid, Arc::new(snap_tbl));
+                                tree_changed = true;
+                            } else {
+                                skipped += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if tree_changed {
+            registry::get_preload_tripswitch().trip();
+        }
+        skipped
+    }
+    /// Returns all the user tables across all keyspaces
+    pub fn list_all_tables(&self) -> Vec<Arc<Table>> {
+        self.keyspaces
+            .iter()
+            .flat_map(|ks| {
+                ks.value()
+                    .tables
+                    .iter()
+                    .map(|tbl| tbl.value().clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+    /// Returns a copy of the whole tree that can be flushed after writers are let back into
+    /// this store. The keyspaces and tables are copied one at a time and every table is
+    /// copied shard by shard (see [`Table::freeze`]), so the copy is only point-in-time if no
+    /// writes run while it's being made: the caller should hold the transaction lock
+    /// exclusively, which holds up every query for as long as the copy takes (it's O(n), but
+    /// only the handles of the keys and values are copied)
+    pub fn freeze(&self) -> Self {
+        self.freeze_with(Table::freeze)
+    }
+    /// Same as [`Memstore::freeze`], but the copy is saved in place of this store (like with
+    /// BGSAVE), so the tables hand over their dirty keys (see [`Table::freeze_for_save`]).
+    /// The caller must hold the transaction lock exclusively, and has to call
+    /// [`Memstore::merge_saved`] once the copy is saved
+    pub fn freeze_for_save(&self) -> Self {
+        self.freeze_with(Table::freeze_for_save)
+    }
+    fn freeze_with(&self, freeze_table: fn(&Table) -> Table) -> Self {
+        let keyspaces = self.keyspaces.freeze_with(|ks| {
+            let tables = ks.tables.freeze_with(|tbl| Arc::new(freeze_table(tbl)));
+            Arc::new(Keyspace::init_with_all_def_strategy(tables))
+        });
+        let system = self
+            .system
+            .tables
+            .freeze_with(|tbl| Wrapper::new(tbl.freeze()));
+        Self::init_with_all(keyspaces, SystemKeyspace::new(system))
+    }
+    /// Take back the dirty keys from a copy made by [`Memstore::freeze_for_save`]. Tables
+    /// can't be created or dropped while the copy is saved (DDL waits on the flush lock), so
+    /// every table has its copy
+    pub fn merge_saved(&self, saved: &Self) {
+        for ks in self.keyspaces.iter() {
+            let saved_ks = match saved.keyspaces.get(ks.key()) {
+                Some(saved_ks) => saved_ks,
+                None => continue,
+            };
+            for tbl in ks.value().tables.iter() {
+                if let Some(saved_tbl) = saved_ks.value().tables.get(tbl.key()) {
+                    tbl.value().merge_saved(saved_tbl.value());
+                }
+            }
+        }
+    }
 }
 
 /// System keyspace
@@ -393,6 +525,15 @@ impl Keyspace {
     pub fn table_count(&self) -> usize {
         self.tables.len()
     }
+    /// Returns an estimate of the number of bytes used by the data in all the tables of this
+    /// keyspace, from a random sample of atmost `samples` entries of every table (see
+    /// [`Table::estimate_memory_usage`]). Every table is sampled with a new seed from `seed`
+    pub fn estimate_memory_usage(&self, samples: usize, mut seed: impl FnMut() -> u64) -> usize {
+        self.tables
+            .iter()
+            .map(|tbl| tbl.estimate_memory_usage(samples, seed()))
+            .sum()
+    }
     /// Get an atomic reference to a table in this keyspace if it exists
     pub fn get_table_atomic_ref<Q>(&self, table_identifier: &Q) -> Option<Arc<Table>>
     where
@@ -28,18 +28,30 @@ use {
     crate::{
         actions::{translate_ddl_error, ActionResult},
         blueql::Entity,
+        cluster::Cluster,
+        config::RuntimeConfig,
         corestore::{
             memstore::{DdlError, Keyspace, Memstore, ObjectID, DEFAULT},
+            stats::ServerStats,
             table::{DescribeTable, Table},
         },
         protocol::interface::ProtocolSpec,
+        queryengine::{hooks::Hooks, monitor::Monitor, quota::Quotas},
         registry,
+        replication::Replication,
+        scripting::ScriptCache,
+        services::jobs::Jobs,
         storage::{
             self,
-            v1::{error::StorageEngineResult, sengine::SnapshotEngine},
+            v1::{
+                aof::AppendOnlyLog, error::StorageEngineResult, interface::DataDir,
+                sengine::SnapshotEngine,
+            },
         },
+        streams::Streams,
         util::{self, Unwrappable},
     },
+    arc_swap::ArcSwap,
     core::{borrow::Borrow, hash::Hash},
     std::sync::Arc,
 };
@@ -56,11 +68,15 @@ pub mod lock;
 pub mod map;
 pub mod memstore;
 pub mod rc;
+pub mod slab;
+pub mod spill;
+pub mod stats;
 pub mod table;
 #[cfg(test)]
 mod tests;
+pub mod zset;
 
-pub use self::rc::SharedSlice;
+pub use self::rc::{SharedSlice, ToSharedSlice};
 
 pub(super) type KeyspaceResult<T> = Result<T, DdlError>;
 
@@ -106,13 +122,38 @@ pub struct Corestore {
     store: Arc<Memstore>,
     /// the snapshot engine
     sengine: Arc<SnapshotEngine>,
+    /// the append-only log (if enabled)
+    aof: Option<Arc<AppendOnlyLog>>,
+    /// the server statistics
+    stats: Arc<ServerStats>,
+    /// the channel on which every query is sent to the monitors
+    monitor: Arc<Monitor>,
+    /// the hooks that every query passes through
+    hooks: Arc<Hooks>,
+    /// the replication state (and the feed on which every write is sent to the replicas)
+    replication: Arc<Replication>,
+    /// the slot table (if in cluster mode)
+    cluster: Arc<Cluster>,
+    /// the compiled scripts
+    scripts: Arc<ScriptCache>,
+    /// the quotas of the keyspaces and the users
+    quotas: Arc<Quotas>,
+    /// the background jobs
+    jobs: Arc<Jobs>,
+    /// the streams of every keyspace
+    streams: Arc<Streams>,
+    /// the settings that can be reloaded while the server is running
+    runtime: Arc<ArcSwap<RuntimeConfig>>,
 }
 
 impl Corestore {
     /// This is the only function you'll ever need to either create a new database instance
-    /// or restore from an earlier instance
-    pub fn init_with_snapcfg(sengine: Arc<SnapshotEngine>) -> StorageEngineResult<Self> {
-        let store = storage::unflush::read_full()?;
+    /// or restore from an earlier instance (in the given data directory)
+    pub fn init_with_snapcfg(
+        dir: &DataDir,
+        sengine: Arc<SnapshotEngine>,
+    ) -> StorageEngineResult<Self> {
+        let store = storage::unflush::read_full(dir)?;
         Ok(Self::default_with_store(store, sengine))
     }
     pub fn clone_store(&self) -> Arc<Memstore> {
@@ -125,11 +166,84 @@ impl Corestore {
             estate: ConnectionEntityState::default(cks, ctable),
             store: Arc::new(store),
             sengine,
+            aof: None,
+            stats: Arc::new(ServerStats::new()),
+            monitor: Arc::new(Monitor::new()),
+            hooks: Arc::new(Hooks::default()),
+            replication: Arc::new(Replication::new()),
+            cluster: Arc::new(Cluster::new()),
+            scripts: Arc::new(ScriptCache::default()),
+            quotas: Arc::new(Quotas::default()),
+            jobs: Arc::new(Jobs::default()),
+            streams: Arc::new(Streams::new()),
+            runtime: RuntimeConfig::default().into_shared(),
         }
     }
     pub fn get_engine(&self) -> &SnapshotEngine {
         &self.sengine
     }
+    /// Start recording writes in the given append-only log. This only affects this instance
+    /// and the instances that are cloned from it _after_ this call
+    pub fn set_aof(&mut self, aof: Arc<AppendOnlyLog>) {
+        self.aof = Some(aof);
+    }
+    /// Returns the append-only log, if enabled
+    pub fn get_aof(&self) -> Option<&AppendOnlyLog> {
+        self.aof.as_deref()
+    }
+    /// Use the given (persisted) streams. Like [`Corestore::set_aof`], this only affects this
+    /// instance and the instances that are cloned from it _after_ this call
+    pub fn set_streams(&mut self, streams: Arc<Streams>) {
+        self.streams = streams;
+    }
+    /// Returns the streams
+    pub fn get_streams(&self) -> &Streams {
+        &self.streams
+    }
+    /// Use the given runtime settings. Like [`Corestore::set_aof`], this only affects this
+    /// instance and the instances that are cloned from it _after_ this call
+    pub fn set_runtime_config(&mut self, runtime: Arc<ArcSwap<RuntimeConfig>>) {
+        self.runtime = runtime;
+    }
+    /// Returns the settings that can be reloaded while the server is running
+    pub fn get_runtime_config(&self) -> &ArcSwap<RuntimeConfig> {
+        &self.runtime
+    }
+    /// Returns the server statistics
+    pub fn get_stats(&self) -> &ServerStats {
+        &self.stats
+    }
+    /// Returns the query monitor
+    pub fn get_monitor(&self) -> &Monitor {
+        &self.monitor
+    }
+    /// Returns the query hooks
+    pub fn get_hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+    /// Returns the replication state
+    pub fn get_replication(&self) -> &Replication {
+        &self.replication
+    }
+    /// Returns the cluster state
+    pub fn get_cluster(&self) -> &Cluster {
+        &self.cluster
+    }
+    /// Returns the script cache
+    pub fn get_scripts(&self) -> &ScriptCache {
+        &self.scripts
+    }
+    /// Returns the quotas
+    pub fn get_quotas(&self) -> &Quotas {
+        &self.quotas
+    }
+    pub fn clone_quotas(&self) -> Arc<Quotas> {
+        self.quotas.clone()
+    }
+    /// Returns the background jobs
+    pub fn get_jobs(&self) -> &Jobs {
+        &self.jobs
+    }
     pub fn get_store(&self) -> &Memstore {
         &self.store
     }
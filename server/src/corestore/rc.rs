@@ -24,15 +24,19 @@
  *
 */
 
-use std::{
-    alloc::{alloc, dealloc, Layout},
-    borrow::Borrow,
-    fmt::Debug,
-    hash::Hash,
-    ops::Deref,
-    ptr::{self, NonNull},
-    slice,
-    sync::atomic::{self, AtomicUsize, Ordering},
+use {
+    super::slab,
+    std::{
+        alloc::{alloc, dealloc, Layout},
+        borrow::Borrow,
+        cmp,
+        fmt::Debug,
+        hash::Hash,
+        ops::Deref,
+        ptr::{self, NonNull},
+        slice,
+        sync::atomic::{self, AtomicUsize, Ordering},
+    },
 };
 
 /// A [`SharedSlice`] is a dynamically sized, heap allocated slice that can be safely shared across threads. This
@@ -40,8 +44,12 @@ use std::{
 /// a new heap allocation. Once init is complete, cloning only increments an atomic counter and when no more owners
 /// of this data exists, i.e the object is orphaned, it will call its destructor and clean up the heap allocation.
 /// Do note that two heap allocations are made:
-/// - One for the actual data
+/// - One for the actual data (small slices take a slot in a [slab](super::slab) instead)
 /// - One for the shared state
+///
+/// A slice can also be backed by a file that is mapped into memory (see [`SharedSlice::mapped`]),
+/// in which case the data isn't on the heap at all and the kernel can drop its pages whenever
+/// it needs the memory
 pub struct SharedSlice {
     inner: NonNull<SharedSliceInner>,
 }
@@ -68,6 +76,53 @@ impl SharedSlice {
             },
         }
     }
+    #[cfg(unix)]
+    /// Create a new [`SharedSlice`] by writing the slice out to `file` and mapping it into
+    /// memory. The mapping outlives the file handle, so the file can be closed (and unlinked)
+    /// right after this returns
+    pub fn mapped(slice: &[u8], mut file: std::fs::File) -> std::io::Result<Self> {
+        use std::{io::Write, os::unix::io::AsRawFd};
+        if slice.is_empty() {
+            // can't map an empty file
+            return Ok(Self::new(slice));
+        }
+        file.write_all(slice)?;
+        let data = unsafe {
+            // UNSAFE(@ohsayan): the file is ours alone and is never written to again
+            libc::mmap(
+                ptr::null_mut(),
+                slice.len(),
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if data == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        super::spill::acquire(slice.len());
+        let inner = SharedSliceInner {
+            data: data as *const u8,
+            len: slice.len(),
+            rc: AtomicUsize::new(1),
+            mapped: true,
+        };
+        Ok(Self {
+            inner: unsafe { NonNull::new_unchecked(Box::leak(Box::new(inner))) },
+        })
+    }
+    #[inline(always)]
+    /// Returns true if the data is in a file that is mapped into memory, rather than on the heap
+    pub fn is_mapped(&self) -> bool {
+        self.inner().mapped
+    }
+    #[inline(always)]
+    /// Returns true if both slices are clones of the same slice (which is cheaper than
+    /// comparing the data)
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
     #[inline(always)]
     /// Returns a reference to te inner heap allocation for shared state
     fn inner(&self) -> &SharedSliceInner {
@@ -79,13 +134,24 @@ impl SharedSlice {
         if self.len() != 0 {
             // IMPORTANT: Do not use the aligned pointer as a sentinel
             let inner = self.inner();
-            // heap array dtor
-            ptr::drop_in_place(slice::from_raw_parts_mut(inner.data as *mut u8, inner.len));
-            // dealloc heap array
-            dealloc(
-                inner.data as *mut u8,
-                Layout::array::<u8>(inner.len).unwrap(),
-            )
+            #[cfg(unix)]
+            if inner.mapped {
+                libc::munmap(inner.data as *mut libc::c_void, inner.len);
+                super::spill::release(inner.len);
+                drop(Box::from_raw(self.inner.as_ptr()));
+                return;
+            }
+            if slab::is_slab_allocated(inner.len) {
+                slab::free_slot(NonNull::new_unchecked(inner.data as *mut u8), inner.len);
+            } else {
+                // heap array dtor
+                ptr::drop_in_place(slice::from_raw_parts_mut(inner.data as *mut u8, inner.len));
+                // dealloc heap array
+                dealloc(
+                    inner.data as *mut u8,
+                    Layout::array::<u8>(inner.len).unwrap(),
+                )
+            }
         }
         // destroy shared state alloc
         drop(Box::from_raw(self.inner.as_ptr()))
@@ -203,8 +269,44 @@ impl From<Vec<u8>> for SharedSlice {
     }
 }
 
+/// Anything that a [`SharedSlice`] can be made from. A [`SharedSlice`] just hands out another
+/// handle to itself, so the places that keep a key around next to the map (the expiry and
+/// access metadata, the dirty keys and the notifications) share the key with the map instead
+/// of copying it, as long as they're given the key that's in the map
+pub trait ToSharedSlice: AsRef<[u8]> {
+    fn to_shared_slice(&self) -> SharedSlice;
+}
+
+impl ToSharedSlice for SharedSlice {
+    #[inline(always)]
+    fn to_shared_slice(&self) -> SharedSlice {
+        self.clone()
+    }
+}
+
+impl ToSharedSlice for [u8] {
+    #[inline(always)]
+    fn to_shared_slice(&self) -> SharedSlice {
+        SharedSlice::new(self)
+    }
+}
+
 impl Eq for SharedSlice {}
 
+impl PartialOrd for SharedSlice {
+    #[inline(always)]
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SharedSlice {
+    #[inline(always)]
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
 /// The shared state structure
 struct SharedSliceInner {
     /// data ptr
@@ -213,6 +315,8 @@ struct SharedSliceInner {
     len: usize,
     /// ref count
     rc: AtomicUsize,
+    /// whether the data is a file mapping
+    mapped: bool,
 }
 
 impl SharedSliceInner {
@@ -223,6 +327,9 @@ impl SharedSliceInner {
             if slice.is_empty() {
                 // HACK(@ohsayan): Just ensure that the address is aligned for this
                 layout.align() as *mut u8
+            } else if let Some(slot) = slab::alloc_slot(slice.len()) {
+                ptr::copy_nonoverlapping(slice.as_ptr(), slot.as_ptr(), slice.len());
+                slot.as_ptr()
             } else {
                 // UNSAFE(@ohsayan): Come on, just a malloc and memcpy
                 let array_ptr = alloc(layout);
@@ -234,6 +341,7 @@ impl SharedSliceInner {
             data,
             len: slice.len(),
             rc: AtomicUsize::new(1),
+            mapped: false,
         }
     }
 }
@@ -266,3 +374,24 @@ fn basic_cloned_across_threads() {
     handles.into_iter().for_each(|h| h.join().unwrap());
     assert_eq!(slice, ST);
 }
+
+#[cfg(unix)]
+#[test]
+fn mapped_cloned() {
+    use std::fs;
+    let path = std::env::temp_dir().join(format!("skyd-rc-mapped-{}", std::process::id()));
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    let slice = SharedSlice::mapped(b"hello", file).unwrap();
+    assert!(slice.is_mapped());
+    let slice_clone = slice.clone();
+    drop(slice);
+    assert_eq!(slice_clone, b"hello");
+    assert!(!SharedSlice::from("hello").is_mapped());
+}
@@ -0,0 +1,461 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Slab allocation for small slices
+//!
+//! The data of a [`SharedSlice`](super::SharedSlice) that is atmost [`SLAB_MAX`] bytes long
+//! isn't allocated on its own. Instead, it takes a slot in one of a few size classes, where
+//! every class carves its slots out of [`CHUNK_SIZE`] byte chunks. This keeps the many small
+//! values of a write-heavy workload from hitting the allocator (and fragmenting the heap) every
+//! time they're replaced.
+//!
+//! Every class is split into [`SHARDS`] shards and a thread always allocates from the same
+//! shard, so writers on different threads rarely wait on each other. A chunk belongs to the
+//! shard that allocated it and keeps its own free list (in a header at the start of the chunk,
+//! which is aligned to its size so that a slot can find it), so a slot goes back to the chunk
+//! it came from, no matter which thread drops it. Once all the slots of a chunk are free, the
+//! chunk is given back to the allocator, unless it's the last chunk of its shard with free
+//! slots (so that a shard that is hovering around a chunk boundary doesn't allocate and free a
+//! chunk every time). [`stats`] reports how much of the memory that is held is actually in use
+
+use {
+    core::{
+        mem,
+        ptr::{self, NonNull},
+        sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    parking_lot::{const_mutex, Mutex},
+    std::alloc::{alloc, dealloc, handle_alloc_error, Layout},
+};
+
+/// The largest slice that is slab allocated
+pub const SLAB_MAX: usize = 256;
+/// The size of the chunks that the slots are carved out of
+pub const CHUNK_SIZE: usize = 64 * 1024;
+/// The number of shards of every class
+const SHARDS: usize = 16;
+/// The sizes of the slots in each class
+const CLASSES: [usize; 5] = [16, 32, 64, 128, 256];
+/// Every slot is aligned to this (the free list keeps a pointer in each free slot)
+const SLOT_ALIGN: usize = 16;
+
+static SLABS: [SizeClass; CLASSES.len()] = [
+    SizeClass::new(CLASSES[0]),
+    SizeClass::new(CLASSES[1]),
+    SizeClass::new(CLASSES[2]),
+    SizeClass::new(CLASSES[3]),
+    SizeClass::new(CLASSES[4]),
+];
+
+/// A free slot, which points to the next free slot of its chunk (if any)
+struct FreeSlot {
+    next: *mut FreeSlot,
+}
+
+/// The header at the start of every chunk. Everything but `shard` is only ever touched with the
+/// lock of that shard held
+struct ChunkHeader {
+    /// the shard that the chunk belongs to (this never changes)
+    shard: *const Shard,
+    /// the first free slot
+    free: *mut FreeSlot,
+    /// the number of slots in use
+    used: usize,
+}
+
+/// The chunks of a shard that have free slots
+struct ChunkList {
+    chunks: Vec<NonNull<ChunkHeader>>,
+}
+
+// UNSAFE(@ohsayan): The chunks are only ever touched with the lock of their shard held
+unsafe impl Send for ChunkList {}
+
+// the shards are padded to keep the counters of different shards off the same cache line
+#[repr(align(128))]
+struct Shard {
+    partial: Mutex<ChunkList>,
+    /// the number of chunks
+    chunks: AtomicU64,
+    /// the number of slots in use
+    used: AtomicU64,
+}
+
+impl Shard {
+    const fn new() -> Self {
+        Self {
+            partial: const_mutex(ChunkList { chunks: Vec::new() }),
+            chunks: AtomicU64::new(0),
+            used: AtomicU64::new(0),
+        }
+    }
+}
+
+struct SizeClass {
+    slot_size: usize,
+    shards: [Shard; SHARDS],
+}
+
+impl SizeClass {
+    const fn new(slot_size: usize) -> Self {
+        // only used to initialize the array, so every shard gets a lock of its own
+        #[allow(clippy::declare_interior_mutable_const)]
+        const SHARD: Shard = Shard::new();
+        Self {
+            slot_size,
+            shards: [SHARD; SHARDS],
+        }
+    }
+    /// Take a slot from the shard of the current thread
+    fn alloc(&self) -> NonNull<u8> {
+        let shard = &self.shards[current_shard()];
+        let mut partial = shard.partial.lock();
+        let chunk = match partial.chunks.last() {
+            Some(chunk) => *chunk,
+            None => {
+                let chunk = unsafe {
+                    // UNSAFE(@ohsayan): The shard lives as long as the class (and that's as
+                    // long as the chunks since they're only freed through it)
+                    new_chunk(shard, self.slot_size)
+                };
+                shard.chunks.fetch_add(1, Ordering::Relaxed);
+                partial.chunks.push(chunk);
+                chunk
+            }
+        };
+        shard.used.fetch_add(1, Ordering::Relaxed);
+        unsafe {
+            // UNSAFE(@ohsayan): We hold the lock of the shard that the chunk belongs to and
+            // every chunk in the list has a free slot
+            let header = &mut *chunk.as_ptr();
+            let slot = header.free;
+            header.free = (*slot).next;
+            header.used += 1;
+            if header.free.is_null() {
+                // the chunk is full, and it's the last one in the list
+                partial.chunks.pop();
+            }
+            NonNull::new_unchecked(slot as *mut u8)
+        }
+    }
+    /// Returns the number of slots in use and the number of chunks, across all the shards
+    fn stats(&self) -> (u64, u64) {
+        self.shards.iter().fold((0, 0), |(used, chunks), shard| {
+            (
+                used + shard.used.load(Ordering::Relaxed),
+                chunks + shard.chunks.load(Ordering::Relaxed),
+            )
+        })
+    }
+}
+
+/// Returns the shard that the current thread allocates from
+fn current_shard() -> usize {
+    static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+    thread_local! {
+        static SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % SHARDS;
+    }
+    // a slice could be allocated while the thread is being torn down
+    SHARD.try_with(|shard| *shard).unwrap_or(0)
+}
+
+/// Returns the class that a slice of `len` bytes goes in, if it's slab allocated at all
+const fn class_of(len: usize) -> Option<usize> {
+    if len == 0 || len > SLAB_MAX {
+        return None;
+    }
+    let mut class = 0;
+    while CLASSES[class] < len {
+        class += 1;
+    }
+    Some(class)
+}
+
+/// Returns true if a slice of `len` bytes is slab allocated
+pub const fn is_slab_allocated(len: usize) -> bool {
+    class_of(len).is_some()
+}
+
+/// Returns a slot that can hold `len` bytes, or `None` if slices of this length aren't slab
+/// allocated (see [`is_slab_allocated`])
+pub fn alloc_slot(len: usize) -> Option<NonNull<u8>> {
+    class_of(len).map(|class| SLABS[class].alloc())
+}
+
+/// Give back a slot that was handed out by [`alloc_slot`] for `len` bytes
+///
+/// ## Safety
+/// The slot must have been allocated for the same length and must not be used after this
+pub unsafe fn free_slot(slot: NonNull<u8>, len: usize) {
+    if is_slab_allocated(len) {
+        release(slot)
+    }
+}
+
+/// Put the slot back in the free list of its chunk, and free the chunk if it's now unused
+unsafe fn release(slot: NonNull<u8>) {
+    let chunk = (slot.as_ptr() as usize & !(CHUNK_SIZE - 1)) as *mut ChunkHeader;
+    let shard = &*(*chunk).shard;
+    let mut partial = shard.partial.lock();
+    let header = &mut *chunk;
+    if header.free.is_null() {
+        // the chunk was full, so it wasn't in the list
+        partial.chunks.push(NonNull::new_unchecked(chunk));
+    }
+    let slot = slot.as_ptr() as *mut FreeSlot;
+    (*slot).next = header.free;
+    header.free = slot;
+    header.used -= 1;
+    shard.used.fetch_sub(1, Ordering::Relaxed);
+    if header.used == 0 && partial.chunks.len() > 1 {
+        if let Some(at) = partial.chunks.iter().position(|c| c.as_ptr() == chunk) {
+            partial.chunks.swap_remove(at);
+        }
+        drop(partial);
+        dealloc(chunk as *mut u8, chunk_layout());
+        shard.chunks.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn chunk_layout() -> Layout {
+    // chunks are aligned to their size so that a slot can find the header of its chunk
+    Layout::from_size_align(CHUNK_SIZE, CHUNK_SIZE).unwrap()
+}
+
+/// Returns the offset of the first slot of a chunk (the slots before it hold the header)
+const fn first_slot(slot_size: usize) -> usize {
+    (mem::size_of::<ChunkHeader>() + slot_size - 1) / slot_size * slot_size
+}
+
+/// Allocate a chunk for `shard` and thread all its slots into the free list of the chunk
+unsafe fn new_chunk(shard: &Shard, slot_size: usize) -> NonNull<ChunkHeader> {
+    let layout = chunk_layout();
+    let chunk = alloc(layout);
+    if chunk.is_null() {
+        handle_alloc_error(layout);
+    }
+    let first = first_slot(slot_size);
+    let slots = (CHUNK_SIZE - first) / slot_size;
+    for i in 0..slots {
+        let slot = chunk.add(first + i * slot_size) as *mut FreeSlot;
+        let next = if i + 1 == slots {
+            ptr::null_mut()
+        } else {
+            chunk.add(first + (i + 1) * slot_size) as *mut FreeSlot
+        };
+        (*slot).next = next;
+    }
+    let header = chunk as *mut ChunkHeader;
+    header.write(ChunkHeader {
+        shard,
+        free: chunk.add(first) as *mut FreeSlot,
+        used: 0,
+    });
+    NonNull::new_unchecked(header)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The memory held by the slabs
+pub struct SlabStats {
+    /// the number of slices in the slabs
+    pub slices: u64,
+    /// the number of bytes taken by the slots of those slices
+    pub used: u64,
+    /// the number of bytes in all the chunks
+    pub reserved: u64,
+}
+
+impl SlabStats {
+    /// Returns the fraction of the reserved memory that isn't in use
+    pub fn fragmentation(&self) -> f32 {
+        if self.reserved == 0 {
+            0.0
+        } else {
+            1.0 - (self.used as f32 / self.reserved as f32)
+        }
+    }
+}
+
+/// Returns the memory held by the slabs across all the classes
+pub fn stats() -> SlabStats {
+    SLABS.iter().fold(SlabStats::default(), |stats, slab| {
+        let (used, chunks) = slab.stats();
+        SlabStats {
+            slices: stats.slices + used,
+            used: stats.used + used * slab.slot_size as u64,
+            reserved: stats.reserved + chunks * CHUNK_SIZE as u64,
+        }
+    })
+}
+
+#[test]
+fn classes() {
+    assert_eq!(class_of(0), None);
+    assert_eq!(class_of(1), Some(0));
+    assert_eq!(class_of(16), Some(0));
+    assert_eq!(class_of(17), Some(1));
+    assert_eq!(class_of(SLAB_MAX), Some(CLASSES.len() - 1));
+    assert_eq!(class_of(SLAB_MAX + 1), None);
+}
+
+#[test]
+fn alloc_and_free() {
+    assert!(alloc_slot(SLAB_MAX + 1).is_none());
+    let slots: Vec<NonNull<u8>> = (0..CHUNK_SIZE / 100)
+        .map(|_| alloc_slot(100).unwrap())
+        .collect();
+    for (i, slot) in slots.iter().enumerate() {
+        assert_eq!(slot.as_ptr() as usize % SLOT_ALIGN, 0);
+        unsafe { ptr::write_bytes(slot.as_ptr(), i as u8, 100) };
+    }
+    // no slot was handed out twice
+    for (i, slot) in slots.iter().enumerate() {
+        let data = unsafe { core::slice::from_raw_parts(slot.as_ptr(), 100) };
+        assert!(data.iter().all(|b| *b == i as u8));
+    }
+    let stats = stats();
+    assert!(stats.slices >= slots.len() as u64);
+    assert!(stats.reserved >= stats.used);
+    for slot in slots {
+        unsafe { free_slot(slot, 100) };
+    }
+}
+
+#[test]
+fn empty_chunks_are_released() {
+    // the chunks point to their class, so it has to outlive them
+    let class: &'static SizeClass = Box::leak(Box::new(SizeClass::new(64)));
+    let per_chunk = (CHUNK_SIZE - first_slot(64)) / 64;
+    let slots: Vec<NonNull<u8>> = (0..3 * per_chunk).map(|_| class.alloc()).collect();
+    assert_eq!(class.stats(), (3 * per_chunk as u64, 3));
+    for slot in slots {
+        unsafe { release(slot) };
+    }
+    // one chunk is kept around for the next allocation
+    assert_eq!(class.stats(), (0, 1));
+    let slot = class.alloc();
+    assert_eq!(class.stats(), (1, 1));
+    unsafe { release(slot) };
+}
+
+#[test]
+fn slots_freed_on_other_threads() {
+    let class: &'static SizeClass = Box::leak(Box::new(SizeClass::new(32)));
+    let threads: Vec<_> = (0..8u8)
+        .map(|t| {
+            std::thread::spawn(move || {
+                let slots: Vec<NonNull<u8>> = (0..10_000).map(|_| class.alloc()).collect();
+                for slot in slots.iter() {
+                    unsafe { ptr::write_bytes(slot.as_ptr(), t, 32) };
+                }
+                // no slot was handed out twice
+                for slot in slots.iter() {
+                    let data = unsafe { core::slice::from_raw_parts(slot.as_ptr(), 32) };
+                    assert!(data.iter().all(|b| *b == t));
+                }
+                // hand them over to another thread, like a value that's dropped elsewhere
+                slots
+                    .into_iter()
+                    .map(|s| s.as_ptr() as usize)
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let slots: Vec<Vec<usize>> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    let (used, _) = class.stats();
+    assert_eq!(used, 80_000);
+    std::thread::spawn(move || {
+        for slot in slots.into_iter().flatten() {
+            unsafe { release(NonNull::new_unchecked(slot as *mut u8)) };
+        }
+    })
+    .join()
+    .unwrap();
+    // atmost one chunk is left in every shard
+    let (used, chunks) = class.stats();
+    assert_eq!(used, 0);
+    assert!(chunks <= SHARDS as u64);
+}
+
+#[cfg(all(test, feature = "nightly"))]
+mod benches {
+    //! The slabs are only worth it if they hold up against the system allocator when many
+    //! threads are replacing small values at once, which is what these compare
+
+    extern crate test;
+    use {super::*, std::thread, test::Bencher};
+
+    const THREADS: usize = 8;
+    const ROUNDS: usize = 10_000;
+    const LEN: usize = 64;
+
+    /// Every thread keeps a few thousand slices alive and keeps replacing the oldest one
+    fn contended(alloc: fn() -> NonNull<u8>, free: fn(NonNull<u8>)) {
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                thread::spawn(move || {
+                    let mut live = Vec::with_capacity(ROUNDS);
+                    for i in 0..ROUNDS {
+                        live.push(alloc().as_ptr() as usize);
+                        if i % 2 == 1 {
+                            free(NonNull::new(live.swap_remove(0) as *mut u8).unwrap());
+                        }
+                    }
+                    for slot in live {
+                        free(NonNull::new(slot as *mut u8).unwrap());
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    fn system_layout() -> Layout {
+        Layout::from_size_align(LEN, SLOT_ALIGN).unwrap()
+    }
+
+    #[bench]
+    fn contended_slab(b: &mut Bencher) {
+        b.iter(|| {
+            contended(
+                || alloc_slot(LEN).unwrap(),
+                |slot| unsafe { free_slot(slot, LEN) },
+            )
+        });
+    }
+
+    #[bench]
+    fn contended_system(b: &mut Bencher) {
+        b.iter(|| {
+            contended(
+                || NonNull::new(unsafe { alloc(system_layout()) }).unwrap(),
+                |slot| unsafe { dealloc(slot.as_ptr(), system_layout()) },
+            )
+        });
+    }
+}
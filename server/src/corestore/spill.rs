@@ -0,0 +1,139 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Spilling large values to disk
+//!
+//! If `storage.spillmin` is set, the values that are at least that many bytes long aren't kept
+//! on the heap. Each one is written to a file of its own in [`DIR_SPILL`] that is mapped into
+//! memory, so that the map only holds a handle to the mapping while the kernel is free to drop
+//! the pages of the value (and read them back in when the value is accessed). The file is
+//! unlinked as soon as it's mapped, so it's cleaned up once the value is dropped, even if the
+//! server crashes. This has nothing to do with persistence: the values are still saved along
+//! with every other value in the table. Since the values are written out as they are, spilling
+//! can't be used along with [encryption](crate::storage::v1::crypt)
+
+use {
+    super::SharedSlice,
+    crate::{
+        storage::v1::interface::{DataDir, DIR_SPILL},
+        IoResult,
+    },
+    core::sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    parking_lot::{const_rwlock, RwLock},
+    std::fs,
+};
+
+/// The size from which values are spilled (zero if spilling is disabled)
+static SPILLMIN: AtomicUsize = AtomicUsize::new(0);
+/// The number of spilled values
+static SPILLED_VALUES: AtomicU64 = AtomicU64::new(0);
+/// The size of the spilled values
+static SPILLED_BYTES: AtomicU64 = AtomicU64::new(0);
+/// Used to pick a name for the file of the next value
+static NEXT_FILE: AtomicU64 = AtomicU64::new(0);
+/// The directory that the values are spilled to
+static SPILL_DIR: RwLock<String> = const_rwlock(String::new());
+
+/// Set the size from which the values that are stored from now on are spilled to disk (zero
+/// disables spilling), into the spill directory in `dir`. Any files left behind by an earlier
+/// run (that crashed before it could unlink them) are removed
+pub fn configure(dir: &DataDir, spillmin: usize) -> IoResult<()> {
+    if spillmin != 0 {
+        if cfg!(not(unix)) {
+            log::warn!("Spilling values to disk is not supported on this platform");
+            return Ok(());
+        }
+        let spill_dir = dir.resolve(DIR_SPILL);
+        match fs::remove_dir_all(&spill_dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        fs::create_dir_all(&spill_dir)?;
+        *SPILL_DIR.write() = spill_dir;
+    }
+    SPILLMIN.store(spillmin, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Returns the number of values that are spilled to disk and their total size
+pub fn stats() -> (u64, u64) {
+    (
+        SPILLED_VALUES.load(Ordering::Relaxed),
+        SPILLED_BYTES.load(Ordering::Relaxed),
+    )
+}
+
+/// Move the value to disk if it's large enough to be spilled. If the value can't be spilled
+/// (or shouldn't be), it's returned as is
+pub fn offload(value: SharedSlice) -> SharedSlice {
+    let spillmin = SPILLMIN.load(Ordering::Relaxed);
+    if spillmin == 0 || value.len() < spillmin || value.is_mapped() {
+        return value;
+    }
+    match spill(&value) {
+        Ok(spilled) => spilled,
+        Err(e) => {
+            log::warn!(
+                "Failed to spill a value of {} bytes to disk: {e}",
+                value.len()
+            );
+            value
+        }
+    }
+}
+
+#[cfg(unix)]
+fn spill(value: &[u8]) -> IoResult<SharedSlice> {
+    let path = format!(
+        "{}/{}",
+        SPILL_DIR.read(),
+        NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+    );
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    SharedSlice::mapped(value, file)
+}
+
+#[cfg(not(unix))]
+fn spill(value: &[u8]) -> IoResult<SharedSlice> {
+    Ok(SharedSlice::new(value))
+}
+
+/// Account for a value that was spilled
+pub(super) fn acquire(len: usize) {
+    SPILLED_VALUES.fetch_add(1, Ordering::Relaxed);
+    SPILLED_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+}
+
+/// Account for a spilled value that was dropped
+pub(super) fn release(len: usize) {
+    SPILLED_VALUES.fetch_sub(1, Ordering::Relaxed);
+    SPILLED_BYTES.fetch_sub(len as u64, Ordering::Relaxed);
+}
@@ -0,0 +1,136 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Server statistics
+//!
+//! Counters that are shared by all the [`Corestore`](super::Corestore) instances and are
+//! updated by the connection layer, the query engine and the background services. The
+//! counters are only meant for reporting, so they use relaxed atomics throughout
+
+use {
+    crate::kvengine::now_millis,
+    core::sync::atomic::{AtomicU64, Ordering},
+    std::time::Instant,
+};
+
+const ORD_RLX: Ordering = Ordering::Relaxed;
+
+#[derive(Debug)]
+pub struct ServerStats {
+    /// the instant at which the server was started
+    started: Instant,
+    /// the number of queries that have been run
+    commands: AtomicU64,
+    /// the number of clients that are connected right now
+    clients: AtomicU64,
+    /// the number of clients that connected since the server was started
+    connections: AtomicU64,
+    /// the number of clients that were turned away because of the connection limit
+    rejected: AtomicU64,
+    /// the number of clients that were turned away because they're in a denied network
+    denied: AtomicU64,
+    /// the number of clients that were turned away because they aren't in an allowed network
+    notallowed: AtomicU64,
+    /// the time (in milliseconds since the UNIX epoch) at which the data was last saved to
+    /// disk (zero if it hasn't been saved since the server was started)
+    last_save: AtomicU64,
+}
+
+impl ServerStats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            commands: AtomicU64::new(0),
+            clients: AtomicU64::new(0),
+            connections: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            denied: AtomicU64::new(0),
+            notallowed: AtomicU64::new(0),
+            last_save: AtomicU64::new(0),
+        }
+    }
+    /// Returns the number of seconds since the server was started
+    pub fn uptime(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+    /// Record that `count` queries were run
+    pub fn record_commands(&self, count: usize) {
+        self.commands.fetch_add(count as u64, ORD_RLX);
+    }
+    pub fn commands(&self) -> u64 {
+        self.commands.load(ORD_RLX)
+    }
+    /// Record that a client connected
+    pub fn client_connected(&self) {
+        self.clients.fetch_add(1, ORD_RLX);
+        self.connections.fetch_add(1, ORD_RLX);
+    }
+    /// Record that a client disconnected
+    pub fn client_disconnected(&self) {
+        self.clients.fetch_sub(1, ORD_RLX);
+    }
+    /// Record that a client was turned away
+    pub fn client_rejected(&self) {
+        self.rejected.fetch_add(1, ORD_RLX);
+    }
+    /// Record that a client in a denied network was turned away
+    pub fn client_denied(&self) {
+        self.denied.fetch_add(1, ORD_RLX);
+    }
+    /// Record that a client outside the allowed networks was turned away
+    pub fn client_not_allowed(&self) {
+        self.notallowed.fetch_add(1, ORD_RLX);
+    }
+    pub fn clients(&self) -> u64 {
+        self.clients.load(ORD_RLX)
+    }
+    pub fn connections(&self) -> u64 {
+        self.connections.load(ORD_RLX)
+    }
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(ORD_RLX)
+    }
+    pub fn denied(&self) -> u64 {
+        self.denied.load(ORD_RLX)
+    }
+    pub fn not_allowed(&self) -> u64 {
+        self.notallowed.load(ORD_RLX)
+    }
+    /// Record that the data was just saved to disk
+    pub fn record_save(&self) {
+        self.last_save.store(now_millis(), ORD_RLX);
+    }
+    /// Returns the time (in seconds since the UNIX epoch) at which the data was last saved
+    pub fn last_save(&self) -> u64 {
+        self.last_save.load(ORD_RLX) / 1000
+    }
+}
+
+impl Default for ServerStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
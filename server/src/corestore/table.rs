@@ -29,9 +29,13 @@ use crate::corestore::{memstore::DdlError, KeyspaceResult};
 use crate::{
     actions::ActionResult,
     auth::Authmap,
-    corestore::{htable::Coremap, SharedSlice},
+    config::EvictionPolicy,
+    corestore::{htable::Coremap, map::MapStats, SharedSlice},
     dbnet::prelude::Corestore,
-    kvengine::{KVEListmap, KVEStandard, LockedVec},
+    kvengine::{
+        dirty::DirtyKeys, notify::Subscription, KVEHashmap, KVEListmap, KVESetmap, KVEStandard,
+        KVEZSetmap, LockedHash, LockedSet, LockedVec, LockedZSet,
+    },
     protocol::interface::ProtocolSpec,
     util,
 };
@@ -79,6 +83,45 @@ impl DescribeTable for KVEList {
     }
 }
 
+pub struct KVESet;
+
+impl DescribeTable for KVESet {
+    type Table = KVESetmap;
+    fn try_get(table: &Table) -> Option<&Self::Table> {
+        if let DataModel::KVExtSetmap(ref kvs) = table.model_store {
+            Some(kvs)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct KVEHash;
+
+impl DescribeTable for KVEHash {
+    type Table = KVEHashmap;
+    fn try_get(table: &Table) -> Option<&Self::Table> {
+        if let DataModel::KVExtHashmap(ref kvh) = table.model_store {
+            Some(kvh)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct KVEZSet;
+
+impl DescribeTable for KVEZSet {
+    type Table = KVEZSetmap;
+    fn try_get(table: &Table) -> Option<&Self::Table> {
+        if let DataModel::KVExtZSetmap(ref kvz) = table.model_store {
+            Some(kvz)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SystemDataModel {
     Auth(Authmap),
@@ -100,12 +143,23 @@ impl SystemTable {
     pub fn new_auth(authmap: Authmap) -> Self {
         Self::new(SystemDataModel::Auth(authmap))
     }
+    /// Returns a copy of this table that is detached from the live one
+    pub fn freeze(&self) -> Self {
+        match &self.data {
+            SystemDataModel::Auth(authmap) => {
+                Self::new_auth(Authmap::new(authmap.freeze_with(Clone::clone)))
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum DataModel {
     KV(KVEStandard),
     KVExtListmap(KVEListmap),
+    KVExtSetmap(KVESetmap),
+    KVExtHashmap(KVEHashmap),
+    KVExtZSetmap(KVEZSetmap),
 }
 
 // same 8 byte ptrs; any chance of optimizations?
@@ -144,10 +198,55 @@ impl Table {
             Err(DdlError::WrongModel)
         }
     }
+    /// Returns the number of keys in this table, leaving out the ones that have expired but
+    /// haven't been purged yet
     pub fn count(&self) -> usize {
         match &self.model_store {
-            DataModel::KV(kv) => kv.len(),
-            DataModel::KVExtListmap(kv) => kv.len(),
+            DataModel::KV(kv) => kv.live_len(),
+            DataModel::KVExtListmap(kv) => kv.live_len(),
+            DataModel::KVExtSetmap(kv) => kv.live_len(),
+            DataModel::KVExtHashmap(kv) => kv.live_len(),
+            DataModel::KVExtZSetmap(kv) => kv.live_len(),
+        }
+    }
+    /// Returns the occupancy statistics of the map holding this table's data
+    pub fn map_stats(&self) -> MapStats {
+        match &self.model_store {
+            DataModel::KV(kv) => kv.map_stats(),
+            DataModel::KVExtListmap(kv) => kv.map_stats(),
+            DataModel::KVExtSetmap(kv) => kv.map_stats(),
+            DataModel::KVExtHashmap(kv) => kv.map_stats(),
+            DataModel::KVExtZSetmap(kv) => kv.map_stats(),
+        }
+    }
+    /// Returns the number of key lookups that hit and missed in this table
+    pub fn lookup_stats(&self) -> (u64, u64) {
+        match &self.model_store {
+            DataModel::KV(kv) => kv.lookup_stats(),
+            DataModel::KVExtListmap(kv) => kv.lookup_stats(),
+            DataModel::KVExtSetmap(kv) => kv.lookup_stats(),
+            DataModel::KVExtHashmap(kv) => kv.lookup_stats(),
+            DataModel::KVExtZSetmap(kv) => kv.lookup_stats(),
+        }
+    }
+    /// Subscribe to the changes made to the keys in this table
+    pub fn subscribe(&self) -> Subscription {
+        match &self.model_store {
+            DataModel::KV(kv) => kv.subscribe(),
+            DataModel::KVExtListmap(kv) => kv.subscribe(),
+            DataModel::KVExtSetmap(kv) => kv.subscribe(),
+            DataModel::KVExtHashmap(kv) => kv.subscribe(),
+            DataModel::KVExtZSetmap(kv) => kv.subscribe(),
+        }
+    }
+    /// Returns the keys that were changed since this table was last saved in full
+    pub fn dirty_keys(&self) -> &DirtyKeys {
+        match &self.model_store {
+            DataModel::KV(kv) => kv.dirty_keys(),
+            DataModel::KVExtListmap(kv) => kv.dirty_keys(),
+            DataModel::KVExtSetmap(kv) => kv.dirty_keys(),
+            DataModel::KVExtHashmap(kv) => kv.dirty_keys(),
+            DataModel::KVExtZSetmap(kv) => kv.dirty_keys(),
         }
     }
     /// Returns this table's _description_
@@ -171,6 +270,33 @@ impl Table {
             6 if !self.is_volatile() => "Keymap { data:(str,list<binstr>), volatile:false }",
             7 if self.is_volatile() => "Keymap { data:(str,list<str>), volatile:true }",
             7 if !self.is_volatile() => "Keymap { data:(str,list<str>), volatile:false }",
+            // KVext => set
+            8 if self.is_volatile() => "Keymap { data:(binstr,set<binstr>), volatile:true }",
+            8 if !self.is_volatile() => "Keymap { data:(binstr,set<binstr>), volatile:false }",
+            9 if self.is_volatile() => "Keymap { data:(binstr,set<str>), volatile:true }",
+            9 if !self.is_volatile() => "Keymap { data:(binstr,set<str>), volatile:false }",
+            10 if self.is_volatile() => "Keymap { data:(str,set<binstr>), volatile:true }",
+            10 if !self.is_volatile() => "Keymap { data:(str,set<binstr>), volatile:false }",
+            11 if self.is_volatile() => "Keymap { data:(str,set<str>), volatile:true }",
+            11 if !self.is_volatile() => "Keymap { data:(str,set<str>), volatile:false }",
+            // KVext => hash
+            12 if self.is_volatile() => "Keymap { data:(binstr,hash<binstr>), volatile:true }",
+            12 if !self.is_volatile() => "Keymap { data:(binstr,hash<binstr>), volatile:false }",
+            13 if self.is_volatile() => "Keymap { data:(binstr,hash<str>), volatile:true }",
+            13 if !self.is_volatile() => "Keymap { data:(binstr,hash<str>), volatile:false }",
+            14 if self.is_volatile() => "Keymap { data:(str,hash<binstr>), volatile:true }",
+            14 if !self.is_volatile() => "Keymap { data:(str,hash<binstr>), volatile:false }",
+            15 if self.is_volatile() => "Keymap { data:(str,hash<str>), volatile:true }",
+            15 if !self.is_volatile() => "Keymap { data:(str,hash<str>), volatile:false }",
+            // KVext => sorted set
+            16 if self.is_volatile() => "Keymap { data:(binstr,zset<binstr>), volatile:true }",
+            16 if !self.is_volatile() => "Keymap { data:(binstr,zset<binstr>), volatile:false }",
+            17 if self.is_volatile() => "Keymap { data:(binstr,zset<str>), volatile:true }",
+            17 if !self.is_volatile() => "Keymap { data:(binstr,zset<str>), volatile:false }",
+            18 if self.is_volatile() => "Keymap { data:(str,zset<binstr>), volatile:true }",
+            18 if !self.is_volatile() => "Keymap { data:(str,zset<binstr>), volatile:false }",
+            19 if self.is_volatile() => "Keymap { data:(str,zset<str>), volatile:true }",
+            19 if !self.is_volatile() => "Keymap { data:(str,zset<str>), volatile:false }",
             _ => unsafe { impossible!() },
         }
     }
@@ -178,11 +304,131 @@ impl Table {
         match self.model_store {
             DataModel::KV(ref kv) => kv.truncate_table(),
             DataModel::KVExtListmap(ref kv) => kv.truncate_table(),
+            DataModel::KVExtSetmap(ref kv) => kv.truncate_table(),
+            DataModel::KVExtHashmap(ref kv) => kv.truncate_table(),
+            DataModel::KVExtZSetmap(ref kv) => kv.truncate_table(),
+        }
+    }
+    /// Move all the data out of this table, leaving it empty. The returned data is freed when
+    /// it is dropped, which can be done elsewhere (say, on a background thread)
+    pub fn detach_data(&self) -> Box<dyn Send> {
+        match self.model_store {
+            DataModel::KV(ref kv) => Box::new(kv.detach_table()),
+            DataModel::KVExtListmap(ref kv) => Box::new(kv.detach_table()),
+            DataModel::KVExtSetmap(ref kv) => Box::new(kv.detach_table()),
+            DataModel::KVExtHashmap(ref kv) => Box::new(kv.detach_table()),
+            DataModel::KVExtZSetmap(ref kv) => Box::new(kv.detach_table()),
         }
     }
     pub fn is_empty(&self) -> bool {
         self.count() == 0
     }
+    /// Replace the data in this table with the data in `other`. If the tables don't have the
+    /// same model and volatility, `other` is returned back without touching any data
+    pub fn replace_with(&self, other: Table) -> Result<(), Table> {
+        if self.get_model_code() != other.get_model_code() || self.volatile != other.volatile {
+            return Err(other);
+        }
+        match (&self.model_store, other.model_store) {
+            (DataModel::KV(ref kv), DataModel::KV(okv)) => kv.replace_with(okv),
+            (DataModel::KVExtListmap(ref kv), DataModel::KVExtListmap(okv)) => kv.replace_with(okv),
+            (DataModel::KVExtSetmap(ref kv), DataModel::KVExtSetmap(okv)) => kv.replace_with(okv),
+            (DataModel::KVExtHashmap(ref kv), DataModel::KVExtHashmap(okv)) => kv.replace_with(okv),
+            (DataModel::KVExtZSetmap(ref kv), DataModel::KVExtZSetmap(okv)) => kv.replace_with(okv),
+            _ => unsafe { impossible!() },
+        }
+        Ok(())
+    }
+    /// Purge all the keys whose TTL has elapsed, returning the number of purged keys
+    pub fn sweep_expired(&self) -> usize {
+        match self.model_store {
+            DataModel::KV(ref kv) => kv.sweep_expired(),
+            DataModel::KVExtListmap(ref kv) => kv.sweep_expired(),
+            DataModel::KVExtSetmap(ref kv) => kv.sweep_expired(),
+            DataModel::KVExtHashmap(ref kv) => kv.sweep_expired(),
+            DataModel::KVExtZSetmap(ref kv) => kv.sweep_expired(),
+        }
+    }
+    /// Returns the expiry deadlines of the keys in this table
+    pub fn get_expiry_ref(&self) -> &Coremap<SharedSlice, u64> {
+        match self.model_store {
+            DataModel::KV(ref kv) => kv.get_expiry_ref(),
+            DataModel::KVExtListmap(ref kv) => kv.get_expiry_ref(),
+            DataModel::KVExtSetmap(ref kv) => kv.get_expiry_ref(),
+            DataModel::KVExtHashmap(ref kv) => kv.get_expiry_ref(),
+            DataModel::KVExtZSetmap(ref kv) => kv.get_expiry_ref(),
+        }
+    }
+    /// Set the deadlines of the keys of this table once it's loaded, returning the keys that
+    /// were dropped since their deadlines had passed (see
+    /// [`KVEngine::restore_expiry`](crate::kvengine::KVEngine::restore_expiry))
+    pub fn restore_expiry<'a>(
+        &self,
+        deadlines: impl IntoIterator<Item = (&'a [u8], u64)>,
+    ) -> Vec<SharedSlice> {
+        match self.model_store {
+            DataModel::KV(ref kv) => kv.restore_expiry(deadlines),
+            DataModel::KVExtListmap(ref kv) => kv.restore_expiry(deadlines),
+            DataModel::KVExtSetmap(ref kv) => kv.restore_expiry(deadlines),
+            DataModel::KVExtHashmap(ref kv) => kv.restore_expiry(deadlines),
+            DataModel::KVExtZSetmap(ref kv) => kv.restore_expiry(deadlines),
+        }
+    }
+    /// Returns the approximate number of bytes used by the data in this table
+    pub fn approx_memory_usage(&self) -> usize {
+        match self.model_store {
+            DataModel::KV(ref kv) => kv.approx_memory_usage(),
+            DataModel::KVExtListmap(ref kv) => kv.approx_memory_usage(),
+            DataModel::KVExtSetmap(ref kv) => kv.approx_memory_usage(),
+            DataModel::KVExtHashmap(ref kv) => kv.approx_memory_usage(),
+            DataModel::KVExtZSetmap(ref kv) => kv.approx_memory_usage(),
+        }
+    }
+    /// Returns an estimate of the number of bytes used by the data in this table, from a random
+    /// sample of atmost `samples` entries (see
+    /// [`KVEngine::estimate_memory_usage`](crate::kvengine::KVEngine::estimate_memory_usage))
+    pub fn estimate_memory_usage(&self, samples: usize, seed: u64) -> usize {
+        match self.model_store {
+            DataModel::KV(ref kv) => kv.estimate_memory_usage(samples, seed),
+            DataModel::KVExtListmap(ref kv) => kv.estimate_memory_usage(samples, seed),
+            DataModel::KVExtSetmap(ref kv) => kv.estimate_memory_usage(samples, seed),
+            DataModel::KVExtHashmap(ref kv) => kv.estimate_memory_usage(samples, seed),
+            DataModel::KVExtZSetmap(ref kv) => kv.estimate_memory_usage(samples, seed),
+        }
+    }
+    /// Returns the approximate number of bytes stored in this table, including the values that
+    /// were spilled to disk
+    pub fn approx_stored_size(&self) -> usize {
+        match self.model_store {
+            DataModel::KV(ref kv) => kv.approx_stored_size(),
+            DataModel::KVExtListmap(ref kv) => kv.approx_stored_size(),
+            DataModel::KVExtSetmap(ref kv) => kv.approx_stored_size(),
+            DataModel::KVExtHashmap(ref kv) => kv.approx_stored_size(),
+            DataModel::KVExtZSetmap(ref kv) => kv.approx_stored_size(),
+        }
+    }
+    /// Returns the approximate number of bytes used by the given key, or `None` if the key
+    /// doesn't exist
+    pub fn memory_usage_of(&self, key: &[u8]) -> Option<usize> {
+        match self.model_store {
+            DataModel::KV(ref kv) => kv.memory_usage_unchecked(key),
+            DataModel::KVExtListmap(ref kv) => kv.memory_usage_unchecked(key),
+            DataModel::KVExtSetmap(ref kv) => kv.memory_usage_unchecked(key),
+            DataModel::KVExtHashmap(ref kv) => kv.memory_usage_unchecked(key),
+            DataModel::KVExtZSetmap(ref kv) => kv.memory_usage_unchecked(key),
+        }
+    }
+    /// Evict a single key from this table using the given policy, returning the approximate
+    /// number of bytes freed
+    pub fn evict_one(&self, policy: EvictionPolicy, samples: usize, seed: u64) -> usize {
+        match self.model_store {
+            DataModel::KV(ref kv) => kv.evict_one(policy, samples, seed),
+            DataModel::KVExtListmap(ref kv) => kv.evict_one(policy, samples, seed),
+            DataModel::KVExtSetmap(ref kv) => kv.evict_one(policy, samples, seed),
+            DataModel::KVExtHashmap(ref kv) => kv.evict_one(policy, samples, seed),
+            DataModel::KVExtZSetmap(ref kv) => kv.evict_one(policy, samples, seed),
+        }
+    }
     /// Returns the storage type as an 8-bit uint
     pub const fn storage_type(&self) -> u8 {
         self.volatile as u8
@@ -191,6 +437,17 @@ impl Table {
     pub const fn is_volatile(&self) -> bool {
         self.volatile
     }
+    fn with_model(model_store: DataModel, volatile: bool) -> Self {
+        let table = Self {
+            model_store,
+            volatile,
+        };
+        if volatile {
+            // volatile tables are never saved, so don't bother tracking their keys
+            table.dirty_keys().mark_all();
+        }
+        table
+    }
     /// Create a new KVEBlob Table with the provided settings
     pub fn new_pure_kve_with_data(
         data: Coremap<SharedSlice, SharedSlice>,
@@ -198,10 +455,10 @@ impl Table {
         k_enc: bool,
         v_enc: bool,
     ) -> Self {
-        Self {
+        Self::with_model(
+            DataModel::KV(KVEStandard::new(k_enc, v_enc, data)),
             volatile,
-            model_store: DataModel::KV(KVEStandard::new(k_enc, v_enc, data)),
-        }
+        )
     }
     pub fn new_kve_listmap_with_data(
         data: Coremap<SharedSlice, LockedVec>,
@@ -209,9 +466,83 @@ impl Table {
         k_enc: bool,
         payload_enc: bool,
     ) -> Self {
-        Self {
+        Self::with_model(
+            DataModel::KVExtListmap(KVEListmap::new(k_enc, payload_enc, data)),
+            volatile,
+        )
+    }
+    pub fn new_kve_setmap_with_data(
+        data: Coremap<SharedSlice, LockedSet>,
+        volatile: bool,
+        k_enc: bool,
+        payload_enc: bool,
+    ) -> Self {
+        Self::with_model(
+            DataModel::KVExtSetmap(KVESetmap::new(k_enc, payload_enc, data)),
+            volatile,
+        )
+    }
+    pub fn new_kve_hashmap_with_data(
+        data: Coremap<SharedSlice, LockedHash>,
+        volatile: bool,
+        k_enc: bool,
+        payload_enc: bool,
+    ) -> Self {
+        Self::with_model(
+            DataModel::KVExtHashmap(KVEHashmap::new(k_enc, payload_enc, data)),
+            volatile,
+        )
+    }
+    pub fn new_kve_zsetmap_with_data(
+        data: Coremap<SharedSlice, LockedZSet>,
+        volatile: bool,
+        k_enc: bool,
+        payload_enc: bool,
+    ) -> Self {
+        Self::with_model(
+            DataModel::KVExtZSetmap(KVEZSetmap::new(k_enc, payload_enc, data)),
             volatile,
-            model_store: DataModel::KVExtListmap(KVEListmap::new(k_enc, payload_enc, data)),
+        )
+    }
+    /// Returns a copy of this table that can be flushed after writers are let back into this
+    /// table (see [`KVEngine::freeze`](crate::kvengine::KVEngine::freeze))
+    pub fn freeze(&self) -> Self {
+        let model_store = match &self.model_store {
+            DataModel::KV(kve) => DataModel::KV(kve.freeze()),
+            DataModel::KVExtListmap(kve) => DataModel::KVExtListmap(kve.freeze()),
+            DataModel::KVExtSetmap(kve) => DataModel::KVExtSetmap(kve.freeze()),
+            DataModel::KVExtHashmap(kve) => DataModel::KVExtHashmap(kve.freeze()),
+            DataModel::KVExtZSetmap(kve) => DataModel::KVExtZSetmap(kve.freeze()),
+        };
+        Self::with_model(model_store, self.volatile)
+    }
+    /// Same as [`Table::freeze`], but for a save that stands in for this table (see
+    /// [`KVEngine::freeze_for_save`](crate::kvengine::KVEngine::freeze_for_save))
+    pub fn freeze_for_save(&self) -> Self {
+        let model_store = match &self.model_store {
+            DataModel::KV(kve) => DataModel::KV(kve.freeze_for_save()),
+            DataModel::KVExtListmap(kve) => DataModel::KVExtListmap(kve.freeze_for_save()),
+            DataModel::KVExtSetmap(kve) => DataModel::KVExtSetmap(kve.freeze_for_save()),
+            DataModel::KVExtHashmap(kve) => DataModel::KVExtHashmap(kve.freeze_for_save()),
+            DataModel::KVExtZSetmap(kve) => DataModel::KVExtZSetmap(kve.freeze_for_save()),
+        };
+        Self::with_model(model_store, self.volatile)
+    }
+    /// Take back the dirty keys of a copy that was made by [`Table::freeze_for_save`]
+    pub fn merge_saved(&self, saved: &Self) {
+        match (&self.model_store, &saved.model_store) {
+            (DataModel::KV(kve), DataModel::KV(saved)) => kve.merge_saved(saved),
+            (DataModel::KVExtListmap(kve), DataModel::KVExtListmap(saved)) => {
+                kve.merge_saved(saved)
+            }
+            (DataModel::KVExtSetmap(kve), DataModel::KVExtSetmap(saved)) => kve.merge_saved(saved),
+            (DataModel::KVExtHashmap(kve), DataModel::KVExtHashmap(saved)) => {
+                kve.merge_saved(saved)
+            }
+            (DataModel::KVExtZSetmap(kve), DataModel::KVExtZSetmap(saved)) => {
+                kve.merge_saved(saved)
+            }
+            _ => unreachable!("a frozen table has the same model as the table"),
         }
     }
     pub fn from_model_code(code: u8, volatile: bool) -> Option<Self> {
@@ -225,6 +556,21 @@ impl Table {
                 Self::new_kve_listmap_with_data(Coremap::new(), volatile, $kenc, $penc)
             };
         }
+        macro_rules! setmap {
+            ($kenc:expr, $penc:expr) => {
+                Self::new_kve_setmap_with_data(Coremap::new(), volatile, $kenc, $penc)
+            };
+        }
+        macro_rules! hashmap {
+            ($kenc:expr, $penc:expr) => {
+                Self::new_kve_hashmap_with_data(Coremap::new(), volatile, $kenc, $penc)
+            };
+        }
+        macro_rules! zsetmap {
+            ($kenc:expr, $penc:expr) => {
+                Self::new_kve_zsetmap_with_data(Coremap::new(), volatile, $kenc, $penc)
+            };
+        }
         let ret = match code {
             // pure kve
             0 => pkve!(false, false),
@@ -236,6 +582,21 @@ impl Table {
             5 => listmap!(false, true),
             6 => listmap!(true, false),
             7 => listmap!(true, true),
+            // kvext: setmap
+            8 => setmap!(false, false),
+            9 => setmap!(false, true),
+            10 => setmap!(true, false),
+            11 => setmap!(true, true),
+            // kvext: hashmap
+            12 => hashmap!(false, false),
+            13 => hashmap!(false, true),
+            14 => hashmap!(true, false),
+            15 => hashmap!(true, true),
+            // kvext: sorted setmap
+            16 => zsetmap!(false, false),
+            17 => zsetmap!(false, true),
+            18 => zsetmap!(true, false),
+            19 => zsetmap!(true, true),
             _ => return None,
         };
         Some(ret)
@@ -277,6 +638,36 @@ impl Table {
                 let (kenc, venc) = kvlistmap.get_encoding_tuple();
                 ((kenc as u8) << 1) + (venc as u8) + 4
             }
+            DataModel::KVExtSetmap(ref kvsetmap) => {
+                /*
+                bin,set<bin> => 8,
+                bin,set<str> => 9,
+                str,set<bin> => 10,
+                str,set<str> => 11
+                */
+                let (kenc, venc) = kvsetmap.get_encoding_tuple();
+                ((kenc as u8) << 1) + (venc as u8) + 8
+            }
+            DataModel::KVExtHashmap(ref kvhashmap) => {
+                /*
+                bin,hash<bin> => 12,
+                bin,hash<str> => 13,
+                str,hash<bin> => 14,
+                str,hash<str> => 15
+                */
+                let (kenc, venc) = kvhashmap.get_encoding_tuple();
+                ((kenc as u8) << 1) + (venc as u8) + 12
+            }
+            DataModel::KVExtZSetmap(ref kvzsetmap) => {
+                /*
+                bin,zset<bin> => 16,
+                bin,zset<str> => 17,
+                str,zset<bin> => 18,
+                str,zset<str> => 19
+                */
+                let (kenc, venc) = kvzsetmap.get_encoding_tuple();
+                ((kenc as u8) << 1) + (venc as u8) + 16
+            }
         }
     }
     /// Returns the inner data model
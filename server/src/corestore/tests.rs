@@ -168,3 +168,153 @@ mod modelcode_tests {
         assert_eq!(tbl4.get_model_code(), 7);
     }
 }
+
+mod freeze_tests {
+    use {
+        super::super::{memstore::*, SharedSlice},
+        crate::kvengine::KVEListmap,
+        parking_lot::RwLock,
+    };
+
+    #[test]
+    fn test_freeze_memstore() {
+        let ms = Memstore::new_default();
+        let kve = || {
+            ms.get_keyspace_atomic_ref(&DEFAULT)
+                .unwrap()
+                .get_table_atomic_ref(&DEFAULT)
+                .unwrap()
+        };
+        kve()
+            .get_kvstore()
+            .unwrap()
+            .set_unchecked(SharedSlice::from("x"), SharedSlice::from("100"));
+        let frozen = ms.freeze();
+        // writes to the live store after the freeze don't show up in the copy
+        let live = kve();
+        let live = live.get_kvstore().unwrap();
+        live.set_unchecked(SharedSlice::from("x"), SharedSlice::from("200"));
+        live.set_unchecked(SharedSlice::from("y"), SharedSlice::from("300"));
+        let myks = unsafe { ObjectID::from_slice("myks") };
+        ms.create_keyspace(myks.clone());
+        let frozen_tbl = frozen
+            .get_keyspace_atomic_ref(&DEFAULT)
+            .unwrap()
+            .get_table_atomic_ref(&DEFAULT)
+            .unwrap();
+        let frozen_kve = frozen_tbl.get_kvstore().unwrap();
+        assert_eq!(frozen_kve.len(), 1);
+        assert_eq!(
+            frozen_kve.get_cloned_unchecked("x").unwrap(),
+            SharedSlice::from("100")
+        );
+        assert!(frozen.get_keyspace_atomic_ref(&myks).is_none());
+    }
+
+    #[test]
+    fn test_freeze_listmap() {
+        let list = KVEListmap::init(false, false);
+        list.set_unchecked(
+            SharedSlice::from("list"),
+            RwLock::new(vec![SharedSlice::from("a")].into()),
+        );
+        let frozen = list.freeze();
+        // the nested list is copied too
+        list.get_unchecked("list")
+            .unwrap()
+            .write()
+            .push_back(SharedSlice::from("b"));
+        assert_eq!(
+            *frozen.get_unchecked("list").unwrap().read(),
+            vec![SharedSlice::from("a")]
+        );
+    }
+}
+
+#[cfg(unix)]
+mod spill_tests {
+    use {
+        super::super::{memstore::*, spill, SharedSlice},
+        crate::storage::v1::{
+            flush,
+            interface::{DataDir, DIR_SPILL},
+            unflush,
+        },
+        std::fs,
+    };
+
+    /// Spilling is configured for the whole process, so this is an odd size that no other
+    /// test stores a value of
+    const SPILLMIN: usize = (1 << 20) + 17;
+
+    fn spilled_files(dir: &DataDir) -> usize {
+        fs::read_dir(dir.resolve(DIR_SPILL)).unwrap().count()
+    }
+
+    #[test]
+    fn test_spill_lifecycle() {
+        let path = std::env::temp_dir().join(format!("skyd-spill-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let dir = DataDir::new(path.to_str().unwrap());
+        let store = unflush::read_full(&dir).unwrap();
+        // a file left behind by a run that crashed
+        fs::create_dir_all(dir.resolve(DIR_SPILL)).unwrap();
+        fs::write(format!("{}/0", dir.resolve(DIR_SPILL)), b"leftover").unwrap();
+        spill::configure(&dir, SPILLMIN).unwrap();
+        assert_eq!(spilled_files(&dir), 0);
+        assert!(!spill::offload(SharedSlice::from("small")).is_mapped());
+        let big = vec![b'x'; SPILLMIN];
+        let tbl = store
+            .get_keyspace_atomic_ref(&DEFAULT)
+            .unwrap()
+            .get_table_atomic_ref(&DEFAULT)
+            .unwrap();
+        let kve = tbl.get_kvstore().unwrap();
+        kve.set_unchecked(SharedSlice::from("big"), SharedSlice::new(&big));
+        let value = kve.get_cloned_unchecked("big").unwrap();
+        assert!(value.is_mapped());
+        assert_eq!(value.as_ref(), big.as_slice());
+        // the file is unlinked as soon as it's mapped
+        assert_eq!(spilled_files(&dir), 0);
+        // spilled values don't take up memory, but they still count against quotas
+        assert!(kve.approx_memory_usage() < SPILLMIN);
+        assert!(kve.approx_stored_size() >= SPILLMIN);
+        assert!(tbl.approx_stored_size() >= SPILLMIN);
+        // growing and swapping a spilled value keeps it on disk
+        assert_eq!(kve.append_unchecked(b"big", b"y"), SPILLMIN + 1);
+        let appended = kve.get_cloned_unchecked("big").unwrap();
+        assert!(appended.is_mapped());
+        assert_eq!(appended.last(), Some(&b'y'));
+        assert_eq!(
+            kve.compare_and_swap_unchecked(
+                SharedSlice::from("big"),
+                &appended,
+                SharedSlice::new(&big)
+            ),
+            Some(true)
+        );
+        assert!(kve.get_cloned_unchecked("big").unwrap().is_mapped());
+        // spilled values are saved like any other value, and are spilled again when read back
+        flush::flush_full(dir.clone(), &store).unwrap();
+        let restarted = unflush::read_full(&dir).unwrap();
+        let restarted_tbl = restarted
+            .get_keyspace_atomic_ref(&DEFAULT)
+            .unwrap()
+            .get_table_atomic_ref(&DEFAULT)
+            .unwrap();
+        let reloaded = restarted_tbl
+            .get_kvstore()
+            .unwrap()
+            .get_cloned_unchecked("big")
+            .unwrap();
+        assert!(reloaded.is_mapped());
+        assert_eq!(reloaded.as_ref(), big.as_slice());
+        // the mapping goes away with the last handle to the value
+        let (spilled, _) = spill::stats();
+        drop((value, appended, reloaded, restarted_tbl, restarted));
+        assert!(kve.remove_unchecked("big"));
+        assert!(spill::stats().0 < spilled);
+        spill::configure(&dir, 0).unwrap();
+        fs::remove_dir_all(path).unwrap();
+    }
+}
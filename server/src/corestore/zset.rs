@@ -0,0 +1,453 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Sorted sets
+//!
+//! A [`SortedSet`] keeps unique members ordered by a floating point score. Members are kept in
+//! a [`RankTree`] ordered by `(score, member)` (ties are broken by comparing the members), so
+//! that the rank of a member can be found in `O(log n)`, and a `HashMap` of member to score is
+//! used for constant time score lookups
+
+use {
+    crate::corestore::{map::splitmix64, SharedSlice},
+    core::cmp::Ordering,
+    std::collections::HashMap,
+};
+
+#[derive(Debug, Clone, Copy)]
+/// A score in a sorted set. Scores can never be `NaN`, so they can be totally ordered
+pub struct Score(f64);
+
+impl Score {
+    /// Returns a score if `score` isn't `NaN`
+    pub fn new(score: f64) -> Option<Self> {
+        if score.is_nan() {
+            None
+        } else {
+            // normalize `-0.0` to `0.0` so that both of them are ordered as equals
+            Some(Self(score + 0.0))
+        }
+    }
+    /// Parse a score from its textual representation (`inf`, `+inf` and `-inf` are accepted)
+    pub fn parse(src: &[u8]) -> Option<Self> {
+        core::str::from_utf8(src)
+            .ok()?
+            .parse::<f64>()
+            .ok()
+            .and_then(Self::new)
+    }
+    pub const fn get(&self) -> f64 {
+        self.0
+    }
+}
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Marks a missing child (or an empty tree)
+const NIL: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+struct Node<K> {
+    key: K,
+    priority: u64,
+    left: usize,
+    right: usize,
+    /// The number of nodes in the subtree rooted at this node
+    size: usize,
+}
+
+#[derive(Debug, Clone)]
+/// An ordered set of unique keys that can count the keys smaller than a given key in
+/// `O(log n)`. It's a treap (a binary search tree that's balanced by giving every node a random
+/// priority and keeping the nodes heap-ordered by their priorities) where every node also
+/// knows the size of its subtree. The nodes are kept in a `Vec` and are linked by their indices
+struct RankTree<K> {
+    nodes: Vec<Node<K>>,
+    root: usize,
+    /// The state used to generate the priorities of the nodes
+    seed: u64,
+}
+
+impl<K> Default for RankTree<K> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            root: NIL,
+            seed: 0,
+        }
+    }
+}
+
+impl<K: Ord> RankTree<K> {
+    fn size(&self, node: usize) -> usize {
+        if node == NIL {
+            0
+        } else {
+            self.nodes[node].size
+        }
+    }
+    fn resize(&mut self, node: usize) {
+        let Node { left, right, .. } = self.nodes[node];
+        self.nodes[node].size = 1 + self.size(left) + self.size(right);
+    }
+    /// Split the subtree rooted at `node` into the keys that are smaller than `key` and the
+    /// rest, returning the roots of both
+    fn split(&mut self, node: usize, key: &K) -> (usize, usize) {
+        if node == NIL {
+            return (NIL, NIL);
+        }
+        if self.nodes[node].key < *key {
+            let (smaller, rest) = self.split(self.nodes[node].right, key);
+            self.nodes[node].right = smaller;
+            self.resize(node);
+            (node, rest)
+        } else {
+            let (smaller, rest) = self.split(self.nodes[node].left, key);
+            self.nodes[node].left = rest;
+            self.resize(node);
+            (smaller, node)
+        }
+    }
+    /// Merge two subtrees where every key in `left` is smaller than every key in `right`,
+    /// returning the root of the merged tree
+    fn merge(&mut self, left: usize, right: usize) -> usize {
+        if left == NIL {
+            return right;
+        }
+        if right == NIL {
+            return left;
+        }
+        if self.nodes[left].priority > self.nodes[right].priority {
+            let merged = self.merge(self.nodes[left].right, right);
+            self.nodes[left].right = merged;
+            self.resize(left);
+            left
+        } else {
+            let merged = self.merge(left, self.nodes[right].left);
+            self.nodes[right].left = merged;
+            self.resize(right);
+            right
+        }
+    }
+    /// Insert a key that isn't in the tree
+    fn insert(&mut self, key: K) {
+        let (smaller, rest) = self.split(self.root, &key);
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            key,
+            priority: splitmix64(&mut self.seed),
+            left: NIL,
+            right: NIL,
+            size: 1,
+        });
+        let smaller = self.merge(smaller, node);
+        self.root = self.merge(smaller, rest);
+    }
+    /// Remove a key that is in the tree
+    fn remove(&mut self, key: &K) {
+        let (smaller, rest) = self.split(self.root, key);
+        // `key` is the smallest key in `rest`
+        let (removed, rest) = self.unlink_first(rest);
+        self.root = self.merge(smaller, rest);
+        // fill the hole with the last node so that the nodes stay contiguous
+        let last = self.nodes.len() - 1;
+        if removed != last {
+            self.relink(last, removed);
+        }
+        self.nodes.swap_remove(removed);
+    }
+    /// Unlink the node with the smallest key from the subtree rooted at `node`, returning it
+    /// and the new root of the subtree
+    fn unlink_first(&mut self, node: usize) -> (usize, usize) {
+        let Node { left, right, .. } = self.nodes[node];
+        if left == NIL {
+            return (node, right);
+        }
+        let (first, left) = self.unlink_first(left);
+        self.nodes[node].left = left;
+        self.resize(node);
+        (first, node)
+    }
+    /// Make the link that points to the node at `from` point to `to` instead
+    fn relink(&mut self, from: usize, to: usize) {
+        let key = &self.nodes[from].key;
+        let (mut parent, mut node) = (NIL, self.root);
+        while node != from {
+            parent = node;
+            node = if *key < self.nodes[node].key {
+                self.nodes[node].left
+            } else {
+                self.nodes[node].right
+            };
+        }
+        if parent == NIL {
+            self.root = to;
+        } else if self.nodes[parent].left == from {
+            self.nodes[parent].left = to;
+        } else {
+            self.nodes[parent].right = to;
+        }
+    }
+    /// Returns the number of keys that are smaller than `key`
+    fn rank(&self, key: &K) -> usize {
+        let (mut rank, mut node) = (0, self.root);
+        while node != NIL {
+            let Node { left, right, .. } = self.nodes[node];
+            if self.nodes[node].key < *key {
+                rank += self.size(left) + 1;
+                node = right;
+            } else {
+                node = left;
+            }
+        }
+        rank
+    }
+    /// Returns an iterator over the keys in ascending order, starting from the first key that
+    /// isn't smaller than `start` (or from the smallest key)
+    fn iter_from(&self, start: Option<&K>) -> RankTreeIter<'_, K> {
+        let mut iter = RankTreeIter {
+            tree: self,
+            path: Vec::new(),
+        };
+        let mut node = self.root;
+        while node != NIL {
+            if !matches!(start, Some(start) if self.nodes[node].key < *start) {
+                iter.path.push(node);
+                node = self.nodes[node].left;
+            } else {
+                node = self.nodes[node].right;
+            }
+        }
+        iter
+    }
+}
+
+/// An in-order iterator over a [`RankTree`]
+struct RankTreeIter<'a, K> {
+    tree: &'a RankTree<K>,
+    /// The nodes that are yet to be visited (along with their right subtrees)
+    path: Vec<usize>,
+}
+
+impl<'a, K> Iterator for RankTreeIter<'a, K> {
+    type Item = &'a K;
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.path.pop()?;
+        let mut next = self.tree.nodes[node].right;
+        while next != NIL {
+            self.path.push(next);
+            next = self.tree.nodes[next].left;
+        }
+        Some(&self.tree.nodes[node].key)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// A set of unique members ordered by their scores
+pub struct SortedSet {
+    scores: HashMap<SharedSlice, Score>,
+    ordered: RankTree<(Score, SharedSlice)>,
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Returns the number of members in the set
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+    /// Add a member with the given score, or update its score if it's already present.
+    /// Returns true if the member is new
+    pub fn insert(&mut self, member: SharedSlice, score: Score) -> bool {
+        match self.scores.insert(member.clone(), score) {
+            Some(old) => {
+                if old != score {
+                    self.ordered.remove(&(old, member.clone()));
+                    self.ordered.insert((score, member));
+                }
+                false
+            }
+            None => {
+                self.ordered.insert((score, member));
+                true
+            }
+        }
+    }
+    /// Remove a member, returning true if it was present
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove_entry(member) {
+            Some((member, score)) => {
+                self.ordered.remove(&(score, member));
+                true
+            }
+            None => false,
+        }
+    }
+    /// Returns the score of a member, if it is present
+    pub fn score(&self, member: &[u8]) -> Option<Score> {
+        self.scores.get(member).copied()
+    }
+    /// Returns the zero-based position of a member in the set (ordered by ascending score),
+    /// if it is present
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        let member = SharedSlice::new(member);
+        Some(self.ordered.rank(&(score, member)))
+    }
+    /// Returns the members with a score in `min..=max`, in ascending order of their scores
+    pub fn range_by_score(
+        &self,
+        min: Score,
+        max: Score,
+    ) -> impl Iterator<Item = (&SharedSlice, Score)> {
+        let iter = if min <= max {
+            Some(
+                self.ordered
+                    .iter_from(Some(&(min, SharedSlice::new(b""))))
+                    .take_while(move |(score, _)| *score <= max),
+            )
+        } else {
+            None
+        };
+        iter.into_iter()
+            .flatten()
+            .map(|(score, member)| (member, *score))
+    }
+    /// Returns an iterator over all the members and their scores, in ascending order of the
+    /// scores
+    pub fn iter(&self) -> impl Iterator<Item = (&SharedSlice, Score)> {
+        self.ordered
+            .iter_from(None)
+            .map(|(score, member)| (member, *score))
+    }
+}
+
+impl FromIterator<(SharedSlice, Score)> for SortedSet {
+    fn from_iter<T: IntoIterator<Item = (SharedSlice, Score)>>(iter: T) -> Self {
+        let mut set = Self::new();
+        iter.into_iter().for_each(|(member, score)| {
+            set.insert(member, score);
+        });
+        set
+    }
+}
+
+#[test]
+fn test_sorted_set_ordering() {
+    let mut set = SortedSet::new();
+    assert!(set.insert("carol".into(), Score::new(30.0).unwrap()));
+    assert!(set.insert("alice".into(), Score::new(10.0).unwrap()));
+    assert!(set.insert("bob".into(), Score::new(20.0).unwrap()));
+    // ties are ordered by member
+    assert!(set.insert("aaron".into(), Score::new(20.0).unwrap()));
+    let members: Vec<&[u8]> = set.iter().map(|(m, _)| m.as_ref()).collect();
+    assert_eq!(members, [&b"alice"[..], b"aaron", b"bob", b"carol"]);
+    assert_eq!(set.rank(b"alice"), Some(0));
+    assert_eq!(set.rank(b"bob"), Some(2));
+    assert_eq!(set.rank(b"dave"), None);
+}
+
+#[test]
+fn test_sorted_set_update_and_remove() {
+    let mut set = SortedSet::new();
+    assert!(set.insert("alice".into(), Score::new(10.0).unwrap()));
+    assert!(set.insert("bob".into(), Score::new(20.0).unwrap()));
+    // updating moves the member
+    assert!(!set.insert("alice".into(), Score::new(30.0).unwrap()));
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.rank(b"alice"), Some(1));
+    assert!(set.remove(b"alice"));
+    assert!(!set.remove(b"alice"));
+    assert_eq!(set.len(), 1);
+    assert_eq!(set.iter().count(), 1);
+}
+
+#[test]
+fn test_sorted_set_range_by_score() {
+    let set: SortedSet = [("a", 1.0), ("b", 2.0), ("c", 3.0), ("d", 4.0)]
+        .into_iter()
+        .map(|(m, s)| (SharedSlice::from(m), Score::new(s).unwrap()))
+        .collect();
+    let range = |min: &str, max: &str| -> Vec<SharedSlice> {
+        set.range_by_score(
+            Score::parse(min.as_bytes()).unwrap(),
+            Score::parse(max.as_bytes()).unwrap(),
+        )
+        .map(|(m, _)| m.clone())
+        .collect()
+    };
+    assert_eq!(range("2", "3"), ["b", "c"]);
+    assert_eq!(range("-inf", "+inf").len(), 4);
+    assert_eq!(range("2.5", "2.9").len(), 0);
+    assert_eq!(range("3", "2").len(), 0);
+    assert!(Score::parse(b"nan").is_none());
+    assert!(Score::parse(b"notanumber").is_none());
+}
+
+#[test]
+fn test_rank_tree() {
+    use std::collections::BTreeSet;
+    let mut tree = RankTree::default();
+    let mut expected = BTreeSet::new();
+    let mut seed = 0;
+    for _ in 0..10_000 {
+        let key = splitmix64(&mut seed) % 500;
+        if splitmix64(&mut seed) & 3 == 0 {
+            if expected.remove(&key) {
+                tree.remove(&key);
+            }
+        } else if expected.insert(key) {
+            tree.insert(key);
+        }
+        let probe = splitmix64(&mut seed) % 510;
+        assert_eq!(tree.rank(&probe), expected.range(..probe).count());
+        assert!(tree.iter_from(Some(&probe)).eq(expected.range(probe..)));
+    }
+    assert_eq!(tree.nodes.len(), expected.len());
+    assert!(tree.iter_from(None).eq(expected.iter()));
+}
@@ -0,0 +1,339 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Compression
+//!
+//! A client can ask for compression in its handshake (see [`super::handshake`]). Once a codec
+//! is agreed on, everything on the connection (in both directions) is sent in frames. A frame
+//! is either `R<len>\n<bytes>` (uncompressed) or `Z<len>,<rawlen>\n<bytes>` (compressed, with
+//! the length of the data after decompressing it). We only compress responses that are at
+//! least `server.compressmin` bytes long, since small payloads rarely shrink. The payloads of
+//! the frames make up the Skyhash stream, so a query or response can span several frames
+
+use {
+    super::BufferedSocketStream,
+    bytes::{Buf, BytesMut},
+    core::{
+        pin::Pin,
+        str,
+        task::{ready, Context, Poll},
+    },
+    std::io::{Error as IoError, ErrorKind, Result as IoResult},
+    tokio::io::{AsyncRead, AsyncWrite, ReadBuf},
+};
+
+/// The largest frame (before or after decompression) that we accept
+const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+/// The longest frame header that we accept
+const MAX_FRAME_HEADER_SIZE: usize = 48;
+const FRAME_RAW: u8 = b'R';
+const FRAME_COMPRESSED: u8 = b'Z';
+const READ_CHUNK_SIZE: usize = 8192;
+
+/// A compression codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Lz4,
+    Zstd,
+}
+
+impl Codec {
+    /// The codecs that we support
+    pub const ALL: [Self; 2] = [Self::Lz4, Self::Zstd];
+    pub const fn name(&self) -> &'static [u8] {
+        match self {
+            Self::Lz4 => b"lz4",
+            Self::Zstd => b"zstd",
+        }
+    }
+    pub fn of(name: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|codec| name.eq_ignore_ascii_case(codec.name()))
+    }
+    pub fn compress(&self, data: &[u8]) -> IoResult<Vec<u8>> {
+        match self {
+            Self::Lz4 => Ok(lz4_flex::block::compress(data)),
+            Self::Zstd => zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+        }
+    }
+    /// Decompress `data`, which must decompress to exactly `rawlen` bytes
+    pub fn decompress(&self, data: &[u8], rawlen: usize) -> IoResult<Vec<u8>> {
+        let ret = match self {
+            Self::Lz4 => lz4_flex::block::decompress(data, rawlen)
+                .map_err(|e| IoError::new(ErrorKind::InvalidData, e))?,
+            Self::Zstd => zstd::bulk::decompress(data, rawlen)?,
+        };
+        if ret.len() == rawlen {
+            Ok(ret)
+        } else {
+            Err(IoError::new(
+                ErrorKind::InvalidData,
+                "decompressed data has the wrong length",
+            ))
+        }
+    }
+}
+
+fn bad_frame() -> IoError {
+    IoError::new(ErrorKind::InvalidData, "malformed frame")
+}
+
+fn parse_len(len: &[u8]) -> IoResult<usize> {
+    str::from_utf8(len)
+        .ok()
+        .and_then(|len| len.parse().ok())
+        .filter(|len| *len <= MAX_FRAME_SIZE)
+        .ok_or_else(bad_frame)
+}
+
+/// A frame header
+#[derive(Debug, PartialEq, Eq)]
+struct FrameHeader {
+    /// the length of the header itself
+    size: usize,
+    /// the length of the payload
+    len: usize,
+    /// the length of the payload after decompressing it (if it's compressed)
+    rawlen: Option<usize>,
+}
+
+/// Parse the header of the frame at the start of `buf`, returning `None` if we need more data
+fn parse_header(buf: &[u8]) -> IoResult<Option<FrameHeader>> {
+    let end = match buf
+        .iter()
+        .take(MAX_FRAME_HEADER_SIZE)
+        .position(|b| *b == b'\n')
+    {
+        Some(end) => end,
+        None if buf.len() >= MAX_FRAME_HEADER_SIZE => return Err(bad_frame()),
+        None => return Ok(None),
+    };
+    let (len, rawlen) = match buf[0] {
+        FRAME_RAW => (parse_len(&buf[1..end])?, None),
+        FRAME_COMPRESSED => {
+            let mut lens = buf[1..end].splitn(2, |b| *b == b',');
+            let len = parse_len(lens.next().unwrap_or_default())?;
+            let rawlen = parse_len(lens.next().ok_or_else(bad_frame)?)?;
+            (len, Some(rawlen))
+        }
+        _ => return Err(bad_frame()),
+    };
+    Ok(Some(FrameHeader {
+        size: end + 1,
+        len,
+        rawlen,
+    }))
+}
+
+/// Encode `data` into a frame, compressing it if it's large enough (and compressing it
+/// actually makes it smaller)
+fn encode_frame(codec: Codec, compressmin: usize, data: &[u8]) -> IoResult<Vec<u8>> {
+    if data.len() >= compressmin {
+        let compressed = codec.compress(data)?;
+        if compressed.len() < data.len() {
+            let mut frame = format!("Z{},{}\n", compressed.len(), data.len()).into_bytes();
+            frame.extend(compressed);
+            return Ok(frame);
+        }
+    }
+    let mut frame = format!("R{}\n", data.len()).into_bytes();
+    frame.extend_from_slice(data);
+    Ok(frame)
+}
+
+/// A stream that frames (and compresses) everything written to it and unwraps the frames
+/// read from it. A frame is written for everything written since the last flush
+pub struct CompressedStream<C> {
+    inner: C,
+    codec: Codec,
+    compressmin: usize,
+    /// the bytes that we read off the socket, but haven't decoded yet
+    incoming: BytesMut,
+    /// the decoded bytes, ready to be read
+    decoded: BytesMut,
+    /// the bytes written since the last flush
+    outgoing: Vec<u8>,
+    /// the encoded frame that we're writing to the socket
+    frame: Vec<u8>,
+    /// the number of bytes of `frame` that have been written
+    written: usize,
+}
+
+impl<C> CompressedStream<C> {
+    /// Wrap a stream. `incoming` is anything that was read off the stream already
+    pub fn new(inner: C, codec: Codec, compressmin: usize, incoming: BytesMut) -> Self {
+        Self {
+            inner,
+            codec,
+            compressmin,
+            incoming,
+            decoded: BytesMut::new(),
+            outgoing: Vec::new(),
+            frame: Vec::new(),
+            written: 0,
+        }
+    }
+    /// Decode a frame from the incoming bytes, returning false if we need more data
+    fn decode_frame(&mut self) -> IoResult<bool> {
+        let header = match parse_header(&self.incoming)? {
+            Some(header) => header,
+            None => return Ok(false),
+        };
+        if self.incoming.len() < header.size + header.len {
+            self.incoming
+                .reserve(header.size + header.len - self.incoming.len());
+            return Ok(false);
+        }
+        self.incoming.advance(header.size);
+        let payload = self.incoming.split_to(header.len);
+        match header.rawlen {
+            Some(rawlen) => self
+                .decoded
+                .extend(self.codec.decompress(&payload, rawlen)?),
+            None => self.decoded.extend_from_slice(&payload),
+        }
+        Ok(true)
+    }
+}
+
+impl<C: AsyncRead + Unpin> AsyncRead for CompressedStream<C> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        loop {
+            if !this.decoded.is_empty() {
+                let len = this.decoded.len().min(buf.remaining());
+                buf.put_slice(&this.decoded[..len]);
+                this.decoded.advance(len);
+                return Poll::Ready(Ok(()));
+            }
+            if this.decode_frame()? {
+                continue;
+            }
+            let mut chunk = [0; READ_CHUNK_SIZE];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk))?;
+            if chunk.filled().is_empty() {
+                // EOF (if a frame was cut short, the reader will find the query cut short)
+                return Poll::Ready(Ok(()));
+            }
+            this.incoming.extend_from_slice(chunk.filled());
+        }
+    }
+}
+
+impl<C: AsyncWrite + Unpin> AsyncWrite for CompressedStream<C> {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        self.get_mut().outgoing.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        if this.written == this.frame.len() && !this.outgoing.is_empty() {
+            this.frame = encode_frame(this.codec, this.compressmin, &this.outgoing)?;
+            this.written = 0;
+            this.outgoing.clear();
+        }
+        while this.written < this.frame.len() {
+            let written =
+                ready!(Pin::new(&mut this.inner).poll_write(cx, &this.frame[this.written..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(IoError::from(ErrorKind::WriteZero)));
+            }
+            this.written += written;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<C: BufferedSocketStream> BufferedSocketStream for CompressedStream<C> {}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test]
+    fn frame_headers() {
+        assert_eq!(
+            parse_header(b"R5\nhello").unwrap(),
+            Some(FrameHeader {
+                size: 3,
+                len: 5,
+                rawlen: None
+            })
+        );
+        assert_eq!(
+            parse_header(b"Z10,100\n").unwrap(),
+            Some(FrameHeader {
+                size: 8,
+                len: 10,
+                rawlen: Some(100)
+            })
+        );
+        assert_eq!(parse_header(b"Z10").unwrap(), None);
+        assert!(parse_header(b"Z10\n").is_err());
+        assert!(parse_header(b"X1\n").is_err());
+        assert!(parse_header(format!("R{}\n", MAX_FRAME_SIZE + 1).as_bytes()).is_err());
+    }
+
+    #[test]
+    fn small_payloads_skip_compression() {
+        let data = vec![b'a'; 100];
+        let frame = encode_frame(Codec::Lz4, 1024, &data).unwrap();
+        assert!(frame.starts_with(b"R100\n"));
+        let frame = encode_frame(Codec::Lz4, 64, &data).unwrap();
+        assert!(frame.starts_with(b"Z"));
+    }
+
+    #[tokio::test]
+    async fn roundtrip() {
+        for codec in Codec::ALL {
+            let (client, server) = tokio::io::duplex(1024);
+            let mut server = CompressedStream::new(server, codec, 16, BytesMut::new());
+            let mut client = CompressedStream::new(client, codec, 16, BytesMut::new());
+            let big = vec![b'x'; 10_000];
+            server.write_all(&big).await.unwrap();
+            server.flush().await.unwrap();
+            server.write_all(b"small").await.unwrap();
+            server.flush().await.unwrap();
+            let mut read = vec![0; big.len() + 5];
+            client.read_exact(&mut read).await.unwrap();
+            assert_eq!(&read[..big.len()], &big[..]);
+            assert_eq!(&read[big.len()..], b"small");
+        }
+    }
+}
@@ -25,18 +25,22 @@
 */
 
 use {
-    super::{BufferedSocketStream, QueryResult},
+    super::{BufferedSocketStream, QueryResult, DEFAULT_MAXINFLIGHT, DEFAULT_MAXOUTBUF},
     crate::{
         corestore::buffers::Integer64,
-        protocol::{interface::ProtocolSpec, ParseError},
+        protocol::{interface::ProtocolSpec, ParseError, QueryLimits},
         IoResult,
     },
-    bytes::BytesMut,
+    bytes::{Buf, BytesMut},
     std::{
         io::{Error as IoError, ErrorKind},
         marker::PhantomData,
+        time::Duration,
+    },
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt, BufWriter},
+        time,
     },
-    tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter},
 };
 
 const BUF_WRITE_CAP: usize = 8192;
@@ -47,9 +51,29 @@ const BUF_READ_CAP: usize = 8192;
 /// The generic connection type allows you to choose:
 /// 1. A stream (TCP, TLS(TCP), UDS, ...)
 /// 2. A protocol (one that implements [`ProtocolSpec`])
+///
+/// Large responses are streamed: once `max_inflight` bytes of a response have been written,
+/// they're flushed to the socket before the rest of the response is written. Queries that are
+/// larger than what the [`QueryLimits`] allow are rejected (and the connection is closed)
+///
+/// Frames that are pushed to the client (like key changes and monitored queries) are queued
+/// in the outbound buffer instead and are drained to the socket in between queries. We don't
+/// read any queries until the outbound buffer has been drained, so a client that doesn't read
+/// what we send it stops being served (and is disconnected once more than `max_outbuf` bytes
+/// pile up)
 pub struct Connection<T, P> {
     pub(super) stream: BufWriter<T>,
     pub(super) buffer: BytesMut,
+    /// the number of bytes written since the last flush
+    inflight: usize,
+    /// the number of bytes that we write before flushing
+    max_inflight: usize,
+    /// the pushed frames that are yet to be written to the socket
+    outbuf: BytesMut,
+    /// the number of bytes that can be queued in `outbuf`
+    max_outbuf: usize,
+    /// the caps on the queries that we read
+    limits: QueryLimits,
     _marker: PhantomData<P>,
 }
 
@@ -58,6 +82,45 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
         Connection {
             stream: BufWriter::with_capacity(BUF_WRITE_CAP, stream),
             buffer: BytesMut::with_capacity(BUF_READ_CAP),
+            inflight: 0,
+            max_inflight: DEFAULT_MAXINFLIGHT,
+            outbuf: BytesMut::new(),
+            max_outbuf: DEFAULT_MAXOUTBUF,
+            limits: QueryLimits::default(),
+            _marker: PhantomData,
+        }
+    }
+    /// Set the number of bytes of a response that we write before flushing them
+    pub fn with_max_inflight(mut self, max_inflight: usize) -> Self {
+        self.max_inflight = max_inflight.max(1);
+        self
+    }
+    /// Set the number of bytes of pushed frames that can be queued for the client
+    pub fn with_max_outbuf(mut self, max_outbuf: usize) -> Self {
+        self.max_outbuf = max_outbuf.max(1);
+        self
+    }
+    /// Set the caps on the queries that we read
+    pub fn with_limits(mut self, limits: QueryLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+    /// Returns the stream and anything that was already buffered. Anything written to the
+    /// connection has to be flushed first
+    pub fn into_inner(self) -> (T, BytesMut) {
+        (self.stream.into_inner(), self.buffer)
+    }
+    /// Switch the connection to another protocol (after it was negotiated), keeping anything
+    /// that was already buffered
+    pub fn into_protocol<Q: ProtocolSpec>(self) -> Connection<T, Q> {
+        Connection {
+            stream: self.stream,
+            buffer: self.buffer,
+            inflight: self.inflight,
+            max_inflight: self.max_inflight,
+            outbuf: self.outbuf,
+            max_outbuf: self.max_outbuf,
+            limits: self.limits,
             _marker: PhantomData,
         }
     }
@@ -66,9 +129,59 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
 // protocol read
 impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
     /// Attempt to read a query
-    pub(super) async fn read_query(&mut self) -> IoResult<QueryResult> {
+    ///
+    /// If a `read_timeout` is provided, then once a part of a query has been received, the rest
+    /// of it has to arrive within the timeout. If the query is larger than what the limits
+    /// allow, an error is sent and the client is disconnected since the rest of the query is
+    /// still on its way (and there's no telling where the next one starts)
+    ///
+    /// If any pushed frames are queued, a part of them is written out instead (and nothing is
+    /// read)
+    pub(super) async fn read_query(
+        &mut self,
+        read_timeout: Option<Duration>,
+    ) -> IoResult<QueryResult> {
+        if !self.outbuf.is_empty() {
+            self.drain_outbuf().await?;
+            return Ok(QueryResult::NextLoop);
+        }
         loop {
-            match self.stream.read_buf(&mut self.buffer).await {
+            let partial = !self.buffer.is_empty();
+            if partial {
+                // see if we have buffered enough data to run anything
+                let decoded = match P::decode_packet(self.buffer.as_ref(), self.limits) {
+                    Err(ParseError::NotEnough)
+                        if self.buffer.len() > self.limits.max_query_size() =>
+                    {
+                        Err(ParseError::QueryTooLarge)
+                    }
+                    decoded => decoded,
+                };
+                match decoded {
+                    Ok(query_with_advance) => return Ok(QueryResult::Q(query_with_advance)),
+                    Err(ParseError::NotEnough) => {}
+                    Err(e) => {
+                        // there's no telling where the next query starts, so drop the rest
+                        self.buffer.clear();
+                        self.write_error(P::SKYHASH_PARSE_ERROR_LUT[e as usize - 1])
+                            .await?;
+                        if e == ParseError::QueryTooLarge {
+                            log::debug!("Disconnecting client whose query was too large");
+                            return Ok(QueryResult::Disconnected);
+                        }
+                        return Ok(QueryResult::NextLoop);
+                    }
+                }
+            }
+            let read = self.stream.read_buf(&mut self.buffer);
+            let read = match read_timeout {
+                Some(timeout) if partial => match time::timeout(timeout, read).await {
+                    Ok(read) => read,
+                    Err(_) => return Ok(QueryResult::TimedOut),
+                },
+                _ => read.await,
+            };
+            match read {
                 Ok(0) => {
                     if self.buffer.is_empty() {
                         // buffer is empty, and the remote pulled off (simple disconnection)
@@ -81,17 +194,22 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
                 Ok(_) => {}
                 Err(e) => return Err(e),
             }
-            // see if we have buffered enough data to run anything
-            match P::decode_packet(self.buffer.as_ref()) {
-                Ok(query_with_advance) => return Ok(QueryResult::Q(query_with_advance)),
-                Err(ParseError::NotEnough) => {}
-                Err(e) => {
-                    self.write_error(P::SKYHASH_PARSE_ERROR_LUT[e as usize - 1])
-                        .await?;
-                    return Ok(QueryResult::NextLoop);
+        }
+    }
+    /// Read a frame of exactly `len` bytes (like the acknowledgements that replicas send us).
+    /// This is cancel safe since the bytes are buffered until the whole frame has arrived.
+    /// `None` is returned if the remote disconnected
+    pub(super) async fn read_fixed_frame(&mut self, len: usize) -> IoResult<Option<BytesMut>> {
+        while self.buffer.len() < len {
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                } else {
+                    return Err(IoError::from(ErrorKind::ConnectionReset));
                 }
             }
         }
+        Ok(Some(self.buffer.split_to(len)))
     }
 }
 
@@ -118,14 +236,111 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
     /// Write an error to the stream (just used to differentiate between "normal" and "errored" writes)
     pub(super) async fn write_error(&mut self, error: &[u8]) -> IoResult<()> {
         self.stream.write_all(error).await?;
+        self.flush().await
+    }
+    /// Flush everything that was written to the socket
+    pub(super) async fn flush(&mut self) -> IoResult<()> {
+        self.inflight = 0;
         self.stream.flush().await
     }
+    /// Write a (possibly large) part of a response, flushing whenever `max_inflight` bytes
+    /// have been written so that large responses don't pile up in the write buffer
+    pub async fn write_streamed(&mut self, mut data: &[u8]) -> IoResult<()> {
+        while !data.is_empty() {
+            let len = data.len().min(self.max_inflight - self.inflight);
+            self.stream.write_all(&data[..len]).await?;
+            self.inflight += len;
+            data = &data[len..];
+            if self.inflight == self.max_inflight {
+                self.flush().await?;
+            }
+        }
+        Ok(())
+    }
     /// Write something "raw" to the stream (intentional underscore to avoid misuse)
     pub async fn _write_raw(&mut self, raw: &[u8]) -> IoResult<()> {
         self.stream.write_all(raw).await
     }
 }
 
+// protocol write (pushed frames)
+impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
+    /// Queue a frame that is to be pushed to the client. It starts with
+    /// [`ProtocolSpec::PUSH_FRAME_HEADER`] so that the client can tell it apart from a response
+    pub(super) fn push_frame(&mut self) -> PushFrame<'_, P> {
+        self.outbuf.extend_from_slice(P::PUSH_FRAME_HEADER);
+        PushFrame {
+            buf: &mut self.outbuf,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns true if more pushed frames are queued than the client is allowed to have
+    pub(super) fn outbuf_exceeded(&self) -> bool {
+        self.outbuf.len() > self.max_outbuf
+    }
+    /// Write all the queued frames to the socket (so that something else can be written)
+    pub(super) async fn write_outbuf(&mut self) -> IoResult<()> {
+        while !self.outbuf.is_empty() {
+            self.drain_outbuf().await?;
+        }
+        Ok(())
+    }
+    /// Write (a part of) the queued frames to the socket. This is cancel safe: whatever was
+    /// written stays written and the rest stays queued
+    async fn drain_outbuf(&mut self) -> IoResult<()> {
+        let written = self.stream.write(&self.outbuf).await?;
+        if written == 0 {
+            return Err(IoError::from(ErrorKind::WriteZero));
+        }
+        self.outbuf.advance(written);
+        if self.outbuf.is_empty() {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+/// A frame that is being queued in a connection's outbound buffer
+pub(super) struct PushFrame<'a, P> {
+    buf: &'a mut BytesMut,
+    _marker: PhantomData<P>,
+}
+
+impl<'a, P: ProtocolSpec> PushFrame<'a, P> {
+    /// Encode an array header
+    pub fn array_header(self, len: usize) -> Self {
+        self.buf.extend_from_slice(&[P::TSYMBOL_ARRAY]);
+        self.buf.extend_from_slice(&Integer64::from(len));
+        self.buf.extend_from_slice(&[P::LF]);
+        self
+    }
+    /// Encode an unicode string
+    pub fn string(self, string: &str) -> Self {
+        self.length_prefixed(string.as_bytes(), P::TSYMBOL_STRING)
+    }
+    /// Encode a blob
+    pub fn binary(self, binary: &[u8]) -> Self {
+        self.length_prefixed(binary, P::TSYMBOL_BINARY)
+    }
+    /// Encode an `u64`
+    pub fn int64(self, int: u64) -> Self {
+        self.buf.extend_from_slice(&[P::TSYMBOL_INT64]);
+        self.buf.extend_from_slice(&Integer64::from(int));
+        self.buf.extend_from_slice(&[P::LF]);
+        self
+    }
+    fn length_prefixed(self, data: &[u8], tsymbol: u8) -> Self {
+        self.buf.extend_from_slice(&[tsymbol]);
+        self.buf.extend_from_slice(&Integer64::from(data.len()));
+        self.buf.extend_from_slice(&[P::LF]);
+        self.buf.extend_from_slice(data);
+        if P::NEEDS_TERMINAL_LF {
+            self.buf.extend_from_slice(&[P::LF]);
+        }
+        self
+    }
+}
+
 // protocol write (dataframe)
 impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
     // monoelements
@@ -142,7 +357,7 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
         // now write LF
         self.stream.write_u8(P::LF).await?;
         // now write the actual body
-        self.stream.write_all(data).await?;
+        self.write_streamed(data).await?;
         if P::NEEDS_TERMINAL_LF {
             self.stream.write_u8(P::LF).await
         } else {
@@ -163,7 +378,6 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
             .await
     }
     /// Encode and write a blob
-    #[allow(unused)]
     pub async fn write_binary(&mut self, binary: &[u8]) -> IoResult<()> {
         self.write_mono_length_prefixed_with_tsymbol(binary, P::TSYMBOL_BINARY)
             .await
@@ -184,6 +398,14 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
             .await
     }
 
+    // array
+    /// Write an array header (the elements can be of any type)
+    pub async fn write_array_header(&mut self, len: usize) -> IoResult<()> {
+        self.stream.write_u8(P::TSYMBOL_ARRAY).await?;
+        self.stream.write_all(&Integer64::from(len)).await?;
+        self.stream.write_u8(P::LF).await
+    }
+
     // typed array
     /// Write a typed array header (including type information and size)
     pub async fn write_typed_array_header(&mut self, len: usize, tsymbol: u8) -> IoResult<()> {
@@ -205,7 +427,7 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
             .write_all(&Integer64::from(element.len()))
             .await?;
         self.stream.write_u8(P::LF).await?;
-        self.stream.write_all(element).await?;
+        self.write_streamed(element).await?;
         if P::NEEDS_TERMINAL_LF {
             self.stream.write_u8(P::LF).await
         } else {
@@ -246,3 +468,152 @@ impl<T: BufferedSocketStream, P: ProtocolSpec> Connection<T, P> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::protocol::Skyhash2,
+        core::{
+            pin::Pin,
+            task::{Context, Poll},
+        },
+        tokio::io::{AsyncRead, AsyncWrite, ReadBuf},
+    };
+
+    /// A stream that records everything written to it, and the number of bytes written before
+    /// each flush
+    #[derive(Default)]
+    struct FlushRecorder {
+        pending: usize,
+        flushes: Vec<usize>,
+        /// everything that was written
+        written: Vec<u8>,
+    }
+
+    impl AsyncRead for FlushRecorder {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            _: &mut ReadBuf<'_>,
+        ) -> Poll<IoResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncWrite for FlushRecorder {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<IoResult<usize>> {
+            self.pending += buf.len();
+            self.written.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+        fn poll_flush(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<IoResult<()>> {
+            if self.pending != 0 {
+                let pending = self.pending;
+                self.flushes.push(pending);
+                self.pending = 0;
+            }
+            Poll::Ready(Ok(()))
+        }
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<IoResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl BufferedSocketStream for FlushRecorder {}
+
+    #[tokio::test]
+    async fn large_values_are_streamed() {
+        let mut con = Connection::<FlushRecorder, Skyhash2>::new(FlushRecorder::default())
+            .with_max_inflight(100_000);
+        con.write_binary(&vec![b'x'; 250_000]).await.unwrap();
+        con.flush().await.unwrap();
+        // the header goes out with the first chunk, and the rest of the value is
+        // flushed once every 100 KB
+        assert_eq!(
+            con.stream.get_ref().flushes,
+            vec![100_000 + b"?250000\n".len(), 100_000, 50_000]
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_queries_are_rejected() {
+        let mut con = Connection::<FlushRecorder, Skyhash2>::new(FlushRecorder::default())
+            .with_limits(QueryLimits::new(16, 16, 16));
+        // the query claims a hundred elements and keeps on coming
+        con.buffer.extend_from_slice(b"*100\n");
+        for _ in 0..12 {
+            con.buffer.extend_from_slice(b"1\nx");
+        }
+        assert!(matches!(
+            con.read_query(None).await.unwrap(),
+            QueryResult::Disconnected
+        ));
+        assert!(con.buffer.is_empty());
+        assert_eq!(
+            con.stream.get_ref().flushes,
+            vec![Skyhash2::FULLRESP_RSTRING_QUERY_TOO_LARGE.len()]
+        );
+    }
+
+    #[tokio::test]
+    async fn pushed_frames_are_drained_before_reading() {
+        let mut con = Connection::<FlushRecorder, Skyhash2>::new(FlushRecorder::default())
+            .with_max_outbuf(32);
+        con.push_frame()
+            .array_header(2)
+            .string("set")
+            .binary(b"key");
+        assert_eq!(con.outbuf.as_ref(), b">&2\n+3\nset?3\nkey");
+        assert!(!con.outbuf_exceeded());
+        // a query is waiting, but the pushed frame has to go out first
+        con.buffer.extend_from_slice(b"*1\n3\nGET");
+        assert!(matches!(
+            con.read_query(None).await.unwrap(),
+            QueryResult::NextLoop
+        ));
+        assert!(con.outbuf.is_empty());
+        assert_eq!(
+            con.stream.get_ref().flushes,
+            vec![b">&2\n+3\nset?3\nkey".len()]
+        );
+        assert!(matches!(
+            con.read_query(None).await.unwrap(),
+            QueryResult::Q(_)
+        ));
+        // a client that doesn't read lets frames pile up
+        con.push_frame().binary(&[b'x'; 32]);
+        assert!(con.outbuf_exceeded());
+    }
+
+    #[tokio::test]
+    async fn pushed_frames_are_told_apart_from_responses() {
+        let mut con = Connection::<FlushRecorder, Skyhash2>::new(FlushRecorder::default());
+        con.push_frame().array_header(2).string("del").binary(b"a");
+        // the response to a query goes out after the frame that was pushed before it
+        con.buffer.extend_from_slice(b"*1\n3\nGET");
+        assert!(matches!(
+            con.read_query(None).await.unwrap(),
+            QueryResult::NextLoop
+        ));
+        assert!(matches!(
+            con.read_query(None).await.unwrap(),
+            QueryResult::Q(_)
+        ));
+        con.write_simple_query_header().await.unwrap();
+        con._write_raw(Skyhash2::RCODE_NIL).await.unwrap();
+        con.flush().await.unwrap();
+        // and then a change that happened after it
+        con.push_frame().array_header(2).string("set").binary(b"a");
+        con.write_outbuf().await.unwrap();
+        let mut expected = b">&2\n+3\ndel?1\na".to_vec();
+        expected.extend_from_slice(b"*");
+        expected.extend_from_slice(Skyhash2::RCODE_NIL);
+        expected.extend_from_slice(b">&2\n+3\nset?1\na");
+        assert_eq!(con.stream.get_ref().written, expected);
+    }
+}
@@ -0,0 +1,235 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Connection governor
+//!
+//! The governor decides whether a new client is admitted (there's an upper bound on the number
+//! of simultaneous clients), keeps track of when every admitted client was last active and
+//! lets the idle reaper (see [`crate::services::reaper`]) close clients that have been idle for
+//! too long. The limit and the timeouts are read from the runtime configuration, so they can be
+//! changed while the server is running (lowering the limit doesn't disconnect anyone, but no
+//! new clients are admitted until enough of them have left)
+
+use {
+    crate::{config::RuntimeConfig, corestore::htable::Coremap},
+    arc_swap::ArcSwap,
+    core::sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    std::{sync::Arc, time::Duration},
+    tokio::{sync::Notify, time::Instant},
+};
+
+/// The state that the governor keeps for every connected client
+pub struct ClientState {
+    /// the time (in milliseconds since the governor was started) at which the client was last
+    /// active
+    last_active: AtomicU64,
+    /// notified when the client has to be disconnected
+    reap: Notify,
+}
+
+impl ClientState {
+    fn new(now: u64) -> Self {
+        Self {
+            last_active: AtomicU64::new(now),
+            reap: Notify::new(),
+        }
+    }
+}
+
+/// The connection governor
+pub struct Governor {
+    /// the clients that are connected right now
+    clients: Coremap<u64, Arc<ClientState>>,
+    /// the ID to be assigned to the next client
+    next_id: AtomicU64,
+    /// the number of clients that were admitted and haven't disconnected yet
+    admitted: AtomicUsize,
+    /// the runtime configuration (with the client limit and the timeouts)
+    config: Arc<ArcSwap<RuntimeConfig>>,
+    /// the instant from which activity timestamps are measured
+    epoch: Instant,
+}
+
+impl Governor {
+    pub fn new(config: Arc<ArcSwap<RuntimeConfig>>) -> Self {
+        Self {
+            clients: Coremap::new(),
+            next_id: AtomicU64::new(1),
+            admitted: AtomicUsize::new(0),
+            config,
+            epoch: Instant::now(),
+        }
+    }
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+    /// Admit a new client, if we're not already serving the maximum number of clients
+    pub fn try_admit(self: &Arc<Self>) -> Option<ClientHandle> {
+        let maxcon = self.config.load().maxcon;
+        self.admitted
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |admitted| {
+                (admitted < maxcon).then_some(admitted + 1)
+            })
+            .ok()?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let state = Arc::new(ClientState::new(self.now()));
+        self.clients.true_if_insert(id, state.clone());
+        Some(ClientHandle {
+            id,
+            state,
+            governor: self.clone(),
+        })
+    }
+    /// Returns the number of connected clients
+    pub fn connected_clients(&self) -> usize {
+        self.clients.len()
+    }
+    /// Returns the time for which a query can be partially read before the client is
+    /// disconnected
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.config.load().timeouts.read()
+    }
+    /// Returns the time for which a client can stay idle
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.config.load().timeouts.idle()
+    }
+    /// Signal all the clients that have been idle for longer than the idle timeout to
+    /// disconnect, returning the number of clients that were signalled
+    pub fn reap_idle(&self) -> usize {
+        let idle = match self.idle_timeout() {
+            Some(idle) => idle.as_millis() as u64,
+            None => return 0,
+        };
+        let now = self.now();
+        let mut reaped = 0;
+        for client in self.clients.iter() {
+            let state = client.value();
+            if now.saturating_sub(state.last_active.load(Ordering::Relaxed)) >= idle {
+                state.reap.notify_one();
+                reaped += 1;
+            }
+        }
+        reaped
+    }
+}
+
+/// A handle to an admitted client. Dropping the handle releases the client's slot
+pub struct ClientHandle {
+    id: u64,
+    state: Arc<ClientState>,
+    governor: Arc<Governor>,
+}
+
+impl ClientHandle {
+    /// Returns the unique ID of this client
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+    /// Mark the client as active
+    pub fn touch(&self) {
+        self.state
+            .last_active
+            .store(self.governor.now(), Ordering::Relaxed)
+    }
+    /// Wait until the reaper asks us to disconnect
+    pub async fn reaped(&self) {
+        self.state.reap.notified().await
+    }
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.governor.read_timeout()
+    }
+}
+
+impl Drop for ClientHandle {
+    fn drop(&mut self) {
+        self.governor.clients.remove(&self.id);
+        self.governor.admitted.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+#[cfg(test)]
+fn runtime_config(maxcon: usize, timeouts: crate::config::ConnectionTimeouts) -> RuntimeConfig {
+    RuntimeConfig::new(
+        vec![],
+        crate::config::BGSave::default(),
+        maxcon,
+        timeouts,
+        crate::config::RateLimits::default(),
+        crate::config::IpFilter::default(),
+        0,
+    )
+}
+
+#[test]
+fn test_governor_admission() {
+    use crate::config::ConnectionTimeouts;
+    let governor = Arc::new(Governor::new(
+        runtime_config(2, ConnectionTimeouts::default()).into_shared(),
+    ));
+    let first = governor.try_admit().unwrap();
+    let second = governor.try_admit().unwrap();
+    assert_ne!(first.id(), second.id());
+    assert_eq!(governor.connected_clients(), 2);
+    // we're full
+    assert!(governor.try_admit().is_none());
+    // a disconnect frees up a slot
+    drop(first);
+    assert_eq!(governor.connected_clients(), 1);
+    assert!(governor.try_admit().is_some());
+}
+
+#[tokio::test]
+async fn test_governor_reap_idle() {
+    let governor = Arc::new(Governor::new(
+        runtime_config(10, crate::config::ConnectionTimeouts::new(1, 0)).into_shared(),
+    ));
+    let client = governor.try_admit().unwrap();
+    assert_eq!(governor.reap_idle(), 0);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(governor.reap_idle(), 1);
+    // the notification is stored until the client waits for it
+    client.reaped().await;
+    client.touch();
+    assert_eq!(governor.reap_idle(), 0);
+}
+
+#[test]
+fn test_governor_reload() {
+    use crate::config::ConnectionTimeouts;
+    let config = runtime_config(2, ConnectionTimeouts::default()).into_shared();
+    let governor = Arc::new(Governor::new(config.clone()));
+    let first = governor.try_admit().unwrap();
+    let _second = governor.try_admit().unwrap();
+    assert!(governor.read_timeout().is_none());
+    // lower the limit: nobody is disconnected, but there's no room for new clients
+    config.store(Arc::new(runtime_config(1, ConnectionTimeouts::new(0, 5))));
+    assert_eq!(governor.read_timeout(), Some(Duration::from_secs(5)));
+    drop(first);
+    assert!(governor.try_admit().is_none());
+    // raise it again
+    config.store(Arc::new(runtime_config(3, ConnectionTimeouts::default())));
+    assert!(governor.try_admit().is_some());
+}
@@ -0,0 +1,533 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # gRPC service
+//!
+//! With the `grpc` feature, the server can expose a typed gRPC interface (see
+//! `proto/skytable.proto`) on `server.grpcport`. Every call is run like a request to the HTTP
+//! gateway: it is admitted by the connection governor, logs in with the `authorization`
+//! metadata entry and runs its queries through the query engine. Scans and key watches are
+//! streamed to the client by a task that lives as long as the call
+
+mod pb {
+    tonic::include_proto!("skytable");
+}
+
+use {
+    self::pb::{
+        skytable_server::{Skytable, SkytableServer},
+        DelRequest, DelResponse, GetRequest, GetResponse, KeyEvent as PbKeyEvent, ScanRequest,
+        ScanResponse, SetRequest, SetResponse, WatchRequest, WatchResponse,
+    },
+    super::{
+        governor::{ClientHandle, Governor},
+        screen_peer, set_keepalive,
+        stateless::{self, Element, LoginError},
+    },
+    crate::{
+        actions::ActionError,
+        auth::AuthProvider,
+        corestore::SharedSlice,
+        dbnet::prelude::*,
+        kvengine::notify::KeyEvent,
+        protocol::Skyhash2,
+        queryengine::{self, notify::KeyWatch},
+        util::error::{Error, SkyResult},
+    },
+    std::{io::Error as IoError, net::IpAddr, sync::Arc},
+    tokio::{
+        net::TcpListener,
+        sync::{broadcast, mpsc, watch},
+        task::JoinHandle,
+    },
+    tokio_stream::{
+        wrappers::{ReceiverStream, TcpListenerStream},
+        StreamExt,
+    },
+    tonic::{transport::Server, Request, Response, Status},
+};
+
+/// The number of responses that we buffer for a streaming call
+const STREAM_BUFFER: usize = 16;
+
+type ResponseStream<T> = ReceiverStream<Result<T, Status>>;
+
+/// Returns the status for a response code that a query failed with
+fn status_of(code: &str) -> Status {
+    match code {
+        // nil
+        "1" | "default-container-unset" | "container-not-found" => Status::not_found(code),
+        // overwrite error
+        "2" => Status::already_exists(code),
+        "5" => Status::internal(code),
+        "10" => Status::unauthenticated(code),
+        "11" => Status::permission_denied(code),
+        "12" | "too-many-connections" => Status::resource_exhausted(code),
+        "cluster-down" | "try-again" | "not-ready" | "lagging" => Status::unavailable(code),
+        "readonly-replica" | "not-lww" => Status::failed_precondition(code),
+        _ if code.starts_with("moved ") || code.starts_with("ask ") => {
+            Status::failed_precondition(code)
+        }
+        _ => Status::invalid_argument(code),
+    }
+}
+
+fn status_of_io(e: IoError) -> Status {
+    Status::internal(e.to_string())
+}
+
+fn unexpected() -> Status {
+    Status::internal("unexpected response")
+}
+
+/// Returns the status for an error that an action (or its authorization) failed with
+fn status_of_action(e: ActionError) -> Status {
+    match e {
+        ActionError::ActionError(code) => match Element::decode(code) {
+            Some(Element::Code(code)) => status_of(&code),
+            _ => unexpected(),
+        },
+        ActionError::IoError(e) => status_of_io(e),
+    }
+}
+
+fn into_bytes(element: Element) -> Option<Vec<u8>> {
+    match element {
+        Element::Binary(value) => Some(value),
+        Element::String(value) => Some(value.into_bytes()),
+        _ => None,
+    }
+}
+
+/// Split the response to a paginated `LSKEYS` into the next cursor and the keys
+fn scan_batch(element: Element) -> Result<(u64, Vec<Vec<u8>>), Status> {
+    let mut elements = match element {
+        Element::Array(elements) => elements.into_iter(),
+        Element::Code(code) => return Err(status_of(&code)),
+        _ => return Err(unexpected()),
+    };
+    let cursor = elements
+        .next()
+        .and_then(into_bytes)
+        .and_then(|cursor| String::from_utf8(cursor).ok())
+        .and_then(|cursor| cursor.parse().ok())
+        .ok_or_else(unexpected)?;
+    let keys = elements
+        .map(into_bytes)
+        .collect::<Option<_>>()
+        .ok_or_else(unexpected)?;
+    Ok((cursor, keys))
+}
+
+fn watch_response(event: KeyEvent, key: Vec<u8>) -> WatchResponse {
+    let event = match event {
+        KeyEvent::Set => PbKeyEvent::Set,
+        KeyEvent::Update => PbKeyEvent::Update,
+        KeyEvent::Delete => PbKeyEvent::Del,
+        KeyEvent::Flush => PbKeyEvent::Flush,
+    };
+    WatchResponse {
+        event: event as i32,
+        key,
+        missed: 0,
+    }
+}
+
+/// An admitted and logged in call
+struct Session {
+    db: Corestore,
+    auth: AuthProviderHandle,
+    /// this call's slot with the connection governor (released on drop)
+    client: ClientHandle,
+}
+
+impl Session {
+    /// Switch to the table that the call works on (the default table is used if `entity`
+    /// is empty)
+    async fn enter(&mut self, entity: &str) -> Result<(), Status> {
+        if entity.is_empty() {
+            return Ok(());
+        }
+        match self.query(&[format!("USE {entity}").into_bytes()]).await? {
+            Element::Code(code) if code == "0" => Ok(()),
+            Element::Code(code) => Err(status_of(&code)),
+            _ => Err(unexpected()),
+        }
+    }
+    async fn query(&mut self, query: &[Vec<u8>]) -> Result<Element, Status> {
+        self.client.touch();
+        stateless::run_query(&mut self.db, &mut self.auth, self.client.id(), query)
+            .await
+            .map_err(status_of_io)
+    }
+}
+
+/// Send an item on a stream, returning false if the stream should be ended (because the
+/// client went away, was reaped or because we're shutting down)
+async fn forward<T>(
+    tx: &mpsc::Sender<Result<T, Status>>,
+    session: &Session,
+    stop: &mut watch::Receiver<bool>,
+    item: Result<T, Status>,
+) -> bool {
+    tokio::select! {
+        sent = tx.send(item) => sent.is_ok(),
+        _ = session.client.reaped() => false,
+        _ = stop.changed() => false,
+    }
+}
+
+#[derive(Clone)]
+struct GrpcService {
+    db: Corestore,
+    auth: AuthProvider,
+    governor: Arc<Governor>,
+    /// set to true when we're shutting down, to end the streaming calls
+    stop: watch::Receiver<bool>,
+    _term_sig_tx: mpsc::Sender<()>,
+}
+
+impl GrpcService {
+    /// Admit a call and log in with its credentials
+    async fn open<T>(&self, request: &Request<T>) -> Result<Session, Status> {
+        let client = match self.governor.try_admit() {
+            Some(client) => client,
+            None => {
+                self.db.get_stats().client_rejected();
+                return Err(Status::resource_exhausted("too-many-connections"));
+            }
+        };
+        let peer = request.remote_addr().map(|addr| addr.ip());
+        let mut auth = AuthProviderHandle::new(self.auth.for_client(peer));
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        match stateless::login_bearer(&mut auth, authorization).await {
+            Ok(()) => {}
+            Err(LoginError::BadCredentials) => return Err(Status::unauthenticated("10")),
+            Err(LoginError::RateLimited) => return Err(Status::resource_exhausted("12")),
+        }
+        Ok(Session {
+            db: self.db.clone(),
+            auth,
+            client,
+        })
+    }
+    /// Returns the stop signal for a streaming call, or `None` if we're already shutting down
+    fn stop_signal(&self) -> Option<watch::Receiver<bool>> {
+        let stop = self.stop.clone();
+        let stopped = *stop.borrow();
+        (!stopped).then_some(stop)
+    }
+}
+
+#[tonic::async_trait]
+impl Skytable for GrpcService {
+    async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetResponse>, Status> {
+        let mut session = self.open(&request).await?;
+        let GetRequest { entity, key } = request.into_inner();
+        session.enter(&entity).await?;
+        let response = match session.query(&[b"GET".to_vec(), key]).await? {
+            Element::Code(code) if code == "1" => GetResponse {
+                found: false,
+                value: Vec::new(),
+            },
+            Element::Code(code) => return Err(status_of(&code)),
+            element => GetResponse {
+                found: true,
+                value: into_bytes(element).ok_or_else(unexpected)?,
+            },
+        };
+        Ok(Response::new(response))
+    }
+    async fn set(&self, request: Request<SetRequest>) -> Result<Response<SetResponse>, Status> {
+        let mut session = self.open(&request).await?;
+        let SetRequest { entity, key, value } = request.into_inner();
+        session.enter(&entity).await?;
+        match session.query(&[b"SET".to_vec(), key, value]).await? {
+            Element::Code(code) if code == "0" => Ok(Response::new(SetResponse {})),
+            Element::Code(code) => Err(status_of(&code)),
+            _ => Err(unexpected()),
+        }
+    }
+    async fn update(&self, request: Request<SetRequest>) -> Result<Response<SetResponse>, Status> {
+        let mut session = self.open(&request).await?;
+        let SetRequest { entity, key, value } = request.into_inner();
+        session.enter(&entity).await?;
+        match session.query(&[b"UPDATE".to_vec(), key, value]).await? {
+            Element::Code(code) if code == "0" => Ok(Response::new(SetResponse {})),
+            Element::Code(code) => Err(status_of(&code)),
+            _ => Err(unexpected()),
+        }
+    }
+    async fn del(&self, request: Request<DelRequest>) -> Result<Response<DelResponse>, Status> {
+        let mut session = self.open(&request).await?;
+        let DelRequest { entity, keys } = request.into_inner();
+        if keys.is_empty() {
+            return Ok(Response::new(DelResponse { removed: 0 }));
+        }
+        session.enter(&entity).await?;
+        let mut query = Vec::with_capacity(keys.len() + 1);
+        query.push(b"DEL".to_vec());
+        query.extend(keys);
+        match session.query(&query).await? {
+            Element::Int(removed) => Ok(Response::new(DelResponse { removed })),
+            Element::Code(code) => Err(status_of(&code)),
+            _ => Err(unexpected()),
+        }
+    }
+
+    type ScanStream = ResponseStream<ScanResponse>;
+
+    async fn scan(
+        &self,
+        request: Request<ScanRequest>,
+    ) -> Result<Response<Self::ScanStream>, Status> {
+        let mut session = self.open(&request).await?;
+        let ScanRequest {
+            entity,
+            pattern,
+            batch,
+        } = request.into_inner();
+        session.enter(&entity).await?;
+        let mut stop = self
+            .stop_signal()
+            .ok_or_else(|| Status::unavailable("err-access-after-termsig"))?;
+        let term_sig_tx = self._term_sig_tx.clone();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            let mut cursor = 0;
+            loop {
+                let mut query = vec![b"LSKEYS".to_vec()];
+                if !pattern.is_empty() {
+                    query.push(b"MATCH".to_vec());
+                    query.push(pattern.clone().into_bytes());
+                }
+                query.push(b"CURSOR".to_vec());
+                query.push(cursor.to_string().into_bytes());
+                if batch != 0 {
+                    query.push(b"LIMIT".to_vec());
+                    query.push(batch.to_string().into_bytes());
+                }
+                let batch = match session.query(&query).await {
+                    Ok(element) => scan_batch(element),
+                    Err(e) => Err(e),
+                };
+                let (next, keys) = match batch {
+                    Ok(batch) => batch,
+                    Err(e) => {
+                        forward(&tx, &session, &mut stop, Err(e)).await;
+                        break;
+                    }
+                };
+                if !keys.is_empty()
+                    && !forward(&tx, &session, &mut stop, Ok(ScanResponse { keys })).await
+                {
+                    break;
+                }
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+            drop(term_sig_tx);
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    type WatchStream = ResponseStream<WatchResponse>;
+
+    async fn watch(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let mut session = self.open(&request).await?;
+        let WatchRequest { entity, prefixes } = request.into_inner();
+        session.enter(&entity).await?;
+        queryengine::authorize::<Skyhash2>(&session.db, &mut session.auth, b"watchprefix")
+            .map_err(status_of_action)?;
+        let table = session
+            .db
+            .get_ctable()
+            .ok_or_else(|| Status::not_found("default-container-unset"))?;
+        let prefixes = if prefixes.is_empty() {
+            // every key starts with the empty prefix
+            vec![SharedSlice::new(b"")]
+        } else {
+            prefixes
+                .iter()
+                .map(|prefix| SharedSlice::new(prefix))
+                .collect()
+        };
+        let mut keywatch = KeyWatch::new(table, prefixes);
+        let mut stop = self
+            .stop_signal()
+            .ok_or_else(|| Status::unavailable("err-access-after-termsig"))?;
+        let term_sig_tx = self._term_sig_tx.clone();
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            loop {
+                let response = tokio::select! {
+                    change = keywatch.next() => match change {
+                        Ok(change) => watch_response(change.event, change.key.as_ref().to_vec()),
+                        Err(missed) => WatchResponse {
+                            event: PbKeyEvent::Lagged as i32,
+                            key: Vec::new(),
+                            missed,
+                        },
+                    },
+                    _ = tx.closed() => break,
+                    _ = session.client.reaped() => break,
+                    _ = stop.changed() => break,
+                };
+                session.client.touch();
+                if !forward(&tx, &session, &mut stop, Ok(response)).await {
+                    break;
+                }
+            }
+            drop(term_sig_tx);
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// The listener for the gRPC service. The service is run by its own task (started by
+/// [`GrpcListener::init`]) since tonic accepts the connections itself
+pub struct GrpcListener {
+    server: JoinHandle<()>,
+    terminate_rx: mpsc::Receiver<()>,
+}
+
+impl GrpcListener {
+    /// Bind to the port and start serving calls. The service shuts down once every sender of
+    /// `signal` is dropped
+    pub async fn init(
+        db: &Corestore,
+        auth: AuthProvider,
+        host: IpAddr,
+        port: u16,
+        governor: Arc<Governor>,
+        keepalive: u64,
+        signal: &broadcast::Sender<()>,
+    ) -> SkyResult<Self> {
+        let listener = TcpListener::bind((host, port))
+            .await
+            .map_err(|e| Error::ioerror_extra(e, format!("binding to port {port}")))?;
+        let (terminate_tx, terminate_rx) = mpsc::channel(1);
+        let (stop_tx, stop) = watch::channel(false);
+        let service = GrpcService {
+            db: db.clone(),
+            auth,
+            governor,
+            stop,
+            _term_sig_tx: terminate_tx,
+        };
+        let mut signal = signal.subscribe();
+        let shutdown = async move {
+            let _ = signal.recv().await;
+            let _ = stop_tx.send(true);
+        };
+        let handle = db.clone();
+        let incoming = TcpListenerStream::new(listener).filter_map(move |stream| match stream {
+            // clients that are turned away by the IP rules are dropped right away
+            Ok(stream) => match stream.peer_addr() {
+                Ok(addr) if !screen_peer(&handle, addr.ip()) => None,
+                _ => {
+                    set_keepalive(&stream, keepalive);
+                    Some(Ok::<_, IoError>(stream))
+                }
+            },
+            Err(e) => {
+                log::debug!("Failed to accept a gRPC connection: {}", e);
+                None
+            }
+        });
+        let server = tokio::spawn(async move {
+            let server = Server::builder()
+                .add_service(SkytableServer::new(service))
+                .serve_with_incoming_shutdown(incoming, shutdown);
+            if let Err(e) = server.await {
+                log::error!("gRPC service failed with: {}", e);
+            }
+        });
+        Ok(Self {
+            server,
+            terminate_rx,
+        })
+    }
+    /// Wait for the service and all of its calls to finish
+    pub async fn release_self(self) {
+        let Self {
+            server,
+            mut terminate_rx,
+        } = self;
+        let _ = server.await;
+        let _ = terminate_rx.recv().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_of_codes() {
+        assert_eq!(status_of("1").code(), tonic::Code::NotFound);
+        assert_eq!(status_of("2").code(), tonic::Code::AlreadyExists);
+        assert_eq!(status_of("10").code(), tonic::Code::Unauthenticated);
+        assert_eq!(status_of("11").code(), tonic::Code::PermissionDenied);
+        assert_eq!(status_of("try-again").code(), tonic::Code::Unavailable);
+        assert_eq!(
+            status_of("moved 3 127.0.0.1:2003").code(),
+            tonic::Code::FailedPrecondition
+        );
+        assert_eq!(status_of("7").code(), tonic::Code::InvalidArgument);
+        assert_eq!(
+            status_of_action(ActionError::ActionError(Skyhash2::AUTH_CODE_PERMS)).code(),
+            tonic::Code::PermissionDenied
+        );
+    }
+
+    #[test]
+    fn scan_batches() {
+        let batch = Element::Array(vec![
+            Element::String("42".to_owned()),
+            Element::String("a".to_owned()),
+            Element::Binary(b"b".to_vec()),
+        ]);
+        assert_eq!(
+            scan_batch(batch).unwrap(),
+            (42, vec![b"a".to_vec(), b"b".to_vec()])
+        );
+        let done = Element::Array(vec![Element::String("0".to_owned())]);
+        assert_eq!(scan_batch(done).unwrap(), (0, vec![]));
+        let error = scan_batch(Element::Code("default-container-unset".to_owned()));
+        assert_eq!(error.unwrap_err().code(), tonic::Code::NotFound);
+        let malformed = scan_batch(Element::Array(vec![Element::Int(1)]));
+        assert_eq!(malformed.unwrap_err().code(), tonic::Code::Internal);
+    }
+}
@@ -0,0 +1,376 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Protocol negotiation
+//!
+//! A client can start a connection by declaring the (major) Skyhash versions that it speaks
+//! with a handshake like `H1,2\n`. We pick the highest version that both of us support and
+//! reply with `H<version>\n`, after which the connection speaks that version. If we have no
+//! version in common, we reply with `H0\n` and disconnect. Clients that don't start with a
+//! handshake speak the version set with `server.protocol`
+//!
+//! A client can also ask for compression by listing the codecs that it supports (in the order
+//! of preference) after its versions, like `H1,2;zstd,lz4\n`. If compression is enabled, we
+//! pick the first codec that we support and name it in the reply, like `H2;zstd\n`. The
+//! connection is then framed (see [`super::compression`]). Clients that ask for compression
+//! have to wait for the reply before sending any queries
+//!
+//! ## Topology
+//!
+//! Smart clients (see [`crate::cluster`]) can ask for the slot table in their handshake, so
+//! that they don't have to run `CLUSTER SLOTS` every time they reconnect. Options go after the
+//! codecs (which can be left empty), like `H2;zstd;topology\n` or `H2;;topology\n`. The reply
+//! then has a third section with the epoch, the number of slot ranges and this node's address
+//! (which is its ID in the cluster), like `H2;zstd;7,2,10.0.0.1:2003\n`, followed by a line
+//! for every range, like `0,8191,10.0.0.1:2003\n`. The ranges are the ones of that epoch, so
+//! clients can keep them until they see a higher epoch (or get a `moved`). If the node isn't in
+//! cluster mode, the third section is just `-`. Like the rest of the reply, the ranges are
+//! never framed
+
+use {
+    super::{compression::Codec, BufferedSocketStream, Connection},
+    crate::{cluster::Cluster, config::ProtocolVersion, IoResult},
+    bytes::Buf,
+    std::time::Duration,
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        time,
+    },
+};
+
+/// The first byte of a handshake (queries start with `*` or `$`)
+const HANDSHAKE_FIRST_BYTE: u8 = b'H';
+/// The longest handshake that we accept
+const MAX_HANDSHAKE_SIZE: usize = 64;
+/// The versions that we support, in the order of preference
+const SUPPORTED_VERSIONS: [ProtocolVersion; 2] = [ProtocolVersion::V2, ProtocolVersion::V1];
+/// Sent when there's no version that we have in common with the client
+const RESPONSE_NO_VERSION: &[u8] = b"H0\n";
+/// The option that asks for the slot table
+const OPTION_TOPOLOGY: &[u8] = b"topology";
+
+/// The outcome of a client's handshake
+#[derive(Debug, PartialEq, Eq)]
+pub enum Negotiated {
+    /// the client didn't send a handshake, so it speaks the configured version
+    Default,
+    /// the client negotiated this version (and codec, if it asked for compression)
+    Version(ProtocolVersion, Option<Codec>),
+    /// the client disconnected, timed out or has no version in common with us
+    Failed,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Parsed {
+    /// the buffer doesn't start with a handshake
+    NotHandshake,
+    /// we need more data
+    Incomplete,
+    /// the handshake is malformed (or too long)
+    Bad,
+    /// the client speaks these versions and supports these codecs (that we know of), wants the
+    /// slot table if `topology` is set and the handshake took up `advance` bytes
+    Complete {
+        versions: Vec<ProtocolVersion>,
+        codecs: Vec<Codec>,
+        topology: bool,
+        advance: usize,
+    },
+}
+
+const fn major_version(version: ProtocolVersion) -> u8 {
+    match version {
+        ProtocolVersion::V1 => b'1',
+        ProtocolVersion::V2 => b'2',
+    }
+}
+
+fn parse(buf: &[u8]) -> Parsed {
+    match buf.first() {
+        Some(&HANDSHAKE_FIRST_BYTE) => {}
+        Some(_) => return Parsed::NotHandshake,
+        None => return Parsed::Incomplete,
+    }
+    let end = match buf
+        .iter()
+        .take(MAX_HANDSHAKE_SIZE)
+        .position(|b| *b == b'\n')
+    {
+        Some(end) => end,
+        None if buf.len() >= MAX_HANDSHAKE_SIZE => return Parsed::Bad,
+        None => return Parsed::Incomplete,
+    };
+    let mut line = buf[1..end].splitn(3, |b| *b == b';');
+    let mut versions = Vec::new();
+    for version in line.next().unwrap_or_default().split(|b| *b == b',') {
+        if version.is_empty() || !version.iter().all(u8::is_ascii_digit) {
+            return Parsed::Bad;
+        }
+        // versions that we don't know of are fine, they just can't be picked
+        versions.extend(
+            SUPPORTED_VERSIONS
+                .iter()
+                .copied()
+                .filter(|supported| version == [major_version(*supported)]),
+        );
+    }
+    let (names, options) = (line.next(), line.next());
+    let mut codecs = Vec::new();
+    if let Some(names) = names {
+        // the codecs can only be left empty if there are options after them
+        if !(names.is_empty() && options.is_some()) {
+            for name in names.split(|b| *b == b',') {
+                if name.is_empty() {
+                    return Parsed::Bad;
+                }
+                codecs.extend(Codec::of(name));
+            }
+        }
+    }
+    let mut topology = false;
+    if let Some(options) = options {
+        for option in options.split(|b| *b == b',') {
+            match option {
+                b"" => return Parsed::Bad,
+                OPTION_TOPOLOGY => topology = true,
+                // options that we don't know of are ignored
+                _ => {}
+            }
+        }
+    }
+    Parsed::Complete {
+        versions,
+        codecs,
+        topology,
+        advance: end + 1,
+    }
+}
+
+/// Returns the highest version that we support out of the client's versions
+fn select(versions: &[ProtocolVersion]) -> Option<ProtocolVersion> {
+    SUPPORTED_VERSIONS
+        .iter()
+        .copied()
+        .find(|version| versions.contains(version))
+}
+
+/// Returns our reply to a handshake. `cluster` is only given if the client asked for the slot
+/// table
+fn response(version: ProtocolVersion, codec: Option<Codec>, cluster: Option<&Cluster>) -> Vec<u8> {
+    let mut response = vec![HANDSHAKE_FIRST_BYTE, major_version(version)];
+    if let Some(codec) = codec {
+        response.push(b';');
+        response.extend(codec.name());
+    }
+    let cluster = match cluster {
+        Some(cluster) => cluster,
+        None => {
+            response.push(b'\n');
+            return response;
+        }
+    };
+    if codec.is_none() {
+        // the codecs are left empty, like in the client's handshake
+        response.push(b';');
+    }
+    response.push(b';');
+    match cluster.snapshot() {
+        Some(snapshot) => {
+            let header = format!(
+                "{},{},{}\n",
+                snapshot.epoch,
+                snapshot.ranges.len(),
+                snapshot.myself
+            );
+            response.extend(header.as_bytes());
+            for range in snapshot.ranges {
+                let range = format!("{},{},{}\n", range.start, range.end, range.node);
+                response.extend(range.as_bytes());
+            }
+        }
+        None => response.extend(b"-\n"),
+    }
+    response
+}
+
+/// Read the client's handshake (if it sends one) and reply to it. A codec is only picked if
+/// `compression` is enabled and the slot table is read from `cluster` if the client asks for
+/// it. Anything that the client sent after the handshake is left in the connection's buffer
+///
+/// Like with queries, once a part of the handshake has been received, the rest of it has to
+/// arrive within the `read_timeout` (if any)
+pub async fn negotiate<C: BufferedSocketStream, P>(
+    con: &mut Connection<C, P>,
+    read_timeout: Option<Duration>,
+    compression: bool,
+    cluster: &Cluster,
+) -> IoResult<Negotiated> {
+    loop {
+        let partial = !con.buffer.is_empty();
+        match parse(&con.buffer) {
+            Parsed::NotHandshake => return Ok(Negotiated::Default),
+            Parsed::Incomplete => {}
+            Parsed::Bad => {
+                con.stream.write_all(RESPONSE_NO_VERSION).await?;
+                con.stream.flush().await?;
+                return Ok(Negotiated::Failed);
+            }
+            Parsed::Complete {
+                versions,
+                codecs,
+                topology,
+                advance,
+            } => {
+                con.buffer.advance(advance);
+                let version = match select(&versions) {
+                    Some(version) => version,
+                    None => {
+                        con.stream.write_all(RESPONSE_NO_VERSION).await?;
+                        con.stream.flush().await?;
+                        return Ok(Negotiated::Failed);
+                    }
+                };
+                let codec = codecs.first().copied().filter(|_| compression);
+                let response = response(version, codec, Some(cluster).filter(|_| topology));
+                con.stream.write_all(&response).await?;
+                con.stream.flush().await?;
+                return Ok(Negotiated::Version(version, codec));
+            }
+        }
+        let read = con.stream.read_buf(&mut con.buffer);
+        let read = match read_timeout {
+            Some(timeout) if partial => match time::timeout(timeout, read).await {
+                Ok(read) => read,
+                Err(_) => return Ok(Negotiated::Failed),
+            },
+            _ => read.await,
+        };
+        if read? == 0 {
+            return Ok(Negotiated::Failed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::cluster::{Node, SLOT_COUNT},
+    };
+
+    #[test]
+    fn parse_handshake() {
+        assert_eq!(parse(b"*1\n~1\n4\nPING\n"), Parsed::NotHandshake);
+        assert_eq!(parse(b""), Parsed::Incomplete);
+        assert_eq!(parse(b"H1,2"), Parsed::Incomplete);
+        assert_eq!(
+            parse(b"H1,2\n*1\n"),
+            Parsed::Complete {
+                versions: vec![ProtocolVersion::V1, ProtocolVersion::V2],
+                codecs: vec![],
+                topology: false,
+                advance: 5
+            }
+        );
+        // unknown versions are skipped
+        assert_eq!(
+            parse(b"H3,1\n"),
+            Parsed::Complete {
+                versions: vec![ProtocolVersion::V1],
+                codecs: vec![],
+                topology: false,
+                advance: 5
+            }
+        );
+        // so are unknown codecs
+        assert_eq!(
+            parse(b"H2;brotli,zstd,lz4\n"),
+            Parsed::Complete {
+                versions: vec![ProtocolVersion::V2],
+                codecs: vec![Codec::Zstd, Codec::Lz4],
+                topology: false,
+                advance: 19
+            }
+        );
+        // the codecs can be left empty if there are options
+        assert_eq!(
+            parse(b"H2;;topology\n"),
+            Parsed::Complete {
+                versions: vec![ProtocolVersion::V2],
+                codecs: vec![],
+                topology: true,
+                advance: 13
+            }
+        );
+        // and unknown options are skipped
+        assert_eq!(
+            parse(b"H2;lz4;routes,topology\n"),
+            Parsed::Complete {
+                versions: vec![ProtocolVersion::V2],
+                codecs: vec![Codec::Lz4],
+                topology: true,
+                advance: 23
+            }
+        );
+        assert_eq!(parse(b"H2;;\n"), Parsed::Bad);
+        assert_eq!(parse(b"H2;zstd;topology,\n"), Parsed::Bad);
+        assert_eq!(parse(b"H2;\n"), Parsed::Bad);
+        assert_eq!(parse(b"H1,,2\n"), Parsed::Bad);
+        assert_eq!(parse(b"H\n"), Parsed::Bad);
+        assert_eq!(parse(b"Hv2\n"), Parsed::Bad);
+        let mut long = vec![b'H'];
+        long.extend([b'1'; MAX_HANDSHAKE_SIZE]);
+        assert_eq!(parse(&long), Parsed::Bad);
+    }
+
+    #[test]
+    fn select_highest_version() {
+        assert_eq!(
+            select(&[ProtocolVersion::V1, ProtocolVersion::V2]),
+            Some(ProtocolVersion::V2)
+        );
+        assert_eq!(select(&[ProtocolVersion::V1]), Some(ProtocolVersion::V1));
+        assert_eq!(select(&[]), None);
+    }
+
+    #[test]
+    fn response_with_topology() {
+        let (v2, zstd) = (ProtocolVersion::V2, Some(Codec::Zstd));
+        let cluster = Cluster::new();
+        assert_eq!(response(v2, None, None), b"H2\n");
+        assert_eq!(response(v2, zstd, None), b"H2;zstd\n");
+        // not in cluster mode
+        assert_eq!(response(v2, None, Some(&cluster)), b"H2;;-\n");
+        let node = |port| Node::new("10.0.0.1".to_owned(), port);
+        cluster.set_myself(node(2003));
+        cluster.assign(0, 8191, node(2003));
+        cluster.assign(8192, SLOT_COUNT - 1, node(2004));
+        assert_eq!(
+            response(v2, zstd, Some(&cluster)),
+            b"H2;zstd;3,2,10.0.0.1:2003\n0,8191,10.0.0.1:2003\n8192,16383,10.0.0.1:2004\n"
+        );
+    }
+}
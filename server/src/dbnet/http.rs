@@ -0,0 +1,868 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # The HTTP gateway
+//!
+//! An optional HTTP/1.1 listener that makes the database usable from `curl` and from
+//! environments that can't hold on to a Skyhash connection. It has the following routes:
+//!
+//! - `GET /keys/{key}`: returns the value of the key as the body of the response
+//! - `PUT /keys/{key}`: sets the key to the body of the request (replacing any value that it had)
+//! - `DELETE /keys/{key}`: removes the key
+//! - `POST /query`: runs a query like `["SET", "x", "100"]` (or an array of such queries, one
+//! after the other) and returns the response as JSON
+//!
+//! Every route takes an optional `entity` parameter (like `?entity=space.model`) to pick the
+//! table. If auth is enabled, every request must carry an `Authorization: Bearer <user>:<token>`
+//! header. The gateway doesn't keep any state between requests, so queries that work with
+//! the state of a connection (like `MULTI` or `WATCH`) can't be run over it.
+//!
+//! ## JSON responses
+//!
+//! Strings and integers become JSON strings and numbers. Binary strings become JSON strings if
+//! they're valid UTF-8, and `{"base64": "..."}` otherwise. Null elements of typed arrays are
+//! `null` and response codes are `{"code": "..."}`.
+
+use {
+    super::{
+        governor::ClientHandle,
+        listener::BaseListener,
+        screen_peer, set_keepalive,
+        stateless::{self, Element, LoginError},
+        NetBackoff,
+    },
+    crate::{auth::AuthProvider, dbnet::prelude::*, queryengine, IoResult},
+    bytes::{Buf, BytesMut},
+    core::str,
+    serde_json::{json, Value},
+    std::{
+        io::{Error as IoError, ErrorKind},
+        time::Duration,
+    },
+    tokio::{
+        net::TcpStream,
+        sync::{broadcast, mpsc},
+        time,
+    },
+};
+
+/// The largest request line and headers that we accept
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+/// The largest request body that we accept
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+/// Sent to clients that wait for us to accept the body (with `Expect: 100-continue`)
+const RESPONSE_CONTINUE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+const ROUTE_KEYS: &str = "/keys/";
+const ROUTE_QUERY: &str = "/query";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Status {
+    Ok,
+    NoContent,
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    LengthRequired,
+    PayloadTooLarge,
+    MisdirectedRequest,
+    TooManyRequests,
+    HeaderFieldsTooLarge,
+    InternalServerError,
+    ServiceUnavailable,
+    VersionNotSupported,
+}
+
+impl Status {
+    const fn code(&self) -> u16 {
+        match self {
+            Self::Ok => 200,
+            Self::NoContent => 204,
+            Self::BadRequest => 400,
+            Self::Unauthorized => 401,
+            Self::Forbidden => 403,
+            Self::NotFound => 404,
+            Self::MethodNotAllowed => 405,
+            Self::LengthRequired => 411,
+            Self::PayloadTooLarge => 413,
+            Self::MisdirectedRequest => 421,
+            Self::TooManyRequests => 429,
+            Self::HeaderFieldsTooLarge => 431,
+            Self::InternalServerError => 500,
+            Self::ServiceUnavailable => 503,
+            Self::VersionNotSupported => 505,
+        }
+    }
+    pub(super) const fn reason(&self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::NoContent => "No Content",
+            Self::BadRequest => "Bad Request",
+            Self::Unauthorized => "Unauthorized",
+            Self::Forbidden => "Forbidden",
+            Self::NotFound => "Not Found",
+            Self::MethodNotAllowed => "Method Not Allowed",
+            Self::LengthRequired => "Length Required",
+            Self::PayloadTooLarge => "Payload Too Large",
+            Self::MisdirectedRequest => "Misdirected Request",
+            Self::TooManyRequests => "Too Many Requests",
+            Self::HeaderFieldsTooLarge => "Request Header Fields Too Large",
+            Self::InternalServerError => "Internal Server Error",
+            Self::ServiceUnavailable => "Service Unavailable",
+            Self::VersionNotSupported => "HTTP Version Not Supported",
+        }
+    }
+    /// Returns the status for a response code that a query on a key failed with
+    fn of_code(code: &str) -> Self {
+        match code {
+            // nil
+            "1" => Self::NotFound,
+            "5" => Self::InternalServerError,
+            "10" => Self::Unauthorized,
+            "11" | "readonly-replica" | "not-lww" => Self::Forbidden,
+            "12" => Self::TooManyRequests,
+            "cluster-down" | "try-again" | "not-ready" | "lagging" => Self::ServiceUnavailable,
+            _ if code.starts_with("moved ") || code.starts_with("ask ") => Self::MisdirectedRequest,
+            _ => Self::BadRequest,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Method {
+    Get,
+    Put,
+    Delete,
+    Post,
+    Other,
+}
+
+impl Method {
+    fn of(method: &str) -> Self {
+        match method {
+            "GET" => Self::Get,
+            "PUT" => Self::Put,
+            "DELETE" => Self::Delete,
+            "POST" => Self::Post,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// An HTTP request
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Request {
+    pub(super) method: Method,
+    /// the path (without the query string)
+    pub(super) path: String,
+    /// the query string, if any
+    params: Option<String>,
+    /// the value of the `Authorization` header, if any
+    authorization: Option<String>,
+    /// whether the client wants to send more requests on this connection
+    keep_alive: bool,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Returns the (decoded) value of the query parameter `name`
+    fn param(&self, name: &str) -> Option<Vec<u8>> {
+        self.params
+            .as_deref()?
+            .split('&')
+            .filter_map(|param| param.split_once('='))
+            .find(|(key, _)| *key == name)
+            .and_then(|(_, value)| percent_decode(value))
+    }
+}
+
+/// The request line and headers of a request, that have to be read before the body
+#[derive(Debug, PartialEq, Eq)]
+struct Head {
+    request: Request,
+    content_length: usize,
+    expects_continue: bool,
+}
+
+/// Returns the length of the request line and headers (including the blank line that ends
+/// them), if all of them have been received
+fn head_length(buf: &[u8]) -> Option<usize> {
+    buf.windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|pos| pos + 4)
+}
+
+/// Parse the request line and headers of a request (without the blank line that ends them)
+fn parse_head(head: &[u8]) -> Result<Head, Status> {
+    let head = str::from_utf8(head).map_err(|_| Status::BadRequest)?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (method, target, version) = match (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) {
+        (Some(method), Some(target), Some(version), None) => (method, target, version),
+        _ => return Err(Status::BadRequest),
+    };
+    let mut keep_alive = match version {
+        "HTTP/1.1" => true,
+        "HTTP/1.0" => false,
+        _ => return Err(Status::VersionNotSupported),
+    };
+    let mut content_length = 0;
+    let mut authorization = None;
+    let mut expects_continue = false;
+    for line in lines {
+        let (name, value) = line.split_once(':').ok_or(Status::BadRequest)?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().map_err(|_| Status::BadRequest)?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            // we don't do chunked bodies
+            return Err(Status::LengthRequired);
+        } else if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.to_owned());
+        } else if name.eq_ignore_ascii_case("connection") {
+            if value.eq_ignore_ascii_case("close") {
+                keep_alive = false;
+            } else if value.eq_ignore_ascii_case("keep-alive") {
+                keep_alive = true;
+            }
+        } else if name.eq_ignore_ascii_case("expect") {
+            expects_continue = value.eq_ignore_ascii_case("100-continue");
+        }
+    }
+    let (path, params) = match target.split_once('?') {
+        Some((path, params)) => (path, Some(params.to_owned())),
+        None => (target, None),
+    };
+    Ok(Head {
+        request: Request {
+            method: Method::of(method),
+            path: path.to_owned(),
+            params,
+            authorization,
+            keep_alive,
+            body: Vec::new(),
+        },
+        content_length,
+        expects_continue,
+    })
+}
+
+/// Decode a percent-encoded path segment or query parameter
+fn percent_decode(input: &str) -> Option<Vec<u8>> {
+    let mut ret = Vec::with_capacity(input.len());
+    let mut bytes = input.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = str::from_utf8(&hex).ok()?;
+            ret.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            ret.push(byte);
+        }
+    }
+    Some(ret)
+}
+
+/// An HTTP response
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Response {
+    status: Status,
+    content_type: Option<&'static str>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub(super) fn json(status: Status, value: Value) -> Self {
+        Self {
+            status,
+            content_type: Some("application/json"),
+            body: value.to_string().into_bytes(),
+        }
+    }
+    pub(super) fn error(status: Status, error: impl AsRef<str>) -> Self {
+        Self::json(status, json!({ "error": error.as_ref() }))
+    }
+    /// A query on a key failed with the response code `code`
+    fn code(code: &str) -> Self {
+        Self::error(Status::of_code(code), code)
+    }
+    fn blob(body: Vec<u8>) -> Self {
+        Self {
+            status: Status::Ok,
+            content_type: Some("application/octet-stream"),
+            body,
+        }
+    }
+    fn empty(status: Status) -> Self {
+        Self {
+            status,
+            content_type: None,
+            body: Vec::new(),
+        }
+    }
+    fn unexpected() -> Self {
+        Self::error(Status::InternalServerError, "unexpected-response")
+    }
+    /// Encode the response, telling the client if we'll close the connection after sending it
+    fn encode(&self, keep_alive: bool) -> Vec<u8> {
+        let mut ret = format!(
+            "HTTP/1.1 {} {}\r\nContent-Length: {}\r\n",
+            self.status.code(),
+            self.status.reason(),
+            self.body.len()
+        );
+        if let Some(content_type) = self.content_type {
+            ret.push_str(&format!("Content-Type: {content_type}\r\n"));
+        }
+        if self.status == Status::Unauthorized {
+            ret.push_str("WWW-Authenticate: Bearer\r\n");
+        }
+        if !keep_alive {
+            ret.push_str("Connection: close\r\n");
+        }
+        ret.push_str("\r\n");
+        let mut ret = ret.into_bytes();
+        ret.extend_from_slice(&self.body);
+        ret
+    }
+}
+
+/// Parse the body of a `/query` request. This is either a query (an array of arguments, which
+/// are strings or numbers) or an array of queries. The returned flag is true for the latter
+fn parse_queries(body: &[u8]) -> Result<(Vec<Vec<Vec<u8>>>, bool), String> {
+    fn parse_query(query: &[Value]) -> Result<Vec<Vec<u8>>, String> {
+        let query = query
+            .iter()
+            .map(|arg| match arg {
+                Value::String(arg) => Ok(arg.clone().into_bytes()),
+                Value::Number(arg) => Ok(arg.to_string().into_bytes()),
+                _ => Err("arguments must be strings or numbers".to_owned()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match query.first() {
+            None => Err("empty query".to_owned()),
+            Some(action) if queryengine::is_connection_bound(action) => Err(format!(
+                "`{}` can't be run over HTTP",
+                String::from_utf8_lossy(action)
+            )),
+            Some(_) => Ok(query),
+        }
+    }
+    let body: Value = serde_json::from_slice(body).map_err(|e| format!("bad JSON: {e}"))?;
+    match body {
+        Value::Array(queries) if queries.iter().all(Value::is_array) && !queries.is_empty() => {
+            let queries = queries
+                .iter()
+                .map(|query| parse_query(query.as_array().unwrap()))
+                .collect::<Result<_, _>>()?;
+            Ok((queries, true))
+        }
+        Value::Array(query) => Ok((vec![parse_query(&query)?], false)),
+        _ => Err("expected a query or an array of queries".to_owned()),
+    }
+}
+
+/// Translate an element of a response to JSON
+fn to_json(element: Element) -> Value {
+    match element {
+        Element::Code(code) => json!({ "code": code }),
+        Element::String(string) => Value::String(string),
+        Element::Binary(binary) => match String::from_utf8(binary) {
+            Ok(string) => Value::String(string),
+            Err(e) => json!({ "base64": base64::encode(e.into_bytes()) }),
+        },
+        Element::Int(int) => Value::from(int),
+        Element::Float(float) => Value::from(float),
+        Element::Array(array) => Value::Array(array.into_iter().map(to_json).collect()),
+        Element::Null => Value::Null,
+    }
+}
+
+/// Result of [`HttpHandler::read_request`]
+pub(super) enum RequestResult {
+    Request(Request),
+    /// The request can't be served, and the client is disconnected after we tell it why
+    Malformed(Status),
+    /// The client disconnected
+    Disconnected,
+    /// The client didn't send the rest of a request in time
+    TimedOut,
+}
+
+/// A connection to a client of the HTTP gateway
+pub(super) struct HttpConnection {
+    stream: TcpStream,
+    buffer: BytesMut,
+}
+
+impl HttpConnection {
+    pub(super) fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            buffer: BytesMut::with_capacity(MAX_HEAD_SIZE),
+        }
+    }
+    /// Read a request. Once a part of a request has been received, the rest of it has to
+    /// arrive within `read_timeout`
+    pub(super) async fn read_request(
+        &mut self,
+        read_timeout: Option<Duration>,
+    ) -> IoResult<RequestResult> {
+        let mut sent_continue = false;
+        loop {
+            match head_length(&self.buffer) {
+                Some(len) => {
+                    let head = match parse_head(&self.buffer[..len - 4]) {
+                        Ok(head) => head,
+                        Err(status) => return Ok(RequestResult::Malformed(status)),
+                    };
+                    if head.content_length > MAX_BODY_SIZE {
+                        return Ok(RequestResult::Malformed(Status::PayloadTooLarge));
+                    }
+                    let total = len + head.content_length;
+                    if self.buffer.len() >= total {
+                        let mut request = head.request;
+                        request.body = self.buffer[len..total].to_vec();
+                        self.buffer.advance(total);
+                        return Ok(RequestResult::Request(request));
+                    }
+                    if head.expects_continue && !sent_continue {
+                        self.stream.write_all(RESPONSE_CONTINUE).await?;
+                        sent_continue = true;
+                    }
+                }
+                None if self.buffer.len() > MAX_HEAD_SIZE => {
+                    return Ok(RequestResult::Malformed(Status::HeaderFieldsTooLarge))
+                }
+                None => {}
+            }
+            let partial = !self.buffer.is_empty();
+            let read = self.stream.read_buf(&mut self.buffer);
+            let read = match read_timeout {
+                Some(timeout) if partial => match time::timeout(timeout, read).await {
+                    Ok(read) => read,
+                    Err(_) => return Ok(RequestResult::TimedOut),
+                },
+                _ => read.await,
+            };
+            if read? == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(RequestResult::Disconnected);
+                } else {
+                    return Err(IoError::from(ErrorKind::ConnectionReset));
+                }
+            }
+        }
+    }
+    /// Send a response, telling the client if we'll close the connection after sending it
+    pub(super) async fn write_response(
+        &mut self,
+        response: &Response,
+        keep_alive: bool,
+    ) -> IoResult<()> {
+        self.stream.write_all(&response.encode(keep_alive)).await
+    }
+}
+
+/// Serves the requests of a client of the HTTP gateway
+struct HttpHandler {
+    db: Corestore,
+    con: HttpConnection,
+    /// this client's slot with the connection governor (released on drop)
+    client: ClientHandle,
+    /// the auth provider that every request logs in with
+    auth: AuthProvider,
+    termination_signal: broadcast::Receiver<()>,
+    _term_sig_tx: mpsc::Sender<()>,
+}
+
+impl HttpHandler {
+    fn new(
+        db: Corestore,
+        stream: TcpStream,
+        auth: AuthProvider,
+        client: ClientHandle,
+        termination_signal: broadcast::Receiver<()>,
+        _term_sig_tx: mpsc::Sender<()>,
+    ) -> Self {
+        db.get_stats().client_connected();
+        Self {
+            db,
+            con: HttpConnection::new(stream),
+            client,
+            auth,
+            termination_signal,
+            _term_sig_tx,
+        }
+    }
+    async fn run(&mut self) -> IoResult<()> {
+        let read_timeout = self.client.read_timeout();
+        loop {
+            let request = tokio::select! {
+                request = self.con.read_request(read_timeout) => request?,
+                _ = self.client.reaped() => {
+                    log::debug!("Disconnecting idle client {}", self.client.id());
+                    return Ok(());
+                }
+                _ = self.termination_signal.recv() => {
+                    return Ok(());
+                }
+            };
+            let request = match request {
+                RequestResult::Request(request) => request,
+                RequestResult::Malformed(status) => {
+                    let response = Response::error(status, status.reason());
+                    return self.con.write_response(&response, false).await;
+                }
+                RequestResult::Disconnected => return Ok(()),
+                RequestResult::TimedOut => {
+                    log::debug!(
+                        "Client {} timed out while sending a request",
+                        self.client.id()
+                    );
+                    return Ok(());
+                }
+            };
+            self.client.touch();
+            let keep_alive = request.keep_alive;
+            let response = self.respond(request).await?;
+            self.con.write_response(&response, keep_alive).await?;
+            if !keep_alive {
+                return Ok(());
+            }
+        }
+    }
+    /// Serve a request
+    async fn respond(&mut self, request: Request) -> IoResult<Response> {
+        let key = match request.path.strip_prefix(ROUTE_KEYS) {
+            Some(key) => match percent_decode(key) {
+                Some(key) if !key.is_empty() => Some(key),
+                _ => return Ok(Response::error(Status::BadRequest, "bad key")),
+            },
+            None if request.path == ROUTE_QUERY => None,
+            None => return Ok(Response::error(Status::NotFound, "unknown route")),
+        };
+        let allowed = match key {
+            Some(_) => matches!(request.method, Method::Get | Method::Put | Method::Delete),
+            None => request.method == Method::Post,
+        };
+        if !allowed {
+            return Ok(Response::error(
+                Status::MethodNotAllowed,
+                "method not allowed",
+            ));
+        }
+        // every request is a session of its own, from the connection's client
+        let mut auth = AuthProviderHandle::new(self.auth.for_client(self.auth.peer()));
+        match stateless::login_bearer(&mut auth, request.authorization.as_deref()).await {
+            Ok(()) => {}
+            Err(LoginError::BadCredentials) => {
+                return Ok(Response::error(Status::Unauthorized, "10"))
+            }
+            Err(LoginError::RateLimited) => {
+                return Ok(Response::error(Status::TooManyRequests, "12"))
+            }
+        }
+        let mut db = self.db.clone();
+        if let Some(entity) = request.param("entity") {
+            let mut query = b"USE ".to_vec();
+            query.extend(entity);
+            match self.run_query(&mut db, &mut auth, &[query]).await? {
+                Element::Code(code) if code == "0" => {}
+                Element::Code(code) => return Ok(Response::code(&code)),
+                _ => return Ok(Response::unexpected()),
+            }
+        }
+        match key {
+            Some(key) => self.respond_key(&mut db, &mut auth, request, key).await,
+            None => self.respond_query(&mut db, &mut auth, &request.body).await,
+        }
+    }
+    /// Serve a request on `/keys/{key}`
+    async fn respond_key(
+        &mut self,
+        db: &mut Corestore,
+        auth: &mut AuthProviderHandle,
+        request: Request,
+        key: Vec<u8>,
+    ) -> IoResult<Response> {
+        let response = match request.method {
+            Method::Get => match self.run_query(db, auth, &[b"GET".to_vec(), key]).await? {
+                Element::Binary(value) => Response::blob(value),
+                Element::String(value) => Response::blob(value.into_bytes()),
+                Element::Code(code) => Response::code(&code),
+                _ => Response::unexpected(),
+            },
+            Method::Put => {
+                let query = [b"USET".to_vec(), key, request.body];
+                match self.run_query(db, auth, &query).await? {
+                    Element::Int(_) => Response::empty(Status::NoContent),
+                    Element::Code(code) => Response::code(&code),
+                    _ => Response::unexpected(),
+                }
+            }
+            _ => match self.run_query(db, auth, &[b"DEL".to_vec(), key]).await? {
+                Element::Int(0) => Response::error(Status::NotFound, "1"),
+                Element::Int(_) => Response::empty(Status::NoContent),
+                Element::Code(code) => Response::code(&code),
+                _ => Response::unexpected(),
+            },
+        };
+        Ok(response)
+    }
+    /// Serve a request on `/query`
+    async fn respond_query(
+        &mut self,
+        db: &mut Corestore,
+        auth: &mut AuthProviderHandle,
+        body: &[u8],
+    ) -> IoResult<Response> {
+        let (queries, is_batch) = match parse_queries(body) {
+            Ok(queries) => queries,
+            Err(e) => return Ok(Response::error(Status::BadRequest, e)),
+        };
+        let mut results = Vec::with_capacity(queries.len());
+        for query in queries.iter() {
+            results.push(to_json(self.run_query(db, auth, query).await?));
+        }
+        let result = if is_batch {
+            Value::Array(results)
+        } else {
+            results.pop().unwrap_or_default()
+        };
+        Ok(Response::json(Status::Ok, result))
+    }
+    async fn run_query(
+        &mut self,
+        db: &mut Corestore,
+        auth: &mut AuthProviderHandle,
+        query: &[Vec<u8>],
+    ) -> IoResult<Element> {
+        stateless::run_query(db, auth, self.client.id(), query).await
+    }
+}
+
+impl Drop for HttpHandler {
+    fn drop(&mut self) {
+        self.db.get_stats().client_disconnected();
+    }
+}
+
+/// Tell a HTTP client that was turned away by the governor why, and then disconnect it
+async fn reject_client(mut stream: TcpStream) {
+    let response = Response::error(Status::ServiceUnavailable, "too-many-connections");
+    let _ = stream.write_all(&response.encode(false)).await;
+}
+
+/// The listener for the HTTP gateway
+pub struct HttpListener {
+    pub base: BaseListener,
+}
+
+impl HttpListener {
+    pub fn new(base: BaseListener) -> Self {
+        Self { base }
+    }
+    /// Accept an incoming connection
+    async fn accept(&mut self) -> IoResult<TcpStream> {
+        let backoff = NetBackoff::new();
+        loop {
+            match self.base.listener.accept().await {
+                Ok((stream, addr)) => {
+                    if screen_peer(&self.base.db, addr.ip()) {
+                        set_keepalive(&stream, self.base.options.keepalive);
+                        return Ok(stream);
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    if backoff.should_disconnect() {
+                        return Err(e);
+                    }
+                }
+            }
+            backoff.spin().await;
+        }
+    }
+    /// Run the gateway
+    pub async fn run(&mut self) -> IoResult<()> {
+        loop {
+            let stream = skip_loop_err!(self.accept().await);
+            let client = match self.base.governor.try_admit() {
+                Some(client) => client,
+                None => {
+                    self.base.db.get_stats().client_rejected();
+                    tokio::spawn(reject_client(stream));
+                    continue;
+                }
+            };
+            let peer = stream.peer_addr().ok().map(|addr| addr.ip());
+            let mut handler = HttpHandler::new(
+                self.base.db.clone(),
+                stream,
+                self.base.auth.for_client(peer),
+                client,
+                self.base.signal.subscribe(),
+                self.base.terminate_tx.clone(),
+            );
+            tokio::spawn(async move {
+                if let Err(e) = handler.run().await {
+                    log::error!("Error: {}", e);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_head_keep_alive() {
+        let head = b"GET /keys/x?entity=default.default HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer root:abcd";
+        let head = parse_head(head).unwrap();
+        assert_eq!(head.content_length, 0);
+        assert!(!head.expects_continue);
+        assert_eq!(head.request.method, Method::Get);
+        assert_eq!(head.request.path, "/keys/x");
+        assert_eq!(head.request.param("entity").unwrap(), b"default.default");
+        assert_eq!(
+            head.request.authorization.as_deref(),
+            Some("Bearer root:abcd")
+        );
+        assert!(head.request.keep_alive);
+    }
+
+    #[test]
+    fn parse_head_with_body() {
+        let head = b"PUT /keys/x HTTP/1.0\r\ncontent-length: 5\r\nExpect: 100-continue";
+        let head = parse_head(head).unwrap();
+        assert_eq!(head.content_length, 5);
+        assert!(head.expects_continue);
+        assert_eq!(head.request.method, Method::Put);
+        assert!(!head.request.keep_alive);
+    }
+
+    #[test]
+    fn parse_head_bad() {
+        assert_eq!(parse_head(b"GET /").unwrap_err(), Status::BadRequest);
+        assert_eq!(
+            parse_head(b"GET / HTTP/2.0").unwrap_err(),
+            Status::VersionNotSupported
+        );
+        assert_eq!(
+            parse_head(b"POST /query HTTP/1.1\r\nTransfer-Encoding: chunked").unwrap_err(),
+            Status::LengthRequired
+        );
+        assert_eq!(
+            parse_head(b"GET / HTTP/1.1\r\nContent-Length: x").unwrap_err(),
+            Status::BadRequest
+        );
+    }
+
+    #[test]
+    fn head_length_needs_blank_line() {
+        assert_eq!(head_length(b"GET / HTTP/1.1\r\n"), None);
+        assert_eq!(head_length(b"GET / HTTP/1.1\r\n\r\nbody"), Some(18));
+    }
+
+    #[test]
+    fn percent_decode_keys() {
+        assert_eq!(percent_decode("a%20b%2Fc").unwrap(), b"a b/c");
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%2"), None);
+    }
+
+    #[test]
+    fn parse_query_or_batch() {
+        let (queries, is_batch) = parse_queries(br#"["INCRBY", "x", 10]"#).unwrap();
+        assert!(!is_batch);
+        assert_eq!(
+            queries,
+            vec![vec![b"INCRBY".to_vec(), b"x".to_vec(), b"10".to_vec()]]
+        );
+        let (queries, is_batch) = parse_queries(br#"[["USE", "a.b"], ["GET", "x"]]"#).unwrap();
+        assert!(is_batch);
+        assert_eq!(queries.len(), 2);
+    }
+
+    #[test]
+    fn parse_query_bad() {
+        assert!(parse_queries(b"[]").is_err());
+        assert!(parse_queries(br#"{"GET": "x"}"#).is_err());
+        assert!(parse_queries(br#"["GET", null]"#).is_err());
+        assert_eq!(
+            parse_queries(br#"["multi"]"#).unwrap_err(),
+            "`multi` can't be run over HTTP"
+        );
+    }
+
+    #[test]
+    fn element_json() {
+        let element = Element::Array(vec![
+            Element::Code("1".to_owned()),
+            Element::Binary(vec![0xff]),
+            Element::Binary(b"ok".to_vec()),
+            Element::Float(1.5),
+            Element::Null,
+        ]);
+        assert_eq!(
+            to_json(element),
+            json!([{ "code": "1" }, { "base64": "/w==" }, "ok", 1.5, null])
+        );
+    }
+
+    #[test]
+    fn encode_response() {
+        let response = Response::error(Status::Unauthorized, "10");
+        assert_eq!(
+            response.encode(false),
+            b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 14\r\nContent-Type: application/json\r\nWWW-Authenticate: Bearer\r\nConnection: close\r\n\r\n{\"error\":\"10\"}"
+        );
+        assert_eq!(
+            Response::empty(Status::NoContent).encode(true),
+            b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn code_status() {
+        assert_eq!(Status::of_code("1"), Status::NotFound);
+        assert_eq!(Status::of_code("11"), Status::Forbidden);
+        assert_eq!(
+            Status::of_code("moved 3 127.0.0.1:2003"),
+            Status::MisdirectedRequest
+        );
+        assert_eq!(Status::of_code("wrong-model"), Status::BadRequest);
+    }
+}
@@ -26,8 +26,11 @@
 
 use {
     super::{
+        governor::Governor,
+        http::HttpListener,
         tcp::{Listener, ListenerV1},
         tls::{SslListener, SslListenerV1},
+        ConnectionOptions,
     },
     crate::{
         auth::AuthProvider,
@@ -40,20 +43,22 @@ use {
     std::{net::IpAddr, sync::Arc},
     tokio::{
         net::TcpListener,
-        sync::{broadcast, mpsc, Semaphore},
+        sync::{broadcast, mpsc},
     },
 };
 
-/// The base TCP listener
-pub struct BaseListener {
+/// The base listener, generic over the socket that it binds to (TCP by default)
+pub struct BaseListener<L = TcpListener> {
     /// An atomic reference to the coretable
     pub db: Corestore,
     /// The auth provider
     pub auth: AuthProvider,
     /// The incoming connection listener (binding)
-    pub listener: TcpListener,
-    /// The maximum number of connections
-    pub climit: Arc<Semaphore>,
+    pub listener: L,
+    /// The connection governor
+    pub governor: Arc<Governor>,
+    /// The settings for the client connections
+    pub options: ConnectionOptions,
     /// The shutdown broadcaster
     pub signal: broadcast::Sender<()>,
     // When all `Sender`s are dropped - the `Receiver` gets a `None` value
@@ -68,7 +73,8 @@ impl BaseListener {
         auth: AuthProvider,
         host: IpAddr,
         port: u16,
-        semaphore: Arc<Semaphore>,
+        governor: Arc<Governor>,
+        options: ConnectionOptions,
         signal: broadcast::Sender<()>,
     ) -> SkyResult<Self> {
         let (terminate_tx, terminate_rx) = mpsc::channel(1);
@@ -79,12 +85,16 @@ impl BaseListener {
             db: db.clone(),
             auth,
             listener,
-            climit: semaphore,
+            governor,
+            options,
             signal,
             terminate_tx,
             terminate_rx,
         })
     }
+}
+
+impl<L> BaseListener<L> {
     pub async fn release_self(self) {
         let Self {
             mut terminate_rx,
@@ -153,6 +163,7 @@ impl MultiListener {
                     ssl.chain,
                     base,
                     ssl.passfile,
+                    ssl.clientca,
                 )?;
                 MultiListener::SecureOnly(listener)
             }
@@ -162,6 +173,7 @@ impl MultiListener {
                     ssl.chain,
                     base,
                     ssl.passfile,
+                    ssl.clientca,
                 )?;
                 MultiListener::SecureOnlyV1(listener)
             }
@@ -182,6 +194,7 @@ impl MultiListener {
                     ssl.chain,
                     ssl_base_listener,
                     ssl.passfile,
+                    ssl.clientca,
                 )?;
                 let insecure_listener = Listener::new(tcp_base_listener);
                 MultiListener::Multi(insecure_listener, secure_listener)
@@ -192,6 +205,7 @@ impl MultiListener {
                     ssl.chain,
                     ssl_base_listener,
                     ssl.passfile,
+                    ssl.clientca,
                 )?;
                 let insecure_listener = ListenerV1::new(tcp_base_listener);
                 MultiListener::MultiV1(insecure_listener, secure_listener)
@@ -239,27 +253,117 @@ impl MultiListener {
     }
 }
 
+#[cfg(unix)]
+/// A listener on a Unix domain socket
+pub enum LocalListener {
+    V2(super::unix::LocalListener),
+    V1(super::unix::LocalListenerV1),
+}
+
+#[cfg(unix)]
+impl LocalListener {
+    async fn run_server(&mut self) -> IoResult<()> {
+        match self {
+            Self::V2(listener) => listener.run().await,
+            Self::V1(listener) => listener.run().await,
+        }
+    }
+    async fn finish_with_termsig(self) {
+        match self {
+            Self::V2(listener) => listener.release_self().await,
+            Self::V1(listener) => listener.release_self().await,
+        }
+    }
+}
+
+/// The network listeners along with the Unix domain socket listener, the HTTP gateway and
+/// the gRPC service, if they were configured
+pub struct Listeners {
+    net: MultiListener,
+    #[cfg(unix)]
+    local: Option<LocalListener>,
+    http: Option<HttpListener>,
+    #[cfg(feature = "grpc")]
+    grpc: Option<super::grpc::GrpcListener>,
+}
+
+impl Listeners {
+    /// Start the server on all the listeners
+    pub async fn run_server(&mut self) -> IoResult<()> {
+        let Self {
+            net,
+            #[cfg(unix)]
+            local,
+            http,
+            ..
+        } = self;
+        #[cfg(unix)]
+        let local = async {
+            if let Some(local) = local.as_mut() {
+                if let Err(e) = local.run_server().await {
+                    log::error!("Unix socket listener failed with: {}", e);
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let local = async {};
+        let http = async {
+            if let Some(http) = http.as_mut() {
+                if let Err(e) = http.run().await {
+                    log::error!("HTTP gateway failed with: {}", e);
+                }
+            }
+        };
+        let (ret, _, _) = tokio::join!(net.run_server(), local, http);
+        ret
+    }
+    /// Signal all the listeners to shut down and only return after they have shut down
+    ///
+    /// See [`MultiListener::finish_with_termsig`]
+    pub async fn finish_with_termsig(self) {
+        self.net.finish_with_termsig().await;
+        #[cfg(unix)]
+        if let Some(local) = self.local {
+            local.finish_with_termsig().await;
+        }
+        if let Some(http) = self.http {
+            http.base.release_self().await;
+        }
+        // the service only shuts down once every listener has dropped its shutdown signal
+        #[cfg(feature = "grpc")]
+        if let Some(grpc) = self.grpc {
+            grpc.release_self().await;
+        }
+    }
+}
+
 /// Initialize the database networking
+#[allow(clippy::too_many_arguments)]
 pub async fn connect(
     ports: PortConfig,
+    unixsocket: Option<String>,
+    httpport: Option<u16>,
+    grpcport: Option<u16>,
     protocol: ProtocolVersion,
-    maxcon: usize,
+    options: ConnectionOptions,
+    governor: Arc<Governor>,
     db: Corestore,
     auth: AuthProvider,
     signal: broadcast::Sender<()>,
-) -> SkyResult<MultiListener> {
-    let climit = Arc::new(Semaphore::new(maxcon));
+) -> SkyResult<Listeners> {
     let base_listener_init = |host, port| {
         BaseListener::init(
             &db,
             auth.clone(),
             host,
             port,
-            climit.clone(),
+            governor.clone(),
+            options,
             signal.clone(),
         )
     };
     let description = ports.get_description();
+    let host = ports.get_host();
     let server = match ports {
         PortConfig::InsecureOnly { host, port } => {
             MultiListener::new_insecure_only(base_listener_init(host, port).await?, protocol)
@@ -276,5 +380,66 @@ pub async fn connect(
         }
     };
     log::info!("Server started on {description}");
-    Ok(server)
+    let http = match httpport {
+        Some(port) => {
+            let base = base_listener_init(host, port).await?;
+            log::info!("HTTP gateway started on http://{host}:{port}");
+            Some(HttpListener::new(base))
+        }
+        None => None,
+    };
+    #[cfg(feature = "grpc")]
+    let grpc = match grpcport {
+        Some(port) => {
+            let grpc = super::grpc::GrpcListener::init(
+                &db,
+                auth.clone(),
+                host,
+                port,
+                governor.clone(),
+                options.keepalive,
+                &signal,
+            )
+            .await?;
+            log::info!("gRPC service started on {host}:{port}");
+            Some(grpc)
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "grpc"))]
+    if grpcport.is_some() {
+        log::warn!("This build doesn't support gRPC. Ignoring `server.grpcport`");
+    }
+    #[cfg(unix)]
+    let local = match unixsocket {
+        Some(path) => {
+            let path = std::path::PathBuf::from(path);
+            let base =
+                BaseListener::init_unix(&db, auth.clone(), &path, governor, options, signal)?;
+            log::info!("Listening for local clients on {}", path.display());
+            Some(match protocol {
+                ProtocolVersion::V2 => {
+                    LocalListener::V2(super::unix::LocalListener::new(base, path))
+                }
+                ProtocolVersion::V1 => {
+                    LocalListener::V1(super::unix::LocalListenerV1::new(base, path))
+                }
+            })
+        }
+        None => None,
+    };
+    #[cfg(not(unix))]
+    if unixsocket.is_some() {
+        log::warn!(
+            "Unix domain sockets are not supported on this platform. Ignoring `server.unixsocket`"
+        );
+    }
+    Ok(Listeners {
+        net: server,
+        #[cfg(unix)]
+        local,
+        http,
+        #[cfg(feature = "grpc")]
+        grpc,
+    })
 }
@@ -0,0 +1,148 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Loopback clients
+//!
+//! The subsystems of the server (like the replication applier, the append-only log's replay
+//! and slot migrations) run their queries through a [`Loopback`] instead of working with the
+//! tables directly. This way the queries go through the same path as the queries of network
+//! clients (see [`queryengine::execute_internal`] for what is and isn't shared), so they are
+//! validated the same way, recorded in the append-only log, sent to the replicas and show up
+//! in the server statistics. They also show up in `MONITOR`, as the queries of the client
+//! with ID [`LOOPBACK_CLIENT`]
+
+pub use super::stateless::Element;
+use {
+    super::{stateless::BufferStream, AuthProviderHandle, Connection},
+    crate::{
+        actions::ActionError,
+        auth::AuthProvider,
+        blueql::Entity,
+        corestore::{memstore::DdlError, Corestore},
+        protocol::{Skyhash2, UnsafeSlice},
+        queryengine, IoResult,
+    },
+    std::io::{Error as IoError, ErrorKind},
+};
+
+/// The client ID of the server's own queries (the IDs of network clients start at 1)
+pub const LOOPBACK_CLIENT: u64 = 0;
+
+/// A client that lives inside the server. It has its own current entity (just like a
+/// connection) and every privilege
+pub struct Loopback {
+    db: Corestore,
+    con: Connection<BufferStream, Skyhash2>,
+    auth: AuthProviderHandle,
+}
+
+impl Loopback {
+    pub fn new(handle: &Corestore) -> Self {
+        Self {
+            db: handle.clone(),
+            con: Connection::new(BufferStream::default()),
+            auth: AuthProviderHandle::new(AuthProvider::new_disabled()),
+        }
+    }
+    /// Switch to another entity (this is the same as running `USE`, but with an entity that
+    /// we already have at hand)
+    pub fn swap_entity(&mut self, entity: &Entity) -> Result<(), DdlError> {
+        self.db.swap_entity(entity)
+    }
+    /// Run a query on the current entity, returning its response. Action errors (like a wrong
+    /// number of arguments) are returned as responses, just like they are to network clients
+    pub async fn run(&mut self, query: &[&[u8]]) -> IoResult<Element> {
+        let query: Vec<UnsafeSlice> = query
+            .iter()
+            .map(|arg| UnsafeSlice::new(arg.as_ptr(), arg.len()))
+            .collect();
+        self.db.get_monitor().publish(LOOPBACK_CLIENT, &query);
+        match queryengine::execute_internal(&mut self.db, &mut self.con, &mut self.auth, &query)
+            .await
+        {
+            Ok(()) => {}
+            Err(ActionError::ActionError(e)) => self.con._write_raw(e).await?,
+            Err(ActionError::IoError(ioe)) => return Err(ioe),
+        }
+        self.con.flush().await?;
+        let response = self.con.stream.get_mut().take();
+        Element::decode(&response).ok_or_else(|| {
+            IoError::new(
+                ErrorKind::InvalidData,
+                "the query engine wrote a malformed response",
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Element, Loopback},
+        crate::{
+            corestore::{memstore::Memstore, Corestore},
+            protocol::{interface::ProtocolSpec, Skyhash2},
+            storage::v1::sengine::SnapshotEngine,
+        },
+        std::sync::Arc,
+    };
+
+    fn code(code: &[u8]) -> Element {
+        Element::decode(code).unwrap()
+    }
+
+    #[tokio::test]
+    async fn loopback_runs_queries() {
+        let handle = Corestore::default_with_store(
+            Memstore::new_default(),
+            Arc::new(SnapshotEngine::new_disabled()),
+        );
+        let mut loopback = Loopback::new(&handle);
+        assert_eq!(
+            loopback.run(&[b"SET", b"x", b"100"]).await.unwrap(),
+            code(Skyhash2::RCODE_OKAY)
+        );
+        assert_eq!(
+            loopback.run(&[b"GET", b"x"]).await.unwrap(),
+            Element::Binary(b"100".to_vec())
+        );
+        assert_eq!(
+            loopback.run(&[b"GET", b"y"]).await.unwrap(),
+            code(Skyhash2::RCODE_NIL)
+        );
+        // validated just like a client's query
+        assert_eq!(
+            loopback.run(&[b"GET"]).await.unwrap(),
+            code(Skyhash2::RCODE_ACTION_ERR)
+        );
+        // the connection-bound actions aren't run here
+        assert!(matches!(
+            loopback.run(&[b"MULTI"]).await.unwrap(),
+            Element::Code(e) if e != "0"
+        ));
+        assert_eq!(handle.get_stats().commands(), 5);
+    }
+}
@@ -25,23 +25,38 @@
 */
 
 use {
-    self::connection::Connection,
+    self::{
+        compression::{Codec, CompressedStream},
+        connection::Connection,
+        governor::ClientHandle,
+        handshake::Negotiated,
+    },
     crate::{
         actions::{ActionError, ActionResult},
         auth::AuthProvider,
+        config::{IpVerdict, ProtocolVersion},
         corestore::Corestore,
-        protocol::{interface::ProtocolSpec, Query},
+        kvengine::notify::KeyChange,
+        protocol::{interface::ProtocolSpec, Query, QueryLimits, Skyhash1, Skyhash2},
+        queryengine::monitor::MonitoredQuery,
+        replication::{self, FeedEvent},
         util::compiler,
         IoResult,
     },
     bytes::Buf,
-    std::{cell::Cell, sync::Arc, time::Duration},
+    socket2::{SockRef, TcpKeepalive},
+    std::{
+        cell::Cell,
+        io::{Error as IoError, ErrorKind},
+        net::IpAddr,
+        sync::Arc,
+        time::Duration,
+    },
     tokio::{
         io::{AsyncReadExt, AsyncWriteExt},
         sync::{
             broadcast::{self},
             mpsc::{self},
-            Semaphore,
         },
         time,
     },
@@ -49,17 +64,39 @@ use {
 
 pub type QueryWithAdvance = (Query, usize);
 pub const MAXIMUM_CONNECTION_LIMIT: usize = 50000;
+/// The default size from which responses are compressed (for clients that asked for it)
+pub const DEFAULT_COMPRESSMIN: usize = 4096;
+/// The default number of bytes of a response that are written before they're flushed
+pub const DEFAULT_MAXINFLIGHT: usize = 1024 * 1024;
+/// The default number of bytes of pushed frames that can be queued for a client
+pub const DEFAULT_MAXOUTBUF: usize = 32 * 1024 * 1024;
+/// The default number of seconds that a client socket can stay silent before TCP keepalive
+/// probes are sent
+pub const DEFAULT_KEEPALIVE: u64 = 300;
 use crate::queryengine;
 
-pub use self::listener::connect;
+pub use self::{listener::connect, stateless::BufferStream, status::StatusListener};
 
+pub mod compression;
 mod connection;
+pub mod governor;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod handshake;
 #[macro_use]
 mod macros;
+mod http;
 mod listener;
+pub mod loopback;
 pub mod prelude;
+mod stateless;
+mod status;
 mod tcp;
 mod tls;
+#[cfg(unix)]
+mod unix;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
 
 /// This is a "marker trait" that ensures that no silly types are
 /// passed into the [`Connection`] type
@@ -73,6 +110,23 @@ enum QueryResult {
     NextLoop,
     /// The client disconnected
     Disconnected,
+    /// The client didn't send the rest of a query in time
+    TimedOut,
+}
+
+/// The settings that apply to every client connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionOptions {
+    /// the size from which responses are compressed (zero disables compression)
+    pub compressmin: usize,
+    /// the number of bytes of a response that are written before they're flushed
+    pub maxinflight: usize,
+    /// the number of bytes of pushed frames that can be queued for a client
+    pub maxoutbuf: usize,
+    /// the seconds of silence after which TCP keepalive probes are sent (zero disables them)
+    pub keepalive: u64,
+    /// the caps on the queries that clients can send
+    pub limits: QueryLimits,
 }
 
 /// A backoff implementation that is meant to be used in connection loops
@@ -107,7 +161,8 @@ pub struct AuthProviderHandle {
 
 impl AuthProviderHandle {
     pub fn new(provider: AuthProvider) -> Self {
-        let auth_good = !provider.is_enabled();
+        // the client might have been logged in already (by its certificate)
+        let auth_good = !provider.is_enabled() || provider.current_user().is_some();
         Self {
             provider,
             auth_good,
@@ -141,10 +196,12 @@ pub struct ConnectionHandler<C, P> {
     db: Corestore,
     /// the connection
     con: Connection<C, P>,
-    /// the semaphore used to impose limits on number of connections
-    climit: Arc<Semaphore>,
+    /// this client's slot with the connection governor (released on drop)
+    client: ClientHandle,
     /// the authentication handle
     auth: AuthProviderHandle,
+    /// the query engine's state for the connection
+    state: queryengine::ConnectionState,
     /// check for termination signals
     termination_signal: broadcast::Receiver<()>,
     /// the sender that we drop when we're done with handling a connection (used for gracefule exit)
@@ -161,29 +218,51 @@ where
         db: Corestore,
         con: Connection<C, P>,
         auth_data: AuthProvider,
-        climit: Arc<Semaphore>,
+        client: ClientHandle,
         termination_signal: broadcast::Receiver<()>,
         _term_sig_tx: mpsc::Sender<()>,
     ) -> Self {
+        db.get_stats().client_connected();
         Self {
             db,
             con,
-            climit,
+            client,
             auth: AuthProviderHandle::new(auth_data),
+            state: queryengine::ConnectionState::new(),
             termination_signal,
             _term_sig_tx,
         }
     }
     pub async fn run(&mut self) -> IoResult<()> {
+        let read_timeout = self.client.read_timeout();
         loop {
             let packet = tokio::select! {
-                pkt = self.con.read_query() => pkt,
+                pkt = self.con.read_query(read_timeout) => pkt,
+                change = self.state.next_key_change() => {
+                    self.push_key_change(change);
+                    if self.is_backlogged() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                query = self.state.next_monitored_query() => {
+                    self.push_monitored_query(query);
+                    if self.is_backlogged() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                _ = self.client.reaped() => {
+                    log::debug!("Disconnecting idle client {}", self.client.id());
+                    return Ok(());
+                }
                 _ = self.termination_signal.recv() => {
                     return Ok(());
                 }
             };
             match packet {
                 Ok(QueryResult::Q((query, advance))) => {
+                    self.client.touch();
                     // the mutable reference to self ensures that the buffer is not modified
                     // hence ensuring that the pointers will remain valid
                     #[cfg(debug_assertions)]
@@ -222,20 +301,152 @@ where
                         // at this point, it's totally fine (so invalidating ptrs is totally cool)
                         self.con.buffer.advance(advance);
                     }
+                    if self.state.is_replica() {
+                        // the connection just synced, so from here on it only streams writes
+                        return self.stream_to_replica().await;
+                    }
                 }
                 Ok(QueryResult::Disconnected) => return Ok(()),
+                Ok(QueryResult::TimedOut) => {
+                    log::debug!(
+                        "Client {} timed out while sending a query",
+                        self.client.id()
+                    );
+                    return Ok(());
+                }
                 Ok(QueryResult::NextLoop) => {}
                 Err(e) => return Err(e),
             }
         }
     }
+    /// Returns true (after logging it) if the client let too many pushed frames pile up, in
+    /// which case it should be disconnected
+    fn is_backlogged(&self) -> bool {
+        let backlogged = self.con.outbuf_exceeded();
+        if backlogged {
+            log::warn!(
+                "Disconnecting client {} since it isn't reading what is pushed to it",
+                self.client.id()
+            );
+        }
+        backlogged
+    }
+    /// Push a change to a watched key (or the number of changes that we missed) to the client
+    fn push_key_change(&mut self, change: Result<KeyChange, u64>) {
+        let frame = self.con.push_frame().array_header(2);
+        match change {
+            Ok(change) => frame.string(change.event.as_str()).binary(&change.key),
+            Err(missed) => frame.string("lagged").int64(missed),
+        };
+    }
+    /// Push a query that was run by some client to the monitoring client (or the number of
+    /// queries that we missed)
+    fn push_monitored_query(&mut self, query: Result<Arc<MonitoredQuery>, u64>) {
+        let frame = self.con.push_frame();
+        match query {
+            Ok(query) => {
+                let mut frame = frame
+                    .array_header(query.args.len() + 2)
+                    .int64(query.timestamp)
+                    .int64(query.client);
+                for arg in query.args.iter() {
+                    frame = frame.binary(arg);
+                }
+            }
+            Err(missed) => {
+                frame.array_header(2).string("lagged").int64(missed);
+            }
+        }
+    }
+    /// Stream the writes to a replica that synced on this connection, until it falls behind or
+    /// disconnects. The replica sends nothing but acknowledgements from here on
+    async fn stream_to_replica(&mut self) -> IoResult<()> {
+        loop {
+            tokio::select! {
+                write = self.state.next_replicated_write() => {
+                    if !self.write_replicated_write(write).await? {
+                        // the replica fell behind and has to sync again
+                        return Ok(());
+                    }
+                }
+                ack = self.con.read_fixed_frame(replication::ACK_FRAME_SIZE) => {
+                    let offset = match ack? {
+                        Some(frame) => replication::decode_ack(&frame),
+                        None => return Ok(()),
+                    };
+                    match offset {
+                        Some(offset) => {
+                            self.state.ack_replicated(offset);
+                            self.client.touch();
+                        }
+                        None => return Err(IoError::from(ErrorKind::InvalidData)),
+                    }
+                }
+                _ = self.client.reaped() => {
+                    // a replica that acknowledges what we send it isn't idle
+                    self.client.touch();
+                }
+                _ = self.termination_signal.recv() => {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    /// Stream a write (or heartbeat) to the replica. If the replica fell behind, it is told so
+    /// and false is returned (the replica has to sync again, on a new connection)
+    async fn write_replicated_write(&mut self, write: Result<FeedEvent, u64>) -> IoResult<bool> {
+        self.con.write_outbuf().await?;
+        let caught_up = match write {
+            Ok(FeedEvent::Write(record)) => {
+                self.con
+                    ._write_raw(&replication::write_frame_header(record.len()))
+                    .await?;
+                self.con._write_raw(&record).await?;
+                true
+            }
+            Ok(FeedEvent::Heartbeat(at)) => {
+                self.con
+                    ._write_raw(&replication::heartbeat_frame(at))
+                    .await?;
+                true
+            }
+            Err(missed) => {
+                log::warn!(
+                    "Replica {} fell behind by {} writes. Disconnecting",
+                    self.client.id(),
+                    missed
+                );
+                self.con._write_raw(&replication::lagged_frame()).await?;
+                false
+            }
+        };
+        self.con.flush().await?;
+        Ok(caught_up)
+    }
     async fn execute_query(&mut self, query: Query) -> ActionResult<()> {
-        let Self { db, con, auth, .. } = self;
+        let Self {
+            db,
+            con,
+            auth,
+            state,
+            client,
+            ..
+        } = self;
+        match query {
+            Query::Simple(ref q) => db.get_monitor().publish(client.id(), q.as_slice()),
+            Query::Pipelined(ref p) => {
+                if db.get_monitor().is_active() {
+                    for stage in p.stages() {
+                        db.get_monitor().publish(client.id(), stage);
+                    }
+                }
+            }
+        }
         match query {
             Query::Simple(q) => {
                 con.write_simple_query_header().await?;
                 if compiler::likely(auth.authenticated()) {
-                    queryengine::execute_simple(db, con, auth, q).await?;
+                    queryengine::execute_simple(db, con, auth, state, q).await?;
                 } else {
                     queryengine::execute_simple_noauth(db, con, auth, q).await?;
                 }
@@ -243,22 +454,161 @@ where
             Query::Pipelined(p) => {
                 if compiler::likely(auth.authenticated()) {
                     con.write_pipelined_query_header(p.len()).await?;
-                    queryengine::execute_pipeline(db, con, auth, p).await?;
+                    queryengine::execute_pipeline(db, con, auth, state, p).await?;
                 } else {
                     con.write_simple_query_header().await?;
                     con.write_error(P::AUTH_CODE_BAD_CREDENTIALS).await?;
                 }
             }
         }
-        con.stream.flush().await?;
+        con.flush().await?;
         Ok(())
     }
 }
 
-impl<C, T> Drop for ConnectionHandler<C, T> {
+impl<C, P> Drop for ConnectionHandler<C, P> {
     fn drop(&mut self) {
-        // Make sure that the permit is returned to the semaphore
-        // in the case that there is a panic inside
-        self.climit.add_permits(1);
+        self.db.get_stats().client_disconnected();
+    }
+}
+
+/// Run a client that was admitted by the governor. If the client starts with a handshake, it
+/// speaks the protocol version that it negotiated and otherwise it speaks `P` (the version
+/// set in the configuration)
+#[allow(clippy::too_many_arguments)]
+pub(self) async fn run_client<C, P>(
+    db: Corestore,
+    stream: C,
+    auth: AuthProvider,
+    client: ClientHandle,
+    options: ConnectionOptions,
+    mut termination_signal: broadcast::Receiver<()>,
+    term_sig_tx: mpsc::Sender<()>,
+) -> IoResult<()>
+where
+    C: BufferedSocketStream,
+    P: ProtocolSpec,
+{
+    let mut con = Connection::<C, P>::new(stream)
+        .with_max_inflight(options.maxinflight)
+        .with_max_outbuf(options.maxoutbuf)
+        .with_limits(options.limits);
+    let read_timeout = client.read_timeout();
+    let negotiated = tokio::select! {
+        negotiated = handshake::negotiate(
+            &mut con,
+            read_timeout,
+            options.compressmin != 0,
+            db.get_cluster(),
+        ) => {
+            negotiated?
+        }
+        _ = client.reaped() => return Ok(()),
+        _ = termination_signal.recv() => return Ok(()),
+    };
+    let handler = ClientParts {
+        db,
+        auth,
+        client,
+        options,
+        termination_signal,
+        term_sig_tx,
+    };
+    match negotiated {
+        Negotiated::Default => handler.serve(con, None).await,
+        Negotiated::Version(ProtocolVersion::V1, codec) => {
+            handler.serve(con.into_protocol::<Skyhash1>(), codec).await
+        }
+        Negotiated::Version(ProtocolVersion::V2, codec) => {
+            handler.serve(con.into_protocol::<Skyhash2>(), codec).await
+        }
+        Negotiated::Failed => Ok(()),
+    }
+}
+
+/// Everything that a client's [`ConnectionHandler`] is made of, apart from the connection
+struct ClientParts {
+    db: Corestore,
+    auth: AuthProvider,
+    client: ClientHandle,
+    options: ConnectionOptions,
+    termination_signal: broadcast::Receiver<()>,
+    term_sig_tx: mpsc::Sender<()>,
+}
+
+impl ClientParts {
+    /// Serve the client after its handshake, framing the connection if it negotiated a codec
+    async fn serve<C, P>(self, con: Connection<C, P>, codec: Option<Codec>) -> IoResult<()>
+    where
+        C: BufferedSocketStream,
+        P: ProtocolSpec,
+    {
+        let Self {
+            db,
+            auth,
+            client,
+            options,
+            termination_signal,
+            term_sig_tx,
+        } = self;
+        match codec {
+            Some(codec) => {
+                let (stream, buffered) = con.into_inner();
+                let stream = CompressedStream::new(stream, codec, options.compressmin, buffered);
+                let con = Connection::<_, P>::new(stream)
+                    .with_max_inflight(options.maxinflight)
+                    .with_max_outbuf(options.maxoutbuf)
+                    .with_limits(options.limits);
+                ConnectionHandler::new(db, con, auth, client, termination_signal, term_sig_tx)
+                    .run()
+                    .await
+            }
+            None => {
+                ConnectionHandler::new(db, con, auth, client, termination_signal, term_sig_tx)
+                    .run()
+                    .await
+            }
+        }
+    }
+}
+
+/// Check the IP rules for a client that connected from `peer`, counting it if it was turned
+/// away. Clients that are turned away are just disconnected, without telling them why
+pub(self) fn screen_peer(db: &Corestore, peer: IpAddr) -> bool {
+    match db.get_runtime_config().load().ipfilter.check(peer) {
+        IpVerdict::Allowed => true,
+        IpVerdict::Denied => {
+            db.get_stats().client_denied();
+            false
+        }
+        IpVerdict::NotAllowed => {
+            db.get_stats().client_not_allowed();
+            false
+        }
+    }
+}
+
+/// Send TCP keepalive probes once a client socket has been silent for `idle` seconds (zero
+/// leaves keepalive off), so that peers that went away without closing the connection are
+/// noticed. This is best effort: a socket that can't be set up is served anyway
+pub(self) fn set_keepalive<'s, S>(stream: &'s S, idle: u64)
+where
+    SockRef<'s>: From<&'s S>,
+{
+    if idle != 0 {
+        let keepalive = TcpKeepalive::new().with_time(Duration::from_secs(idle));
+        let _ = SockRef::from(stream).set_tcp_keepalive(&keepalive);
+    }
+}
+
+/// Tell a client that was turned away by the governor why, and then disconnect it
+pub(self) async fn reject_client<C, P>(stream: C)
+where
+    C: BufferedSocketStream,
+    P: ProtocolSpec,
+{
+    let mut con = Connection::<C, P>::new(stream);
+    if con.write_simple_query_header().await.is_ok() {
+        let _ = con.write_error(P::RSTRING_TOO_MANY_CONNECTIONS).await;
     }
 }
@@ -33,7 +33,7 @@ pub use {
     crate::{
         actions::{ensure_boolean_or_aerr, ensure_length, translate_ddl_error},
         corestore::{
-            table::{KVEBlob, KVEList},
+            table::{KVEBlob, KVEHash, KVEList, KVESet, KVEZSet},
             Corestore,
         },
         get_tbl, handle_entity, is_lowbit_set,
@@ -0,0 +1,413 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Stateless clients
+//!
+//! Clients of the gateways (HTTP and gRPC) don't hold on to a Skyhash connection. Every
+//! request logs in with a bearer token (if auth is enabled) and its queries are run on a
+//! connection that only lives as long as the request. The responses that the query engine
+//! writes are decoded into [`Element`]s, which the gateways translate for their clients
+
+use {
+    crate::{
+        actions::ActionError,
+        corestore::lazy::Lazy,
+        dbnet::{prelude::*, BufferedSocketStream},
+        protocol::{Skyhash2, UnsafeSlice},
+        queryengine, IoResult,
+    },
+    core::{
+        mem,
+        pin::Pin,
+        str,
+        task::{Context, Poll},
+    },
+    openssl::{
+        hash::MessageDigest,
+        pkey::{PKey, Private},
+        sign::Signer,
+    },
+    parking_lot::Mutex,
+    std::{
+        collections::HashMap,
+        io::{Error as IoError, ErrorKind},
+        time::{Duration, Instant},
+    },
+    tokio::io::{AsyncRead, AsyncWrite, ReadBuf},
+};
+
+/// How long a verified bearer token is accepted before its key is verified again
+const BEARER_TTL: Duration = Duration::from_secs(30);
+/// The largest number of verified bearer tokens that we remember
+const BEARER_CACHE_CAPACITY: usize = 4096;
+
+/// The bearer tokens that were verified recently (see [`BearerCache`])
+static BEARER_CACHE: Lazy<BearerCache, fn() -> BearerCache> = Lazy::new(BearerCache::new);
+
+/// An element of a (Skyhash 2) response, decoded so that it can be sent to the client
+#[derive(Debug, PartialEq)]
+pub enum Element {
+    /// a response code (or string)
+    Code(String),
+    String(String),
+    Binary(Vec<u8>),
+    Int(u64),
+    Float(f64),
+    Array(Vec<Element>),
+    /// a null element of a typed array
+    Null,
+}
+
+impl Element {
+    /// Decode a response written by the query engine
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        let mut decoder = Decoder { buf };
+        let ret = decoder.element()?;
+        decoder.buf.is_empty().then_some(ret)
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.buf.len() < len {
+            return None;
+        }
+        let (ret, rem) = self.buf.split_at(len);
+        self.buf = rem;
+        Some(ret)
+    }
+    /// Take everything up to the next LF (skipping the LF)
+    fn line(&mut self) -> Option<&'a str> {
+        let pos = self.buf.iter().position(|byte| *byte == Skyhash2::LF)?;
+        let ret = self.take(pos)?;
+        self.buf = &self.buf[1..];
+        str::from_utf8(ret).ok()
+    }
+    fn number<T: str::FromStr>(&mut self) -> Option<T> {
+        self.line()?.parse().ok()
+    }
+    /// Take a length-prefixed string or binary string
+    fn mono(&mut self, tsymbol: u8) -> Option<Element> {
+        let len = self.number()?;
+        let data = self.take(len)?;
+        if tsymbol == Skyhash2::TSYMBOL_STRING {
+            Some(Element::String(str::from_utf8(data).ok()?.to_owned()))
+        } else {
+            Some(Element::Binary(data.to_vec()))
+        }
+    }
+    fn element(&mut self) -> Option<Element> {
+        let tsymbol = self.take(1)?[0];
+        match tsymbol {
+            b'!' => Some(Element::Code(self.line()?.to_owned())),
+            Skyhash2::TSYMBOL_STRING | Skyhash2::TSYMBOL_BINARY => self.mono(tsymbol),
+            Skyhash2::TSYMBOL_INT64 => Some(Element::Int(self.number()?)),
+            Skyhash2::TSYMBOL_FLOAT => Some(Element::Float(self.number()?)),
+            Skyhash2::TSYMBOL_ARRAY => {
+                let len: usize = self.number()?;
+                (0..len)
+                    .map(|_| self.element())
+                    .collect::<Option<_>>()
+                    .map(Element::Array)
+            }
+            Skyhash2::TSYMBOL_TYPED_ARRAY | Skyhash2::TSYMBOL_TYPED_NON_NULL_ARRAY => {
+                let element_tsymbol = self.take(1)?[0];
+                let len: usize = self.number()?;
+                (0..len)
+                    .map(|_| {
+                        if tsymbol == Skyhash2::TSYMBOL_TYPED_ARRAY
+                            && self
+                                .buf
+                                .starts_with(Skyhash2::TYPE_TYPED_ARRAY_ELEMENT_NULL)
+                        {
+                            self.buf = &self.buf[Skyhash2::TYPE_TYPED_ARRAY_ELEMENT_NULL.len()..];
+                            Some(Element::Null)
+                        } else {
+                            self.mono(element_tsymbol)
+                        }
+                    })
+                    .collect::<Option<_>>()
+                    .map(Element::Array)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A stream that collects everything written to it and never has anything to be read. This
+/// is where the responses to the queries of stateless clients go (and the responses of a
+/// transaction, till it's done)
+#[derive(Default)]
+pub struct BufferStream(Vec<u8>);
+
+impl BufferStream {
+    /// Take everything that was written so far
+    pub(super) fn take(&mut self) -> Vec<u8> {
+        mem::take(&mut self.0)
+    }
+}
+
+impl<P: ProtocolSpec> Connection<BufferStream, P> {
+    /// Take everything that was written to the connection so far
+    pub async fn take_buffered(&mut self) -> IoResult<Vec<u8>> {
+        self.flush().await?;
+        Ok(self.stream.get_mut().take())
+    }
+}
+
+impl AsyncRead for BufferStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        _: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for BufferStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.0.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl BufferedSocketStream for BufferStream {}
+
+/// Why a stateless client couldn't log in
+#[derive(Debug, PartialEq, Eq)]
+pub enum LoginError {
+    /// the credentials were missing or wrong
+    BadCredentials,
+    /// the account (or the client) is being throttled
+    RateLimited,
+}
+
+/// A MAC of a bearer token (see [`BearerCache::proof`])
+type Proof = [u8; 32];
+
+/// Every request of a stateless client logs in again, and verifying its key every time
+/// (which takes tens of milliseconds with the memory-hard schemes) would cap the gateways at a
+/// handful of requests a second per core. So a token that was verified is accepted without
+/// verifying it again for [`BEARER_TTL`]. We only remember a MAC of the token (keyed with a
+/// secret that is generated on every boot) and not the token itself.
+///
+/// The MAC also covers the hash of the user's current key, so regenerating the key (or
+/// removing the user) forgets their tokens right away. A token that was verified against a
+/// retired key can outlive the key's grace period by at most [`BEARER_TTL`]
+struct BearerCache {
+    secret: PKey<Private>,
+    /// when each token was verified
+    verified: Mutex<HashMap<Proof, Instant>>,
+}
+
+impl BearerCache {
+    fn new() -> Self {
+        let mut secret = [0u8; 32];
+        openssl::rand::rand_bytes(&mut secret).unwrap();
+        Self {
+            secret: PKey::hmac(&secret).unwrap(),
+            verified: Mutex::new(HashMap::new()),
+        }
+    }
+    /// Returns the MAC of `token` for `user`, whose current key has the hash `key`
+    fn proof(&self, user: &str, token: &str, key: &[u8]) -> Proof {
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.secret).unwrap();
+        for part in [user.as_bytes(), token.as_bytes(), key] {
+            // length-prefixed, so that the parts can't run into each other
+            signer.update(&(part.len() as u64).to_le_bytes()).unwrap();
+            signer.update(part).unwrap();
+        }
+        let mut proof = [0u8; 32];
+        signer.sign(&mut proof).unwrap();
+        proof
+    }
+    fn is_fresh(&self, proof: &Proof) -> bool {
+        self.verified
+            .lock()
+            .get(proof)
+            .map_or(false, |verified| verified.elapsed() < BEARER_TTL)
+    }
+    fn remember(&self, proof: Proof) {
+        let mut verified = self.verified.lock();
+        if verified.len() >= BEARER_CACHE_CAPACITY {
+            verified.retain(|_, verified| verified.elapsed() < BEARER_TTL);
+            if verified.len() >= BEARER_CACHE_CAPACITY {
+                // they'll just be verified again
+                verified.clear();
+            }
+        }
+        verified.insert(proof, Instant::now());
+    }
+}
+
+/// Log in with a bearer token (of the form `<user>:<token>`), if auth is enabled.
+/// `authorization` is the value of the client's `Authorization` header (or its equivalent).
+/// Tokens that were verified recently aren't verified again (see [`BearerCache`])
+pub async fn login_bearer(
+    auth: &mut AuthProviderHandle,
+    authorization: Option<&str>,
+) -> Result<(), LoginError> {
+    if auth.authenticated() {
+        // auth is disabled
+        return Ok(());
+    }
+    let (user, token) = authorization
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .and_then(|(_, credentials)| credentials.trim().rsplit_once(':'))
+        .ok_or(LoginError::BadCredentials)?;
+    let provider = auth.provider_mut();
+    let proof = provider
+        .authmap()
+        .get(user.as_bytes())
+        .map(|record| BEARER_CACHE.proof(user, token, record.key()));
+    let result = match proof {
+        Some(proof) if BEARER_CACHE.is_fresh(&proof) => {
+            provider.login_verified::<Skyhash2>(user.as_bytes())
+        }
+        _ => {
+            let result = provider
+                .login::<Skyhash2>(user.as_bytes(), token.as_bytes())
+                .await;
+            if let (Ok(()), Some(proof)) = (&result, proof) {
+                BEARER_CACHE.remember(proof);
+            }
+            result
+        }
+    };
+    match result {
+        Ok(()) => {
+            auth.set_auth();
+            Ok(())
+        }
+        Err(ActionError::ActionError(code)) if code == Skyhash2::AUTH_CODE_RATE_LIMITED => {
+            Err(LoginError::RateLimited)
+        }
+        Err(_) => Err(LoginError::BadCredentials),
+    }
+}
+
+/// Run a query for the client with the given ID, returning the response that the query
+/// engine wrote
+pub async fn run_query(
+    db: &mut Corestore,
+    auth: &mut AuthProviderHandle,
+    client: u64,
+    query: &[Vec<u8>],
+) -> IoResult<Element> {
+    let query: Vec<UnsafeSlice> = query
+        .iter()
+        .map(|arg| UnsafeSlice::new(arg.as_ptr(), arg.len()))
+        .collect();
+    db.get_monitor().publish(client, &query);
+    let mut con = Connection::<BufferStream, Skyhash2>::new(BufferStream::default());
+    queryengine::execute_stateless(db, &mut con, auth, &query).await?;
+    con.flush().await?;
+    Element::decode(&con.stream.get_ref().0).ok_or_else(|| {
+        IoError::new(
+            ErrorKind::InvalidData,
+            "the query engine wrote a malformed response",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::auth::{AuthProvider, Permissions},
+    };
+
+    #[test]
+    fn decode_elements() {
+        assert_eq!(
+            Element::decode(b"!0\n"),
+            Some(Element::Code("0".to_owned()))
+        );
+        assert_eq!(
+            Element::decode(b"+5\nhello"),
+            Some(Element::String("hello".to_owned()))
+        );
+        assert_eq!(Element::decode(b":100\n"), Some(Element::Int(100)));
+        assert_eq!(
+            Element::decode(b"&2\n?2\nhi:1\n"),
+            Some(Element::Array(vec![
+                Element::Binary(b"hi".to_vec()),
+                Element::Int(1)
+            ]))
+        );
+        assert_eq!(
+            Element::decode(b"@+2\n1\na\0"),
+            Some(Element::Array(vec![
+                Element::String("a".to_owned()),
+                Element::Null
+            ]))
+        );
+        // trailing data
+        assert_eq!(Element::decode(b":1\n:2\n"), None);
+        assert_eq!(Element::decode(b"+5\nhi"), None);
+    }
+
+    #[tokio::test]
+    async fn cached_bearer_tokens_follow_the_key() {
+        const ORIG: &[u8; 40] = b"c4299d190fb9a00626797fcc138c56eae9971664";
+        let mut provider = AuthProvider::new_blank(Some(*ORIG));
+        let _ = provider.claim_root::<Skyhash2>(ORIG).unwrap();
+        let oldkey = provider
+            ._claim_user::<Skyhash2>(b"sayan", Permissions::DEFAULT)
+            .unwrap();
+        let login = |key: &str| {
+            let mut auth = AuthProviderHandle::new(provider.for_client(None));
+            let authorization = format!("Bearer sayan:{key}");
+            async move { login_bearer(&mut auth, Some(&authorization)).await }
+        };
+        assert_eq!(login(&oldkey).await, Ok(()));
+        // now it's cached
+        assert_eq!(login(&oldkey).await, Ok(()));
+        assert_eq!(login("badkey").await, Err(LoginError::BadCredentials));
+        let newkey = provider
+            .regenerate_using_origin::<Skyhash2>(ORIG, b"sayan")
+            .unwrap();
+        assert_eq!(login(&oldkey).await, Err(LoginError::BadCredentials));
+        assert_eq!(login(&newkey).await, Ok(()));
+        assert_eq!(login(&newkey).await, Ok(()));
+    }
+}
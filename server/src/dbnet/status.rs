@@ -0,0 +1,288 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Health and readiness endpoints
+//!
+//! With `server.statusport`, a small HTTP/1.1 listener serves the endpoints that orchestrators
+//! (like Kubernetes) probe. It's started before the data is loaded, so that a server that takes
+//! a while to load isn't mistaken for a dead one:
+//!
+//! - `GET /health`: always `200` while the server is running
+//! - `GET /ready`: `200` once the data is loaded, the server can write to disk and (on a
+//! replica) the link to the primary is streaming writes, and `503` otherwise
+//!
+//! Both return the state of the server as a JSON object like `{"loaded": true, "health":
+//! "good", "writable": true, "replication": {"role": "primary", "replicas": 0}}`. For a
+//! replica, the replication status also has the `primary`, the state of the `link`, the
+//! number of writes `applied` since the last sync and when it `lastsync`ed (like
+//! `SYS INFO replication`). The endpoints don't need credentials, aren't subject to the
+//! connection limit and every connection is closed after its response
+
+use {
+    super::http::{HttpConnection, Method, RequestResult, Response, Status},
+    crate::{
+        corestore::Corestore,
+        registry,
+        replication::{LinkState, ReplicationStatus},
+        util::error::{Error, SkyResult},
+    },
+    parking_lot::RwLock,
+    serde_json::{json, Value},
+    std::{net::IpAddr, sync::Arc, time::Duration},
+    tokio::{
+        net::{TcpListener, TcpStream},
+        sync::broadcast,
+        task::JoinHandle,
+        time,
+    },
+};
+
+const ROUTE_HEALTH: &str = "/health";
+const ROUTE_READY: &str = "/ready";
+/// The time that a client has to send its request in
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The store, once it has been loaded
+type LoadedStore = Arc<RwLock<Option<Corestore>>>;
+
+/// The state of the server, as reported by the endpoints
+struct ServerStatus {
+    /// whether the data has been loaded
+    loaded: bool,
+    /// whether the server can write to disk
+    healthy: bool,
+    /// the replication status (once the data has been loaded)
+    replication: Option<ReplicationStatus>,
+}
+
+impl ServerStatus {
+    fn of(store: Option<&Corestore>) -> Self {
+        Self {
+            loaded: store.is_some(),
+            healthy: registry::state_okay(),
+            replication: store.map(|db| db.get_replication().status()),
+        }
+    }
+    /// Returns true if writes are accepted
+    fn is_writable(&self) -> bool {
+        self.loaded
+            && self.healthy
+            && matches!(self.replication, Some(ReplicationStatus::Primary { .. }))
+    }
+    /// Returns true if the server can serve queries
+    fn is_ready(&self) -> bool {
+        self.loaded
+            && self.healthy
+            && match self.replication {
+                Some(ReplicationStatus::Replica { link, .. }) => link == LinkState::Streaming,
+                _ => true,
+            }
+    }
+    fn to_json(&self) -> Value {
+        let replication = match self.replication {
+            Some(ReplicationStatus::Primary { replicas }) => {
+                json!({ "role": "primary", "replicas": replicas })
+            }
+            Some(ReplicationStatus::Replica {
+                ref primary,
+                link,
+                applied,
+                last_sync,
+                lag,
+            }) => json!({
+                "role": "replica",
+                "primary": primary,
+                "link": link.as_str(),
+                "applied": applied,
+                "lastsync": last_sync,
+                "lag": lag,
+            }),
+            None => Value::Null,
+        };
+        json!({
+            "loaded": self.loaded,
+            "health": if self.healthy { "good" } else { "critical" },
+            "writable": self.is_writable(),
+            "replication": replication,
+        })
+    }
+}
+
+/// The listener for the health and readiness endpoints
+pub struct StatusListener {
+    store: LoadedStore,
+    task: JoinHandle<()>,
+}
+
+impl StatusListener {
+    /// Bind to the port and start serving the endpoints. The listener shuts down once every
+    /// sender of `signal` is dropped
+    pub async fn init(host: IpAddr, port: u16, signal: &broadcast::Sender<()>) -> SkyResult<Self> {
+        let listener = TcpListener::bind((host, port))
+            .await
+            .map_err(|e| Error::ioerror_extra(e, format!("binding to port {port}")))?;
+        let store = LoadedStore::default();
+        let task = tokio::spawn(self::serve(listener, store.clone(), signal.subscribe()));
+        Ok(Self { store, task })
+    }
+    /// Mark the data as loaded
+    pub fn set_loaded(&self, db: &Corestore) {
+        *self.store.write() = Some(db.clone());
+    }
+    /// Wait for the listener to shut down and let go of the store
+    pub async fn release_self(self) {
+        let _ = self.task.await;
+        self.store.write().take();
+    }
+}
+
+async fn serve(listener: TcpListener, store: LoadedStore, mut signal: broadcast::Receiver<()>) {
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log::debug!("Failed to accept a status connection: {}", e);
+                    continue;
+                }
+            },
+            _ = signal.recv() => return,
+        };
+        tokio::spawn(self::respond(stream, store.clone()));
+    }
+}
+
+/// Serve a single request and close the connection
+async fn respond(stream: TcpStream, store: LoadedStore) {
+    let mut con = HttpConnection::new(stream);
+    let request = match time::timeout(READ_TIMEOUT, con.read_request(None)).await {
+        Ok(Ok(RequestResult::Request(request))) => request,
+        Ok(Ok(RequestResult::Malformed(status))) => {
+            let _ = con
+                .write_response(&Response::error(status, status.reason()), false)
+                .await;
+            return;
+        }
+        _ => return,
+    };
+    let readiness = match request.path.as_str() {
+        ROUTE_HEALTH => false,
+        ROUTE_READY => true,
+        _ => {
+            let response = Response::error(Status::NotFound, "unknown route");
+            let _ = con.write_response(&response, false).await;
+            return;
+        }
+    };
+    let response = if request.method == Method::Get {
+        let status = ServerStatus::of(store.read().as_ref());
+        if readiness && !status.is_ready() {
+            Response::json(Status::ServiceUnavailable, status.to_json())
+        } else {
+            Response::json(Status::Ok, status.to_json())
+        }
+    } else {
+        Response::error(Status::MethodNotAllowed, "method not allowed")
+    };
+    let _ = con.write_response(&response, false).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::ServerStatus,
+        crate::replication::{LinkState, ReplicationStatus},
+        serde_json::json,
+    };
+
+    fn replica(link: LinkState) -> Option<ReplicationStatus> {
+        Some(ReplicationStatus::Replica {
+            primary: "127.0.0.1:2003".to_owned(),
+            link,
+            applied: 10,
+            last_sync: 1700000000,
+            lag: Some(120),
+        })
+    }
+
+    #[test]
+    fn loading_is_not_ready() {
+        let status = ServerStatus {
+            loaded: false,
+            healthy: true,
+            replication: None,
+        };
+        assert!(!status.is_ready());
+        assert!(!status.is_writable());
+        assert_eq!(
+            status.to_json(),
+            json!({ "loaded": false, "health": "good", "writable": false, "replication": null })
+        );
+    }
+
+    #[test]
+    fn primary_is_ready() {
+        let mut status = ServerStatus {
+            loaded: true,
+            healthy: true,
+            replication: Some(ReplicationStatus::Primary { replicas: 2 }),
+        };
+        assert!(status.is_ready() && status.is_writable());
+        assert_eq!(
+            status.to_json()["replication"],
+            json!({ "role": "primary", "replicas": 2 })
+        );
+        // writes are stopped after a failed save
+        status.healthy = false;
+        assert!(!status.is_ready() && !status.is_writable());
+        assert_eq!(status.to_json()["health"], "critical");
+    }
+
+    #[test]
+    fn replica_is_ready_once_streaming() {
+        let mut status = ServerStatus {
+            loaded: true,
+            healthy: true,
+            replication: replica(LinkState::Syncing),
+        };
+        assert!(!status.is_ready());
+        status.replication = replica(LinkState::Streaming);
+        assert!(status.is_ready());
+        // replicas never accept writes
+        assert!(!status.is_writable());
+        assert_eq!(
+            status.to_json()["replication"],
+            json!({
+                "role": "replica",
+                "primary": "127.0.0.1:2003",
+                "link": "streaming",
+                "applied": 10,
+                "lastsync": 1700000000,
+                "lag": 120,
+            })
+        );
+    }
+}
@@ -28,7 +28,10 @@ pub use protocol::{ParseResult, Query};
 use {
     super::NetBackoff,
     crate::{
-        dbnet::{listener::BaseListener, BufferedSocketStream, Connection, ConnectionHandler},
+        dbnet::{
+            listener::BaseListener, reject_client, run_client, screen_peer, set_keepalive,
+            BufferedSocketStream,
+        },
         protocol::{self, interface::ProtocolSpec, Skyhash1, Skyhash2},
         IoResult,
     },
@@ -59,8 +62,14 @@ impl<P: ProtocolSpec + 'static> RawListener<P> {
         let backoff = NetBackoff::new();
         loop {
             match self.base.listener.accept().await {
-                // We don't need the bindaddr
-                Ok((stream, _)) => return Ok(stream),
+                Ok((stream, addr)) => {
+                    if screen_peer(&self.base.db, addr.ip()) {
+                        set_keepalive(&stream, self.base.options.keepalive);
+                        return Ok(stream);
+                    }
+                    // turned away by the IP rules; dropping the stream disconnects it
+                    continue;
+                }
                 Err(e) => {
                     if backoff.should_disconnect() {
                         // Too many retries, goodbye user
@@ -74,10 +83,11 @@ impl<P: ProtocolSpec + 'static> RawListener<P> {
     }
     /// Run the server
     pub async fn run(&mut self) -> IoResult<()> {
+        #[cfg(all(feature = "io-uring", target_os = "linux"))]
+        if super::uring::is_supported() {
+            return super::uring::run::<P>(&self.base).await;
+        }
         loop {
-            // Take the permit first, but we won't use it right now
-            // that's why we will forget it
-            self.base.climit.acquire().await.unwrap().forget();
             /*
              SECURITY: Ignore any errors that may arise in the accept
              loop. If we apply the try operator here, we will immediately
@@ -87,16 +97,26 @@ impl<P: ProtocolSpec + 'static> RawListener<P> {
              in a crash
             */
             let stream = skip_loop_err!(self.accept().await);
-            let mut chandle = ConnectionHandler::<TcpStream, P>::new(
+            let client = match self.base.governor.try_admit() {
+                Some(client) => client,
+                None => {
+                    self.base.db.get_stats().client_rejected();
+                    tokio::spawn(reject_client::<TcpStream, P>(stream));
+                    continue;
+                }
+            };
+            let peer = stream.peer_addr().ok().map(|addr| addr.ip());
+            let run = run_client::<TcpStream, P>(
                 self.base.db.clone(),
-                Connection::new(stream),
-                self.base.auth.clone(),
-                self.base.climit.clone(),
+                stream,
+                self.base.auth.for_client(peer),
+                client,
+                self.base.options,
                 self.base.signal.subscribe(),
                 self.base.terminate_tx.clone(),
             );
             tokio::spawn(async move {
-                if let Err(e) = chandle.run().await {
+                if let Err(e) = run.await {
                     log::error!("Error: {}", e);
                 }
             });
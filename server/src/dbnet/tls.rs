@@ -27,16 +27,19 @@
 use {
     crate::{
         dbnet::{
-            listener::BaseListener, BufferedSocketStream, Connection, ConnectionHandler, NetBackoff,
+            listener::BaseListener, reject_client, run_client, screen_peer, set_keepalive,
+            BufferedSocketStream, NetBackoff,
         },
         protocol::{interface::ProtocolSpec, Skyhash1, Skyhash2},
         util::error::{Error, SkyResult},
         IoResult,
     },
     openssl::{
+        nid::Nid,
         pkey::PKey,
         rsa::Rsa,
-        ssl::{Ssl, SslAcceptor, SslFiletype, SslMethod},
+        ssl::{Ssl, SslAcceptor, SslFiletype, SslMethod, SslVerifyMode},
+        x509::{X509Name, X509Ref},
     },
     std::{fs, marker::PhantomData, pin::Pin},
     tokio::net::TcpStream,
@@ -60,6 +63,7 @@ impl<P: ProtocolSpec + 'static> SslListenerRaw<P> {
         chain_file: String,
         base: BaseListener,
         tls_passfile: Option<String>,
+        clientca: Option<String>,
     ) -> SkyResult<SslListenerRaw<P>> {
         let mut acceptor_builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
         // cert is the same for both
@@ -80,6 +84,12 @@ impl<P: ProtocolSpec + 'static> SslListenerRaw<P> {
             // no passphrase, needs interactive
             acceptor_builder.set_private_key_file(key_file, SslFiletype::PEM)?;
         }
+        if let Some(clientca) = clientca {
+            // only let in clients with a certificate that was issued by this CA
+            acceptor_builder.set_ca_file(&clientca)?;
+            acceptor_builder.set_client_ca_list(X509Name::load_client_ca_file(&clientca)?);
+            acceptor_builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
         Ok(Self {
             acceptor: acceptor_builder.build(),
             base,
@@ -90,10 +100,13 @@ impl<P: ProtocolSpec + 'static> SslListenerRaw<P> {
         let backoff = NetBackoff::new();
         loop {
             match self.base.listener.accept().await {
-                // We don't need the bindaddr
+                // Clients that are turned away by the IP rules are dropped before the
+                // handshake
+                Ok((_, addr)) if !screen_peer(&self.base.db, addr.ip()) => continue,
                 // We get the encrypted stream which we need to decrypt
                 // by using the acceptor
                 Ok((stream, _)) => {
+                    set_keepalive(&stream, self.base.options.keepalive);
                     let ssl = Ssl::new(self.acceptor.context())?;
                     let mut stream = SslStream::new(ssl, stream)?;
                     Pin::new(&mut stream).accept().await?;
@@ -112,9 +125,6 @@ impl<P: ProtocolSpec + 'static> SslListenerRaw<P> {
     }
     pub async fn run(&mut self) -> IoResult<()> {
         loop {
-            // Take the permit first, but we won't use it right now
-            // that's why we will forget it
-            self.base.climit.acquire().await.unwrap().forget();
             /*
              SECURITY: Ignore any errors that may arise in the accept
              loop. If we apply the try operator here, we will immediately
@@ -124,19 +134,54 @@ impl<P: ProtocolSpec + 'static> SslListenerRaw<P> {
              in a crash
             */
             let stream = skip_loop_err!(self.accept().await);
-            let mut sslhandle = ConnectionHandler::<SslStream<TcpStream>, P>::new(
+            let client = match self.base.governor.try_admit() {
+                Some(client) => client,
+                None => {
+                    self.base.db.get_stats().client_rejected();
+                    tokio::spawn(reject_client::<SslStream<TcpStream>, P>(stream));
+                    continue;
+                }
+            };
+            let peer = stream.get_ref().peer_addr().ok().map(|addr| addr.ip());
+            let mut auth = self.base.auth.for_client(peer);
+            if let Some(cert) = stream.ssl().peer_certificate() {
+                auth.login_with_certificate(&self::certificate_names(&cert));
+            }
+            let run = run_client::<SslStream<TcpStream>, P>(
                 self.base.db.clone(),
-                Connection::new(stream),
-                self.base.auth.clone(),
-                self.base.climit.clone(),
+                stream,
+                auth,
+                client,
+                self.base.options,
                 self.base.signal.subscribe(),
                 self.base.terminate_tx.clone(),
             );
             tokio::spawn(async move {
-                if let Err(e) = sslhandle.run().await {
+                if let Err(e) = run.await {
                     log::error!("Error: {}", e);
                 }
             });
         }
     }
 }
+
+/// Returns the names that a client certificate was issued to: the common name of its subject,
+/// followed by the DNS names and email addresses among its subject alternative names. The
+/// client is logged in as the first of them that is a user
+fn certificate_names(cert: &X509Ref) -> Vec<String> {
+    let mut names: Vec<String> = cert
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .filter_map(|entry| entry.data().as_utf8().ok())
+        .map(|name| name.to_string())
+        .collect();
+    if let Some(alt_names) = cert.subject_alt_names() {
+        names.extend(
+            alt_names
+                .iter()
+                .filter_map(|name| name.dnsname().or_else(|| name.email()))
+                .map(str::to_owned),
+        );
+    }
+    names
+}
@@ -0,0 +1,173 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    super::{governor::Governor, NetBackoff},
+    crate::{
+        auth::AuthProvider,
+        corestore::Corestore,
+        dbnet::{
+            listener::BaseListener, reject_client, run_client, BufferedSocketStream,
+            ConnectionOptions,
+        },
+        protocol::{interface::ProtocolSpec, Skyhash1, Skyhash2},
+        util::error::{Error, SkyResult},
+        IoResult,
+    },
+    std::{
+        fs,
+        io::ErrorKind,
+        marker::PhantomData,
+        os::unix::fs::FileTypeExt,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+    tokio::{
+        net::{UnixListener, UnixStream},
+        sync::{broadcast, mpsc},
+    },
+};
+
+impl BufferedSocketStream for UnixStream {}
+
+pub type LocalListener = RawLocalListener<Skyhash2>;
+pub type LocalListenerV1 = RawLocalListener<Skyhash1>;
+
+impl BaseListener<UnixListener> {
+    /// Bind to the Unix domain socket at `path`
+    ///
+    /// A socket file left behind by an earlier instance is removed before binding, but we
+    /// refuse to clobber anything that isn't a socket
+    pub fn init_unix(
+        db: &Corestore,
+        auth: AuthProvider,
+        path: &Path,
+        governor: Arc<Governor>,
+        options: ConnectionOptions,
+        signal: broadcast::Sender<()>,
+    ) -> SkyResult<Self> {
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.file_type().is_socket() => fs::remove_file(path).map_err(|e| {
+                Error::ioerror_extra(e, format!("removing stale socket `{}`", path.display()))
+            })?,
+            Ok(_) => {
+                return Err(Error::OtherError(format!(
+                    "`{}` already exists and is not a socket",
+                    path.display()
+                )))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(Error::ioerror_extra(
+                    e,
+                    format!("checking socket path `{}`", path.display()),
+                ))
+            }
+        }
+        let (terminate_tx, terminate_rx) = mpsc::channel(1);
+        let listener = UnixListener::bind(path)
+            .map_err(|e| Error::ioerror_extra(e, format!("binding to `{}`", path.display())))?;
+        Ok(Self {
+            db: db.clone(),
+            auth,
+            listener,
+            governor,
+            options,
+            signal,
+            terminate_tx,
+            terminate_rx,
+        })
+    }
+}
+
+/// A listener for local clients connecting over a Unix domain socket
+pub struct RawLocalListener<P> {
+    pub base: BaseListener<UnixListener>,
+    path: PathBuf,
+    _marker: PhantomData<P>,
+}
+
+impl<P: ProtocolSpec + 'static> RawLocalListener<P> {
+    pub fn new(base: BaseListener<UnixListener>, path: PathBuf) -> Self {
+        Self {
+            base,
+            path,
+            _marker: PhantomData,
+        }
+    }
+    /// Accept an incoming connection
+    async fn accept(&mut self) -> IoResult<UnixStream> {
+        let backoff = NetBackoff::new();
+        loop {
+            match self.base.listener.accept().await {
+                // unix peers are almost always unnamed, so there's nothing to keep
+                Ok((stream, _)) => return Ok(stream),
+                Err(e) => {
+                    if backoff.should_disconnect() {
+                        return Err(e);
+                    }
+                }
+            }
+            backoff.spin().await;
+        }
+    }
+    /// Run the server
+    pub async fn run(&mut self) -> IoResult<()> {
+        loop {
+            let stream = skip_loop_err!(self.accept().await);
+            let client = match self.base.governor.try_admit() {
+                Some(client) => client,
+                None => {
+                    self.base.db.get_stats().client_rejected();
+                    tokio::spawn(reject_client::<UnixStream, P>(stream));
+                    continue;
+                }
+            };
+            let run = run_client::<UnixStream, P>(
+                self.base.db.clone(),
+                stream,
+                self.base.auth.for_client(None),
+                client,
+                self.base.options,
+                self.base.signal.subscribe(),
+                self.base.terminate_tx.clone(),
+            );
+            tokio::spawn(async move {
+                if let Err(e) = run.await {
+                    log::error!("Error: {}", e);
+                }
+            });
+        }
+    }
+    /// Wait for the connections to terminate and then remove the socket file
+    pub async fn release_self(self) {
+        let Self { base, path, .. } = self;
+        base.release_self().await;
+        if let Err(e) = fs::remove_file(&path) {
+            log::warn!("Failed to remove socket `{}`: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,359 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # io_uring networking
+//!
+//! With the `io-uring` feature (on Linux), plain TCP clients are accepted and served by a set
+//! of worker threads that each run an io_uring driven runtime, so that accepting, reading and
+//! writing don't need a syscall (and an epoll wakeup) each. The workers share the listener that
+//! was bound by the [`BaseListener`], and they use the same connection handling (and shutdown)
+//! as the epoll based listeners. If the kernel doesn't support io_uring, we fall back to
+//! the epoll based listener
+
+use {
+    super::{
+        governor::Governor, listener::BaseListener, reject_client, run_client, screen_peer,
+        set_keepalive, BufferedSocketStream, ConnectionOptions, NetBackoff,
+    },
+    crate::{
+        auth::AuthProvider, corestore::Corestore, protocol::interface::ProtocolSpec, IoResult,
+    },
+    core::{
+        future::Future,
+        mem,
+        pin::Pin,
+        task::{ready, Context, Poll},
+    },
+    std::{
+        io::{Error as IoError, ErrorKind},
+        net::{SocketAddr, TcpListener as StdTcpListener},
+        os::unix::io::AsFd,
+        rc::Rc,
+        sync::Arc,
+        thread,
+    },
+    tokio::{
+        io::{AsyncRead, AsyncWrite, ReadBuf},
+        sync::{broadcast, mpsc, watch},
+        task,
+    },
+    tokio_uring::{
+        net::{TcpListener, TcpStream},
+        BufResult,
+    },
+};
+
+/// The number of bytes that we read with a single operation
+const READ_CAP: usize = 8192;
+
+/// Returns true if the kernel lets us set up an io_uring
+pub fn is_supported() -> bool {
+    match io_uring::IoUring::new(2) {
+        Ok(_) => true,
+        Err(e) => {
+            log::warn!("io_uring is not supported on this system ({e}). Falling back to epoll");
+            false
+        }
+    }
+}
+
+/// Serve the clients on the listener of the `base` with io_uring workers (one for every
+/// available core). This only returns if the workers couldn't be started, or once they've
+/// shut down
+pub async fn run<P: ProtocolSpec + 'static>(base: &BaseListener) -> IoResult<()> {
+    let worker_count = thread::available_parallelism().map_or(1, |count| count.get());
+    // the workers stop accepting clients once this is dropped (just like the epoll based
+    // listeners stop accepting once their run loop is dropped)
+    let (stop_tx, stop_rx) = watch::channel(());
+    let mut workers = Vec::with_capacity(worker_count);
+    for _ in 0..worker_count {
+        let listener = StdTcpListener::from(base.listener.as_fd().try_clone_to_owned()?);
+        let worker = Worker {
+            terminate_tx: base.terminate_tx.clone(),
+            db: base.db.clone(),
+            auth: base.auth.clone(),
+            governor: base.governor.clone(),
+            options: base.options,
+            signal: base.signal.subscribe(),
+            stop: stop_rx.clone(),
+        };
+        let handle = thread::Builder::new()
+            .name("server-uring".into())
+            .spawn(move || tokio_uring::start(worker.run::<P>(listener)))?;
+        workers.push(handle);
+    }
+    log::info!("Serving TCP clients with io_uring on {worker_count} threads");
+    let joined = task::spawn_blocking(move || {
+        for worker in workers {
+            let _ = worker.join();
+        }
+    });
+    let ret = joined.await.map_err(|e| IoError::new(ErrorKind::Other, e));
+    drop(stop_tx);
+    ret
+}
+
+/// A worker thread that accepts and serves clients
+struct Worker {
+    terminate_tx: mpsc::Sender<()>,
+    db: Corestore,
+    auth: AuthProvider,
+    governor: Arc<Governor>,
+    options: ConnectionOptions,
+    signal: broadcast::Receiver<()>,
+    stop: watch::Receiver<()>,
+}
+
+impl Worker {
+    /// Accept an incoming connection. `None` is returned once we're asked to stop
+    async fn accept(
+        &mut self,
+        listener: &TcpListener,
+    ) -> Option<IoResult<(TcpStream, SocketAddr)>> {
+        let backoff = NetBackoff::new();
+        loop {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = self.stop.changed() => return None,
+            };
+            match accepted {
+                Ok(accepted) => return Some(Ok(accepted)),
+                Err(e) => {
+                    if backoff.should_disconnect() {
+                        return Some(Err(e));
+                    }
+                }
+            }
+            backoff.spin().await;
+        }
+    }
+    async fn run<P: ProtocolSpec + 'static>(mut self, listener: StdTcpListener) {
+        let listener = TcpListener::from_std(listener);
+        // every client holds a clone of this, so we know when all of them are done
+        let (done_tx, mut done_rx) = mpsc::channel::<()>(1);
+        while let Some(accepted) = self.accept(&listener).await {
+            // SECURITY: see the epoll based listener for why we don't log (or bail on) errors
+            let (stream, peer) = skip_loop_err!(accepted);
+            if !screen_peer(&self.db, peer.ip()) {
+                // dropping the stream disconnects it
+                continue;
+            }
+            set_keepalive(&stream, self.options.keepalive);
+            let stream = RingStream::new(stream);
+            let client = match self.governor.try_admit() {
+                Some(client) => client,
+                None => {
+                    self.db.get_stats().client_rejected();
+                    tokio_uring::spawn(reject_client::<RingStream, P>(stream));
+                    continue;
+                }
+            };
+            let run = run_client::<RingStream, P>(
+                self.db.clone(),
+                stream,
+                self.auth.for_client(Some(peer.ip())),
+                client,
+                self.options,
+                self.signal.resubscribe(),
+                done_tx.clone(),
+            );
+            tokio_uring::spawn(async move {
+                if let Err(e) = run.await {
+                    log::error!("Error: {}", e);
+                }
+            });
+        }
+        // the clients exit once they're signalled to shut down
+        drop(done_tx);
+        let _ = done_rx.recv().await;
+        // let the listener know that we're done only after we've let go of the database
+        let Self {
+            terminate_tx, db, ..
+        } = self;
+        drop(db);
+        drop(terminate_tx);
+    }
+}
+
+type Op = Pin<Box<dyn Future<Output = BufResult<usize, Vec<u8>>>>>;
+
+/// A TCP stream that is read from and written to with io_uring. Since io_uring needs to own
+/// the buffers while an operation is in flight, data is copied in and out of buffers that are
+/// owned by the stream (and operations that are in flight survive their futures being dropped)
+pub struct RingStream {
+    stream: Rc<TcpStream>,
+    /// the read that is in flight
+    read: Option<Op>,
+    /// the data that was read (what comes before `read_pos` was already handed out)
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    /// the write that is in flight
+    write: Option<Op>,
+    /// a buffer from a completed write that we reuse for the next one
+    write_buf: Vec<u8>,
+}
+
+impl RingStream {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream: Rc::new(stream),
+            read: None,
+            read_buf: Vec::new(),
+            read_pos: 0,
+            write: None,
+            write_buf: Vec::new(),
+        }
+    }
+    fn submit_read(&self, buf: Vec<u8>) -> Op {
+        let stream = self.stream.clone();
+        Box::pin(async move { stream.read(buf).await })
+    }
+    fn submit_write(&self, buf: Vec<u8>) -> Op {
+        let stream = self.stream.clone();
+        Box::pin(async move { stream.write(buf).await })
+    }
+    /// Wait for the write in flight (if any) to complete, submitting the rest of it if it
+    /// was only partially written
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        while let Some(write) = self.write.as_mut() {
+            let (ret, mut buf) = ready!(write.as_mut().poll(cx));
+            self.write = None;
+            let written = ret?;
+            if written == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            if written < buf.len() {
+                buf.drain(..written);
+                self.write = Some(self.submit_write(buf));
+            } else {
+                buf.clear();
+                self.write_buf = buf;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for RingStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        out: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let this = &mut *self;
+        if this.read_pos == this.read_buf.len() {
+            if this.read.is_none() {
+                let mut buf = mem::take(&mut this.read_buf);
+                buf.clear();
+                buf.reserve(READ_CAP.max(out.remaining()));
+                this.read = Some(this.submit_read(buf));
+            }
+            let read = this.read.as_mut().unwrap();
+            let (ret, buf) = ready!(read.as_mut().poll(cx));
+            this.read = None;
+            this.read_buf = buf;
+            this.read_pos = 0;
+            // nothing was read if this is EOF (or an error)
+            ret?;
+        }
+        let available = &this.read_buf[this.read_pos..];
+        let len = available.len().min(out.remaining());
+        out.put_slice(&available[..len]);
+        this.read_pos += len;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for RingStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        data: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        let this = &mut *self;
+        ready!(this.poll_drain(cx))?;
+        let mut buf = mem::take(&mut this.write_buf);
+        buf.extend_from_slice(data);
+        this.write = Some(this.submit_write(buf));
+        // submit it right away. errors (if it completes now) are returned here, and otherwise
+        // by the next write or flush
+        if let Poll::Ready(Err(e)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(data.len()))
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.poll_drain(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        ready!(self.poll_drain(cx))?;
+        Poll::Ready(self.stream.shutdown(std::net::Shutdown::Write))
+    }
+}
+
+impl BufferedSocketStream for RingStream {}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        tokio::io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test]
+    fn ring_stream_roundtrip() {
+        if !is_supported() {
+            return;
+        }
+        // large enough to need several reads (and possibly partial writes)
+        let payload: Vec<u8> = (0..256 * 1024).map(|i| i as u8).collect();
+        let expected = payload.to_ascii_uppercase();
+        tokio_uring::start(async move {
+            let listener = StdTcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let listener = TcpListener::from_std(listener);
+            let len = payload.len();
+            let client = tokio_uring::spawn(async move {
+                let mut stream = RingStream::new(TcpStream::connect(addr).await.unwrap());
+                stream.write_all(&payload).await.unwrap();
+                stream.flush().await.unwrap();
+                let mut echoed = vec![0; len];
+                stream.read_exact(&mut echoed).await.unwrap();
+                echoed
+            });
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = RingStream::new(stream);
+            let mut received = vec![0; len];
+            stream.read_exact(&mut received).await.unwrap();
+            stream
+                .write_all(&received.to_ascii_uppercase())
+                .await
+                .unwrap();
+            stream.flush().await.unwrap();
+            assert_eq!(client.await.unwrap(), expected);
+        });
+    }
+}
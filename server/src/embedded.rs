@@ -0,0 +1,291 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Embedded mode
+//!
+//! This module lets applications use Skytable in-process, without running the network server.
+//! The data is stored in exactly the same format that `skyd` uses, so a directory written by
+//! an application can be served by `skyd` later (and vice versa), just not at the same time.
+//!
+//! The embedded API works on the default table (`default:default`) which is a binary
+//! key/value table. Writes are kept in memory until [`Database::flush`] is called or the
+//! database is dropped.
+//!
+//! Every database is in a directory of its own (which holds the same files that `skyd` keeps
+//! in its working directory), so a process can have several databases open at once. A
+//! directory can only be used by one database (or server) at a time though.
+//!
+//! ```
+//! use skyd::embedded::Database;
+//!
+//! let path = std::env::temp_dir().join(format!("skyd-embedded-doc-{}", std::process::id()));
+//! let db = Database::open(&path)?;
+//! db.set("hello", "world");
+//! assert_eq!(db.get("hello").as_deref(), Some(&b"world"[..]));
+//! // also done when the database is dropped
+//! drop(db);
+//! let db = Database::open(&path)?;
+//! assert_eq!(db.get("hello").as_deref(), Some(&b"world"[..]));
+//! # drop(db);
+//! # std::fs::remove_dir_all(&path)?;
+//! # Ok::<(), skyd::embedded::Error>(())
+//! ```
+
+use {
+    crate::{
+        corestore::{table::DataModel, Corestore, SharedSlice},
+        diskstore::flock::FileLock,
+        kvengine::KVEStandard,
+        registry,
+        storage::v1::{
+            error::StorageEngineError,
+            flush,
+            interface::{self, DataDir},
+            sengine::SnapshotEngine,
+        },
+        PID_FILE_PATH,
+    },
+    core::fmt,
+    std::{
+        error, fs,
+        io::{Error as IoError, ErrorKind},
+        path::Path,
+        process,
+        sync::Arc,
+    },
+};
+
+/// The result of an operation on an embedded database
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug)]
+/// Errors returned by the embedded API
+pub enum Error {
+    /// An I/O error
+    Io(IoError),
+    /// The data directory couldn't be read (the message says why)
+    Storage(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::Storage(e) => write!(f, "storage error: {e}"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<IoError> for Error {
+    fn from(e: IoError) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<StorageEngineError> for Error {
+    fn from(e: StorageEngineError) -> Self {
+        Self::Storage(e.to_string())
+    }
+}
+
+/// A Skytable database opened in-process
+///
+/// The handle can be shared across threads (wrap it in an [`Arc`]); all the operations take
+/// `&self`. See the [module level docs](self) for how the data is stored
+pub struct Database {
+    db: Corestore,
+    /// where the data is
+    dir: DataDir,
+    /// the lock on the data directory (only taken out on drop)
+    pid_file: Option<FileLock>,
+}
+
+impl Database {
+    /// Open the database in the given directory, creating it if it doesn't exist. This fails
+    /// if the directory is in use by another database (in this process or in another one) or
+    /// by `skyd`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        fs::create_dir_all(path)?;
+        // the storage engine's paths are strings
+        let dir = match path.to_str() {
+            Some(path) => DataDir::new(path),
+            None => {
+                return Err(Error::Io(IoError::new(
+                    ErrorKind::InvalidInput,
+                    "the path to the database isn't valid UTF-8",
+                )))
+            }
+        };
+        // same as the server: lock the directory so that no one else writes to it
+        let mut pid_file = FileLock::lock(dir.resolve(PID_FILE_PATH))?;
+        pid_file.write(process::id().to_string().as_bytes())?;
+        let db = Corestore::init_with_snapcfg(&dir, Arc::new(SnapshotEngine::new_disabled()))?;
+        Ok(Self {
+            db,
+            dir,
+            pid_file: Some(pid_file),
+        })
+    }
+    fn kv(&self) -> &KVEStandard {
+        match self.db.get_ctable_ref().map(|tbl| tbl.get_model_ref()) {
+            Some(DataModel::KV(kve)) => kve,
+            _ => unreachable!("the default table is always a key/value table"),
+        }
+    }
+    /// Returns the value of the given key
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<Vec<u8>> {
+        // the default table is binary, so there's no encoding to check
+        self.kv()
+            .get_cloned_unchecked(key)
+            .map(|val| val.as_slice().to_owned())
+    }
+    /// Set the value of the given key, replacing the older value (if any)
+    pub fn set(&self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) {
+        self.kv().upsert_unchecked(
+            SharedSlice::new(key.as_ref()),
+            SharedSlice::new(value.as_ref()),
+        )
+    }
+    /// Remove the given key. Returns true if the key existed
+    pub fn delete(&self, key: impl AsRef<[u8]>) -> bool {
+        self.kv().remove_unchecked(key)
+    }
+    /// Returns atmost `limit` key/value pairs starting at `cursor`, along with the cursor for
+    /// the next call. Start with a cursor of `0`; the scan is complete once the returned cursor
+    /// is `0` again. Like `LSKEYS`, keys that are added or removed during a scan may or may not
+    /// be returned
+    pub fn scan(&self, cursor: u64, limit: usize) -> (Vec<(Vec<u8>, Vec<u8>)>, u64) {
+        let kv = self.kv();
+        let (keys, next) = kv.get_inner_ref().scan_keys(cursor, limit, |_| true);
+        let pairs = keys
+            .into_iter()
+            .filter_map(|key| {
+                // the key may have been removed since
+                kv.get_cloned_unchecked(&key)
+                    .map(|val| (key.as_slice().to_owned(), val.as_slice().to_owned()))
+            })
+            .collect();
+        (pairs, next)
+    }
+    /// Write everything to disk
+    pub fn flush(&self) -> Result<()> {
+        // the same lock that BGSAVE holds. There's no append-only log to rotate and no
+        // queries run here, so (unlike BGSAVE) we don't have to stop any writes
+        let _flush_lock = registry::lock_flush_state();
+        flush::flush_full(self.dir.clone(), self.db.get_store())?;
+        self.db.get_stats().record_save();
+        Ok(())
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("Failed to flush the database: {e}");
+        }
+        if let Some(mut pid_file) = self.pid_file.take() {
+            if let Err(e) = interface::cleanup_tree(&self.dir, self.db.get_store()) {
+                log::error!("Failed to compact the tree: {e}");
+            }
+            if let Err(e) = pid_file.unlock() {
+                log::error!("Failed to unlock the database: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Database, Error},
+        std::{env, fs, path::PathBuf},
+    };
+
+    fn path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("skyd-embedded-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn open_set_get_close_reopen() {
+        let path = path("reopen");
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.get("x"), None);
+        db.set("x", "1");
+        db.set("y", "2");
+        db.set("y", "3");
+        db.set("z", "4");
+        assert!(db.delete("z"));
+        assert!(!db.delete("z"));
+        assert_eq!(db.get("y").as_deref(), Some(&b"3"[..]));
+        drop(db);
+        let db = Database::open(&path).unwrap();
+        let (mut pairs, next) = db.scan(0, 10);
+        pairs.sort();
+        assert_eq!(next, 0);
+        assert_eq!(
+            pairs,
+            [
+                (b"x".to_vec(), b"1".to_vec()),
+                (b"y".to_vec(), b"3".to_vec())
+            ]
+        );
+        // a flush is the same as a close as far as the data is concerned
+        db.set("x", "5");
+        db.flush().unwrap();
+        drop(db);
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.get("x").as_deref(), Some(&b"5"[..]));
+        drop(db);
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn databases_are_independent() {
+        let cwd = env::current_dir().unwrap();
+        let (first_path, second_path) = (path("first"), path("second"));
+        let first = Database::open(&first_path).unwrap();
+        let second = Database::open(&second_path).unwrap();
+        assert_eq!(env::current_dir().unwrap(), cwd);
+        first.set("key", "first");
+        second.set("key", "second");
+        // a directory can only be used by one database at a time
+        assert!(matches!(Database::open(&first_path), Err(Error::Io(_))));
+        drop(first);
+        drop(second);
+        let first = Database::open(&first_path).unwrap();
+        assert_eq!(first.get("key").as_deref(), Some(&b"first"[..]));
+        let second = Database::open(&second_path).unwrap();
+        assert_eq!(second.get("key").as_deref(), Some(&b"second"[..]));
+        drop((first, second));
+        fs::remove_dir_all(first_path).unwrap();
+        fs::remove_dir_all(second_path).unwrap();
+    }
+}
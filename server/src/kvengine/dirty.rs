@@ -0,0 +1,145 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Dirty keys
+//!
+//! Every engine keeps track of the keys that were changed since its table was last written
+//! out in full, so that a save only has to write those keys (see
+//! [`delta`](crate::storage::v1::delta)). Once too many keys are dirty (or the whole table
+//! was cleared), we stop tracking individual keys and just rewrite the entire table on the
+//! next save
+
+use {
+    crate::{
+        corestore::{htable::Coremap, SharedSlice, ToSharedSlice},
+        storage::v1::header::PayloadId,
+    },
+    core::sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    parking_lot::Mutex,
+};
+
+/// Once this many keys are dirty, we give up on tracking them and rewrite the entire table
+const MAX_DIRTY_KEYS: usize = 1 << 20;
+
+#[derive(Debug)]
+pub struct DirtyKeys {
+    /// the keys that were changed since the last full save
+    keys: Coremap<SharedSlice, ()>,
+    /// the (approximate) number of keys in `keys`
+    count: AtomicUsize,
+    /// the entire table needs to be rewritten (and `keys` isn't maintained)
+    all: AtomicBool,
+    /// the payload of the last full save that's on disk, if we know it
+    base: Mutex<Option<PayloadId>>,
+}
+
+impl DirtyKeys {
+    pub fn new() -> Self {
+        Self {
+            keys: Coremap::new(),
+            count: AtomicUsize::new(0),
+            all: AtomicBool::new(false),
+            base: Mutex::new(None),
+        }
+    }
+    /// Mark a key as changed
+    pub fn mark<K: ToSharedSlice + ?Sized>(&self, key: &K) {
+        if self.all.load(Ordering::Acquire) || self.keys.contains_key(key.as_ref()) {
+            return;
+        }
+        if self.keys.true_if_insert(key.to_shared_slice(), ())
+            && self.count.fetch_add(1, Ordering::Relaxed) >= MAX_DIRTY_KEYS
+        {
+            self.mark_all();
+        }
+    }
+    /// Mark every key as changed, so that the table is rewritten in full on the next save
+    pub fn mark_all(&self) {
+        if !self.all.swap(true, Ordering::AcqRel) {
+            self.keys.clear();
+            self.count.store(0, Ordering::Relaxed);
+        }
+    }
+    /// Returns the keys that were changed since the last full save. `None` is returned if
+    /// the entire table needs to be rewritten
+    pub fn keys(&self) -> Option<Vec<SharedSlice>> {
+        if self.all.load(Ordering::Acquire) {
+            None
+        } else {
+            Some(self.keys.snapshot_with(|key, _| key.clone()))
+        }
+    }
+    /// Forget all the changed keys. This is done right before a full save; anything that
+    /// changes after this is tracked again
+    pub fn reset(&self) {
+        let _ = self.keys.take();
+        self.count.store(0, Ordering::Relaxed);
+        self.all.store(false, Ordering::Release);
+    }
+    /// Returns the payload of the last full save, if we know it
+    pub fn base(&self) -> Option<PayloadId> {
+        *self.base.lock()
+    }
+    pub fn set_base(&self, base: Option<PayloadId>) {
+        *self.base.lock() = base;
+    }
+    /// Move the changed keys out (for a save of a frozen copy of the table), leaving this set
+    /// with the same base and no changed keys. Nothing should change the table while this
+    /// runs. Once the copy has been saved, the outcome has to be [merged](Self::merge) back
+    pub fn take(&self) -> Self {
+        let all = self.all.swap(false, Ordering::AcqRel);
+        Self {
+            keys: self.keys.take(),
+            count: AtomicUsize::new(self.count.swap(0, Ordering::Relaxed)),
+            all: AtomicBool::new(all),
+            base: Mutex::new(self.base()),
+        }
+    }
+    /// Merge back the state of a set returned by [`take`](Self::take) after its save. If the
+    /// save wrote a full save, only the keys that changed since the `take` are left.
+    /// Otherwise (it wrote a delta or it failed), the keys that it had are changed again
+    pub fn merge(&self, saved: &Self) {
+        self.set_base(saved.base());
+        match saved.keys() {
+            Some(keys) => keys.iter().for_each(|key| self.mark(key)),
+            None => self.mark_all(),
+        }
+    }
+    /// Restore the state that was on disk when the table was loaded: the full save `base`
+    /// and the `keys` that were changed after it
+    pub fn restore(&self, base: Option<PayloadId>, keys: impl IntoIterator<Item = SharedSlice>) {
+        self.set_base(base);
+        for key in keys {
+            self.mark(&key);
+        }
+    }
+}
+
+impl Default for DirtyKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,173 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    super::{notify::KeyEvent, now_millis, KVEValue, KVEngine},
+    crate::{
+        config::EvictionPolicy,
+        corestore::{htable::Coremap, SharedSlice, ToSharedSlice},
+        registry,
+    },
+    core::{
+        mem,
+        sync::atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// The approximate overhead of a single entry in the map (key and value headers, hash
+/// metadata and so on)
+pub const ENTRY_OVERHEAD: usize = 2 * mem::size_of::<SharedSlice>() + mem::size_of::<u64>();
+
+const ORD_RLX: Ordering = Ordering::Relaxed;
+
+/// Returns the approximate number of bytes of memory used by an entry: the key, the value and
+/// the overhead of the entry itself. Everything that needs the memory used by the data
+/// (eviction, `SYS MEMORY` and so on) measures it with this
+pub fn footprint<T: KVEValue>(key: &[u8], value: &T) -> usize {
+    key.len() + value.approx_size() + ENTRY_OVERHEAD
+}
+
+/// Returns the approximate number of bytes stored for an entry, whether they're in memory or
+/// spilled to disk. Quotas are measured with this, since a spilled value still takes up space
+pub fn stored_size<T: KVEValue>(key: &[u8], value: &T) -> usize {
+    footprint(key, value) + value.spilled_size()
+}
+
+// eviction impls
+impl<T> KVEngine<T> {
+    /// Returns a reference to the access map
+    pub fn get_access_ref(&self) -> &Coremap<SharedSlice, AtomicU64> {
+        &self.access
+    }
+    /// Record an access for the given key if the active eviction policy needs it
+    pub fn touch<K: ToSharedSlice + ?Sized>(&self, key: &K) {
+        let policy = registry::get_eviction_policy();
+        if !policy.needs_access_tracking() {
+            return;
+        }
+        let updated = match self.access.get(key.as_ref()) {
+            Some(meta) => {
+                match policy {
+                    EvictionPolicy::Lru => meta.store(now_millis(), ORD_RLX),
+                    _ => {
+                        let _ = meta.fetch_add(1, ORD_RLX);
+                    }
+                }
+                true
+            }
+            None => false,
+        };
+        if !updated {
+            // the ref is dropped, so we won't deadlock on the shard
+            let initial = match policy {
+                EvictionPolicy::Lru => now_millis(),
+                _ => 1,
+            };
+            let _ = self
+                .access
+                .true_if_insert(key.to_shared_slice(), AtomicU64::new(initial));
+        }
+    }
+    /// Remove the access metadata for the given key
+    pub(super) fn forget(&self, key: &[u8]) {
+        if registry::get_eviction_policy().needs_access_tracking() {
+            let _ = self.access.remove(key);
+        }
+    }
+}
+
+impl<T: KVEValue> KVEngine<T> {
+    /// Returns the approximate number of bytes used by the keys and values in this engine
+    pub fn approx_memory_usage(&self) -> usize {
+        self.data
+            .iter()
+            .map(|kv| footprint(kv.key(), kv.value()))
+            .sum()
+    }
+    /// Returns an estimate of [`KVEngine::approx_memory_usage`] from a random sample of atmost
+    /// `samples` entries (picked using `seed`), so that not every entry has to be visited.
+    /// Engines with no more than `samples` entries are measured exactly
+    pub fn estimate_memory_usage(&self, samples: usize, seed: u64) -> usize {
+        let len = self.data.len();
+        if len <= samples {
+            return self.approx_memory_usage();
+        }
+        let sample = self
+            .data
+            .random_sample(samples, seed, |key, value| footprint(key, value));
+        if sample.is_empty() {
+            return 0;
+        }
+        let sampled: usize = sample.iter().sum();
+        (sampled as u128 * len as u128 / sample.len() as u128) as usize
+    }
+    /// Returns the approximate number of bytes stored in this engine, in memory or on disk (see
+    /// [`stored_size`])
+    pub fn approx_stored_size(&self) -> usize {
+        self.data
+            .iter()
+            .map(|kv| stored_size(kv.key(), kv.value()))
+            .sum()
+    }
+    /// Returns the approximate number of bytes used by the given key (see [`footprint`]) or
+    /// `None` if the key doesn't exist. This doesn't count as an access of the key
+    pub fn memory_usage_unchecked(&self, key: &[u8]) -> Option<usize> {
+        self.purge_if_expired(key);
+        self.data.get(key).map(|kv| footprint(kv.key(), kv.value()))
+    }
+    /// Evict a single key chosen by `policy` from a random sample of atmost `samples` keys
+    /// (picked using `seed`). Returns the approximate number of bytes freed (zero if nothing
+    /// was evicted)
+    pub fn evict_one(&self, policy: EvictionPolicy, samples: usize, seed: u64) -> usize {
+        let candidates = self.data.sample_keys(samples, seed);
+        let victim = match policy {
+            EvictionPolicy::NoEviction => None,
+            EvictionPolicy::Random => candidates.into_iter().next(),
+            EvictionPolicy::Lru | EvictionPolicy::Lfu => {
+                // keys without any metadata were never touched since we started tracking,
+                // so they're the best candidates
+                candidates.into_iter().min_by_key(|key| {
+                    self.access
+                        .get(key.as_slice())
+                        .map(|meta| meta.load(ORD_RLX))
+                        .unwrap_or(0)
+                })
+            }
+        };
+        match victim {
+            Some(key) => {
+                let _ = self.access.remove(key.as_slice());
+                self.remove_entry(key.as_slice())
+                    .map(|(k, v)| {
+                        self.notify(KeyEvent::Delete, &k);
+                        footprint(&k, &v)
+                    })
+                    .unwrap_or(0)
+            }
+            None => 0,
+        }
+    }
+}
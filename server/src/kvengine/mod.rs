@@ -26,22 +26,53 @@
 
 #![allow(dead_code)] // TODO(@ohsayan): Clean this up later
 
+pub mod dirty;
 pub mod encoding;
+mod eviction;
+pub mod notify;
 #[cfg(test)]
 mod tests;
 
 use {
-    self::encoding::{ENCODING_LUT, ENCODING_LUT_PAIR},
+    self::{
+        dirty::DirtyKeys,
+        encoding::{ENCODING_LUT, ENCODING_LUT_PAIR},
+        notify::{KeyEvent, KeyVersions, Notifier},
+    },
     crate::{
-        corestore::{booltable::BoolTable, htable::Coremap, map::bref::Ref, SharedSlice},
+        corestore::{
+            booltable::BoolTable,
+            htable::Coremap,
+            map::{
+                bref::{Entry, Ref},
+                MapStats,
+            },
+            spill,
+            zset::{Score, SortedSet},
+            SharedSlice,
+        },
         util::compiler,
     },
+    core::sync::atomic::{AtomicU64, Ordering},
     parking_lot::RwLock,
+    std::{
+        collections::{HashMap, HashSet, VecDeque},
+        future::Future,
+        mem,
+        time::{SystemTime, UNIX_EPOCH},
+    },
 };
 
 pub type KVEStandard = KVEngine<SharedSlice>;
 pub type KVEListmap = KVEngine<LockedVec>;
-pub type LockedVec = RwLock<Vec<SharedSlice>>;
+/// A list. It's a deque, so that elements can be pushed and popped at either end in O(1)
+pub type LockedVec = RwLock<VecDeque<SharedSlice>>;
+pub type KVESetmap = KVEngine<LockedSet>;
+pub type LockedSet = RwLock<HashSet<SharedSlice>>;
+pub type KVEHashmap = KVEngine<LockedHash>;
+pub type LockedHash = RwLock<HashMap<SharedSlice, SharedSlice>>;
+pub type KVEZSetmap = KVEngine<LockedZSet>;
+pub type LockedZSet = RwLock<SortedSet>;
 pub type SingleEncoder = fn(&[u8]) -> bool;
 pub type DoubleEncoder = fn(&[u8], &[u8]) -> bool;
 type EntryRef<'a, T> = Ref<'a, SharedSlice, T>;
@@ -53,6 +84,22 @@ const TSYMBOL_LUT: BoolTable<u8> = BoolTable::new(b'+', b'?');
 
 pub trait KVEValue {
     fn verify_encoding(&self, e_v: bool) -> EncodingResult<()>;
+    /// Returns the approximate number of bytes of memory used by this value
+    fn approx_size(&self) -> usize;
+    /// Returns the number of bytes of this value that were spilled to disk (see [`spill`])
+    fn spilled_size(&self) -> usize {
+        0
+    }
+    /// Returns a point-in-time copy of this value
+    fn snapshot(&self) -> Self;
+    /// Returns this value, moved to disk if it's large enough to be spilled (see
+    /// [`spill::offload`]). Only blobs are ever spilled
+    fn offload(self) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
 }
 
 impl KVEValue for SharedSlice {
@@ -63,6 +110,27 @@ impl KVEValue for SharedSlice {
             Err(())
         }
     }
+    fn approx_size(&self) -> usize {
+        // a spilled value doesn't take up any memory of its own
+        if self.is_mapped() {
+            0
+        } else {
+            self.len()
+        }
+    }
+    fn spilled_size(&self) -> usize {
+        if self.is_mapped() {
+            self.len()
+        } else {
+            0
+        }
+    }
+    fn snapshot(&self) -> Self {
+        self.clone()
+    }
+    fn offload(self) -> Self {
+        spill::offload(self)
+    }
 }
 
 impl KVEValue for LockedVec {
@@ -74,11 +142,133 @@ impl KVEValue for LockedVec {
             Err(())
         }
     }
+    fn approx_size(&self) -> usize {
+        self.read()
+            .iter()
+            .map(|v| v.len() + mem::size_of::<SharedSlice>())
+            .sum()
+    }
+    fn snapshot(&self) -> Self {
+        RwLock::new(self.read().clone())
+    }
+}
+
+impl KVEValue for LockedSet {
+    fn verify_encoding(&self, e_v: bool) -> EncodingResult<()> {
+        let func = ENCODING_LUT[e_v];
+        if self.read().iter().all(|v| func(v)) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+    fn approx_size(&self) -> usize {
+        self.read()
+            .iter()
+            .map(|v| v.len() + mem::size_of::<SharedSlice>())
+            .sum()
+    }
+    fn snapshot(&self) -> Self {
+        RwLock::new(self.read().clone())
+    }
+}
+
+impl KVEValue for LockedHash {
+    fn verify_encoding(&self, e_v: bool) -> EncodingResult<()> {
+        let func = ENCODING_LUT[e_v];
+        if self.read().iter().all(|(f, v)| func(f) && func(v)) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+    fn approx_size(&self) -> usize {
+        self.read()
+            .iter()
+            .map(|(f, v)| f.len() + v.len() + 2 * mem::size_of::<SharedSlice>())
+            .sum()
+    }
+    fn snapshot(&self) -> Self {
+        RwLock::new(self.read().clone())
+    }
+}
+
+impl KVEValue for LockedZSet {
+    fn verify_encoding(&self, e_v: bool) -> EncodingResult<()> {
+        let func = ENCODING_LUT[e_v];
+        if self.read().iter().all(|(member, _)| func(member)) {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+    fn approx_size(&self) -> usize {
+        self.read()
+            .iter()
+            .map(|(member, score)| {
+                // the member is held twice: once in the score map and once in the ordered set
+                member.len() + 2 * (mem::size_of::<SharedSlice>() + mem::size_of_val(&score))
+            })
+            .sum()
+    }
+    fn snapshot(&self) -> Self {
+        RwLock::new(self.read().clone())
+    }
+}
+
+/// Returns the current time as milliseconds since the UNIX epoch
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+tokio::task_local! {
+    /// The time at which the write that is being replayed was originally made
+    static WRITTEN_AT: u64;
+}
+
+/// Returns the time at which the write that is being run was made (as milliseconds since the
+/// UNIX epoch). This is the current time, unless the write is being replayed (see
+/// [`replay_at`]). Anything that's relative to the time of a write (like a TTL) must use this
+/// instead of [`now_millis`]
+pub fn write_time() -> u64 {
+    WRITTEN_AT
+        .try_with(|at| *at)
+        .unwrap_or_else(|_| now_millis())
+}
+
+/// Run `query` as a replay of a write that was made at `at` (see [`write_time`])
+pub async fn replay_at<F: Future>(at: u64, query: F) -> F::Output {
+    WRITTEN_AT.scope(at, query).await
+}
+
+/// The data that was moved out of a table by [`KVEngine::detach_table`]. Dropping this
+/// frees the data
+pub struct DetachedTable<T> {
+    _data: Coremap<SharedSlice, T>,
+    _expiry: Coremap<SharedSlice, u64>,
+    _access: Coremap<SharedSlice, AtomicU64>,
 }
 
 #[derive(Debug)]
 pub struct KVEngine<T> {
     data: Coremap<SharedSlice, T>,
+    /// Expiry deadlines (milliseconds since the UNIX epoch) for the keys that have a TTL
+    expiry: Coremap<SharedSlice, u64>,
+    /// Access metadata for the keys; only maintained if the eviction policy needs it
+    access: Coremap<SharedSlice, AtomicU64>,
+    /// Subscribers to the changes made to the keys
+    notifier: Notifier,
+    /// The keys that were changed since the last full save
+    dirty: DirtyKeys,
+    /// The versions of the keys, for `WATCH`
+    versions: KeyVersions,
+    /// The number of lookups that found the key
+    hits: AtomicU64,
+    /// The number of lookups that didn't find the key
+    misses: AtomicU64,
     e_k: bool,
     e_v: bool,
 }
@@ -87,7 +277,18 @@ pub struct KVEngine<T> {
 impl<T> KVEngine<T> {
     /// Create a new KVEBlob
     pub fn new(e_k: bool, e_v: bool, data: Coremap<SharedSlice, T>) -> Self {
-        Self { data, e_k, e_v }
+        Self {
+            data,
+            expiry: Coremap::new(),
+            access: Coremap::new(),
+            notifier: Notifier::new(),
+            dirty: DirtyKeys::new(),
+            versions: KeyVersions::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            e_k,
+            e_v,
+        }
     }
     /// Create a new empty KVEBlob
     pub fn init(e_k: bool, e_v: bool) -> Self {
@@ -97,9 +298,56 @@ impl<T> KVEngine<T> {
     pub fn len(&self) -> usize {
         self.data.len()
     }
-    /// Delete all the key/value pairs
+    /// Returns the keys that were changed since the last full save
+    pub fn dirty_keys(&self) -> &DirtyKeys {
+        &self.dirty
+    }
+    /// Returns the occupancy statistics of the map holding the key/value pairs
+    pub fn map_stats(&self) -> MapStats {
+        self.data.stats()
+    }
+    /// Returns the number of lookups that found the key (hits) and the number of lookups that
+    /// didn't (misses)
+    pub fn lookup_stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+    fn record_lookup(&self, found: bool) {
+        let counter = if found { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Delete all the key/value pairs, releasing the memory held by the table
     pub fn truncate_table(&self) {
-        self.data.clear()
+        self.data.clear();
+        self.data.compact();
+        self.expiry.clear();
+        self.expiry.compact();
+        self.access.clear();
+        self.access.compact();
+        self.notify(KeyEvent::Flush, &b""[..]);
+    }
+    /// Move all the key/value pairs (along with their metadata) out of the table, leaving it
+    /// empty. Unlike [`KVEngine::truncate_table`], this doesn't drop anything so it finishes
+    /// quickly irrespective of the size of the table; the returned data can then be dropped
+    /// elsewhere
+    pub fn detach_table(&self) -> DetachedTable<T> {
+        let detached = DetachedTable {
+            _data: self.data.take(),
+            _expiry: self.expiry.take(),
+            _access: self.access.take(),
+        };
+        self.notify(KeyEvent::Flush, &b""[..]);
+        detached
+    }
+    /// Replace all the key/value pairs with the ones in `other`. Any expiry and access
+    /// metadata for the older keys is discarded
+    pub fn replace_with(&self, other: Self) {
+        self.truncate_table();
+        for (key, value) in other.data {
+            self.data.upsert(key, value);
+        }
     }
     /// Returns a reference to the inner structure
     pub fn get_inner_ref(&self) -> &Coremap<SharedSlice, T> {
@@ -161,6 +409,194 @@ impl<T> KVEngine<T> {
     }
 }
 
+// expiry impls
+impl<T> KVEngine<T> {
+    /// Returns a reference to the expiry map
+    pub fn get_expiry_ref(&self) -> &Coremap<SharedSlice, u64> {
+        &self.expiry
+    }
+    /// Remove the key if its deadline has passed. Returns true if the key was purged
+    pub fn purge_if_expired(&self, key: &[u8]) -> bool {
+        let now = now_millis();
+        // most keys don't have a deadline, so look before locking the key for a write
+        let expired = self
+            .expiry
+            .get(key)
+            .map(|deadline| *deadline <= now)
+            .unwrap_or(false);
+        compiler::unlikely(expired) && self.remove_expired(key, now)
+    }
+    /// Returns true if the key has a deadline at or before `now`. The key isn't purged
+    fn is_expired_at(&self, key: &[u8], now: u64) -> bool {
+        self.expiry
+            .get(key)
+            .map(|deadline| *deadline <= now)
+            .unwrap_or(false)
+    }
+    /// Returns the number of keys that haven't expired. Keys whose deadlines have passed
+    /// aren't purged, they're just left out of the count
+    pub fn live_len(&self) -> usize {
+        let now = now_millis();
+        let expired = self.expiry.iter().filter(|kv| *kv.value() <= now).count();
+        self.data.len().saturating_sub(expired)
+    }
+    /// Returns atmost `count` keys that haven't expired
+    pub fn live_keys(&self, count: usize) -> Vec<SharedSlice> {
+        let now = now_millis();
+        self.data
+            .iter()
+            .filter(|kv| !self.is_expired_at(kv.key(), now))
+            .map(|kv| kv.key().clone())
+            .take(count)
+            .collect()
+    }
+    /// Same as [`Coremap::scan_keys`], but keys that have expired are left out
+    pub fn scan_live_keys(
+        &self,
+        cursor: u64,
+        limit: usize,
+        mut filter: impl FnMut(&SharedSlice) -> bool,
+    ) -> (Vec<SharedSlice>, u64) {
+        let now = now_millis();
+        self.data.scan_keys(cursor, limit, |key| {
+            !self.is_expired_at(key, now) && filter(key)
+        })
+    }
+    /// Remove the key if its deadline is at or before `now`. The deadline is checked (and
+    /// removed) while the key is locked, so a write that replaces the key in the meantime
+    /// (and clears or moves its deadline) is never removed along with the old value
+    fn remove_expired(&self, key: &[u8], now: u64) -> bool {
+        let removed = self.data.remove_if(key, |_, _| {
+            self.expiry
+                .true_remove_if(key, |_, deadline| *deadline <= now)
+        });
+        match removed {
+            Some((key, _)) => {
+                self.forget(&key);
+                self.notify(KeyEvent::Delete, &key);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Remove the key along with its deadline. The deadline is removed while the key is
+    /// locked, so it can't be set again in between (see [`KVEngine::expire_at_unchecked`])
+    fn remove_entry(&self, key: &[u8]) -> Option<(SharedSlice, T)> {
+        self.data.remove_if(key, |_, _| {
+            let _ = self.expiry.remove(key);
+            true
+        })
+    }
+    /// Set the expiry deadline for an existing key without encoding checks. Returns
+    /// false if the key doesn't exist
+    pub fn expire_at_unchecked(&self, key: &[u8], deadline: u64) -> bool {
+        if self.purge_if_expired(key) {
+            return false;
+        }
+        // the key stays locked until the deadline is set, so a deadline is never left behind
+        // for a key that was removed in the meantime
+        match self.data.get(key) {
+            Some(kv) => {
+                // share the key with the map
+                self.expiry.upsert(kv.key().clone(), deadline);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Returns the number of milliseconds until the key expires without encoding checks.
+    /// `None` is returned if the key doesn't exist, while `Some(None)` is returned if the
+    /// key doesn't have an expiry
+    pub fn ttl_unchecked(&self, key: &[u8]) -> Option<Option<u64>> {
+        if self.purge_if_expired(key) || !self.data.contains_key(key) {
+            None
+        } else {
+            Some(
+                self.expiry
+                    .get_cloned(key)
+                    .map(|deadline| deadline.saturating_sub(now_millis())),
+            )
+        }
+    }
+    /// Remove the expiry for the given key without encoding checks. Returns false if the
+    /// key doesn't exist
+    pub fn persist_unchecked(&self, key: &[u8]) -> bool {
+        if self.purge_if_expired(key) || !self.data.contains_key(key) {
+            false
+        } else {
+            let _ = self.expiry.remove(key);
+            true
+        }
+    }
+    /// Returns a key chosen uniformly at random using the provided random number. Expired keys
+    /// that are picked are purged and another key is picked (upto a few times)
+    pub fn random_key(&self, mut random: u64) -> Option<SharedSlice> {
+        const ATTEMPTS: usize = 8;
+        for _ in 0..ATTEMPTS {
+            let key = self.data.random_key(random)?;
+            if !self.purge_if_expired(&key) {
+                return Some(key);
+            }
+            // fibonacci hashing to get the next number
+            random = random.wrapping_mul(0x9E3779B97F4A7C15).rotate_left(29);
+        }
+        None
+    }
+    /// Returns a uniform random sample of atmost `count` keys along with the approximate sizes
+    /// of their values (see [`KVEValue::approx_size`]), using the provided random number as
+    /// the seed. Sampled keys that have expired are purged and left out of the sample
+    pub fn sample(&self, count: usize, random: u64) -> Vec<(SharedSlice, usize)>
+    where
+        T: KVEValue,
+    {
+        let mut sample = self.data.reservoir_sample(count, random, |key, value| {
+            (key.clone(), value.approx_size())
+        });
+        sample.retain(|(key, _)| !self.purge_if_expired(key));
+        sample
+    }
+    /// Set the deadlines of the keys of a table that was just loaded. The keys whose deadlines
+    /// have passed are removed instead (without notifying anyone) and returned, while the
+    /// deadlines of keys that don't exist are ignored
+    pub fn restore_expiry<'a>(
+        &self,
+        deadlines: impl IntoIterator<Item = (&'a [u8], u64)>,
+    ) -> Vec<SharedSlice> {
+        let now = now_millis();
+        let mut expired = Vec::new();
+        for (key, deadline) in deadlines {
+            if deadline <= now {
+                if let Some((key, _)) = self.data.remove(key) {
+                    expired.push(key);
+                }
+            } else if let Some(key) = self.data.get(key).map(|kv| kv.key().clone()) {
+                // share the key with the map
+                self.expiry.upsert(key, deadline);
+            }
+        }
+        expired
+    }
+    /// Remove all the keys whose deadlines have passed, returning the number of keys
+    /// that were purged
+    pub fn sweep_expired(&self) -> usize {
+        let now = now_millis();
+        let expired = self.expired_keys(now);
+        // the keys may have been written since, so each one is checked again
+        expired
+            .into_iter()
+            .filter(|key| self.remove_expired(key, now))
+            .count()
+    }
+    /// Returns the keys whose deadlines are at or before `now`
+    fn expired_keys(&self, now: u64) -> Vec<SharedSlice> {
+        self.expiry
+            .iter()
+            .filter(|kv| *kv.value() <= now)
+            .map(|kv| kv.key().clone())
+            .collect()
+    }
+}
+
 // dict impls
 impl<T: KVEValue> KVEngine<T> {
     /// Get the value of the given key
@@ -170,7 +606,13 @@ impl<T: KVEValue> KVEngine<T> {
     }
     /// Get the value of the given key without any encoding checks
     pub fn get_unchecked<Q: AsRef<[u8]>>(&self, key: Q) -> OptionRef<T> {
-        self.data.get(key.as_ref())
+        self.purge_if_expired(key.as_ref());
+        let ret = self.data.get(key.as_ref());
+        if let Some(ref kv) = ret {
+            self.touch(kv.key());
+        }
+        self.record_lookup(ret.is_some());
+        ret
     }
     /// Set the value of the given key
     pub fn set(&self, key: SharedSlice, val: T) -> EncodingResult<bool> {
@@ -178,9 +620,25 @@ impl<T: KVEValue> KVEngine<T> {
             .and_then(|_| val.verify_encoding(self.e_v))
             .map(|_| self.set_unchecked(key, val))
     }
-    /// Same as set, but doesn't check encoding. Caller must check encoding
+    /// Same as set, but doesn't check encoding. Caller must check encoding. A new key never
+    /// has a deadline, even if one was somehow left behind for an older key
     pub fn set_unchecked(&self, key: SharedSlice, val: T) -> bool {
-        self.data.true_if_insert(key, val)
+        self.purge_if_expired(&key);
+        self.touch(&key);
+        let inserted = match self.data.fresh_entry(key.clone()) {
+            Some(entry) => {
+                // clear it while the key is locked, so that we don't clear a deadline that is
+                // set for the new key
+                let _ = self.expiry.remove(key.as_slice());
+                entry.insert(val.offload());
+                true
+            }
+            None => false,
+        };
+        if inserted {
+            self.notify(KeyEvent::Set, &key);
+        }
+        inserted
     }
     /// Check if the provided key exists
     pub fn exists<Q: AsRef<[u8]>>(&self, key: Q) -> EncodingResult<bool> {
@@ -188,6 +646,7 @@ impl<T: KVEValue> KVEngine<T> {
         Ok(self.exists_unchecked(key.as_ref()))
     }
     pub fn exists_unchecked<Q: AsRef<[u8]>>(&self, key: Q) -> bool {
+        self.purge_if_expired(key.as_ref());
         self.data.contains_key(key.as_ref())
     }
     /// Update the value of an existing key. Returns `true` if updated
@@ -196,9 +655,18 @@ impl<T: KVEValue> KVEngine<T> {
         val.verify_encoding(self.e_v)?;
         Ok(self.update_unchecked(key, val))
     }
-    /// Update the value of an existing key without encoding checks
+    /// Update the value of an existing key without encoding checks. Just like
+    /// [`KVEngine::upsert_unchecked`], this clears the expiry of the key: a new value doesn't
+    /// inherit the time-to-live of the value it replaces
     pub fn update_unchecked(&self, key: SharedSlice, val: T) -> bool {
-        self.data.true_if_update(key, val)
+        self.purge_if_expired(&key);
+        let _ = self.expiry.remove(key.as_slice());
+        self.touch(&key);
+        let updated = self.data.true_if_update(key.clone(), val.offload());
+        if updated {
+            self.notify(KeyEvent::Update, &key);
+        }
+        updated
     }
     /// Update or insert an entry
     pub fn upsert(&self, key: SharedSlice, val: T) -> EncodingResult<()> {
@@ -207,9 +675,52 @@ impl<T: KVEValue> KVEngine<T> {
         self.upsert_unchecked(key, val);
         Ok(())
     }
-    /// Update or insert an entry without encoding checks
+    /// Update or insert an entry without encoding checks. This clears the expiry of the key
     pub fn upsert_unchecked(&self, key: SharedSlice, val: T) {
-        self.data.upsert(key, val)
+        let _ = self.expiry.remove(key.as_slice());
+        self.touch(&key);
+        let event = match self.data.upsert(key.clone(), val.offload()) {
+            Some(_) => KeyEvent::Update,
+            None => KeyEvent::Set,
+        };
+        self.notify(event, &key);
+    }
+    /// Make room for at least `additional` more keys, so that they can be inserted without
+    /// growing the table
+    pub fn reserve(&self, additional: usize) {
+        self.data.reserve(additional)
+    }
+    /// Update or insert an entry for a bulk load, without encoding checks. Like
+    /// [`KVEngine::upsert_unchecked`] this clears the expiry of the key, but the key isn't
+    /// marked dirty, its accesses aren't tracked and the subscribers aren't told about it (only
+    /// its version is bumped). The caller should mark every key dirty instead
+    pub fn bulk_upsert_unchecked(&self, key: SharedSlice, val: T) {
+        let _ = self.expiry.remove(key.as_slice());
+        self.data.upsert(key.clone(), val.offload());
+        self.bump_version(key.as_slice());
+    }
+    /// Update or insert an entry that expires at `deadline` (in milliseconds since the epoch)
+    pub fn upsert_with_expiry(
+        &self,
+        key: SharedSlice,
+        val: T,
+        deadline: u64,
+    ) -> EncodingResult<()> {
+        self.check_key_encoding(&key)?;
+        val.verify_encoding(self.e_v)?;
+        self.upsert_with_expiry_unchecked(key, val, deadline);
+        Ok(())
+    }
+    /// Update or insert an entry that expires at `deadline` without encoding checks. The
+    /// deadline is set before the value, so the value is never visible without its expiry
+    pub fn upsert_with_expiry_unchecked(&self, key: SharedSlice, val: T, deadline: u64) {
+        self.expiry.upsert(key.clone(), deadline);
+        self.touch(&key);
+        let event = match self.data.upsert(key.clone(), val.offload()) {
+            Some(_) => KeyEvent::Update,
+            None => KeyEvent::Set,
+        };
+        self.notify(event, &key);
     }
     /// Remove an entry
     pub fn remove<Q: AsRef<[u8]>>(&self, key: Q) -> EncodingResult<bool> {
@@ -218,7 +729,18 @@ impl<T: KVEValue> KVEngine<T> {
     }
     /// Remove an entry without encoding checks
     pub fn remove_unchecked<Q: AsRef<[u8]>>(&self, key: Q) -> bool {
-        self.data.true_if_removed(key.as_ref())
+        let expired = self.purge_if_expired(key.as_ref());
+        self.forget(key.as_ref());
+        if expired {
+            return false;
+        }
+        match self.remove_entry(key.as_ref()) {
+            Some((key, _)) => {
+                self.notify(KeyEvent::Delete, &key);
+                true
+            }
+            None => false,
+        }
     }
     /// Pop an entry
     pub fn pop<Q: AsRef<[u8]>>(&self, key: Q) -> EncodingResult<Option<T>> {
@@ -227,7 +749,42 @@ impl<T: KVEValue> KVEngine<T> {
     }
     /// Pop an entry without encoding checks
     pub fn pop_unchecked<Q: AsRef<[u8]>>(&self, key: Q) -> Option<T> {
-        self.data.remove(key.as_ref()).map(|(_, v)| v)
+        self.purge_if_expired(key.as_ref());
+        self.forget(key.as_ref());
+        self.remove_entry(key.as_ref()).map(|(key, value)| {
+            self.notify(KeyEvent::Delete, &key);
+            value
+        })
+    }
+    /// Returns a copy of the data in this table that can be serialized after writers are let
+    /// back in. Every shard is copied while it is read-locked (see [`Coremap::freeze_with`])
+    /// and the values are copied with [`KVEValue::snapshot`], which only clones the handles
+    /// of the blobs. The expiry deadlines are copied along since they're persisted with the
+    /// data, but the access metadata isn't
+    pub fn freeze(&self) -> Self {
+        Self {
+            expiry: self.expiry.freeze_with(|deadline| *deadline),
+            ..Self::new(
+                self.e_k,
+                self.e_v,
+                self.data.freeze_with(KVEValue::snapshot),
+            )
+        }
+    }
+    /// Same as [`freeze`](Self::freeze), but the copy also takes the [dirty keys](DirtyKeys::take)
+    /// so that it can be saved incrementally in place of this table. Nothing should write to
+    /// the table while the copy is made, and the copy has to be
+    /// [merged back](Self::merge_saved) once it's saved
+    pub fn freeze_for_save(&self) -> Self {
+        Self {
+            dirty: self.dirty.take(),
+            ..self.freeze()
+        }
+    }
+    /// Take back the dirty keys of a copy that was made by
+    /// [`freeze_for_save`](Self::freeze_for_save) (and has since been saved)
+    pub fn merge_saved(&self, saved: &Self) {
+        self.dirty.merge(&saved.dirty)
     }
 }
 
@@ -237,12 +794,19 @@ impl<T: Clone> KVEngine<T> {
         Ok(self.get_cloned_unchecked(key.as_ref()))
     }
     pub fn get_cloned_unchecked<Q: AsRef<[u8]>>(&self, key: Q) -> Option<T> {
-        self.data.get_cloned(key.as_ref())
+        self.purge_if_expired(key.as_ref());
+        let ret = self.data.get_cloned(key.as_ref());
+        if ret.is_some() {
+            self.touch(key.as_ref());
+        }
+        self.record_lookup(ret.is_some());
+        ret
     }
 }
 
 impl KVEStandard {
     pub fn take_snapshot_unchecked<Q: AsRef<[u8]>>(&self, key: Q) -> Option<SharedSlice> {
+        self.purge_if_expired(key.as_ref());
         self.data.get_cloned(key.as_ref())
     }
     /// Returns an encoder that checks each key and each value in turn
@@ -255,6 +819,103 @@ impl KVEStandard {
     pub fn get_double_encoder(&self) -> DoubleEncoder {
         ENCODING_LUT_PAIR[(self.e_k, self.e_v)]
     }
+    /// Atomically replace the value of the key with `new` if (and only if) the current value
+    /// is `expected`. Returns `None` if the key doesn't exist and `Some(swapped)` otherwise.
+    /// Caller must check encoding
+    pub fn compare_and_swap_unchecked(
+        &self,
+        key: SharedSlice,
+        expected: &[u8],
+        new: SharedSlice,
+    ) -> Option<bool> {
+        self.purge_if_expired(&key);
+        // spilling writes out a file, so it's done before we lock the shard
+        let new = spill::offload(new);
+        let swapped = self.data.mut_entry(key.clone()).map(|mut entry| {
+            // we hold the write lock on the shard, so nobody can sneak in between
+            let matches = entry.value().as_ref() == expected;
+            if matches {
+                entry.insert(new);
+            }
+            matches
+        });
+        if swapped == Some(true) {
+            self.notify(KeyEvent::Update, &key);
+        }
+        swapped
+    }
+    /// Atomically add `delta` to the integer stored in `key`, returning the new value. A key
+    /// that doesn't exist is treated as `0`. Caller must check encoding
+    pub fn increment_unchecked(&self, key: &[u8], delta: i64) -> Result<i64, CounterError> {
+        self.purge_if_expired(key);
+        let key = SharedSlice::new(key);
+        // we hold the write lock on the shard, so nobody can sneak in between
+        let (new, event) = match self.data.entry(key.clone()) {
+            Entry::Occupied(mut entry) => {
+                let current: i64 = core::str::from_utf8(entry.value())
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or(CounterError::NotAnInteger)?;
+                let new = current.checked_add(delta).ok_or(CounterError::Overflow)?;
+                entry.insert(SharedSlice::from(new.to_string()));
+                (new, KeyEvent::Update)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(SharedSlice::from(delta.to_string()));
+                (delta, KeyEvent::Set)
+            }
+        };
+        self.touch(&key);
+        self.notify(event, &key);
+        Ok(new)
+    }
+    /// Append `suffix` to the value of `key` (creating it if it doesn't exist), returning the
+    /// new length of the value. Caller must check encoding
+    pub fn append_unchecked(&self, key: &[u8], suffix: &[u8]) -> usize {
+        self.purge_if_expired(key);
+        let key = SharedSlice::new(key);
+        // the new value is built (and spilled, which writes out a file) before we lock the
+        // shard, and is only stored if nobody changed the value in the meantime. Otherwise
+        // we start over, so no append can be lost
+        let (len, event) = loop {
+            let current = self.data.get_cloned(&key);
+            let new = match current {
+                Some(ref current) => {
+                    let mut value = Vec::with_capacity(current.len() + suffix.len());
+                    value.extend_from_slice(current);
+                    value.extend_from_slice(suffix);
+                    SharedSlice::from(value)
+                }
+                None => SharedSlice::new(suffix),
+            };
+            let new = spill::offload(new);
+            let len = new.len();
+            let event = match (self.data.entry(key.clone()), current) {
+                (Entry::Occupied(mut entry), Some(current)) if entry.value().ptr_eq(&current) => {
+                    entry.insert(new);
+                    KeyEvent::Update
+                }
+                (Entry::Vacant(entry), None) => {
+                    entry.insert(new);
+                    KeyEvent::Set
+                }
+                _ => continue,
+            };
+            break (len, event);
+        };
+        self.touch(&key);
+        self.notify(event, &key);
+        len
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Errors that can occur when incrementing or decrementing a counter
+pub enum CounterError {
+    /// the stored value isn't a 64-bit signed integer
+    NotAnInteger,
+    /// the result doesn't fit in a 64-bit signed integer
+    Overflow,
 }
 
 // list impls
@@ -262,10 +923,11 @@ impl KVEListmap {
     #[cfg(test)]
     pub fn add_list(&self, listname: SharedSlice) -> EncodingResult<bool> {
         self.check_key_encoding(&listname)?;
-        Ok(self.data.true_if_insert(listname, LockedVec::new(vec![])))
+        Ok(self.data.true_if_insert(listname, LockedVec::default()))
     }
     pub fn list_len(&self, listname: &[u8]) -> EncodingResult<Option<usize>> {
         self.check_key_encoding(listname)?;
+        self.purge_if_expired(listname);
         Ok(self.data.get(listname).map(|list| list.read().len()))
     }
     pub fn list_cloned(
@@ -274,6 +936,7 @@ impl KVEListmap {
         count: usize,
     ) -> EncodingResult<Option<Vec<SharedSlice>>> {
         self.check_key_encoding(listname)?;
+        self.purge_if_expired(listname);
         Ok(self
             .data
             .get(listname)
@@ -281,6 +944,7 @@ impl KVEListmap {
     }
     pub fn list_cloned_full(&self, listname: &[u8]) -> EncodingResult<Option<Vec<SharedSlice>>> {
         self.check_key_encoding(listname)?;
+        self.purge_if_expired(listname);
         Ok(self
             .data
             .get(listname)
@@ -288,6 +952,272 @@ impl KVEListmap {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An operation that combines multiple sets into one
+pub enum SetOperation {
+    /// members present in any of the sets
+    Union,
+    /// members present in all of the sets
+    Intersection,
+    /// members of the first set that aren't present in any of the others
+    Difference,
+}
+
+// set impls
+impl KVESetmap {
+    /// Add the members to the set, creating the set if it doesn't exist. Returns the number
+    /// of members that weren't already present. Caller must check encoding
+    pub fn set_add_unchecked(&self, setname: &[u8], members: Vec<SharedSlice>) -> usize {
+        self.purge_if_expired(setname);
+        let (added, event, key) = loop {
+            if let Some(set) = self.data.get(setname) {
+                let mut wset = set.write();
+                let before = wset.len();
+                wset.extend(members);
+                break (wset.len() - before, KeyEvent::Update, set.key().clone());
+            }
+            // the set doesn't exist, so try to create it. If someone else created it in the
+            // meantime, we'll just add to their set
+            let key = SharedSlice::new(setname);
+            if let Some(entry) = self.data.fresh_entry(key.clone()) {
+                let set: HashSet<SharedSlice> = members.into_iter().collect();
+                let added = set.len();
+                entry.insert(LockedSet::new(set));
+                break (added, KeyEvent::Set, key);
+            }
+        };
+        self.touch(&key);
+        self.notify(event, &key);
+        added
+    }
+    /// Remove the members from the set, returning the number of members that were removed
+    /// if the set exists. Caller must check encoding
+    pub fn set_remove_unchecked<'a>(
+        &self,
+        setname: &[u8],
+        members: impl Iterator<Item = &'a [u8]>,
+    ) -> Option<usize> {
+        self.purge_if_expired(setname);
+        let removed = self.data.get(setname).map(|set| {
+            let mut wset = set.write();
+            members.filter(|member| wset.remove(*member)).count()
+        });
+        if matches!(removed, Some(count) if count != 0) {
+            self.notify(KeyEvent::Update, setname);
+        }
+        removed
+    }
+    pub fn set_len(&self, setname: &[u8]) -> EncodingResult<Option<usize>> {
+        self.check_key_encoding(setname)?;
+        self.purge_if_expired(setname);
+        Ok(self.data.get(setname).map(|set| set.read().len()))
+    }
+    pub fn set_contains(&self, setname: &[u8], member: &[u8]) -> EncodingResult<Option<bool>> {
+        self.check_key_encoding(setname)?;
+        self.purge_if_expired(setname);
+        Ok(self
+            .data
+            .get(setname)
+            .map(|set| set.read().contains(member)))
+    }
+    pub fn set_members(&self, setname: &[u8]) -> EncodingResult<Option<Vec<SharedSlice>>> {
+        self.check_key_encoding(setname)?;
+        self.purge_if_expired(setname);
+        Ok(self
+            .data
+            .get(setname)
+            .map(|set| set.read().iter().cloned().collect()))
+    }
+    /// Combine the given sets using `op`. Sets that don't exist are treated as empty sets.
+    /// Caller must check encoding
+    pub fn set_combine_unchecked<'a>(
+        &self,
+        mut setnames: impl Iterator<Item = &'a [u8]>,
+        op: SetOperation,
+    ) -> Vec<SharedSlice> {
+        let mut combined: HashSet<SharedSlice> = match setnames.next() {
+            Some(first) => {
+                self.purge_if_expired(first);
+                match self.data.get(first) {
+                    Some(set) => set.read().clone(),
+                    None => HashSet::new(),
+                }
+            }
+            None => return Vec::new(),
+        };
+        for setname in setnames {
+            self.purge_if_expired(setname);
+            match (self.data.get(setname), op) {
+                (Some(set), SetOperation::Union) => combined.extend(set.read().iter().cloned()),
+                (Some(set), SetOperation::Intersection) => {
+                    let rset = set.read();
+                    combined.retain(|member| rset.contains(member))
+                }
+                (Some(set), SetOperation::Difference) => {
+                    let rset = set.read();
+                    combined.retain(|member| !rset.contains(member))
+                }
+                (None, SetOperation::Intersection) => combined.clear(),
+                (None, _) => {}
+            }
+        }
+        combined.into_iter().collect()
+    }
+}
+
+// hash impls
+impl KVEHashmap {
+    /// Set the fields of the hash, creating the hash if it doesn't exist. Returns the number
+    /// of fields that weren't already present. Caller must check encoding
+    pub fn hash_set_unchecked(
+        &self,
+        hashname: &[u8],
+        pairs: Vec<(SharedSlice, SharedSlice)>,
+    ) -> usize {
+        self.purge_if_expired(hashname);
+        let (added, event, key) = loop {
+            if let Some(hash) = self.data.get(hashname) {
+                let mut whash = hash.write();
+                let added = pairs
+                    .into_iter()
+                    .map(|(field, value)| whash.insert(field, value))
+                    .filter(Option::is_none)
+                    .count();
+                break (added, KeyEvent::Update, hash.key().clone());
+            }
+            // the hash doesn't exist, so try to create it. If someone else created it in the
+            // meantime, we'll just set the fields in their hash
+            let key = SharedSlice::new(hashname);
+            if let Some(entry) = self.data.fresh_entry(key.clone()) {
+                let hash: HashMap<SharedSlice, SharedSlice> = pairs.into_iter().collect();
+                let added = hash.len();
+                entry.insert(LockedHash::new(hash));
+                break (added, KeyEvent::Set, key);
+            }
+        };
+        self.touch(&key);
+        self.notify(event, &key);
+        added
+    }
+    /// Remove the fields from the hash, returning the number of fields that were removed if
+    /// the hash exists. Caller must check encoding
+    pub fn hash_remove_unchecked<'a>(
+        &self,
+        hashname: &[u8],
+        fields: impl Iterator<Item = &'a [u8]>,
+    ) -> Option<usize> {
+        self.purge_if_expired(hashname);
+        let removed = self.data.get(hashname).map(|hash| {
+            let mut whash = hash.write();
+            fields
+                .filter(|field| whash.remove(*field).is_some())
+                .count()
+        });
+        if matches!(removed, Some(count) if count != 0) {
+            self.notify(KeyEvent::Update, hashname);
+        }
+        removed
+    }
+    /// Returns the value of the field, if both the hash and the field exist
+    pub fn hash_get(&self, hashname: &[u8], field: &[u8]) -> EncodingResult<Option<SharedSlice>> {
+        self.check_key_encoding(hashname)?;
+        self.purge_if_expired(hashname);
+        Ok(self
+            .data
+            .get(hashname)
+            .and_then(|hash| hash.read().get(field).cloned()))
+    }
+    /// Returns all the field/value pairs in the hash, if it exists
+    pub fn hash_get_all(
+        &self,
+        hashname: &[u8],
+    ) -> EncodingResult<Option<Vec<(SharedSlice, SharedSlice)>>> {
+        self.check_key_encoding(hashname)?;
+        self.purge_if_expired(hashname);
+        Ok(self.data.get(hashname).map(|hash| {
+            hash.read()
+                .iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()
+        }))
+    }
+}
+
+// sorted set impls
+impl KVEZSetmap {
+    /// Add the members with their scores to the sorted set, creating the sorted set if it
+    /// doesn't exist. Existing members have their scores updated. Returns the number of members
+    /// that weren't already present. Caller must check encoding
+    pub fn zset_add_unchecked(&self, zsetname: &[u8], members: Vec<(SharedSlice, Score)>) -> usize {
+        self.purge_if_expired(zsetname);
+        let (added, event, key) = loop {
+            if let Some(zset) = self.data.get(zsetname) {
+                let mut wzset = zset.write();
+                let added = members
+                    .into_iter()
+                    .filter(|(member, score)| wzset.insert(member.clone(), *score))
+                    .count();
+                break (added, KeyEvent::Update, zset.key().clone());
+            }
+            // the sorted set doesn't exist, so try to create it. If someone else created it in
+            // the meantime, we'll just add to their sorted set
+            let key = SharedSlice::new(zsetname);
+            if let Some(entry) = self.data.fresh_entry(key.clone()) {
+                let zset: SortedSet = members.into_iter().collect();
+                let added = zset.len();
+                entry.insert(LockedZSet::new(zset));
+                break (added, KeyEvent::Set, key);
+            }
+        };
+        self.touch(&key);
+        self.notify(event, &key);
+        added
+    }
+    /// Remove the members from the sorted set, returning the number of members that were
+    /// removed if the sorted set exists. Caller must check encoding
+    pub fn zset_remove_unchecked<'a>(
+        &self,
+        zsetname: &[u8],
+        members: impl Iterator<Item = &'a [u8]>,
+    ) -> Option<usize> {
+        self.purge_if_expired(zsetname);
+        let removed = self.data.get(zsetname).map(|zset| {
+            let mut wzset = zset.write();
+            members.filter(|member| wzset.remove(*member)).count()
+        });
+        if matches!(removed, Some(count) if count != 0) {
+            self.notify(KeyEvent::Update, zsetname);
+        }
+        removed
+    }
+    /// Returns the members with a score in `min..=max` (ordered by their scores) along with
+    /// their scores, if the sorted set exists
+    pub fn zset_range_by_score(
+        &self,
+        zsetname: &[u8],
+        min: Score,
+        max: Score,
+    ) -> EncodingResult<Option<Vec<(SharedSlice, Score)>>> {
+        self.check_key_encoding(zsetname)?;
+        self.purge_if_expired(zsetname);
+        Ok(self.data.get(zsetname).map(|zset| {
+            zset.read()
+                .range_by_score(min, max)
+                .map(|(member, score)| (member.clone(), score))
+                .collect()
+        }))
+    }
+    /// Returns the rank of the member, if both the sorted set and the member exist
+    pub fn zset_rank(&self, zsetname: &[u8], member: &[u8]) -> EncodingResult<Option<usize>> {
+        self.check_key_encoding(zsetname)?;
+        self.purge_if_expired(zsetname);
+        Ok(self
+            .data
+            .get(zsetname)
+            .and_then(|zset| zset.read().rank(member)))
+    }
+}
+
 impl<T> Default for KVEngine<T> {
     fn default() -> Self {
         Self::init(false, false)
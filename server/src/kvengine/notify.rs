@@ -0,0 +1,223 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Key change notifications
+//!
+//! Every engine has a [`Notifier`] that connections can subscribe to, to be told whenever
+//! a key is set, updated or deleted. Since most tables never have a subscriber, we keep
+//! track of the subscribers ourselves so that writes don't touch the channel at all when
+//! nobody is listening
+//!
+//! Every change also bumps the version of the key (see [`KeyVersions`]), which is what a
+//! transaction's `WATCH` looks at
+
+use {
+    super::KVEngine,
+    crate::corestore::{map::DefaultState, SharedSlice, ToSharedSlice},
+    core::{
+        hash::{BuildHasher, Hasher},
+        sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    std::sync::Arc,
+    tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender},
+};
+
+/// The number of changes that we'll buffer for a subscriber before it starts lagging
+const NOTIFY_CAPACITY: usize = 1024;
+/// The number of version counters of an engine
+const VERSION_STRIPES: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kind of change made to a key
+pub enum KeyEvent {
+    /// a key was created
+    Set,
+    /// the value of an existing key was changed
+    Update,
+    /// a key was deleted (or it expired or was evicted)
+    Delete,
+    /// all the keys in the table were deleted
+    Flush,
+}
+
+impl KeyEvent {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Set => "set",
+            Self::Update => "update",
+            Self::Delete => "del",
+            Self::Flush => "flush",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A change made to a key. The key is empty for [`KeyEvent::Flush`]
+pub struct KeyChange {
+    pub event: KeyEvent,
+    pub key: SharedSlice,
+}
+
+#[derive(Debug)]
+/// The notification channel of an engine
+pub struct Notifier {
+    tx: Sender<KeyChange>,
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(NOTIFY_CAPACITY);
+        Self {
+            tx,
+            subscribers: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    fn has_subscribers(&self) -> bool {
+        self.subscribers.load(Ordering::Acquire) != 0
+    }
+    fn subscribe(&self) -> Subscription {
+        // subscribe before we bump the count, so that a writer that sees the count will
+        // definitely find us on the channel
+        let rx = self.tx.subscribe();
+        self.subscribers.fetch_add(1, Ordering::AcqRel);
+        Subscription {
+            rx,
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+/// The versions of the keys of an engine, which go up with every change to a key. Values
+/// can't tell us whether a key changed (it could have been changed and then changed back),
+/// but versions can. Keys share a fixed number of counters (by their hash), so a change to
+/// one key can make another look changed too; that only costs a `WATCH`ing client a retry
+pub struct KeyVersions {
+    hasher: DefaultState,
+    stripes: Box<[AtomicU64]>,
+    /// bumped when every key changes at once
+    flushes: AtomicU64,
+}
+
+impl KeyVersions {
+    pub fn new() -> Self {
+        Self {
+            hasher: DefaultState::new(),
+            stripes: (0..VERSION_STRIPES).map(|_| AtomicU64::new(0)).collect(),
+            flushes: AtomicU64::new(0),
+        }
+    }
+    fn stripe(&self, key: &[u8]) -> &AtomicU64 {
+        let mut hasher = self.hasher.build_hasher();
+        hasher.write(key);
+        &self.stripes[hasher.finish() as usize % VERSION_STRIPES]
+    }
+    /// Returns the current version of `key`
+    pub fn get(&self, key: &[u8]) -> u64 {
+        // both of them only go up, so their sum changes if either of them does
+        self.stripe(key).load(Ordering::Acquire) + self.flushes.load(Ordering::Acquire)
+    }
+    fn bump(&self, key: &[u8]) {
+        self.stripe(key).fetch_add(1, Ordering::AcqRel);
+    }
+    fn bump_all(&self) {
+        self.flushes.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+impl Default for KeyVersions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A subscription to the changes made to the keys of an engine. Dropping the subscription
+/// unsubscribes
+pub struct Subscription {
+    pub(super) rx: Receiver<KeyChange>,
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl Subscription {
+    /// Wait for the next change. If we fell behind, [`RecvError::Lagged`] is returned with the
+    /// number of changes that we missed
+    pub async fn recv(&mut self) -> Result<KeyChange, RecvError> {
+        self.rx.recv().await
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.subscribers.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+// notification impls
+impl<T> KVEngine<T> {
+    /// Subscribe to the changes made to the keys in this engine
+    pub fn subscribe(&self) -> Subscription {
+        self.notifier.subscribe()
+    }
+    /// Returns the current version of `key` (see [`KeyVersions`]). An expired key is purged
+    /// first, so its expiry counts as a change
+    pub fn key_version(&self, key: &[u8]) -> u64 {
+        self.purge_if_expired(key);
+        self.versions.get(key)
+    }
+    /// Record that `key` changed without telling anyone about it (for bulk loads). Only its
+    /// version is bumped
+    pub(super) fn bump_version(&self, key: &[u8]) {
+        self.versions.bump(key)
+    }
+    /// Record a change to the given key: the key is marked dirty, its version is bumped and
+    /// the subscribers (if any) are told about it
+    pub fn notify<K: ToSharedSlice + ?Sized>(&self, event: KeyEvent, key: &K) {
+        match event {
+            KeyEvent::Flush => {
+                self.dirty.mark_all();
+                self.versions.bump_all();
+            }
+            _ => {
+                self.dirty.mark(key);
+                self.versions.bump(key.as_ref());
+            }
+        }
+        if self.notifier.has_subscribers() {
+            // an error only means that everyone unsubscribed in the meantime
+            let _ = self.notifier.tx.send(KeyChange {
+                event,
+                key: key.to_shared_slice(),
+            });
+        }
+    }
+}
@@ -24,7 +24,13 @@
  *
 */
 
-use super::{KVEStandard, SharedSlice};
+use {
+    super::{
+        eviction::ENTRY_OVERHEAD, notify::KeyEvent, now_millis, CounterError, KVEStandard,
+        SharedSlice,
+    },
+    crate::config::EvictionPolicy,
+};
 
 #[test]
 fn test_ignore_encoding() {
@@ -109,3 +115,299 @@ fn test_encoder_validate_with_non_unicode() {
     let encoder = tbl.get_double_encoder();
     assert!(!encoder("hello".as_bytes(), b"Hello \xF0\x90\x80World"));
 }
+
+#[test]
+fn test_lazy_expiry() {
+    let tbl = KVEStandard::default();
+    assert!(tbl.set("x".into(), "100".into()).unwrap());
+    assert!(tbl.expire_at_unchecked(b"x", now_millis() - 1));
+    // the key is gone as soon as we touch it
+    assert!(tbl.get_unchecked(b"x").is_none());
+    assert!(!tbl.exists_unchecked(b"x"));
+    assert!(tbl.get_expiry_ref().get(&b"x"[..]).is_none());
+    // so a fresh set succeeds
+    assert!(tbl.set("x".into(), "200".into()).unwrap());
+}
+
+#[test]
+fn test_ttl_and_persist() {
+    let tbl = KVEStandard::default();
+    assert!(tbl.ttl_unchecked(b"x").is_none());
+    assert!(!tbl.expire_at_unchecked(b"x", now_millis() + 10_000));
+    assert!(tbl.set("x".into(), "100".into()).unwrap());
+    assert_eq!(tbl.ttl_unchecked(b"x"), Some(None));
+    assert!(tbl.expire_at_unchecked(b"x", now_millis() + 10_000));
+    assert!(tbl.ttl_unchecked(b"x").unwrap().unwrap() <= 10_000);
+    assert!(tbl.persist_unchecked(b"x"));
+    assert_eq!(tbl.ttl_unchecked(b"x"), Some(None));
+}
+
+#[test]
+fn test_sweep_expired() {
+    let tbl = KVEStandard::default();
+    for key in ["a", "b", "c"] {
+        assert!(tbl.set(key.into(), "value".into()).unwrap());
+    }
+    assert!(tbl.expire_at_unchecked(b"a", now_millis() - 1));
+    assert!(tbl.expire_at_unchecked(b"b", now_millis() - 1));
+    assert!(tbl.expire_at_unchecked(b"c", now_millis() + 10_000));
+    assert_eq!(tbl.sweep_expired(), 2);
+    assert_eq!(tbl.len(), 1);
+    assert!(tbl.exists_unchecked(b"c"));
+}
+
+#[test]
+fn test_expired_keys_are_left_out() {
+    let tbl = KVEStandard::default();
+    for key in ["a", "b", "c"] {
+        assert!(tbl.set(key.into(), "value".into()).unwrap());
+    }
+    assert!(tbl.expire_at_unchecked(b"a", now_millis() - 1));
+    assert!(tbl.expire_at_unchecked(b"b", now_millis() + 10_000));
+    // nothing is purged, the expired key is just left out
+    assert_eq!(tbl.len(), 3);
+    assert_eq!(tbl.live_len(), 2);
+    let mut keys = tbl.live_keys(10);
+    keys.sort_unstable();
+    assert_eq!(keys, vec![SharedSlice::from("b"), SharedSlice::from("c")]);
+    assert_eq!(tbl.live_keys(1).len(), 1);
+    let (mut keys, cursor) = tbl.scan_live_keys(0, 10, |_| true);
+    keys.sort_unstable();
+    assert_eq!(keys, vec![SharedSlice::from("b"), SharedSlice::from("c")]);
+    assert_eq!(cursor, 0);
+}
+
+#[test]
+fn test_update_clears_ttl() {
+    let tbl = KVEStandard::default();
+    assert!(tbl.set("a".into(), "old".into()).unwrap());
+    assert!(tbl.expire_at_unchecked(b"a", now_millis() + 10_000));
+    assert!(tbl.update("a".into(), "new".into()).unwrap());
+    assert_eq!(tbl.ttl_unchecked(b"a"), Some(None));
+    // just like an upsert
+    assert!(tbl.expire_at_unchecked(b"a", now_millis() + 10_000));
+    tbl.upsert_unchecked("a".into(), "newer".into());
+    assert_eq!(tbl.ttl_unchecked(b"a"), Some(None));
+}
+
+#[test]
+fn test_set_after_del_has_no_ttl() {
+    let tbl = KVEStandard::default();
+    assert!(tbl.set("a".into(), "old".into()).unwrap());
+    assert!(tbl.expire_at_unchecked(b"a", now_millis() + 10_000));
+    assert!(tbl.remove_unchecked(b"a"));
+    assert!(!tbl.get_expiry_ref().contains_key(b"a".as_slice()));
+    assert!(!tbl.expire_at_unchecked(b"a", now_millis() + 10_000));
+    assert!(!tbl.get_expiry_ref().contains_key(b"a".as_slice()));
+    // even if a deadline is left behind, a new key doesn't pick it up
+    tbl.get_expiry_ref()
+        .upsert("a".into(), now_millis() + 10_000);
+    assert!(tbl.set("a".into(), "new".into()).unwrap());
+    assert_eq!(tbl.ttl_unchecked(b"a"), Some(None));
+}
+
+#[test]
+fn test_sweep_skips_rewritten_keys() {
+    let tbl = KVEStandard::default();
+    for key in ["a", "b", "c"] {
+        assert!(tbl.set(key.into(), "old".into()).unwrap());
+        assert!(tbl.expire_at_unchecked(key.as_bytes(), now_millis() - 1));
+    }
+    let now = now_millis();
+    let expired = tbl.expired_keys(now);
+    assert_eq!(expired.len(), 3);
+    // the sweep found the keys, but they're written before it gets to them
+    tbl.upsert_unchecked("a".into(), "new".into());
+    tbl.upsert_with_expiry_unchecked("b".into(), "new".into(), now + 10_000);
+    let swept = expired
+        .iter()
+        .filter(|key| tbl.remove_expired(key, now))
+        .count();
+    assert_eq!(swept, 1);
+    assert_eq!(tbl.get_cloned_unchecked(b"a").unwrap().as_slice(), b"new");
+    assert_eq!(tbl.ttl_unchecked(b"a"), Some(None));
+    assert_eq!(tbl.get_cloned_unchecked(b"b").unwrap().as_slice(), b"new");
+    assert!(tbl.ttl_unchecked(b"b").unwrap().is_some());
+    assert!(!tbl.exists_unchecked(b"c"));
+}
+
+#[test]
+fn test_evict_one() {
+    let tbl = KVEStandard::default();
+    assert_eq!(tbl.evict_one(EvictionPolicy::Random, 16, 0), 0);
+    for key in ["a", "b", "c"] {
+        assert!(tbl.set(key.into(), "value".into()).unwrap());
+    }
+    let usage = tbl.approx_memory_usage();
+    assert_eq!(usage, 3 * (1 + 5 + ENTRY_OVERHEAD));
+    // nothing is evicted without a policy
+    assert_eq!(tbl.evict_one(EvictionPolicy::NoEviction, 16, 0), 0);
+    let freed = tbl.evict_one(EvictionPolicy::Random, 16, 7);
+    assert_eq!(freed, 1 + 5 + ENTRY_OVERHEAD);
+    assert_eq!(tbl.len(), 2);
+    assert_eq!(tbl.approx_memory_usage(), usage - freed);
+    assert!(tbl.evict_one(EvictionPolicy::Lru, 16, 3) != 0);
+    assert!(tbl.evict_one(EvictionPolicy::Lfu, 16, 3) != 0);
+    assert_eq!(tbl.len(), 0);
+}
+
+#[test]
+fn test_estimate_memory_usage() {
+    let tbl = KVEStandard::default();
+    assert_eq!(tbl.estimate_memory_usage(16, 1), 0);
+    for i in 0..1000 {
+        let key = format!("key{:04}", i);
+        assert!(tbl.set(key.as_str().into(), "value".into()).unwrap());
+    }
+    // every entry has the same footprint, so the sample gives away the exact usage
+    let usage = tbl.approx_memory_usage();
+    assert_eq!(usage, 1000 * (7 + 5 + ENTRY_OVERHEAD));
+    assert_eq!(tbl.estimate_memory_usage(16, 1), usage);
+    assert_eq!(tbl.estimate_memory_usage(2000, 1), usage);
+}
+
+#[test]
+fn test_memory_usage() {
+    let tbl = KVEStandard::default();
+    assert_eq!(tbl.memory_usage_unchecked(b"a"), None);
+    assert!(tbl.set("a".into(), "value".into()).unwrap());
+    assert!(tbl.set("bb".into(), "".into()).unwrap());
+    assert_eq!(
+        tbl.memory_usage_unchecked(b"a"),
+        Some(1 + 5 + ENTRY_OVERHEAD)
+    );
+    assert_eq!(tbl.memory_usage_unchecked(b"bb"), Some(2 + ENTRY_OVERHEAD));
+    // the keys add up to the table
+    assert_eq!(
+        tbl.approx_memory_usage(),
+        tbl.memory_usage_unchecked(b"a").unwrap() + tbl.memory_usage_unchecked(b"bb").unwrap()
+    );
+    // an expired key takes up nothing
+    assert!(tbl.expire_at_unchecked(b"a", now_millis() - 1));
+    assert_eq!(tbl.memory_usage_unchecked(b"a"), None);
+}
+
+#[test]
+fn test_increment() {
+    let tbl = KVEStandard::default();
+    // missing keys start at zero
+    assert_eq!(tbl.increment_unchecked(b"x", 1), Ok(1));
+    assert_eq!(tbl.increment_unchecked(b"x", -5), Ok(-4));
+    assert_eq!(tbl.get_cloned_unchecked(b"x").unwrap(), "-4");
+    assert!(tbl.set("y".into(), "hello".into()).unwrap());
+    assert_eq!(
+        tbl.increment_unchecked(b"y", 1),
+        Err(CounterError::NotAnInteger)
+    );
+    assert!(tbl.set("z".into(), i64::MAX.to_string().into()).unwrap());
+    assert_eq!(
+        tbl.increment_unchecked(b"z", 1),
+        Err(CounterError::Overflow)
+    );
+    // the value is left untouched on error
+    assert_eq!(
+        tbl.get_cloned_unchecked(b"z").unwrap(),
+        i64::MAX.to_string()
+    );
+}
+
+#[test]
+fn test_append() {
+    let tbl = KVEStandard::default();
+    assert_eq!(tbl.append_unchecked(b"x", b"hello"), 5);
+    assert_eq!(tbl.append_unchecked(b"x", b", world"), 12);
+    assert_eq!(tbl.get_cloned_unchecked(b"x").unwrap(), "hello, world");
+}
+
+#[test]
+fn test_notify() {
+    let tbl = KVEStandard::default();
+    // nobody is listening yet, so this is just dropped
+    assert!(tbl.set("x".into(), "1".into()).unwrap());
+    let mut sub = tbl.subscribe();
+    assert!(tbl.update("x".into(), "2".into()).unwrap());
+    assert!(tbl.set("y".into(), "1".into()).unwrap());
+    // nothing changes, so nothing is sent
+    assert!(!tbl.remove("z").unwrap());
+    assert!(tbl.remove("x").unwrap());
+    tbl.truncate_table();
+    let mut events = Vec::new();
+    while let Ok(change) = sub.rx.try_recv() {
+        events.push((change.event, change.key));
+    }
+    assert_eq!(
+        events,
+        vec![
+            (KeyEvent::Update, SharedSlice::from("x")),
+            (KeyEvent::Set, SharedSlice::from("y")),
+            (KeyEvent::Delete, SharedSlice::from("x")),
+            (KeyEvent::Flush, SharedSlice::from("")),
+        ]
+    );
+}
+
+#[test]
+fn test_shared_keys() {
+    let tbl = KVEStandard::default();
+    assert!(tbl.set("x".into(), "1".into()).unwrap());
+    let stored = tbl.get_inner_ref().get(&b"x"[..]).unwrap().key().as_ptr();
+    // the expiry and the dirty keys hold the key in the map, rather than a copy of it
+    assert!(tbl.expire_at_unchecked(b"x", now_millis() + 60_000));
+    let expiry = tbl.get_expiry_ref().get(&b"x"[..]).unwrap().key().as_ptr();
+    assert_eq!(expiry, stored);
+    let dirty = tbl.dirty_keys().keys().unwrap();
+    assert_eq!(dirty[0].as_ptr(), stored);
+    // and so do the notifications, even if the key is only given as a slice
+    let mut sub = tbl.subscribe();
+    assert!(tbl.remove_unchecked(b"x"));
+    assert_eq!(sub.rx.try_recv().unwrap().key.as_ptr(), stored);
+}
+
+#[test]
+fn test_bulk_upsert() {
+    let tbl = KVEStandard::default();
+    assert!(tbl.set("x".into(), "1".into()).unwrap());
+    assert!(tbl.expire_at_unchecked(b"x", now_millis() + 60_000));
+    tbl.dirty_keys().reset();
+    let mut sub = tbl.subscribe();
+    tbl.reserve(100);
+    tbl.bulk_upsert_unchecked("x".into(), "2".into());
+    tbl.bulk_upsert_unchecked("y".into(), "1".into());
+    assert_eq!(tbl.get_cloned_unchecked(b"x").unwrap(), "2");
+    assert_eq!(tbl.get_cloned_unchecked(b"y").unwrap(), "1");
+    // the expiry is cleared, just like with an upsert
+    assert_eq!(tbl.ttl_unchecked(b"x"), Some(None));
+    // but nobody is told and nothing is marked dirty
+    assert!(sub.rx.try_recv().is_err());
+    assert!(tbl.dirty_keys().keys().unwrap().is_empty());
+}
+
+#[test]
+fn test_lookup_stats() {
+    let tbl = KVEStandard::default();
+    assert!(tbl.set("x".into(), "100".into()).unwrap());
+    assert!(tbl.get_unchecked(b"x").is_some());
+    assert!(tbl.get_cloned_unchecked(b"x").is_some());
+    assert!(tbl.get_unchecked(b"y").is_none());
+    assert_eq!(tbl.lookup_stats(), (2, 1));
+}
+
+#[test]
+fn test_dirty_keys() {
+    let tbl = KVEStandard::default();
+    assert!(tbl.set("x".into(), "1".into()).unwrap());
+    assert!(tbl.set("y".into(), "1".into()).unwrap());
+    assert!(tbl.remove("x").unwrap());
+    // a lookup doesn't change anything
+    assert!(tbl.get_unchecked(b"y").is_some());
+    let mut dirty = tbl.dirty_keys().keys().unwrap();
+    dirty.sort();
+    assert_eq!(dirty, vec![SharedSlice::from("x"), SharedSlice::from("y")]);
+    tbl.dirty_keys().reset();
+    assert!(tbl.dirty_keys().keys().unwrap().is_empty());
+    // every key has to be written out once the table is cleared
+    tbl.truncate_table();
+    assert!(tbl.dirty_keys().keys().is_none());
+    tbl.dirty_keys().reset();
+    assert!(tbl.dirty_keys().keys().unwrap().is_empty());
+}
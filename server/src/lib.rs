@@ -0,0 +1,190 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#![deny(unused_crate_dependencies)]
+#![deny(unused_imports)]
+#![deny(unused_must_use)]
+#![cfg_attr(feature = "nightly", feature(test))]
+
+//! # Skytable
+//!
+//! The `skyd` crate (or the `server` folder) is Skytable's database server and maybe
+//! is the most important part of the project. There are several modules within this crate; see
+//! the modules for their respective documentation.
+//!
+//! The crate is also a library so that applications can use the storage engine in-process
+//! through the [`embedded`] API, without running the network server. Everything else is an
+//! implementation detail of the `skyd` binary.
+
+use {
+    crate::{
+        config::{ConfigurationSet, StartupActions},
+        diskstore::flock::FileLock,
+        util::exit_error,
+    },
+    libsky::{URL, VERSION},
+    std::process,
+};
+
+#[macro_use]
+mod util;
+mod actions;
+mod admin;
+mod arbiter;
+mod auth;
+mod blueql;
+mod cluster;
+mod config;
+mod corestore;
+mod dbnet;
+mod diskstore;
+pub mod embedded;
+mod kvengine;
+mod logging;
+mod protocol;
+mod queryengine;
+mod registry;
+mod replication;
+mod scripting;
+mod services;
+mod storage;
+mod streams;
+#[cfg(test)]
+mod tests;
+
+const PID_FILE_PATH: &str = ".sky_pid";
+
+#[cfg(test)]
+const ROOT_DIR: &str = env!("ROOT_DIR");
+#[cfg(test)]
+const TEST_AUTH_ORIGIN_KEY: &str = env!("TEST_ORIGIN_KEY");
+
+// the allocator is only installed by the `skyd` binary; embedders pick their own
+#[cfg(all(not(target_env = "msvc"), not(miri)))]
+use jemallocator as _;
+
+/// The terminal art for `!noart` configurations
+const TEXT: &str = "
+███████ ██   ██ ██    ██ ████████  █████  ██████  ██      ███████
+██      ██  ██   ██  ██     ██    ██   ██ ██   ██ ██      ██
+███████ █████     ████      ██    ███████ ██████  ██      █████
+     ██ ██  ██     ██       ██    ██   ██ ██   ██ ██      ██
+███████ ██   ██    ██       ██    ██   ██ ██████  ███████ ███████
+";
+
+type IoResult<T> = std::io::Result<T>;
+
+/// Run the database server until it is asked to terminate. This is all that the `skyd`
+/// binary does and it isn't a part of the embedded API
+#[doc(hidden)]
+pub fn run_server() {
+    logging::init();
+    // Start the server which asynchronously waits for a CTRL+C signal
+    // which will safely shut down the server
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .thread_name("server")
+        .enable_all()
+        .build()
+        .unwrap();
+    let (cfg, startup) = check_args_and_get_cfg();
+    // check if any other process is using the data directory and lock it if not (else error)
+    // important: create the pid_file just here and nowhere else because check_args can also
+    // involve passing --help or wrong arguments which can falsely create a PID file
+    let pid_file = run_pre_startup_tasks();
+    let db = runtime.block_on(async move { arbiter::run(cfg, startup).await });
+    // Make sure all background workers terminate
+    drop(runtime);
+    let db = match db {
+        Ok(d) => d,
+        Err(e) => {
+            // uh oh, something happened while starting up
+            log::error!("{}", e);
+            services::pre_shutdown_cleanup(pid_file, None);
+            process::exit(1);
+        }
+    };
+    log::info!("Stopped accepting incoming connections");
+    arbiter::finalize_shutdown(db, pid_file);
+    logging::flush();
+    {
+        // remove this file in debug builds for harness to pick it up
+        #[cfg(debug_assertions)]
+        std::fs::remove_file(PID_FILE_PATH).unwrap();
+    }
+}
+
+/// This function checks the command line arguments and either returns a config object
+/// or prints an error to `stderr` and terminates the server
+fn check_args_and_get_cfg() -> (ConfigurationSet, StartupActions) {
+    match config::get_config() {
+        Ok(cfg) => {
+            if let Err(e) = logging::configure(cfg.logging()) {
+                log::error!("Startup failure: Failed to open log file: {}", e);
+                crate::exit_error();
+            }
+            if cfg.is_artful() {
+                log::info!("Skytable v{} | {}\n{}", VERSION, URL, TEXT);
+            } else {
+                log::info!("Skytable v{} | {}", VERSION, URL);
+            }
+            if cfg.is_custom() {
+                log::info!("Using settings from supplied configuration");
+            } else {
+                log::warn!("No configuration file supplied. Using default settings");
+            }
+            // print warnings if any
+            cfg.print_warnings();
+            cfg.finish()
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            crate::exit_error();
+        }
+    }
+}
+
+/// On startup, we attempt to check if a `.sky_pid` file exists. If it does, then
+/// this file will contain the kernel/operating system assigned process ID of the
+/// skyd process. We will attempt to read that and log an error complaining that
+/// the directory is in active use by another process. If the file doesn't then
+/// we're free to create our own file and write our own PID to it. Any subsequent
+/// processes will detect this and this helps us prevent two processes from writing
+/// to the same directory which can cause potentially undefined behavior.
+///
+fn run_pre_startup_tasks() -> FileLock {
+    let mut file = match FileLock::lock(PID_FILE_PATH) {
+        Ok(fle) => fle,
+        Err(e) => {
+            log::error!("Startup failure: Failed to lock pid file: {}", e);
+            crate::exit_error();
+        }
+    };
+    if let Err(e) = file.write(process::id().to_string().as_bytes()) {
+        log::error!("Startup failure: Failed to write to pid file: {}", e);
+        crate::exit_error();
+    }
+    file
+}
@@ -0,0 +1,121 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Log files
+//!
+//! Log files are rotated once they grow beyond a certain size or once they've been written to
+//! for a certain amount of time, whichever comes first. When `skyd.log` is rotated, it is
+//! renamed to `skyd.log.1` (after `skyd.log.1` is renamed to `skyd.log.2` and so on) and the
+//! oldest log file is removed once there are more than `keep` rotated files
+
+use {
+    crate::{config::LogRotation, IoResult},
+    std::{
+        fs::{self, File, OpenOptions},
+        io::Write,
+        path::{Path, PathBuf},
+        time::Instant,
+    },
+};
+
+#[derive(Debug)]
+/// A log file that rotates itself
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    /// the number of bytes in the current file
+    size: u64,
+    /// when the current file was opened
+    opened: Instant,
+    rotation: LogRotation,
+}
+
+impl RotatingFile {
+    /// Open (or create) the log file at `path`. Records are appended to the file if it exists
+    pub fn open(path: impl AsRef<Path>, rotation: LogRotation) -> IoResult<Self> {
+        let path = path.as_ref().to_owned();
+        let file = Self::open_file(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            opened: Instant::now(),
+            rotation,
+        })
+    }
+    fn open_file(path: &Path) -> IoResult<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+    /// Returns the path of the `n`th rotated file
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{n}"));
+        path.into()
+    }
+    /// Check if the file has to be rotated before `incoming` bytes are written to it
+    fn needs_rotation(&self, incoming: u64) -> bool {
+        let maxsize = self.rotation.maxsize();
+        let too_large = maxsize != 0 && self.size != 0 && self.size + incoming > maxsize;
+        let too_old = self
+            .rotation
+            .every()
+            .map_or(false, |every| self.opened.elapsed() >= every);
+        too_large || too_old
+    }
+    /// Rotate the log file
+    pub fn rotate(&mut self) -> IoResult<()> {
+        self.file.flush()?;
+        // remove the oldest file and then make room for the current one
+        let oldest = self.rotated_path(self.rotation.keep());
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+        for n in (1..self.rotation.keep()).rev() {
+            let rotated = self.rotated_path(n);
+            if rotated.exists() {
+                fs::rename(rotated, self.rotated_path(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        self.file = Self::open_file(&self.path)?;
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+    /// Write a line to the log file, rotating it first if needed
+    pub fn write_line(&mut self, line: &str) -> IoResult<()> {
+        if self.needs_rotation(line.len() as u64) {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+    pub fn flush(&mut self) -> IoResult<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,350 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Logging
+//!
+//! This module implements the server's logger. Every record is attributed to one of the
+//! server's subsystems ([`LogModule`]) using the path of the module that it was logged from,
+//! and every subsystem can have its own level which can be changed while the server is
+//! running (using `LOGLEVEL`). A subsystem that doesn't have a level of its own uses the
+//! default level. Records are either written as plain text or as JSON objects (one per line),
+//! to `stderr` or to a log file that is rotated once it grows too large or too old (see
+//! [`RotatingFile`]).
+//!
+//! Before the configuration is loaded, the levels are picked up from the `SKY_LOG` environment
+//! variable which accepts a comma separated list of levels like `info,protocol=debug`
+
+mod file;
+#[cfg(test)]
+mod tests;
+
+pub use self::file::RotatingFile;
+use {
+    crate::{
+        config::{LogFormat, LoggingConfig},
+        IoResult,
+    },
+    chrono::prelude::Utc,
+    core::{
+        fmt::Write as _,
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    log::{LevelFilter, Log, Metadata, Record},
+    parking_lot::{const_mutex, Mutex},
+    std::{
+        env,
+        io::{self, Write as _},
+    },
+};
+
+/// The environment variable with the initial log levels
+const LOG_ENV: &str = "SKY_LOG";
+/// A module level that isn't set (the default level is used instead)
+const LEVEL_INHERIT: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+/// The subsystems that have their own log level
+pub enum LogModule {
+    /// everything that doesn't belong to any other subsystem
+    Default = 0,
+    /// the networking layer and the protocol
+    Protocol = 1,
+    /// the in-memory store and the query engine
+    Coredb = 2,
+    /// the storage engine (including BGSAVE and snapshots)
+    Diskstore = 3,
+    /// authn/authz
+    Auth = 4,
+}
+
+impl LogModule {
+    pub const COUNT: usize = 5;
+    pub const ALL: [Self; Self::COUNT] = [
+        Self::Default,
+        Self::Protocol,
+        Self::Coredb,
+        Self::Diskstore,
+        Self::Auth,
+    ];
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Protocol => "protocol",
+            Self::Coredb => "coredb",
+            Self::Diskstore => "diskstore",
+            Self::Auth => "auth",
+        }
+    }
+    /// Returns the subsystem with the given name (ignoring case)
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|module| module.name().as_bytes().eq_ignore_ascii_case(name))
+    }
+    /// Returns the subsystem that a record logged from the module at `target` belongs to
+    pub fn from_target(target: &str) -> Self {
+        let mut path = match target.strip_prefix("skyd::") {
+            Some(path) => path.split("::"),
+            None => return Self::Default,
+        };
+        match (path.next(), path.next()) {
+            (Some("protocol" | "dbnet"), _) => Self::Protocol,
+            (Some("corestore" | "kvengine" | "queryengine" | "actions" | "blueql"), _) => {
+                Self::Coredb
+            }
+            (Some("storage" | "diskstore"), _) => Self::Diskstore,
+            (Some("services"), Some("bgsave" | "snapshot" | "aof")) => Self::Diskstore,
+            (Some("auth"), _) => Self::Auth,
+            _ => Self::Default,
+        }
+    }
+}
+
+/// Where log records are written to
+enum Sink {
+    Stderr,
+    File(RotatingFile),
+}
+
+impl Sink {
+    fn write_line(&mut self, line: &str) {
+        match self {
+            Self::Stderr => {
+                let _ = io::stderr().lock().write_all(line.as_bytes());
+            }
+            Self::File(file) => {
+                if let Err(e) = file.write_line(line) {
+                    // there's no one else to tell
+                    let _ = writeln!(io::stderr(), "Failed to write to the log file: {e}");
+                    let _ = io::stderr().lock().write_all(line.as_bytes());
+                }
+            }
+        }
+    }
+    fn flush(&mut self) {
+        match self {
+            Self::Stderr => {
+                let _ = io::stderr().flush();
+            }
+            Self::File(file) => {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+/// The logger
+pub struct Logger {
+    /// the level of each module (indexed by [`LogModule`]), [`LEVEL_INHERIT`] if not set
+    levels: [AtomicUsize; LogModule::COUNT],
+    /// if set, records are written as JSON
+    json: AtomicBool,
+    sink: Mutex<Sink>,
+}
+
+static LOGGER: Logger = Logger::new();
+
+const fn level_to_usize(level: LevelFilter) -> usize {
+    level as usize
+}
+
+fn level_from_usize(level: usize) -> LevelFilter {
+    match level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+impl Logger {
+    const fn new() -> Self {
+        Self {
+            levels: [
+                AtomicUsize::new(level_to_usize(LevelFilter::Info)),
+                AtomicUsize::new(LEVEL_INHERIT),
+                AtomicUsize::new(LEVEL_INHERIT),
+                AtomicUsize::new(LEVEL_INHERIT),
+                AtomicUsize::new(LEVEL_INHERIT),
+            ],
+            json: AtomicBool::new(false),
+            sink: const_mutex(Sink::Stderr),
+        }
+    }
+    /// Returns the level that the module has been set to (if any)
+    fn own_level(&self, module: LogModule) -> Option<LevelFilter> {
+        match self.levels[module as usize].load(Ordering::Relaxed) {
+            LEVEL_INHERIT => None,
+            level => Some(level_from_usize(level)),
+        }
+    }
+    /// Returns the level that the module is using
+    fn level(&self, module: LogModule) -> LevelFilter {
+        self.own_level(module)
+            .or_else(|| self.own_level(LogModule::Default))
+            .unwrap_or(LevelFilter::Info)
+    }
+    fn set_level(&self, module: LogModule, level: LevelFilter) {
+        self.levels[module as usize].store(level_to_usize(level), Ordering::Relaxed);
+        // the log macros skip anything above the max level, so it has to let through the
+        // records of the most verbose module
+        let max = LogModule::ALL
+            .into_iter()
+            .map(|module| self.level(module))
+            .max()
+            .unwrap_or(LevelFilter::Info);
+        log::set_max_level(max);
+    }
+    fn format_text(record: &Record, out: &mut String) {
+        let _ = writeln!(
+            out,
+            "[{} {:<5} {}] {}",
+            Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+    fn format_json(record: &Record, module: LogModule, out: &mut String) {
+        let _ = write!(
+            out,
+            "{{\"timestamp\":\"{}\",\"level\":\"{}\",\"module\":\"{}\",\"target\":",
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            record.level(),
+            module.name(),
+        );
+        escape_json(record.target(), out);
+        out.push_str(",\"message\":");
+        escape_json(&record.args().to_string(), out);
+        out.push_str("}\n");
+    }
+}
+
+/// Write `string` to `out` as a JSON string
+fn escape_json(string: &str, out: &mut String) {
+    out.push('"');
+    for chr in string.chars() {
+        match chr {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            chr if chr.is_control() => {
+                let _ = write!(out, "\\u{:04x}", chr as u32);
+            }
+            chr => out.push(chr),
+        }
+    }
+    out.push('"');
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(LogModule::from_target(metadata.target()))
+    }
+    fn log(&self, record: &Record) {
+        let module = LogModule::from_target(record.target());
+        if record.level() > self.level(module) {
+            return;
+        }
+        let mut line = String::new();
+        if self.json.load(Ordering::Relaxed) {
+            Self::format_json(record, module, &mut line);
+        } else {
+            Self::format_text(record, &mut line);
+        }
+        self.sink.lock().write_line(&line);
+    }
+    fn flush(&self) {
+        self.sink.lock().flush()
+    }
+}
+
+/// Parse a list of levels like `info,protocol=debug`. A level without a module sets the
+/// default level
+fn parse_levels(spec: &str) -> Option<Vec<(LogModule, LevelFilter)>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| match directive.split_once('=') {
+            Some((module, level)) => Some((
+                LogModule::from_name(module.trim().as_bytes())?,
+                level.trim().parse().ok()?,
+            )),
+            None => Some((LogModule::Default, directive.parse().ok()?)),
+        })
+        .collect()
+}
+
+/// Install the logger, with the levels from `SKY_LOG` (if set). This should be called before
+/// anything is logged
+pub fn init() {
+    let _ = log::set_logger(&LOGGER);
+    LOGGER.set_level(LogModule::Default, LevelFilter::Info);
+    if let Ok(spec) = env::var(LOG_ENV) {
+        match parse_levels(&spec) {
+            Some(levels) => levels
+                .into_iter()
+                .for_each(|(module, level)| LOGGER.set_level(module, level)),
+            None => log::warn!("Ignoring bad value for `{LOG_ENV}`: `{spec}`"),
+        }
+    }
+}
+
+/// Apply the logging configuration. The levels from the configuration override the ones from
+/// `SKY_LOG`
+pub fn configure(cfg: &LoggingConfig) -> IoResult<()> {
+    if let Some(path) = cfg.file.as_ref() {
+        let file = RotatingFile::open(path, cfg.rotation)?;
+        *LOGGER.sink.lock() = Sink::File(file);
+    }
+    LOGGER
+        .json
+        .store(cfg.format == LogFormat::Json, Ordering::Relaxed);
+    cfg.levels
+        .iter()
+        .for_each(|(module, level)| LOGGER.set_level(*module, *level));
+    Ok(())
+}
+
+/// Returns the level that the module is using
+pub fn level(module: LogModule) -> LevelFilter {
+    LOGGER.level(module)
+}
+
+/// Set the level of a module
+pub fn set_level(module: LogModule, level: LevelFilter) {
+    LOGGER.set_level(module, level)
+}
+
+/// Flush the logs
+pub fn flush() {
+    LOGGER.flush()
+}
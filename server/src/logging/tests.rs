@@ -0,0 +1,124 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    super::{escape_json, parse_levels, LogModule, RotatingFile},
+    crate::config::LogRotation,
+    log::LevelFilter,
+    std::fs,
+};
+
+#[test]
+fn test_module_from_target() {
+    assert_eq!(
+        LogModule::from_target("skyd::protocol::v2"),
+        LogModule::Protocol
+    );
+    assert_eq!(
+        LogModule::from_target("skyd::dbnet::tcp"),
+        LogModule::Protocol
+    );
+    assert_eq!(LogModule::from_target("skyd::kvengine"), LogModule::Coredb);
+    assert_eq!(
+        LogModule::from_target("skyd::storage::v1::sengine"),
+        LogModule::Diskstore
+    );
+    assert_eq!(
+        LogModule::from_target("skyd::services::bgsave"),
+        LogModule::Diskstore
+    );
+    assert_eq!(
+        LogModule::from_target("skyd::services::reaper"),
+        LogModule::Default
+    );
+    assert_eq!(
+        LogModule::from_target("skyd::auth::provider"),
+        LogModule::Auth
+    );
+    assert_eq!(LogModule::from_target("skyd::arbiter"), LogModule::Default);
+    assert_eq!(LogModule::from_target("tokio::runtime"), LogModule::Default);
+}
+
+#[test]
+fn test_module_from_name() {
+    assert_eq!(
+        LogModule::from_name(b"DiskStore"),
+        Some(LogModule::Diskstore)
+    );
+    assert_eq!(LogModule::from_name(b"default"), Some(LogModule::Default));
+    assert_eq!(LogModule::from_name(b"network"), None);
+}
+
+#[test]
+fn test_parse_levels() {
+    assert_eq!(
+        parse_levels("info, protocol=debug,auth=off").unwrap(),
+        vec![
+            (LogModule::Default, LevelFilter::Info),
+            (LogModule::Protocol, LevelFilter::Debug),
+            (LogModule::Auth, LevelFilter::Off),
+        ]
+    );
+    assert_eq!(parse_levels("").unwrap(), vec![]);
+    assert!(parse_levels("skyd=debug").is_none());
+    assert!(parse_levels("protocol=loud").is_none());
+}
+
+#[test]
+fn test_escape_json() {
+    let mut out = String::new();
+    escape_json("said \"hi\"\n\\\u{1}", &mut out);
+    assert_eq!(out, r#""said \"hi\"\n\\\u0001""#);
+}
+
+#[test]
+fn test_rotate_on_size() {
+    let _ = fs::remove_dir_all("logtest_rotate");
+    fs::create_dir_all("logtest_rotate").unwrap();
+    let path = "logtest_rotate/skyd.log";
+    let mut file = RotatingFile::open(path, LogRotation::new(16, 0, 2)).unwrap();
+    for line in [
+        "first line\n",
+        "second line\n",
+        "third line\n",
+        "fourth line\n",
+    ] {
+        file.write_line(line).unwrap();
+    }
+    file.flush().unwrap();
+    // every line is rotated away by the next one and we only keep two rotated files
+    assert_eq!(fs::read_to_string(path).unwrap(), "fourth line\n");
+    assert_eq!(
+        fs::read_to_string("logtest_rotate/skyd.log.1").unwrap(),
+        "third line\n"
+    );
+    assert_eq!(
+        fs::read_to_string("logtest_rotate/skyd.log.2").unwrap(),
+        "second line\n"
+    );
+    assert!(!std::path::Path::new("logtest_rotate/skyd.log.3").exists());
+    fs::remove_dir_all("logtest_rotate").unwrap();
+}
@@ -25,7 +25,7 @@
 */
 
 use {
-    super::ParseError,
+    super::{ParseError, QueryLimits},
     crate::{
         corestore::booltable::{BytesBoolTable, BytesNicheLUT},
         dbnet::QueryWithAdvance,
@@ -69,6 +69,10 @@ pub trait ProtocolSpec: Send + Sync {
     const SIMPLE_QUERY_HEADER: &'static [u8];
     /// The header for pipelined queries (excluding length, obviously)
     const PIPELINED_QUERY_FIRST_BYTE: u8;
+    /// The header for frames that are pushed to the client (like key changes and monitored
+    /// queries). These aren't the response to any query and can show up in between
+    /// responses, so clients tell them apart by this header (which no response starts with)
+    const PUSH_FRAME_HEADER: &'static [u8];
 
     // typed array
     /// Null element represenation for a typed array
@@ -107,6 +111,10 @@ pub trait ProtocolSpec: Send + Sync {
     const RSTRING_SNAPSHOT_DUPLICATE: &'static [u8];
     /// Respstring when snapshot has illegal chars
     const RSTRING_SNAPSHOT_ILLEGAL_NAME: &'static [u8];
+    /// Respstring when a snapshot with the given name doesn't exist
+    const RSTRING_SNAPSHOT_NOT_FOUND: &'static [u8];
+    /// Respstring when a snapshot can't be restored because the writes are being replicated
+    const RSTRING_SNAPSHOT_REPLICATED: &'static [u8];
     /// Respstring when a **very bad error** happens (use after termsig)
     const RSTRING_ERR_ACCESS_AFTER_TERMSIG: &'static [u8];
     /// Respstring when the default container is unset
@@ -119,6 +127,8 @@ pub trait ProtocolSpec: Send + Sync {
     const RSTRING_PROTECTED_OBJECT: &'static [u8];
     /// Respstring when an action is not suitable for the current table model
     const RSTRING_WRONG_MODEL: &'static [u8];
+    /// Respstring when a typed action is run on a table whose values have another type
+    const RSTRING_WRONG_VALUE_TYPE: &'static [u8];
     /// Respstring when the container already exists
     const RSTRING_ALREADY_EXISTS: &'static [u8];
     /// Respstring when the container is not ready
@@ -150,6 +160,68 @@ pub trait ProtocolSpec: Send + Sync {
     const RSTRING_LISTMAP_BAD_INDEX: &'static [u8];
     /// Respstring when a list is empty and we attempt to access/modify it
     const RSTRING_LISTMAP_LIST_IS_EMPTY: &'static [u8];
+    /// Respstring when the TTL of a key without an expiry is requested
+    const RSTRING_NO_EXPIRY: &'static [u8];
+    /// Respstring when the current value doesn't match the expected value in a `CAS`
+    const RSTRING_CAS_MISMATCH: &'static [u8];
+    /// Respstring when a write is rejected because the memory limit has been exceeded
+    const RSTRING_MEMORY_LIMIT_EXCEEDED: &'static [u8];
+    /// Respstring when a score for a sorted set is not a valid number
+    const RSTRING_ZSET_BAD_SCORE: &'static [u8];
+    /// Respstring when a counter is incremented or decremented but the value isn't an integer
+    const RSTRING_NOT_AN_INTEGER: &'static [u8];
+    /// Respstring when incrementing or decrementing a counter overflows
+    const RSTRING_INTEGER_OVERFLOW: &'static [u8];
+    /// Respstring when `MULTI` is run while a transaction has already been started
+    const RSTRING_TXN_NESTED: &'static [u8];
+    /// Respstring when `EXEC` or `DISCARD` is run without starting a transaction
+    const RSTRING_TXN_NOT_STARTED: &'static [u8];
+    /// Respstring when a query can't be queued in a transaction
+    const RSTRING_TXN_BAD_QUERY: &'static [u8];
+    /// Respstring when a transaction is discarded on `EXEC` because a query couldn't be queued
+    const RSTRING_TXN_ABORTED: &'static [u8];
+    /// Respstring when a client is turned away because the server is serving the maximum number
+    /// of clients
+    const RSTRING_TOO_MANY_CONNECTIONS: &'static [u8];
+    /// Respstring when a write is attempted on a replica
+    const RSTRING_READONLY_REPLICA: &'static [u8];
+    /// Respstring when a replica-only action is run on a node that isn't a replica
+    const RSTRING_NOT_A_REPLICA: &'static [u8];
+    /// Respstring when a read is attempted on a replica that lags too far behind its primary
+    const RSTRING_REPLICA_LAGGING: &'static [u8];
+    /// Respstring when a write that can't be resolved last-writer-wins is attempted in
+    /// multi-primary mode
+    const RSTRING_NOT_LWW: &'static [u8];
+    /// Respstring when a cluster action is run on a node that isn't in cluster mode
+    const RSTRING_CLUSTER_DISABLED: &'static [u8];
+    /// Respstring when not all the slots in the cluster are assigned
+    const RSTRING_CLUSTER_DOWN: &'static [u8];
+    /// Respstring when the keys in a query map to different slots
+    const RSTRING_CROSS_SLOT: &'static [u8];
+    /// Respstring when a slot number is out of range
+    const RSTRING_BAD_SLOT: &'static [u8];
+    /// Respstring when a write touches a key that is being moved to another node
+    const RSTRING_TRY_AGAIN: &'static [u8];
+    /// Respstring when a slot that this node doesn't own is to be moved
+    const RSTRING_NOT_SLOT_OWNER: &'static [u8];
+    /// Respstring when a slot that is already being moved is to be moved again
+    const RSTRING_MIGRATION_BUSY: &'static [u8];
+    /// Respstring when a bulk load is started while one is already in progress
+    const RSTRING_BULKLOAD_ACTIVE: &'static [u8];
+    /// Respstring when pairs are loaded (or a bulk load is ended) without starting a bulk load
+    const RSTRING_BULKLOAD_NOT_STARTED: &'static [u8];
+    /// Respstring when a bulk load is started on a node with replicas or in cluster mode
+    const RSTRING_BULKLOAD_UNAVAILABLE: &'static [u8];
+    /// Respstring when a blob passed to `RESTORE` is corrupted or of an unknown version
+    const RSTRING_BAD_DUMP: &'static [u8];
+    /// Respstring when `EVALSHA` names a script that isn't in the script cache
+    const RSTRING_NO_SCRIPT: &'static [u8];
+    /// Respstring when a script fails to compile or raises an error
+    const RSTRING_SCRIPT_ERROR: &'static [u8];
+    /// Respstring when a script runs for longer than it's allowed to
+    const RSTRING_SCRIPT_TIMEOUT: &'static [u8];
+    /// Respstring when a script is run in cluster mode
+    const RSTRING_SCRIPTS_UNAVAILABLE: &'static [u8];
 
     // element responses
     /// A string element containing the text "HEY!"
@@ -160,6 +232,8 @@ pub trait ProtocolSpec: Send + Sync {
     const FULLRESP_RCODE_PACKET_ERR: &'static [u8];
     /// A **full response** for a wrongtype error
     const FULLRESP_RCODE_WRONG_TYPE: &'static [u8];
+    /// A **full response** for a query that is larger than what the query limits allow
+    const FULLRESP_RSTRING_QUERY_TOO_LARGE: &'static [u8];
 
     // LUTs
     /// A LUT for SET operations
@@ -183,11 +257,12 @@ pub trait ProtocolSpec: Send + Sync {
         Self::RCODE_OKAY,
         Self::RCODE_NIL,
     );
-    const SKYHASH_PARSE_ERROR_LUT: [&'static [u8]; 4] = [
+    const SKYHASH_PARSE_ERROR_LUT: [&'static [u8]; 5] = [
         Self::FULLRESP_RCODE_PACKET_ERR,
         Self::FULLRESP_RCODE_PACKET_ERR,
         Self::FULLRESP_RCODE_WRONG_TYPE,
         Self::FULLRESP_RCODE_WRONG_TYPE,
+        Self::FULLRESP_RSTRING_QUERY_TOO_LARGE,
     ];
 
     // auth error respstrings
@@ -203,6 +278,16 @@ pub trait ProtocolSpec: Send + Sync {
     const AUTH_ERROR_ILLEGAL_USERNAME: &'static [u8];
     /// respstring: ID is protected/in use
     const AUTH_ERROR_FAILED_TO_DELETE_USER: &'static [u8];
+    /// respstring: unknown permission name
+    const AUTH_ERROR_UNKNOWN_PERMISSION: &'static [u8];
+    /// respstring: the permissions of root cannot be changed
+    const AUTH_ERROR_ROOT_PERMISSIONS: &'static [u8];
+    /// respcode(12): too many failed logins; try again later
+    const AUTH_CODE_RATE_LIMITED: &'static [u8];
+    /// respstring: the origin key is set in the configuration (and not kept in a file)
+    const AUTH_ERROR_ORIGIN_NOT_ROTATABLE: &'static [u8];
+    /// respstring: the users couldn't be imported (the file is missing or invalid)
+    const AUTH_ERROR_BAD_IMPORT: &'static [u8];
 
     // BlueQL respstrings
     const BQL_BAD_EXPRESSION: &'static [u8];
@@ -218,5 +303,8 @@ pub trait ProtocolSpec: Send + Sync {
     /// The body is terminated by a linefeed
     const NEEDS_TERMINAL_LF: bool;
 
-    fn decode_packet(input: &[u8]) -> Result<QueryWithAdvance, ParseError>;
+    /// Decode a query, rejecting it if it's larger than what the `limits` allow
+    fn decode_packet(input: &[u8], limits: QueryLimits) -> Result<QueryWithAdvance, ParseError>;
+    /// Encode a respstring that is only known at runtime (like a redirect to another node)
+    fn encode_respstring(rstring: &str) -> Vec<u8>;
 }
@@ -31,6 +31,66 @@ use {
     core::{hint::unreachable_unchecked, iter::FusedIterator, ops::Deref, slice::Iter},
 };
 
+/// Elements up to this length are case-folded on the stack
+const INLINE_FOLD_LEN: usize = 32;
+
+/// A case-folded copy of an element, used to match action names and keywords. Short elements
+/// (which includes every action name and keyword) are folded on the stack so that matching
+/// them doesn't cost an allocation for every query
+pub enum Folded {
+    Inline([u8; INLINE_FOLD_LEN], u8),
+    Heap(Box<[u8]>),
+}
+
+impl Folded {
+    #[inline(always)]
+    fn new(src: &[u8], fold: fn(&mut [u8])) -> Self {
+        if src.len() <= INLINE_FOLD_LEN {
+            let mut buf = [0u8; INLINE_FOLD_LEN];
+            buf[..src.len()].copy_from_slice(src);
+            fold(&mut buf[..src.len()]);
+            Self::Inline(buf, src.len() as u8)
+        } else {
+            let mut buf = src.to_owned().into_boxed_slice();
+            fold(&mut buf);
+            Self::Heap(buf)
+        }
+    }
+    /// Returns a lowercase copy of `src`
+    #[inline(always)]
+    pub fn lowercase(src: &[u8]) -> Self {
+        Self::new(src, <[u8]>::make_ascii_lowercase)
+    }
+    /// Returns an uppercase copy of `src`
+    #[inline(always)]
+    pub fn uppercase(src: &[u8]) -> Self {
+        Self::new(src, <[u8]>::make_ascii_uppercase)
+    }
+    #[cfg(test)]
+    /// Returns true if the element was folded on the stack
+    pub const fn is_inline(&self) -> bool {
+        matches!(self, Self::Inline(..))
+    }
+}
+
+impl Deref for Folded {
+    type Target = [u8];
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Inline(buf, len) => &buf[..*len as usize],
+            Self::Heap(buf) => buf,
+        }
+    }
+}
+
+impl AsRef<[u8]> for Folded {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
 /// An iterator over an [`AnyArray`] (an [`UnsafeSlice`]). The validity of the iterator is
 /// left to the caller who has to guarantee:
 /// - Source pointers for the unsafe slice are valid
@@ -83,33 +143,19 @@ impl<'a> AnyArrayIter<'a> {
     }
     /// Returns the next value in uppercase
     #[inline(always)]
-    pub fn next_uppercase(&mut self) -> Option<Box<[u8]>> {
-        self.iter.next().map(|v| {
-            unsafe {
-                // UNSAFE(@ohsayan): The ctor of `Self` allows us to "assume" this is safe
-                v.as_slice()
-            }
-            .to_ascii_uppercase()
-            .into_boxed_slice()
-        })
+    pub fn next_uppercase(&mut self) -> Option<Folded> {
+        self.next().map(Folded::uppercase)
     }
     #[inline(always)]
-    pub fn next_lowercase(&mut self) -> Option<Box<[u8]>> {
-        self.iter.next().map(|v| {
-            unsafe {
-                // UNSAFE(@ohsayan): The ctor of `Self` allows us to "assume" this is safe
-                v.as_slice()
-            }
-            .to_ascii_lowercase()
-            .into_boxed_slice()
-        })
+    pub fn next_lowercase(&mut self) -> Option<Folded> {
+        self.next().map(Folded::lowercase)
     }
     #[inline(always)]
-    pub unsafe fn next_lowercase_unchecked(&mut self) -> Box<[u8]> {
+    pub unsafe fn next_lowercase_unchecked(&mut self) -> Folded {
         self.next_lowercase().unwrap_or_else(|| impossible!())
     }
     #[inline(always)]
-    pub unsafe fn next_uppercase_unchecked(&mut self) -> Box<[u8]> {
+    pub unsafe fn next_uppercase_unchecked(&mut self) -> Folded {
         match self.next_uppercase() {
             Some(s) => s,
             None => {
@@ -112,11 +112,80 @@ pub enum ParseError {
     DatatypeParseFailure = 3u8,
     /// The client supplied the wrong query data type for the given query
     WrongType = 4u8,
+    /// The query (or a part of it) is larger than what the [`QueryLimits`] allow
+    QueryTooLarge = 5u8,
 }
 
 /// A generic result to indicate parsing errors thorugh the [`ParseError`] enum
 pub type ParseResult<T> = Result<T, ParseError>;
 
+/// The default maximum size of a query (64 MiB)
+pub const DEFAULT_MAXQUERYSIZE: usize = 64 * 1024 * 1024;
+/// The default maximum number of queries in a pipeline
+pub const DEFAULT_MAXPIPELINE: usize = 65536;
+/// The default maximum length of a single argument (16 MiB)
+pub const DEFAULT_MAXARGLEN: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Caps on what a client can send us in a single query. Sizes and counts in a packet are
+/// checked against these as soon as they're read, so that we don't keep buffering (or
+/// allocating for) frames that a client only claims are huge
+pub struct QueryLimits {
+    /// the maximum number of bytes in a query
+    max_query_size: usize,
+    /// the maximum number of queries in a pipeline
+    max_pipeline: usize,
+    /// the maximum number of bytes in an argument
+    max_arg_len: usize,
+}
+
+impl QueryLimits {
+    pub const fn new(max_query_size: usize, max_pipeline: usize, max_arg_len: usize) -> Self {
+        Self {
+            max_query_size,
+            max_pipeline,
+            max_arg_len,
+        }
+    }
+    /// The default limits
+    ///
+    /// Defaults:
+    /// - `max_query_size`: 64 MiB
+    /// - `max_pipeline`: 65536
+    /// - `max_arg_len`: 16 MiB
+    pub const fn default() -> Self {
+        Self::new(DEFAULT_MAXQUERYSIZE, DEFAULT_MAXPIPELINE, DEFAULT_MAXARGLEN)
+    }
+    /// Returns the maximum number of bytes in a query
+    pub const fn max_query_size(&self) -> usize {
+        self.max_query_size
+    }
+    /// Returns the maximum number of queries in a pipeline
+    pub const fn max_pipeline(&self) -> usize {
+        self.max_pipeline
+    }
+    /// Returns the maximum number of bytes in an argument
+    pub const fn max_arg_len(&self) -> usize {
+        self.max_arg_len
+    }
+    /// Check the number of queries in a pipeline
+    fn check_pipeline(&self, count: usize) -> ParseResult<()> {
+        if count > self.max_pipeline {
+            Err(ParseError::QueryTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+    /// Check the length of an argument
+    fn check_arg_len(&self, len: usize) -> ParseResult<()> {
+        if len > self.max_arg_len || len > self.max_query_size {
+            Err(ParseError::QueryTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Query {
     Simple(SimpleQuery),
@@ -168,6 +237,10 @@ impl PipelinedQuery {
     pub fn into_inner(self) -> HeapArray<HeapArray<UnsafeSlice>> {
         self.data
     }
+    /// Returns the stages of the pipeline
+    pub fn stages(&self) -> impl Iterator<Item = &[UnsafeSlice]> {
+        self.data.iter().map(|stage| &stage[..])
+    }
     #[cfg(test)]
     fn into_owned(self) -> OwnedPipelinedQuery {
         OwnedPipelinedQuery {
@@ -169,6 +169,16 @@ pub(super) trait RawParserExt: RawParser + RawParserMeta {
         }
         Ok(ret)
     }
+    /// Check if the rest of the buffer can hold `count` items. Every item takes up at least a
+    /// byte, so if it can't, we don't have the full packet yet (and we shouldn't allocate
+    /// for a count that a client has only claimed)
+    fn expect_items(&self, count: usize) -> ParseResult<()> {
+        if self.has_remaining(count) {
+            Ok(())
+        } else {
+            Err(ParseError::NotEnough)
+        }
+    }
 }
 
 impl<T> RawParserExt for T where T: RawParser + RawParserMeta {}
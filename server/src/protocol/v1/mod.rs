@@ -27,7 +27,7 @@
 use {
     super::{
         raw_parser::{RawParser, RawParserExt, RawParserMeta},
-        ParseError, ParseResult, PipelinedQuery, Query, SimpleQuery, UnsafeSlice,
+        ParseError, ParseResult, PipelinedQuery, Query, QueryLimits, SimpleQuery, UnsafeSlice,
     },
     crate::{
         corestore::heap_array::{HeapArray, HeapArrayWriter},
@@ -59,6 +59,7 @@ mod tests;
 pub struct Parser {
     end: *const u8,
     cursor: *const u8,
+    limits: QueryLimits,
 }
 
 unsafe impl RawParser for Parser {
@@ -83,6 +84,7 @@ impl Parser {
             Self {
                 end: slice.as_ptr().add(slice.len()),
                 cursor: slice.as_ptr(),
+                limits: QueryLimits::default(),
             }
         }
     }
@@ -113,6 +115,7 @@ impl Parser {
     /// Gets the _next element. **The cursor should be at the tsymbol (passed)**
     fn _next(&mut self) -> ParseResult<UnsafeSlice> {
         let element_size = self.read_usize()?;
+        self.limits.check_arg_len(element_size)?;
         self.read_until(element_size)
     }
 }
@@ -140,7 +143,7 @@ impl Parser {
 // query abstractions
 impl Parser {
     /// The buffer should resemble the below structure:
-    /// ```
+    /// ```text
     /// ~<count>\n
     /// <e0l0>\n
     /// <e0>\n
@@ -159,6 +162,7 @@ impl Parser {
                 self.incr_cursor();
             }
             let query_count = self.read_usize()?;
+            self.expect_items(query_count)?;
             let mut writer = HeapArrayWriter::with_capacity(query_count);
             for i in 0..query_count {
                 unsafe {
@@ -224,6 +228,8 @@ impl Parser {
                 self.incr_cursor()
             };
             let query_count = self.read_usize()?; // get the length
+            self.limits.check_pipeline(query_count)?;
+            self.expect_items(query_count)?;
             if query_count == 1 {
                 Ok(Query::Simple(self.parse_simple_query()?))
             } else {
@@ -233,8 +239,11 @@ impl Parser {
             Err(ParseError::NotEnough)
         }
     }
-    pub fn parse(buf: &[u8]) -> ParseResult<QueryWithAdvance> {
-        let mut slf = Self::new(buf);
+    pub fn parse(buf: &[u8], limits: QueryLimits) -> ParseResult<QueryWithAdvance> {
+        let mut slf = Self {
+            limits,
+            ..Self::new(buf)
+        };
         let body = slf._parse()?;
         let consumed = slf.cursor_ptr() as usize - buf.as_ptr() as usize;
         Ok((body, consumed))
@@ -26,7 +26,7 @@
 
 use {
     super::Parser,
-    crate::protocol::{ParseError, Query},
+    crate::protocol::{ParseError, Query, QueryLimits},
 };
 
 #[cfg(test)]
@@ -37,7 +37,7 @@ const PQPAYLOAD: &[u8] = b"*2\n~3\n3\nSET\n1\nx\n3\n100\n~2\n3\nGET\n1\nx\n";
 #[test]
 fn parse_simple_query() {
     let payload = SQPAYLOAD.to_vec();
-    let (q, f) = Parser::parse(&payload).unwrap();
+    let (q, f) = Parser::parse(&payload, QueryLimits::default()).unwrap();
     let q: Vec<String> = if let Query::Simple(q) = q {
         q.as_slice()
             .iter()
@@ -54,14 +54,17 @@ fn parse_simple_query() {
 fn parse_simple_query_incomplete() {
     for i in 0..SQPAYLOAD.len() - 1 {
         let slice = &SQPAYLOAD[..i];
-        assert_eq!(Parser::parse(slice).unwrap_err(), ParseError::NotEnough);
+        assert_eq!(
+            Parser::parse(slice, QueryLimits::default()).unwrap_err(),
+            ParseError::NotEnough
+        );
     }
 }
 
 #[test]
 fn parse_pipelined_query() {
     let payload = PQPAYLOAD.to_vec();
-    let (q, f) = Parser::parse(&payload).unwrap();
+    let (q, f) = Parser::parse(&payload, QueryLimits::default()).unwrap();
     let q: Vec<Vec<String>> = if let Query::Pipelined(q) = q {
         q.into_inner()
             .iter()
@@ -88,6 +91,21 @@ fn parse_pipelined_query() {
 fn parse_pipelined_query_incomplete() {
     for i in 0..PQPAYLOAD.len() - 1 {
         let slice = &PQPAYLOAD[..i];
-        assert_eq!(Parser::parse(slice).unwrap_err(), ParseError::NotEnough);
+        assert_eq!(
+            Parser::parse(slice, QueryLimits::default()).unwrap_err(),
+            ParseError::NotEnough
+        );
     }
 }
+
+#[test]
+fn parse_query_too_large() {
+    assert_eq!(
+        Parser::parse(SQPAYLOAD, QueryLimits::new(1024, 16, 2)).unwrap_err(),
+        ParseError::QueryTooLarge
+    );
+    assert_eq!(
+        Parser::parse(PQPAYLOAD, QueryLimits::new(1024, 1, 1024)).unwrap_err(),
+        ParseError::QueryTooLarge
+    );
+}
@@ -26,7 +26,10 @@
 
 extern crate test;
 use {
-    super::{super::Query, Parser},
+    super::{
+        super::{Query, QueryLimits},
+        Parser,
+    },
     test::Bencher,
 };
 
@@ -35,7 +38,7 @@ fn simple_query(b: &mut Bencher) {
     const PAYLOAD: &[u8] = b"*3\n3\nSET1\nx3\n100";
     let expected = vec!["SET".to_owned(), "x".to_owned(), "100".to_owned()];
     b.iter(|| {
-        let (query, forward) = Parser::parse(PAYLOAD).unwrap();
+        let (query, forward) = Parser::parse(PAYLOAD, QueryLimits::default()).unwrap();
         assert_eq!(forward, PAYLOAD.len());
         let query = if let Query::Simple(sq) = query {
             sq
@@ -59,7 +62,7 @@ fn pipelined_query(b: &mut Bencher) {
         vec!["GET".to_owned(), "x".to_owned()],
     ];
     b.iter(|| {
-        let (query, forward) = Parser::parse(PAYLOAD).unwrap();
+        let (query, forward) = Parser::parse(PAYLOAD, QueryLimits::default()).unwrap();
         assert_eq!(forward, PAYLOAD.len());
         let query = if let Query::Pipelined(sq) = query {
             sq
@@ -27,7 +27,7 @@
 use {
     crate::{
         dbnet::QueryWithAdvance,
-        protocol::{interface::ProtocolSpec, ParseError, Skyhash2},
+        protocol::{interface::ProtocolSpec, ParseError, QueryLimits, Skyhash2},
     },
     ::sky_macros::compiled_eresp_bytes as eresp,
 };
@@ -53,6 +53,7 @@ impl ProtocolSpec for Skyhash2 {
     // metaframe
     const SIMPLE_QUERY_HEADER: &'static [u8] = b"*";
     const PIPELINED_QUERY_FIRST_BYTE: u8 = b'$';
+    const PUSH_FRAME_HEADER: &'static [u8] = b">";
 
     // respcodes
     const RCODE_OKAY: &'static [u8] = eresp!("0");
@@ -72,6 +73,8 @@ impl ProtocolSpec for Skyhash2 {
     const RSTRING_SNAPSHOT_DISABLED: &'static [u8] = eresp!("err-snapshot-disabled");
     const RSTRING_SNAPSHOT_DUPLICATE: &'static [u8] = eresp!("duplicate-snapshot");
     const RSTRING_SNAPSHOT_ILLEGAL_NAME: &'static [u8] = eresp!("err-invalid-snapshot-name");
+    const RSTRING_SNAPSHOT_NOT_FOUND: &'static [u8] = eresp!("err-snapshot-not-found");
+    const RSTRING_SNAPSHOT_REPLICATED: &'static [u8] = eresp!("err-snapshot-replicated");
     const RSTRING_ERR_ACCESS_AFTER_TERMSIG: &'static [u8] = eresp!("err-access-after-termsig");
 
     // keyspace related resps
@@ -80,6 +83,7 @@ impl ProtocolSpec for Skyhash2 {
     const RSTRING_STILL_IN_USE: &'static [u8] = eresp!("still-in-use");
     const RSTRING_PROTECTED_OBJECT: &'static [u8] = eresp!("err-protected-object");
     const RSTRING_WRONG_MODEL: &'static [u8] = eresp!("wrong-model");
+    const RSTRING_WRONG_VALUE_TYPE: &'static [u8] = eresp!("wrong-value-type");
     const RSTRING_ALREADY_EXISTS: &'static [u8] = eresp!("err-already-exists");
     const RSTRING_NOT_READY: &'static [u8] = eresp!("not-ready");
     const RSTRING_DDL_TRANSACTIONAL_FAILURE: &'static [u8] = eresp!("transactional-failure");
@@ -95,6 +99,36 @@ impl ProtocolSpec for Skyhash2 {
     const RSTRING_BAD_TYPE_FOR_KEY: &'static [u8] = eresp!("bad-type-for-key");
     const RSTRING_LISTMAP_BAD_INDEX: &'static [u8] = eresp!("bad-list-index");
     const RSTRING_LISTMAP_LIST_IS_EMPTY: &'static [u8] = eresp!("list-is-empty");
+    const RSTRING_NO_EXPIRY: &'static [u8] = eresp!("no-expiry");
+    const RSTRING_CAS_MISMATCH: &'static [u8] = eresp!("cas-mismatch");
+    const RSTRING_MEMORY_LIMIT_EXCEEDED: &'static [u8] = eresp!("memory-limit-exceeded");
+    const RSTRING_ZSET_BAD_SCORE: &'static [u8] = eresp!("bad-score");
+    const RSTRING_NOT_AN_INTEGER: &'static [u8] = eresp!("not-an-integer");
+    const RSTRING_INTEGER_OVERFLOW: &'static [u8] = eresp!("integer-overflow");
+    const RSTRING_TXN_NESTED: &'static [u8] = eresp!("transaction-nested");
+    const RSTRING_TXN_NOT_STARTED: &'static [u8] = eresp!("transaction-not-started");
+    const RSTRING_TXN_BAD_QUERY: &'static [u8] = eresp!("transaction-bad-query");
+    const RSTRING_TXN_ABORTED: &'static [u8] = eresp!("transaction-aborted");
+    const RSTRING_TOO_MANY_CONNECTIONS: &'static [u8] = eresp!("too-many-connections");
+    const RSTRING_READONLY_REPLICA: &'static [u8] = eresp!("readonly-replica");
+    const RSTRING_NOT_A_REPLICA: &'static [u8] = eresp!("not-a-replica");
+    const RSTRING_REPLICA_LAGGING: &'static [u8] = eresp!("lagging");
+    const RSTRING_NOT_LWW: &'static [u8] = eresp!("not-lww");
+    const RSTRING_CLUSTER_DISABLED: &'static [u8] = eresp!("cluster-disabled");
+    const RSTRING_CLUSTER_DOWN: &'static [u8] = eresp!("cluster-down");
+    const RSTRING_CROSS_SLOT: &'static [u8] = eresp!("cross-slot");
+    const RSTRING_BAD_SLOT: &'static [u8] = eresp!("bad-slot");
+    const RSTRING_TRY_AGAIN: &'static [u8] = eresp!("try-again");
+    const RSTRING_NOT_SLOT_OWNER: &'static [u8] = eresp!("not-slot-owner");
+    const RSTRING_MIGRATION_BUSY: &'static [u8] = eresp!("migration-in-progress");
+    const RSTRING_BULKLOAD_ACTIVE: &'static [u8] = eresp!("bulkload-in-progress");
+    const RSTRING_BULKLOAD_NOT_STARTED: &'static [u8] = eresp!("bulkload-not-started");
+    const RSTRING_BULKLOAD_UNAVAILABLE: &'static [u8] = eresp!("bulkload-unavailable");
+    const RSTRING_BAD_DUMP: &'static [u8] = eresp!("bad-dump");
+    const RSTRING_NO_SCRIPT: &'static [u8] = eresp!("no-script");
+    const RSTRING_SCRIPT_ERROR: &'static [u8] = eresp!("script-error");
+    const RSTRING_SCRIPT_TIMEOUT: &'static [u8] = eresp!("script-timeout");
+    const RSTRING_SCRIPTS_UNAVAILABLE: &'static [u8] = eresp!("scripts-unavailable");
 
     // elements
     const ELEMRESP_HEYA: &'static [u8] = b"+4\nHEY!";
@@ -102,6 +136,7 @@ impl ProtocolSpec for Skyhash2 {
     // full responses
     const FULLRESP_RCODE_PACKET_ERR: &'static [u8] = b"*!4\n";
     const FULLRESP_RCODE_WRONG_TYPE: &'static [u8] = b"*!7\n";
+    const FULLRESP_RSTRING_QUERY_TOO_LARGE: &'static [u8] = b"*!query-too-large\n";
 
     // auth respcodes/strings
     const AUTH_ERROR_ALREADYCLAIMED: &'static [u8] = eresp!("err-auth-already-claimed");
@@ -110,6 +145,11 @@ impl ProtocolSpec for Skyhash2 {
     const AUTH_CODE_PERMS: &'static [u8] = eresp!("11");
     const AUTH_ERROR_ILLEGAL_USERNAME: &'static [u8] = eresp!("err-auth-illegal-username");
     const AUTH_ERROR_FAILED_TO_DELETE_USER: &'static [u8] = eresp!("err-auth-deluser-fail");
+    const AUTH_ERROR_UNKNOWN_PERMISSION: &'static [u8] = eresp!("err-auth-unknown-permission");
+    const AUTH_ERROR_ROOT_PERMISSIONS: &'static [u8] = eresp!("err-auth-root-permissions");
+    const AUTH_CODE_RATE_LIMITED: &'static [u8] = eresp!("12");
+    const AUTH_ERROR_ORIGIN_NOT_ROTATABLE: &'static [u8] = eresp!("err-auth-origin-not-rotatable");
+    const AUTH_ERROR_BAD_IMPORT: &'static [u8] = eresp!("err-auth-bad-import");
 
     // bql respstrings
     const BQL_BAD_EXPRESSION: &'static [u8] = eresp!("bql-bad-expression");
@@ -124,7 +164,11 @@ impl ProtocolSpec for Skyhash2 {
 
     const NEEDS_TERMINAL_LF: bool = false;
 
-    fn decode_packet(input: &[u8]) -> Result<QueryWithAdvance, ParseError> {
-        Skyhash2::parse(input)
+    fn decode_packet(input: &[u8], limits: QueryLimits) -> Result<QueryWithAdvance, ParseError> {
+        Skyhash2::parse(input, limits)
+    }
+
+    fn encode_respstring(rstring: &str) -> Vec<u8> {
+        format!("!{}\n", rstring).into_bytes()
     }
 }
@@ -29,7 +29,7 @@ mod interface_impls;
 use {
     super::{
         raw_parser::{RawParser, RawParserExt, RawParserMeta},
-        ParseError, ParseResult, PipelinedQuery, Query, SimpleQuery, UnsafeSlice,
+        ParseError, ParseResult, PipelinedQuery, Query, QueryLimits, SimpleQuery, UnsafeSlice,
     },
     crate::{corestore::heap_array::HeapArray, dbnet::QueryWithAdvance},
 };
@@ -44,6 +44,7 @@ mod tests;
 pub struct Parser {
     end: *const u8,
     cursor: *const u8,
+    limits: QueryLimits,
 }
 
 unsafe impl RawParser for Parser {
@@ -68,6 +69,7 @@ impl Parser {
             Self {
                 end: slice.as_ptr().add(slice.len()),
                 cursor: slice.as_ptr(),
+                limits: QueryLimits::default(),
             }
         }
     }
@@ -89,10 +91,12 @@ impl Parser {
     /// ```
     fn _next_simple_query(&mut self) -> ParseResult<HeapArray<UnsafeSlice>> {
         let element_count = self.read_usize()?;
+        self.expect_items(element_count)?;
         unsafe {
             let mut data = HeapArray::new_writer(element_count);
             for i in 0..element_count {
                 let element_size = self.read_usize()?;
+                self.limits.check_arg_len(element_size)?;
                 let element = self.read_until(element_size)?;
                 data.write_to_index(i, element);
             }
@@ -143,6 +147,8 @@ impl Parser {
     /// ```
     fn next_pipeline(&mut self) -> ParseResult<PipelinedQuery> {
         let query_count = self.read_usize()?;
+        self.limits.check_pipeline(query_count)?;
+        self.expect_items(query_count)?;
         unsafe {
             let mut queries = HeapArray::new_writer(query_count);
             for i in 0..query_count {
@@ -178,8 +184,11 @@ impl Parser {
     }
     // only expose this. don't expose Self::new since that'll be _relatively easier_ to
     // invalidate invariants for
-    pub fn parse(buf: &[u8]) -> ParseResult<QueryWithAdvance> {
-        let mut slf = Self::new(buf);
+    pub fn parse(buf: &[u8], limits: QueryLimits) -> ParseResult<QueryWithAdvance> {
+        let mut slf = Self {
+            limits,
+            ..Self::new(buf)
+        };
         let body = slf._parse()?;
         let consumed = slf.cursor_ptr() as usize - buf.as_ptr() as usize;
         Ok((body, consumed))
@@ -29,7 +29,7 @@ use {
         super::raw_parser::{RawParser, RawParserExt, RawParserMeta},
         Parser, PipelinedQuery, Query, SimpleQuery,
     },
-    crate::protocol::{iter::AnyArrayIter, ParseError},
+    crate::protocol::{iter::AnyArrayIter, ParseError, QueryLimits},
     std::{iter::Map, vec::IntoIter as VecIntoIter},
 };
 
@@ -554,7 +554,7 @@ fn read_usize_fail() {
 fn parse_fail_because_unknown_query_scheme() {
     let body = v!(b"?3\n3\nSET1\nx3\n100");
     assert_eq!(
-        Parser::parse(&body).unwrap_err(),
+        Parser::parse(&body, QueryLimits::default()).unwrap_err(),
         ParseError::UnexpectedByte
     )
 }
@@ -562,7 +562,7 @@ fn parse_fail_because_unknown_query_scheme() {
 #[test]
 fn simple_query_okay() {
     let body = v!(b"*3\n3\nSET1\nx3\n100");
-    let (ret, skip) = Parser::parse(&body).unwrap();
+    let (ret, skip) = Parser::parse(&body, QueryLimits::default()).unwrap();
     assert_eq!(skip, body.len());
     let query = simple_query(ret);
     assert_eq!(query.into_owned().data, v!["SET", "x", "100"]);
@@ -571,7 +571,7 @@ fn simple_query_okay() {
 #[test]
 fn simple_query_okay_empty_elements() {
     let body = v!(b"*3\n3\nSET0\n0\n");
-    let (ret, skip) = Parser::parse(&body).unwrap();
+    let (ret, skip) = Parser::parse(&body, QueryLimits::default()).unwrap();
     assert_eq!(skip, body.len());
     let query = simple_query(ret);
     assert_eq!(query.into_owned().data, v!["SET", "", ""]);
@@ -585,7 +585,7 @@ fn parse_fail_because_not_enough() {
         .collect();
     for body in samples {
         assert_eq!(
-            Parser::parse(&body).unwrap_err(),
+            Parser::parse(&body, QueryLimits::default()).unwrap_err(),
             ParseError::NotEnough,
             "Failed with body len: {}",
             body.len()
@@ -596,7 +596,7 @@ fn parse_fail_because_not_enough() {
 #[test]
 fn pipelined_query_okay() {
     let body = v!(b"$2\n3\n3\nSET1\nx3\n1002\n3\nGET1\nx");
-    let (ret, skip) = Parser::parse(&body).unwrap();
+    let (ret, skip) = Parser::parse(&body, QueryLimits::default()).unwrap();
     assert_eq!(skip, body.len());
     let query = pipelined_query(ret);
     assert_eq!(
@@ -608,7 +608,7 @@ fn pipelined_query_okay() {
 #[test]
 fn pipelined_query_okay_empty_elements() {
     let body = v!(b"$2\n3\n3\nSET0\n3\n1002\n3\nGET0\n");
-    let (ret, skip) = Parser::parse(&body).unwrap();
+    let (ret, skip) = Parser::parse(&body, QueryLimits::default()).unwrap();
     assert_eq!(skip, body.len());
     let query = pipelined_query(ret);
     assert_eq!(
@@ -624,7 +624,7 @@ fn pipelined_query_fail_because_not_enough() {
         .map(|i| full_payload.iter().cloned().take(i).collect())
         .collect();
     for body in samples {
-        let ret = Parser::parse(&body).unwrap_err();
+        let ret = Parser::parse(&body, QueryLimits::default()).unwrap_err();
         assert_eq!(ret, ParseError::NotEnough)
     }
 }
@@ -632,7 +632,7 @@ fn pipelined_query_fail_because_not_enough() {
 #[test]
 fn test_iter() {
     use super::{Parser, Query};
-    let (q, _fwby) = Parser::parse(b"*3\n3\nset1\nx3\n100").unwrap();
+    let (q, _fwby) = Parser::parse(b"*3\n3\nset1\nx3\n100", QueryLimits::default()).unwrap();
     let r = match q {
         Query::Simple(q) => q,
         _ => panic!("Wrong query"),
@@ -643,3 +643,58 @@ fn test_iter() {
     assert_eq!(iter.next().unwrap(), "x".as_bytes());
     assert_eq!(iter.next().unwrap(), "100".as_bytes());
 }
+
+#[test]
+fn parse_fail_because_arg_too_long() {
+    let body = v!(b"*3\n3\nSET1\nx3\n100");
+    assert_eq!(
+        Parser::parse(&body, QueryLimits::new(1024, 16, 2)).unwrap_err(),
+        ParseError::QueryTooLarge
+    );
+    // a claimed length is rejected before the argument itself arrives
+    let body = v!(b"*1\n1048576\n");
+    assert_eq!(
+        Parser::parse(&body, QueryLimits::new(1024, 16, 1024)).unwrap_err(),
+        ParseError::QueryTooLarge
+    );
+}
+
+#[test]
+fn parse_fail_because_pipeline_too_long() {
+    let body = v!(b"$2\n3\n3\nSET1\nx3\n1002\n3\nGET1\nx");
+    assert_eq!(
+        Parser::parse(&body, QueryLimits::new(1024, 1, 1024)).unwrap_err(),
+        ParseError::QueryTooLarge
+    );
+    let (ret, _) = Parser::parse(&body, QueryLimits::new(1024, 2, 1024)).unwrap();
+    assert_eq!(pipelined_query(ret).len(), 2);
+}
+
+#[test]
+fn parse_unbacked_count_is_not_enough() {
+    // we shouldn't allocate for elements that haven't arrived yet
+    let body = v!(b"*18446744073709551615\n");
+    assert_eq!(
+        Parser::parse(&body, QueryLimits::default()).unwrap_err(),
+        ParseError::NotEnough
+    );
+}
+
+#[test]
+fn fold_elements() {
+    use super::super::iter::Folded;
+    let folded = Folded::uppercase(b"lset");
+    assert!(folded.is_inline());
+    assert_eq!(folded.as_ref(), b"LSET");
+    let folded = Folded::lowercase(b"LSET");
+    assert!(folded.is_inline());
+    assert_eq!(folded.as_ref(), b"lset");
+    // statements can be longer than any action name
+    let statement = b"CREATE MODEL twitter.tweets(string, string)";
+    let folded = Folded::lowercase(statement);
+    assert!(!folded.is_inline());
+    assert_eq!(
+        folded.as_ref(),
+        b"create model twitter.tweets(string, string)"
+    );
+}
@@ -0,0 +1,219 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Bulk loading
+//!
+//! A bulk load is meant for loading a large dataset into an empty (or mostly empty) key/value
+//! table as fast as possible. `BULKLOAD BEGIN [<count>]` starts a bulk load into the current
+//! table, making room for `count` more keys up front so that the table doesn't have to grow
+//! (and rehash) while it's being loaded. `BULKLOAD PUT <key> <value> ...` then upserts pairs
+//! just like `USET`, except that:
+//! - every batch is recorded in the append-only log as a single `USET` (instead of being
+//!   checked and recorded like a query of its own)
+//! - the connections watching the keys aren't told about them
+//! - the keys aren't tracked as dirty one by one; the whole table is rewritten on the next save
+//!
+//! `BULKLOAD END` saves all the data to disk (just like a BGSAVE) and returns the number of
+//! loaded pairs. A bulk load can't be run on a node that replicas are streaming from (or on a
+//! node in cluster mode, where keys have to be routed to their slots); this is checked for
+//! every batch since either can change during a bulk load. If the connection is closed during
+//! a bulk load, the loaded pairs are kept and saved with the next BGSAVE
+
+use {
+    crate::{
+        actions::ActionResult,
+        corestore::{
+            memstore::ObjectID,
+            table::{DescribeTable, Table},
+            SharedSlice,
+        },
+        dbnet::{prelude::*, BufferedSocketStream},
+        kvengine::encoding::ENCODING_LUT_ITER_PAIR,
+        queryengine, services,
+        util::compiler,
+    },
+    core::str,
+    std::sync::Arc,
+};
+
+const BEGIN: &[u8] = b"begin";
+const PUT: &[u8] = b"put";
+const END: &[u8] = b"end";
+/// The action that a batch is recorded as
+const USET: &[u8] = b"USET";
+
+/// The most keys that we make room for up front. More keys can still be loaded; the table
+/// just grows as usual
+const MAX_RESERVED_KEYS: usize = 1 << 26;
+
+/// A bulk load that was started on a connection
+pub struct BulkLoad {
+    /// the table that the pairs are loaded into
+    table: Arc<Table>,
+    /// the keyspace and the table (the connection may switch to another table meanwhile)
+    ids: (Option<ObjectID>, Option<ObjectID>),
+    /// the number of pairs that were loaded
+    loaded: usize,
+}
+
+/// Run a `BULKLOAD BEGIN|PUT|END` query
+pub async fn bulkload<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    bulk: &mut Option<BulkLoad>,
+    mut act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len != 0)?;
+    let subaction = unsafe {
+        // SAFETY: We have already checked that there is at least one argument
+        act.next_lowercase_unchecked()
+    };
+    match subaction.as_ref() {
+        BEGIN => self::begin(handle, con, bulk, act).await,
+        PUT => self::put(handle, con, bulk, act).await,
+        END => {
+            ensure_length::<P>(act.len(), |len| len == 0)?;
+            self::end(handle, con, bulk).await
+        }
+        _ => util::err(P::RCODE_UNKNOWN_ACTION),
+    }
+}
+
+async fn begin<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    bulk: &mut Option<BulkLoad>,
+    mut act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len < 2)?;
+    if bulk.is_some() {
+        return util::err(P::RSTRING_BULKLOAD_ACTIVE);
+    }
+    if !self::is_available(handle) {
+        return util::err(P::RSTRING_BULKLOAD_UNAVAILABLE);
+    }
+    let count = match act.next() {
+        Some(count) => match str::from_utf8(count)
+            .ok()
+            .and_then(|c| c.parse::<usize>().ok())
+        {
+            Some(count) => count,
+            None => return util::err(P::RCODE_WRONGTYPE_ERR),
+        },
+        None => 0,
+    };
+    let kve = handle.get_table_with::<P, KVEBlob>()?;
+    let table = handle.get_ctable().unwrap_or_aerr::<P>()?;
+    kve.reserve(count.min(MAX_RESERVED_KEYS));
+    let (ks, tbl) = handle.get_ids();
+    *bulk = Some(BulkLoad {
+        table,
+        ids: (ks.cloned(), tbl.cloned()),
+        loaded: 0,
+    });
+    con._write_raw(P::RCODE_OKAY).await?;
+    Ok(())
+}
+
+/// Returns true if a bulk load can run on this node
+fn is_available(handle: &Corestore) -> bool {
+    !(handle.get_replication().is_streaming() || handle.get_cluster().is_enabled())
+}
+
+async fn put<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    bulk: &mut Option<BulkLoad>,
+    mut act: ActionIter<'a>,
+) -> ActionResult<()> {
+    let howmany = act.len();
+    ensure_length::<P>(howmany, |size| size & 1 == 0 && size != 0)?;
+    let bulk = match bulk {
+        Some(bulk) => bulk,
+        None => return util::err(P::RSTRING_BULKLOAD_NOT_STARTED),
+    };
+    if !registry::memory_okay() {
+        return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+    }
+    if !registry::state_okay() {
+        return util::err(P::RCODE_SERVER_ERR);
+    }
+    let kve = KVEBlob::try_get(&bulk.table).unwrap_or_aerr::<P>()?;
+    let encoding_is_okay = ENCODING_LUT_ITER_PAIR[kve.get_encoding_tuple()](&act);
+    if compiler::likely(encoding_is_okay) {
+        // just like any other write: a BGSAVE (or a slot migration, or a replica's sync)
+        // either sees the whole batch or none of it
+        let _txn_lock = registry::lock_txn_shared().await;
+        // a replica may have attached (or the cluster may have been enabled) since the last batch
+        if !self::is_available(handle) {
+            return util::err(P::RSTRING_BULKLOAD_UNAVAILABLE);
+        }
+        // a BGSAVE that ran since the last batch forgot about the dirty keys
+        kve.dirty_keys().mark_all();
+        let logging = queryengine::is_logging(handle);
+        let mut record = Vec::with_capacity(if logging { howmany + 1 } else { 0 });
+        record.push(USET);
+        while let (Some(key), Some(val)) = (act.next(), act.next()) {
+            kve.bulk_upsert_unchecked(SharedSlice::new(key), SharedSlice::new(val));
+            if logging {
+                record.extend([key, val]);
+            }
+        }
+        if logging {
+            let (ks, tbl) = &bulk.ids;
+            queryengine::log_record_in(handle, ks.as_ref(), tbl.as_ref(), record.into_iter());
+        }
+        bulk.loaded += howmany / 2;
+        con.write_usize(howmany / 2).await?;
+        Ok(())
+    } else {
+        util::err(P::RCODE_ENCODING_ERROR)
+    }
+}
+
+async fn end<C: BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    bulk: &mut Option<BulkLoad>,
+) -> ActionResult<()> {
+    let loaded = match bulk.take() {
+        Some(bulk) => bulk.loaded,
+        None => return util::err(P::RSTRING_BULKLOAD_NOT_STARTED),
+    };
+    let cloned_handle = handle.clone();
+    // the save blocks, so it's run on the blocking pool
+    let saved = tokio::task::spawn_blocking(move || {
+        services::bgsave::bgsave_blocking_section(cloned_handle)
+    })
+    .await
+    .expect("Something caused the background service to panic");
+    if saved {
+        con.write_usize(loaded).await?;
+        Ok(())
+    } else {
+        util::err(P::RCODE_SERVER_ERR)
+    }
+}
@@ -0,0 +1,338 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # The command table
+//!
+//! Every action that the query engine runs is declared along with its arity and flags: the
+//! actions that are dispatched by [`execute_stage`](super::execute_stage) are declared right
+//! where they're dispatched, while the actions that work with the connection state (and the
+//! ones that run scripts) are declared here. `SYS COMMANDS` lists all of them so that drivers
+//! can be generated from it. BlueQL statements (like `CREATE` or `USE`) have a grammar of their
+//! own and aren't listed
+
+/// The number of arguments that an action takes (not counting the action itself)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exactly(u8),
+    AtLeast(u8),
+}
+
+impl Arity {
+    /// Returns the least number of arguments that the action takes
+    pub const fn min(&self) -> u8 {
+        match *self {
+            Self::Exactly(count) | Self::AtLeast(count) => count,
+        }
+    }
+    pub const fn is_variadic(&self) -> bool {
+        matches!(self, Self::AtLeast(_))
+    }
+    /// Returns true if the action can take `count` arguments. The action may still reject
+    /// them (like `MSET` does with an odd number of arguments)
+    pub const fn accepts(&self, count: usize) -> bool {
+        match *self {
+            Self::Exactly(expected) => count == expected as usize,
+            Self::AtLeast(least) => count >= least as usize,
+        }
+    }
+}
+
+/// Returns the [`Arity`] of `n` (exactly `n` arguments) or `n..` (at least `n` arguments)
+macro_rules! arity {
+    ($count:literal) => {
+        $crate::queryengine::commands::Arity::Exactly($count)
+    };
+    ($count:literal..) => {
+        $crate::queryengine::commands::Arity::AtLeast($count)
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A bitmap of the properties of an action. Everything else in the server that treats actions
+/// differently (permissions, keyspace ACLs, the append-only log, cluster routing and so on)
+/// looks at these flags, so a new action only has to be declared once
+pub struct ActionFlags(u16);
+
+impl ActionFlags {
+    pub const NONE: Self = Self(0);
+    /// the action only reads data
+    pub const READONLY: Self = Self(1 << 0);
+    /// the action modifies data (it needs the `write` permission, or `admin` if that's set too,
+    /// and it isn't run on a replica)
+    pub const WRITE: Self = Self(1 << 1);
+    /// the action needs the `admin` permission
+    pub const ADMIN: Self = Self(1 << 2);
+    /// the action works with the state of the connection (and can't be queued in a
+    /// transaction)
+    pub const STATEFUL: Self = Self(1 << 3);
+    /// the query is recorded in the append-only log as is (and sent to replicas and
+    /// changefeeds). Writes that record something else (like the pops of `BLPOP`) don't set
+    /// this
+    pub const LOGGED: Self = Self(1 << 4);
+    /// anyone can run the action, even without the `read` permission
+    pub const UNRESTRICTED: Self = Self(1 << 5);
+    /// the action doesn't touch any keyspace
+    pub const KEYSPACE_FREE: Self = Self(1 << 6);
+    /// the action works on the whole server (only users that aren't restricted to some
+    /// keyspaces can run it)
+    pub const SERVER: Self = Self(1 << 7);
+    /// the action replaces (or deletes) whole values, so the peers can resolve it
+    /// last-writer-wins
+    pub const LWW: Self = Self(1 << 8);
+    /// the first argument is a key
+    pub const FIRST_KEY: Self = Self(1 << 9);
+    /// every argument is a key
+    pub const ALL_KEYS: Self = Self(1 << 10);
+    /// the arguments are key/value pairs
+    pub const KEY_PAIRS: Self = Self(1 << 11);
+    /// the flags that are listed by `SYS COMMANDS` (the rest are only used by the server)
+    const NAMES: [(&'static str, Self); 4] = [
+        ("readonly", Self::READONLY),
+        ("write", Self::WRITE),
+        ("admin", Self::ADMIN),
+        ("stateful", Self::STATEFUL),
+    ];
+    /// Returns true if all the flags in `other` are set
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+    /// Returns the names of the flags that are set
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        Self::NAMES
+            .iter()
+            .filter(|(_, flag)| self.contains(*flag))
+            .map(|(name, _)| *name)
+    }
+    /// Returns which of the arguments are keys
+    pub const fn key_layout(&self) -> KeyLayout {
+        if self.contains(Self::FIRST_KEY) {
+            KeyLayout::First
+        } else if self.contains(Self::ALL_KEYS) {
+            KeyLayout::All
+        } else if self.contains(Self::KEY_PAIRS) {
+            KeyLayout::Pairs
+        } else {
+            KeyLayout::Keyless
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Which of the arguments of an action are keys
+pub enum KeyLayout {
+    /// the action doesn't work on keys
+    Keyless,
+    /// the first argument is the key
+    First,
+    /// every argument is a key
+    All,
+    /// the arguments are key/value pairs
+    Pairs,
+}
+
+impl KeyLayout {
+    /// Returns true if the argument at `idx` is a key
+    pub const fn is_key(&self, idx: usize) -> bool {
+        match self {
+            Self::Keyless => false,
+            Self::First => idx == 0,
+            Self::All => true,
+            Self::Pairs => idx % 2 == 0,
+        }
+    }
+    /// Returns the keys in `args`
+    pub fn keys<T>(&self, args: impl IntoIterator<Item = T>) -> impl Iterator<Item = T> {
+        let layout = *self;
+        args.into_iter()
+            .enumerate()
+            .filter(move |(idx, _)| layout.is_key(*idx))
+            .map(|(_, arg)| arg)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An entry in the command table
+pub struct ActionSpec {
+    /// the name of the action (in uppercase)
+    pub name: &'static str,
+    pub arity: Arity,
+    pub flags: ActionFlags,
+}
+
+impl ActionSpec {
+    pub const fn new(name: &'static str, arity: Arity, flags: ActionFlags) -> Self {
+        Self { name, arity, flags }
+    }
+    /// A short description of the flags (like `write+variadic`), where `variadic` means that
+    /// the action takes at least [`Arity::min`] arguments. `none` is returned if no flag is set
+    pub fn describe_flags(&self) -> String {
+        let mut names: Vec<&str> = self.flags.names().collect();
+        if self.arity.is_variadic() {
+            names.push("variadic");
+        }
+        if names.is_empty() {
+            "none".to_owned()
+        } else {
+            names.join("+")
+        }
+    }
+}
+
+/// The actions that work with the state of the connection (see
+/// [`execute_stage_stateful`](super::execute_stage_stateful))
+pub const STATEFUL_ACTIONS: [ActionSpec; 15] = {
+    use ActionFlags as F;
+    const FREE: ActionFlags = F::STATEFUL.union(F::KEYSPACE_FREE);
+    const READ: ActionFlags = F::STATEFUL.union(F::READONLY);
+    const ADMIN: ActionFlags = F::STATEFUL.union(F::ADMIN);
+    const POP: ActionFlags = F::STATEFUL.union(F::WRITE).union(F::FIRST_KEY);
+    [
+        ActionSpec::new("MULTI", arity!(0), FREE),
+        ActionSpec::new("EXEC", arity!(0), FREE),
+        ActionSpec::new("DISCARD", arity!(0), FREE),
+        ActionSpec::new("WATCH", arity!(1..), READ.union(F::ALL_KEYS)),
+        ActionSpec::new("UNWATCH", arity!(0), FREE),
+        ActionSpec::new("WATCHPREFIX", arity!(1..), READ),
+        ActionSpec::new("UNWATCHPREFIX", arity!(0..), F::STATEFUL),
+        ActionSpec::new("MONITOR", arity!(0), FREE),
+        ActionSpec::new("UNMONITOR", arity!(0), FREE),
+        ActionSpec::new("SYNC", arity!(0), ADMIN.union(F::SERVER)),
+        ActionSpec::new("WAITSYNC", arity!(2), FREE),
+        ActionSpec::new("XREAD", arity!(4), READ),
+        ActionSpec::new("BLPOP", arity!(2), POP),
+        ActionSpec::new("BRPOP", arity!(2), POP),
+        ActionSpec::new("BULKLOAD", arity!(1..), ADMIN.union(F::WRITE)),
+    ]
+};
+
+/// The actions that run scripts (see [`scripting`](crate::scripting)). They take the
+/// transaction lock exclusively, so they can't be queued in a transaction either. A script's
+/// writes are logged as the queries that redo them (not as the script), and a replica runs
+/// the scripts that don't write
+pub const SCRIPT_ACTIONS: [ActionSpec; 2] = [
+    ActionSpec::new("EVAL", arity!(1..), ActionFlags::WRITE),
+    ActionSpec::new("EVALSHA", arity!(1..), ActionFlags::WRITE),
+];
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{ActionFlags, ActionSpec, Arity, KeyLayout},
+        crate::{
+            auth::{acl::Scope, Permissions},
+            queryengine,
+            storage::v1::aof,
+        },
+        std::collections::HashSet,
+    };
+
+    #[test]
+    fn flags_are_consistent() {
+        use ActionFlags as F;
+        let mut seen = HashSet::new();
+        for spec in queryengine::all_actions() {
+            assert!(seen.insert(spec.name), "{} is declared twice", spec.name);
+            let flags = spec.flags;
+            assert!(
+                !flags.contains(F::READONLY.union(F::WRITE)),
+                "{}",
+                spec.name
+            );
+            // only writes are logged, and the peers can only resolve writes that are logged
+            // and have keys
+            assert!(
+                !flags.contains(F::LOGGED) || flags.contains(F::WRITE),
+                "{}",
+                spec.name
+            );
+            if flags.contains(F::LWW) {
+                assert!(flags.contains(F::LOGGED), "{}", spec.name);
+                assert_ne!(flags.key_layout(), KeyLayout::Keyless, "{}", spec.name);
+            }
+            let layouts = [F::FIRST_KEY, F::ALL_KEYS, F::KEY_PAIRS];
+            let layouts = layouts.iter().filter(|layout| flags.contains(**layout));
+            assert!(layouts.count() <= 1, "{}", spec.name);
+            assert!(
+                !flags.contains(F::KEYSPACE_FREE.union(F::SERVER)),
+                "{}",
+                spec.name
+            );
+            assert!(
+                !flags.contains(F::UNRESTRICTED) || flags.contains(F::KEYSPACE_FREE),
+                "{}",
+                spec.name
+            );
+            assert_eq!(
+                flags.contains(F::STATEFUL),
+                super::STATEFUL_ACTIONS.contains(spec),
+                "{}",
+                spec.name
+            );
+        }
+    }
+
+    #[test]
+    fn derived_from_flags() {
+        assert!(aof::is_logged(b"set"));
+        assert!(!aof::is_logged(b"blpop"));
+        assert!(queryengine::writes(b"blpop"));
+        assert!(queryengine::writes(b"xadd"));
+        assert!(queryengine::writes(b"xtrim"));
+        assert!(queryengine::writes(
+            b"create model twitter.tweets(string, string)"
+        ));
+        assert!(!queryengine::writes(b"get"));
+        assert_eq!(Permissions::required_for(b"xadd"), Permissions::WRITE);
+        assert_eq!(Permissions::required_for(b"bulkload"), Permissions::ADMIN);
+        assert!(matches!(Scope::of(b"waitsync"), Scope::Free));
+        assert!(matches!(Scope::of(b"quota"), Scope::Server));
+        const ARGS: [&[u8]; 4] = [b"a", b"1", b"b", b"2"];
+        fn keys(action: &[u8]) -> Vec<&'static [u8]> {
+            queryengine::action_flags(action)
+                .key_layout()
+                .keys(ARGS)
+                .collect()
+        }
+        assert_eq!(keys(b"mset"), [b"a", b"b"]);
+        assert_eq!(keys(b"lpush"), [b"a"]);
+        assert_eq!(keys(b"mget"), ARGS);
+        assert!(keys(b"heya").is_empty());
+    }
+
+    #[test]
+    fn describe_flags() {
+        let get = ActionSpec::new("GET", Arity::Exactly(1), ActionFlags::READONLY);
+        assert_eq!(get.describe_flags(), "readonly");
+        let flags = ActionFlags::STATEFUL.union(ActionFlags::ADMIN);
+        let bulkload = ActionSpec::new("BULKLOAD", Arity::AtLeast(1), flags);
+        assert_eq!(bulkload.describe_flags(), "admin+stateful+variadic");
+        let whereami = ActionSpec::new("WHEREAMI", Arity::Exactly(0), ActionFlags::NONE);
+        assert_eq!(whereami.describe_flags(), "none");
+    }
+}
@@ -0,0 +1,202 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Query hooks
+//!
+//! Every query (or stage of a pipeline) goes through the same chain of stages before its
+//! action runs:
+//! 1. **Auth**: the user needs the permissions (and keyspace access) that the action requires
+//! (see [`authorize`](super::authorize))
+//! 2. **Admission**: every registered hook gets to turn the query away, in the order in which
+//! the hooks were registered. This is where rate limits and quotas go
+//! 3. **Metrics**: the query is counted in the server statistics
+//! 4. **Action**: the action runs, after which every hook is told how it went
+//!
+//! Hooks are registered on the [`Hooks`] of a [`Corestore`](crate::corestore::Corestore),
+//! which are shared by every connection, so that features that need to see every action
+//! (like auditing or quotas) don't need to touch the action handlers. Hooks are run inline
+//! with the query, so they need to be quick
+
+use {
+    crate::{auth::provider::AuthID, corestore::memstore::ObjectID, protocol::UnsafeSlice},
+    arc_swap::ArcSwap,
+    core::fmt,
+    std::{net::IpAddr, sync::Arc},
+};
+
+/// A query, as seen by a hook
+pub struct QueryInfo<'a> {
+    action: &'a [u8],
+    args: &'a [UnsafeSlice],
+    user: Option<AuthID>,
+    peer: Option<IpAddr>,
+    keyspace: Option<ObjectID>,
+}
+
+impl<'a> QueryInfo<'a> {
+    pub(super) fn new(
+        action: &'a [u8],
+        args: &'a [UnsafeSlice],
+        user: Option<AuthID>,
+        peer: Option<IpAddr>,
+        keyspace: Option<ObjectID>,
+    ) -> Self {
+        Self {
+            action,
+            args,
+            user,
+            peer,
+            keyspace,
+        }
+    }
+    /// Returns the action (in lowercase)
+    pub const fn action(&self) -> &[u8] {
+        self.action
+    }
+    /// Returns the arguments that were passed to the action
+    pub fn args(&self) -> impl Iterator<Item = &[u8]> {
+        self.args.iter().map(|arg| unsafe {
+            // UNSAFE(@ohsayan): The presence of the connection guarantees that this
+            // won't suddenly become invalid
+            arg.as_slice()
+        })
+    }
+    /// Returns the user that ran the query (if they were logged in)
+    pub fn user(&self) -> Option<&AuthID> {
+        self.user.as_ref()
+    }
+    /// Returns the IP of the client that ran the query (if it didn't use a Unix domain socket)
+    pub const fn peer(&self) -> Option<IpAddr> {
+        self.peer
+    }
+    /// Returns the keyspace that the connection was using (if any)
+    pub fn keyspace(&self) -> Option<&ObjectID> {
+        self.keyspace.as_ref()
+    }
+}
+
+/// Something that wants to see every query that is run
+pub trait QueryHook: Send + Sync {
+    /// Called before the action runs. If an error is returned, the query is turned away and
+    /// the error is sent to the client as a respstring
+    fn admit(&self, _query: &QueryInfo) -> Result<(), String> {
+        Ok(())
+    }
+    /// Called once the action has run. `okay` is false if the action failed
+    fn finished(&self, _query: &QueryInfo, _okay: bool) {}
+}
+
+#[derive(Default)]
+/// The hooks that every query passes through
+pub struct Hooks {
+    hooks: ArcSwap<Vec<Arc<dyn QueryHook>>>,
+}
+
+impl Hooks {
+    /// Add a hook to the end of the chain. It applies to every query from now on
+    pub fn register(&self, hook: Arc<dyn QueryHook>) {
+        self.hooks.rcu(|hooks| {
+            let mut hooks = Vec::clone(hooks);
+            hooks.push(hook.clone());
+            hooks
+        });
+    }
+    /// Returns the number of registered hooks
+    pub fn count(&self) -> usize {
+        self.hooks.load().len()
+    }
+    /// Run the admission stage for a query, returning the error of the first hook that turned
+    /// it away (the hooks after it aren't run)
+    pub fn admit(&self, query: &QueryInfo) -> Result<(), String> {
+        self.hooks
+            .load()
+            .iter()
+            .try_for_each(|hook| hook.admit(query))
+    }
+    /// Tell every hook that a query has run
+    pub fn finished(&self, query: &QueryInfo, okay: bool) {
+        self.hooks
+            .load()
+            .iter()
+            .for_each(|hook| hook.finished(query, okay))
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Hooks, QueryHook, QueryInfo},
+        std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    /// Turns away `DEL`s and counts the queries that ran
+    #[derive(Default)]
+    struct NoDeletes {
+        ran: AtomicUsize,
+    }
+
+    impl QueryHook for NoDeletes {
+        fn admit(&self, query: &QueryInfo) -> Result<(), String> {
+            if query.action() == b"del" {
+                Err("no-deletes".to_owned())
+            } else {
+                Ok(())
+            }
+        }
+        fn finished(&self, _query: &QueryInfo, okay: bool) {
+            if okay {
+                self.ran.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    #[test]
+    fn admit_and_finish() {
+        let hooks = Hooks::default();
+        let query = QueryInfo::new(b"del", &[], None, None, None);
+        assert_eq!(hooks.admit(&query), Ok(()));
+        let hook = Arc::new(NoDeletes::default());
+        hooks.register(hook.clone());
+        assert_eq!(hooks.count(), 1);
+        assert_eq!(hooks.admit(&query), Err("no-deletes".to_owned()));
+        let query = QueryInfo::new(b"get", &[], None, None, None);
+        assert_eq!(hooks.admit(&query), Ok(()));
+        hooks.finished(&query, true);
+        hooks.finished(&query, false);
+        assert_eq!(hook.ran.load(Ordering::Relaxed), 1);
+    }
+}
@@ -28,20 +28,139 @@
 
 use crate::{
     actions::{self, ActionError, ActionResult},
-    admin, auth, blueql,
-    corestore::Corestore,
-    dbnet::{prelude::*, BufferedSocketStream},
-    protocol::{iter::AnyArrayIter, PipelinedQuery, SimpleQuery, UnsafeSlice},
+    admin,
+    auth::{self, acl::Scope, audit::AuditEvent, Permissions},
+    blueql,
+    cluster::{self, Route},
+    corestore::{
+        memstore::ObjectID,
+        table::{DataModel, Table},
+        Corestore,
+    },
+    dbnet::{prelude::*, BufferStream, BufferedSocketStream},
+    kvengine::{self, notify::KeyChange},
+    protocol::{
+        iter::{AnyArrayIter, Folded},
+        PipelinedQuery, SimpleQuery, UnsafeSlice,
+    },
+    replication::{self, FeedEvent, FeedSubscription},
+    scripting,
+    storage::v1::aof,
+    streams,
+};
+use std::sync::Arc;
+
+mod bulk;
+#[macro_use]
+pub mod commands;
+pub mod hooks;
+pub mod monitor;
+pub mod notify;
+pub mod quota;
+pub mod ratelimit;
+#[cfg(test)]
+mod tests;
+mod txn;
+use {
+    bulk::BulkLoad,
+    commands::{ActionFlags, ActionSpec},
+    hooks::QueryInfo,
+    monitor::{MonitorSubscription, MonitoredQuery},
+    notify::KeyWatch,
+    txn::Transaction,
 };
 
 pub type ActionIter<'a> = AnyArrayIter<'a>;
 
 const ACTION_AUTH: &[u8] = b"auth";
+const ACTION_MULTI: &[u8] = b"multi";
+const ACTION_EXEC: &[u8] = b"exec";
+const ACTION_DISCARD: &[u8] = b"discard";
+const ACTION_WATCH: &[u8] = b"watch";
+const ACTION_UNWATCH: &[u8] = b"unwatch";
+const ACTION_WATCHPREFIX: &[u8] = b"watchprefix";
+const ACTION_UNWATCHPREFIX: &[u8] = b"unwatchprefix";
+const ACTION_MONITOR: &[u8] = b"monitor";
+const ACTION_UNMONITOR: &[u8] = b"unmonitor";
+const ACTION_SYNC: &[u8] = b"sync";
+const ACTION_WAITSYNC: &[u8] = b"waitsync";
+const ACTION_XREAD: &[u8] = b"xread";
+const ACTION_BLPOP: &[u8] = b"blpop";
+const ACTION_BRPOP: &[u8] = b"brpop";
+const ACTION_BULKLOAD: &[u8] = b"bulkload";
+const ACTION_EVAL: &[u8] = b"eval";
+const ACTION_EVALSHA: &[u8] = b"evalsha";
+const ACTION_SYS: &[u8] = b"sys";
+
+#[derive(Default)]
+/// The state that the query engine keeps for a connection
+pub struct ConnectionState {
+    /// the transaction state
+    txn: Transaction,
+    /// the watched key prefixes, if any
+    watch: Option<KeyWatch>,
+    /// the subscription to the monitor, if the connection is monitoring queries
+    monitor: Option<MonitorSubscription>,
+    /// the subscription to the writes, if the connection is a replica that synced with us
+    replica: Option<FeedSubscription>,
+    /// the bulk load, if one was started on the connection
+    bulk: Option<BulkLoad>,
+    /// the replication offset after the last query of the connection that could have written
+    write_offset: u64,
+}
+
+impl ConnectionState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Wait for the next change to a watched key. If we fell behind, the number of changes
+    /// that we missed is returned as an error instead. This never completes if no keys
+    /// are watched
+    pub async fn next_key_change(&mut self) -> Result<KeyChange, u64> {
+        match self.watch {
+            Some(ref mut watch) => watch.next().await,
+            None => core::future::pending().await,
+        }
+    }
+    /// Wait for the next query to be sent to the monitor. If we fell behind, the number of
+    /// queries that we missed is returned as an error instead. This never completes if the
+    /// connection isn't monitoring queries
+    pub async fn next_monitored_query(&mut self) -> Result<Arc<MonitoredQuery>, u64> {
+        match self.monitor {
+            Some(ref mut monitor) => monitor.next().await,
+            None => core::future::pending().await,
+        }
+    }
+    /// Returns true if the connection is a replica that synced with us
+    pub fn is_replica(&self) -> bool {
+        self.replica.is_some()
+    }
+    /// Wait for the next write (or heartbeat) to be sent to the replica. If we fell behind, the
+    /// number of writes that we missed is returned as an error instead. This never completes if the
+    /// connection isn't a replica
+    pub async fn next_replicated_write(&mut self) -> Result<FeedEvent, u64> {
+        match self.replica {
+            Some(ref mut replica) => replica.next().await,
+            None => core::future::pending().await,
+        }
+    }
+    /// Record that the replica has applied the writes up to `offset`. This does nothing if the
+    /// connection isn't a replica
+    pub fn ack_replicated(&self, offset: u64) {
+        if let Some(ref replica) = self.replica {
+            replica.ack(offset);
+        }
+    }
+}
 
+/// Declare the actions that are dispatched by a stage, along with their arity and flags (see
+/// the [`commands`] module). This generates the dispatch function and the command table
 macro_rules! gen_constants_and_matches {
     (
-        $con:expr, $buf:ident, $db:ident, $($action:ident => $fns:path),*,
-        {$($action2:ident => $fns2:expr),*}
+        $(#[$attr:meta])*
+        fn $dispatch:ident($con:ident, $iter:ident, $db:ident, $auth:ident, $queue:ident, $stage:ident);
+        $($action:ident[$($flags:ident)|+; $($arity:tt)+] => $fns:path),*,
+        {$($action2:ident[$($flags2:ident)|+; $($arity2:tt)+] => $fns2:expr),*}
     ) => {
         mod tags {
             //! This module is a collection of tags/strings used for evaluating queries
@@ -53,30 +172,179 @@ macro_rules! gen_constants_and_matches {
                 pub const $action2: &[u8] = stringify!($action2).as_bytes();
             )*
         }
-        let first_slice = $buf.next().unwrap_or_custom_aerr(P::RCODE_PACKET_ERR)?;
-        let first = first_slice.to_ascii_uppercase();
-        match first.as_ref() {
+        /// The actions that are dispatched by a stage
+        const DISPATCHED_ACTIONS: &[ActionSpec] = &[
             $(
-                tags::$action => $fns($db, $con, $buf).await?,
+                ActionSpec::new(
+                    stringify!($action),
+                    arity!($($arity)+),
+                    ActionFlags::NONE$(.union(ActionFlags::$flags))+,
+                ),
             )*
             $(
-                tags::$action2 => $fns2.await?,
+                ActionSpec::new(
+                    stringify!($action2),
+                    arity!($($arity2)+),
+                    ActionFlags::NONE$(.union(ActionFlags::$flags2))+,
+                ),
             )*
-            _ => {
-                blueql::execute($db, $con, first_slice, $buf.len()).await?;
+        ];
+        $(#[$attr])*
+        async fn $dispatch<'a, P: ProtocolSpec, C: BufferedSocketStream>(
+            $db: &mut Corestore,
+            $con: &mut Connection<C, P>,
+            $auth: &mut AuthProviderHandle,
+            $queue: Option<&mut Transaction>,
+            $stage: &[UnsafeSlice],
+        ) -> ActionResult<()> {
+            let mut $iter = unsafe {
+                // UNSAFE(@ohsayan): The presence of the connection guarantees that this
+                // won't suddenly become invalid
+                AnyArrayIter::new($stage.iter())
+            };
+            let first_slice = $iter.next().unwrap_or_custom_aerr(P::RCODE_PACKET_ERR)?;
+            let first = Folded::uppercase(first_slice);
+            if let Some(txn) = $queue {
+                // we're in a transaction; only simple actions can be queued, and only with
+                // a number of arguments that they can take
+                let arity = match first.as_ref() {
+                    $(
+                        tags::$action => arity!($($arity)+),
+                    )*
+                    _ => {
+                        txn.abort();
+                        return util::err(P::RSTRING_TXN_BAD_QUERY);
+                    }
+                };
+                if arity.accepts($iter.len()) {
+                    txn.enqueue($stage);
+                    $con.write_string("QUEUED").await?;
+                    return Ok(());
+                } else {
+                    txn.abort();
+                    return util::err(P::RCODE_ACTION_ERR);
+                }
+            }
+            match first.as_ref() {
+                $(
+                    tags::$action => $fns($db, $con, $iter).await?,
+                )*
+                $(
+                    tags::$action2 => $fns2.await?,
+                )*
+                _ => {
+                    blueql::execute($db, $con, $auth.provider(), first_slice, $iter.len()).await?;
+                }
             }
+            Ok(())
         }
     };
 }
 
+gen_constants_and_matches!(
+    /// Run a stage with the action that it names. If `queue` is provided, the stage is
+    /// validated and queued into the transaction instead of being run
+    fn dispatch(con, iter, db, auth, queue, stage);
+    GET[READONLY | FIRST_KEY; 1] => actions::get::get,
+    SET[WRITE | LOGGED | LWW | FIRST_KEY; 2..] => actions::set::set,
+    UPDATE[WRITE | LOGGED | LWW | FIRST_KEY; 2] => actions::update::update,
+    CAS[WRITE | LOGGED | FIRST_KEY; 3] => actions::cas::cas,
+    DEL[WRITE | LOGGED | LWW | ALL_KEYS; 1..] => actions::del::del,
+    DUMP[READONLY | FIRST_KEY; 1] => actions::dump::dump,
+    RESTORE[WRITE | LOGGED | FIRST_KEY; 2..] => actions::dump::restore,
+    MDEL[WRITE | LOGGED | LWW | ALL_KEYS; 1..] => actions::del::del,
+    HEYA[KEYSPACE_FREE; 0..] => actions::heya::heya,
+    EXISTS[READONLY | ALL_KEYS; 1..] => actions::exists::exists,
+    MSET[WRITE | LOGGED | LWW | KEY_PAIRS; 2..] => actions::mset::mset,
+    MGET[READONLY | ALL_KEYS; 1..] => actions::mget::mget,
+    MUPDATE[WRITE | LOGGED | LWW | KEY_PAIRS; 2..] => actions::mupdate::mupdate,
+    SSET[WRITE | LOGGED | KEY_PAIRS; 2..] => actions::strong::sset,
+    SDEL[WRITE | LOGGED | ALL_KEYS; 1..] => actions::strong::sdel,
+    SUPDATE[WRITE | LOGGED | KEY_PAIRS; 2..] => actions::strong::supdate,
+    DBSIZE[READONLY; 0..] => actions::dbsize::dbsize,
+    DBHASH[READONLY; 0..] => actions::dbhash::dbhash,
+    HASHRANGE[READONLY; 1..] => actions::dbhash::hashrange,
+    USET[WRITE | LOGGED | LWW | KEY_PAIRS; 2..] => actions::uset::uset,
+    KEYLEN[READONLY | FIRST_KEY; 1] => actions::keylen::keylen,
+    STRLEN[READONLY | FIRST_KEY; 1] => actions::keylen::keylen,
+    APPEND[WRITE | LOGGED | FIRST_KEY; 2] => actions::append::append,
+    TYPE[READONLY | FIRST_KEY; 1] => actions::keytype::keytype,
+    STRSET[WRITE | LOGGED | LWW | FIRST_KEY; 2] => actions::typed::strset,
+    STRGET[READONLY | FIRST_KEY; 1] => actions::typed::strget,
+    BINSET[WRITE | LOGGED | LWW | FIRST_KEY; 2] => actions::typed::binset,
+    BINGET[READONLY | FIRST_KEY; 1] => actions::typed::binget,
+    INCR[WRITE | LOGGED | FIRST_KEY; 1] => actions::counter::incr,
+    DECR[WRITE | LOGGED | FIRST_KEY; 1] => actions::counter::decr,
+    INCRBY[WRITE | LOGGED | FIRST_KEY; 2] => actions::counter::incrby,
+    DECRBY[WRITE | LOGGED | FIRST_KEY; 2] => actions::counter::decrby,
+    MKSNAP[ADMIN | SERVER; 0..] => admin::mksnap::mksnap,
+    LSSNAP[ADMIN | SERVER; 0] => admin::snapshot::lssnap,
+    RESTORESNAP[ADMIN | WRITE | SERVER; 1] => admin::snapshot::restoresnap,
+    DELPREFIX[ADMIN | WRITE; 1..] => admin::delprefix::delprefix,
+    LSKEYS[READONLY; 0..] => actions::lskeys::lskeys,
+    POP[WRITE | LOGGED | FIRST_KEY; 1] => actions::pop::pop,
+    MPOP[WRITE | LOGGED | ALL_KEYS; 1..] => actions::mpop::mpop,
+    LSET[WRITE | LOGGED | FIRST_KEY; 1..] => actions::lists::lset,
+    LGET[READONLY | FIRST_KEY; 1..] => actions::lists::lget::lget,
+    LMOD[WRITE | LOGGED | FIRST_KEY; 2..] => actions::lists::lmod::lmod,
+    LPUSH[WRITE | LOGGED | FIRST_KEY; 2..] => actions::lists::deque::lpush,
+    RPUSH[WRITE | LOGGED | FIRST_KEY; 2..] => actions::lists::deque::rpush,
+    LPOP[WRITE | LOGGED | FIRST_KEY; 1] => actions::lists::deque::lpop,
+    RPOP[WRITE | LOGGED | FIRST_KEY; 1] => actions::lists::deque::rpop,
+    LRANGE[READONLY | FIRST_KEY; 2..] => actions::lists::lget::lrange,
+    SADD[WRITE | LOGGED | FIRST_KEY; 2..] => actions::sets::sadd,
+    SREM[WRITE | LOGGED | FIRST_KEY; 2..] => actions::sets::srem,
+    SMEMBERS[READONLY | FIRST_KEY; 1] => actions::sets::smembers,
+    SISMEMBER[READONLY | FIRST_KEY; 2] => actions::sets::sismember,
+    SCARD[READONLY | FIRST_KEY; 1] => actions::sets::scard,
+    SINTER[READONLY | ALL_KEYS; 1..] => actions::sets::sinter,
+    SUNION[READONLY | ALL_KEYS; 1..] => actions::sets::sunion,
+    SDIFF[READONLY | ALL_KEYS; 1..] => actions::sets::sdiff,
+    HSET[WRITE | LOGGED | FIRST_KEY; 3..] => actions::hashes::hset,
+    HGET[READONLY | FIRST_KEY; 2] => actions::hashes::hget,
+    HDEL[WRITE | LOGGED | FIRST_KEY; 2..] => actions::hashes::hdel,
+    HGETALL[READONLY | FIRST_KEY; 1] => actions::hashes::hgetall,
+    ZADD[WRITE | LOGGED | FIRST_KEY; 3..] => actions::zsets::zadd,
+    ZREM[WRITE | LOGGED | FIRST_KEY; 2..] => actions::zsets::zrem,
+    ZRANGEBYSCORE[READONLY | FIRST_KEY; 3] => actions::zsets::zrangebyscore,
+    ZRANK[READONLY | FIRST_KEY; 2] => actions::zsets::zrank,
+    WHEREAMI[KEYSPACE_FREE; 0] => actions::whereami::whereami,
+    EXPIRE[WRITE | LOGGED | FIRST_KEY; 2] => actions::expire::expire,
+    TTL[READONLY | FIRST_KEY; 1] => actions::expire::ttl,
+    PERSIST[WRITE | LOGGED | FIRST_KEY; 1] => actions::expire::persist,
+    SETEX[WRITE | LOGGED | FIRST_KEY; 3] => actions::expire::setex,
+    RANDOMKEY[READONLY; 0] => actions::randomkey::randomkey,
+    SAMPLE[READONLY; 1..] => actions::sample::sample,
+    REPLICAOF[ADMIN | SERVER; 2..] => replication::replicaof,
+    IMPORTKEY[WRITE | LOGGED | FIRST_KEY; 3] => cluster::importkey,
+    XADD[WRITE; 2..] => streams::xadd,
+    XRANGE[READONLY; 3..] => streams::xrange,
+    XLEN[READONLY; 1] => streams::xlen,
+    XTRIM[WRITE; 2] => streams::xtrim,
+    XFEED[ADMIN; 1..] => streams::xfeed,
+    {
+        // actions that need other arguments
+        AUTH[UNRESTRICTED | KEYSPACE_FREE; 1..] => auth::auth(con, auth, iter),
+        CONTEXT[UNRESTRICTED | KEYSPACE_FREE; 0] => actions::context::context(db, con, auth, iter),
+        FLUSHDB[WRITE | LOGGED; 0..] => actions::flushdb::flushdb(db, con, auth, iter),
+        LOGLEVEL[KEYSPACE_FREE; 0..] => admin::loglevel::loglevel(con, auth, iter),
+        SYS[KEYSPACE_FREE; 1..] => admin::sys::sys(db, con, auth, iter),
+        REPLICA[ADMIN | SERVER; 1..] => replication::replica(db, con, auth, iter),
+        CLUSTER[KEYSPACE_FREE; 1..] => cluster::cluster(db, con, auth, iter),
+        SCRIPT[KEYSPACE_FREE; 1..] => scripting::script(db, con, auth, iter),
+        QUOTA[SERVER; 1..] => admin::quota::quota(db, con, auth, iter)
+    }
+);
+
 action! {
     /// Execute queries for an anonymous user
     fn execute_simple_noauth(
-        _db: &mut Corestore,
+        db: &mut Corestore,
         con: &mut Connection<C, P>,
         auth: &mut AuthProviderHandle,
         buf: SimpleQuery
     ) {
+        db.get_stats().record_commands(1);
         let bufref = buf.as_slice();
         let mut iter = unsafe {
             // UNSAFE(@ohsayan): The presence of the connection guarantees that this
@@ -85,6 +353,7 @@ action! {
         };
         match iter.next_lowercase().unwrap_or_custom_aerr(P::RCODE_PACKET_ERR)?.as_ref() {
             ACTION_AUTH => auth::auth_login_only(con, auth, iter).await,
+            ACTION_SYS => admin::sys::sys_noauth(con, iter).await,
             _ => util::err(P::AUTH_CODE_BAD_CREDENTIALS),
         }
     }
@@ -93,70 +362,397 @@ action! {
         db: &mut Corestore,
         con: &mut Connection<C, P>,
         auth: &mut AuthProviderHandle,
+        state: &mut ConnectionState,
         buf: SimpleQuery
     ) {
-        self::execute_stage(db, con, auth, buf.as_slice()).await
+        self::execute_stage_stateful(db, con, auth, state, buf.as_slice()).await
     }
 }
 
-async fn execute_stage<'a, P: ProtocolSpec, C: BufferedSocketStream>(
+/// Execute a stage, taking the connection's state into account. Queries that work with the
+/// connection state are handled here; any other query is either queued (if a transaction
+/// was started) or run while holding the transaction lock shared
+async fn execute_stage_stateful<'a, P: ProtocolSpec, C: BufferedSocketStream>(
     db: &mut Corestore,
     con: &mut Connection<C, P>,
     auth: &mut AuthProviderHandle,
+    state: &mut ConnectionState,
     buf: &[UnsafeSlice],
 ) -> ActionResult<()> {
+    let ConnectionState {
+        txn,
+        watch,
+        monitor,
+        replica,
+        bulk,
+        write_offset,
+    } = state;
     let mut iter = unsafe {
         // UNSAFE(@ohsayan): The presence of the connection guarantees that this
         // won't suddenly become invalid
         AnyArrayIter::new(buf.iter())
     };
+    let action = iter
+        .next_lowercase()
+        .unwrap_or_custom_aerr(P::RCODE_PACKET_ERR)?;
+    let is_script = [ACTION_EVAL, ACTION_EVALSHA].contains(&action.as_ref());
+    // the stages that every query goes through before its action runs (see the `hooks`
+    // module). First, make sure that the user is allowed to run this (queued actions are
+    // checked here too)
+    self::authorize::<P>(db, auth, action.as_ref())?;
+    // a replica only takes writes from its primary (which are replayed without a connection).
+    // Scripts check this for every write that they make, so that the ones which only read
+    // can still be run
+    if db.get_replication().is_replica() && self::writes(action.as_ref()) && !is_script {
+        return util::err(P::RSTRING_READONLY_REPLICA);
+    }
+    // and it doesn't serve reads if it lags too far behind its primary
+    if db.get_replication().is_replica()
+        && self::is_read_only(action.as_ref())
+        && db
+            .get_replication()
+            .lags_behind(db.get_runtime_config().load().maxlag)
+    {
+        return util::err(P::RSTRING_REPLICA_LAGGING);
+    }
+    // in multi-primary mode, it only takes the writes that its peers can resolve
+    if db.get_replication().peers().is_enabled()
+        && !self::is_resolvable(db, action.as_ref(), txn.is_active())
     {
-        gen_constants_and_matches!(
-            con, iter, db,
-            GET => actions::get::get,
-            SET => actions::set::set,
-            UPDATE => actions::update::update,
-            DEL => actions::del::del,
-            HEYA => actions::heya::heya,
-            EXISTS => actions::exists::exists,
-            MSET => actions::mset::mset,
-            MGET => actions::mget::mget,
-            MUPDATE => actions::mupdate::mupdate,
-            SSET => actions::strong::sset,
-            SDEL => actions::strong::sdel,
-            SUPDATE => actions::strong::supdate,
-            DBSIZE => actions::dbsize::dbsize,
-            FLUSHDB => actions::flushdb::flushdb,
-            USET => actions::uset::uset,
-            KEYLEN => actions::keylen::keylen,
-            MKSNAP => admin::mksnap::mksnap,
-            LSKEYS => actions::lskeys::lskeys,
-            POP => actions::pop::pop,
-            MPOP => actions::mpop::mpop,
-            LSET => actions::lists::lset,
-            LGET => actions::lists::lget::lget,
-            LMOD => actions::lists::lmod::lmod,
-            WHEREAMI => actions::whereami::whereami,
-            SYS => admin::sys::sys,
-            {
-                // actions that need other arguments
-                AUTH => auth::auth(con, auth, iter)
+        return util::err(P::RSTRING_NOT_LWW);
+    }
+    // then let the hooks turn it away
+    let query = QueryInfo::new(
+        action.as_ref(),
+        &buf[1..],
+        auth.provider().current_user().cloned(),
+        auth.provider().peer(),
+        db.get_ids().0.cloned(),
+    );
+    if let Err(reason) = db.get_hooks().admit(&query) {
+        con._write_raw(&P::encode_respstring(&reason)).await?;
+        return Ok(());
+    }
+    // and count it
+    db.get_stats().record_commands(1);
+    let ret = match action.as_ref() {
+        ACTION_MULTI => txn::multi(con, txn, iter).await,
+        ACTION_EXEC => txn::exec(db, con, auth, txn, iter).await,
+        ACTION_DISCARD => txn::discard(con, txn, iter).await,
+        ACTION_WATCH => txn::watch(db, con, txn, iter).await,
+        ACTION_UNWATCH => txn::unwatch(con, txn, iter).await,
+        ACTION_WATCHPREFIX => notify::watchprefix(db, con, watch, iter).await,
+        ACTION_UNWATCHPREFIX => notify::unwatchprefix(con, watch, iter).await,
+        ACTION_MONITOR => monitor::monitor(db, con, auth, monitor, iter).await,
+        ACTION_UNMONITOR => monitor::unmonitor(con, monitor, iter).await,
+        ACTION_SYNC => replication::sync(db, con, replica, iter).await,
+        ACTION_WAITSYNC => replication::waitsync(db, con, *write_offset, iter).await,
+        ACTION_XREAD => streams::xread(db, con, iter).await,
+        ACTION_BLPOP | ACTION_BRPOP if txn.is_active() => {
+            // these can block for a while, and a transaction runs all at once
+            txn.abort();
+            util::err(P::RSTRING_TXN_BAD_QUERY)
+        }
+        ACTION_BLPOP | ACTION_BRPOP => {
+            if self::route_query(db, con, action.as_ref(), &iter, false).await? {
+                if action.as_ref() == ACTION_BLPOP {
+                    actions::lists::deque::blpop(db, con, iter).await
+                } else {
+                    actions::lists::deque::brpop(db, con, iter).await
+                }
+            } else {
+                Ok(())
+            }
+        }
+        ACTION_BULKLOAD => bulk::bulkload(db, con, bulk, iter).await,
+        ACTION_EVAL | ACTION_EVALSHA if txn.is_active() => {
+            // scripts are atomic already, and they take the transaction lock exclusively
+            txn.abort();
+            util::err(P::RSTRING_TXN_BAD_QUERY)
+        }
+        ACTION_EVAL => scripting::eval(db, con, iter).await,
+        ACTION_EVALSHA => scripting::evalsha(db, con, iter).await,
+        _ if txn.is_active() => {
+            if self::route_query(db, con, action.as_ref(), &iter, true).await? {
+                self::execute_stage(db, con, auth, Some(txn), buf).await
+            } else {
+                Ok(())
             }
-        );
+        }
+        _ => {
+            // the response is collected while we hold the lock and is written once we've let
+            // go of it (just like EXEC does), so a client that doesn't read its responses can't
+            // hold up the queries that need the lock exclusively (and the ones queued behind
+            // them)
+            let mut response = Connection::<_, P>::new(BufferStream::default());
+            let ret = {
+                let _txn_lock = registry::lock_txn_shared().await;
+                // in multi-primary mode, the writes that are pushed to the peers are made (and
+                // stamped) one at a time
+                let peers = db.get_replication().peers();
+                let lww = peers.is_enabled() && replication::is_lww(action.as_ref());
+                let _ordered = if lww { Some(peers.order().await) } else { None };
+                // this is routed under the lock since a slot migration moves keys while holding
+                // the lock exclusively
+                match self::route_query(db, &mut response, action.as_ref(), &iter, false).await {
+                    Ok(true) => {
+                        let ret = self::execute_stage(db, &mut response, auth, None, buf).await;
+                        if lww && ret.is_ok() {
+                            let args: Vec<&[u8]> = buf[1..]
+                                .iter()
+                                .map(|arg| unsafe {
+                                    // UNSAFE(@ohsayan): The presence of the connection
+                                    // guarantees that this won't suddenly become invalid
+                                    arg.as_slice()
+                                })
+                                .collect();
+                            db.get_replication()
+                                .peers()
+                                .record(db, action.as_ref(), &args);
+                        }
+                        ret
+                    }
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            };
+            // whatever was written before an error goes out before the error does
+            let response = response.take_buffered().await?;
+            con.write_streamed(&response).await?;
+            ret
+        }
+    };
+    if self::writes(action.as_ref()) || action.as_ref() == ACTION_EXEC {
+        // whatever this wrote is (at the latest) at the current offset
+        *write_offset = db.get_replication().offset();
+    }
+    db.get_hooks().finished(&query, ret.is_ok());
+    ret
+}
+
+/// Make sure that the user is allowed to run `action` (in lowercase) on the current table
+pub fn authorize<P: ProtocolSpec>(
+    db: &Corestore,
+    auth: &mut AuthProviderHandle,
+    action: &[u8],
+) -> ActionResult<()> {
+    // a lapsed session has no privileges until the client logs in again
+    auth.provider_mut().refresh_session();
+    let provider = auth.provider();
+    let required = Permissions::required_for(action);
+    provider.ensure_permissions::<P>(required)?;
+    match Scope::of(action) {
+        Scope::CurrentKeyspace => {
+            provider.ensure_keyspace_access::<P>(db.get_ids().0.map(|ks| ks.as_slice()))?
+        }
+        Scope::Server => provider.ensure_keyspace_access::<P>(None)?,
+        // statements are checked when they're executed
+        Scope::Free | Scope::Statement => {}
+    }
+    if required.contains(Permissions::ADMIN) {
+        provider.audit(AuditEvent::Privileged, String::from_utf8_lossy(action));
+    }
+    Ok(())
+}
+
+/// Make sure that a query's keys are served by this node (in cluster mode, keys are only
+/// served by the node that owns their slot). Returns false if the client was redirected to
+/// another node
+async fn route_query<P: ProtocolSpec, C: BufferedSocketStream>(
+    db: &Corestore,
+    con: &mut Connection<C, P>,
+    action: &[u8],
+    iter: &AnyArrayIter<'_>,
+    queued: bool,
+) -> ActionResult<bool> {
+    let exists = |key: &[u8]| {
+        db.get_ctable_ref()
+            .map(|table| cluster::migrate::table_has_key(table, key))
+            // without a table, the query fails here anyway
+            .unwrap_or(true)
+    };
+    let exists: Option<&dyn Fn(&[u8]) -> bool> = if queued { None } else { Some(&exists) };
+    let redirect = match db.get_cluster().route(action, iter.as_ref(), exists) {
+        Route::Local => return Ok(true),
+        Route::Moved { slot, node } => format!("moved {slot} {node}"),
+        Route::Ask { slot, node } => format!("ask {slot} {node}"),
+        Route::CrossSlot => return util::err(P::RSTRING_CROSS_SLOT),
+        Route::Down => return util::err(P::RSTRING_CLUSTER_DOWN),
+        Route::TryAgain => return util::err(P::RSTRING_TRY_AGAIN),
+    };
+    con._write_raw(&P::encode_respstring(&redirect)).await?;
+    Ok(false)
+}
+
+/// Execute a stage. If `queue` is provided, the stage is validated and queued into the
+/// transaction instead of being run
+async fn execute_stage<'a, P: ProtocolSpec, C: BufferedSocketStream>(
+    db: &mut Corestore,
+    con: &mut Connection<C, P>,
+    auth: &mut AuthProviderHandle,
+    queue: Option<&mut Transaction>,
+    buf: &[UnsafeSlice],
+) -> ActionResult<()> {
+    // a queued write is logged when EXEC runs it (and never if the transaction is discarded)
+    let needs_logging = queue.is_none()
+        && self::is_logging(db)
+        && buf
+            .first()
+            .map(|action| aof::is_logged(unsafe { action.as_slice() }))
+            .unwrap_or(false);
+    self::dispatch(db, con, auth, queue, buf).await?;
+    if needs_logging {
+        self::log_write(db, buf);
     }
     Ok(())
 }
 
+/// Returns true if writes are recorded in the append-only log, sent to replicas or appended
+/// to a changefeed
+pub fn is_logging(db: &Corestore) -> bool {
+    db.get_aof().is_some() || db.get_replication().is_streaming() || db.get_streams().has_feeds()
+}
+
+fn log_write(db: &Corestore, buf: &[UnsafeSlice]) {
+    let args = buf.iter().map(|arg| unsafe {
+        // UNSAFE(@ohsayan): The presence of the connection guarantees that this
+        // won't suddenly become invalid
+        arg.as_slice()
+    });
+    self::log_record(db, args)
+}
+
+/// Record a write that was just run on the current table (as the query `args`) in the
+/// append-only log, send it to the replicas and append it to the keyspace's changefeed (if
+/// any). If we fail to record the write, the system is
+/// poisoned (just like a failed BGSAVE) so that we don't silently lose writes
+pub fn log_record<'a>(db: &Corestore, args: impl ExactSizeIterator<Item = &'a [u8]>) {
+    let (ks, tbl) = db.get_ids();
+    self::log_record_in(db, ks, tbl, args)
+}
+
+/// Record a write that was just run on `ks.tbl` (see [`log_record`])
+pub fn log_record_in<'a>(
+    db: &Corestore,
+    ks: Option<&ObjectID>,
+    tbl: Option<&ObjectID>,
+    args: impl ExactSizeIterator<Item = &'a [u8]>,
+) {
+    let args: Vec<&[u8]> = args.collect();
+    let mut record = Vec::new();
+    // a replica keeps the time at which the primary made the write
+    aof::encode_record(
+        &mut record,
+        kvengine::write_time(),
+        ks,
+        tbl,
+        args.iter().copied(),
+    );
+    if let Some(aof) = db.get_aof() {
+        if let Err(e) = aof.append(&record) {
+            log::error!("Failed to write to the append-only log with error: {}", e);
+            registry::poison();
+        }
+    }
+    db.get_replication().publish(record);
+    if let Some(ks) = ks {
+        db.get_streams().record_change(ks, tbl, &args);
+    }
+}
+
+/// Run a query for a subsystem of the server (see [`Loopback`](crate::dbnet::loopback::Loopback)).
+/// The query is validated, run, counted and recorded just like a client's query. It isn't
+/// authorized, routed or passed through the hooks though: the server trusts itself, it only
+/// works with the keys that it holds and the limits that the hooks enforce are for clients.
+/// Writes are also allowed on a replica, since applying the primary's writes is one of these
+/// subsystems
+pub async fn execute_internal<P: ProtocolSpec, C: BufferedSocketStream>(
+    db: &mut Corestore,
+    con: &mut Connection<C, P>,
+    auth: &mut AuthProviderHandle,
+    buf: &[UnsafeSlice],
+) -> ActionResult<()> {
+    db.get_stats().record_commands(1);
+    // don't interleave with transactions, just like any other query
+    let _txn_lock = registry::lock_txn_shared().await;
+    self::execute_stage(db, con, auth, None, buf).await
+}
+
+/// Returns true if `action` works with the state of the connection that it's run on. These
+/// can't be run by clients that don't hold on to a connection (like the HTTP gateway)
+pub fn is_connection_bound(action: &[u8]) -> bool {
+    self::action_flags(action).contains(ActionFlags::STATEFUL)
+}
+
+/// Returns the entry for `action` in the command table, if it's an action (and not a BlueQL
+/// statement)
+pub fn find_action(action: &[u8]) -> Option<&'static ActionSpec> {
+    self::all_actions().find(|spec| action.eq_ignore_ascii_case(spec.name.as_bytes()))
+}
+
+/// Returns the flags of `action` (no flags are set if it isn't in the command table)
+pub fn action_flags(action: &[u8]) -> ActionFlags {
+    self::find_action(action)
+        .map(|spec| spec.flags)
+        .unwrap_or(ActionFlags::NONE)
+}
+
+/// Returns true if `action` only reads data (see [`ActionFlags::READONLY`])
+fn is_read_only(action: &[u8]) -> bool {
+    self::action_flags(action).contains(ActionFlags::READONLY)
+}
+
+/// Returns true if a query starting with `action` modifies data: either the action does (see
+/// [`ActionFlags::WRITE`]) or it's a DDL statement
+pub fn writes(action: &[u8]) -> bool {
+    self::action_flags(action).contains(ActionFlags::WRITE) || aof::is_ddl(action)
+}
+
+/// Returns true if a query starting with `action` (in lowercase) can be run in multi-primary
+/// mode: it either doesn't write, or it's a write that the peers can resolve last-writer-wins
+/// (which only works on key/value tables, and can't be queued in a transaction)
+fn is_resolvable(db: &Corestore, action: &[u8], queued: bool) -> bool {
+    !self::action_flags(action).contains(ActionFlags::WRITE)
+        || (replication::is_lww(action)
+            && !queued
+            && matches!(
+                db.get_ctable_ref().map(Table::get_model_ref),
+                Some(DataModel::KV(_))
+            ))
+}
+
+/// Returns every action in the command table
+pub fn all_actions() -> impl Iterator<Item = &'static ActionSpec> {
+    DISPATCHED_ACTIONS
+        .iter()
+        .chain(commands::STATEFUL_ACTIONS.iter())
+        .chain(commands::SCRIPT_ACTIONS.iter())
+}
+
+/// Run a query for a client that doesn't hold on to a connection (see
+/// [`is_connection_bound`]). Action errors are written out, just like they are for the stages
+/// of a pipeline
+pub async fn execute_stateless<P: ProtocolSpec, C: BufferedSocketStream>(
+    db: &mut Corestore,
+    con: &mut Connection<C, P>,
+    auth: &mut AuthProviderHandle,
+    query: &[UnsafeSlice],
+) -> crate::IoResult<()> {
+    let mut state = ConnectionState::new();
+    self::execute_stage_pedantic(db, con, auth, &mut state, query).await
+}
+
 /// Execute a stage **completely**. This means that action errors are never propagated
 /// over the try operator
 async fn execute_stage_pedantic<'a, C: BufferedSocketStream, P: ProtocolSpec>(
     handle: &mut Corestore,
     con: &mut Connection<C, P>,
     auth: &mut AuthProviderHandle,
+    state: &mut ConnectionState,
     stage: &[UnsafeSlice],
 ) -> crate::IoResult<()> {
     let ret = async {
-        self::execute_stage(handle, con, auth, stage).await?;
+        self::execute_stage_stateful(handle, con, auth, state, stage).await?;
         Ok(())
     };
     match ret.await {
@@ -172,10 +768,11 @@ action! {
         handle: &mut Corestore,
         con: &mut Connection<C, P>,
         auth: &mut AuthProviderHandle,
+        state: &mut ConnectionState,
         pipeline: PipelinedQuery
     ) {
         for stage in pipeline.into_inner().iter() {
-            self::execute_stage_pedantic(handle, con, auth, stage).await?;
+            self::execute_stage_pedantic(handle, con, auth, state, stage).await?;
         }
         Ok(())
     }
@@ -0,0 +1,181 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Query monitoring
+//!
+//! A connection can ask to be sent every query that the server runs (from any client) with
+//! `MONITOR`, which is handy for debugging. Every query is pushed to the connection as an
+//! array of the time at which it was received (milliseconds since the UNIX epoch), the ID of
+//! the client that sent it and then the query itself. Like with key change notifications, a
+//! connection that can't keep up gets a `lagged` event with the number of queries that it
+//! missed. Since the monitor usually has no subscribers, queries are only copied if someone
+//! is listening
+
+use {
+    crate::{
+        actions::ActionResult,
+        corestore::SharedSlice,
+        dbnet::{prelude::*, BufferedSocketStream},
+        kvengine::now_millis,
+        protocol::UnsafeSlice,
+    },
+    core::sync::atomic::{AtomicUsize, Ordering},
+    std::sync::Arc,
+    tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender},
+};
+
+/// The number of queries that we'll buffer for a monitor before it starts lagging
+const MONITOR_CAPACITY: usize = 4096;
+/// The arguments of these actions are never sent to monitors because they carry credentials
+const REDACTED_ACTIONS: [&[u8]; 1] = [b"auth"];
+
+#[derive(Debug)]
+/// A query that was sent to a monitor
+pub struct MonitoredQuery {
+    /// the time at which the query was received (milliseconds since the UNIX epoch)
+    pub timestamp: u64,
+    /// the ID of the client that sent the query
+    pub client: u64,
+    /// the query
+    pub args: Vec<SharedSlice>,
+}
+
+#[derive(Debug)]
+/// The channel that every query is published on
+pub struct Monitor {
+    tx: Sender<Arc<MonitoredQuery>>,
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(MONITOR_CAPACITY);
+        Self {
+            tx,
+            subscribers: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    /// Returns true if anyone is monitoring queries
+    pub fn is_active(&self) -> bool {
+        self.subscribers.load(Ordering::Acquire) != 0
+    }
+    fn subscribe(&self) -> MonitorSubscription {
+        // see Notifier::subscribe for why we subscribe first
+        let rx = self.tx.subscribe();
+        self.subscribers.fetch_add(1, Ordering::AcqRel);
+        MonitorSubscription {
+            rx,
+            subscribers: self.subscribers.clone(),
+        }
+    }
+    /// Send a query run by `client` to the monitors (if any)
+    pub fn publish(&self, client: u64, query: &[UnsafeSlice]) {
+        if !self.is_active() {
+            return;
+        }
+        let mut args: Vec<SharedSlice> = query
+            .iter()
+            .map(|arg| unsafe {
+                // UNSAFE(@ohsayan): The query is alive for as long as it is being run
+                SharedSlice::new(arg.as_slice())
+            })
+            .collect();
+        let redact = args.first().map_or(false, |action| {
+            REDACTED_ACTIONS
+                .iter()
+                .any(|redacted| action.eq_ignore_ascii_case(redacted))
+        });
+        if redact {
+            // keep the subaction, but nothing after it
+            args.truncate(2);
+        }
+        let _ = self.tx.send(Arc::new(MonitoredQuery {
+            timestamp: now_millis(),
+            client,
+            args,
+        }));
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A connection's subscription to the monitor. Dropping it stops monitoring
+pub struct MonitorSubscription {
+    rx: Receiver<Arc<MonitoredQuery>>,
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl MonitorSubscription {
+    /// Wait for the next query. If we fell behind, the number of queries that we missed is
+    /// returned as an error instead
+    pub async fn next(&mut self) -> Result<Arc<MonitoredQuery>, u64> {
+        match self.rx.recv().await {
+            Ok(query) => Ok(query),
+            Err(RecvError::Lagged(missed)) => Err(missed),
+            // the monitor lives as long as the store, so this can't really happen
+            Err(RecvError::Closed) => core::future::pending().await,
+        }
+    }
+}
+
+impl Drop for MonitorSubscription {
+    fn drop(&mut self) {
+        self.subscribers.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Run a `MONITOR` query. If auth is enabled, only root can monitor queries
+pub async fn monitor<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    auth: &AuthProviderHandle,
+    monitor: &mut Option<MonitorSubscription>,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 0)?;
+    auth.provider().ensure_root_if_enabled::<P>()?;
+    if monitor.is_none() {
+        *monitor = Some(handle.get_monitor().subscribe());
+    }
+    con._write_raw(P::RCODE_OKAY).await?;
+    Ok(())
+}
+
+/// Run an `UNMONITOR` query
+pub async fn unmonitor<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    con: &mut Connection<C, P>,
+    monitor: &mut Option<MonitorSubscription>,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 0)?;
+    *monitor = None;
+    con._write_raw(P::RCODE_OKAY).await?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Key change notifications
+//!
+//! A connection can ask to be told about the changes made to keys in the current table
+//! that start with a given prefix with `WATCHPREFIX <prefix> ...`. Every change is then
+//! pushed to the connection as an array of the event (`set`, `update`, `del` or `flush`)
+//! and the key. If the connection can't keep up, some changes are dropped and a `lagged`
+//! event is pushed with the number of changes that were missed instead
+
+use {
+    crate::{
+        actions::ActionResult,
+        corestore::{table::Table, SharedSlice},
+        dbnet::{prelude::*, BufferedSocketStream},
+        kvengine::notify::{KeyChange, KeyEvent, Subscription},
+    },
+    std::sync::Arc,
+    tokio::sync::broadcast::error::RecvError,
+};
+
+/// The prefixes watched by a connection, along with its subscription to the table that
+/// they were watched in
+pub struct KeyWatch {
+    table: Arc<Table>,
+    subscription: Subscription,
+    prefixes: Vec<SharedSlice>,
+}
+
+impl KeyWatch {
+    /// Start watching the given prefixes in `table`
+    pub fn new(table: Arc<Table>, prefixes: Vec<SharedSlice>) -> Self {
+        Self {
+            subscription: table.subscribe(),
+            table,
+            prefixes,
+        }
+    }
+    fn is_interested(&self, change: &KeyChange) -> bool {
+        change.event == KeyEvent::Flush
+            || self
+                .prefixes
+                .iter()
+                .any(|prefix| change.key.starts_with(prefix))
+    }
+    /// Wait for the next change to a watched key. If we fell behind, the number of changes
+    /// that we missed is returned as an error instead
+    pub async fn next(&mut self) -> Result<KeyChange, u64> {
+        loop {
+            match self.subscription.recv().await {
+                Ok(change) if self.is_interested(&change) => return Ok(change),
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => return Err(missed),
+                // we hold a reference to the table, so this can't really happen
+                Err(RecvError::Closed) => core::future::pending().await,
+            }
+        }
+    }
+}
+
+/// Run a `WATCHPREFIX` query. Watching prefixes in another table stops watching the prefixes
+/// in the table that was previously watched
+pub async fn watchprefix<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    watch: &mut Option<KeyWatch>,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len != 0)?;
+    let table = match handle.get_ctable() {
+        Some(table) => table,
+        None => return util::err(P::RSTRING_DEFAULT_UNSET),
+    };
+    let mut current = match watch.take() {
+        Some(current) if Arc::ptr_eq(&current.table, &table) => current,
+        _ => KeyWatch::new(table, Vec::new()),
+    };
+    current.prefixes.extend(act.map(SharedSlice::new));
+    *watch = Some(current);
+    con._write_raw(P::RCODE_OKAY).await?;
+    Ok(())
+}
+
+/// Run an `UNWATCHPREFIX` query. Without any prefixes, all the prefixes are unwatched
+pub async fn unwatchprefix<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    con: &mut Connection<C, P>,
+    watch: &mut Option<KeyWatch>,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    if act.is_empty() {
+        *watch = None;
+    } else if let Some(current) = watch {
+        for prefix in act {
+            current
+                .prefixes
+                .retain(|watched| watched.as_ref() != prefix);
+        }
+        if current.prefixes.is_empty() {
+            *watch = None;
+        }
+    }
+    con._write_raw(P::RCODE_OKAY).await?;
+    Ok(())
+}
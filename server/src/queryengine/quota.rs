@@ -0,0 +1,335 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Quotas
+//!
+//! Root can cap the number of keys and the (approximate) number of bytes in a keyspace, and
+//! in the keyspaces that a user can use (every keyspace, if the user isn't restricted to a few
+//! of them). The usage is measured every second by the quota service, and a write is turned
+//! away with `quota-exceeded` if its keyspace (or its user) already holds the maximum number of
+//! keys, or if its arguments would take the usage over the maximum number of bytes. Writes that
+//! only remove data (like `DEL`) are always let through, so that a tenant can get back under
+//! their quota.
+//!
+//! The bytes of the writes that are let through are added to the usage until it's measured
+//! again, so that a burst of writes can't go far past a quota. Quotas are set with `QUOTA`
+//! and, like log levels, aren't persisted
+
+use {
+    super::hooks::{QueryHook, QueryInfo},
+    crate::{
+        auth::{provider::AuthID, Authmap, Permissions},
+        corestore::{
+            htable::Coremap,
+            memstore::{Keyspace, Memstore, ObjectID},
+        },
+    },
+    std::hash::Hash,
+};
+
+/// The response to a write that was turned away
+const QUOTA_EXCEEDED: &str = "quota-exceeded";
+/// Writes that only remove data, which are let through even if a quota is exceeded
+const FREEING_ACTIONS: [&[u8]; 13] = [
+    b"DEL", b"MDEL", b"SDEL", b"POP", b"MPOP", b"LPOP", b"RPOP", b"SREM", b"HDEL", b"ZREM",
+    b"FLUSHDB", b"EXPIRE", b"PERSIST",
+];
+
+/// Returns true if a query starting with `action` can add data
+fn can_grow(action: &[u8]) -> bool {
+    Permissions::required_for(action) == Permissions::WRITE
+        && !FREEING_ACTIONS
+            .iter()
+            .any(|freeing| action.eq_ignore_ascii_case(freeing))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The limits of a quota (a limit of zero means that there is no limit)
+pub struct Quota {
+    maxkeys: u64,
+    maxbytes: u64,
+}
+
+impl Quota {
+    pub const fn new(maxkeys: u64, maxbytes: u64) -> Self {
+        Self { maxkeys, maxbytes }
+    }
+    pub const fn maxkeys(&self) -> u64 {
+        self.maxkeys
+    }
+    pub const fn maxbytes(&self) -> u64 {
+        self.maxbytes
+    }
+    pub const fn is_unlimited(&self) -> bool {
+        self.maxkeys == 0 && self.maxbytes == 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The number of keys and the approximate number of bytes in one or more keyspaces
+pub struct Usage {
+    pub keys: u64,
+    pub bytes: u64,
+}
+
+impl Usage {
+    fn of(keyspace: &Keyspace) -> Self {
+        keyspace
+            .tables
+            .iter()
+            .map(|tbl| Self {
+                keys: tbl.count() as u64,
+                // a spilled value doesn't take up memory, but it counts against the quota
+                bytes: tbl.approx_stored_size() as u64,
+            })
+            .fold(Self::default(), Self::add)
+    }
+    fn add(self, other: Self) -> Self {
+        Self {
+            keys: self.keys + other.keys,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tracked {
+    quota: Quota,
+    /// the usage when it was last measured
+    usage: Usage,
+    /// the bytes of the writes that were let through since the usage was measured
+    pending: u64,
+}
+
+impl Tracked {
+    fn new(quota: Quota) -> Self {
+        Self {
+            quota,
+            usage: Usage::default(),
+            pending: 0,
+        }
+    }
+    /// Returns true if a write with `incoming` bytes of arguments fits in the quota
+    fn fits(&self, incoming: u64) -> bool {
+        let Quota { maxkeys, maxbytes } = self.quota;
+        (maxkeys == 0 || self.usage.keys < maxkeys)
+            && (maxbytes == 0 || self.usage.bytes + self.pending + incoming <= maxbytes)
+    }
+}
+
+#[derive(Debug, Default)]
+/// The quotas of the keyspaces and the users, shared by all connections. This is registered
+/// as a query hook when the server starts
+pub struct Quotas {
+    keyspaces: Coremap<ObjectID, Tracked>,
+    users: Coremap<AuthID, Tracked>,
+}
+
+impl Quotas {
+    pub fn is_empty(&self) -> bool {
+        self.keyspaces.len() == 0 && self.users.len() == 0
+    }
+    /// Set the quota of a keyspace (an unlimited quota removes it)
+    pub fn set_keyspace(&self, keyspace: ObjectID, quota: Quota) {
+        Self::set(&self.keyspaces, keyspace, quota)
+    }
+    /// Set the quota of a user (an unlimited quota removes it)
+    pub fn set_user(&self, user: AuthID, quota: Quota) {
+        Self::set(&self.users, user, quota)
+    }
+    fn set<K: Eq + Hash>(map: &Coremap<K, Tracked>, key: K, quota: Quota) {
+        if quota.is_unlimited() {
+            map.true_if_removed(&key);
+        } else {
+            map.get_or_insert_with(key, || Tracked::new(quota)).quota = quota;
+        }
+    }
+    /// Returns the quota of a keyspace and its usage (if the keyspace has a quota)
+    pub fn keyspace(&self, keyspace: &[u8]) -> Option<(Quota, Usage)> {
+        self.keyspaces
+            .get(keyspace)
+            .map(|tracked| (tracked.quota, tracked.usage))
+    }
+    /// Returns the quota of a user and their usage (if the user has a quota)
+    pub fn user(&self, user: &[u8]) -> Option<(Quota, Usage)> {
+        self.users
+            .get(user)
+            .map(|tracked| (tracked.quota, tracked.usage))
+    }
+    /// Returns the keyspaces that have a quota
+    pub fn keyspaces(&self) -> Vec<ObjectID> {
+        self.keyspaces.iter().map(|kv| kv.key().clone()).collect()
+    }
+    /// Returns the users that have a quota
+    pub fn users(&self) -> Vec<AuthID> {
+        self.users.iter().map(|kv| kv.key().clone()).collect()
+    }
+    /// Measure the usage of every keyspace and user that has a quota
+    pub fn measure(&self, store: &Memstore, authmap: &Authmap) {
+        if self.is_empty() {
+            return;
+        }
+        let keyspaces: Vec<(ObjectID, Usage)> = store
+            .keyspaces
+            .iter()
+            .map(|ks| (ks.key().clone(), Usage::of(ks.value())))
+            .collect();
+        self.update(&keyspaces, |user, keyspace| {
+            authmap
+                .get(user)
+                .map_or(false, |record| record.acl().allows(Some(keyspace)))
+        })
+    }
+    /// Set the usage of the keyspaces and the users from the usage of every keyspace.
+    /// `allows` returns true if a user can use a keyspace
+    fn update(&self, keyspaces: &[(ObjectID, Usage)], allows: impl Fn(&AuthID, &[u8]) -> bool) {
+        self.keyspaces.retain(|keyspace, tracked| {
+            tracked.usage = keyspaces
+                .iter()
+                .find(|(ks, _)| ks.eq(keyspace))
+                .map(|(_, usage)| *usage)
+                // the keyspace doesn't exist (yet)
+                .unwrap_or_default();
+            tracked.pending = 0;
+            true
+        });
+        self.users.retain(|user, tracked| {
+            tracked.usage = keyspaces
+                .iter()
+                .filter(|(ks, _)| allows(user, ks))
+                .fold(Usage::default(), |total, (_, usage)| total.add(*usage));
+            tracked.pending = 0;
+            true
+        });
+    }
+    fn fits<K: Eq + Hash>(map: &Coremap<K, Tracked>, key: &K, incoming: u64) -> bool {
+        map.get(key).map_or(true, |tracked| tracked.fits(incoming))
+    }
+    fn reserve<K: Eq + Hash + Clone>(map: &Coremap<K, Tracked>, key: &K, incoming: u64) {
+        if let Some(mut entry) = map.mut_entry(key.clone()) {
+            entry.value_mut().pending += incoming;
+        }
+    }
+}
+
+impl QueryHook for Quotas {
+    fn admit(&self, query: &QueryInfo) -> Result<(), String> {
+        if self.is_empty() || !can_grow(query.action()) {
+            return Ok(());
+        }
+        let incoming = query.args().map(|arg| arg.len() as u64).sum();
+        let (keyspace, user) = (query.keyspace(), query.user());
+        let fits = keyspace.map_or(true, |ks| Self::fits(&self.keyspaces, ks, incoming))
+            && user.map_or(true, |user| Self::fits(&self.users, user, incoming));
+        if !fits {
+            return Err(QUOTA_EXCEEDED.to_owned());
+        }
+        if let Some(ks) = keyspace {
+            Self::reserve(&self.keyspaces, ks, incoming);
+        }
+        if let Some(user) = user {
+            Self::reserve(&self.users, user, incoming);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Quota, Quotas, Usage, QUOTA_EXCEEDED},
+        crate::{
+            auth::provider::AuthID,
+            corestore::memstore::ObjectID,
+            protocol::UnsafeSlice,
+            queryengine::hooks::{QueryHook, QueryInfo},
+        },
+    };
+
+    fn tenant() -> ObjectID {
+        ObjectID::try_from_slice(b"tenant").unwrap()
+    }
+
+    /// Run `action` with a single argument of `size` bytes in the `tenant` keyspace
+    fn admit(quotas: &Quotas, action: &[u8], size: usize, user: Option<AuthID>) -> bool {
+        let arg = vec![0u8; size];
+        let args = [UnsafeSlice::new(arg.as_ptr(), arg.len())];
+        let query = QueryInfo::new(action, &args, user, None, Some(tenant()));
+        match quotas.admit(&query) {
+            Ok(()) => true,
+            Err(e) => {
+                assert_eq!(e, QUOTA_EXCEEDED);
+                false
+            }
+        }
+    }
+
+    #[test]
+    fn bytes_over_the_quota() {
+        let quotas = Quotas::default();
+        assert!(quotas.is_empty());
+        quotas.set_keyspace(tenant(), Quota::new(0, 100));
+        assert!(admit(&quotas, b"set", 60, None));
+        // the first write hasn't been measured yet, but it still counts
+        assert!(!admit(&quotas, b"set", 60, None));
+        assert!(admit(&quotas, b"set", 40, None));
+        // removing data is always fine
+        assert!(admit(&quotas, b"del", 60, None));
+        // and so is reading it
+        assert!(admit(&quotas, b"get", 60, None));
+        // the usage is measured (and it's way smaller)
+        quotas.update(&[(tenant(), Usage { keys: 1, bytes: 10 })], |_, _| true);
+        assert_eq!(
+            quotas.keyspace(b"tenant"),
+            Some((Quota::new(0, 100), Usage { keys: 1, bytes: 10 }))
+        );
+        assert!(admit(&quotas, b"set", 60, None));
+    }
+
+    #[test]
+    fn keys_over_the_quota() {
+        let quotas = Quotas::default();
+        let user = AuthID::try_from_slice(b"sayan").unwrap();
+        quotas.set_user(user.clone(), Quota::new(2, 0));
+        assert!(admit(&quotas, b"set", 10, Some(user.clone())));
+        // the user can use a keyspace that already holds two keys
+        quotas.update(&[(tenant(), Usage { keys: 2, bytes: 10 })], |_, ks| {
+            ks == b"tenant"
+        });
+        assert!(!admit(&quotas, b"set", 10, Some(user.clone())));
+        // other users are fine
+        assert!(admit(&quotas, b"set", 10, None));
+        // but not if the keyspace has a quota too
+        quotas.set_keyspace(tenant(), Quota::new(1, 0));
+        assert_eq!(quotas.keyspaces(), vec![tenant()]);
+        assert!(!admit(&quotas, b"set", 10, None));
+        // an unlimited quota is the same as no quota
+        quotas.set_keyspace(tenant(), Quota::default());
+        quotas.set_user(user.clone(), Quota::default());
+        assert!(quotas.is_empty());
+        assert!(admit(&quotas, b"set", 10, Some(user)));
+    }
+}
@@ -0,0 +1,207 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Rate limiting
+//!
+//! Every user that is logged in and every client IP gets a token bucket that holds up to
+//! `burst` tokens and is refilled at `rate` tokens a second. A query takes a token from the
+//! bucket of its client's IP and from the bucket of its user, and if either bucket is empty,
+//! the query is turned away with `throttled` before its action runs. Anonymous clients (or
+//! any client, if authn is disabled) are only limited by IP, while clients on a Unix domain
+//! socket are only limited by user.
+//!
+//! The limits are read from the runtime configuration on every query, so they can be changed
+//! (or turned off) with a reload
+
+use {
+    super::hooks::{QueryHook, QueryInfo},
+    crate::{
+        auth::provider::AuthID,
+        config::{RateLimit, RuntimeConfig},
+        corestore::htable::Coremap,
+    },
+    arc_swap::ArcSwap,
+    std::{
+        hash::Hash,
+        net::IpAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Instant,
+    },
+};
+
+/// The response to a query that was turned away
+const THROTTLED: &str = "throttled";
+/// Stale buckets are pruned once a map holds this many buckets
+const PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    /// the last time the bucket was refilled
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn full(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst() as f64,
+            refilled: now,
+        }
+    }
+    /// Add the tokens for the time that has passed since the last refill
+    fn refill(&mut self, limit: RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate() as f64).min(limit.burst() as f64);
+        self.refilled = now;
+    }
+    /// Take a token, returning false if the bucket is empty
+    fn take(&mut self, limit: RateLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+    /// Returns true if the bucket would be full by now (and can be forgotten)
+    fn is_stale(&self, limit: RateLimit, now: Instant) -> bool {
+        let mut bucket = *self;
+        bucket.refill(limit, now);
+        bucket.tokens >= limit.burst() as f64
+    }
+}
+
+/// The buckets for one kind of key
+struct Buckets<K> {
+    map: Coremap<K, Bucket>,
+    /// the next prune happens once the map holds this many buckets
+    prune_at: AtomicUsize,
+}
+
+impl<K: Eq + Hash> Buckets<K> {
+    fn new() -> Self {
+        Self {
+            map: Coremap::new(),
+            prune_at: AtomicUsize::new(PRUNE_THRESHOLD),
+        }
+    }
+    fn take(&self, key: K, limit: RateLimit, now: Instant) -> bool {
+        if self.map.len() >= self.prune_at.load(Ordering::Relaxed) {
+            self.map.retain(|_, bucket| !bucket.is_stale(limit, now));
+            // if most of the buckets are still in use, don't prune again until the map
+            // doubles in size
+            let prune_at = PRUNE_THRESHOLD.max(self.map.len() * 2);
+            self.prune_at.store(prune_at, Ordering::Relaxed);
+        }
+        self.map
+            .get_or_insert_with(key, || Bucket::full(limit, now))
+            .take(limit, now)
+    }
+}
+
+/// Turns away the queries of users and clients that go over their rate limits. This is
+/// registered as a query hook when the server starts
+pub struct RateLimiter {
+    config: Arc<ArcSwap<RuntimeConfig>>,
+    users: Buckets<AuthID>,
+    clients: Buckets<IpAddr>,
+}
+
+impl RateLimiter {
+    pub fn new(config: Arc<ArcSwap<RuntimeConfig>>) -> Self {
+        Self {
+            config,
+            users: Buckets::new(),
+            clients: Buckets::new(),
+        }
+    }
+}
+
+impl QueryHook for RateLimiter {
+    fn admit(&self, query: &QueryInfo) -> Result<(), String> {
+        let limits = self.config.load().ratelimits;
+        let now = Instant::now();
+        let within_ip = match query.peer() {
+            Some(ip) if limits.ip.is_enabled() => self.clients.take(ip, limits.ip, now),
+            _ => true,
+        };
+        let within_user = match query.user() {
+            Some(user) if limits.user.is_enabled() => {
+                self.users.take(user.clone(), limits.user, now)
+            }
+            _ => true,
+        };
+        if within_ip && within_user {
+            Ok(())
+        } else {
+            Err(THROTTLED.to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Bucket, Buckets},
+        crate::config::RateLimit,
+        std::time::{Duration, Instant},
+    };
+
+    #[test]
+    fn bucket_refills() {
+        let limit = RateLimit::new(10, 2);
+        let now = Instant::now();
+        let mut bucket = Bucket::full(limit, now);
+        assert!(bucket.take(limit, now));
+        assert!(bucket.take(limit, now));
+        assert!(!bucket.take(limit, now));
+        // a tenth of a second gets one token back
+        let later = now + Duration::from_millis(100);
+        assert!(bucket.take(limit, later));
+        assert!(!bucket.take(limit, later));
+        // but the bucket never holds more than the burst
+        let much_later = now + Duration::from_secs(60);
+        assert!(bucket.is_stale(limit, much_later));
+        assert!(bucket.take(limit, much_later));
+        assert!(bucket.take(limit, much_later));
+        assert!(!bucket.take(limit, much_later));
+    }
+
+    #[test]
+    fn burst_defaults_to_rate() {
+        let limit = RateLimit::new(3, 0);
+        let now = Instant::now();
+        let buckets = Buckets::new();
+        assert!((0..3).all(|_| buckets.take("sayan", limit, now)));
+        assert!(!buckets.take("sayan", limit, now));
+        // every key has its own bucket
+        assert!(buckets.take("nandan", limit, now));
+    }
+}
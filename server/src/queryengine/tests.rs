@@ -0,0 +1,346 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    super::ConnectionState,
+    crate::{
+        auth::AuthProvider,
+        config::FsyncPolicy,
+        corestore::{memstore::Memstore, table::DataModel, Corestore},
+        dbnet::{prelude::Connection, AuthProviderHandle, BufferedSocketStream},
+        kvengine,
+        protocol::{Skyhash2, UnsafeSlice},
+        registry,
+        replication::FeedEvent,
+        services::{self, aof::Replayer},
+        storage::v1::{
+            aof::{self, AppendOnlyLog, Record, AOF_PATH, AOF_ROTATED_PATH},
+            flush,
+            interface::DataDir,
+            sengine::SnapshotEngine,
+            unflush,
+        },
+    },
+    std::{collections::BTreeMap, fs, sync::Arc, time::Duration},
+    tokio::io::{self, DuplexStream},
+};
+
+impl BufferedSocketStream for DuplexStream {}
+
+/// A client connection. Its responses are thrown away; the tests look at what was logged
+struct Client {
+    db: Corestore,
+    con: Connection<DuplexStream, Skyhash2>,
+    auth: AuthProviderHandle,
+    state: ConnectionState,
+    _peer: DuplexStream,
+}
+
+impl Client {
+    fn new(handle: &Corestore) -> Self {
+        let (stream, peer) = io::duplex(1 << 20);
+        Self {
+            db: handle.clone(),
+            con: Connection::new(stream),
+            auth: AuthProviderHandle::new(AuthProvider::new_disabled()),
+            state: ConnectionState::new(),
+            _peer: peer,
+        }
+    }
+    async fn run(&mut self, query: &[&[u8]]) {
+        let query: Vec<UnsafeSlice> = query
+            .iter()
+            .map(|arg| UnsafeSlice::new(arg.as_ptr(), arg.len()))
+            .collect();
+        super::execute_stage_pedantic(
+            &mut self.db,
+            &mut self.con,
+            &mut self.auth,
+            &mut self.state,
+            &query,
+        )
+        .await
+        .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn queued_writes_are_logged_once_on_exec() {
+    let handle = Corestore::default_with_store(
+        Memstore::new_default(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    // a replica is sent the very records that are appended to the append-only log
+    let mut feed = handle.get_replication().subscribe();
+    let mut client = Client::new(&handle);
+    client.run(&[b"MULTI"]).await;
+    client.run(&[b"SET", b"x", b"1"]).await;
+    client.run(&[b"DISCARD"]).await;
+    client.run(&[b"SET", b"y", b"1"]).await;
+    client.run(&[b"MULTI"]).await;
+    client.run(&[b"INCR", b"y"]).await;
+    client.run(&[b"EXEC"]).await;
+    let mut logged = Vec::new();
+    while let Ok(event) = tokio::time::timeout(Duration::from_millis(50), feed.next()).await {
+        if let Ok(FeedEvent::Write(record)) = event {
+            logged.push(aof::decode_record(&record).unwrap().args);
+        }
+    }
+    assert_eq!(
+        logged,
+        [
+            vec![b"SET".to_vec(), b"y".to_vec(), b"1".to_vec()],
+            vec![b"INCR".to_vec(), b"y".to_vec()],
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn slow_readers_dont_hold_the_txn_lock() {
+    let handle = Corestore::default_with_store(
+        Memstore::new_default(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    const QUERIES: [&[&[u8]]; 2] = [&[b"GET", b"x"], &[b"EVAL", b"get(ARGS[0])", b"x"]];
+    let value = vec![b'a'; 1 << 16];
+    Client::new(&handle).run(&[b"SET", b"x", &value]).await;
+    for query in QUERIES {
+        // nobody reads the responses of this client, and its socket can only hold a few bytes
+        let (stream, peer) = io::duplex(16);
+        let mut slow = Client {
+            con: Connection::new(stream),
+            _peer: peer,
+            ..Client::new(&handle)
+        };
+        let running = tokio::spawn(async move { slow.run(query).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // the query is stuck writing its response, but it let go of the lock before that
+        let locked =
+            tokio::time::timeout(Duration::from_secs(5), registry::lock_txn_exclusive()).await;
+        assert!(locked.is_ok());
+        assert!(!running.is_finished());
+        running.abort();
+    }
+}
+
+#[tokio::test]
+async fn replayed_ttls_are_relative_to_the_write() {
+    const MINUTE: u64 = 60_000;
+    let handle = Corestore::default_with_store(
+        Memstore::new_default(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    let now = kvengine::now_millis();
+    let record = |ago: u64, args: &[&[u8]]| Record {
+        timestamp: now - ago,
+        keyspace: None,
+        table: None,
+        args: args.iter().map(|arg| arg.to_vec()).collect(),
+    };
+    let mut replayer = Replayer::new(&handle);
+    for record in [
+        // this key expired an hour ago
+        record(120 * MINUTE, &[b"SETEX", b"gone", b"3600", b"1"]),
+        record(30 * MINUTE, &[b"SET", b"kept", b"1"]),
+        record(30 * MINUTE, &[b"EXPIRE", b"kept", b"3600"]),
+    ] {
+        assert!(replayer.apply(&record).await.unwrap());
+    }
+    let table = handle.get_ctable_ref().unwrap();
+    let kve = match table.get_model_ref() {
+        DataModel::KV(kve) => kve,
+        _ => panic!("expected a key/value table"),
+    };
+    assert_eq!(kve.ttl_unchecked(b"gone"), None);
+    let ttl = kve.ttl_unchecked(b"kept").unwrap().unwrap();
+    assert!(ttl <= 30 * MINUTE, "{ttl}");
+    // and the time outside of a replay is the current time
+    assert!(kvengine::write_time() >= now);
+}
+
+/// Returns every key and value in the default table
+fn contents(handle: &Corestore) -> BTreeMap<Vec<u8>, Vec<u8>> {
+    match handle.get_ctable_ref().unwrap().get_model_ref() {
+        DataModel::KV(kve) => kve
+            .get_inner_ref()
+            .iter()
+            .map(|kv| (kv.key().as_ref().to_vec(), kv.value().as_ref().to_vec()))
+            .collect(),
+        _ => panic!("expected a key/value table"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn bgsave_with_concurrent_writes() {
+    let path = std::env::temp_dir().join(format!("skyd-bgsave-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    let dir = DataDir::new(path.to_str().unwrap());
+    let mut handle = Corestore::default_with_store(
+        unflush::read_full(&dir).unwrap(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    handle.set_aof(Arc::new(
+        AppendOnlyLog::open(&dir, FsyncPolicy::No, false).unwrap(),
+    ));
+    let writers: Vec<_> = (0..4)
+        .map(|writer| {
+            let mut client = Client::new(&handle);
+            tokio::spawn(async move {
+                for i in 0..250 {
+                    // replaying an INCR that's already in the save would count it twice
+                    client.run(&[b"INCR", b"counter"]).await;
+                    let key = format!("{writer}-{}", i % 50);
+                    client
+                        .run(&[b"SET", key.as_bytes(), i.to_string().as_bytes()])
+                        .await;
+                    if i % 7 == 0 {
+                        client.run(&[b"DEL", key.as_bytes()]).await;
+                    }
+                }
+            })
+        })
+        .collect();
+    for _ in 0..5 {
+        let (dir, handle) = (dir.clone(), handle.clone());
+        tokio::task::spawn_blocking(move || services::bgsave::save_in(&dir, &handle))
+            .await
+            .unwrap()
+            .unwrap();
+    }
+    for writer in writers {
+        writer.await.unwrap();
+    }
+    // what a restart would see: the last save and the writes in the log after it
+    let restarted = Corestore::default_with_store(
+        unflush::read_full(&dir).unwrap(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    let mut records = Vec::new();
+    for log in [AOF_ROTATED_PATH, AOF_PATH] {
+        if let Ok(log) = aof::read_log(&dir.resolve(log)) {
+            records.extend(log);
+        }
+    }
+    services::aof::replay_records(&restarted, &records)
+        .await
+        .unwrap();
+    let live = contents(&handle);
+    assert_eq!(live.get(b"counter".as_ref()), Some(&b"1000".to_vec()));
+    assert_eq!(contents(&restarted), live);
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restored_snapshots_survive_a_restart() {
+    let path = std::env::temp_dir().join(format!("skyd-restoresnap-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    let dir = DataDir::new(path.join("data").to_str().unwrap());
+    let snapshot_dir = DataDir::new(path.join("snapshot").to_str().unwrap());
+    let mut handle = Corestore::default_with_store(
+        unflush::read_full(&dir).unwrap(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    handle.set_aof(Arc::new(
+        AppendOnlyLog::open(&dir, FsyncPolicy::No, false).unwrap(),
+    ));
+    let mut client = Client::new(&handle);
+    client.run(&[b"SET", b"a", b"1"]).await;
+    client.run(&[b"SET", b"b", b"1"]).await;
+    // this creates the snapshot's directory tree
+    unflush::read_full(&snapshot_dir).unwrap();
+    flush::flush_full(snapshot_dir.clone(), &handle.get_store().freeze()).unwrap();
+    // these are in the log, but they must not be replayed on top of the restored data
+    client.run(&[b"UPDATE", b"a", b"2"]).await;
+    client.run(&[b"DEL", b"b"]).await;
+    client.run(&[b"SET", b"c", b"3"]).await;
+    let (restore_dir, restore_handle) = (dir.clone(), handle.clone());
+    tokio::task::spawn_blocking(move || {
+        let snapshot = unflush::read_full(&snapshot_dir).unwrap();
+        let txn_lock = registry::lock_txn_exclusive_blocking();
+        services::bgsave::restore_in(&restore_dir, &restore_handle, snapshot, txn_lock)
+    })
+    .await
+    .unwrap()
+    .unwrap();
+    client.run(&[b"SET", b"d", b"4"]).await;
+    let live = contents(&handle);
+    let expected: BTreeMap<_, _> = [(b"a", b"1"), (b"b", b"1"), (b"d", b"4")]
+        .into_iter()
+        .map(|(key, value)| (key.to_vec(), value.to_vec()))
+        .collect();
+    assert_eq!(live, expected);
+    // what a restart would see: the last save and the writes in the log after it
+    let restarted = Corestore::default_with_store(
+        unflush::read_full(&dir).unwrap(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    let mut records = Vec::new();
+    for log in [AOF_ROTATED_PATH, AOF_PATH] {
+        if let Ok(log) = aof::read_log(&dir.resolve(log)) {
+            records.extend(log);
+        }
+    }
+    services::aof::replay_records(&restarted, &records)
+        .await
+        .unwrap();
+    assert_eq!(contents(&restarted), live);
+    fs::remove_dir_all(path).unwrap();
+}
+
+#[tokio::test]
+async fn bulk_loads_are_logged_per_batch() {
+    let path = std::env::temp_dir().join(format!("skyd-bulkload-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    let dir = DataDir::new(path.to_str().unwrap());
+    let mut handle = Corestore::default_with_store(
+        unflush::read_full(&dir).unwrap(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    handle.set_aof(Arc::new(
+        AppendOnlyLog::open(&dir, FsyncPolicy::No, false).unwrap(),
+    ));
+    let mut client = Client::new(&handle);
+    client.run(&[b"BULKLOAD", b"BEGIN"]).await;
+    client
+        .run(&[b"BULKLOAD", b"PUT", b"a", b"1", b"b", b"2"])
+        .await;
+    // a replica that attaches during the load would miss the earlier batches
+    let feed = handle.get_replication().subscribe();
+    client.run(&[b"BULKLOAD", b"PUT", b"c", b"3"]).await;
+    drop(feed);
+    let logged: Vec<_> = aof::read_log(&dir.resolve(AOF_PATH))
+        .unwrap()
+        .into_iter()
+        .map(|record| record.args)
+        .collect();
+    let expected: [&[u8]; 5] = [b"USET", b"a", b"1", b"b", b"2"];
+    assert_eq!(logged, [expected.map(<[u8]>::to_vec)]);
+    assert_eq!(
+        contents(&handle).into_keys().collect::<Vec<_>>(),
+        [b"a".to_vec(), b"b".to_vec()]
+    );
+    fs::remove_dir_all(path).unwrap();
+}
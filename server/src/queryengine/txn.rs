@@ -0,0 +1,223 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Transactions
+//!
+//! A transaction is started with `MULTI`, after which queries are validated (including their
+//! number of arguments) and queued instead of being run. `EXEC` then runs all the queued
+//! queries in order while holding the transaction lock exclusively (so that no other query
+//! can interleave with them) and `DISCARD` drops them. The responses are buffered and only
+//! sent once the lock is released, so a client that is slow to read them doesn't hold up
+//! everyone else. Keys in the current table can be `WATCH`ed before a transaction: if any
+//! watched key is changed before `EXEC` (even if it's changed back), the transaction is
+//! dropped and `EXEC` returns `Nil`
+
+use {
+    crate::{
+        actions::{ActionError, ActionResult},
+        corestore::{
+            table::{DescribeTable, Table},
+            SharedSlice,
+        },
+        dbnet::{prelude::*, BufferStream, BufferedSocketStream},
+        kvengine::encoding::ENCODING_LUT_ITER,
+        protocol::UnsafeSlice,
+        util::compiler,
+    },
+    std::sync::Arc,
+};
+
+/// A key that was watched along with the version it had at the time it was watched
+struct WatchedKey {
+    table: Arc<Table>,
+    key: SharedSlice,
+    version: u64,
+}
+
+impl WatchedKey {
+    fn is_unchanged(&self) -> bool {
+        KVEBlob::try_get(&self.table)
+            .map(|kve| kve.key_version(&self.key) == self.version)
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Default)]
+/// The transaction state of a connection
+pub struct Transaction {
+    /// the queued queries (`None` if no transaction was started)
+    queue: Option<Vec<Box<[Box<[u8]>]>>>,
+    /// the keys watched by this connection
+    watched: Vec<WatchedKey>,
+    /// set if a query couldn't be queued, in which case `EXEC` will abort the transaction
+    aborted: bool,
+}
+
+impl Transaction {
+    /// Returns true if a transaction was started with `MULTI`
+    pub fn is_active(&self) -> bool {
+        self.queue.is_some()
+    }
+    /// Queue a query, copying it out of the connection buffer
+    pub fn enqueue(&mut self, query: &[UnsafeSlice]) {
+        if let Some(ref mut queue) = self.queue {
+            let query = query
+                .iter()
+                .map(|arg| unsafe {
+                    // UNSAFE(@ohsayan): The presence of the connection guarantees that this
+                    // won't suddenly become invalid
+                    Box::from(arg.as_slice())
+                })
+                .collect();
+            queue.push(query);
+        }
+    }
+    /// Mark the current transaction as aborted (because a query couldn't be queued)
+    pub fn abort(&mut self) {
+        self.aborted = true;
+    }
+    fn reset(&mut self) {
+        self.queue = None;
+        self.watched.clear();
+        self.aborted = false;
+    }
+}
+
+/// Run a `MULTI` query
+pub async fn multi<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    con: &mut Connection<C, P>,
+    txn: &mut Transaction,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 0)?;
+    if txn.is_active() {
+        return util::err(P::RSTRING_TXN_NESTED);
+    }
+    txn.queue = Some(Vec::new());
+    con._write_raw(P::RCODE_OKAY).await?;
+    Ok(())
+}
+
+/// Run a `DISCARD` query
+pub async fn discard<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    con: &mut Connection<C, P>,
+    txn: &mut Transaction,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 0)?;
+    if !txn.is_active() {
+        return util::err(P::RSTRING_TXN_NOT_STARTED);
+    }
+    txn.reset();
+    con._write_raw(P::RCODE_OKAY).await?;
+    Ok(())
+}
+
+/// Run a `WATCH` query. Only keys in key/value tables can be watched
+pub async fn watch<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    txn: &mut Transaction,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len != 0)?;
+    if txn.is_active() {
+        return util::err(P::RSTRING_TXN_BAD_QUERY);
+    }
+    let kve = handle.get_table_with::<P, KVEBlob>()?;
+    let encoding_is_okay = ENCODING_LUT_ITER[kve.is_key_encoded()](act.as_ref());
+    if compiler::likely(encoding_is_okay) {
+        let table = handle.get_ctable().unwrap_or_aerr::<P>()?;
+        txn.watched.extend(act.map(|key| WatchedKey {
+            table: table.clone(),
+            key: SharedSlice::new(key),
+            version: kve.key_version(key),
+        }));
+        con._write_raw(P::RCODE_OKAY).await?;
+        Ok(())
+    } else {
+        util::err(P::RCODE_ENCODING_ERROR)
+    }
+}
+
+/// Run an `UNWATCH` query
+pub async fn unwatch<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    con: &mut Connection<C, P>,
+    txn: &mut Transaction,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 0)?;
+    txn.watched.clear();
+    con._write_raw(P::RCODE_OKAY).await?;
+    Ok(())
+}
+
+/// Run an `EXEC` query. The response is an array with the response of each queued query,
+/// or `Nil` if a watched key was modified
+pub async fn exec<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &mut Corestore,
+    con: &mut Connection<C, P>,
+    auth: &mut AuthProviderHandle,
+    txn: &mut Transaction,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 0)?;
+    let queue = match txn.queue.take() {
+        Some(queue) => queue,
+        None => return util::err(P::RSTRING_TXN_NOT_STARTED),
+    };
+    let aborted = txn.aborted;
+    let watched = core::mem::take(&mut txn.watched);
+    txn.reset();
+    if aborted {
+        return util::err(P::RSTRING_TXN_ABORTED);
+    }
+    let mut replies = Connection::<_, P>::new(BufferStream::default());
+    {
+        // no other query can run till we're done
+        let txn_lock = registry::lock_txn_exclusive().await;
+        if !watched.iter().all(WatchedKey::is_unchanged) {
+            drop(txn_lock);
+            con._write_raw(P::RCODE_NIL).await?;
+            return Ok(());
+        }
+        for query in queue.iter() {
+            let stage: Vec<UnsafeSlice> = query
+                .iter()
+                .map(|arg| UnsafeSlice::new(arg.as_ptr(), arg.len()))
+                .collect();
+            match super::execute_stage(handle, &mut replies, auth, None, &stage).await {
+                Ok(()) => {}
+                Err(ActionError::ActionError(e)) => replies._write_raw(e).await?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    let replies = replies.take_buffered().await?;
+    con.write_array_header(queue.len()).await?;
+    con._write_raw(&replies).await?;
+    Ok(())
+}
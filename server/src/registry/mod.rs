@@ -30,8 +30,12 @@
 //!
 
 use {
-    crate::corestore::lock::{QLGuard, QuickLock},
-    core::sync::atomic::{AtomicBool, Ordering},
+    crate::{
+        config::EvictionPolicy,
+        corestore::lock::{QLGuard, QuickLock},
+    },
+    core::sync::atomic::{AtomicBool, AtomicU8, Ordering},
+    tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 const ORD_ACQ: Ordering = Ordering::Acquire;
@@ -78,9 +82,16 @@ impl Trip {
 static GLOBAL_STATE: AtomicBool = AtomicBool::new(true);
 /// The global flush state
 static FLUSH_STATE: QuickLock<()> = QuickLock::new(());
+/// The transaction lock. Queries hold it shared while they run, while `EXEC` holds it
+/// exclusively so that the queries in a transaction never interleave with other queries
+static TXN_LOCK: RwLock<()> = RwLock::const_new(());
 /// The preload trip switch
 static PRELOAD_TRIPSWITCH: Trip = Trip::new_untripped();
 static CLEANUP_TRIPSWITCH: Trip = Trip::new_untripped();
+/// The memory trip switch (tripped when the memory limit is exceeded and nothing can be evicted)
+static MEMORY_TRIPSWITCH: Trip = Trip::new_untripped();
+/// The active eviction policy
+static EVICTION_POLICY: AtomicU8 = AtomicU8::new(EvictionPolicy::NoEviction as u8);
 
 /// Check the global system state
 pub fn state_okay() -> bool {
@@ -93,6 +104,24 @@ pub fn lock_flush_state() -> QLGuard<'static, ()> {
     FLUSH_STATE.lock()
 }
 
+/// Lock the transaction state for running a single query. This is only ever blocked by
+/// a running transaction
+pub async fn lock_txn_shared() -> RwLockReadGuard<'static, ()> {
+    TXN_LOCK.read().await
+}
+
+/// Lock the transaction state for running a transaction. This waits for all running queries
+/// to complete and blocks all new queries until the guard is dropped
+pub async fn lock_txn_exclusive() -> RwLockWriteGuard<'static, ()> {
+    TXN_LOCK.write().await
+}
+
+/// Same as [`lock_txn_exclusive`], but for threads that don't run async tasks (like the
+/// threads of the blocking pool). This panics if it's called from an async task
+pub fn lock_txn_exclusive_blocking() -> RwLockWriteGuard<'static, ()> {
+    TXN_LOCK.blocking_write()
+}
+
 /// Poison the global system state
 pub fn poison() {
     GLOBAL_STATE.store(false, ORD_REL)
@@ -112,3 +141,23 @@ pub fn get_preload_tripswitch() -> &'static Trip {
 pub fn get_cleanup_tripswitch() -> &'static Trip {
     &CLEANUP_TRIPSWITCH
 }
+
+/// Get a static reference to the global memory trip switch
+pub fn get_memory_tripswitch() -> &'static Trip {
+    &MEMORY_TRIPSWITCH
+}
+
+/// Check if the memory limit (if any) has not been exceeded
+pub fn memory_okay() -> bool {
+    !MEMORY_TRIPSWITCH.is_tripped()
+}
+
+/// Set the global eviction policy
+pub fn set_eviction_policy(policy: EvictionPolicy) {
+    EVICTION_POLICY.store(policy as u8, ORD_REL)
+}
+
+/// Get the global eviction policy
+pub fn get_eviction_policy() -> EvictionPolicy {
+    EvictionPolicy::from_u8(EVICTION_POLICY.load(ORD_ACQ))
+}
@@ -0,0 +1,133 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Last-writer-wins: the clock that orders the writes that are made in multi-primary mode and
+//! the stamps that record when every key was last written (see the module docs of
+//! [`replication`](super))
+
+use {
+    crate::{
+        corestore::memstore::ObjectID,
+        kvengine,
+        queryengine::{self, commands::ActionFlags},
+    },
+    parking_lot::Mutex,
+    std::collections::HashMap,
+};
+
+/// The number of low bits of a clock reading that hold the logical counter
+const LOGICAL_BITS: u32 = 16;
+
+/// Returns true if `action` is a write that can be resolved last-writer-wins (see
+/// [`ActionFlags::LWW`])
+pub fn is_lww(action: &[u8]) -> bool {
+    queryengine::action_flags(action).contains(ActionFlags::LWW)
+}
+
+/// Returns the keys that a last-writer-wins write (`action` with `args`) writes to
+pub fn written_keys<'a>(action: &[u8], args: &'a [&'a [u8]]) -> Vec<&'a [u8]> {
+    queryengine::action_flags(action)
+        .key_layout()
+        .keys(args.iter().copied())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// When (and where) a key was written. Stamps are ordered by their time, and writes that were
+/// made at the same time on different nodes are ordered by the IDs of the nodes
+pub struct Stamp {
+    /// a reading of the [`Hlc`] of the node that made the write
+    pub time: u64,
+    /// the ID of the node that made the write
+    pub node: u64,
+}
+
+#[derive(Debug, Default)]
+/// A hybrid logical clock. A reading has the physical time (in milliseconds since the UNIX
+/// epoch) in its high bits and a counter in its low bits. The clock never goes backwards and
+/// is moved past every write that we get from a peer, so a write is always stamped later than
+/// every write that the node had seen when it was made (even if the clocks of the nodes drift
+/// apart)
+pub struct Hlc {
+    last: Mutex<u64>,
+}
+
+impl Hlc {
+    /// Returns the time of a write that is made now
+    pub fn now(&self) -> u64 {
+        self.tick(kvengine::now_millis(), 0)
+    }
+    /// Move the clock past the time of a write that we got from a peer
+    pub fn observe(&self, remote: u64) {
+        self.tick(kvengine::now_millis(), remote);
+    }
+    /// Move the clock to the physical time `physical`, past the last reading and past `remote`
+    pub(super) fn tick(&self, physical: u64, remote: u64) -> u64 {
+        let mut last = self.last.lock();
+        *last = (physical << LOGICAL_BITS)
+            .max(*last + 1)
+            .max(remote.saturating_add(1));
+        *last
+    }
+}
+
+/// A key in a table, as it is stamped
+pub type StampedKey = (ObjectID, ObjectID, Box<[u8]>);
+
+#[derive(Debug, Default)]
+/// The stamps of the keys that were written in multi-primary mode (deleted keys keep theirs, so
+/// that an older write can't bring them back). They're only kept in memory
+pub struct Stamps {
+    stamps: Mutex<HashMap<StampedKey, Stamp>>,
+}
+
+impl Stamps {
+    /// Stamp `key` in `keyspace.table` with `stamp`, unless it was already written later.
+    /// Returns true if the write wins (and has to be applied)
+    pub fn advance(&self, keyspace: &ObjectID, table: &ObjectID, key: &[u8], stamp: Stamp) -> bool {
+        let mut stamps = self.stamps.lock();
+        let stamped = stamps
+            .entry((keyspace.clone(), table.clone(), key.into()))
+            .or_insert(stamp);
+        if *stamped > stamp {
+            false
+        } else {
+            *stamped = stamp;
+            true
+        }
+    }
+    /// Returns the stamp of `key` in `keyspace.table` (if it was ever written)
+    pub fn get(&self, keyspace: &ObjectID, table: &ObjectID, key: &[u8]) -> Option<Stamp> {
+        self.stamps
+            .lock()
+            .get(&(keyspace.clone(), table.clone(), key.into()))
+            .copied()
+    }
+    /// Returns every key that has a stamp
+    pub fn keys(&self) -> Vec<StampedKey> {
+        self.stamps.lock().keys().cloned().collect()
+    }
+}
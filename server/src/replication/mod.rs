@@ -0,0 +1,740 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Replication
+//!
+//! A node can be made a _replica_ of another node (its _primary_) with `REPLICAOF`. The replica
+//! connects to the primary and sends a `SYNC` query, after which the primary:
+//! 1. Briefly blocks all writes while it takes a snapshot of its data, so that the snapshot
+//! ends exactly where the stream of writes that follows it starts
+//! 2. Sends the snapshot over, which the replica restores (just like `RESTORESNAP`)
+//! 3. Streams every write that it runs to the replica, which runs it against its own store
+//!
+//! Replication is asynchronous: the primary never waits for its replicas (but clients can, see
+//! below). A replica that can't keep up with the writes is disconnected and has to sync again,
+//! and a replica that loses its connection to the primary keeps retrying (and syncing) in the
+//! background.
+//!
+//! Once a primary accepts `SYNC`, it stops speaking Skyhash on that connection and sends
+//! frames instead:
+//! ```text
+//! [tag: u8][payload length: u64][payload]
+//! ```
+//! - `F`: a file in the snapshot, as `[path length: u64][path][contents]`. A large file is
+//!   split into several frames (with the same path) whose contents are appended in order
+//! - `S`: the end of the snapshot, carrying the offset (see below) that the snapshot ends at
+//! - `W`: a write, encoded just like a record in the append-only log
+//! - `H`: a heartbeat, carrying the primary's clock (in milliseconds since the UNIX epoch)
+//! - `L`: the replica fell behind and the stream ends here
+//!
+//! The replica sends `A` frames back, each carrying the offset of the last write that it
+//! applied. A replica drops the link if a frame claims to be larger than 1 GiB. All sizes (and
+//! numbers) are little endian. Since both the snapshot and the writes
+//! use the storage engine's encoding, the primary and its replicas need to have the same
+//! endianness.
+//!
+//! A replica rejects every write that doesn't come from its primary (with `readonly-replica`).
+//! `REPLICA PROMOTE` drops the link to the primary (once the write that is being applied, if
+//! any, is done) and turns the replica into a primary.
+//!
+//! ## Lag
+//!
+//! The primary sends a heartbeat to its replicas every [`HEARTBEAT_INTERVAL`] milliseconds.
+//! Heartbeats go through the same feed as the writes, so once a replica gets one it has every
+//! write that the primary ran before sending it. A replica's _lag_ is how long ago (by its own
+//! clock) the primary sent the last heartbeat that it got, which means that the clocks of the
+//! primary and its replicas have to be in sync (like with NTP). If `server.maxlag` is set, a
+//! replica that lags behind by more than that (or hasn't got a heartbeat since it started
+//! replicating) refuses reads with `lagging`, so that clients that need fresh data can go to
+//! another node.
+//!
+//! ## Acknowledgements
+//!
+//! Every write that a primary runs gets an _offset_: the number of writes that it had run
+//! (since it started) once it ran that write. A replica knows the offset that its snapshot ends
+//! at and counts the writes that it applies from there, acknowledging the offset that it
+//! reached whenever it has applied everything that it received (and on every heartbeat).
+//! `WAITSYNC <n> <timeout>` waits until at least `n` replicas have acknowledged the offset that
+//! the primary was at after the last write of the connection, which lets clients choose how
+//! durable their writes have to be.
+//!
+//! Note: replicas only get what a snapshot and the writes carry. This means that the data in
+//! volatile tables is only replicated from the time a replica syncs, and that restoring a
+//! snapshot on the primary isn't replicated (run `REPLICAOF` on the replicas to resync them)
+//!
+//! ## Multi-primary mode
+//!
+//! For deployments that can't run a single primary (like nodes at the edge), several nodes can
+//! accept writes and exchange them instead. Once a node has a _peer_ (added with `REPLICA PEER`),
+//! every write to a key in a key/value table is stamped with the time of the node's hybrid
+//! logical clock (and the node's ID, to break ties) and pushed to the peers as a
+//! `REPLICA APPLY` query carrying the key's new value (or its deletion). A node applies a write
+//! that it gets from a peer only if it's stamped later than the last write to the key that it
+//! knows of, so every node ends up with the last write to every key (_last-writer-wins_) no
+//! matter the order in which the writes arrive. Every node has to peer with every other node,
+//! since the writes that a node gets from its peers aren't pushed any further.
+//!
+//! Only writes that replace or delete whole values (`SET`, `UPDATE`, `USET`, `MSET`, `MUPDATE`,
+//! `DEL` and `MDEL`) can be resolved like this, so any other write (and any write in a
+//! transaction or script) is refused with `not-lww` while a node has peers. Tables have to be
+//! created on every node. Whenever the link to a peer is (re)established, we send it the latest
+//! write to every key that we have a stamp for, so a peer that was down (or fell behind)
+//! catches up. The stamps are only kept in memory though, so a node that restarts doesn't
+//! resolve the writes that it got before it restarted
+
+use {
+    crate::{
+        actions::ActionResult, corestore::memstore::ObjectID, dbnet::prelude::*, kvengine::encoding,
+    },
+    core::{
+        str,
+        sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering},
+    },
+    parking_lot::Mutex,
+    std::{collections::HashMap, sync::Arc},
+    tokio::{
+        sync::{
+            broadcast::{self, Sender},
+            watch,
+        },
+        task::JoinHandle,
+        time::{self, Duration},
+    },
+};
+
+mod lww;
+mod peer;
+mod primary;
+mod replica;
+#[cfg(test)]
+mod tests;
+
+pub use self::lww::{is_lww, Stamp};
+pub use self::peer::{Op, Peers};
+pub use self::primary::{heartbeat_service, sync, waitsync, FeedSubscription};
+pub(crate) use self::replica::{encode_query, expect_okay};
+
+/// The number of writes that we'll buffer for a replica before it starts lagging
+const FEED_CAPACITY: usize = 16384;
+/// The interval (in milliseconds) between the heartbeats that we send to replicas
+pub const HEARTBEAT_INTERVAL: u64 = 100;
+/// A file in the snapshot
+const FRAME_FILE: u8 = b'F';
+/// The end of the snapshot
+const FRAME_SYNCED: u8 = b'S';
+/// A write
+const FRAME_WRITE: u8 = b'W';
+/// A heartbeat
+const FRAME_HEARTBEAT: u8 = b'H';
+/// An acknowledgement (sent by the replica)
+const FRAME_ACK: u8 = b'A';
+/// The size of a frame that carries a single number
+const NUMBER_FRAME_SIZE: usize = 17;
+/// The size of an acknowledgement from a replica
+pub const ACK_FRAME_SIZE: usize = NUMBER_FRAME_SIZE;
+/// The replica fell behind
+const FRAME_LAGGED: u8 = b'L';
+/// The largest frame that a replica accepts. No write comes close to this, and files in the
+/// snapshot are sent in chunks of [`FILE_CHUNK_SIZE`] bytes
+const MAX_FRAME_SIZE: u64 = 1024 * 1024 * 1024;
+/// The largest part of a file in the snapshot that is sent in a single frame
+const FILE_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Returns the header of a frame with the given tag and payload length
+fn frame_header(tag: u8, len: usize) -> [u8; 9] {
+    let mut header = [tag; 9];
+    header[1..].copy_from_slice(&(len as u64).to_le_bytes());
+    header
+}
+
+/// Returns a frame telling a replica that it fell behind
+pub fn lagged_frame() -> [u8; 9] {
+    frame_header(FRAME_LAGGED, 0)
+}
+
+/// Returns the header of the frame for a write with a payload of `len` bytes
+pub fn write_frame_header(len: usize) -> [u8; 9] {
+    frame_header(FRAME_WRITE, len)
+}
+
+/// Returns a frame with the given tag that carries `number`
+fn number_frame(tag: u8, number: u64) -> [u8; NUMBER_FRAME_SIZE] {
+    let mut frame = [0; NUMBER_FRAME_SIZE];
+    frame[..9].copy_from_slice(&frame_header(tag, 8));
+    frame[9..].copy_from_slice(&number.to_le_bytes());
+    frame
+}
+
+/// Returns the number in the payload of a frame that carries one
+fn decode_number(payload: &[u8]) -> Option<u64> {
+    payload.try_into().ok().map(u64::from_le_bytes)
+}
+
+/// Returns a heartbeat frame for the given time (in milliseconds since the UNIX epoch)
+pub fn heartbeat_frame(at: u64) -> [u8; NUMBER_FRAME_SIZE] {
+    number_frame(FRAME_HEARTBEAT, at)
+}
+
+/// Returns the offset in an acknowledgement from a replica (of [`ACK_FRAME_SIZE`] bytes), or
+/// `None` if `frame` isn't one
+pub fn decode_ack(frame: &[u8]) -> Option<u64> {
+    if frame.len() != NUMBER_FRAME_SIZE || frame[..9] != frame_header(FRAME_ACK, 8) {
+        return None;
+    }
+    decode_number(&frame[9..])
+}
+
+#[derive(Debug, Clone)]
+/// What is sent to the replicas on the feed
+pub enum FeedEvent {
+    /// a write, encoded like a record in the append-only log
+    Write(Arc<[u8]>),
+    /// a heartbeat, sent at the given time (in milliseconds since the UNIX epoch)
+    Heartbeat(u64),
+}
+
+const PROMOTE: &[u8] = b"promote";
+const PEER: &[u8] = b"peer";
+const UNPEER: &[u8] = b"unpeer";
+const PEERS: &[u8] = b"peers";
+const APPLY: &[u8] = b"apply";
+
+/// The credentials that a replica uses to log in to its primary
+pub type Credentials = (String, String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+/// The state of a replica's link to its primary
+pub enum LinkState {
+    /// trying to connect to the primary
+    Connecting = 0,
+    /// receiving a snapshot
+    Syncing = 1,
+    /// receiving writes
+    Streaming = 2,
+}
+
+impl LinkState {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Connecting => "connecting",
+            Self::Syncing => "syncing",
+            Self::Streaming => "streaming",
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The status of a replica's link to its primary, as updated by the replication task
+pub struct LinkStatus {
+    state: AtomicU8,
+    /// the number of writes applied since the last sync
+    applied: AtomicU64,
+    /// when we last synced (in seconds since the UNIX epoch; zero if never)
+    last_sync: AtomicU64,
+    /// when the primary sent the last heartbeat that we got (in milliseconds since the UNIX
+    /// epoch; zero if never)
+    last_heartbeat: AtomicU64,
+}
+
+impl LinkStatus {
+    fn new() -> Self {
+        Self {
+            state: AtomicU8::new(LinkState::Connecting as u8),
+            applied: AtomicU64::new(0),
+            last_sync: AtomicU64::new(0),
+            last_heartbeat: AtomicU64::new(0),
+        }
+    }
+    pub fn state(&self) -> LinkState {
+        match self.state.load(Ordering::Acquire) {
+            0 => LinkState::Connecting,
+            1 => LinkState::Syncing,
+            _ => LinkState::Streaming,
+        }
+    }
+    fn set_state(&self, state: LinkState) {
+        self.state.store(state as u8, Ordering::Release);
+    }
+    /// Mark the end of a sync
+    fn synced(&self) {
+        self.applied.store(0, Ordering::Release);
+        self.last_sync
+            .store(crate::kvengine::now_millis() / 1000, Ordering::Release);
+        self.set_state(LinkState::Streaming);
+    }
+    fn record_applied(&self) {
+        self.applied.fetch_add(1, Ordering::AcqRel);
+    }
+    /// Record a heartbeat that the primary sent at `at`
+    fn record_heartbeat(&self, at: u64) {
+        self.last_heartbeat.store(at, Ordering::Release);
+    }
+    /// Returns how far (in milliseconds) we are behind the primary, or `None` if we haven't got
+    /// a heartbeat yet. This keeps growing while we're disconnected, since we're missing the
+    /// writes that the primary runs in the meantime
+    pub fn lag(&self) -> Option<u64> {
+        match self.last_heartbeat.load(Ordering::Acquire) {
+            0 => None,
+            at => Some(crate::kvengine::now_millis().saturating_sub(at)),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// A replica's link to its primary
+struct ReplicaLink {
+    primary: String,
+    status: Arc<LinkStatus>,
+    task: JoinHandle<()>,
+    /// tells the replication task to stop
+    terminator: Sender<()>,
+}
+
+impl ReplicaLink {
+    /// Stop replicating and wait for the replication task to finish
+    async fn detach(self) {
+        let _ = self.terminator.send(());
+        let _ = self.task.await;
+    }
+}
+
+/// The replication status of this node, as reported by `SYS INFO replication`
+pub enum ReplicationStatus {
+    Primary {
+        /// the number of replicas that are streaming writes from us
+        replicas: usize,
+    },
+    Replica {
+        /// the address of the primary
+        primary: String,
+        link: LinkState,
+        /// the number of writes applied since the last sync
+        applied: u64,
+        /// when we last synced (in seconds since the UNIX epoch; zero if never)
+        last_sync: u64,
+        /// how far (in milliseconds) we are behind the primary (`None` until we get a heartbeat)
+        lag: Option<u64>,
+    },
+}
+
+#[derive(Debug)]
+/// The offsets that the replicas which are streaming writes from us have acknowledged
+struct Acks {
+    /// the last offset acknowledged by every replica (by the ID of its subscription)
+    offsets: Mutex<HashMap<u64, u64>>,
+    /// used to give every subscription an ID
+    next_id: AtomicU64,
+    /// signalled whenever a replica acknowledges an offset (or goes away)
+    changed: watch::Sender<()>,
+    /// cloned by those who wait for the signal (and keeps it from failing)
+    waiter: watch::Receiver<()>,
+}
+
+impl Acks {
+    fn new() -> Self {
+        let (changed, waiter) = watch::channel(());
+        Self {
+            offsets: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            changed,
+            waiter,
+        }
+    }
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+    fn set(&self, id: u64, offset: u64) {
+        self.offsets.lock().insert(id, offset);
+        let _ = self.changed.send(());
+    }
+    fn remove(&self, id: u64) {
+        self.offsets.lock().remove(&id);
+        let _ = self.changed.send(());
+    }
+    /// Returns the number of replicas that have acknowledged `offset`
+    fn count(&self, offset: u64) -> usize {
+        self.offsets
+            .lock()
+            .values()
+            .filter(|acked| **acked >= offset)
+            .count()
+    }
+}
+
+#[derive(Debug)]
+/// The replication state of a node. Every write is published on the feed (if a replica is
+/// listening) and if this node is a replica, the link to its primary is kept here
+pub struct Replication {
+    feed: Sender<FeedEvent>,
+    subscribers: Arc<AtomicUsize>,
+    /// the offset of the last write that we ran
+    offset: AtomicU64,
+    /// held while a write is sent to the replicas, so that the writes are sent in the order of
+    /// their offsets
+    publishing: Mutex<()>,
+    acks: Arc<Acks>,
+    /// used to name the snapshots that are sent to replicas
+    snapshots: AtomicU64,
+    link: Mutex<Option<ReplicaLink>>,
+    /// set while this node is a replica (so that we can reject writes without locking `link`)
+    is_replica: AtomicBool,
+    /// the peers (if this node is in multi-primary mode)
+    peers: Peers,
+}
+
+impl Replication {
+    pub fn new() -> Self {
+        let (feed, _) = tokio::sync::broadcast::channel(FEED_CAPACITY);
+        Self {
+            feed,
+            subscribers: Arc::new(AtomicUsize::new(0)),
+            offset: AtomicU64::new(0),
+            publishing: Mutex::new(()),
+            acks: Arc::new(Acks::new()),
+            snapshots: AtomicU64::new(0),
+            link: Mutex::new(None),
+            is_replica: AtomicBool::new(false),
+            peers: Peers::new(),
+        }
+    }
+    /// Returns true if this node is replicating from a primary
+    pub fn is_replica(&self) -> bool {
+        self.is_replica.load(Ordering::Acquire)
+    }
+    /// Returns the peers of this node (see [multi-primary mode](self#multi-primary-mode))
+    pub fn peers(&self) -> &Peers {
+        &self.peers
+    }
+    /// Returns true if any replica is streaming writes from us
+    pub fn is_streaming(&self) -> bool {
+        self.subscribers.load(Ordering::Acquire) != 0
+    }
+    /// Returns the offset of the last write that we ran
+    pub fn offset(&self) -> u64 {
+        self.offset.load(Ordering::Acquire)
+    }
+    /// Send a write (encoded like a record in the append-only log) to the replicas (if any)
+    pub fn publish(&self, record: Vec<u8>) {
+        if self.is_streaming() {
+            let _ordered = self.publishing.lock();
+            self.offset.fetch_add(1, Ordering::AcqRel);
+            let _ = self.feed.send(FeedEvent::Write(Arc::from(record)));
+        } else {
+            // nobody can subscribe while a write runs, so there's nothing to order
+            self.offset.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+    /// Send a heartbeat to the replicas (if any)
+    pub fn heartbeat(&self) {
+        if self.is_streaming() {
+            let _ = self
+                .feed
+                .send(FeedEvent::Heartbeat(crate::kvengine::now_millis()));
+        }
+    }
+    pub(crate) fn subscribe(&self) -> FeedSubscription {
+        // see Notifier::subscribe for why we subscribe first
+        let rx = self.feed.subscribe();
+        self.subscribers.fetch_add(1, Ordering::AcqRel);
+        FeedSubscription::new(
+            rx,
+            self.subscribers.clone(),
+            self.acks.next_id(),
+            self.acks.clone(),
+        )
+    }
+    /// Wait until at least `count` replicas have acknowledged `offset`, or until `timeout`
+    /// elapses. Returns the number of replicas that have acknowledged it
+    pub async fn wait_for_acks(&self, offset: u64, count: usize, timeout: Duration) -> usize {
+        let deadline = time::Instant::now() + timeout;
+        let mut changed = self.acks.waiter.clone();
+        loop {
+            let acked = self.acks.count(offset);
+            if acked >= count {
+                return acked;
+            }
+            if time::timeout_at(deadline, changed.changed()).await.is_err() {
+                return self.acks.count(offset);
+            }
+        }
+    }
+    fn next_snapshot_name(&self) -> String {
+        self.snapshots.fetch_add(1, Ordering::Relaxed).to_string()
+    }
+    /// Start replicating from the primary at `host:port`, dropping the link to the current
+    /// primary (if any)
+    pub async fn replicate_from(
+        &self,
+        handle: &Corestore,
+        host: String,
+        port: u16,
+        credentials: Option<Credentials>,
+    ) {
+        // reject writes right away; we'll overwrite whatever we have with the primary's data
+        self.is_replica.store(true, Ordering::Release);
+        let old = self.link.lock().take();
+        if let Some(old) = old {
+            old.detach().await;
+        }
+        let status = Arc::new(LinkStatus::new());
+        let primary = format!("{host}:{port}");
+        let (terminator, terminated) = broadcast::channel(1);
+        let task = tokio::spawn(replica::run(
+            handle.clone(),
+            status.clone(),
+            host,
+            port,
+            credentials,
+            self.next_snapshot_name(),
+            terminated,
+        ));
+        log::info!("Replicating from {}", primary);
+        let old = self.link.lock().replace(ReplicaLink {
+            primary,
+            status,
+            task,
+            terminator,
+        });
+        if let Some(old) = old {
+            // someone else ran REPLICAOF at the same time; the last one wins
+            old.detach().await;
+        }
+    }
+    /// Stop replicating and make this node a primary. Returns false if this node wasn't a
+    /// replica
+    pub async fn promote(&self) -> bool {
+        let old = self.link.lock().take();
+        match old {
+            Some(link) => {
+                let primary = link.primary.clone();
+                link.detach().await;
+                self.is_replica.store(false, Ordering::Release);
+                log::info!("Promoted to primary (was replicating from {})", primary);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Returns the replication status of this node
+    pub fn status(&self) -> ReplicationStatus {
+        match *self.link.lock() {
+            Some(ref link) => ReplicationStatus::Replica {
+                primary: link.primary.clone(),
+                link: link.status.state(),
+                applied: link.status.applied.load(Ordering::Acquire),
+                last_sync: link.status.last_sync.load(Ordering::Acquire),
+                lag: link.status.lag(),
+            },
+            None => ReplicationStatus::Primary {
+                replicas: self.subscribers.load(Ordering::Acquire),
+            },
+        }
+    }
+    /// Returns true if this node is a replica that is more than `max_lag` milliseconds behind
+    /// its primary (or doesn't know how far behind it is). Any lag is fine if `max_lag` is zero
+    pub fn lags_behind(&self, max_lag: u64) -> bool {
+        if max_lag == 0 || !self.is_replica() {
+            return false;
+        }
+        match *self.link.lock() {
+            Some(ref link) => link.status.lag().map_or(true, |lag| lag > max_lag),
+            // we're switching to another primary
+            None => true,
+        }
+    }
+}
+
+impl Default for Replication {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+action! {
+    /// Run a `REPLICAOF <host> <port> [<username> <token>]` query. The credentials are only
+    /// needed if auth is enabled on the primary
+    fn replicaof(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2 || len == 4)?;
+        let (host, port) = unsafe {
+            // SAFETY: We have already checked that there are at least two items
+            (act.next_unchecked(), act.next_unchecked())
+        };
+        let (host, port) = self::parse_address::<P>(host, port)?;
+        let credentials = match (act.next_string_owned(), act.next_string_owned()) {
+            (Some(username), Some(token)) => Some((username, token)),
+            _ => None,
+        };
+        handle
+            .get_replication()
+            .replicate_from(handle, host, port, credentials)
+            .await;
+        con._write_raw(P::RCODE_OKAY).await?;
+        Ok(())
+    }
+}
+
+/// Parse the address of another node from a query
+fn parse_address<P: ProtocolSpec>(host: &[u8], port: &[u8]) -> ActionResult<(String, u16)> {
+    if !encoding::is_utf8(host) {
+        return util::err(P::RCODE_ENCODING_ERROR);
+    }
+    match str::from_utf8(port)
+        .ok()
+        .and_then(|port| port.parse::<u16>().ok())
+    {
+        Some(port) => Ok((String::from_utf8_lossy(host).into_owned(), port)),
+        None => util::err(P::RCODE_WRONGTYPE_ERR),
+    }
+}
+
+/// Parse a write that a peer pushed to us with `REPLICA APPLY <keyspace> <table> <time> <node>
+/// <key> [<value>]`
+fn parse_op<P: ProtocolSpec>(mut act: ActionIter<'_>) -> ActionResult<Op> {
+    let (keyspace, table, time, node, key) = unsafe {
+        // SAFETY: The caller has checked that there are at least five items
+        (
+            act.next_unchecked(),
+            act.next_unchecked(),
+            act.next_unchecked(),
+            act.next_unchecked(),
+            act.next_unchecked(),
+        )
+    };
+    let number = |number: &[u8]| {
+        str::from_utf8(number)
+            .ok()
+            .and_then(|number| number.parse::<u64>().ok())
+    };
+    let (keyspace, table) = match (
+        ObjectID::try_from_slice(keyspace),
+        ObjectID::try_from_slice(table),
+    ) {
+        (Some(keyspace), Some(table)) => (keyspace, table),
+        _ => return util::err(P::RSTRING_BAD_CONTAINER_NAME),
+    };
+    let stamp = match (number(time), number(node)) {
+        (Some(time), Some(node)) => Stamp { time, node },
+        _ => return util::err(P::RCODE_WRONGTYPE_ERR),
+    };
+    Ok(Op {
+        keyspace,
+        table,
+        key: key.to_vec(),
+        value: act.next().map(<[u8]>::to_vec),
+        stamp,
+    })
+}
+
+action! {
+    /// Run a `REPLICA` query:
+    /// - `REPLICA PROMOTE`: stop replicating and make this node a primary
+    /// - `REPLICA PEER <host> <port> [<username> <token>]`: push the writes made on this node to
+    /// another primary (logging in with the given credentials), entering multi-primary mode
+    /// - `REPLICA UNPEER <host> <port>`: stop pushing writes to a peer
+    /// - `REPLICA PEERS`: the peers of this node along with the states of the links to them
+    /// - `REPLICA APPLY <keyspace> <table> <time> <node> <key> [<value>]`: apply a write that
+    /// a peer made (sent by the peer)
+    ///
+    /// If auth is enabled, only root can promote this node and change its peers
+    fn replica(
+        handle: &Corestore,
+        con: &mut Connection<C, P>,
+        auth: &mut AuthProviderHandle,
+        act: ActionIter<'a>,
+    ) {
+        let mut act = act;
+        ensure_length::<P>(act.len(), |len| len != 0)?;
+        let replication = handle.get_replication();
+        let subaction = unsafe {
+            // SAFETY: We have already checked that there is at least one argument
+            act.next_lowercase_unchecked()
+        };
+        match subaction.as_ref() {
+            PROMOTE => {
+                ensure_length::<P>(act.len(), |len| len == 0)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                if replication.promote().await {
+                    con._write_raw(P::RCODE_OKAY).await?;
+                } else {
+                    con._write_raw(P::RSTRING_NOT_A_REPLICA).await?;
+                }
+            }
+            PEER => {
+                ensure_length::<P>(act.len(), |len| len == 2 || len == 4)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                let (host, port) = unsafe {
+                    // SAFETY: We have already checked that there are at least two items
+                    (act.next_unchecked(), act.next_unchecked())
+                };
+                let (host, port) = self::parse_address::<P>(host, port)?;
+                let credentials = match (act.next_string_owned(), act.next_string_owned()) {
+                    (Some(username), Some(token)) => Some((username, token)),
+                    _ => None,
+                };
+                replication
+                    .peers()
+                    .peer_with(handle, host, port, credentials)
+                    .await;
+                con._write_raw(P::RCODE_OKAY).await?;
+            }
+            UNPEER => {
+                ensure_length::<P>(act.len(), |len| len == 2)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                let (host, port) = unsafe {
+                    // SAFETY: We have already checked the length
+                    (act.next_unchecked(), act.next_unchecked())
+                };
+                let (host, port) = self::parse_address::<P>(host, port)?;
+                if replication.peers().unpeer(&format!("{host}:{port}")).await {
+                    con._write_raw(P::RCODE_OKAY).await?;
+                } else {
+                    con._write_raw(P::RCODE_NIL).await?;
+                }
+            }
+            PEERS => {
+                ensure_length::<P>(act.len(), |len| len == 0)?;
+                let peers = replication.peers().list();
+                con.write_typed_non_null_array_header(peers.len() * 2, b'+')
+                    .await?;
+                for (peer, state) in peers {
+                    con.write_typed_non_null_array_element(peer.as_bytes())
+                        .await?;
+                    con.write_typed_non_null_array_element(state.as_str().as_bytes())
+                        .await?;
+                }
+            }
+            APPLY => {
+                ensure_length::<P>(act.len(), |len| len == 5 || len == 6)?;
+                let op = self::parse_op::<P>(act)?;
+                replication.peers().apply(handle, &op).await;
+                con._write_raw(P::RCODE_OKAY).await?;
+            }
+            _ => return util::err(P::RCODE_UNKNOWN_ACTION),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,425 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! Multi-primary mode: pushing the writes that are made on this node to its peers and applying
+//! the writes that they push to us (see the module docs of [`replication`](super))
+
+use {
+    super::{
+        encode_query, expect_okay,
+        lww::{self, Hlc, Stamp, StampedKey, Stamps},
+        replica::{BACKOFF_MAX, BACKOFF_MIN},
+        Credentials, LinkState, LinkStatus,
+    },
+    crate::{
+        corestore::{
+            memstore::ObjectID,
+            table::{DataModel, Table},
+            Corestore, SharedSlice,
+        },
+        queryengine, IoResult,
+    },
+    parking_lot::Mutex,
+    std::{
+        io::{Error as IoError, ErrorKind},
+        sync::Arc,
+    },
+    tokio::{
+        io::{AsyncWriteExt, BufReader},
+        net::TcpStream,
+        sync::{
+            broadcast::{self, error::RecvError, Receiver, Sender},
+            Mutex as AsyncMutex, OwnedMutexGuard,
+        },
+        task::JoinHandle,
+        time::{self, Duration},
+    },
+};
+
+/// The number of writes that we'll buffer for a peer before it has to catch up again
+const FEED_CAPACITY: usize = 16384;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A write to a key, as it is pushed to the peers
+pub struct Op {
+    pub keyspace: ObjectID,
+    pub table: ObjectID,
+    pub key: Vec<u8>,
+    /// the value that the key was set to (`None` if it was deleted)
+    pub value: Option<Vec<u8>>,
+    pub stamp: Stamp,
+}
+
+impl Op {
+    /// Encode the write as a `REPLICA APPLY` query
+    pub fn encode(&self) -> Vec<u8> {
+        let time = self.stamp.time.to_string();
+        let node = self.stamp.node.to_string();
+        let mut query = vec![
+            b"REPLICA".as_slice(),
+            b"APPLY",
+            self.keyspace.as_slice(),
+            self.table.as_slice(),
+            time.as_bytes(),
+            node.as_bytes(),
+            &self.key,
+        ];
+        if let Some(ref value) = self.value {
+            query.push(value);
+        }
+        encode_query(&query)
+    }
+}
+
+#[derive(Debug)]
+/// A link to a peer, which the writes made on this node are pushed on
+struct PeerLink {
+    peer: String,
+    status: Arc<LinkStatus>,
+    task: JoinHandle<()>,
+    /// tells the task to stop
+    terminator: Sender<()>,
+}
+
+impl PeerLink {
+    /// Stop pushing writes and wait for the task to finish
+    async fn detach(self) {
+        let _ = self.terminator.send(());
+        let _ = self.task.await;
+    }
+}
+
+#[derive(Debug)]
+/// The multi-primary state of a node: its clock, the stamps of the keys and the links to its
+/// peers. A node is in multi-primary mode while it has at least one peer
+pub struct Peers {
+    /// the ID of this node, which orders the writes that are made at the same time
+    node: u64,
+    clock: Hlc,
+    stamps: Stamps,
+    /// the writes made on this node, which are pushed to every peer
+    feed: Sender<Arc<Op>>,
+    /// held while a write is made (and stamped), so that the writes to a key are stamped in
+    /// the order in which they're made
+    ordering: Arc<AsyncMutex<()>>,
+    links: Mutex<Vec<PeerLink>>,
+}
+
+impl Peers {
+    pub fn new() -> Self {
+        let mut node = [0; 8];
+        openssl::rand::rand_bytes(&mut node).unwrap();
+        let (feed, _) = broadcast::channel(FEED_CAPACITY);
+        Self {
+            node: u64::from_le_bytes(node),
+            clock: Hlc::default(),
+            stamps: Stamps::default(),
+            feed,
+            ordering: Arc::new(AsyncMutex::new(())),
+            links: Mutex::new(Vec::new()),
+        }
+    }
+    /// Returns true if this node is in multi-primary mode
+    pub fn is_enabled(&self) -> bool {
+        !self.links.lock().is_empty()
+    }
+    /// Wait for our turn to make a write (see [`Peers::record`])
+    pub async fn order(&self) -> OwnedMutexGuard<()> {
+        self.ordering.clone().lock_owned().await
+    }
+    /// Stamp the keys that a last-writer-wins write (`action` with `args`) just wrote to in
+    /// the current table of `handle` and push them to the peers. This has to be called while
+    /// holding the guard returned by [`Peers::order`]
+    pub fn record(&self, handle: &Corestore, action: &[u8], args: &[&[u8]]) {
+        let (keyspace, table) = match handle.get_ids() {
+            (Some(keyspace), Some(table)) => (keyspace, table),
+            _ => return,
+        };
+        let kve = match handle.get_ctable_ref().map(Table::get_model_ref) {
+            Some(DataModel::KV(kve)) => kve,
+            _ => return,
+        };
+        for key in lww::written_keys(action, args) {
+            let stamp = Stamp {
+                time: self.clock.now(),
+                node: self.node,
+            };
+            self.stamps.advance(keyspace, table, key, stamp);
+            let op = Op {
+                keyspace: keyspace.clone(),
+                table: table.clone(),
+                key: key.to_vec(),
+                value: kve
+                    .get_cloned_unchecked(key)
+                    .map(|value| value.as_ref().to_vec()),
+                stamp,
+            };
+            // nobody may be listening if the links are down
+            let _ = self.feed.send(Arc::new(op));
+        }
+    }
+    /// Apply a write that a peer pushed to us, unless the key was written later. Returns true
+    /// if the write was applied
+    pub async fn apply(&self, handle: &Corestore, op: &Op) -> bool {
+        let _ordered = self.order().await;
+        self.clock.observe(op.stamp.time);
+        let table = match self::kv_table(handle, &op.keyspace, &op.table) {
+            Some(table) => table,
+            None => {
+                log::warn!("Skipping a write from a peer for a missing (or non key/value) table");
+                return false;
+            }
+        };
+        if !self
+            .stamps
+            .advance(&op.keyspace, &op.table, &op.key, op.stamp)
+        {
+            return false;
+        }
+        let kve = match table.get_model_ref() {
+            DataModel::KV(kve) => kve,
+            _ => unreachable!("kv_table only returns key/value tables"),
+        };
+        let (written, record): (_, Vec<&[u8]>) = match op.value {
+            Some(ref value) => (
+                kve.upsert(SharedSlice::new(&op.key), SharedSlice::new(value)),
+                vec![b"USET".as_slice(), &op.key, value],
+            ),
+            None => (
+                kve.remove(&op.key).map(|_| ()),
+                vec![b"DEL".as_slice(), &op.key],
+            ),
+        };
+        if written.is_err() {
+            log::warn!("Skipping a write from a peer with a bad encoding");
+            return false;
+        }
+        if queryengine::is_logging(handle) {
+            queryengine::log_record_in(
+                handle,
+                Some(&op.keyspace),
+                Some(&op.table),
+                record.iter().copied(),
+            );
+        }
+        true
+    }
+    /// Returns the latest write to `key` (as it is now), if it has a stamp
+    async fn current(&self, handle: &Corestore, key: &StampedKey) -> Option<Op> {
+        let _ordered = self.order().await;
+        let (keyspace, table, key) = key;
+        let stamp = self.stamps.get(keyspace, table, key)?;
+        let value = match self::kv_table(handle, keyspace, table)?.get_model_ref() {
+            DataModel::KV(kve) => kve
+                .get_cloned_unchecked(key)
+                .map(|value| value.as_ref().to_vec()),
+            _ => return None,
+        };
+        Some(Op {
+            keyspace: keyspace.clone(),
+            table: table.clone(),
+            key: key.to_vec(),
+            value,
+            stamp,
+        })
+    }
+    /// Start pushing writes to the peer at `host:port`, dropping the link to it (if any)
+    pub async fn peer_with(
+        &self,
+        handle: &Corestore,
+        host: String,
+        port: u16,
+        credentials: Option<Credentials>,
+    ) {
+        let peer = format!("{host}:{port}");
+        let status = Arc::new(LinkStatus::new());
+        let (terminator, terminated) = broadcast::channel(1);
+        let task = tokio::spawn(self::run(
+            handle.clone(),
+            status.clone(),
+            host,
+            port,
+            credentials,
+            terminated,
+        ));
+        log::info!("Peering with {}", peer);
+        let old = {
+            let mut links = self.links.lock();
+            let old = links
+                .iter()
+                .position(|link| link.peer == peer)
+                .map(|at| links.remove(at));
+            links.push(PeerLink {
+                peer,
+                status,
+                task,
+                terminator,
+            });
+            old
+        };
+        if let Some(old) = old {
+            old.detach().await;
+        }
+    }
+    /// Stop pushing writes to `peer` (as `host:port`). Returns false if it isn't a peer
+    pub async fn unpeer(&self, peer: &str) -> bool {
+        let link = {
+            let mut links = self.links.lock();
+            match links.iter().position(|link| link.peer == peer) {
+                Some(at) => links.remove(at),
+                None => return false,
+            }
+        };
+        link.detach().await;
+        log::info!("Stopped peering with {}", peer);
+        true
+    }
+    /// Returns the address of every peer along with the state of the link to it
+    pub fn list(&self) -> Vec<(String, LinkState)> {
+        self.links
+            .lock()
+            .iter()
+            .map(|link| (link.peer.clone(), link.status.state()))
+            .collect()
+    }
+}
+
+impl Default for Peers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns `keyspace.table`, if it exists and is a key/value table
+fn kv_table(handle: &Corestore, keyspace: &ObjectID, table: &ObjectID) -> Option<Arc<Table>> {
+    let table = handle
+        .get_store()
+        .get_keyspace_atomic_ref(keyspace)?
+        .get_table_atomic_ref(table)?;
+    matches!(table.get_model_ref(), DataModel::KV(_)).then_some(table)
+}
+
+/// Keep pushing writes to the peer at `host:port` until the link is dropped. Every time the
+/// link is (re)established, the peer catches up first
+async fn run(
+    handle: Corestore,
+    status: Arc<LinkStatus>,
+    host: String,
+    port: u16,
+    credentials: Option<Credentials>,
+    mut terminator: Receiver<()>,
+) {
+    let mut backoff = BACKOFF_MIN;
+    loop {
+        status.set_state(LinkState::Connecting);
+        let ret = self::push(
+            &handle,
+            &status,
+            (&host, port),
+            &credentials,
+            &mut terminator,
+        )
+        .await;
+        if status.state() == LinkState::Streaming {
+            // we did catch up, so this isn't a problem with connecting to the peer
+            backoff = BACKOFF_MIN;
+        }
+        match ret {
+            Ok(()) => break,
+            Err(e) => log::error!(
+                "Pushing writes to the peer {}:{} failed with error: {}. Retrying in {}s",
+                host,
+                port,
+                e,
+                backoff
+            ),
+        }
+        let slept = tokio::select! {
+            _ = time::sleep(Duration::from_secs(backoff)) => true,
+            _ = terminator.recv() => false,
+        };
+        if !slept {
+            break;
+        }
+        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+}
+
+/// Connect to the peer, send it the latest write to every key that has a stamp (so that it
+/// catches up with whatever it missed) and then push the writes as they're made. This returns
+/// once the link is dropped, or on an error (including falling behind)
+async fn push(
+    handle: &Corestore,
+    status: &LinkStatus,
+    peer: (&str, u16),
+    credentials: &Option<Credentials>,
+    terminator: &mut Receiver<()>,
+) -> IoResult<()> {
+    let peers = handle.get_replication().peers();
+    // subscribe first, so that we don't miss the writes that are made while we catch up
+    let mut feed = peers.feed.subscribe();
+    let mut stream = tokio::select! {
+        stream = TcpStream::connect(peer) => BufReader::new(stream?),
+        _ = terminator.recv() => return Ok(()),
+    };
+    if let Some((username, token)) = credentials {
+        let login = encode_query(&[b"AUTH", b"LOGIN", username.as_bytes(), token.as_bytes()]);
+        stream.write_all(&login).await?;
+        expect_okay(&mut stream, "peer", "log in").await?;
+    }
+    status.set_state(LinkState::Syncing);
+    for key in peers.stamps.keys() {
+        if terminator.try_recv().is_ok() {
+            return Ok(());
+        }
+        if let Some(op) = peers.current(handle, &key).await {
+            stream.write_all(&op.encode()).await?;
+            expect_okay(&mut stream, "peer", "apply a write").await?;
+        }
+    }
+    status.set_state(LinkState::Streaming);
+    log::info!("Caught up the peer {}:{}", peer.0, peer.1);
+    loop {
+        let op = tokio::select! {
+            op = feed.recv() => op,
+            _ = terminator.recv() => return Ok(()),
+        };
+        match op {
+            Ok(op) => {
+                stream.write_all(&op.encode()).await?;
+                expect_okay(&mut stream, "peer", "apply a write").await?;
+            }
+            Err(RecvError::Lagged(missed)) => {
+                return Err(IoError::new(
+                    ErrorKind::Other,
+                    format!("fell behind by {missed} writes (the peer will catch up)"),
+                ))
+            }
+            // the feed lives as long as the store, so this can't really happen
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,252 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! The primary's side of replication: sending a snapshot to a replica and then streaming
+//! the writes (and heartbeats) to it
+
+use {
+    super::{
+        frame_header, number_frame, Acks, FeedEvent, FILE_CHUNK_SIZE, FRAME_FILE, FRAME_SYNCED,
+        HEARTBEAT_INTERVAL,
+    },
+    crate::{
+        actions::ActionResult,
+        dbnet::{prelude::*, BufferedSocketStream},
+        storage::v1::flush::{self, ReplicationSnapshot, StorageTarget},
+        IoResult,
+    },
+    core::{
+        iter, str,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+    std::{
+        fs,
+        io::ErrorKind,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+    tokio::{
+        sync::broadcast::{error::RecvError, Receiver},
+        time::{self, Duration},
+    },
+};
+
+/// A replica's subscription to the writes. Dropping it stops streaming
+pub struct FeedSubscription {
+    rx: Receiver<FeedEvent>,
+    subscribers: Arc<AtomicUsize>,
+    id: u64,
+    acks: Arc<Acks>,
+}
+
+impl FeedSubscription {
+    pub(super) fn new(
+        rx: Receiver<FeedEvent>,
+        subscribers: Arc<AtomicUsize>,
+        id: u64,
+        acks: Arc<Acks>,
+    ) -> Self {
+        Self {
+            rx,
+            subscribers,
+            id,
+            acks,
+        }
+    }
+    /// Record that the replica has applied the writes up to `offset`
+    pub fn ack(&self, offset: u64) {
+        self.acks.set(self.id, offset);
+    }
+    /// Wait for the next write (or heartbeat). If we fell behind, the number of events that we
+    /// missed is returned as an error instead
+    pub async fn next(&mut self) -> Result<FeedEvent, u64> {
+        match self.rx.recv().await {
+            Ok(event) => Ok(event),
+            Err(RecvError::Lagged(missed)) => Err(missed),
+            // the feed lives as long as the store, so this can't really happen
+            Err(RecvError::Closed) => core::future::pending().await,
+        }
+    }
+}
+
+impl Drop for FeedSubscription {
+    fn drop(&mut self) {
+        self.subscribers.fetch_sub(1, Ordering::AcqRel);
+        self.acks.remove(self.id);
+    }
+}
+
+/// Send a heartbeat to the replicas every [`HEARTBEAT_INTERVAL`] milliseconds, so that they
+/// can tell how far behind they are even when no writes are run
+pub async fn heartbeat_service(handle: Corestore, mut terminator: Receiver<()>) {
+    let mut interval = time::interval(Duration::from_millis(HEARTBEAT_INTERVAL));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => handle.get_replication().heartbeat(),
+            _ = terminator.recv() => break,
+        }
+    }
+    log::info!("Replication heartbeats have stopped");
+}
+
+/// Run a `SYNC` query (sent by a replica). A snapshot is sent to the replica, after which
+/// the connection is subscribed to the writes
+pub async fn sync<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    feed: &mut Option<FeedSubscription>,
+    act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 0)?;
+    if !registry::state_okay() {
+        return util::err(P::RCODE_SERVER_ERR);
+    }
+    let replication = handle.get_replication();
+    let target = ReplicationSnapshot::new(replication.next_snapshot_name());
+    let root = target.root();
+    let (subscription, offset, frozen) = {
+        // no writes can run while we hold this, so every write that we'll stream is one
+        // that isn't a part of the snapshot. We only hold it for as long as it takes to
+        // copy the store: the copy is written out after writers are let back in
+        let _txn_lock = registry::lock_txn_exclusive().await;
+        let subscription = replication.subscribe();
+        let offset = replication.offset();
+        let store = handle.clone_store();
+        let frozen = tokio::task::spawn_blocking(move || store.freeze())
+            .await
+            .expect("sync thread panicked");
+        (subscription, offset, frozen)
+    };
+    // nothing else can touch the copy, so this doesn't need the flush lock either
+    let flushed = tokio::task::spawn_blocking(move || flush::flush_full(target, &frozen))
+        .await
+        .expect("sync thread panicked");
+    if let Err(e) = flushed {
+        log::error!(
+            "Failed to create a snapshot for a replica with error: {}",
+            e
+        );
+        self::remove_snapshot(root).await;
+        return util::err(P::RCODE_SERVER_ERR);
+    }
+    con._write_raw(P::RCODE_OKAY).await?;
+    let sent = self::send_snapshot(con, &root, offset).await;
+    self::remove_snapshot(root).await;
+    sent?;
+    *feed = Some(subscription);
+    log::info!("A replica has synced");
+    Ok(())
+}
+
+/// Run a `WAITSYNC <n> <timeout>` query. This waits until at least `n` replicas have
+/// acknowledged `offset` (the offset after the connection's latest write), or until `timeout`
+/// milliseconds have elapsed (0 doesn't wait at all). The number of replicas that acknowledged
+/// it is returned
+pub async fn waitsync<'a, C: 'a + BufferedSocketStream, P: ProtocolSpec>(
+    handle: &Corestore,
+    con: &mut Connection<C, P>,
+    offset: u64,
+    mut act: ActionIter<'a>,
+) -> ActionResult<()> {
+    ensure_length::<P>(act.len(), |len| len == 2)?;
+    let (count, timeout) = unsafe {
+        // UNSAFE(@ohsayan): We have checked that there are two arguments
+        (act.next_unchecked(), act.next_unchecked())
+    };
+    let count = str::from_utf8(count)
+        .ok()
+        .and_then(|count| count.parse::<usize>().ok());
+    let timeout = str::from_utf8(timeout)
+        .ok()
+        .and_then(|timeout| timeout.parse::<u64>().ok());
+    let (count, timeout) = match (count, timeout) {
+        (Some(count), Some(timeout)) => (count, Duration::from_millis(timeout)),
+        _ => return util::err(P::RCODE_WRONGTYPE_ERR),
+    };
+    let acked = handle
+        .get_replication()
+        .wait_for_acks(offset, count, timeout)
+        .await;
+    con.write_usize(acked).await?;
+    Ok(())
+}
+
+/// Send all the files in the snapshot at `root` (which ends at `offset`) to the replica
+async fn send_snapshot<C: BufferedSocketStream, P: ProtocolSpec>(
+    con: &mut Connection<C, P>,
+    root: &str,
+    offset: u64,
+) -> IoResult<()> {
+    let dir = PathBuf::from(root);
+    let files = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        self::list_files(&dir, "", &mut files).map(|_| files)
+    })
+    .await
+    .expect("sync thread panicked")?;
+    for (name, path) in files {
+        let contents = tokio::fs::read(&path).await?;
+        // a large file is sent in chunks, but an empty file still needs a frame of its own
+        let mut chunks = contents.chunks(FILE_CHUNK_SIZE);
+        let first = chunks.next().unwrap_or_default();
+        for chunk in iter::once(first).chain(chunks) {
+            let len = 8 + name.len() + chunk.len();
+            con._write_raw(&frame_header(FRAME_FILE, len)).await?;
+            con._write_raw(&(name.len() as u64).to_le_bytes()).await?;
+            con._write_raw(name.as_bytes()).await?;
+            con._write_raw(chunk).await?;
+        }
+    }
+    con._write_raw(&number_frame(FRAME_SYNCED, offset)).await
+}
+
+/// Collect all the files under `dir` along with their `/` separated paths relative to the
+/// root of the snapshot
+fn list_files(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) -> IoResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            self::list_files(&entry.path(), &format!("{name}/"), files)?;
+        } else {
+            files.push((name, entry.path()));
+        }
+    }
+    Ok(())
+}
+
+/// Remove a snapshot once it has been sent (or if we failed to create it)
+async fn remove_snapshot(root: String) {
+    match tokio::fs::remove_dir_all(&root).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            log::warn!(
+                "Failed to remove the snapshot for a replica (ignored): {}",
+                e
+            )
+        }
+        _ => {}
+    }
+}
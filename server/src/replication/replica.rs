@@ -0,0 +1,345 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! The replica's side of replication: syncing with the primary and then applying the writes
+//! that it streams to us
+
+use {
+    super::{
+        decode_number, number_frame, Credentials, LinkState, LinkStatus, FRAME_ACK, FRAME_FILE,
+        FRAME_HEARTBEAT, FRAME_LAGGED, FRAME_SYNCED, FRAME_WRITE, MAX_FRAME_SIZE,
+    },
+    crate::{
+        corestore::Corestore,
+        services::aof::Replayer,
+        storage::v1::{aof, interface::DIR_REPLROOT, unflush},
+        IoResult,
+    },
+    std::{
+        fs,
+        io::{Error as IoError, ErrorKind},
+        path::PathBuf,
+        sync::Arc,
+    },
+    tokio::{
+        fs::OpenOptions,
+        io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::TcpStream,
+        sync::broadcast::Receiver,
+        time::{self, Duration},
+    },
+};
+
+/// The initial delay (in seconds) before we try to connect to the primary (or a peer) again
+pub(super) const BACKOFF_MIN: u64 = 1;
+/// The maximum delay (in seconds) before we try to connect to the primary (or a peer) again
+pub(super) const BACKOFF_MAX: u64 = 32;
+/// The response to a query that went through
+const RESPONSE_OKAY: &[u8] = b"*!0\n";
+
+fn invalid_data(msg: impl Into<String>) -> IoError {
+    IoError::new(ErrorKind::InvalidData, msg.into())
+}
+
+/// Encode a simple query (Skyhash 2.0)
+pub(crate) fn encode_query(args: &[&[u8]]) -> Vec<u8> {
+    let mut query = format!("*{}\n", args.len()).into_bytes();
+    for arg in args {
+        query.extend_from_slice(format!("{}\n", arg.len()).as_bytes());
+        query.extend_from_slice(arg);
+    }
+    query
+}
+
+/// Returns the path (under `staging`) for a file in the snapshot, if the name that the primary
+/// sent is a plain relative path
+pub(super) fn staged_path(staging: &str, name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::from(staging);
+    for component in name.split('/') {
+        let is_plain = !component.is_empty()
+            && component != "."
+            && component != ".."
+            && !component.contains(['\\', ':']);
+        if !is_plain {
+            return None;
+        }
+        path.push(component);
+    }
+    Some(path)
+}
+
+/// Keep replicating from the primary at `host:port` until the link is dropped. Every time
+/// the stream ends (or fails), we connect and sync again
+pub(super) async fn run(
+    handle: Corestore,
+    status: Arc<LinkStatus>,
+    host: String,
+    port: u16,
+    credentials: Option<Credentials>,
+    name: String,
+    mut terminator: Receiver<()>,
+) {
+    let staging = format!("{DIR_REPLROOT}/incoming-{name}");
+    let mut backoff = BACKOFF_MIN;
+    loop {
+        status.set_state(LinkState::Connecting);
+        let ret = self::replicate(
+            &handle,
+            &status,
+            (&host, port),
+            &credentials,
+            &staging,
+            &mut terminator,
+        )
+        .await;
+        if status.state() == LinkState::Streaming {
+            // we did sync, so this isn't a problem with connecting to the primary
+            backoff = BACKOFF_MIN;
+        }
+        match ret {
+            Ok(StreamEnd::Detached) => break,
+            Ok(StreamEnd::Lagged) => {
+                log::warn!("Fell behind the primary {}:{}. Syncing again", host, port)
+            }
+            Err(e) => log::error!(
+                "Replication from {}:{} failed with error: {}. Retrying in {}s",
+                host,
+                port,
+                e,
+                backoff
+            ),
+        }
+        let slept = tokio::select! {
+            _ = time::sleep(Duration::from_secs(backoff)) => true,
+            _ = terminator.recv() => false,
+        };
+        if !slept {
+            break;
+        }
+        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+    // we may have been detached in the middle of a sync
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+    log::info!("Stopped replicating from {}:{}", host, port);
+}
+
+/// Why the stream from the primary ended
+enum StreamEnd {
+    /// we fell behind and have to sync again
+    Lagged,
+    /// the link to the primary was dropped
+    Detached,
+}
+
+/// Connect to the primary (logging in, if needed) and ask it to sync with us
+async fn handshake(
+    primary: (&str, u16),
+    credentials: &Option<Credentials>,
+) -> IoResult<BufReader<TcpStream>> {
+    let mut stream = BufReader::new(TcpStream::connect(primary).await?);
+    if let Some((username, token)) = credentials {
+        let login = encode_query(&[b"AUTH", b"LOGIN", username.as_bytes(), token.as_bytes()]);
+        stream.write_all(&login).await?;
+        self::expect_okay(&mut stream, "primary", "log in").await?;
+    }
+    stream.write_all(&encode_query(&[b"SYNC"])).await?;
+    self::expect_okay(&mut stream, "primary", "sync").await?;
+    Ok(stream)
+}
+
+/// Connect to the primary, sync with it and then apply the writes that it streams,
+/// acknowledging them (see the module docs) whenever we've applied all that we received. This
+/// returns once the primary tells us that we fell behind, once the link is dropped (we only
+/// stop between writes, so that a write is never applied partially) or on an error
+async fn replicate(
+    handle: &Corestore,
+    status: &LinkStatus,
+    primary: (&str, u16),
+    credentials: &Option<Credentials>,
+    staging: &str,
+    terminator: &mut Receiver<()>,
+) -> IoResult<StreamEnd> {
+    let mut stream = tokio::select! {
+        stream = self::handshake(primary, credentials) => stream?,
+        _ = terminator.recv() => return Ok(StreamEnd::Detached),
+    };
+    status.set_state(LinkState::Syncing);
+    let mut offset = match self::receive_snapshot(handle, &mut stream, staging, terminator).await? {
+        Some(offset) => offset,
+        None => return Ok(StreamEnd::Detached),
+    };
+    status.synced();
+    log::info!("Synced with the primary {}:{}", primary.0, primary.1);
+    stream.write_all(&number_frame(FRAME_ACK, offset)).await?;
+    let mut replayer = Replayer::new(handle);
+    loop {
+        let (tag, payload) = match self::next_frame(&mut stream, terminator).await? {
+            Some(frame) => frame,
+            None => return Ok(StreamEnd::Detached),
+        };
+        match tag {
+            FRAME_WRITE => {
+                let record = aof::decode_record(&payload)
+                    .ok_or_else(|| invalid_data("bad write from the primary"))?;
+                if !replayer.apply(&record).await? {
+                    log::warn!("Skipping a write from the primary for a missing entity");
+                }
+                status.record_applied();
+                offset += 1;
+                if stream.buffer().is_empty() {
+                    stream.write_all(&number_frame(FRAME_ACK, offset)).await?;
+                }
+            }
+            FRAME_HEARTBEAT => {
+                let at = decode_number(&payload)
+                    .ok_or_else(|| invalid_data("bad heartbeat from the primary"))?;
+                status.record_heartbeat(at);
+                stream.write_all(&number_frame(FRAME_ACK, offset)).await?;
+            }
+            FRAME_LAGGED => return Ok(StreamEnd::Lagged),
+            _ => return Err(invalid_data("unexpected frame from the primary")),
+        }
+    }
+}
+
+/// Receive the snapshot from the primary into `staging` and then restore it. Returns the offset
+/// that the snapshot ends at, or `None` if the link was dropped before we received all of it
+async fn receive_snapshot(
+    handle: &Corestore,
+    stream: &mut BufReader<TcpStream>,
+    staging: &str,
+    terminator: &mut Receiver<()>,
+) -> IoResult<Option<u64>> {
+    match tokio::fs::remove_dir_all(staging).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let offset = loop {
+        let (tag, payload) = match self::next_frame(stream, terminator).await? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        match tag {
+            FRAME_FILE => {
+                let (name, contents) = self::split_file(&payload)
+                    .ok_or_else(|| invalid_data("bad file in the snapshot from the primary"))?;
+                let path = staged_path(staging, name).ok_or_else(|| {
+                    invalid_data("bad file name in the snapshot from the primary")
+                })?;
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                // the staging directory starts out empty, so we can just append every chunk
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(contents).await?;
+            }
+            FRAME_SYNCED => {
+                break decode_number(&payload)
+                    .ok_or_else(|| invalid_data("bad end of the snapshot from the primary"))?;
+            }
+            _ => return Err(invalid_data("unexpected frame from the primary")),
+        }
+    };
+    let store = handle.clone_store();
+    let root = staging.to_owned();
+    tokio::task::spawn_blocking(move || {
+        let snapshot = unflush::read_full_from(&root)
+            .map_err(|e| IoError::new(ErrorKind::Other, e.to_string()))?;
+        let skipped = store.restore_from(snapshot);
+        if skipped != 0 {
+            log::warn!(
+                "Skipped restoring {} tables from the primary that changed their model and are \
+                 in use",
+                skipped
+            );
+        }
+        fs::remove_dir_all(&root)
+    })
+    .await
+    .expect("replication thread panicked")?;
+    Ok(Some(offset))
+}
+
+/// Split the payload of a file frame into the name and contents of the file
+pub(super) fn split_file(payload: &[u8]) -> Option<(&str, &[u8])> {
+    let (len, rest) = payload.split_at(payload.len().min(8));
+    let len = u64::from_le_bytes(len.try_into().ok()?) as usize;
+    if rest.len() < len {
+        return None;
+    }
+    let (name, contents) = rest.split_at(len);
+    Some((core::str::from_utf8(name).ok()?, contents))
+}
+
+/// Wait for the next frame from the primary, unless the link is dropped first (in which case
+/// `None` is returned)
+async fn next_frame(
+    stream: &mut BufReader<TcpStream>,
+    terminator: &mut Receiver<()>,
+) -> IoResult<Option<(u8, Vec<u8>)>> {
+    tokio::select! {
+        frame = self::read_frame(stream) => frame.map(Some),
+        _ = terminator.recv() => Ok(None),
+    }
+}
+
+/// Read a frame, refusing to allocate for one that claims to be larger than [`MAX_FRAME_SIZE`]
+pub(super) async fn read_frame(stream: &mut (impl AsyncRead + Unpin)) -> IoResult<(u8, Vec<u8>)> {
+    let tag = stream.read_u8().await?;
+    let len = stream.read_u64_le().await?;
+    if len > MAX_FRAME_SIZE {
+        return Err(invalid_data("frame from the primary is too large"));
+    }
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload).await?;
+    Ok((tag, payload))
+}
+
+/// Make sure that the other node accepted the query that we just sent. `peer` is what that
+/// node is to us (like `primary`)
+pub(crate) async fn expect_okay(
+    stream: &mut BufReader<TcpStream>,
+    peer: &str,
+    what: &str,
+) -> IoResult<()> {
+    let mut response = Vec::new();
+    stream.read_until(b'\n', &mut response).await?;
+    if response == RESPONSE_OKAY {
+        Ok(())
+    } else {
+        Err(IoError::new(
+            ErrorKind::Other,
+            format!(
+                "the {peer} refused to let us {what} ({})",
+                String::from_utf8_lossy(&response).trim_end()
+            ),
+        ))
+    }
+}
@@ -0,0 +1,309 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    super::{
+        decode_ack, frame_header, heartbeat_frame,
+        lww::{self, Hlc, Stamps},
+        number_frame,
+        replica::{encode_query, read_frame, split_file, staged_path},
+        LinkState, LinkStatus, Op, Replication, ReplicationStatus, Stamp, FRAME_ACK, FRAME_FILE,
+        FRAME_HEARTBEAT, FRAME_WRITE, MAX_FRAME_SIZE,
+    },
+    crate::{
+        corestore::{
+            memstore::{Memstore, DEFAULT},
+            table::DataModel,
+            Corestore,
+        },
+        kvengine,
+        protocol::{interface::ProtocolSpec, Query, QueryLimits, Skyhash2},
+        storage::v1::sengine::SnapshotEngine,
+    },
+    std::{path::PathBuf, sync::Arc},
+    tokio::time::Duration,
+};
+
+#[test]
+fn encoded_query_is_valid_skyhash() {
+    let query = encode_query(&[b"AUTH", b"LOGIN", b"root", b"mypassword"]);
+    let (decoded, advance) = Skyhash2::decode_packet(&query, QueryLimits::default()).unwrap();
+    assert_eq!(advance, query.len());
+    let args: Vec<&[u8]> = match decoded {
+        Query::Simple(ref q) => q
+            .as_slice()
+            .iter()
+            .map(|arg| unsafe { arg.as_slice() })
+            .collect(),
+        Query::Pipelined(_) => panic!("expected a simple query"),
+    };
+    assert_eq!(args, [b"AUTH".as_ref(), b"LOGIN", b"root", b"mypassword"]);
+}
+
+fn decode_query(query: &[u8]) -> Vec<Vec<u8>> {
+    let (decoded, advance) = Skyhash2::decode_packet(query, QueryLimits::default()).unwrap();
+    assert_eq!(advance, query.len());
+    match decoded {
+        Query::Simple(ref q) => q
+            .as_slice()
+            .iter()
+            .map(|arg| unsafe { arg.as_slice() }.to_vec())
+            .collect(),
+        Query::Pipelined(_) => panic!("expected a simple query"),
+    }
+}
+
+#[test]
+fn frame_header_layout() {
+    let header = frame_header(FRAME_FILE, 258);
+    assert_eq!(header[0], FRAME_FILE);
+    assert_eq!(u64::from_le_bytes(header[1..].try_into().unwrap()), 258);
+}
+
+#[test]
+fn heartbeat_frame_layout() {
+    let frame = heartbeat_frame(1700000000123);
+    assert_eq!(frame[..9], frame_header(FRAME_HEARTBEAT, 8));
+    assert_eq!(
+        u64::from_le_bytes(frame[9..].try_into().unwrap()),
+        1700000000123
+    );
+}
+
+#[test]
+fn decode_ack_frame() {
+    assert_eq!(decode_ack(&number_frame(FRAME_ACK, 42)), Some(42));
+    assert_eq!(decode_ack(&number_frame(FRAME_HEARTBEAT, 42)), None);
+    assert_eq!(decode_ack(&number_frame(FRAME_ACK, 42)[..16]), None);
+}
+
+#[tokio::test]
+async fn wait_for_acks_from_replicas() {
+    let replication = Replication::new();
+    let timeout = Duration::from_millis(50);
+    // nobody has to acknowledge anything
+    assert_eq!(replication.wait_for_acks(1, 0, timeout).await, 0);
+    let first = replication.subscribe();
+    let second = replication.subscribe();
+    assert_eq!(replication.wait_for_acks(1, 1, timeout).await, 0);
+    first.ack(1);
+    second.ack(2);
+    assert_eq!(replication.wait_for_acks(1, 2, timeout).await, 2);
+    assert_eq!(replication.wait_for_acks(2, 2, timeout).await, 1);
+    // a replica that goes away no longer counts
+    drop(second);
+    assert_eq!(replication.wait_for_acks(1, 1, timeout).await, 1);
+    // and we wake up as soon as enough replicas have caught up
+    let waiter = tokio::spawn(async move {
+        let acked = replication
+            .wait_for_acks(3, 1, Duration::from_secs(60))
+            .await;
+        (replication, acked)
+    });
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    first.ack(3);
+    let (_, acked) = waiter.await.unwrap();
+    assert_eq!(acked, 1);
+}
+
+#[test]
+fn hlc_never_goes_backwards() {
+    let clock = Hlc::default();
+    let first = clock.tick(1000, 0);
+    assert_eq!(first, 1000 << 16);
+    // the physical clock went backwards (or didn't move)
+    assert_eq!(clock.tick(999, 0), first + 1);
+    assert_eq!(clock.tick(1000, 0), first + 2);
+    // a peer is ahead of us
+    let remote = 5000 << 16;
+    assert_eq!(clock.tick(1000, remote), remote + 1);
+    assert_eq!(clock.tick(6000, 0), 6000 << 16);
+}
+
+#[test]
+fn stamps_keep_the_last_write() {
+    let stamps = Stamps::default();
+    let stamp = |time, node| Stamp { time, node };
+    assert_eq!(stamps.get(&DEFAULT, &DEFAULT, b"key"), None);
+    assert!(stamps.advance(&DEFAULT, &DEFAULT, b"key", stamp(10, 1)));
+    assert!(!stamps.advance(&DEFAULT, &DEFAULT, b"key", stamp(9, 2)));
+    // the same time on another node is ordered by the node's ID
+    assert!(!stamps.advance(&DEFAULT, &DEFAULT, b"key", stamp(10, 0)));
+    assert!(stamps.advance(&DEFAULT, &DEFAULT, b"key", stamp(10, 2)));
+    assert_eq!(stamps.get(&DEFAULT, &DEFAULT, b"key"), Some(stamp(10, 2)));
+    assert!(stamps.advance(&DEFAULT, &DEFAULT, b"other", stamp(1, 1)));
+    assert_eq!(stamps.keys().len(), 2);
+}
+
+#[test]
+fn lww_written_keys() {
+    assert!(lww::is_lww(b"uset"));
+    assert!(!lww::is_lww(b"incr"));
+    let args: [&[u8]; 4] = [b"a", b"1", b"b", b"2"];
+    assert_eq!(lww::written_keys(b"mset", &args), [b"a", b"b"]);
+    assert_eq!(lww::written_keys(b"DEL", &args), args);
+}
+
+#[test]
+fn op_is_encoded_as_replica_apply() {
+    let mut op = Op {
+        keyspace: DEFAULT,
+        table: DEFAULT,
+        key: b"key".to_vec(),
+        value: Some(b"value".to_vec()),
+        stamp: Stamp { time: 42, node: 7 },
+    };
+    let expected: [&[u8]; 8] = [
+        b"REPLICA", b"APPLY", b"default", b"default", b"42", b"7", b"key", b"value",
+    ];
+    assert_eq!(decode_query(&op.encode()), expected);
+    op.value = None;
+    assert_eq!(decode_query(&op.encode()), expected[..7]);
+}
+
+#[tokio::test]
+async fn peers_apply_the_last_write() {
+    let handle = Corestore::default_with_store(
+        Memstore::new_default(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    let peers = handle.get_replication().peers();
+    assert!(!peers.is_enabled());
+    let op = |time, value: Option<&[u8]>| Op {
+        keyspace: DEFAULT,
+        table: DEFAULT,
+        key: b"key".to_vec(),
+        value: value.map(<[u8]>::to_vec),
+        stamp: Stamp { time, node: 1 },
+    };
+    let value = || match handle.get_ctable_ref().unwrap().get_model_ref() {
+        DataModel::KV(kve) => kve
+            .get_cloned_unchecked(b"key")
+            .map(|value| value.as_ref().to_vec()),
+        _ => panic!("expected a key/value table"),
+    };
+    assert!(peers.apply(&handle, &op(10, Some(b"new"))).await);
+    assert_eq!(value(), Some(b"new".to_vec()));
+    // an older write arrives late
+    assert!(!peers.apply(&handle, &op(5, Some(b"old"))).await);
+    assert_eq!(value(), Some(b"new".to_vec()));
+    // and a deletion that was made later wins
+    assert!(peers.apply(&handle, &op(20, None)).await);
+    assert_eq!(value(), None);
+    assert!(!peers.apply(&handle, &op(15, Some(b"older"))).await);
+    assert_eq!(value(), None);
+}
+
+#[test]
+fn lag_is_measured_from_the_last_heartbeat() {
+    let status = LinkStatus::new();
+    assert_eq!(status.lag(), None);
+    status.record_heartbeat(kvengine::now_millis() - 500);
+    assert!(matches!(status.lag(), Some(lag) if lag >= 500));
+    // a heartbeat that seems to be from the future (since the clocks are off) isn't a lag
+    status.record_heartbeat(kvengine::now_millis() + 60_000);
+    assert_eq!(status.lag(), Some(0));
+}
+
+#[test]
+fn split_file_payload() {
+    let mut payload = 7u64.to_le_bytes().to_vec();
+    payload.extend(b"PRELOADcontents");
+    assert_eq!(
+        split_file(&payload),
+        Some(("PRELOAD", b"contents".as_ref()))
+    );
+    // the name is longer than what was sent
+    assert_eq!(split_file(&payload[..12]), None);
+    assert_eq!(split_file(&payload[..4]), None);
+}
+
+#[tokio::test]
+async fn oversized_frames_are_rejected() {
+    let mut frame = frame_header(FRAME_WRITE, 3).to_vec();
+    frame.extend(b"abc");
+    let (tag, payload) = read_frame(&mut frame.as_slice()).await.unwrap();
+    assert_eq!((tag, payload.as_slice()), (FRAME_WRITE, b"abc".as_ref()));
+    // we don't try to allocate for a frame that only claims to be huge
+    let frame = frame_header(FRAME_WRITE, MAX_FRAME_SIZE as usize + 1);
+    let e = read_frame(&mut frame.as_slice()).await.unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn staged_paths_stay_in_staging() {
+    assert_eq!(
+        staged_path("data/repl/incoming-0", "default/PARTMAP"),
+        Some(PathBuf::from("data/repl/incoming-0/default/PARTMAP"))
+    );
+    for bad in [
+        "",
+        "/etc/passwd",
+        "../ks/PRELOAD",
+        "default/../../x",
+        "default//x",
+        "C:x",
+    ] {
+        assert_eq!(staged_path("data/repl/incoming-0", bad), None, "{bad}");
+    }
+}
+
+#[tokio::test]
+async fn promote_detaches_from_the_primary() {
+    let handle = Corestore::default_with_store(
+        Memstore::new_default(),
+        Arc::new(SnapshotEngine::new_disabled()),
+    );
+    let replication = handle.get_replication();
+    assert!(!replication.is_replica());
+    assert!(!replication.promote().await);
+    // nothing listens on this port, so the replica keeps retrying
+    replication
+        .replicate_from(&handle, "127.0.0.1".to_owned(), 1, None)
+        .await;
+    assert!(replication.is_replica());
+    // we haven't heard from the primary, so we can't tell how far behind we are
+    assert!(replication.lags_behind(1000));
+    assert!(!replication.lags_behind(0));
+    match replication.status() {
+        ReplicationStatus::Replica {
+            primary, link, lag, ..
+        } => {
+            assert_eq!(primary, "127.0.0.1:1");
+            assert_eq!(link, LinkState::Connecting);
+            assert_eq!(lag, None);
+        }
+        ReplicationStatus::Primary { .. } => panic!("expected a replica"),
+    }
+    assert!(replication.promote().await);
+    assert!(!replication.is_replica());
+    assert!(!replication.lags_behind(1000));
+    assert!(matches!(
+        replication.status(),
+        ReplicationStatus::Primary { replicas: 0 }
+    ));
+    assert!(!replication.promote().await);
+}
@@ -0,0 +1,381 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # The script engine
+//!
+//! Scripts are run by a [`rhai`] engine with the standard library minus the time functions, no
+//! module resolver and no `eval`, so that a script does the same thing wherever it runs. The
+//! functions that work with keys are registered for every run, on top of the
+//! [`ScriptContext`] of that run
+
+use {
+    crate::{
+        corestore::{
+            lazy::Lazy,
+            table::{DescribeTable, KVEBlob, Table},
+            SharedSlice,
+        },
+        kvengine::KVEStandard,
+        registry,
+    },
+    core::str,
+    parking_lot::Mutex,
+    rhai::{
+        module_resolvers::DummyModuleResolver,
+        packages::{
+            BasicArrayPackage, BasicBlobPackage, BasicMapPackage, BasicMathPackage,
+            BitFieldPackage, CorePackage, LogicPackage, MoreStringPackage, Package,
+        },
+        Array, Blob, Dynamic, Engine, EvalAltResult, ImmutableString, ParseError, Scope, Shared,
+        AST,
+    },
+    std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+};
+
+/// How long a script can run for
+pub const TIME_LIMIT: Duration = Duration::from_secs(5);
+/// The number of operations between two checks of the clock
+const CLOCK_CHECK_INTERVAL: u64 = 1024;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPR_DEPTH: usize = 64;
+/// The largest string, blob, array or map that a script can build
+const MAX_SIZE: usize = 64 * 1024 * 1024;
+
+/// The packages that every engine has
+static STDLIB: Lazy<Vec<Shared<rhai::Module>>, fn() -> Vec<Shared<rhai::Module>>> =
+    Lazy::new(|| {
+        vec![
+            CorePackage::new().as_shared_module(),
+            LogicPackage::new().as_shared_module(),
+            BitFieldPackage::new().as_shared_module(),
+            BasicMathPackage::new().as_shared_module(),
+            BasicArrayPackage::new().as_shared_module(),
+            BasicBlobPackage::new().as_shared_module(),
+            BasicMapPackage::new().as_shared_module(),
+            MoreStringPackage::new().as_shared_module(),
+        ]
+    });
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Create an engine with the standard library and our limits
+fn engine() -> Engine {
+    let mut engine = Engine::new_raw();
+    for package in STDLIB.iter() {
+        engine.register_global_module(package.clone());
+    }
+    // scripts can't load modules from the disk
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+    engine.set_max_string_size(MAX_SIZE);
+    engine.set_max_array_size(MAX_SIZE);
+    engine.set_max_map_size(MAX_SIZE);
+    engine
+}
+
+/// Compile a script
+pub fn compile(source: &str) -> Result<AST, ParseError> {
+    self::engine().compile(source)
+}
+
+/// The table that a script works on, and the writes that it made
+pub struct ScriptContext {
+    table: Arc<Table>,
+    /// false on a replica, which only takes writes from its primary
+    writable: bool,
+    /// the queries that redo the writes made by the script, if writes are being logged
+    effects: Option<Mutex<Vec<Vec<Vec<u8>>>>>,
+}
+
+impl ScriptContext {
+    pub fn new(table: Arc<Table>, writable: bool, logging: bool) -> Self {
+        Self {
+            table,
+            writable,
+            effects: logging.then(Mutex::default),
+        }
+    }
+    /// Returns the queries that redo the writes made by the script (in the order that they
+    /// were made). Nothing is returned if writes aren't being logged
+    pub fn take_effects(&self) -> Vec<Vec<Vec<u8>>> {
+        self.effects
+            .as_ref()
+            .map(|effects| core::mem::take(&mut *effects.lock()))
+            .unwrap_or_default()
+    }
+    fn kve(&self) -> ScriptResult<&KVEStandard> {
+        KVEBlob::try_get(&self.table).ok_or_else(|| "wrong-model".into())
+    }
+    fn get(&self, key: Dynamic) -> ScriptResult<Dynamic> {
+        match self.kve()?.get_cloned(self::to_bytes(key)?) {
+            Ok(Some(value)) => Ok(self::to_dynamic(value.as_slice())),
+            Ok(None) => Ok(Dynamic::UNIT),
+            Err(()) => Err("encoding-error".into()),
+        }
+    }
+    fn exists(&self, key: Dynamic) -> ScriptResult<bool> {
+        self.kve()?
+            .exists(self::to_bytes(key)?)
+            .map_err(|()| "encoding-error".into())
+    }
+    /// Run a write on the table, recording it as `query` (the query that redoes it) if it went
+    /// through
+    fn write(
+        &self,
+        query: &[&[u8]],
+        grows: bool,
+        write: impl FnOnce(&KVEStandard) -> Result<bool, ()>,
+    ) -> ScriptResult<bool> {
+        if !self.writable {
+            return Err("readonly-replica".into());
+        }
+        if grows && !registry::memory_okay() {
+            return Err("memory-limit-exceeded".into());
+        }
+        if !registry::state_okay() {
+            return Err("server-error".into());
+        }
+        let done = write(self.kve()?).map_err(|()| "encoding-error")?;
+        if let (true, Some(effects)) = (done, &self.effects) {
+            effects
+                .lock()
+                .push(query.iter().map(|arg| arg.to_vec()).collect());
+        }
+        Ok(done)
+    }
+    fn set(&self, key: Dynamic, value: Dynamic) -> ScriptResult<bool> {
+        let (key, value) = (self::to_bytes(key)?, self::to_bytes(value)?);
+        self.write(&[b"SET", &key, &value], true, |kve| {
+            kve.set(SharedSlice::new(&key), SharedSlice::new(&value))
+        })
+    }
+    fn update(&self, key: Dynamic, value: Dynamic) -> ScriptResult<bool> {
+        let (key, value) = (self::to_bytes(key)?, self::to_bytes(value)?);
+        self.write(&[b"UPDATE", &key, &value], true, |kve| {
+            kve.update(SharedSlice::new(&key), SharedSlice::new(&value))
+        })
+    }
+    fn uset(&self, key: Dynamic, value: Dynamic) -> ScriptResult<()> {
+        let (key, value) = (self::to_bytes(key)?, self::to_bytes(value)?);
+        self.write(&[b"USET", &key, &value], true, |kve| {
+            kve.upsert(SharedSlice::new(&key), SharedSlice::new(&value))
+                .map(|()| true)
+        })
+        .map(|_| ())
+    }
+    fn del(&self, key: Dynamic) -> ScriptResult<bool> {
+        let key = self::to_bytes(key)?;
+        self.write(&[b"DEL", &key], false, |kve| kve.remove(&key))
+    }
+}
+
+/// Returns the bytes of a key or value passed by a script. Blobs are taken as they are, and
+/// anything else (other than `()`, arrays and maps) is turned into a string
+fn to_bytes(value: Dynamic) -> ScriptResult<Vec<u8>> {
+    if value.is_blob() {
+        Ok(value.cast::<Blob>())
+    } else if value.is_unit() || value.is_array() || value.is_map() {
+        Err(format!("expected a string or a blob, got {}", value.type_name()).into())
+    } else {
+        Ok(value.to_string().into_bytes())
+    }
+}
+
+/// Hand bytes to a script as a string if they're valid UTF-8, and as a blob otherwise
+pub fn to_dynamic(bytes: &[u8]) -> Dynamic {
+    match str::from_utf8(bytes) {
+        Ok(string) => Dynamic::from(ImmutableString::from(string)),
+        Err(_) => Dynamic::from_blob(bytes.to_vec()),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// A value returned by a script, as it's written out to the client
+pub enum Value {
+    Nil,
+    String(String),
+    Binary(Vec<u8>),
+    Int(u64),
+}
+
+impl Value {
+    /// `()` is `Nil`, blobs are binary strings and non-negative integers are integers. Anything
+    /// else is turned into a string
+    fn from_dynamic(value: Dynamic) -> Self {
+        if value.is_unit() {
+            Self::Nil
+        } else if value.is_blob() {
+            Self::Binary(value.cast::<Blob>())
+        } else {
+            match value.as_int() {
+                Ok(int) if int >= 0 => Self::Int(int as u64),
+                _ => Self::String(value.to_string()),
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+/// What a script returned. An array is returned as an array of values (an array nested in it
+/// is turned into a string)
+pub enum Reply {
+    Value(Value),
+    Array(Vec<Value>),
+}
+
+impl Reply {
+    fn from_dynamic(value: Dynamic) -> Self {
+        if value.is_array() {
+            let items = value.cast::<Array>();
+            Self::Array(items.into_iter().map(Value::from_dynamic).collect())
+        } else {
+            Self::Value(Value::from_dynamic(value))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    /// the script raised an error (or called a function with bad arguments)
+    Failed(Box<EvalAltResult>),
+    /// the script ran for longer than [`TIME_LIMIT`]
+    TimedOut,
+}
+
+/// Run a compiled script with the given arguments (which it sees as `ARGS`). This blocks for
+/// up to [`TIME_LIMIT`]
+pub fn run(script: &AST, context: Arc<ScriptContext>, args: Array) -> Result<Reply, ScriptError> {
+    let mut engine = self::engine();
+    let deadline = Instant::now() + TIME_LIMIT;
+    engine.on_progress(move |ops| {
+        if ops % CLOCK_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+            Some(Dynamic::UNIT)
+        } else {
+            None
+        }
+    });
+    let ctx = context.clone();
+    engine.register_fn("get", move |key: Dynamic| ctx.get(key));
+    let ctx = context.clone();
+    engine.register_fn("exists", move |key: Dynamic| ctx.exists(key));
+    let ctx = context.clone();
+    engine.register_fn("set", move |key: Dynamic, value: Dynamic| {
+        ctx.set(key, value)
+    });
+    let ctx = context.clone();
+    engine.register_fn("update", move |key: Dynamic, value: Dynamic| {
+        ctx.update(key, value)
+    });
+    let ctx = context.clone();
+    engine.register_fn("uset", move |key: Dynamic, value: Dynamic| {
+        ctx.uset(key, value)
+    });
+    engine.register_fn("del", move |key: Dynamic| context.del(key));
+    let mut scope = Scope::new();
+    scope.push_constant("ARGS", args);
+    match engine.eval_ast_with_scope::<Dynamic>(&mut scope, script) {
+        Ok(value) => Ok(Reply::from_dynamic(value)),
+        Err(e) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => Err(ScriptError::TimedOut),
+        Err(e) => Err(ScriptError::Failed(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Reply, ScriptContext, ScriptError, Value},
+        crate::corestore::table::Table,
+        rhai::Array,
+        std::sync::Arc,
+    };
+
+    fn run(source: &str, context: &Arc<ScriptContext>, args: Array) -> Result<Reply, ScriptError> {
+        let script = super::compile(source).unwrap();
+        super::run(&script, context.clone(), args)
+    }
+
+    #[test]
+    fn writes_are_recorded() {
+        let context = Arc::new(ScriptContext::new(
+            Arc::new(Table::new_default_kve()),
+            true,
+            true,
+        ));
+        let args = vec![super::to_dynamic(b"x"), super::to_dynamic(b"1")];
+        let reply = run(
+            r#"set(ARGS[0], ARGS[1]); set(ARGS[0], "2"); del("y"); [get(ARGS[0]), 10, -1, ()]"#,
+            &context,
+            args,
+        )
+        .unwrap();
+        assert_eq!(
+            reply,
+            Reply::Array(vec![
+                Value::String("1".to_owned()),
+                Value::Int(10),
+                Value::String("-1".to_owned()),
+                Value::Nil,
+            ])
+        );
+        // only the writes that went through are recorded
+        assert_eq!(
+            context.take_effects(),
+            vec![vec![b"SET".to_vec(), b"x".to_vec(), b"1".to_vec()]]
+        );
+    }
+
+    #[test]
+    fn no_writes_on_a_replica() {
+        let context = Arc::new(ScriptContext::new(
+            Arc::new(Table::new_default_kve()),
+            false,
+            true,
+        ));
+        assert!(matches!(
+            run(r#"uset("x", "1")"#, &context, Array::new()),
+            Err(ScriptError::Failed(_))
+        ));
+        assert!(context.take_effects().is_empty());
+    }
+
+    #[test]
+    fn no_clock() {
+        let context = Arc::new(ScriptContext::new(
+            Arc::new(Table::new_default_kve()),
+            true,
+            false,
+        ));
+        assert!(matches!(
+            run("timestamp()", &context, Array::new()),
+            Err(ScriptError::Failed(_))
+        ));
+    }
+}
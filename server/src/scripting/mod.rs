@@ -0,0 +1,270 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Server-side scripting
+//!
+//! `EVAL <script> [args...]` runs a [Rhai](https://rhai.rs) script on the current table (which
+//! has to be a key/value table). The script sees the arguments as the `ARGS` array, and the
+//! value of its last expression is returned to the client. It works with keys through a few
+//! functions:
+//! - `get(key)`: returns the value of a key, or `()` if it doesn't exist
+//! - `exists(key)`: returns true if a key exists
+//! - `set(key, value)`: sets a key that doesn't exist yet (returns false if it does)
+//! - `update(key, value)`: updates a key that exists (returns false if it doesn't)
+//! - `uset(key, value)`: sets a key, whether it exists or not
+//! - `del(key)`: deletes a key (returns false if it doesn't exist)
+//!
+//! Keys and values can be strings or blobs (numbers and the like are turned into strings), and
+//! values (and arguments) are handed to scripts as strings if they're valid UTF-8 and as blobs
+//! otherwise.
+//!
+//! A script runs while holding the transaction lock exclusively, so no other query runs while
+//! it does. This is also why a script that runs for longer than
+//! [`TIME_LIMIT`](engine::TIME_LIMIT) is cut short
+//! with `script-timeout` (the writes that it made until then are kept, just like they are when
+//! a script raises an error).
+//!
+//! ## The script cache
+//!
+//! Every script that is run (or loaded with `SCRIPT LOAD <script>`) is compiled once and kept
+//! under the SHA1 of its source, so that it can be run again with `EVALSHA <sha1> [args...]`.
+//! `SCRIPT EXISTS <sha1>...` checks for scripts in the cache and `SCRIPT FLUSH` empties it.
+//! The cache is only kept in memory, so clients should fall back to `EVAL` on `no-script`.
+//!
+//! ## Replication
+//!
+//! A script isn't recorded in the append-only log (or sent to replicas) itself. The writes that
+//! it makes are, as the `SET`, `UPDATE`, `USET` and `DEL` queries that redo them, so a replica
+//! ends up with the same data even if the script would do something else there. Still, scripts
+//! have no access to the clock, the file system or other modules, and can't write on a
+//! replica.
+//!
+//! Note: scripts can't be run in cluster mode, since their keys can't be routed
+
+use {
+    self::engine::{Reply, ScriptContext, ScriptError, Value},
+    crate::{
+        corestore::table::DescribeTable,
+        dbnet::{prelude::*, BufferedSocketStream},
+        queryengine, IoResult,
+    },
+    core::{fmt, str},
+    parking_lot::RwLock,
+    rhai::AST,
+    std::{collections::HashMap, sync::Arc},
+};
+
+mod engine;
+
+const LOAD: &[u8] = b"load";
+const EXISTS: &[u8] = b"exists";
+const FLUSH: &[u8] = b"flush";
+
+#[derive(Default)]
+/// The compiled scripts, by the SHA1 of their source (in hex)
+pub struct ScriptCache {
+    scripts: RwLock<HashMap<String, Arc<AST>>>,
+}
+
+impl ScriptCache {
+    /// Compile a script (unless it's cached already) and cache it. Returns the SHA1 of the
+    /// source and the compiled script
+    fn load(&self, source: &[u8]) -> Result<(String, Arc<AST>), String> {
+        let sha: String = openssl::sha::sha1(source)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        if let Some(script) = self.get(&sha) {
+            return Ok((sha, script));
+        }
+        let source = str::from_utf8(source).map_err(|e| e.to_string())?;
+        let script = Arc::new(engine::compile(source).map_err(|e| e.to_string())?);
+        self.scripts.write().insert(sha.clone(), script.clone());
+        Ok((sha, script))
+    }
+    fn get(&self, sha: &str) -> Option<Arc<AST>> {
+        self.scripts.read().get(sha).cloned()
+    }
+    fn flush(&self) {
+        self.scripts.write().clear()
+    }
+}
+
+impl fmt::Debug for ScriptCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptCache")
+            .field("scripts", &self.scripts.read().len())
+            .finish()
+    }
+}
+
+action! {
+    /// Run an `EVAL <script> [args...]` query
+    fn eval(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len != 0)?;
+        let source = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is at least one argument
+            act.next_unchecked()
+        };
+        let script = match handle.get_scripts().load(source) {
+            Ok((_, script)) => script,
+            Err(e) => {
+                log::debug!("Failed to compile script with error: {}", e);
+                return util::err(P::RSTRING_SCRIPT_ERROR);
+            }
+        };
+        self::run(handle, con, script, act).await
+    }
+
+    /// Run an `EVALSHA <sha1> [args...]` query
+    fn evalsha(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len != 0)?;
+        let sha = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is at least one argument
+            act.next_lowercase_unchecked()
+        };
+        let script = str::from_utf8(sha.as_ref())
+            .ok()
+            .and_then(|sha| handle.get_scripts().get(sha));
+        match script {
+            Some(script) => self::run(handle, con, script, act).await,
+            None => util::err(P::RSTRING_NO_SCRIPT),
+        }
+    }
+
+    /// Run a `SCRIPT LOAD|EXISTS|FLUSH` query
+    fn script(
+        handle: &Corestore,
+        con: &mut Connection<C, P>,
+        auth: &mut AuthProviderHandle,
+        act: ActionIter<'a>,
+    ) {
+        let mut act = act;
+        ensure_length::<P>(act.len(), |len| len != 0)?;
+        let subaction = unsafe {
+            // UNSAFE(@ohsayan): We have checked that there is at least one argument
+            act.next_lowercase_unchecked()
+        };
+        let scripts = handle.get_scripts();
+        match subaction.as_ref() {
+            LOAD => {
+                ensure_length::<P>(act.len(), |len| len == 1)?;
+                let source = unsafe {
+                    // UNSAFE(@ohsayan): We have checked that there is exactly one argument
+                    act.next_unchecked()
+                };
+                match scripts.load(source) {
+                    Ok((sha, _)) => con.write_string(&sha).await?,
+                    Err(e) => {
+                        log::debug!("Failed to compile script with error: {}", e);
+                        return util::err(P::RSTRING_SCRIPT_ERROR);
+                    }
+                }
+            }
+            EXISTS => {
+                ensure_length::<P>(act.len(), |len| len != 0)?;
+                con.write_array_header(act.len()).await?;
+                for sha in act {
+                    let exists = str::from_utf8(sha)
+                        .map(|sha| scripts.get(&sha.to_ascii_lowercase()).is_some())
+                        .unwrap_or(false);
+                    con.write_int64(exists as u64).await?;
+                }
+            }
+            FLUSH => {
+                ensure_length::<P>(act.len(), |len| len == 0)?;
+                auth.provider().ensure_root_if_enabled::<P>()?;
+                scripts.flush();
+                con._write_raw(P::RCODE_OKAY).await?;
+            }
+            _ => return util::err(P::RCODE_UNKNOWN_ACTION),
+        }
+        Ok(())
+    }
+
+    /// Run a compiled script with the rest of the arguments of an `EVAL` or `EVALSHA` query
+    fn run(handle: &Corestore, con: &mut Connection<C, P>, script: Arc<AST>, act: ActionIter<'a>) {
+        if handle.get_cluster().is_enabled() {
+            return util::err(P::RSTRING_SCRIPTS_UNAVAILABLE);
+        }
+        let table = match handle.get_ctable() {
+            Some(table) => table,
+            None => return util::err(P::RSTRING_DEFAULT_UNSET),
+        };
+        if KVEBlob::try_get(&table).is_none() {
+            return util::err(P::RSTRING_WRONG_MODEL);
+        }
+        let args = act.map(engine::to_dynamic).collect();
+        let ret = {
+            // nothing else runs until the script is done and its writes are logged (and a
+            // replica or a changefeed can't start streaming halfway through, so we check after
+            // locking)
+            let _txn_lock = registry::lock_txn_exclusive().await;
+            let context = Arc::new(ScriptContext::new(
+                table,
+                !handle.get_replication().is_replica(),
+                queryengine::is_logging(handle),
+            ));
+            let ctx = context.clone();
+            let ret = tokio::task::spawn_blocking(move || engine::run(&script, ctx, args))
+                .await
+                .expect("A script caused the blocking task to panic");
+            for query in context.take_effects() {
+                queryengine::log_record(handle, query.iter().map(Vec::as_slice));
+            }
+            ret
+        };
+        // the reply is written once we've let go of the lock, so that a client that reads it
+        // slowly doesn't hold up every other query
+        match ret {
+            Ok(Reply::Value(value)) => self::write_value(con, value).await?,
+            Ok(Reply::Array(values)) => {
+                con.write_array_header(values.len()).await?;
+                for value in values {
+                    self::write_value(con, value).await?;
+                }
+            }
+            Err(ScriptError::Failed(e)) => {
+                log::debug!("Script failed with error: {}", e);
+                return util::err(P::RSTRING_SCRIPT_ERROR);
+            }
+            Err(ScriptError::TimedOut) => return util::err(P::RSTRING_SCRIPT_TIMEOUT),
+        }
+        Ok(())
+    }
+}
+
+async fn write_value<C: BufferedSocketStream, P: ProtocolSpec>(
+    con: &mut Connection<C, P>,
+    value: Value,
+) -> IoResult<()> {
+    match value {
+        Value::Nil => con._write_raw(P::RCODE_NIL).await,
+        Value::String(string) => con.write_string(&string).await,
+        Value::Binary(binary) => con.write_binary(&binary).await,
+        Value::Int(int) => con.write_int64(int).await,
+    }
+}
@@ -0,0 +1,202 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::{
+        blueql::{Entity, RawSlice},
+        config::RecoveryTarget,
+        corestore::Corestore,
+        dbnet::loopback::Loopback,
+        kvengine,
+        storage::v1::{
+            aof::{self, Record, AOF_ARCHIVE_PATH, AOF_PATH, AOF_ROTATED_PATH},
+            interface::{DIR_BACKUPS, DIR_KSROOT, DIR_SNAPROOT},
+            sengine,
+        },
+        util::os,
+        IoResult,
+    },
+    chrono::prelude::Utc,
+    std::{
+        fs,
+        io::{Error as IoError, ErrorKind},
+        path::Path,
+    },
+    tokio::{
+        sync::broadcast::Receiver,
+        time::{self, Duration},
+    },
+};
+
+/// The interval (in seconds) after which the log is synced with the `everysec` policy
+const SYNC_INTERVAL: u64 = 1;
+
+/// Runs recorded writes (from the log or from a replication stream) through the query engine,
+/// just like they were run when they were first received
+pub struct Replayer {
+    loopback: Loopback,
+}
+
+impl Replayer {
+    pub fn new(handle: &Corestore) -> Self {
+        Self {
+            loopback: Loopback::new(handle),
+        }
+    }
+    /// Run a record against the entity that the connection was using at the time. Returns
+    /// false if the record was skipped because that entity doesn't exist
+    pub async fn apply(&mut self, record: &Record) -> IoResult<bool> {
+        let entity = match (&record.keyspace, &record.table) {
+            (Some(ks), Some(tbl)) => Some(unsafe {
+                // UNSAFE(@ohsayan): The record outlives the entity
+                Entity::Full(
+                    RawSlice::new(ks.as_ptr(), ks.len()),
+                    RawSlice::new(tbl.as_ptr(), tbl.len()),
+                )
+            }),
+            (Some(ks), None) => Some(unsafe {
+                // UNSAFE(@ohsayan): The record outlives the entity
+                Entity::Current(RawSlice::new(ks.as_ptr(), ks.len()))
+            }),
+            _ => None,
+        };
+        if let Some(entity) = entity {
+            if self.loopback.swap_entity(&entity).is_err() {
+                return Ok(false);
+            }
+        }
+        let query: Vec<&[u8]> = record.args.iter().map(Vec::as_slice).collect();
+        // the response is ignored since the query had the same outcome when it was first run
+        let _ = kvengine::replay_at(record.timestamp, self.loopback.run(&query)).await?;
+        Ok(true)
+    }
+}
+
+/// Replay `records` in order, returning the number of replayed records
+pub async fn replay_records(handle: &Corestore, records: &[Record]) -> IoResult<usize> {
+    let mut replayer = Replayer::new(handle);
+    let mut replayed = 0;
+    for record in records {
+        if replayer.apply(record).await? {
+            replayed += 1;
+        } else {
+            log::warn!("Skipping a record in the append-only log for a missing entity");
+        }
+    }
+    Ok(replayed)
+}
+
+/// Replay the rotated log (if any) and then the current log, returning the number of
+/// replayed records
+pub async fn replay_log(handle: &Corestore) -> IoResult<usize> {
+    let mut records = Vec::new();
+    for path in [AOF_ROTATED_PATH, AOF_PATH] {
+        match aof::read_log(path) {
+            Ok(log) => records.extend(log),
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    self::replay_records(handle, &records).await
+}
+
+/// Get the data on disk ready for a recovery to `target`. The data is replaced with the latest
+/// snapshot that was started at or before `target`, and the writes that were recorded since
+/// that snapshot (up to `target`) are returned so that they can be replayed once the store
+/// is loaded. Everything that is replaced (or rolled back) is backed up first.
+///
+/// Note: a snapshot isn't atomic, so the writes that were made while it was being created
+/// may already be in it and are replayed again
+pub fn prepare_recovery(target: RecoveryTarget) -> IoResult<Vec<Record>> {
+    let until = target.until();
+    let snapshots = sengine::local_snapshots()?;
+    let (since, snapshot) = match snapshots
+        .iter()
+        .rev()
+        .find(|(started, _)| *started <= until)
+    {
+        Some((started, name)) => (*started, name.clone()),
+        None => {
+            return Err(IoError::new(
+                ErrorKind::NotFound,
+                "no snapshot was made before the recovery target",
+            ))
+        }
+    };
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S").to_string();
+    let backup = format!("{DIR_BACKUPS}/recovery-{timestamp}");
+    log::info!("Recovering from snapshot {snapshot}. Backing up the data to {backup}");
+    os::recursive_copy(DIR_KSROOT, format!("{backup}/ks"))?;
+    for path in [AOF_ROTATED_PATH, AOF_PATH, AOF_ARCHIVE_PATH] {
+        let dst = format!("{backup}/{}", path.trim_start_matches("data/"));
+        if Path::new(path).is_dir() {
+            os::recursive_copy(path, dst)?;
+        } else if Path::new(path).exists() {
+            fs::copy(path, dst)?;
+        }
+    }
+    // the snapshots from after the target are from the timeline that we're rolling back
+    for (_, newer) in snapshots.iter().filter(|(started, _)| *started > until) {
+        fs::create_dir_all(format!("{backup}/snaps"))?;
+        fs::rename(
+            format!("{DIR_SNAPROOT}/{newer}"),
+            format!("{backup}/snaps/{newer}"),
+        )?;
+    }
+    let records = aof::read_logs_between(since, until)?;
+    // so that the writes after the target are never replayed by a later recovery
+    aof::rewrite_archive(&records)?;
+    fs::remove_dir_all(DIR_KSROOT)?;
+    os::recursive_copy(format!("{DIR_SNAPROOT}/{snapshot}"), DIR_KSROOT)?;
+    Ok(records)
+}
+
+/// The AOF syncer syncs the log (and the streams) to the disk every second. This is only
+/// needed for the `everysec` policy
+pub async fn aof_syncer(handle: Corestore, mut terminator: Receiver<()>) {
+    let duration = Duration::from_secs(SYNC_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = time::sleep_until(time::Instant::now() + duration) => {
+                let cloned_handle = handle.clone();
+                let ret = tokio::task::spawn_blocking(move || {
+                    match cloned_handle.get_aof() {
+                        Some(aof) => aof.sync(),
+                        None => Ok(()),
+                    }
+                    .and_then(|_| cloned_handle.get_streams().sync())
+                }).await.expect("Something caused the AOF syncer to panic");
+                if let Err(e) = ret {
+                    log::error!("Failed to sync the append-only log with error: {}", e);
+                }
+            }
+            _ = terminator.recv() => {
+                break;
+            }
+        }
+    }
+    log::info!("AOF syncer has exited");
+}
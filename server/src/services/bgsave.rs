@@ -27,50 +27,54 @@
 use {
     crate::{
         config::BGSave,
-        corestore::Corestore,
+        corestore::{memstore::Memstore, Corestore},
         registry,
-        storage::{self, v1::flush::Autoflush},
+        storage::{self, v1::interface::DataDir},
         IoResult,
     },
     tokio::{
-        sync::broadcast::Receiver,
+        sync::{broadcast::Receiver, RwLockWriteGuard},
         time::{self, Duration},
     },
 };
 
+/// The interval (in seconds) after which the scheduler wakes up to check if a BGSAVE is due
+const BGSAVE_TICK: u64 = 1;
+
 /// The bgsave_scheduler calls the bgsave task in `Corestore` after `every` seconds
 ///
-/// The time after which the scheduler will wake up the BGSAVE task is determined by
-/// `bgsave_cfg` which is to be passed as an argument. If BGSAVE is disabled, this function
-/// immediately returns
-pub async fn bgsave_scheduler(handle: Corestore, bgsave_cfg: BGSave, mut terminator: Receiver<()>) {
-    match bgsave_cfg {
-        BGSave::Enabled(duration) => {
-            // If we're here - the user doesn't trust his power supply or just values
-            // his data - which is good! So we'll turn this into a `Duration`
-            let duration = Duration::from_secs(duration);
-            loop {
-                tokio::select! {
-                    // Sleep until `duration` from the current time instant
-                    _ = time::sleep_until(time::Instant::now() + duration) => {
-                        let cloned_handle = handle.clone();
-                        // we spawn this process just to ensure that it doesn't block the runtime's workers
-                        // dedicated to async tasks (non-blocking)
-                        tokio::task::spawn_blocking(move || {
-                            let owned_handle = cloned_handle;
-                            let _ = bgsave_blocking_section(owned_handle);
-                        }).await.expect("Something caused the background service to panic");
-                    }
-                    // Otherwise wait for a notification
-                    _ = terminator.recv() => {
-                        // we got a notification to quit; so break out
-                        break;
+/// The interval is read from the runtime configuration on every tick, so it can be changed
+/// (or BGSAVE can be turned on and off) with a config reload. Writes only wait for the store
+/// to be copied (see [`run_bgsave`]); the copy is written out while they carry on
+pub async fn bgsave_scheduler(handle: Corestore, mut terminator: Receiver<()>) {
+    let tick = Duration::from_secs(BGSAVE_TICK);
+    let mut last_save = time::Instant::now();
+    loop {
+        tokio::select! {
+            _ = time::sleep_until(time::Instant::now() + tick) => {
+                match handle.get_runtime_config().load().bgsave {
+                    BGSave::Enabled(every) if last_save.elapsed() >= Duration::from_secs(every) => {}
+                    BGSave::Enabled(_) => continue,
+                    BGSave::Disabled => {
+                        // count from the time it's turned back on
+                        last_save = time::Instant::now();
+                        continue;
                     }
                 }
+                let cloned_handle = handle.clone();
+                // we spawn this process just to ensure that it doesn't block the runtime's workers
+                // dedicated to async tasks (non-blocking)
+                tokio::task::spawn_blocking(move || {
+                    let owned_handle = cloned_handle;
+                    let _ = bgsave_blocking_section(owned_handle);
+                }).await.expect("Something caused the background service to panic");
+                last_save = time::Instant::now();
+            }
+            // Otherwise wait for a notification
+            _ = terminator.recv() => {
+                // we got a notification to quit; so break out
+                break;
             }
-        }
-        BGSave::Disabled => {
-            // the user doesn't bother about his data; cool, let's not bother about it either
         }
     }
     log::info!("BGSAVE service has exited");
@@ -78,14 +82,66 @@ pub async fn bgsave_scheduler(handle: Corestore, bgsave_cfg: BGSave, mut termina
 
 /// Run bgsave
 ///
-/// This function just hides away the BGSAVE blocking section from the _public API_
+/// This function just hides away the BGSAVE blocking section from the _public API_. It
+/// blocks, so it has to be run on a thread that doesn't run async tasks
 pub fn run_bgsave(handle: &Corestore) -> IoResult<()> {
-    storage::v1::flush::flush_full(Autoflush, handle.get_store())
+    self::save_in(&DataDir::current(), handle)
+}
+
+/// Save the store to the data directory `dir` (see [`run_bgsave`])
+pub(crate) fn save_in(dir: &DataDir, handle: &Corestore) -> IoResult<()> {
+    self::save_locked(dir, handle, registry::lock_txn_exclusive_blocking())
+}
+
+/// Replace the data in the store with the data in `snapshot` (see [`Memstore::restore_from`])
+/// and save the store to the data directory `dir`, returning the number of tables that
+/// couldn't be restored. `txn_lock` (the transaction lock, held exclusively) is held till
+/// the restored store has been copied for the save, so no query ever sees a half-restored
+/// store. The save rotates the append-only log, so the writes that were logged before the
+/// restore are never replayed on top of it
+pub(crate) fn restore_in(
+    dir: &DataDir,
+    handle: &Corestore,
+    snapshot: Memstore,
+    txn_lock: RwLockWriteGuard<'static, ()>,
+) -> IoResult<usize> {
+    let skipped = handle.get_store().restore_from(snapshot);
+    self::save_locked(dir, handle, txn_lock).map(|_| skipped)
+}
+
+/// Same as [`save_in`], but for a caller that already holds the transaction lock exclusively
+fn save_locked(
+    dir: &DataDir,
+    handle: &Corestore,
+    txn_lock: RwLockWriteGuard<'static, ()>,
+) -> IoResult<()> {
+    let (frozen, _flush_lock) = {
+        // no write can run while we hold this, so the copy has every write in the rotated log
+        // and none of the writes that go into the fresh one (replaying a write that's already
+        // in the copy would apply it twice). We only hold it for as long as it takes to copy
+        // the store: the copy is written out after writers are let back in
+        let _txn_lock = txn_lock;
+        // DDL queries wait on this (while holding the transaction lock shared, which is why
+        // this is taken second) so that no table is created or dropped till the copy is out
+        let flush_lock = registry::lock_flush_state();
+        if let Some(aof) = handle.get_aof() {
+            aof.rotate()?;
+        }
+        (handle.get_store().freeze_for_save(), flush_lock)
+    };
+    let flushed = storage::v1::flush::flush_full(dir.clone(), &frozen);
+    // whatever happened, the tables need to know what's on disk now
+    handle.get_store().merge_saved(&frozen);
+    flushed?;
+    if let Some(aof) = handle.get_aof() {
+        aof.remove_rotated()?;
+    }
+    handle.get_stats().record_save();
+    Ok(())
 }
 
-/// This just wraps around [`_bgsave_blocking_section`] and prints nice log messages depending on the outcome
-fn bgsave_blocking_section(handle: Corestore) -> bool {
-    registry::lock_flush_state();
+/// This just wraps around [`run_bgsave`] and prints nice log messages depending on the outcome
+pub fn bgsave_blocking_section(handle: Corestore) -> bool {
     match run_bgsave(&handle) {
         Ok(_) => {
             log::info!("BGSAVE completed successfully");
@@ -0,0 +1,151 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::{
+        config::{EvictionConfig, EvictionPolicy},
+        corestore::Corestore,
+        kvengine::now_millis,
+        registry,
+    },
+    tokio::{
+        sync::broadcast::Receiver,
+        time::{self, Duration},
+    },
+};
+
+/// The interval (in seconds) after which the eviction service checks the memory usage
+const EVICTION_INTERVAL: u64 = 1;
+/// The number of keys sampled per eviction
+const EVICTION_SAMPLES: usize = 16;
+/// The number of entries sampled per table to estimate the memory usage
+const ESTIMATE_SAMPLES: usize = 256;
+/// The maximum number of keys evicted in a single run. If we're still over the limit once
+/// this is used up, writes are rejected till a later run catches up
+const EVICTION_BUDGET: usize = 1024;
+
+/// A tiny xorshift generator; the samples don't need to be any more random than this
+fn next_seed(state: &mut u64) -> u64 {
+    let mut x = *state;
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    *state = x;
+    x
+}
+
+/// Check the memory usage and evict keys (if the policy allows it) until we are within the
+/// limit. Returns the number of evicted keys.
+///
+/// The usage is estimated from a random sample of every table and atmost [`EVICTION_BUDGET`]
+/// keys are evicted, so the time that a run takes doesn't depend on the size of the dataset
+pub fn run_eviction(handle: &Corestore, maxmemory: usize, policy: EvictionPolicy) -> usize {
+    let tables = handle.get_store().list_all_tables();
+    let mut seed = now_millis() | 1;
+    let usage: usize = tables
+        .iter()
+        .map(|tbl| tbl.estimate_memory_usage(ESTIMATE_SAMPLES, next_seed(&mut seed)))
+        .sum();
+    let tripswitch = registry::get_memory_tripswitch();
+    if usage <= maxmemory {
+        if tripswitch.check_and_untrip() {
+            log::info!("Memory usage is back within the limit. Accepting writes again");
+        }
+        return 0;
+    }
+    if policy == EvictionPolicy::NoEviction {
+        if !tripswitch.is_tripped() {
+            log::warn!(
+                "Memory usage ({} bytes) exceeds the limit ({} bytes). Rejecting writes",
+                usage,
+                maxmemory
+            );
+            tripswitch.trip();
+        }
+        return 0;
+    }
+    let mut excess = usage - maxmemory;
+    let mut evicted = 0;
+    let mut idle_rounds = 0;
+    // go round-robin across the tables so that a single table doesn't bear the brunt
+    while excess != 0 && idle_rounds < tables.len() && evicted < EVICTION_BUDGET {
+        for tbl in tables.iter() {
+            let freed = tbl.evict_one(policy, EVICTION_SAMPLES, next_seed(&mut seed));
+            if freed == 0 {
+                idle_rounds += 1;
+            } else {
+                idle_rounds = 0;
+                evicted += 1;
+                excess = excess.saturating_sub(freed);
+            }
+            if excess == 0 || evicted == EVICTION_BUDGET {
+                break;
+            }
+        }
+    }
+    if excess == 0 {
+        if tripswitch.check_and_untrip() {
+            log::info!("Memory usage is back within the limit. Accepting writes again");
+        }
+    } else if evicted == EVICTION_BUDGET {
+        if !tripswitch.is_tripped() {
+            log::warn!("Memory limit exceeded faster than keys can be evicted. Rejecting writes");
+            tripswitch.trip();
+        }
+    } else if !tripswitch.is_tripped() {
+        log::warn!("Memory limit exceeded and there is nothing left to evict. Rejecting writes");
+        tripswitch.trip();
+    }
+    evicted
+}
+
+/// The eviction service periodically checks the approximate memory usage and evicts keys
+/// using the configured policy when the memory limit is exceeded
+pub async fn eviction_service(
+    handle: Corestore,
+    EvictionConfig { maxmemory, policy }: EvictionConfig,
+    mut terminator: Receiver<()>,
+) {
+    let duration = Duration::from_secs(EVICTION_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = time::sleep_until(time::Instant::now() + duration) => {
+                let cloned_handle = handle.clone();
+                // this locks the shards of every table, so keep it off the async workers
+                let evicted = tokio::task::spawn_blocking(move || {
+                    run_eviction(&cloned_handle, maxmemory, policy)
+                }).await.expect("Something caused the eviction service to panic");
+                if evicted != 0 {
+                    log::trace!("Eviction service evicted {} keys", evicted);
+                }
+            }
+            _ = terminator.recv() => {
+                break;
+            }
+        }
+    }
+    log::info!("Eviction service has exited");
+}
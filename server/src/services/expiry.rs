@@ -0,0 +1,62 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::corestore::Corestore,
+    tokio::{
+        sync::broadcast::Receiver,
+        time::{self, Duration},
+    },
+};
+
+/// The interval (in seconds) after which the sweeper wakes up to purge expired keys
+const SWEEP_INTERVAL: u64 = 1;
+
+/// The expiry sweeper periodically purges keys whose TTL has elapsed
+///
+/// Expired keys are also lazily purged when they are accessed, so the sweeper only makes
+/// sure that keys which are never accessed again don't hog memory (or end up on disk)
+pub async fn expiry_sweeper(handle: Corestore, mut terminator: Receiver<()>) {
+    let duration = Duration::from_secs(SWEEP_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = time::sleep_until(time::Instant::now() + duration) => {
+                let cloned_handle = handle.clone();
+                // sweeping can touch a lot of keys, so keep it off the async workers
+                let purged = tokio::task::spawn_blocking(move || {
+                    cloned_handle.get_store().sweep_expired()
+                }).await.expect("Something caused the expiry sweeper to panic");
+                if purged != 0 {
+                    log::trace!("Expiry sweeper purged {} keys", purged);
+                }
+            }
+            _ = terminator.recv() => {
+                break;
+            }
+        }
+    }
+    log::info!("Expiry sweeper has exited");
+}
@@ -0,0 +1,274 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Background jobs
+//!
+//! Work that is too slow to be done while a client waits for it (like deleting every key in a
+//! namespace or moving a slot to another node) is run as a job. Starting a job returns its ID,
+//! which can be passed to `SYS JOB` to see how far along it is. `SYS JOBS LIST` lists the jobs
+//! and `SYS JOBS KILL` asks a job to stop. A job only stops at the points where it checks
+//! [`Job::ensure_alive`] (like in between batches), so the work that it already did stays done.
+//! The last [`MAX_FINISHED`] finished jobs are kept around so that their outcome can still be
+//! looked up
+
+use {
+    crate::IoResult,
+    parking_lot::RwLock,
+    std::{
+        collections::BTreeMap,
+        io::{Error as IoError, ErrorKind},
+        sync::{
+            atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+            Arc,
+        },
+    },
+};
+
+/// The number of finished jobs that we remember
+pub const MAX_FINISHED: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What a job does
+pub enum JobKind {
+    /// deleting the keys that match a `DELPREFIX`
+    DelPrefix,
+    /// moving a slot to another node
+    Migration,
+    /// creating a snapshot
+    Snapshot,
+}
+
+impl JobKind {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::DelPrefix => "delprefix",
+            Self::Migration => "migration",
+            Self::Snapshot => "snapshot",
+        }
+    }
+    /// Returns true if jobs of this kind stop when they're killed (a snapshot is written out
+    /// in one go, so it can't be stopped halfway)
+    pub const fn is_killable(&self) -> bool {
+        !matches!(self, Self::Snapshot)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The state of a job
+pub enum JobState {
+    Running = 0,
+    Done = 1,
+    Failed = 2,
+    Killed = 3,
+}
+
+impl JobState {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Killed => "killed",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// Why a job couldn't be killed
+pub enum KillError {
+    /// there's no such job or it has already finished
+    NotRunning,
+    /// the job can't be stopped halfway
+    NotKillable,
+}
+
+#[derive(Debug)]
+/// A job and its progress. The progress is counted in the units of the job's work (keys, for
+/// the jobs that work with keys)
+pub struct Job {
+    kind: JobKind,
+    state: AtomicU8,
+    /// set when the job is asked to stop
+    killed: AtomicBool,
+    /// the number of units of work that the job found so far
+    total: AtomicU64,
+    /// the number of units of work that the job did
+    done: AtomicU64,
+}
+
+impl Job {
+    fn new(kind: JobKind) -> Self {
+        Self {
+            kind,
+            state: AtomicU8::new(JobState::Running as u8),
+            killed: AtomicBool::new(false),
+            total: AtomicU64::new(0),
+            done: AtomicU64::new(0),
+        }
+    }
+    pub fn kind(&self) -> JobKind {
+        self.kind
+    }
+    pub fn state(&self) -> JobState {
+        match self.state.load(Ordering::Acquire) {
+            0 => JobState::Running,
+            1 => JobState::Done,
+            2 => JobState::Failed,
+            _ => JobState::Killed,
+        }
+    }
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+    pub fn done(&self) -> u64 {
+        self.done.load(Ordering::Relaxed)
+    }
+    /// Record that `units` more units of work were found
+    pub fn add_total(&self, units: u64) {
+        self.total.fetch_add(units, Ordering::Relaxed);
+    }
+    /// Record that `units` more units of work were done
+    pub fn add_done(&self, units: u64) {
+        self.done.fetch_add(units, Ordering::Relaxed);
+    }
+    /// Returns true if the job was asked to stop
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Acquire)
+    }
+    /// Returns an error if the job was asked to stop, so that the job can bail out with `?`
+    pub fn ensure_alive(&self) -> IoResult<()> {
+        if self.is_killed() {
+            Err(IoError::new(ErrorKind::Interrupted, "the job was killed"))
+        } else {
+            Ok(())
+        }
+    }
+    /// Mark the job as finished. If it didn't succeed, it's marked as killed if it was asked to
+    /// stop (or else as failed)
+    pub fn finish(&self, succeeded: bool) {
+        let state = if succeeded {
+            JobState::Done
+        } else if self.is_killed() {
+            JobState::Killed
+        } else {
+            JobState::Failed
+        };
+        self.state.store(state as u8, Ordering::Release)
+    }
+}
+
+#[derive(Debug, Default)]
+/// The jobs that are running and the ones that finished recently, by their ID
+pub struct Jobs {
+    last_id: AtomicU64,
+    jobs: RwLock<BTreeMap<u64, Arc<Job>>>,
+}
+
+impl Jobs {
+    /// Register a new job, returning its ID (IDs start at 1) and the job itself
+    pub fn start(&self, kind: JobKind) -> (u64, Arc<Job>) {
+        let id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let job = Arc::new(Job::new(kind));
+        let mut jobs = self.jobs.write();
+        let finished: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, job)| job.state() != JobState::Running)
+            .map(|(id, _)| *id)
+            .collect();
+        // the IDs are in order, so these are the oldest ones
+        for id in &finished[..finished.len().saturating_sub(MAX_FINISHED)] {
+            jobs.remove(id);
+        }
+        jobs.insert(id, job.clone());
+        (id, job)
+    }
+    /// Returns a job (if we know about it)
+    pub fn get(&self, id: u64) -> Option<Arc<Job>> {
+        self.jobs.read().get(&id).cloned()
+    }
+    /// Returns every job that we know about, oldest first
+    pub fn list(&self) -> Vec<(u64, Arc<Job>)> {
+        self.jobs
+            .read()
+            .iter()
+            .map(|(id, job)| (*id, job.clone()))
+            .collect()
+    }
+    /// Ask a running job to stop
+    pub fn kill(&self, id: u64) -> Result<(), KillError> {
+        match self.get(id) {
+            Some(job) if job.state() == JobState::Running => {
+                if job.kind().is_killable() {
+                    job.killed.store(true, Ordering::Release);
+                    Ok(())
+                } else {
+                    Err(KillError::NotKillable)
+                }
+            }
+            _ => Err(KillError::NotRunning),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JobKind, JobState, Jobs, KillError, MAX_FINISHED};
+
+    #[test]
+    fn jobs_forget_old_finished_jobs() {
+        let jobs = Jobs::default();
+        let (running, _) = jobs.start(JobKind::DelPrefix);
+        for _ in 0..MAX_FINISHED + 1 {
+            let (_, job) = jobs.start(JobKind::DelPrefix);
+            job.finish(true);
+        }
+        // the oldest finished job is forgotten when the next job starts
+        let (last, _) = jobs.start(JobKind::DelPrefix);
+        assert_eq!(jobs.get(running).unwrap().state(), JobState::Running);
+        assert!(jobs.get(running + 1).is_none());
+        assert_eq!(jobs.get(running + 2).unwrap().state(), JobState::Done);
+        assert_eq!(jobs.get(last).unwrap().state(), JobState::Running);
+        assert!(jobs.get(last + 1).is_none());
+        assert_eq!(jobs.list().len(), MAX_FINISHED + 2);
+    }
+
+    #[test]
+    fn kill_jobs() {
+        let jobs = Jobs::default();
+        let (id, job) = jobs.start(JobKind::DelPrefix);
+        assert!(job.ensure_alive().is_ok());
+        assert_eq!(jobs.kill(id), Ok(()));
+        assert!(job.ensure_alive().is_err());
+        job.finish(false);
+        assert_eq!(job.state(), JobState::Killed);
+        assert_eq!(jobs.kill(id), Err(KillError::NotRunning));
+        assert_eq!(jobs.kill(id + 1), Err(KillError::NotRunning));
+        let (id, job) = jobs.start(JobKind::Snapshot);
+        assert_eq!(jobs.kill(id), Err(KillError::NotKillable));
+        job.finish(false);
+        assert_eq!(job.state(), JobState::Failed);
+    }
+}
@@ -24,10 +24,21 @@
  *
 */
 
+pub mod aof;
 pub mod bgsave;
+pub mod eviction;
+pub mod expiry;
+pub mod jobs;
+pub mod quota;
+pub mod reaper;
+pub mod reload;
 pub mod snapshot;
 use crate::{
-    corestore::memstore::Memstore, diskstore::flock::FileLock, storage, util::os, IoResult,
+    corestore::memstore::Memstore,
+    diskstore::flock::FileLock,
+    storage::{self, v1::interface::DataDir},
+    util::os,
+    IoResult,
 };
 
 pub fn restore_data(src: Option<String>) -> IoResult<()> {
@@ -46,7 +57,7 @@ pub fn pre_shutdown_cleanup(mut pid_file: FileLock, mr: Option<&Memstore>) -> bo
     }
     if let Some(mr) = mr {
         log::info!("Compacting tree");
-        if let Err(e) = storage::v1::interface::cleanup_tree(mr) {
+        if let Err(e) = storage::v1::interface::cleanup_tree(&DataDir::current(), mr) {
             log::error!("Failed to compact tree: {}", e);
             return false;
         }
@@ -0,0 +1,63 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::{auth::Authmap, corestore::Corestore},
+    tokio::{
+        sync::broadcast::Receiver,
+        time::{self, Duration},
+    },
+};
+
+/// The interval (in seconds) after which the quota service measures the usage
+const QUOTA_INTERVAL: u64 = 1;
+
+/// The quota service periodically measures the usage of the keyspaces and the users that
+/// have a quota (see [`Quotas`](crate::queryengine::quota::Quotas))
+pub async fn quota_service(handle: Corestore, authmap: Authmap, mut terminator: Receiver<()>) {
+    let duration = Duration::from_secs(QUOTA_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = time::sleep_until(time::Instant::now() + duration) => {
+                if handle.get_quotas().is_empty() {
+                    continue;
+                }
+                let cloned_handle = handle.clone();
+                let authmap = authmap.clone();
+                // measuring walks every table, so keep it off the async workers
+                tokio::task::spawn_blocking(move || {
+                    cloned_handle
+                        .get_quotas()
+                        .measure(cloned_handle.get_store(), &authmap)
+                }).await.expect("Something caused the quota service to panic");
+            }
+            _ = terminator.recv() => {
+                break;
+            }
+        }
+    }
+    log::info!("Quota service has exited");
+}
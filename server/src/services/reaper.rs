@@ -0,0 +1,58 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::dbnet::governor::Governor,
+    std::sync::Arc,
+    tokio::{
+        sync::broadcast::Receiver,
+        time::{self, Duration},
+    },
+};
+
+/// The interval (in seconds) after which the reaper wakes up to look for idle clients
+const REAP_INTERVAL: u64 = 1;
+
+/// The idle reaper periodically disconnects clients that have been idle for longer than the
+/// configured idle timeout. The reaper always runs since the timeout can be enabled with a
+/// config reload
+pub async fn idle_reaper(governor: Arc<Governor>, mut terminator: Receiver<()>) {
+    let duration = Duration::from_secs(REAP_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = time::sleep_until(time::Instant::now() + duration) => {
+                let reaped = governor.reap_idle();
+                if reaped != 0 {
+                    log::trace!("Idle reaper disconnected {} clients", reaped);
+                }
+            }
+            _ = terminator.recv() => {
+                break;
+            }
+        }
+    }
+    log::info!("Idle reaper has exited");
+}
@@ -0,0 +1,84 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::{
+        config::{self, ConfigError, RuntimeConfig},
+        corestore::Corestore,
+        logging,
+    },
+    std::sync::Arc,
+};
+
+/// Re-read the configuration (from the same file, environment and CLI args that the server
+/// was started with) and apply the settings that can be changed at runtime: the log levels,
+/// the BGSAVE interval, the client limit, the connection timeouts, the rate limits, the IP
+/// rules and the replica lag limit. Everything else needs a restart. If the configuration is
+/// invalid, nothing is changed
+pub fn reload_config(handle: &Corestore) -> Result<(), ConfigError> {
+    let cfg = config::get_config()?;
+    cfg.print_warnings();
+    let (cfg, _) = cfg.finish();
+    let runtime = RuntimeConfig::from_config(&cfg);
+    runtime
+        .levels
+        .iter()
+        .for_each(|(module, level)| logging::set_level(*module, *level));
+    handle.get_runtime_config().store(Arc::new(runtime));
+    log::info!("Reloaded the configuration");
+    Ok(())
+}
+
+/// The reload service reloads the configuration (see [`reload_config`]) whenever the server
+/// receives a `SIGHUP`
+#[cfg(unix)]
+pub async fn reload_service(
+    handle: Corestore,
+    mut terminator: tokio::sync::broadcast::Receiver<()>,
+) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            log::error!("Failed to bind to SIGHUP with error: {e}");
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            _ = hangup.recv() => {
+                log::info!("Received SIGHUP. Reloading the configuration");
+                if let Err(e) = reload_config(&handle) {
+                    log::error!("Failed to reload the configuration: {e}");
+                }
+            }
+            _ = terminator.recv() => {
+                break;
+            }
+        }
+    }
+    log::info!("Reload service has exited");
+}
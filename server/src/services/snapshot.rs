@@ -29,6 +29,7 @@ use {
         config::SnapshotConfig,
         corestore::Corestore,
         registry,
+        services::jobs::JobKind,
         storage::v1::sengine::{SnapshotActionResult, SnapshotEngine},
     },
     std::sync::Arc,
@@ -62,7 +63,9 @@ pub async fn snapshot_service(
             loop {
                 tokio::select! {
                     _ = time::sleep_until(time::Instant::now() + duration) => {
+                        let (_, job) = handle.get_jobs().start(JobKind::Snapshot);
                         let succeeded = engine.mksnap(handle.clone_store()).await == SnapshotActionResult::Ok;
+                        job.finish(succeeded);
                         #[cfg(test)]
                         {
                             use std::env::set_var;
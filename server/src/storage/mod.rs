@@ -47,7 +47,7 @@ the functions here
     Now, create the new structures using the old ones and then finally return them
 
 Here's some rust-flavored pseudocode:
-```
+```text
 let version = find_version(preload_file_contents)?;
 match version {
     V1 => {
@@ -63,13 +63,19 @@ match version {
 The migration module, which doesn't exist, yet will always have a way to transform older structures into
 the current one. This can be achieved with some trait/generic hackery (although it might be pretty simple
 in practice).
+
+Changes to how a single file is encoded don't need any of this: every file carries a format version in its
+header, and `v1::migrate` upgrades older files (both when they're loaded and in place, on startup).
 */
 
 pub mod v1;
 
 pub mod unflush {
-    use crate::{corestore::memstore::Memstore, storage::v1::error::StorageEngineResult};
-    pub fn read_full() -> StorageEngineResult<Memstore> {
-        super::v1::unflush::read_full()
+    use crate::{
+        corestore::memstore::Memstore,
+        storage::v1::{error::StorageEngineResult, interface::DataDir},
+    };
+    pub fn read_full(dir: &DataDir) -> StorageEngineResult<Memstore> {
+        super::v1::unflush::read_full(dir)
     }
 }
@@ -0,0 +1,602 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Append-only log
+//!
+//! The append-only log (AOF) records every write that was run against the database so that
+//! the writes made since the last flush can be replayed on startup. Each record looks like:
+//! ```text
+//! [timestamp: u64][keyspace len: u8][keyspace][table len: u8][table][argc: u64]([arglen: u64][arg])*
+//! ```
+//! The timestamp is the time (in milliseconds since the epoch) at which the write was made. It
+//! is also the time that the TTLs set by the write (like with `EXPIRE`, `SETEX` and `RESTORE`)
+//! are relative to when it is replayed, so that a key doesn't outlive its TTL across restarts.
+//! A zero length keyspace or table indicates that the connection didn't have one set. All
+//! sizes are stored in native endian (like everything else in this storage engine).
+//!
+//! Whenever a BGSAVE starts, the log is _rotated_: the current log is moved aside and a fresh
+//! one is started. Once the flush succeeds, the rotated log is no longer needed and is removed.
+//! If local snapshots are enabled, it is moved into the _archive_ instead (named after the
+//! time at which it was archived) so that the writes made after a snapshot can be replayed on
+//! top of it to recover the data as it was at some point in time. Segments that are older than
+//! the oldest snapshot are of no use for this, and are pruned once a snapshot is created
+//!
+//! If files are [encrypted](super::crypt), every record is encrypted on its own and written as
+//! a _frame_ instead:
+//! ```text
+//! [marker: u64::MAX][len: u64][encrypted record]
+//! ```
+//! No record has that timestamp, so a log can have both plain records (written before
+//! encryption was turned on) and frames.
+//!
+//! Note: records are written once a query has run, so racing writes to the same key from
+//! different connections may be replayed in a different order
+
+use {
+    crate::{
+        config::FsyncPolicy,
+        corestore::memstore::ObjectID,
+        kvengine::now_millis,
+        queryengine::{self, commands::ActionFlags},
+        storage::v1::{
+            crypt::{self, OpenError},
+            interface::DataDir,
+        },
+        IoResult,
+    },
+    core::mem,
+    parking_lot::Mutex,
+    std::{
+        borrow::Cow,
+        fs::{self, File, OpenOptions},
+        io::{Error as IoError, ErrorKind, Write},
+        path::Path,
+    },
+};
+
+/// The path to the append-only log
+pub const AOF_PATH: &str = "data/aof";
+/// The path to the rotated append-only log
+pub const AOF_ROTATED_PATH: &str = "data/aof.old";
+/// The directory in which older logs are archived
+pub const AOF_ARCHIVE_PATH: &str = "data/aof-archive";
+
+/// Takes the place of the timestamp in an encrypted record
+const FRAME_MARKER: u64 = u64::MAX;
+
+/// The BlueQL statements (DDL) that are recorded in the log
+const LOGGED_STATEMENTS: [&[u8]; 2] = [b"CREATE", b"DROP"];
+
+/// Returns true if a query starting with `action` needs to be recorded in the log (see
+/// [`ActionFlags::LOGGED`])
+pub fn is_logged(action: &[u8]) -> bool {
+    queryengine::action_flags(action).contains(ActionFlags::LOGGED) || is_ddl(action)
+}
+
+/// Returns true if a query starting with `action` is a DDL statement
+pub fn is_ddl(action: &[u8]) -> bool {
+    LOGGED_STATEMENTS.iter().any(|stmt| {
+        // a statement is followed by whitespace, so we just look at the first word
+        action.len() > stmt.len()
+            && action[..stmt.len()].eq_ignore_ascii_case(stmt)
+            && action[stmt.len()].is_ascii_whitespace()
+    })
+}
+
+/// A single record in the log
+#[derive(Debug, PartialEq, Eq)]
+pub struct Record {
+    /// the time (in milliseconds since the epoch) at which the write was recorded
+    pub timestamp: u64,
+    /// the keyspace the connection was using
+    pub keyspace: Option<ObjectID>,
+    /// the table the connection was using
+    pub table: Option<ObjectID>,
+    /// the query
+    pub args: Vec<Vec<u8>>,
+}
+
+impl Record {
+    /// Encode this record into `buf`
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        encode_record(
+            buf,
+            self.timestamp,
+            self.keyspace.as_ref(),
+            self.table.as_ref(),
+            self.args.iter().map(|arg| arg.as_slice()),
+        )
+    }
+}
+
+/// Encode a record into `buf`
+pub fn encode_record<'a>(
+    buf: &mut Vec<u8>,
+    timestamp: u64,
+    keyspace: Option<&ObjectID>,
+    table: Option<&ObjectID>,
+    args: impl ExactSizeIterator<Item = &'a [u8]>,
+) {
+    buf.extend_from_slice(&timestamp.to_ne_bytes());
+    for id in [keyspace, table] {
+        match id {
+            Some(id) => {
+                buf.push(id.len() as u8);
+                buf.extend_from_slice(id);
+            }
+            None => buf.push(0),
+        }
+    }
+    buf.extend_from_slice(&(args.len() as u64).to_ne_bytes());
+    for arg in args {
+        buf.extend_from_slice(&(arg.len() as u64).to_ne_bytes());
+        buf.extend_from_slice(arg);
+    }
+}
+
+/// Returns the encoded `record` as it should be written to a log: as a frame if files are
+/// encrypted, and as it is otherwise
+pub fn seal_record(record: &[u8]) -> IoResult<Cow<'_, [u8]>> {
+    match crypt::seal(record)? {
+        Some(sealed) => Ok(Cow::Owned(self::frame(&sealed))),
+        None => Ok(Cow::Borrowed(record)),
+    }
+}
+
+/// Put an encrypted record into a frame
+fn frame(sealed: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(sealed.len() + 2 * mem::size_of::<u64>());
+    frame.extend_from_slice(&FRAME_MARKER.to_ne_bytes());
+    frame.extend_from_slice(&(sealed.len() as u64).to_ne_bytes());
+    frame.extend_from_slice(sealed);
+    frame
+}
+
+/// A cursor over the raw log
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            None
+        } else {
+            let (ret, rem) = self.data.split_at(len);
+            self.data = rem;
+            Some(ret)
+        }
+    }
+    fn take_u64(&mut self) -> Option<u64> {
+        let mut bytes = [0u8; mem::size_of::<u64>()];
+        bytes.copy_from_slice(self.take(mem::size_of::<u64>())?);
+        Some(u64::from_ne_bytes(bytes))
+    }
+    fn take_id(&mut self) -> Option<Option<ObjectID>> {
+        let len = self.take(1)?[0] as usize;
+        if len > 64 {
+            return None;
+        }
+        let id = self.take(len)?;
+        if id.is_empty() {
+            Some(None)
+        } else {
+            Some(Some(unsafe {
+                // UNSAFE(@ohsayan): We just checked the length
+                ObjectID::from_slice(id)
+            }))
+        }
+    }
+    /// Take a frame (see [`seal_record`]) if that's what comes next. Returns `Some(None)` if
+    /// the frame was cut short
+    fn take_frame(&mut self) -> Option<Option<&'a [u8]>> {
+        if !self.data.starts_with(&FRAME_MARKER.to_ne_bytes()) {
+            return None;
+        }
+        self.data = &self.data[mem::size_of::<u64>()..];
+        Some(self.take_u64().and_then(|len| self.take(len as usize)))
+    }
+    fn take_record(&mut self) -> Option<Record> {
+        let timestamp = self.take_u64()?;
+        let keyspace = self.take_id()?;
+        let table = self.take_id()?;
+        let argc = self.take_u64()? as usize;
+        // don't trust argc for the allocation; a torn write can have anything in there
+        let mut args = Vec::new();
+        for _ in 0..argc {
+            let len = self.take_u64()? as usize;
+            args.push(self.take(len)?.to_owned());
+        }
+        Some(Record {
+            timestamp,
+            keyspace,
+            table,
+            args,
+        })
+    }
+}
+
+/// Decode all the records in `data`, decrypting the ones that are encrypted. If the last
+/// record is incomplete (say, the host crashed in the middle of a write), it is ignored
+pub fn decode_log(data: &[u8]) -> Result<Vec<Record>, OpenError> {
+    decode_log_with(data, crypt::open)
+}
+
+/// Same as [`decode_log`], but decrypts records with `open`
+fn decode_log_with(
+    data: &[u8],
+    open: impl Fn(&[u8]) -> Result<Vec<u8>, OpenError>,
+) -> Result<Vec<Record>, OpenError> {
+    let mut decoder = Decoder { data };
+    let mut records = Vec::new();
+    while !decoder.data.is_empty() {
+        let record = match decoder.take_frame() {
+            None => decoder.take_record(),
+            Some(Some(frame)) => match open(frame) {
+                Ok(record) => decode_record(&record),
+                // a torn write can leave a frame that's cut short at the end
+                Err(OpenError::Corrupted) if decoder.data.is_empty() => None,
+                Err(e) => return Err(e),
+            },
+            Some(None) => None,
+        };
+        match record {
+            Some(record) => records.push(record),
+            None => {
+                log::warn!("Ignoring an incomplete record at the end of the append-only log");
+                break;
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Decode a single record that takes up all of `data`
+pub fn decode_record(data: &[u8]) -> Option<Record> {
+    let mut decoder = Decoder { data };
+    let record = decoder.take_record()?;
+    if decoder.data.is_empty() {
+        Some(record)
+    } else {
+        None
+    }
+}
+
+/// Read all the records in the log at `path`
+pub fn read_log(path: &str) -> IoResult<Vec<Record>> {
+    decode_log(&fs::read(path)?).map_err(|e| {
+        let error = match e {
+            OpenError::MissingKey(id) => format!("{path} has records encrypted with key {id}"),
+            OpenError::Corrupted => format!("{path} has a corrupted record"),
+        };
+        IoError::new(ErrorKind::InvalidData, error)
+    })
+}
+
+/// Remove the file at `path`, ignoring it if it doesn't exist
+fn remove_if_exists(path: &str) -> IoResult<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Remove both the current and the rotated logs. This should only be called once all the
+/// records have been flushed
+pub fn remove_logs() -> IoResult<()> {
+    remove_if_exists(AOF_ROTATED_PATH)?;
+    remove_if_exists(AOF_PATH)
+}
+
+/// Returns the path to the archived segment that was closed at `closed_at`
+fn segment_path(closed_at: u64) -> String {
+    format!("{AOF_ARCHIVE_PATH}/{closed_at}")
+}
+
+/// Move the log at `path` (if any) into the archive of the data directory `dir`
+fn archive_log(dir: &DataDir, path: &str) -> IoResult<()> {
+    if !Path::new(path).exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir.resolve(AOF_ARCHIVE_PATH))?;
+    // two logs can be archived in the same millisecond (say, on startup)
+    let mut closed_at = now_millis();
+    while Path::new(&dir.resolve(&segment_path(closed_at))).exists() {
+        closed_at += 1;
+    }
+    fs::rename(path, dir.resolve(&segment_path(closed_at)))
+}
+
+/// Archive both the current and the rotated logs. Just like [`remove_logs`], this should
+/// only be called once all the records have been flushed
+pub fn archive_logs() -> IoResult<()> {
+    archive_log(&DataDir::current(), AOF_ROTATED_PATH)?;
+    archive_log(&DataDir::current(), AOF_PATH)
+}
+
+/// Returns the times at which the segments in the archive were closed, oldest first
+pub fn list_archive() -> IoResult<Vec<u64>> {
+    let dir = match fs::read_dir(AOF_ARCHIVE_PATH) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut segments = Vec::new();
+    for entry in dir {
+        let name = entry?.file_name();
+        match name.to_str().and_then(|name| name.parse().ok()) {
+            Some(closed_at) => segments.push(closed_at),
+            None => log::warn!("Ignoring unknown file {:?} in the AOF archive", name),
+        }
+    }
+    segments.sort_unstable();
+    Ok(segments)
+}
+
+/// Remove the archived segments that were closed before `cutoff` (in milliseconds since the
+/// epoch), returning the number of segments that were removed
+pub fn prune_archive(cutoff: u64) -> IoResult<usize> {
+    let mut pruned = 0;
+    for closed_at in list_archive()? {
+        if closed_at < cutoff {
+            fs::remove_file(segment_path(closed_at))?;
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+/// Read the records that were made at or after `since` and at or before `until` (both in
+/// milliseconds since the epoch) from the archive, the rotated log and the current log, in
+/// the order in which they were made
+pub fn read_logs_between(since: u64, until: u64) -> IoResult<Vec<Record>> {
+    let mut paths: Vec<String> = list_archive()?
+        .into_iter()
+        // everything in a segment was recorded before it was closed
+        .filter(|closed_at| *closed_at >= since)
+        .map(segment_path)
+        .collect();
+    paths.push(AOF_ROTATED_PATH.to_owned());
+    paths.push(AOF_PATH.to_owned());
+    let mut records = Vec::new();
+    for path in paths {
+        match read_log(&path) {
+            Ok(log) => records.extend(
+                log.into_iter()
+                    .filter(|record| record.timestamp >= since && record.timestamp <= until),
+            ),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(records)
+}
+
+/// Replace the archive with a single segment that holds `records`, and remove the current and
+/// the rotated logs. This is done after a recovery so that the writes which were rolled back
+/// are never replayed again
+pub fn rewrite_archive(records: &[Record]) -> IoResult<()> {
+    let mut buf = Vec::new();
+    for record in records {
+        let mut encoded = Vec::new();
+        record.encode(&mut encoded);
+        buf.extend_from_slice(&seal_record(&encoded)?);
+    }
+    let tmp = format!("{AOF_ARCHIVE_PATH}.tmp");
+    fs::write(&tmp, buf)?;
+    match fs::remove_dir_all(AOF_ARCHIVE_PATH) {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    fs::create_dir_all(AOF_ARCHIVE_PATH)?;
+    fs::rename(tmp, segment_path(now_millis()))?;
+    remove_logs()
+}
+
+fn open_log(dir: &DataDir) -> IoResult<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.resolve(AOF_PATH))
+}
+
+/// The append-only log
+#[derive(Debug)]
+pub struct AppendOnlyLog {
+    file: Mutex<File>,
+    fsync: FsyncPolicy,
+    archive: bool,
+    /// the data directory that the log is in
+    dir: DataDir,
+}
+
+impl AppendOnlyLog {
+    /// Open (or create) the log in the data directory `dir`. If `archive` is set, rotated logs
+    /// are archived instead of being removed
+    pub fn open(dir: &DataDir, fsync: FsyncPolicy, archive: bool) -> IoResult<Self> {
+        Ok(Self {
+            file: Mutex::new(open_log(dir)?),
+            fsync,
+            archive,
+            dir: dir.clone(),
+        })
+    }
+    /// Returns the fsync policy for this log
+    pub const fn fsync_policy(&self) -> FsyncPolicy {
+        self.fsync
+    }
+    /// Append a record (see [`encode_record`]) to the log, encrypting it if files are
+    /// encrypted. With the `always` policy, this returns only once the record has been synced
+    /// to the disk
+    pub fn append(&self, record: &[u8]) -> IoResult<()> {
+        let record = seal_record(record)?;
+        let mut file = self.file.lock();
+        file.write_all(&record)?;
+        if self.fsync == FsyncPolicy::Always {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+    /// Sync the log to the disk
+    pub fn sync(&self) -> IoResult<()> {
+        self.file.lock().sync_data()
+    }
+    /// Move the current log aside and start a fresh one. If an older rotated log is still
+    /// around (because the flush that followed it failed), the current log is moved to its
+    /// end instead since the older log has to be kept till a flush succeeds
+    pub fn rotate(&self) -> IoResult<()> {
+        let mut file = self.file.lock();
+        let rotated = self.dir.resolve(AOF_ROTATED_PATH);
+        file.sync_data()?;
+        if fs::metadata(&rotated).is_ok() {
+            // the writes in the current log are a part of the next flush too, so they can't
+            // be left in it (they would be replayed on top of the flush)
+            let current = fs::read(self.dir.resolve(AOF_PATH))?;
+            let mut older = OpenOptions::new().append(true).open(&rotated)?;
+            older.write_all(&current)?;
+            older.sync_data()?;
+            file.set_len(0)?;
+            return file.sync_data();
+        }
+        fs::rename(self.dir.resolve(AOF_PATH), rotated)?;
+        *file = open_log(&self.dir)?;
+        Ok(())
+    }
+    /// Remove (or archive) the rotated log (if any). Call this once a flush has succeeded
+    pub fn remove_rotated(&self) -> IoResult<()> {
+        let _lck = self.file.lock();
+        let rotated = self.dir.resolve(AOF_ROTATED_PATH);
+        if self.archive {
+            archive_log(&self.dir, &rotated)
+        } else {
+            remove_if_exists(&rotated)
+        }
+    }
+}
+
+#[test]
+fn test_is_logged() {
+    assert!(is_logged(b"set"));
+    assert!(is_logged(b"LMOD"));
+    assert!(is_logged(b"create model mymodel(string, string)"));
+    assert!(is_logged(b"DROP space myspace"));
+    assert!(!is_logged(b"get"));
+    assert!(!is_logged(b"created"));
+    assert!(!is_logged(b"use myspace"));
+    // streams are persisted in a log of their own
+    assert!(!is_logged(b"XADD"));
+    assert!(!is_logged(b"xtrim"));
+}
+
+#[test]
+fn test_encode_decode_log() {
+    let ks = unsafe { ObjectID::from_slice("default") };
+    let mut buf = Vec::new();
+    let args: [&[u8]; 3] = [b"SET", b"x", b"100"];
+    encode_record(&mut buf, 10, Some(&ks), None, args.iter().copied());
+    encode_record(&mut buf, 20, None, None, [b"DEL".as_ref()].iter().copied());
+    let complete_len = buf.len();
+    // a torn write
+    encode_record(&mut buf, 30, Some(&ks), Some(&ks), args.iter().copied());
+    buf.truncate(buf.len() - 2);
+    let records = decode_log(&buf).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        records[0],
+        Record {
+            timestamp: 10,
+            keyspace: Some(ks),
+            table: None,
+            args: vec![b"SET".to_vec(), b"x".to_vec(), b"100".to_vec()],
+        }
+    );
+    assert_eq!(records[1].args, vec![b"DEL".to_vec()]);
+    assert_eq!(records[1].timestamp, 20);
+    assert_eq!(decode_log(&buf[..complete_len]).unwrap().len(), 2);
+    // re-encoding a record gives back the same bytes
+    let mut reencoded = Vec::new();
+    records
+        .iter()
+        .for_each(|record| record.encode(&mut reencoded));
+    assert_eq!(reencoded, &buf[..complete_len]);
+}
+
+#[test]
+fn test_decode_single_record() {
+    let mut buf = Vec::new();
+    let args: [&[u8]; 2] = [b"DEL", b"x"];
+    encode_record(&mut buf, 0, None, None, args.iter().copied());
+    let record = decode_record(&buf).unwrap();
+    assert_eq!(record.args, vec![b"DEL".to_vec(), b"x".to_vec()]);
+    // trailing data and torn records are rejected
+    assert_eq!(decode_record(&buf[..buf.len() - 1]), None);
+    buf.push(0);
+    assert_eq!(decode_record(&buf), None);
+}
+
+#[test]
+fn test_decode_encrypted_log() {
+    use crate::{config::StorageCipher, storage::v1::crypt::Keyring};
+    const KEY: &str = "1:000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    let keyring = Keyring::parse(StorageCipher::Aes256Gcm, KEY).unwrap();
+    let seal = |args: &[&[u8]], buf: &mut Vec<u8>| {
+        let mut record = Vec::new();
+        encode_record(&mut record, 10, None, None, args.iter().copied());
+        buf.extend_from_slice(&frame(&keyring.seal(&record).unwrap().unwrap()));
+    };
+    // records written before encryption was turned on are still read
+    let mut buf = Vec::new();
+    let args: [&[u8]; 2] = [b"DEL", b"x"];
+    encode_record(&mut buf, 10, None, None, args.iter().copied());
+    seal(&[b"SET", b"x", b"100"], &mut buf);
+    assert!(!buf.windows(3).any(|w| w == b"100"));
+    let complete_len = buf.len();
+    // a torn write
+    seal(&[b"SET", b"y", b"200"], &mut buf);
+    let open = |frame: &[u8]| keyring.open(frame);
+    for torn in [complete_len + 4, complete_len + 20, buf.len() - 1] {
+        let records = decode_log_with(&buf[..torn], open).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].args, vec![b"DEL".to_vec(), b"x".to_vec()]);
+        assert_eq!(
+            records[1].args,
+            vec![b"SET".to_vec(), b"x".to_vec(), b"100".to_vec()]
+        );
+    }
+    assert_eq!(decode_log_with(&buf, open).unwrap().len(), 3);
+    // a frame that was tampered with is only ignored if it's the last one
+    let mut tampered = buf.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert_eq!(decode_log_with(&tampered, open).unwrap().len(), 2);
+    let mut tampered = buf.clone();
+    tampered[complete_len - 1] ^= 1;
+    assert_eq!(decode_log_with(&tampered, open), Err(OpenError::Corrupted));
+    // the frames can't be read without the key
+    assert_eq!(
+        decode_log_with(&buf, |frame| Keyring::empty().open(frame)),
+        Err(OpenError::MissingKey(1))
+    );
+}
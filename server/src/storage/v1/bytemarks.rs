@@ -41,6 +41,9 @@
  * KVEBlob:
  * (1) Pure KVEBlob: [0, 3]
  * (2) KVExt/Listmap: [4, 7]
+ * (3) KVExt/Setmap: [8, 11]
+ * (4) KVExt/Hashmap: [12, 15]
+ * (5) KVExt/Sorted setmap: [16, 19]
 */
 /// KVEBlob model bytemark with key:bin, val:bin
 pub const BYTEMARK_MODEL_KV_BIN_BIN: u8 = 0;
@@ -58,6 +61,30 @@ pub const BYTEMARK_MODEL_KV_BINSTR_LIST_STR: u8 = 5;
 pub const BYTEMARK_MODEL_KV_STR_LIST_BINSTR: u8 = 6;
 /// KVEBlob model bytemark with key:str, val: list<str>
 pub const BYTEMARK_MODEL_KV_STR_LIST_STR: u8 = 7;
+/// KVEBlob model bytemark with key:binstr, val: set<binstr>
+pub const BYTEMARK_MODEL_KV_BINSTR_SET_BINSTR: u8 = 8;
+/// KVEBlob model bytemark with key:binstr, val: set<str>
+pub const BYTEMARK_MODEL_KV_BINSTR_SET_STR: u8 = 9;
+/// KVEBlob model bytemark with key:str, val: set<binstr>
+pub const BYTEMARK_MODEL_KV_STR_SET_BINSTR: u8 = 10;
+/// KVEBlob model bytemark with key:str, val: set<str>
+pub const BYTEMARK_MODEL_KV_STR_SET_STR: u8 = 11;
+/// KVEBlob model bytemark with key:binstr, val: hash<binstr>
+pub const BYTEMARK_MODEL_KV_BINSTR_HASH_BINSTR: u8 = 12;
+/// KVEBlob model bytemark with key:binstr, val: hash<str>
+pub const BYTEMARK_MODEL_KV_BINSTR_HASH_STR: u8 = 13;
+/// KVEBlob model bytemark with key:str, val: hash<binstr>
+pub const BYTEMARK_MODEL_KV_STR_HASH_BINSTR: u8 = 14;
+/// KVEBlob model bytemark with key:str, val: hash<str>
+pub const BYTEMARK_MODEL_KV_STR_HASH_STR: u8 = 15;
+/// KVEBlob model bytemark with key:binstr, val: zset<binstr>
+pub const BYTEMARK_MODEL_KV_BINSTR_ZSET_BINSTR: u8 = 16;
+/// KVEBlob model bytemark with key:binstr, val: zset<str>
+pub const BYTEMARK_MODEL_KV_BINSTR_ZSET_STR: u8 = 17;
+/// KVEBlob model bytemark with key:str, val: zset<binstr>
+pub const BYTEMARK_MODEL_KV_STR_ZSET_BINSTR: u8 = 18;
+/// KVEBlob model bytemark with key:str, val: zset<str>
+pub const BYTEMARK_MODEL_KV_STR_ZSET_STR: u8 = 19;
 
 // storage bym
 /// Persistent storage bytemark
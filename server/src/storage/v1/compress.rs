@@ -0,0 +1,183 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Value compression
+//!
+//! If `storage.compress` is set, the values (and the members of lists, sets, hashes and sorted
+//! sets) that are at least `storage.compressmin` bytes long are compressed when they're written
+//! to disk. The extent of a compressed value has its highest bit set, and its payload looks like:
+//! ```text
+//! [1B: Codec][8B: Length of the value][?B: Compressed value]
+//! ```
+//! The length is little endian. A value is only written compressed if that makes it smaller.
+//! Since every value records the codec that it was compressed with, changing the codec (or
+//! turning compression off) doesn't affect the values that are already on disk
+
+use {
+    crate::{
+        config::StorageCompression, corestore::SharedSlice, dbnet::compression::Codec, IoResult,
+    },
+    core::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
+    std::borrow::Cow,
+};
+
+/// The size from which values are compressed by default
+pub const DEFAULT_COMPRESSMIN: usize = 1024;
+/// Set on the extent of a compressed value
+const COMPRESSED: u64 = 1 << 63;
+/// The size of the part of a compressed payload that comes before the compressed value
+const PREFIX_LEN: usize = 9;
+const CODEC_NONE: u8 = 0;
+const CODEC_LZ4: u8 = 1;
+const CODEC_ZSTD: u8 = 2;
+
+static CODEC: AtomicU8 = AtomicU8::new(CODEC_NONE);
+static MIN: AtomicUsize = AtomicUsize::new(DEFAULT_COMPRESSMIN);
+static COMPRESSED_VALUES: AtomicU64 = AtomicU64::new(0);
+static RAW_BYTES: AtomicU64 = AtomicU64::new(0);
+static STORED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+const fn codec_id(codec: Codec) -> u8 {
+    match codec {
+        Codec::Lz4 => CODEC_LZ4,
+        Codec::Zstd => CODEC_ZSTD,
+    }
+}
+
+const fn codec_of(id: u8) -> Option<Codec> {
+    match id {
+        CODEC_LZ4 => Some(Codec::Lz4),
+        CODEC_ZSTD => Some(Codec::Zstd),
+        _ => None,
+    }
+}
+
+/// Set how the values that are written from now on are compressed
+pub fn configure(settings: StorageCompression) {
+    let codec = settings.codec.codec().map_or(CODEC_NONE, codec_id);
+    CODEC.store(codec, Ordering::Relaxed);
+    MIN.store(settings.min, Ordering::Relaxed);
+}
+
+/// Returns the codec that values are compressed with and the size from which they're
+/// compressed, if compression is enabled
+pub fn settings() -> Option<(Codec, usize)> {
+    codec_of(CODEC.load(Ordering::Relaxed)).map(|codec| (codec, MIN.load(Ordering::Relaxed)))
+}
+
+/// The values that were written compressed since the server was started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionStats {
+    /// the number of values
+    pub values: u64,
+    /// their size before they were compressed
+    pub raw: u64,
+    /// their size on disk
+    pub stored: u64,
+}
+
+impl CompressionStats {
+    /// Returns the number of bytes that compression saved
+    pub const fn saved(&self) -> u64 {
+        self.raw.saturating_sub(self.stored)
+    }
+}
+
+/// Returns the statistics of the values that were written compressed
+pub fn stats() -> CompressionStats {
+    CompressionStats {
+        values: COMPRESSED_VALUES.load(Ordering::Relaxed),
+        raw: RAW_BYTES.load(Ordering::Relaxed),
+        stored: STORED_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// Encode a value for writing it out, returning its extent and its payload. The value is
+/// compressed with the configured settings (see [`encode_value_with`])
+pub fn encode_value(value: &[u8]) -> IoResult<(u64, Cow<'_, [u8]>)> {
+    self::encode_value_with(value, self::settings())
+}
+
+/// Encode a value for writing it out, returning its extent and its payload. The value is
+/// compressed with `codec` if it is at least `min` bytes long and compressing it makes it smaller
+pub fn encode_value_with(
+    value: &[u8],
+    settings: Option<(Codec, usize)>,
+) -> IoResult<(u64, Cow<'_, [u8]>)> {
+    if let Some((codec, min)) = settings {
+        if value.len() >= min {
+            let compressed = codec.compress(value)?;
+            if PREFIX_LEN + compressed.len() < value.len() {
+                let mut payload = Vec::with_capacity(PREFIX_LEN + compressed.len());
+                payload.push(codec_id(codec));
+                payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+                payload.extend_from_slice(&compressed);
+                COMPRESSED_VALUES.fetch_add(1, Ordering::Relaxed);
+                RAW_BYTES.fetch_add(value.len() as u64, Ordering::Relaxed);
+                STORED_BYTES.fetch_add(payload.len() as u64, Ordering::Relaxed);
+                return Ok((payload.len() as u64 | COMPRESSED, Cow::Owned(payload)));
+            }
+        }
+    }
+    Ok((value.len() as u64, Cow::Borrowed(value)))
+}
+
+/// The extent of a value that is being read back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueExtent {
+    /// the length of the payload
+    pub len: usize,
+    compressed: bool,
+}
+
+impl ValueExtent {
+    /// Decode an extent that was read back, returning `None` if the payload can't fit in
+    /// memory
+    pub fn decode(extent: u64) -> Option<Self> {
+        Some(Self {
+            len: usize::try_from(extent & !COMPRESSED).ok()?,
+            compressed: extent & COMPRESSED != 0,
+        })
+    }
+}
+
+/// Decode the payload of a value, returning `None` if it is corrupted
+pub fn decode_value(extent: ValueExtent, payload: &[u8]) -> Option<SharedSlice> {
+    if !extent.compressed {
+        return Some(SharedSlice::new(payload));
+    }
+    if payload.len() < PREFIX_LEN {
+        return None;
+    }
+    let (prefix, compressed) = payload.split_at(PREFIX_LEN);
+    let codec = codec_of(prefix[0])?;
+    let rawlen = u64::from_le_bytes(prefix[1..].try_into().unwrap());
+    let rawlen = usize::try_from(rawlen).ok()?;
+    codec
+        .decompress(compressed, rawlen)
+        .ok()
+        .map(SharedSlice::from)
+}
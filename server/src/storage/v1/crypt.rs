@@ -0,0 +1,404 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Encryption at rest
+//!
+//! If `storage.encrypt` is set, every file that's flushed (see [`flush::oneshot`]) is
+//! encrypted as it's written out, in chunks of [`CHUNK_LEN`] bytes. An encrypted file looks
+//! like:
+//! ```text
+//! [8B: Magic][1B: Cipher][4B: Key ID][12B: Nonce]([?B: Encrypted chunk][16B: Tag])*
+//! ```
+//! The key ID is little endian. Every chunk but the last one is [`CHUNK_LEN`] bytes long (the
+//! last one can be empty), and is encrypted with the nonce XORed with the index of the chunk
+//! (in its last 8 bytes). Everything that comes before the first chunk is authenticated along
+//! with every chunk, as are the index of the chunk and whether it's the last one, so chunks
+//! can't be swapped, dropped or cut off without it being noticed. The encrypted file is what
+//! would've been written otherwise (with its [header]), so the format of the payloads doesn't
+//! change.
+//!
+//! The keys are given as `<id>:<key>` entries (separated by commas or newlines), where the key
+//! is 32 bytes written in hex. New files are always encrypted with the key that has the highest
+//! ID, while a file is decrypted with the key that it names. So, to rotate the key, add one
+//! with a higher ID and keep the older ones around for as long as there are files (or
+//! snapshots) that were encrypted with them. Turning encryption off doesn't affect the files
+//! that are already encrypted either, as long as their keys are still there.
+//!
+//! The records of the append-only log and the streams log are encrypted one by one, each as a
+//! file of its own (see [`aof`]). Values can't be spilled to disk while encryption is enabled,
+//! since they're mapped into memory as they are
+//!
+//! [`flush::oneshot`]: super::flush::oneshot
+//! [header]: super::header
+//! [`aof`]: super::aof
+
+use {
+    crate::{
+        config::{StorageCipher, StorageEncryption},
+        IoResult,
+    },
+    openssl::{
+        rand::rand_bytes,
+        symm::{self, Cipher},
+    },
+    parking_lot::{const_rwlock, RwLock},
+    std::{
+        collections::BTreeMap,
+        fs,
+        io::{Cursor, Error as IoError, ErrorKind, Seek, SeekFrom, Write},
+        mem,
+    },
+};
+
+/// The magic that encrypted files start with
+const MAGIC: [u8; 8] = *b"SKYDCRPT";
+const CIPHER_AES256GCM: u8 = 1;
+const CIPHER_CHACHA20POLY1305: u8 = 2;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+/// The size of the part of an encrypted file that comes before the encrypted chunks
+const PREFIX_LEN: usize = MAGIC.len() + 1 + 4 + NONCE_LEN;
+/// The size of the chunks that a file is encrypted in
+pub const CHUNK_LEN: usize = 64 * 1024;
+
+static KEYRING: RwLock<Keyring> = const_rwlock(Keyring::empty());
+
+pub type Key = [u8; KEY_LEN];
+
+const fn cipher_id(cipher: StorageCipher) -> Option<u8> {
+    match cipher {
+        StorageCipher::None => None,
+        StorageCipher::Aes256Gcm => Some(CIPHER_AES256GCM),
+        StorageCipher::Chacha20Poly1305 => Some(CIPHER_CHACHA20POLY1305),
+    }
+}
+
+fn cipher_of(id: u8) -> Option<Cipher> {
+    match id {
+        CIPHER_AES256GCM => Some(Cipher::aes_256_gcm()),
+        CIPHER_CHACHA20POLY1305 => Some(Cipher::chacha20_poly1305()),
+        _ => None,
+    }
+}
+
+/// Why an encrypted file couldn't be decrypted
+#[derive(Debug, PartialEq, Eq)]
+pub enum OpenError {
+    /// The file was encrypted with a key (with this ID) that we don't have
+    MissingKey(u32),
+    /// The file was cut short or tampered with
+    Corrupted,
+}
+
+/// The keys that files are encrypted with
+pub struct Keyring {
+    /// the cipher that new files are encrypted with (if they are)
+    cipher: Option<u8>,
+    keys: BTreeMap<u32, Key>,
+}
+
+impl Keyring {
+    /// A keyring that doesn't encrypt anything (and can't decrypt anything either)
+    pub const fn empty() -> Self {
+        Self {
+            cipher: None,
+            keys: BTreeMap::new(),
+        }
+    }
+    /// Create a keyring from `<id>:<key>` entries. Returns `None` if an entry is malformed or
+    /// if an ID is repeated
+    pub fn parse(cipher: StorageCipher, entries: &str) -> Option<Self> {
+        let mut keys = BTreeMap::new();
+        for entry in entries.split([',', '\n']).map(str::trim) {
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let (id, key) = entry.split_once(':')?;
+            let id = id.trim().parse().ok()?;
+            let key = self::decode_key(key.trim())?;
+            if keys.insert(id, key).is_some() {
+                return None;
+            }
+        }
+        Some(Self {
+            cipher: cipher_id(cipher),
+            keys,
+        })
+    }
+    /// Returns true if new files are encrypted
+    pub fn is_enabled(&self) -> bool {
+        self.cipher.is_some() && !self.keys.is_empty()
+    }
+    /// Returns a [`Sealer`] that encrypts what's written to `inner` with the newest key, or
+    /// `None` if files aren't encrypted
+    pub fn sealer<W: Write + Seek>(&self, inner: W) -> IoResult<Option<Sealer<W>>> {
+        match (self.cipher, self.keys.iter().next_back()) {
+            (Some(cipher_id), Some((key_id, key))) => {
+                Sealer::new(inner, cipher_id, *key_id, *key).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+    /// Encrypt `file` with the newest key. Returns `None` if files aren't encrypted
+    pub fn seal(&self, file: &[u8]) -> IoResult<Option<Vec<u8>>> {
+        let buf = Cursor::new(Vec::with_capacity(PREFIX_LEN + file.len() + TAG_LEN));
+        match self.sealer(buf)? {
+            Some(mut sealer) => {
+                sealer.write_all(file)?;
+                Ok(Some(sealer.finish(&[])?.into_inner()))
+            }
+            None => Ok(None),
+        }
+    }
+    /// Decrypt a file that was encrypted with a [`Sealer`] (by this keyring or by one that
+    /// shares the key)
+    pub fn open(&self, data: &[u8]) -> Result<Vec<u8>, OpenError> {
+        if data.len() < PREFIX_LEN + TAG_LEN || !self::is_encrypted(data) {
+            return Err(OpenError::Corrupted);
+        }
+        let (prefix, mut rest) = data.split_at(PREFIX_LEN);
+        let prefix: &[u8; PREFIX_LEN] = prefix.try_into().unwrap();
+        let cipher = cipher_of(prefix[MAGIC.len()]).ok_or(OpenError::Corrupted)?;
+        let key_id = u32::from_le_bytes(prefix[MAGIC.len() + 1..][..4].try_into().unwrap());
+        let key = self
+            .keys
+            .get(&key_id)
+            .ok_or(OpenError::MissingKey(key_id))?;
+        let mut file = Vec::with_capacity(rest.len());
+        let mut index = 0;
+        loop {
+            // only the last chunk is shorter than a full one
+            let last = rest.len() < CHUNK_LEN + TAG_LEN;
+            let len = rest.len().min(CHUNK_LEN + TAG_LEN);
+            let (chunk, next) = rest.split_at(len);
+            if chunk.len() < TAG_LEN {
+                return Err(OpenError::Corrupted);
+            }
+            let (encrypted, tag) = chunk.split_at(chunk.len() - TAG_LEN);
+            let opened = symm::decrypt_aead(
+                cipher,
+                key,
+                Some(&chunk_nonce(prefix, index)),
+                &chunk_aad(prefix, index, last),
+                encrypted,
+                tag,
+            )
+            .map_err(|_| OpenError::Corrupted)?;
+            file.extend_from_slice(&opened);
+            if last {
+                return Ok(file);
+            }
+            rest = next;
+            index += 1;
+        }
+    }
+}
+
+/// Returns the nonce that the chunk with the given index is encrypted with
+fn chunk_nonce(prefix: &[u8; PREFIX_LEN], index: u64) -> [u8; NONCE_LEN] {
+    let mut nonce: [u8; NONCE_LEN] = prefix[PREFIX_LEN - NONCE_LEN..].try_into().unwrap();
+    for (byte, i) in nonce[NONCE_LEN - 8..].iter_mut().zip(index.to_be_bytes()) {
+        *byte ^= i;
+    }
+    nonce
+}
+
+/// Returns the data that is authenticated along with the chunk with the given index
+fn chunk_aad(prefix: &[u8; PREFIX_LEN], index: u64, last: bool) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(PREFIX_LEN + 9);
+    aad.extend_from_slice(prefix);
+    aad.extend_from_slice(&index.to_le_bytes());
+    aad.push(last as u8);
+    aad
+}
+
+/// Encrypts a file as it's written out, a chunk at a time. The first chunk is held back till
+/// the file is [finished](Sealer::finish), so that the start of the file can still be changed
+/// (like the header, which is only known once the payload is written)
+pub struct Sealer<W> {
+    inner: W,
+    cipher: Cipher,
+    key: Key,
+    prefix: [u8; PREFIX_LEN],
+    /// the chunk that's being filled
+    chunk: Vec<u8>,
+    /// the index of the chunk that's being filled
+    index: u64,
+    /// the first chunk, once it's filled
+    first: Option<Vec<u8>>,
+}
+
+impl<W: Write + Seek> Sealer<W> {
+    fn new(mut inner: W, cipher_id: u8, key_id: u32, key: Key) -> IoResult<Self> {
+        let mut prefix = [0; PREFIX_LEN];
+        prefix[..MAGIC.len()].copy_from_slice(&MAGIC);
+        prefix[MAGIC.len()] = cipher_id;
+        prefix[MAGIC.len() + 1..][..4].copy_from_slice(&key_id.to_le_bytes());
+        rand_bytes(&mut prefix[PREFIX_LEN - NONCE_LEN..])?;
+        inner.write_all(&prefix)?;
+        Ok(Self {
+            inner,
+            cipher: cipher_of(cipher_id).unwrap(),
+            key,
+            prefix,
+            chunk: Vec::with_capacity(CHUNK_LEN),
+            index: 0,
+            first: None,
+        })
+    }
+    /// Encrypt the chunk with the given index, returning it along with its tag
+    fn seal_chunk(&self, index: u64, chunk: &[u8], last: bool) -> IoResult<Vec<u8>> {
+        let mut tag = [0; TAG_LEN];
+        let mut sealed = symm::encrypt_aead(
+            self.cipher,
+            &self.key,
+            Some(&chunk_nonce(&self.prefix, index)),
+            &chunk_aad(&self.prefix, index, last),
+            chunk,
+            &mut tag,
+        )?;
+        sealed.extend_from_slice(&tag);
+        Ok(sealed)
+    }
+    /// Write out the chunk that was just filled
+    fn write_chunk(&mut self) -> IoResult<()> {
+        let chunk = mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_LEN));
+        if self.index == 0 {
+            // leave room for it; it's written once the file is finished
+            self.inner.write_all(&[0; CHUNK_LEN + TAG_LEN])?;
+            self.first = Some(chunk);
+        } else {
+            let sealed = self.seal_chunk(self.index, &chunk, false)?;
+            self.inner.write_all(&sealed)?;
+        }
+        self.index += 1;
+        Ok(())
+    }
+    /// Write out the rest of the file, with its first bytes replaced by `start` (the file has
+    /// to be atleast as long), and return the inner writer
+    pub fn finish(mut self, start: &[u8]) -> IoResult<W> {
+        let mut last = mem::take(&mut self.chunk);
+        match self.first.take() {
+            Some(mut first) => {
+                first[..start.len()].copy_from_slice(start);
+                let sealed = self.seal_chunk(self.index, &last, true)?;
+                self.inner.write_all(&sealed)?;
+                let end = PREFIX_LEN + self.index as usize * (CHUNK_LEN + TAG_LEN) + sealed.len();
+                let sealed = self.seal_chunk(0, &first, false)?;
+                self.inner.seek(SeekFrom::Start(PREFIX_LEN as u64))?;
+                self.inner.write_all(&sealed)?;
+                self.inner.seek(SeekFrom::Start(end as u64))?;
+            }
+            None => {
+                last[..start.len()].copy_from_slice(start);
+                let sealed = self.seal_chunk(0, &last, true)?;
+                self.inner.write_all(&sealed)?;
+            }
+        }
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write + Seek> Write for Sealer<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let take = buf.len().min(CHUNK_LEN - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..take]);
+        if self.chunk.len() == CHUNK_LEN {
+            self.write_chunk()?;
+        }
+        Ok(take)
+    }
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decode a key written in hex
+fn decode_key(hex: &str) -> Option<Key> {
+    let hex = hex.as_bytes();
+    if hex.len() != KEY_LEN * 2 {
+        return None;
+    }
+    let digit = |d: u8| (d as char).to_digit(16).map(|d| d as u8);
+    let mut key = [0; KEY_LEN];
+    for (byte, digits) in key.iter_mut().zip(hex.chunks_exact(2)) {
+        *byte = (digit(digits[0])? << 4) | digit(digits[1])?;
+    }
+    Some(key)
+}
+
+/// Returns true if `data` is (the start of) an encrypted file
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Load the keys in `settings`, and encrypt the files that are written from now on as set
+pub fn configure(settings: &StorageEncryption) -> IoResult<()> {
+    let mut entries = settings.keys.clone().unwrap_or_default();
+    if let Some(keyfile) = &settings.keyfile {
+        entries.push('\n');
+        entries.push_str(&fs::read_to_string(keyfile)?);
+    }
+    let keyring = Keyring::parse(settings.cipher, &entries).ok_or_else(|| {
+        IoError::new(
+            ErrorKind::InvalidData,
+            "expected `<id>:<key>` entries with unique IDs and 32 byte keys written in hex",
+        )
+    })?;
+    if settings.is_enabled() && !keyring.is_enabled() {
+        return Err(IoError::new(
+            ErrorKind::InvalidData,
+            "no encryption keys were given",
+        ));
+    }
+    *KEYRING.write() = keyring;
+    Ok(())
+}
+
+/// Returns true if the files that are written are encrypted
+pub fn is_enabled() -> bool {
+    KEYRING.read().is_enabled()
+}
+
+/// Returns a [`Sealer`] that encrypts what's written to `inner` with the configured keys. This
+/// fails if files aren't encrypted (see [`is_enabled`])
+pub fn sealer<W: Write + Seek>(inner: W) -> IoResult<Sealer<W>> {
+    KEYRING
+        .read()
+        .sealer(inner)?
+        .ok_or_else(|| IoError::new(ErrorKind::Other, "files aren't encrypted"))
+}
+
+/// Encrypt `file` with the configured keys. Returns `None` if files aren't encrypted
+pub fn seal(file: &[u8]) -> IoResult<Option<Vec<u8>>> {
+    KEYRING.read().seal(file)
+}
+
+/// Decrypt an encrypted file with the configured keys
+pub fn open(data: &[u8]) -> Result<Vec<u8>, OpenError> {
+    KEYRING.read().open(data)
+}
@@ -0,0 +1,150 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Deltas
+//!
+//! Rewriting a large table on every save is wasteful when only a handful of its keys were
+//! changed. So once a table has been written out in full, a save only writes the keys that
+//! changed since then (see [`DirtyKeys`](crate::kvengine::dirty::DirtyKeys)) into a _delta_
+//! that sits next to the table file (`{table}.delta`). Its payload looks like:
+//! ```text
+//! [4B: CRC32 of the base][8B: Length of the base] => The full save that this delta applies to
+//! [8B: Extent of the upserts][?B: Upserts] => The changed keys, encoded like the table
+//! [?B: Deletes] => The deleted keys, encoded as a map with empty values
+//! ```
+//! The integers are little endian. A delta is cumulative: every save rewrites it with all the
+//! changes since the last full save. When the table is loaded, the delta is applied over the
+//! full save, but only if it was written against that exact payload; a delta that was left
+//! behind by an older full save is ignored. Once a delta has too many keys compared to the
+//! table, the next save _compacts_ the table by writing it out in full and removing the delta
+
+use {
+    super::{de::DeserializeInto, header::PayloadId},
+    crate::{
+        corestore::{htable::Coremap, SharedSlice},
+        kvengine::{KVEValue, KVEngine},
+        IoResult,
+    },
+    std::io::Write,
+};
+
+/// The suffix of the delta of a table
+pub const DELTA_SUFFIX: &str = ".delta";
+/// A table is compacted once more than `1/COMPACTION_RATIO` of its keys are in the delta
+const COMPACTION_RATIO: usize = 4;
+/// The size of the fixed part of a delta
+const DELTA_PREFIX_LEN: usize = 20;
+
+/// Returns true if a table with `len` keys (of which `dirty` were changed) should be written
+/// out in full instead of as a delta
+pub fn should_compact(dirty: usize, len: usize) -> bool {
+    dirty.saturating_mul(COMPACTION_RATIO) > len
+}
+
+/// Serialize a delta of the given `keys` of `engine` (against the full save `base`) and write
+/// it to the provided buffer. `serialize` is the routine that serializes the table's map
+pub fn raw_serialize_delta<W, T>(
+    w: &mut W,
+    base: PayloadId,
+    engine: &KVEngine<T>,
+    keys: &[SharedSlice],
+    serialize: impl Fn(&Coremap<SharedSlice, T>, &mut Vec<u8>) -> IoResult<()>,
+) -> IoResult<()>
+where
+    W: Write,
+    T: KVEValue,
+{
+    let upserts = Coremap::new();
+    let deletes: Coremap<SharedSlice, SharedSlice> = Coremap::new();
+    for key in keys {
+        match engine.get_inner_ref().get(key.as_slice()) {
+            Some(value) => {
+                upserts.upsert(key.clone(), value.snapshot());
+            }
+            None => {
+                deletes.upsert(key.clone(), SharedSlice::new(b""));
+            }
+        }
+    }
+    // we need the extent of the upserts before we write them out
+    let mut encoded_upserts = Vec::new();
+    serialize(&upserts, &mut encoded_upserts)?;
+    w.write_all(&base.checksum.to_le_bytes())?;
+    w.write_all(&base.len.to_le_bytes())?;
+    w.write_all(&(encoded_upserts.len() as u64).to_le_bytes())?;
+    w.write_all(&encoded_upserts)?;
+    super::se::raw_serialize_map(&deletes, w)
+}
+
+/// A delta that was read back
+pub struct Delta<T> {
+    /// the full save that this delta applies to
+    pub base: PayloadId,
+    upserts: Coremap<SharedSlice, T>,
+    deletes: Coremap<SharedSlice, SharedSlice>,
+}
+
+impl<T> Delta<T> {
+    /// Apply this delta to the data that was loaded from its base, returning the keys that
+    /// were changed
+    pub fn apply_to(self, data: &Coremap<SharedSlice, T>) -> Vec<SharedSlice> {
+        let mut changed = Vec::with_capacity(self.upserts.len() + self.deletes.len());
+        for (key, value) in self.upserts {
+            data.upsert(key.clone(), value);
+            changed.push(key);
+        }
+        for (key, _) in self.deletes {
+            let _ = data.remove(key.as_slice());
+            changed.push(key);
+        }
+        changed
+    }
+}
+
+/// Deserialize the payload of a delta
+pub fn deserialize_delta<T>(data: &[u8]) -> Option<Delta<T>>
+where
+    Coremap<SharedSlice, T>: DeserializeInto,
+{
+    if data.len() < DELTA_PREFIX_LEN {
+        return None;
+    }
+    let (prefix, rest) = data.split_at(DELTA_PREFIX_LEN);
+    let base = PayloadId {
+        checksum: u32::from_le_bytes(prefix[..4].try_into().unwrap()),
+        len: u64::from_le_bytes(prefix[4..12].try_into().unwrap()),
+    };
+    let upserts_len = u64::from_le_bytes(prefix[12..].try_into().unwrap());
+    let upserts_len = usize::try_from(upserts_len)
+        .ok()
+        .filter(|len| *len <= rest.len())?;
+    let (upserts, deletes) = rest.split_at(upserts_len);
+    Some(Delta {
+        base,
+        upserts: super::de::deserialize_into(upserts)?,
+        deletes: super::de::deserialize_map(deletes)?,
+    })
+}
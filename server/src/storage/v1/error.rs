@@ -49,6 +49,8 @@ pub enum StorageEngineError {
     CorruptedFile(String),
     /// The file contains bad metadata
     BadMetadata(String),
+    /// The file was encrypted with a key (with this ID) that isn't configured
+    MissingKey(String, u32),
 }
 
 impl StorageEngineError {
@@ -85,6 +87,10 @@ impl fmt::Display for StorageEngineError {
             Self::IoErrorExtra(ioe, extra) => write!(f, "I/O error while {extra}: {ioe}"),
             Self::CorruptedFile(cfile) => write!(f, "file `{cfile}` is corrupted"),
             Self::BadMetadata(file) => write!(f, "bad metadata in file `{file}`"),
+            Self::MissingKey(file, id) => write!(
+                f,
+                "file `{file}` was encrypted with key {id}, which isn't configured"
+            ),
         }
     }
 }
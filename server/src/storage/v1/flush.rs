@@ -30,14 +30,18 @@
 //! the table level
 
 use {
-    super::{bytemarks, interface},
+    super::{bytemarks, delta, header::PayloadId, interface, ttl},
     crate::{
+        auth::provider::UserRecord,
         corestore::{
+            htable::Coremap,
             map::iter::BorrowedIter,
             memstore::SYSTEM,
             memstore::{Keyspace, Memstore, ObjectID, SystemKeyspace},
             table::{DataModel, SystemDataModel, SystemTable, Table},
+            SharedSlice,
         },
+        kvengine::dirty::DirtyKeys,
         registry,
         util::Wrapper,
         IoResult,
@@ -54,6 +58,11 @@ pub trait StorageTarget {
     ///
     /// Example cases where this doesn't apply: snapshots
     const SHOULD_UNTRIP_PRELOAD_TRIPSWITCH: bool;
+    /// This storage target keeps the tables that it wrote earlier, so only the keys that
+    /// changed since then need to be written (as [deltas](delta))
+    ///
+    /// Example cases where this doesn't apply: snapshots (they're always written afresh)
+    const INCREMENTAL: bool;
     /// The root for this storage target. **Must not be separator terminated!**
     fn root(&self) -> String;
     /// Returns the path to the `PRELOAD_` **temporary file** ($ROOT/PRELOAD)
@@ -94,11 +103,23 @@ pub struct Autoflush;
 impl StorageTarget for Autoflush {
     const NEEDS_TREE_INIT: bool = false;
     const SHOULD_UNTRIP_PRELOAD_TRIPSWITCH: bool = true;
+    const INCREMENTAL: bool = true;
     fn root(&self) -> String {
         String::from(interface::DIR_KSROOT)
     }
 }
 
+/// Same as [`Autoflush`], but for the tree in the given data directory (like the one of an
+/// embedded database)
+impl StorageTarget for interface::DataDir {
+    const NEEDS_TREE_INIT: bool = false;
+    const SHOULD_UNTRIP_PRELOAD_TRIPSWITCH: bool = true;
+    const INCREMENTAL: bool = true;
+    fn root(&self) -> String {
+        self.resolve(interface::DIR_KSROOT)
+    }
+}
+
 /// A remote snapshot storage target
 pub struct RemoteSnapshot<'a> {
     name: &'a str,
@@ -113,6 +134,7 @@ impl<'a> RemoteSnapshot<'a> {
 impl<'a> StorageTarget for RemoteSnapshot<'a> {
     const NEEDS_TREE_INIT: bool = true;
     const SHOULD_UNTRIP_PRELOAD_TRIPSWITCH: bool = false;
+    const INCREMENTAL: bool = false;
     fn root(&self) -> String {
         let mut p = String::from(interface::DIR_RSNAPROOT);
         p.push('/');
@@ -135,6 +157,7 @@ impl LocalSnapshot {
 impl StorageTarget for LocalSnapshot {
     const NEEDS_TREE_INIT: bool = true;
     const SHOULD_UNTRIP_PRELOAD_TRIPSWITCH: bool = false;
+    const INCREMENTAL: bool = false;
     fn root(&self) -> String {
         let mut p = String::from(interface::DIR_SNAPROOT);
         p.push('/');
@@ -143,6 +166,29 @@ impl StorageTarget for LocalSnapshot {
     }
 }
 
+/// The snapshot that is sent to a replica when it syncs with us
+pub struct ReplicationSnapshot {
+    name: String,
+}
+
+impl ReplicationSnapshot {
+    pub fn new(name: String) -> Self {
+        Self { name }
+    }
+}
+
+impl StorageTarget for ReplicationSnapshot {
+    const NEEDS_TREE_INIT: bool = true;
+    const SHOULD_UNTRIP_PRELOAD_TRIPSWITCH: bool = false;
+    const INCREMENTAL: bool = false;
+    fn root(&self) -> String {
+        let mut p = String::from(interface::DIR_REPLROOT);
+        p.push('/');
+        p.push_str(&self.name);
+        p
+    }
+}
+
 /// A keyspace that can be flushed
 pub trait FlushableKeyspace<T: FlushableTable, U: Deref<Target = T>> {
     /// The number of tables in this keyspace
@@ -179,6 +225,31 @@ pub trait FlushableTable {
     fn write_table_to<W: Write>(&self, writer: &mut W) -> IoResult<()>;
     /// Returns the model code bytemark
     fn model_code(&self) -> u8;
+    /// Returns the number of keys in the table
+    fn key_count(&self) -> usize;
+    /// Returns the keys that were changed since the table was last written out in full, if
+    /// the table keeps track of them. Tables that don't are always written out in full
+    fn dirty_keys(&self) -> Option<&DirtyKeys> {
+        None
+    }
+    /// Serializes a [delta](delta) of the given keys against the full save `base` and writes
+    /// it to the provided buffer. Only called for tables that have
+    /// [dirty keys](Self::dirty_keys)
+    fn write_delta_to<W: Write>(
+        &self,
+        writer: &mut W,
+        base: PayloadId,
+        keys: &[SharedSlice],
+    ) -> IoResult<()>;
+    /// Returns the expiry deadlines of the keys in the table, if the table has any. They're
+    /// [written](ttl) next to the table every time it's saved
+    fn expiry(&self) -> Option<&Coremap<SharedSlice, u64>> {
+        None
+    }
+    /// Purge the keys whose deadlines have passed, returning the number of purged keys
+    fn sweep_expired(&self) -> usize {
+        0
+    }
 }
 
 impl FlushableTable for Table {
@@ -191,6 +262,15 @@ impl FlushableTable for Table {
             DataModel::KVExtListmap(ref kvl) => {
                 super::se::raw_serialize_list_map(kvl.get_inner_ref(), writer)
             }
+            DataModel::KVExtSetmap(ref kvs) => {
+                super::se::raw_serialize_set_map(kvs.get_inner_ref(), writer)
+            }
+            DataModel::KVExtHashmap(ref kvh) => {
+                super::se::raw_serialize_hash_map(kvh.get_inner_ref(), writer)
+            }
+            DataModel::KVExtZSetmap(ref kvz) => {
+                super::se::raw_serialize_zset_map(kvz.get_inner_ref(), writer)
+            }
         }
     }
     fn storage_code(&self) -> u8 {
@@ -199,6 +279,60 @@ impl FlushableTable for Table {
     fn model_code(&self) -> u8 {
         self.get_model_code()
     }
+    fn key_count(&self) -> usize {
+        self.count()
+    }
+    fn dirty_keys(&self) -> Option<&DirtyKeys> {
+        Some(self.dirty_keys())
+    }
+    fn expiry(&self) -> Option<&Coremap<SharedSlice, u64>> {
+        Some(self.get_expiry_ref())
+    }
+    fn sweep_expired(&self) -> usize {
+        self.sweep_expired()
+    }
+    fn write_delta_to<W: Write>(
+        &self,
+        writer: &mut W,
+        base: PayloadId,
+        keys: &[SharedSlice],
+    ) -> IoResult<()> {
+        match self.get_model_ref() {
+            DataModel::KV(ref kve) => {
+                delta::raw_serialize_delta(writer, base, kve, keys, |m, w| {
+                    super::se::raw_serialize_map(m, w)
+                })
+            }
+            DataModel::KVExtListmap(ref kvl) => delta::raw_serialize_delta(
+                writer,
+                base,
+                kvl,
+                keys,
+                super::se::raw_serialize_list_map,
+            ),
+            DataModel::KVExtSetmap(ref kvs) => delta::raw_serialize_delta(
+                writer,
+                base,
+                kvs,
+                keys,
+                super::se::raw_serialize_set_map,
+            ),
+            DataModel::KVExtHashmap(ref kvh) => delta::raw_serialize_delta(
+                writer,
+                base,
+                kvh,
+                keys,
+                super::se::raw_serialize_hash_map,
+            ),
+            DataModel::KVExtZSetmap(ref kvz) => delta::raw_serialize_delta(
+                writer,
+                base,
+                kvz,
+                keys,
+                super::se::raw_serialize_zset_map,
+            ),
+        }
+    }
 }
 
 impl FlushableTable for SystemTable {
@@ -207,7 +341,9 @@ impl FlushableTable for SystemTable {
     }
     fn write_table_to<W: Write>(&self, writer: &mut W) -> IoResult<()> {
         match self.get_model_ref() {
-            SystemDataModel::Auth(amap) => super::se::raw_serialize_map(amap.as_ref(), writer),
+            SystemDataModel::Auth(amap) => {
+                super::se::raw_serialize_map_with(amap.as_ref(), writer, UserRecord::encode)
+            }
         }
     }
     fn storage_code(&self) -> u8 {
@@ -218,6 +354,19 @@ impl FlushableTable for SystemTable {
             SystemDataModel::Auth(_) => bytemarks::SYSTEM_TABLE_AUTH,
         }
     }
+    fn key_count(&self) -> usize {
+        match self.get_model_ref() {
+            SystemDataModel::Auth(amap) => amap.len(),
+        }
+    }
+    fn write_delta_to<W: Write>(
+        &self,
+        _writer: &mut W,
+        _base: PayloadId,
+        _keys: &[SharedSlice],
+    ) -> IoResult<()> {
+        unreachable!("system tables are always written out in full")
+    }
 }
 
 /// Flush the entire **preload + keyspaces + their partmaps**
@@ -267,20 +416,104 @@ pub mod oneshot {
     //! files et al are handled
     //!
     use super::*;
-    use std::fs::{self, File};
+    use crate::storage::v1::crypt;
+    use crate::storage::v1::header::{self, ChecksumWriter};
+    #[cfg(all(feature = "io-uring", target_os = "linux"))]
+    use crate::storage::v1::uring::RingFile as FlushFile;
+    #[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+    use std::fs::File as FlushFile;
+    use std::{
+        fs,
+        io::{BufWriter, ErrorKind, Seek, SeekFrom},
+    };
 
+    /// Write the file to `cowfile_name` (with a [header](header)), sync it and then rename
+    /// it to its actual name (without the trailing `_`). A crash at any point leaves either
+    /// the older file or the newer one in place, never a partly written one. Returns the id
+    /// of the payload that was written
     #[inline(always)]
     fn cowfile(
         cowfile_name: &str,
-        with_open: impl FnOnce(&mut File) -> IoResult<()>,
-    ) -> IoResult<()> {
-        let mut f = File::create(cowfile_name)?;
-        with_open(&mut f)?;
+        with_open: impl FnOnce(&mut ChecksumWriter<&mut dyn Write>) -> IoResult<()>,
+    ) -> IoResult<PayloadId> {
+        let id = write_cowfile(cowfile_name, with_open)?;
+        commit_cowfile(cowfile_name)?;
+        Ok(id)
+    }
+
+    /// The first half of [`cowfile`]: write the file to `cowfile_name` and sync it
+    fn write_cowfile(
+        cowfile_name: &str,
+        with_open: impl FnOnce(&mut ChecksumWriter<&mut dyn Write>) -> IoResult<()>,
+    ) -> IoResult<PayloadId> {
+        let mut f = FlushFile::create(cowfile_name)?;
+        let id = if crypt::is_enabled() {
+            // the sealer holds back the first chunk, so the header can be filled in once the
+            // payload is written
+            let mut sealer = crypt::sealer(&mut f)?;
+            sealer.write_all(&[0; header::HEADER_LEN])?;
+            let mut writer = ChecksumWriter::new(&mut sealer as &mut dyn Write);
+            with_open(&mut writer)?;
+            let (header, id) = writer.finish();
+            sealer.finish(&header)?;
+            id
+        } else {
+            // leave room for the header; we'll only have the checksum once the payload is
+            // written
+            f.write_all(&[0; header::HEADER_LEN])?;
+            let mut writer = ChecksumWriter::new(&mut f as &mut dyn Write);
+            with_open(&mut writer)?;
+            let (header, id) = writer.finish();
+            f.seek(SeekFrom::Start(0))?;
+            f.write_all(&header)?;
+            id
+        };
         f.sync_all()?;
-        fs::rename(cowfile_name, &cowfile_name[..cowfile_name.len() - 1])
+        Ok(id)
+    }
+
+    /// The second half of [`cowfile`]: rename the file at `cowfile_name` to its actual name
+    fn commit_cowfile(cowfile_name: &str) -> IoResult<()> {
+        let path = &cowfile_name[..cowfile_name.len() - 1];
+        fs::rename(cowfile_name, path)?;
+        sync_parent_dir(path)
+    }
+
+    /// Sync the directory that `path` is in, so that a file that was renamed into it stays
+    /// there even if we crash
+    #[cfg(unix)]
+    fn sync_parent_dir(path: &str) -> IoResult<()> {
+        use std::path::Path;
+        let dir = match Path::new(path).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        fs::File::open(dir)?.sync_all()
     }
 
-    /// No `partmap` handling. Just flushes the table to the expected location
+    #[cfg(not(unix))]
+    fn sync_parent_dir(_path: &str) -> IoResult<()> {
+        Ok(())
+    }
+
+    /// Remove the file at `path`, if there is one
+    fn remove_if_exists(path: &str) -> IoResult<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace the file at `path` with a file that has the given payload (and a fresh header)
+    pub fn rewrite_file(path: &str, payload: &[u8]) -> IoResult<()> {
+        cowfile(&concat_str!(path, "_"), |file| file.write_all(payload))?;
+        Ok(())
+    }
+
+    /// No `partmap` handling. Just flushes the table (and the [deadlines](ttl) of its keys) to
+    /// the expected location. For [incremental](StorageTarget::INCREMENTAL) targets, tables
+    /// that keep track of their dirty keys are only written out in full once in a while;
+    /// otherwise, only their [delta](delta) is written
     pub fn flush_table<T: StorageTarget, U: FlushableTable>(
         target: &T,
         tableid: &ObjectID,
@@ -289,13 +522,105 @@ pub mod oneshot {
     ) -> IoResult<()> {
         if table.is_volatile() {
             // no flushing needed
-            Ok(())
+            return Ok(());
+        }
+        if ttl::purge_before_save() {
+            table.sweep_expired();
+        }
+        let path = unsafe { target.table_target(ksid.as_str(), tableid.as_str()) };
+        match table.dirty_keys() {
+            Some(dirty) if T::INCREMENTAL => self::flush_table_incremental(&path, table, dirty)?,
+            _ => {
+                cowfile(&path, |file| {
+                    super::interface::serialize_table_into_slow_buffer(file, table)
+                })?;
+            }
+        }
+        self::flush_expiry(&path, table.expiry())
+    }
+
+    /// Write the deadlines of the keys of the table at `path` (the **temporary file**) next
+    /// to it, or remove them if none of its keys have a TTL
+    fn flush_expiry(path: &str, expiry: Option<&Coremap<SharedSlice, u64>>) -> IoResult<()> {
+        let ttl_path = concat_str!(&path[..path.len() - 1], ttl::TTL_SUFFIX, "_");
+        match expiry {
+            Some(expiry) if expiry.len() != 0 => {
+                cowfile(&ttl_path, |file| {
+                    let mut file = BufWriter::new(file);
+                    ttl::raw_serialize_expiry(&mut file, expiry)?;
+                    file.flush()
+                })?;
+                Ok(())
+            }
+            _ => self::remove_if_exists(&ttl_path[..ttl_path.len() - 1]),
+        }
+    }
+
+    /// Write the delta of the table at `path` (the **temporary file**) if we can, or compact
+    /// the table otherwise
+    fn flush_table_incremental<U: FlushableTable>(
+        path: &str,
+        table: &U,
+        dirty: &DirtyKeys,
+    ) -> IoResult<()> {
+        let delta_path = concat_str!(&path[..path.len() - 1], delta::DELTA_SUFFIX, "_");
+        if let (Some(base), Some(keys)) = (dirty.base(), dirty.keys()) {
+            if keys.is_empty() {
+                // nothing changed since the last save
+                return Ok(());
+            }
+            if !delta::should_compact(keys.len(), table.key_count()) {
+                cowfile(&delta_path, |file| {
+                    let mut file = BufWriter::new(file);
+                    table.write_delta_to(&mut file, base, &keys)?;
+                    file.flush()
+                })?;
+                return Ok(());
+            }
+        }
+        // anything that changes from here on goes into the next delta
+        let base = dirty.base();
+        dirty.reset();
+        match self::compact_table(path, &delta_path[..delta_path.len() - 1], table, base) {
+            Ok(id) => {
+                dirty.set_base(Some(id));
+                Ok(())
+            }
+            Err(e) => {
+                // we don't know what's on disk anymore, so start over on the next save
+                dirty.set_base(None);
+                dirty.mark_all();
+                Err(e)
+            }
+        }
+    }
+
+    /// Write the table at `path` (the **temporary file**) out in full and remove its delta
+    /// (at `delta_path`). `base` is the full save that the delta (if any) applies to
+    fn compact_table<U: FlushableTable>(
+        path: &str,
+        delta_path: &str,
+        table: &U,
+        base: Option<PayloadId>,
+    ) -> IoResult<PayloadId> {
+        if base.is_none() {
+            // whatever delta is lying around isn't ours
+            self::remove_if_exists(delta_path)?;
+        }
+        let id = write_cowfile(path, |file| {
+            super::interface::serialize_table_into_slow_buffer(file, table)
+        })?;
+        if Some(id) == base {
+            // the full save on disk is the same as the new one, so the delta would still
+            // apply to it; get rid of the delta first (the full save alone is up to date)
+            self::remove_if_exists(delta_path)?;
+            commit_cowfile(path)?;
         } else {
-            let path = unsafe { target.table_target(ksid.as_str(), tableid.as_str()) };
-            cowfile(&path, |file| {
-                super::interface::serialize_table_into_slow_buffer(file, table)
-            })
+            // once the new full save is in place, the delta no longer applies to it
+            commit_cowfile(path)?;
+            self::remove_if_exists(delta_path)?;
         }
+        Ok(id)
     }
 
     /// Flushes an entire keyspace to the expected location. No `partmap` or `preload` handling
@@ -323,7 +648,8 @@ pub mod oneshot {
         let path = unsafe { target.partmap_target(ksid.as_str()) };
         cowfile(&path, |file| {
             super::interface::serialize_partmap_into_slow_buffer(file, keyspace)
-        })
+        })?;
+        Ok(())
     }
 
     // Flush the `PRELOAD`
@@ -331,6 +657,7 @@ pub mod oneshot {
         let preloadtmp = target.preload_target();
         cowfile(&preloadtmp, |file| {
             super::interface::serialize_preload_into_slow_buffer(file, store)
-        })
+        })?;
+        Ok(())
     }
 }
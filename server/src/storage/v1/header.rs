@@ -0,0 +1,149 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # File headers
+//!
+//! Every file that we flush (the `PRELOAD`, the `PARTMAP`s and the tables) starts with a
+//! header that lets us tell a complete file from one that was only partly written (or was
+//! damaged on disk):
+//! ```text
+//! [8B: Magic][4B: Format version][4B: CRC32 of the payload][8B: Payload length] => Header
+//! [Payload]
+//! ```
+//! All the integers in the header are little endian. Files written before we had headers
+//! don't have one, and are treated as version `0` (without any verification). Files in an
+//! older format are upgraded by the [migrations](super::migrate)
+
+use {
+    super::error::{StorageEngineError, StorageEngineResult},
+    crate::IoResult,
+    crc32fast::Hasher,
+    std::io::Write,
+};
+
+/// Marks a file that starts with a header
+pub const MAGIC: [u8; 8] = *b"SKYDFILE";
+/// The version of the format of the payload
+pub const FORMAT_VERSION: u32 = 2;
+/// The size of the header
+pub const HEADER_LEN: usize = 24;
+
+/// Identifies a payload by its checksum and length (as recorded in its header)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadId {
+    pub checksum: u32,
+    pub len: u64,
+}
+
+impl PayloadId {
+    pub fn of(payload: &[u8]) -> Self {
+        Self {
+            checksum: crc32fast::hash(payload),
+            len: payload.len() as u64,
+        }
+    }
+}
+
+/// Encode the header for a payload with the given checksum and length
+fn encode_header(checksum: u32, len: u64) -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..8].copy_from_slice(&MAGIC);
+    header[8..12].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+    header[12..16].copy_from_slice(&checksum.to_le_bytes());
+    header[16..].copy_from_slice(&len.to_le_bytes());
+    header
+}
+
+/// A writer that keeps track of the checksum and the length of everything that's written
+/// through it, so that the header can be written once the payload is done
+pub struct ChecksumWriter<W> {
+    inner: W,
+    hasher: Hasher,
+    len: u64,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Hasher::new(),
+            len: 0,
+        }
+    }
+    /// Returns the header for everything that was written, along with the id of the payload
+    pub fn finish(self) -> ([u8; HEADER_LEN], PayloadId) {
+        let id = PayloadId {
+            checksum: self.hasher.finalize(),
+            len: self.len,
+        };
+        (encode_header(id.checksum, id.len), id)
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.len += written as u64;
+        Ok(written)
+    }
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns the format version of a file, given (at least) the first 12 bytes of it. `None`
+/// is returned if the header is cut short
+pub fn peek_version(prefix: &[u8]) -> Option<u32> {
+    if !prefix.starts_with(&MAGIC) {
+        // written before we had headers
+        return Some(0);
+    }
+    Some(u32::from_le_bytes(prefix.get(8..12)?.try_into().unwrap()))
+}
+
+/// Verify the header of the file that was read into `data` and return the format version and
+/// the payload. `file` is only used for errors
+pub fn read_header<'a>(data: &'a [u8], file: &str) -> StorageEngineResult<(u32, &'a [u8])> {
+    if !data.starts_with(&MAGIC) {
+        // written before we had headers
+        return Ok((0, data));
+    }
+    if data.len() < HEADER_LEN {
+        return Err(StorageEngineError::CorruptedFile(file.to_owned()));
+    }
+    let (header, payload) = data.split_at(HEADER_LEN);
+    let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    if version > FORMAT_VERSION {
+        return Err(StorageEngineError::BadMetadata(file.to_owned()));
+    }
+    let checksum = u32::from_le_bytes(header[12..16].try_into().unwrap());
+    let len = u64::from_le_bytes(header[16..].try_into().unwrap());
+    if len != payload.len() as u64 || checksum != crc32fast::hash(payload) {
+        return Err(StorageEngineError::CorruptedFile(file.to_owned()));
+    }
+    Ok((version, payload))
+}
@@ -32,7 +32,11 @@ use {
     crate::{
         corestore::memstore::Memstore,
         registry,
-        storage::v1::flush::{FlushableKeyspace, FlushableTable, StorageTarget},
+        storage::v1::{
+            delta,
+            flush::{FlushableKeyspace, FlushableTable, StorageTarget},
+            ttl,
+        },
         IoResult,
     },
     core::ops::Deref,
@@ -47,8 +51,39 @@ pub const DIR_KSROOT: &str = "data/ks";
 pub const DIR_SNAPROOT: &str = "data/snaps";
 pub const DIR_RSNAPROOT: &str = "data/rsnap";
 pub const DIR_BACKUPS: &str = "data/backups";
+pub const DIR_REPLROOT: &str = "data/repl";
+pub const DIR_SPILL: &str = "data/spill";
 pub const DIR_ROOT: &str = "data";
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The location of a data directory. All of the paths above are relative to the directory
+/// that holds the data directory: `skyd` uses the one in its working directory, while an
+/// embedded database can be in any directory (and there can be more than one of them)
+pub struct DataDir {
+    /// the directory holding the data directory (`None` for the working directory)
+    base: Option<String>,
+}
+
+impl DataDir {
+    /// The data directory in the working directory
+    pub const fn current() -> Self {
+        Self { base: None }
+    }
+    /// The data directory in `base`
+    pub fn new(base: impl Into<String>) -> Self {
+        Self {
+            base: Some(base.into()),
+        }
+    }
+    /// Returns where `path` (one of the paths above) is for this data directory
+    pub fn resolve(&self, path: &str) -> String {
+        match self.base {
+            Some(ref base) => concat_str!(base, "/", path),
+            None => path.to_owned(),
+        }
+    }
+}
+
 /// Creates the directories for the keyspaces
 pub fn create_tree<T: StorageTarget>(target: &T, memroot: &Memstore) -> IoResult<()> {
     for ks in memroot.keyspaces.iter() {
@@ -60,7 +95,7 @@ pub fn create_tree<T: StorageTarget>(target: &T, memroot: &Memstore) -> IoResult
 }
 
 /// This creates the root directory structure:
-/// ```
+/// ```text
 /// data/
 ///     ks/
 ///         ks1/
@@ -71,29 +106,30 @@ pub fn create_tree<T: StorageTarget>(target: &T, memroot: &Memstore) -> IoResult
 /// ```
 ///
 /// If any directories exist, they are simply ignored
-pub fn create_tree_fresh<T: StorageTarget>(target: &T, memroot: &Memstore) -> IoResult<()> {
+pub fn create_tree_fresh(dir: &DataDir, memroot: &Memstore) -> IoResult<()> {
     try_dir_ignore_existing!(
-        DIR_ROOT,
-        DIR_KSROOT,
-        DIR_BACKUPS,
-        DIR_SNAPROOT,
-        DIR_RSNAPROOT
+        dir.resolve(DIR_ROOT),
+        dir.resolve(DIR_KSROOT),
+        dir.resolve(DIR_BACKUPS),
+        dir.resolve(DIR_SNAPROOT),
+        dir.resolve(DIR_RSNAPROOT)
     );
-    self::create_tree(target, memroot)
+    self::create_tree(dir, memroot)
 }
 
 /// Clean up the tree
 ///
 /// **Warning**: Calling this is quite inefficient so consider calling it once or twice
 /// throughout the lifecycle of the server
-pub fn cleanup_tree(memroot: &Memstore) -> IoResult<()> {
+pub fn cleanup_tree(dir: &DataDir, memroot: &Memstore) -> IoResult<()> {
     if registry::get_cleanup_tripswitch().is_tripped() {
         log::info!("We're cleaning up ...");
         // only run a cleanup if someone tripped the switch
         // hashset because the fs itself will not allow duplicate entries
         // the keyspaces directory will contain the PRELOAD file, but we'll just
         // remove it from the list
-        let mut dir_keyspaces: HashSet<String> = read_dir_to_col!(DIR_KSROOT);
+        let ksroot = dir.resolve(DIR_KSROOT);
+        let mut dir_keyspaces: HashSet<String> = read_dir_to_col!(&ksroot);
         dir_keyspaces.remove("PRELOAD");
         let our_keyspaces: HashMap<String, HashSet<String>> = memroot
             .keyspaces
@@ -116,7 +152,7 @@ pub fn cleanup_tree(memroot: &Memstore) -> IoResult<()> {
             .filter(|ksname| !our_keyspaces.contains_key(ksname.as_str()))
             .collect();
         for folder in keyspaces_to_remove {
-            let ks_path = concat_str!(DIR_KSROOT, "/", folder);
+            let ks_path = concat_str!(&ksroot, "/", folder);
             fs::remove_dir_all(ks_path)?;
         }
 
@@ -129,14 +165,21 @@ pub fn cleanup_tree(memroot: &Memstore) -> IoResult<()> {
 
         // now remove the dropped tables
         for (keyspace, tables) in our_keyspaces {
-            let ks_path = concat_str!(DIR_KSROOT, "/", keyspace.as_str());
+            let ks_path = concat_str!(&ksroot, "/", keyspace.as_str());
             // read what is present in the tables directory
             let mut dir_tbls: HashSet<String> = read_dir_to_col!(&ks_path);
             // in the list of directories we collected, remove PARTMAP because we should NOT
             // delete it
             dir_tbls.remove("PARTMAP");
-            // find what tables we should remove
-            let tables_to_remove = dir_tbls.difference(&tables);
+            // find what tables we should remove (the delta and the deadlines of a table go
+            // with the table)
+            let tables_to_remove = dir_tbls.iter().filter(|file| {
+                let table = file
+                    .strip_suffix(delta::DELTA_SUFFIX)
+                    .or_else(|| file.strip_suffix(ttl::TTL_SUFFIX))
+                    .unwrap_or(file.as_str());
+                !tables.contains(table)
+            });
             for removed_table in tables_to_remove {
                 let fpath = concat_path!(&ks_path, removed_table);
                 fs::remove_file(&fpath)?;
@@ -34,7 +34,10 @@
 */
 
 use {
-    crate::storage::v1::SharedSlice,
+    crate::storage::v1::{
+        compress::{self, ValueExtent},
+        SharedSlice,
+    },
     core::{mem, ptr, slice},
 };
 
@@ -45,6 +48,17 @@ const SIZE_128BIT: usize = SIZE_64BIT * 2;
 /// you're reading data from a BE machine, then simply set the endian to big. This only affects the first read and not
 /// subsequent ones (unless you switch between machines of different endian, obviously)
 static mut NATIVE_ENDIAN_READER: unsafe fn(*const u8) -> usize = super::de::transmute_len;
+/// Same as [`NATIVE_ENDIAN_READER`], but for the extents of values which have to be read in
+/// full (see [`compress`])
+static mut NATIVE_ENDIAN_READER_U64: unsafe fn(*const u8) -> u64 = read_u64_same_endian;
+
+unsafe fn read_u64_same_endian(start_ptr: *const u8) -> u64 {
+    ptr::read_unaligned(start_ptr.cast())
+}
+
+unsafe fn read_u64_swapped_endian(start_ptr: *const u8) -> u64 {
+    read_u64_same_endian(start_ptr).swap_bytes()
+}
 
 /// Use this to set the current endian to LE.
 ///
@@ -52,6 +66,11 @@ static mut NATIVE_ENDIAN_READER: unsafe fn(*const u8) -> usize = super::de::tran
 /// Make sure this is run from a single thread only! If not, good luck
 pub(super) unsafe fn endian_set_little() {
     NATIVE_ENDIAN_READER = super::de::transmute_len_le;
+    NATIVE_ENDIAN_READER_U64 = if cfg!(target_endian = "little") {
+        read_u64_same_endian
+    } else {
+        read_u64_swapped_endian
+    };
 }
 
 /// Use this to set the current endian to BE.
@@ -60,6 +79,11 @@ pub(super) unsafe fn endian_set_little() {
 /// Make sure this is run from a single thread only! If not, good luck
 pub(super) unsafe fn endian_set_big() {
     NATIVE_ENDIAN_READER = super::de::transmute_len_be;
+    NATIVE_ENDIAN_READER_U64 = if cfg!(target_endian = "big") {
+        read_u64_same_endian
+    } else {
+        read_u64_swapped_endian
+    };
 }
 
 /// A raw slice iterator by using raw pointers
@@ -153,6 +177,23 @@ impl<'a> RawSliceIter<'a> {
             }
         }
     }
+    /// Get the extent of the next value
+    pub fn next_value_extent(&mut self) -> Option<ValueExtent> {
+        if self.remaining() < 8 {
+            None
+        } else {
+            unsafe {
+                let extent = NATIVE_ENDIAN_READER_U64(self.cursor);
+                self.incr_cursor_by(SIZE_64BIT);
+                ValueExtent::decode(extent)
+            }
+        }
+    }
+    /// Get the next value with the provided extent, decompressing it if needed
+    pub fn next_value(&mut self, extent: ValueExtent) -> Option<SharedSlice> {
+        let payload = self.next_borrowed_slice(extent.len)?;
+        compress::decode_value(extent, payload)
+    }
     /// Get the next 8-bit unsigned integer
     pub fn next_8bit_integer(&mut self) -> Option<u8> {
         if self.exhausted() {
@@ -228,6 +269,30 @@ impl<'a> RawSliceIterBorrowed<'a> {
             }
         }
     }
+    /// Get the extent of the next value
+    pub fn next_value_extent(&mut self) -> Option<ValueExtent> {
+        if self.remaining() < 8 {
+            None
+        } else {
+            unsafe {
+                let extent = NATIVE_ENDIAN_READER_U64(self.cursor);
+                self.incr_cursor_by(SIZE_64BIT);
+                ValueExtent::decode(extent)
+            }
+        }
+    }
+    /// Get the next value with the provided extent, decompressing it if needed
+    pub fn next_value(&mut self, extent: ValueExtent) -> Option<SharedSlice> {
+        if self.remaining() < extent.len {
+            None
+        } else {
+            unsafe {
+                let payload = slice::from_raw_parts(self.cursor, extent.len);
+                self.incr_cursor_by(extent.len);
+                compress::decode_value(extent, payload)
+            }
+        }
+    }
 }
 
 impl<'a> Drop for RawSliceIterBorrowed<'a> {
@@ -116,6 +116,6 @@ macro_rules! read_dir_to_col {
 macro_rules! lvec {
     ($($item:expr),+ $(,)?) => {{
         let v = std::vec![$($item.into()),*];
-        parking_lot::RwLock::new(v)
+        parking_lot::RwLock::new(std::collections::VecDeque::from(v))
     }};
 }
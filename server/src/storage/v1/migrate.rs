@@ -0,0 +1,182 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Format migrations
+//!
+//! Every flushed file records the version of the format that it was written in (see
+//! [`header`]). When a file in an older format is loaded, its payload is upgraded by running
+//! it through every migration after its version, in order. On startup, the files in the data
+//! directory that are in an older format are also upgraded in place (after the directory has
+//! been backed up), so that we don't have to carry the older formats around forever.
+//!
+//! To change the format of a file: bump [`header::FORMAT_VERSION`], and add a migration from
+//! the previous version to [`MIGRATIONS`]. Keep in mind that a [delta](delta) only applies to
+//! the exact payload of the table that it was written for
+
+use {
+    super::{
+        crypt::{self, OpenError},
+        delta,
+        error::{ErrorContext, StorageEngineError, StorageEngineResult},
+        flush, header, ttl,
+    },
+    crate::util::os,
+    chrono::prelude::Utc,
+    std::{borrow::Cow, fs, io::Read},
+};
+
+/// The kinds of files in a tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Preload,
+    Partmap,
+    Table,
+    Delta,
+    Ttl,
+}
+
+/// A migration upgrades the payload of a file to the next version (returning `None` if the
+/// payload isn't valid in the older format)
+type Migration = fn(FileKind, Vec<u8>) -> Option<Vec<u8>>;
+
+/// `MIGRATIONS[n]` upgrades a payload from version `n` to version `n + 1`
+const MIGRATIONS: [Migration; header::FORMAT_VERSION as usize] = [v0_to_v1, v1_to_v2];
+
+/// Version 1 only added the header, so the payload stays the same
+fn v0_to_v1(_kind: FileKind, payload: Vec<u8>) -> Option<Vec<u8>> {
+    Some(payload)
+}
+
+/// Version 2 added [compressed values](super::compress). The extents of the values in older
+/// payloads never have the compression bit set, so the payload stays the same
+fn v1_to_v2(_kind: FileKind, payload: Vec<u8>) -> Option<Vec<u8>> {
+    Some(payload)
+}
+
+/// Decrypt the [encrypted](crypt) file that was read into `data`. `file` is only used for
+/// errors
+fn decrypt(data: &[u8], file: &str) -> StorageEngineResult<Vec<u8>> {
+    crypt::open(data).map_err(|e| match e {
+        OpenError::MissingKey(id) => StorageEngineError::MissingKey(file.to_owned(), id),
+        OpenError::Corrupted => StorageEngineError::CorruptedFile(file.to_owned()),
+    })
+}
+
+/// Verify (and decrypt, if needed) the file that was read into `data` and return its payload
+/// in the current format. `file` is only used for errors
+pub fn read_payload<'a>(
+    data: &'a [u8],
+    kind: FileKind,
+    file: &str,
+) -> StorageEngineResult<Cow<'a, [u8]>> {
+    if crypt::is_encrypted(data) {
+        let data = self::decrypt(data, file)?;
+        return self::read_payload(&data, kind, file)
+            .map(|payload| Cow::Owned(payload.into_owned()));
+    }
+    let (version, payload) = header::read_header(data, file)?;
+    if version == header::FORMAT_VERSION {
+        return Ok(Cow::Borrowed(payload));
+    }
+    MIGRATIONS[version as usize..]
+        .iter()
+        .try_fold(payload.to_vec(), |payload, migrate| migrate(kind, payload))
+        .map(Cow::Owned)
+        .ok_or_else(|| StorageEngineError::CorruptedFile(file.to_owned()))
+}
+
+/// Returns the format version of the file at `path`
+fn version_of(path: &str) -> StorageEngineResult<u32> {
+    let mut prefix = Vec::with_capacity(12);
+    fs::File::open(path)
+        .and_then(|file| file.take(12).read_to_end(&mut prefix))
+        .map_err_context(format!("reading {path}"))?;
+    if crypt::is_encrypted(&prefix) {
+        // the header is encrypted along with the rest of the file
+        let data = fs::read(path).map_err_context(format!("reading {path}"))?;
+        prefix = self::decrypt(&data, path)?;
+    }
+    header::peek_version(&prefix).ok_or_else(|| StorageEngineError::CorruptedFile(path.to_owned()))
+}
+
+/// Returns the files in the tree at `root` that are in an older format
+fn outdated_files(root: &str) -> StorageEngineResult<Vec<(String, FileKind)>> {
+    let mut files = vec![(concat_str!(root, "/PRELOAD"), FileKind::Preload)];
+    for keyspace in fs::read_dir(root).map_err_context(format!("listing {root}"))? {
+        let keyspace = keyspace?;
+        if !keyspace.file_type()?.is_dir() {
+            continue;
+        }
+        let ks_path = keyspace.path();
+        for file in fs::read_dir(&ks_path).map_err_context("listing a keyspace")? {
+            let file = file?;
+            let name = file.file_name().to_string_lossy().into_owned();
+            if name.ends_with('_') {
+                // a flush that never completed
+                continue;
+            }
+            let kind = match name.as_str() {
+                "PARTMAP" => FileKind::Partmap,
+                _ if name.ends_with(delta::DELTA_SUFFIX) => FileKind::Delta,
+                _ if name.ends_with(ttl::TTL_SUFFIX) => FileKind::Ttl,
+                _ => FileKind::Table,
+            };
+            files.push((file.path().to_string_lossy().into_owned(), kind));
+        }
+    }
+    let mut outdated = Vec::new();
+    for (path, kind) in files {
+        if self::version_of(&path)? < header::FORMAT_VERSION {
+            outdated.push((path, kind));
+        }
+    }
+    Ok(outdated)
+}
+
+/// Upgrade the files in the tree at `root` that are in an older format. The tree is copied
+/// into a new directory in `backup_root` before anything is changed. Returns the number of
+/// files that were upgraded
+pub fn migrate_tree(root: &str, backup_root: &str) -> StorageEngineResult<usize> {
+    let outdated = self::outdated_files(root)?;
+    if outdated.is_empty() {
+        return Ok(0);
+    }
+    let timestamp = Utc::now().format("%Y%m%d-%H%M%S").to_string();
+    let backup = concat_str!(backup_root, "/migration-", &timestamp);
+    log::info!(
+        "Upgrading {} files to format version {}. Backing up the data to {backup}",
+        outdated.len(),
+        header::FORMAT_VERSION
+    );
+    os::recursive_copy(root, &backup).map_err_context("backing up the data")?;
+    for (path, kind) in outdated.iter() {
+        let data = fs::read(path).map_err_context(format!("reading {path}"))?;
+        let payload = self::read_payload(&data, *kind, path)?;
+        flush::oneshot::rewrite_file(path, &payload)
+            .map_err_context(format!("upgrading {path}"))?;
+    }
+    Ok(outdated.len())
+}
@@ -61,14 +61,23 @@ use {
 #[macro_use]
 mod macros;
 // endof do not mess
+pub mod aof;
 pub mod bytemarks;
+pub mod compress;
+pub mod crypt;
+pub mod delta;
 pub mod error;
 pub mod flush;
+pub mod header;
 pub mod interface;
 pub mod iter;
+pub mod migrate;
 pub mod preload;
 pub mod sengine;
+pub mod ttl;
 pub mod unflush;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+mod uring;
 // test
 #[cfg(test)]
 mod tests;
@@ -184,7 +193,8 @@ unsafe fn raw_byte_repr<'a, T: 'a>(len: &'a T) -> &'a [u8] {
 
 mod se {
     use super::*;
-    use crate::kvengine::LockedVec;
+    use crate::kvengine::{LockedHash, LockedSet, LockedVec, LockedZSet};
+    use crate::storage::v1::compress;
     use crate::storage::v1::flush::FlushableKeyspace;
     use crate::storage::v1::flush::FlushableTable;
     use crate::IoResult;
@@ -208,21 +218,55 @@ mod se {
         Ok(w)
     }
 
-    /// Serialize a map and write it to a provided buffer
+    /// Serialize a map and write it to a provided buffer. The values are compressed if value
+    /// compression is enabled (see [`compress`])
+    ///
+    /// The map is first copied into a point-in-time snapshot (which is cheap since we only
+    /// clone the handles) and the snapshot is then written out, so that no shard is locked
+    /// while we're waiting on the writer
     pub fn raw_serialize_map<W: Write, T: AsRef<[u8]>, U: AsRef<[u8]>>(
         map: &Coremap<T, U>,
         w: &mut W,
     ) -> IoResult<()>
     where
         W: Write,
-        T: AsRef<[u8]> + Hash + Eq,
-        U: AsRef<[u8]>,
+        T: AsRef<[u8]> + Hash + Eq + Clone,
+        U: AsRef<[u8]> + Clone,
     {
+        let snapshot = map.snapshot_with(|k, v| (k.clone(), v.clone()));
         unsafe {
-            w.write_all(raw_byte_repr(&to_64bit_native_endian!(map.len())))?;
+            w.write_all(raw_byte_repr(&to_64bit_native_endian!(snapshot.len())))?;
             // now the keys and values
-            for kv in map.iter() {
-                let (k, v) = (kv.key(), kv.value());
+            for (k, v) in snapshot.iter() {
+                let kref = k.as_ref();
+                let (vextent, vref) = compress::encode_value(v.as_ref())?;
+                w.write_all(raw_byte_repr(&to_64bit_native_endian!(kref.len())))?;
+                w.write_all(raw_byte_repr(&vextent))?;
+                w.write_all(kref)?;
+                w.write_all(&vref)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Serialize a map whose values have to be encoded first (using `encode`) and write it to
+    /// a provided buffer. The encoded values are read back by their own decoders, so they're
+    /// never compressed
+    pub fn raw_serialize_map_with<W, T, U, E>(
+        map: &Coremap<T, U>,
+        w: &mut W,
+        encode: impl Fn(&U) -> E,
+    ) -> IoResult<()>
+    where
+        W: Write,
+        T: AsRef<[u8]> + Hash + Eq + Clone,
+        E: AsRef<[u8]>,
+    {
+        let snapshot = map.snapshot_with(|k, v| (k.clone(), encode(v)));
+        unsafe {
+            w.write_all(raw_byte_repr(&to_64bit_native_endian!(snapshot.len())))?;
+            // now the keys and values
+            for (k, v) in snapshot.iter() {
                 let kref = k.as_ref();
                 let vref = v.as_ref();
                 w.write_all(raw_byte_repr(&to_64bit_native_endian!(kref.len())))?;
@@ -291,16 +335,16 @@ mod se {
         /*
         [8B: Extent]([8B: Key extent][?B: Key][8B: Max index][?B: Payload])*
         */
+        // same as with maps: snapshot first so that we don't hold any locks while writing
+        let snapshot = data.snapshot_with(|k, v| {
+            let list: Vec<SharedSlice> = v.read().iter().cloned().collect();
+            (k.clone(), list)
+        });
         unsafe {
             // Extent
-            w.write_all(unsafe_sz_byte_repr!(data.len()))?;
+            w.write_all(unsafe_sz_byte_repr!(snapshot.len()))?;
             // Enter iter
-            '_1: for key in data.iter() {
-                // key
-                let k = key.key();
-                // list payload
-                let vread = key.value().read();
-                let v: &Vec<SharedSlice> = &vread;
+            '_1: for (k, v) in snapshot.iter() {
                 // write the key extent
                 w.write_all(unsafe_sz_byte_repr!(k.len()))?;
                 // write the key
@@ -311,7 +355,107 @@ mod se {
         }
         Ok(())
     }
-    /// Serialize a `[[u8]]` (i.e a slice of slices)
+    /// Serialize a set map. Sets are written out exactly like lists
+    pub fn raw_serialize_set_map<W>(
+        data: &Coremap<SharedSlice, LockedSet>,
+        w: &mut W,
+    ) -> IoResult<()>
+    where
+        W: Write,
+    {
+        /*
+        [8B: Extent]([8B: Key extent][?B: Key][8B: Max index][?B: Payload])*
+        */
+        let snapshot = data.snapshot_with(|k, v| {
+            let members: Vec<SharedSlice> = v.read().iter().cloned().collect();
+            (k.clone(), members)
+        });
+        unsafe {
+            // Extent
+            w.write_all(unsafe_sz_byte_repr!(snapshot.len()))?;
+            for (k, v) in snapshot.iter() {
+                // write the key extent
+                w.write_all(unsafe_sz_byte_repr!(k.len()))?;
+                // write the key
+                w.write_all(k)?;
+                // write the set payload
+                self::raw_serialize_nested_list(w, &v)?;
+            }
+        }
+        Ok(())
+    }
+    /// Serialize a hash map. Every hash is written out as a list of alternating fields and
+    /// values
+    pub fn raw_serialize_hash_map<W>(
+        data: &Coremap<SharedSlice, LockedHash>,
+        w: &mut W,
+    ) -> IoResult<()>
+    where
+        W: Write,
+    {
+        /*
+        [8B: Extent]([8B: Key extent][?B: Key][8B: Max index][?B: Payload])*
+        */
+        let snapshot = data.snapshot_with(|k, v| {
+            let pairs: Vec<SharedSlice> = v
+                .read()
+                .iter()
+                .flat_map(|(field, value)| [field.clone(), value.clone()])
+                .collect();
+            (k.clone(), pairs)
+        });
+        unsafe {
+            // Extent
+            w.write_all(unsafe_sz_byte_repr!(snapshot.len()))?;
+            for (k, v) in snapshot.iter() {
+                // write the key extent
+                w.write_all(unsafe_sz_byte_repr!(k.len()))?;
+                // write the key
+                w.write_all(k)?;
+                // write the hash payload
+                self::raw_serialize_nested_list(w, &v)?;
+            }
+        }
+        Ok(())
+    }
+    /// Serialize a sorted set map. Every sorted set is written out as a list of alternating
+    /// members and scores (as 8 byte little endian floats)
+    pub fn raw_serialize_zset_map<W>(
+        data: &Coremap<SharedSlice, LockedZSet>,
+        w: &mut W,
+    ) -> IoResult<()>
+    where
+        W: Write,
+    {
+        /*
+        [8B: Extent]([8B: Key extent][?B: Key][8B: Max index][?B: Payload])*
+        */
+        let snapshot = data.snapshot_with(|k, v| {
+            let pairs: Vec<SharedSlice> = v
+                .read()
+                .iter()
+                .flat_map(|(member, score)| {
+                    [member.clone(), SharedSlice::new(&score.get().to_le_bytes())]
+                })
+                .collect();
+            (k.clone(), pairs)
+        });
+        unsafe {
+            // Extent
+            w.write_all(unsafe_sz_byte_repr!(snapshot.len()))?;
+            for (k, v) in snapshot.iter() {
+                // write the key extent
+                w.write_all(unsafe_sz_byte_repr!(k.len()))?;
+                // write the key
+                w.write_all(k)?;
+                // write the sorted set payload
+                self::raw_serialize_nested_list(w, &v)?;
+            }
+        }
+        Ok(())
+    }
+    /// Serialize a `[[u8]]` (i.e a slice of slices). The elements are compressed if value
+    /// compression is enabled (see [`compress`])
     pub fn raw_serialize_nested_list<'a, W, T: 'a + ?Sized, U: 'a>(
         w: &mut W,
         inp: &'a T,
@@ -328,11 +472,11 @@ mod se {
             w.write_all(unsafe_sz_byte_repr!(inp.len()))?;
             // now enter loop and write elements
             for element in inp.iter() {
-                let element = element.as_ref();
+                let (extent, element) = compress::encode_value(element.as_ref())?;
                 // write element extent
-                w.write_all(unsafe_sz_byte_repr!(element.len()))?;
+                w.write_all(raw_byte_repr(&extent))?;
                 // write element
-                w.write_all(element)?;
+                w.write_all(&element)?;
             }
         }
         Ok(())
@@ -342,14 +486,17 @@ mod se {
 mod de {
     use super::iter::{RawSliceIter, RawSliceIterBorrowed};
     use super::{Array, Coremap, Hash, HashSet, SharedSlice};
-    use crate::kvengine::LockedVec;
+    use crate::auth::provider::{UserRecord, AUTHKEY_SIZE};
+    use crate::corestore::spill;
+    use crate::corestore::zset::{Score, SortedSet};
+    use crate::kvengine::{LockedHash, LockedSet, LockedVec, LockedZSet};
     use core::ptr;
     use parking_lot::RwLock;
     use std::collections::HashMap;
 
-    pub trait DeserializeFrom {
+    pub trait DeserializeFrom: Sized {
         fn is_expected_len(clen: usize) -> bool;
-        fn from_slice(slice: &[u8]) -> Self;
+        fn from_slice(slice: &[u8]) -> Option<Self>;
     }
 
     pub trait DeserializeInto: Sized {
@@ -375,6 +522,33 @@ mod de {
         }
     }
 
+    impl DeserializeInto for Coremap<SharedSlice, LockedSet> {
+        fn new_empty() -> Self {
+            Coremap::new()
+        }
+        fn from_slice(slice: &[u8]) -> Option<Self> {
+            self::deserialize_set_map(slice)
+        }
+    }
+
+    impl DeserializeInto for Coremap<SharedSlice, LockedHash> {
+        fn new_empty() -> Self {
+            Coremap::new()
+        }
+        fn from_slice(slice: &[u8]) -> Option<Self> {
+            self::deserialize_hash_map(slice)
+        }
+    }
+
+    impl DeserializeInto for Coremap<SharedSlice, LockedZSet> {
+        fn new_empty() -> Self {
+            Coremap::new()
+        }
+        fn from_slice(slice: &[u8]) -> Option<Self> {
+            self::deserialize_zset_map(slice)
+        }
+    }
+
     impl<T, U> DeserializeInto for Coremap<T, U>
     where
         T: Hash + Eq + DeserializeFrom,
@@ -396,8 +570,8 @@ mod de {
         fn is_expected_len(clen: usize) -> bool {
             clen <= N
         }
-        fn from_slice(slice: &[u8]) -> Self {
-            unsafe { Self::from_slice(slice) }
+        fn from_slice(slice: &[u8]) -> Option<Self> {
+            Some(unsafe { Self::from_slice(slice) })
         }
     }
 
@@ -405,8 +579,18 @@ mod de {
         fn is_expected_len(clen: usize) -> bool {
             clen == N
         }
-        fn from_slice(slice: &[u8]) -> Self {
-            slice.try_into().unwrap()
+        fn from_slice(slice: &[u8]) -> Option<Self> {
+            Some(slice.try_into().unwrap())
+        }
+    }
+
+    impl DeserializeFrom for UserRecord {
+        fn is_expected_len(clen: usize) -> bool {
+            // users from older versions only have their key stored
+            clen >= AUTHKEY_SIZE
+        }
+        fn from_slice(slice: &[u8]) -> Option<Self> {
+            UserRecord::decode(slice)
         }
     }
 
@@ -423,8 +607,8 @@ mod de {
             if !(T::is_expected_len(lenkey) && U::is_expected_len(lenval)) {
                 return None;
             }
-            let key = T::from_slice(rawiter.next_borrowed_slice(lenkey)?);
-            let value = U::from_slice(rawiter.next_borrowed_slice(lenval)?);
+            let key = T::from_slice(rawiter.next_borrowed_slice(lenkey)?)?;
+            let value = U::from_slice(rawiter.next_borrowed_slice(lenval)?)?;
             if !map.true_if_insert(key, value) {
                 // duplicates
                 return None;
@@ -448,7 +632,7 @@ mod de {
                 return None;
             }
             // get the key as a raw slice, we've already checked if end_ptr is less
-            let key = T::from_slice(rawiter.next_borrowed_slice(lenkey)?);
+            let key = T::from_slice(rawiter.next_borrowed_slice(lenkey)?)?;
             // push it in
             if !set.insert(key) {
                 // repeat?; that's not what we wanted
@@ -479,7 +663,7 @@ mod de {
                 return None;
             }
             // get the key as a raw slice, we've already checked if end_ptr is less
-            let key = T::from_slice(rawiter.next_borrowed_slice(lenkey)?);
+            let key = T::from_slice(rawiter.next_borrowed_slice(lenkey)?)?;
             let bytemark_a = rawiter.next_8bit_integer()?;
             let bytemark_b = rawiter.next_8bit_integer()?;
             // push it in
@@ -501,11 +685,12 @@ mod de {
         let len = rawiter.next_64bit_integer_to_usize()?;
         let hm = Coremap::try_with_capacity(len).ok()?;
         for _ in 0..len {
-            let (lenkey, lenval) = rawiter.next_64bit_integer_pair_to_usize()?;
+            let lenkey = rawiter.next_64bit_integer_to_usize()?;
+            let extent = rawiter.next_value_extent()?;
             let key = rawiter.next_owned_data(lenkey)?;
-            let val = rawiter.next_owned_data(lenval)?;
-            // push it in
-            hm.upsert(key, val);
+            let val = rawiter.next_value(extent)?;
+            // push it in (large values are spilled right away)
+            hm.upsert(key, spill::offload(val));
         }
         if rawiter.end_of_allocation() {
             Some(hm)
@@ -529,7 +714,86 @@ mod de {
             let borrowed_iter = rawiter.get_borrowed_iter();
             let list = self::deserialize_nested_list(borrowed_iter)?;
             // push it in
-            map.true_if_insert(key, RwLock::new(list));
+            map.true_if_insert(key, RwLock::new(list.into()));
+        }
+        if rawiter.end_of_allocation() {
+            Some(map)
+        } else {
+            // someone returned more data
+            None
+        }
+    }
+
+    pub fn deserialize_set_map(bytes: &[u8]) -> Option<Coremap<SharedSlice, LockedSet>> {
+        let mut rawiter = RawSliceIter::new(bytes);
+        let len = rawiter.next_64bit_integer_to_usize()?;
+        let map = Coremap::try_with_capacity(len).ok()?;
+        for _ in 0..len {
+            let keylen = rawiter.next_64bit_integer_to_usize()?;
+            let key = rawiter.next_owned_data(keylen)?;
+            let borrowed_iter = rawiter.get_borrowed_iter();
+            // sets are stored just like lists
+            let set: HashSet<SharedSlice> = self::deserialize_nested_list(borrowed_iter)?
+                .into_iter()
+                .collect();
+            map.true_if_insert(key, RwLock::new(set));
+        }
+        if rawiter.end_of_allocation() {
+            Some(map)
+        } else {
+            // someone returned more data
+            None
+        }
+    }
+
+    pub fn deserialize_hash_map(bytes: &[u8]) -> Option<Coremap<SharedSlice, LockedHash>> {
+        let mut rawiter = RawSliceIter::new(bytes);
+        let len = rawiter.next_64bit_integer_to_usize()?;
+        let map = Coremap::try_with_capacity(len).ok()?;
+        for _ in 0..len {
+            let keylen = rawiter.next_64bit_integer_to_usize()?;
+            let key = rawiter.next_owned_data(keylen)?;
+            let borrowed_iter = rawiter.get_borrowed_iter();
+            // hashes are stored as a list of alternating fields and values
+            let pairs = self::deserialize_nested_list(borrowed_iter)?;
+            if pairs.len() % 2 != 0 {
+                return None;
+            }
+            let mut pairs = pairs.into_iter();
+            let mut hash = HashMap::new();
+            while let (Some(field), Some(value)) = (pairs.next(), pairs.next()) {
+                hash.insert(field, value);
+            }
+            map.true_if_insert(key, RwLock::new(hash));
+        }
+        if rawiter.end_of_allocation() {
+            Some(map)
+        } else {
+            // someone returned more data
+            None
+        }
+    }
+
+    pub fn deserialize_zset_map(bytes: &[u8]) -> Option<Coremap<SharedSlice, LockedZSet>> {
+        let mut rawiter = RawSliceIter::new(bytes);
+        let len = rawiter.next_64bit_integer_to_usize()?;
+        let map = Coremap::try_with_capacity(len).ok()?;
+        for _ in 0..len {
+            let keylen = rawiter.next_64bit_integer_to_usize()?;
+            let key = rawiter.next_owned_data(keylen)?;
+            let borrowed_iter = rawiter.get_borrowed_iter();
+            // sorted sets are stored as a list of alternating members and scores
+            let pairs = self::deserialize_nested_list(borrowed_iter)?;
+            if pairs.len() % 2 != 0 {
+                return None;
+            }
+            let mut pairs = pairs.into_iter();
+            let mut zset = SortedSet::new();
+            while let (Some(member), Some(score)) = (pairs.next(), pairs.next()) {
+                let score = Score::new(f64::from_le_bytes(score.as_ref().try_into().ok()?))?;
+                zset.insert(member, score);
+            }
+            map.true_if_insert(key, RwLock::new(zset));
         }
         if rawiter.end_of_allocation() {
             Some(map)
@@ -548,9 +812,9 @@ mod de {
         list.try_reserve(list_payload_extent).ok()?;
         for _ in 0..list_payload_extent {
             // get element size
-            let list_element_payload_size = iter.next_64bit_integer_to_usize()?;
+            let extent = iter.next_value_extent()?;
             // now get element
-            let element = iter.next_owned_data(list_element_payload_size)?;
+            let element = iter.next_value(extent)?;
             list.push(element);
         }
         Some(list)
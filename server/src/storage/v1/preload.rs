@@ -73,7 +73,7 @@ pub(super) fn raw_generate_preload<W: Write>(w: &mut W, store: &Memstore) -> IoR
 }
 
 /// Reads the preload file and returns a set
-pub(super) fn read_preload_raw(preload: Vec<u8>) -> StorageEngineResult<HashSet<ObjectID>> {
+pub(super) fn read_preload_raw(preload: &[u8]) -> StorageEngineResult<HashSet<ObjectID>> {
     if preload.len() < 16 {
         // nah, this is a bad disk file
         return Err(StorageEngineError::corrupted_preload());
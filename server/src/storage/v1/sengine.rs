@@ -29,9 +29,13 @@ use {
     super::interface::{DIR_RSNAPROOT, DIR_SNAPROOT},
     crate::{
         corestore::{iarray::IArray, lazy::Lazy, lock::QuickLock, memstore::Memstore},
-        storage::v1::flush::{LocalSnapshot, RemoteSnapshot},
+        storage::v1::{
+            aof,
+            flush::{LocalSnapshot, RemoteSnapshot},
+            unflush::read_full_from,
+        },
     },
-    chrono::prelude::Utc,
+    chrono::{prelude::Utc, NaiveDateTime},
     core::{fmt, str},
     regex::Regex,
     std::{collections::HashSet, fs, io::Error as IoError, path::Path, sync::Arc},
@@ -48,6 +52,30 @@ pub static SNAP_MATCH: Lazy<Regex, fn() -> Regex> = Lazy::new(|| {
     Regex::new("^\\d{4}(0[1-9]|1[012])(0[1-9]|[12][0-9]|3[01])(-)(?:(?:([01]?\\d|2[0-3]))?([0-5]?\\d))?([0-5]?\\d)$").unwrap()
 });
 
+/// Returns the time (in milliseconds since the epoch) at which the local snapshot with the
+/// given name was started. Every write that was recorded before this is in the snapshot
+pub fn snapshot_time(name: &str) -> Option<u64> {
+    NaiveDateTime::parse_from_str(name, "%Y%m%d-%H%M%S")
+        .ok()
+        .map(|time| time.timestamp_millis() as u64)
+}
+
+/// Returns the names of the local snapshots on disk along with the time at which they were
+/// started, oldest first. This doesn't need a snapshot engine, so it can be used on startup
+pub fn local_snapshots() -> Result<Vec<(u64, String)>, IoError> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(DIR_SNAPROOT)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if SNAP_MATCH.is_match(&name) {
+            if let Some(time) = snapshot_time(&name) {
+                snapshots.push((time, name));
+            }
+        }
+    }
+    snapshots.sort_unstable();
+    Ok(snapshots)
+}
+
 #[derive(Debug)]
 pub enum SnapshotEngineError {
     Io(IoError),
@@ -99,6 +127,8 @@ pub enum SnapshotActionResult {
     Disabled,
     Failure,
     AlreadyExists,
+    NotFound,
+    Replicated,
 }
 
 impl SnapshotEngine {
@@ -219,6 +249,20 @@ impl SnapshotEngine {
                 .await
                 .expect("mksnap thread panicked");
             }
+            // the archived logs from before the oldest snapshot can't be used for a recovery
+            if let Some(cutoff) = queue
+                .list()
+                .first()
+                .and_then(|oldest| snapshot_time(oldest))
+            {
+                tokio::task::spawn_blocking(move || match aof::prune_archive(cutoff) {
+                    Ok(0) => {}
+                    Ok(pruned) => log::info!("Pruned {} archived logs", pruned),
+                    Err(e) => log::warn!("Failed to prune the archived logs (ignored): {}", e),
+                })
+                .await
+                .expect("mksnap thread panicked");
+            }
             drop(queue);
             SnapshotActionResult::Ok
         } else {
@@ -258,6 +302,63 @@ impl SnapshotEngine {
             ret
         }
     }
+    /// Returns the names of all the local snapshots (oldest first) followed by the names of
+    /// all the remote snapshots
+    pub fn list_snapshots(&self) -> Vec<String> {
+        let mut snapshots = self.local_queue.lock().list();
+        snapshots.extend(
+            self.remote_queue
+                .lock()
+                .iter()
+                .map(|rsnap| String::from_utf8_lossy(rsnap).to_string()),
+        );
+        snapshots
+    }
+    /// Spawns a blocking task to read the local or remote snapshot with the given name and
+    /// hand it to `apply`, which restores it without restarting the server. Returns either
+    /// whatever `apply` returns or:
+    /// - `Failure` => Error
+    /// - `Busy` => Busy
+    /// - `NotFound` => No such snapshot
+    pub async fn restore(
+        &self,
+        name: &[u8],
+        apply: impl FnOnce(Memstore) -> SnapshotActionResult + Send + 'static,
+    ) -> SnapshotActionResult {
+        // hold both the locks so that no snapshot is created or removed while we're reading one
+        let local_queue = match self.local_queue.try_lock() {
+            Some(lck) => lck,
+            None => return SnapshotActionResult::Busy,
+        };
+        let remote_queue = match self.remote_queue.try_lock() {
+            Some(q) => q,
+            None => return SnapshotActionResult::Busy,
+        };
+        let name_str = match str::from_utf8(name) {
+            Ok(name) => name,
+            Err(_) => return SnapshotActionResult::NotFound,
+        };
+        // local snapshots take precedence in the (rare) case of a name clash
+        let root = if local_queue.contains(name_str) {
+            format!("{DIR_SNAPROOT}/{name_str}")
+        } else if remote_queue.contains(name) {
+            format!("{DIR_RSNAPROOT}/{name_str}")
+        } else {
+            return SnapshotActionResult::NotFound;
+        };
+        let ret = tokio::task::spawn_blocking(move || match read_full_from(&root) {
+            Ok(snapshot) => apply(snapshot),
+            Err(e) => {
+                log::error!("Failed to restore snapshot with error: {}", e);
+                SnapshotActionResult::Failure
+            }
+        })
+        .await
+        .expect("restore thread panicked");
+        drop(remote_queue);
+        drop(local_queue);
+        ret
+    }
 }
 
 mod queue {
@@ -321,6 +422,14 @@ mod queue {
         pub fn pop_last(&mut self) -> Option<String> {
             self.queue.pop()
         }
+        /// Check if the queue has the given item
+        pub fn contains(&self, item: &str) -> bool {
+            self.queue.iter().any(|queued| queued == item)
+        }
+        /// Returns all the items in the queue, oldest first
+        pub fn list(&self) -> Vec<String> {
+            self.queue.to_vec()
+        }
     }
 
     #[test]
@@ -340,6 +449,20 @@ mod queue {
         );
     }
 
+    #[test]
+    fn test_queue_contains_and_list() {
+        let mut q = Queue::new(2, false);
+        assert!(q.add_new(String::from("snap1")).is_none());
+        assert!(q.add_new(String::from("snap2")).is_none());
+        assert!(q.contains("snap1"));
+        assert_eq!(
+            q.add_new(String::from("snap3")),
+            Some(String::from("snap1"))
+        );
+        assert!(!q.contains("snap1"));
+        assert_eq!(q.list(), vec![String::from("snap2"), String::from("snap3")]);
+    }
+
     #[test]
     fn test_queue_dontpop() {
         // This means that items can only be added or all of them can be deleted
@@ -352,3 +475,10 @@ mod queue {
         assert!(q.add_new(String::from("snap6")).is_none());
     }
 }
+
+#[test]
+fn test_snapshot_time() {
+    assert_eq!(snapshot_time("20260101-000000"), Some(1_767_225_600_000));
+    assert_eq!(snapshot_time("20260101-000001"), Some(1_767_225_601_000));
+    assert_eq!(snapshot_time("mysnapshot"), None);
+}
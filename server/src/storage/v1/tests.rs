@@ -66,6 +66,64 @@ fn test_ser_de_few_elements() {
         .all(|kv| cmap.get(kv.key()).unwrap().eq(kv.value())));
 }
 
+#[test]
+fn test_ser_de_authmap() {
+    use crate::auth::{
+        provider::{AuthID, UserRecord},
+        Permissions,
+    };
+    let authmap: Coremap<AuthID, UserRecord> = Coremap::new();
+    authmap.upsert(
+        AuthID::try_from_slice("reader").unwrap(),
+        UserRecord::new(Box::new([1; 97]), Permissions::READ),
+    );
+    authmap.upsert(
+        AuthID::try_from_slice("admin").unwrap(),
+        UserRecord::claimed(Box::new([2; 97]), Permissions::ALL),
+    );
+    // a user whose key was regenerated, and whose old key is still in its grace period
+    let mut rotated = UserRecord::new(Box::new([4; 97]), Permissions::DEFAULT);
+    rotated.rotate(
+        Box::new([5; 97]),
+        Some(std::time::Duration::from_secs(3600)),
+    );
+    authmap.upsert(AuthID::try_from_slice("rotated").unwrap(), rotated);
+    // a user that can only use the `tenant` keyspace
+    let mut restricted = vec![40];
+    restricted.extend([3; 40]);
+    restricted.extend([Permissions::DEFAULT.raw(), 1, 6]);
+    restricted.extend(b"tenant");
+    authmap.upsert(
+        AuthID::try_from_slice("tenant").unwrap(),
+        UserRecord::decode(&restricted).unwrap(),
+    );
+    let mut ser = Vec::new();
+    se::raw_serialize_map_with(&authmap, &mut ser, UserRecord::encode).unwrap();
+    let de: Coremap<AuthID, UserRecord> = de::deserialize_into(&ser).unwrap();
+    assert_eq!(de.len(), authmap.len());
+    assert!(de
+        .iter()
+        .all(|kv| authmap.get(kv.key()).unwrap().eq(kv.value())));
+}
+
+#[test]
+fn test_de_authmap_legacy_key() {
+    use crate::auth::{
+        provider::{AuthID, UserRecord},
+        Permissions,
+    };
+    // users stored by older versions only have their (rcrypt) key
+    let authmap: Coremap<AuthID, [u8; 40]> = Coremap::new();
+    authmap.upsert(AuthID::try_from_slice("sayan").unwrap(), [1; 40]);
+    let mut ser = Vec::new();
+    se::raw_serialize_map(&authmap, &mut ser).unwrap();
+    let de: Coremap<AuthID, UserRecord> = de::deserialize_into(&ser).unwrap();
+    assert_eq!(
+        de.get("sayan".as_bytes()).unwrap().value(),
+        &UserRecord::new(Box::new([1; 40]), Permissions::ALL)
+    );
+}
+
 cfg_test!(
     use libstress::utils::generate_random_string_vector;
     use rand::thread_rng;
@@ -163,16 +221,15 @@ fn test_runtime_panic_32bit_or_lower() {
 }
 
 mod interface_tests {
-    use super::interface::{create_tree_fresh, DIR_KSROOT, DIR_SNAPROOT};
+    use super::interface::{create_tree_fresh, DataDir, DIR_KSROOT, DIR_SNAPROOT};
     use crate::corestore::memstore::Memstore;
-    use crate::storage::v1::flush::Autoflush;
     use std::fs;
     use std::path::PathBuf;
     #[test]
     fn test_tree() {
         // HACK(@ohsayan): M1 builder is broken
         if std::env::var_os("HACK_SKYD_TEST_IGNORE_TREE_TEST_M1").is_none() {
-            create_tree_fresh(&Autoflush, &Memstore::new_default()).unwrap();
+            create_tree_fresh(&DataDir::current(), &Memstore::new_default()).unwrap();
             let read_ks: Vec<String> = fs::read_dir(DIR_KSROOT)
                 .unwrap()
                 .map(|dir| {
@@ -204,7 +261,7 @@ mod preload_tests {
         let memstore = Memstore::new_default();
         let mut v = Vec::new();
         preload::raw_generate_preload(&mut v, &memstore).unwrap();
-        let de: Vec<String> = preload::read_preload_raw(v)
+        let de: Vec<String> = preload::read_preload_raw(&v)
             .unwrap()
             .into_iter()
             .map(|each| unsafe { each.as_str().to_owned() })
@@ -213,6 +270,238 @@ mod preload_tests {
     }
 }
 
+mod header_tests {
+    use crate::storage::v1::{
+        error::StorageEngineError,
+        header::{self, ChecksumWriter, PayloadId, FORMAT_VERSION, HEADER_LEN},
+    };
+    use std::io::Write;
+    fn file_with_payload(payload: &[u8]) -> Vec<u8> {
+        let mut written = Vec::new();
+        let mut writer = ChecksumWriter::new(&mut written);
+        writer.write_all(payload).unwrap();
+        let (header, id) = writer.finish();
+        assert_eq!(id, PayloadId::of(payload));
+        let mut file = header.to_vec();
+        file.extend(written);
+        file
+    }
+    #[test]
+    fn test_header_roundtrip() {
+        let file = file_with_payload(b"hello, world");
+        assert_eq!(file.len(), HEADER_LEN + 12);
+        assert_eq!(
+            header::read_header(&file, "test").unwrap(),
+            (FORMAT_VERSION, &b"hello, world"[..])
+        );
+        // an empty payload is fine too
+        let file = file_with_payload(b"");
+        assert!(header::read_header(&file, "test").unwrap().1.is_empty());
+    }
+    #[test]
+    fn test_header_detects_corruption() {
+        let mut file = file_with_payload(b"hello, world");
+        // flip a bit in the payload
+        file[HEADER_LEN + 3] ^= 1;
+        assert!(matches!(
+            header::read_header(&file, "test"),
+            Err(StorageEngineError::CorruptedFile(_))
+        ));
+        // a partly written file
+        let file = file_with_payload(b"hello, world");
+        for len in [10, HEADER_LEN, file.len() - 1] {
+            assert!(matches!(
+                header::read_header(&file[..len], "test"),
+                Err(StorageEngineError::CorruptedFile(_))
+            ));
+        }
+    }
+    #[test]
+    fn test_header_newer_version() {
+        let mut file = file_with_payload(b"hello, world");
+        file[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            header::read_header(&file, "test"),
+            Err(StorageEngineError::BadMetadata(_))
+        ));
+    }
+    #[test]
+    fn test_header_legacy_file() {
+        // files written by older versions don't have a header
+        let legacy: [u8; 12] = [1, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0];
+        assert_eq!(
+            header::read_header(&legacy, "test").unwrap(),
+            (0, &legacy[..])
+        );
+        assert_eq!(header::peek_version(&legacy), Some(0));
+    }
+}
+
+mod crypt_tests {
+    use crate::{
+        config::StorageCipher,
+        storage::v1::crypt::{self, Keyring, OpenError, CHUNK_LEN},
+    };
+    use std::io::{Cursor, Write};
+    const KEY_1: &str = "1:000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    const KEY_2: &str = "2:202122232425262728292A2B2C2D2E2F303132333435363738393A3B3C3D3E3F";
+    #[test]
+    fn test_crypt_roundtrip() {
+        for cipher in [StorageCipher::Aes256Gcm, StorageCipher::Chacha20Poly1305] {
+            let keyring = Keyring::parse(cipher, KEY_1).unwrap();
+            let sealed = keyring.seal(b"hello, world").unwrap().unwrap();
+            assert!(crypt::is_encrypted(&sealed));
+            assert!(!sealed.windows(12).any(|w| w == b"hello, world"));
+            assert_eq!(keyring.open(&sealed).unwrap(), b"hello, world");
+            // every file gets its own nonce
+            assert_ne!(keyring.seal(b"hello, world").unwrap().unwrap(), sealed);
+        }
+    }
+    #[test]
+    fn test_crypt_disabled() {
+        let keyring = Keyring::parse(StorageCipher::None, KEY_1).unwrap();
+        assert!(!keyring.is_enabled());
+        assert!(keyring.seal(b"hello, world").unwrap().is_none());
+        assert!(Keyring::empty().seal(b"hello, world").unwrap().is_none());
+    }
+    #[test]
+    fn test_crypt_key_rotation() {
+        let old = Keyring::parse(StorageCipher::Aes256Gcm, KEY_1).unwrap();
+        let sealed_old = old.seal(b"older").unwrap().unwrap();
+        // the newest key encrypts, but the older one still decrypts
+        let rotated = Keyring::parse(
+            StorageCipher::Chacha20Poly1305,
+            &format!("{KEY_1},\n{KEY_2}\n"),
+        )
+        .unwrap();
+        let sealed_new = rotated.seal(b"newer").unwrap().unwrap();
+        assert_eq!(rotated.open(&sealed_old).unwrap(), b"older");
+        assert_eq!(rotated.open(&sealed_new).unwrap(), b"newer");
+        assert_eq!(old.open(&sealed_new), Err(OpenError::MissingKey(2)));
+    }
+    #[test]
+    fn test_crypt_tampered() {
+        let keyring = Keyring::parse(StorageCipher::Aes256Gcm, KEY_1).unwrap();
+        let sealed = keyring.seal(b"hello, world").unwrap().unwrap();
+        for i in [8, 12, 20, sealed.len() - 20, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[i] ^= 1;
+            assert!(keyring.open(&tampered).is_err());
+        }
+        assert_eq!(
+            keyring.open(&sealed[..sealed.len() - 1]),
+            Err(OpenError::Corrupted)
+        );
+    }
+    #[test]
+    fn test_crypt_chunked() {
+        let keyring = Keyring::parse(StorageCipher::Aes256Gcm, KEY_1).unwrap();
+        for len in [0, 10, CHUNK_LEN - 1, CHUNK_LEN, 5 * CHUNK_LEN / 2] {
+            let file: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let mut sealer = keyring.sealer(Cursor::new(Vec::new())).unwrap().unwrap();
+            // written in pieces that don't line up with the chunks
+            for piece in file.chunks(1000) {
+                sealer.write_all(piece).unwrap();
+            }
+            // the start of the file can be changed till it's finished
+            let start: &[u8] = if len >= 4 { b"HEAD" } else { b"" };
+            let sealed = sealer.finish(start).unwrap().into_inner();
+            let mut expected = file.clone();
+            expected[..start.len()].copy_from_slice(start);
+            assert_eq!(keyring.open(&sealed).unwrap(), expected);
+        }
+    }
+    #[test]
+    fn test_crypt_chunks_tampered() {
+        let keyring = Keyring::parse(StorageCipher::Chacha20Poly1305, KEY_1).unwrap();
+        let sealed = keyring.seal(&vec![7; 3 * CHUNK_LEN]).unwrap().unwrap();
+        // three full chunks and an empty one at the end, each with a tag
+        let chunk = CHUNK_LEN + 16;
+        let prefix = sealed.len() - 3 * chunk - 16;
+        // cutting off chunks at the end
+        for len in [sealed.len() - 16, prefix + 2 * chunk, prefix + chunk + 16] {
+            assert_eq!(keyring.open(&sealed[..len]), Err(OpenError::Corrupted));
+        }
+        // swapping chunks (with the same contents)
+        let mut swapped = sealed.clone();
+        let (first, rest) = swapped[prefix..].split_at_mut(chunk);
+        first.swap_with_slice(&mut rest[..chunk]);
+        assert_eq!(keyring.open(&swapped), Err(OpenError::Corrupted));
+    }
+    #[test]
+    fn test_crypt_bad_keys() {
+        let cipher = StorageCipher::Aes256Gcm;
+        assert!(Keyring::parse(cipher, "1:0011").is_none());
+        assert!(Keyring::parse(cipher, "one:0011").is_none());
+        assert!(Keyring::parse(cipher, &KEY_1.replace('0', "g")).is_none());
+        assert!(Keyring::parse(cipher, &format!("{KEY_1},{KEY_1}")).is_none());
+        let keyring = Keyring::parse(cipher, "# no keys yet\n").unwrap();
+        assert!(!keyring.is_enabled());
+    }
+}
+
+mod migration_tests {
+    use crate::{
+        corestore::memstore::{Memstore, ObjectID},
+        storage::v1::{
+            flush::{self, LocalSnapshot},
+            header::{self, FORMAT_VERSION, HEADER_LEN},
+            migrate, unflush,
+        },
+        util::os::{self, EntryKind},
+    };
+    use std::fs;
+    const ROOT: &str = "data/snaps/migratesnap";
+    const BACKUP_ROOT: &str = "data/migratesnap-backups";
+    fn files_in(root: &str) -> Vec<String> {
+        os::rlistdir(root)
+            .unwrap()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                EntryKind::File(path) => Some(path),
+                EntryKind::Directory(_) => None,
+            })
+            .collect()
+    }
+    fn version_of(path: &str) -> u32 {
+        header::peek_version(&fs::read(path).unwrap()).unwrap()
+    }
+    #[test]
+    fn test_migrate_legacy_tree() {
+        let store = Memstore::new_default();
+        assert!(store.create_keyspace(ObjectID::try_from_slice("oldks").unwrap()));
+        flush::flush_full(LocalSnapshot::new("migratesnap".to_owned()), &store).unwrap();
+        // turn it into a tree written before we had headers
+        let files = files_in(ROOT);
+        for file in files.iter() {
+            let data = fs::read(file).unwrap();
+            fs::write(file, &data[HEADER_LEN..]).unwrap();
+            assert_eq!(version_of(file), 0);
+        }
+        assert_eq!(
+            migrate::migrate_tree(ROOT, BACKUP_ROOT).unwrap(),
+            files.len()
+        );
+        for file in files.iter() {
+            assert_eq!(version_of(file), FORMAT_VERSION);
+        }
+        // the backup has the files as they were
+        let backups = files_in(BACKUP_ROOT);
+        assert_eq!(backups.len(), files.len());
+        for file in backups.iter() {
+            assert_eq!(version_of(file), 0);
+        }
+        // nothing left to do
+        assert_eq!(migrate::migrate_tree(ROOT, BACKUP_ROOT).unwrap(), 0);
+        let restored = unflush::read_full_from(ROOT).unwrap();
+        assert!(restored
+            .get_keyspace_atomic_ref("oldks".as_bytes())
+            .is_some());
+        fs::remove_dir_all(ROOT).unwrap();
+        fs::remove_dir_all(BACKUP_ROOT).unwrap();
+    }
+}
+
 mod bytemark_set_tests {
     use super::*;
     use crate::corestore::memstore::{Keyspace, ObjectID};
@@ -365,10 +654,9 @@ mod bytemark_actual_table_restore {
                 .get_inner_ref()
                 .fresh_entry(SharedSlice::from("super"))
                 .unwrap()
-                .insert(LockedVec::new(vec![
-                    SharedSlice::from("hello"),
-                    SharedSlice::from("world"),
-                ]))
+                .insert(LockedVec::new(
+                    vec![SharedSlice::from("hello"), SharedSlice::from("world")].into(),
+                ))
         };
     }
 
@@ -463,13 +751,40 @@ mod flush_routines {
         );
     }
 
+    #[test]
+    fn test_unflush_corrupted_table() {
+        let tbl = Table::new_default_kve();
+        tbl.get_kvstore()
+            .unwrap()
+            .set("hello".into(), "world".into())
+            .unwrap();
+        let tblid = unsafe { ObjectID::from_slice("mytbl2") };
+        let ksid = unsafe { ObjectID::from_slice("myks2") };
+        fs::create_dir_all("data/ks/myks2").unwrap();
+        super::flush::oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        // damage the last byte of the file on disk
+        let mut file = fs::read("data/ks/myks2/mytbl2").unwrap();
+        *file.last_mut().unwrap() ^= 1;
+        fs::write("data/ks/myks2/mytbl2", file).unwrap();
+        let ret = super::unflush::read_table::<Table>(
+            &ksid,
+            &tblid,
+            false,
+            bytemarks::BYTEMARK_MODEL_KV_BIN_BIN,
+        );
+        assert!(matches!(
+            ret,
+            Err(super::error::StorageEngineError::CorruptedFile(_))
+        ));
+    }
+
     #[test]
     fn test_flush_unflush_table_kvext_listmap() {
         let tbl = Table::new_kve_listmap_with_data(Coremap::new(), false, true, true);
         if let DataModel::KVExtListmap(kvl) = tbl.get_model_ref() {
             kvl.add_list("mylist".into()).unwrap();
             let list = kvl.get("mylist".as_bytes()).unwrap().unwrap();
-            list.write().push("mysupervalue".into());
+            list.write().push_back("mysupervalue".into());
         } else {
             panic!("Bad model!");
         }
@@ -517,7 +832,8 @@ mod flush_routines {
 
         // and a table with lists
         let cmap = Coremap::new();
-        cmap.true_if_insert("mylist".into(), LockedVec::new(vec!["myvalue".into()]));
+        let mylist = vec![SharedSlice::from("myvalue")];
+        cmap.true_if_insert("mylist".into(), LockedVec::new(mylist.into()));
         let my_list_tbl = Table::new_kve_listmap_with_data(cmap, false, true, true);
         assert!(ks.create_table(list_tbl.clone(), my_list_tbl));
 
@@ -561,13 +877,200 @@ mod flush_routines {
     }
 }
 
+mod delta_tests {
+    use crate::{
+        corestore::{memstore::ObjectID, table::Table, SharedSlice},
+        storage::v1::{bytemarks, flush::oneshot, flush::Autoflush, unflush},
+    };
+    use std::{fs, path::Path};
+    fn table_with_keys(count: usize) -> Table {
+        let tbl = Table::new_default_kve();
+        let kve = tbl.get_kvstore().unwrap();
+        for i in 0..count {
+            kve.set(format!("key{i}").into(), "value".into()).unwrap();
+        }
+        tbl
+    }
+    fn read_back(ksid: &ObjectID, tblid: &ObjectID) -> Table {
+        unflush::read_table(ksid, tblid, false, bytemarks::BYTEMARK_MODEL_KV_BIN_BIN).unwrap()
+    }
+    fn value_of(tbl: &Table, key: &str) -> Option<SharedSlice> {
+        tbl.get_kvstore()
+            .unwrap()
+            .get(&SharedSlice::from(key))
+            .unwrap()
+            .map(|v| v.clone())
+    }
+    #[test]
+    fn test_incremental_flush_unflush() {
+        let tblid = unsafe { ObjectID::from_slice("deltatbl1") };
+        let ksid = unsafe { ObjectID::from_slice("deltaks1") };
+        fs::create_dir_all("data/ks/deltaks1").unwrap();
+        let tbl = table_with_keys(20);
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        let full = fs::read("data/ks/deltaks1/deltatbl1").unwrap();
+        assert!(!Path::new("data/ks/deltaks1/deltatbl1.delta").exists());
+        // nothing changed, so nothing is written
+        assert_eq!(tbl.dirty_keys().keys().unwrap().len(), 0);
+        let kve = tbl.get_kvstore().unwrap();
+        kve.upsert("key1".into(), "newvalue".into()).unwrap();
+        assert!(kve.remove("key2").unwrap());
+        kve.set("key20".into(), "value".into()).unwrap();
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        // only the delta was written
+        assert_eq!(fs::read("data/ks/deltaks1/deltatbl1").unwrap(), full);
+        assert!(Path::new("data/ks/deltaks1/deltatbl1.delta").exists());
+        let ret = read_back(&ksid, &tblid);
+        assert_eq!(ret.count(), 20);
+        assert_eq!(
+            value_of(&ret, "key1").unwrap(),
+            SharedSlice::from("newvalue")
+        );
+        assert!(value_of(&ret, "key2").is_none());
+        assert_eq!(value_of(&ret, "key20").unwrap(), SharedSlice::from("value"));
+        // the keys in the delta are still dirty, since the delta is rewritten on every save
+        assert_eq!(ret.dirty_keys().keys().unwrap().len(), 3);
+        fs::remove_dir_all("data/ks/deltaks1").unwrap();
+    }
+    #[test]
+    fn test_compaction_and_stale_delta() {
+        let tblid = unsafe { ObjectID::from_slice("deltatbl2") };
+        let ksid = unsafe { ObjectID::from_slice("deltaks2") };
+        fs::create_dir_all("data/ks/deltaks2").unwrap();
+        let tbl = table_with_keys(20);
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        let kve = tbl.get_kvstore().unwrap();
+        kve.upsert("key1".into(), "newvalue".into()).unwrap();
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        let stale_delta = fs::read("data/ks/deltaks2/deltatbl2.delta").unwrap();
+        // change too many keys for a delta
+        for i in 0..10 {
+            kve.upsert(format!("key{i}").into(), "othervalue".into())
+                .unwrap();
+        }
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        assert!(!Path::new("data/ks/deltaks2/deltatbl2.delta").exists());
+        assert_eq!(tbl.dirty_keys().keys().unwrap().len(), 0);
+        // pretend that we crashed before the older delta was removed
+        fs::write("data/ks/deltaks2/deltatbl2.delta", stale_delta).unwrap();
+        let ret = read_back(&ksid, &tblid);
+        assert_eq!(ret.count(), 20);
+        assert_eq!(
+            value_of(&ret, "key1").unwrap(),
+            SharedSlice::from("othervalue")
+        );
+        fs::remove_dir_all("data/ks/deltaks2").unwrap();
+    }
+    #[test]
+    fn test_truncated_table_is_compacted() {
+        let tblid = unsafe { ObjectID::from_slice("deltatbl3") };
+        let ksid = unsafe { ObjectID::from_slice("deltaks3") };
+        fs::create_dir_all("data/ks/deltaks3").unwrap();
+        let tbl = table_with_keys(20);
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        tbl.get_kvstore()
+            .unwrap()
+            .upsert("key1".into(), "newvalue".into())
+            .unwrap();
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        tbl.truncate_table();
+        assert!(tbl.dirty_keys().keys().is_none());
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        assert!(!Path::new("data/ks/deltaks3/deltatbl3.delta").exists());
+        assert_eq!(read_back(&ksid, &tblid).count(), 0);
+        fs::remove_dir_all("data/ks/deltaks3").unwrap();
+    }
+}
+
+mod ttl_tests {
+    use crate::{
+        corestore::{htable::Coremap, memstore::ObjectID, table::Table, SharedSlice},
+        kvengine::now_millis,
+        storage::v1::{bytemarks, flush::oneshot, flush::Autoflush, ttl, unflush},
+    };
+    use std::{fs, path::Path};
+    const HOUR: u64 = 60 * 60 * 1000;
+    fn table_with_keys(count: usize) -> Table {
+        let tbl = Table::new_default_kve();
+        let kve = tbl.get_kvstore().unwrap();
+        for i in 0..count {
+            kve.set(format!("key{i}").into(), "value".into()).unwrap();
+        }
+        tbl
+    }
+    fn read_back(ksid: &ObjectID, tblid: &ObjectID) -> Table {
+        unflush::read_table(ksid, tblid, false, bytemarks::BYTEMARK_MODEL_KV_BIN_BIN).unwrap()
+    }
+    #[test]
+    fn test_expiry_flush_unflush() {
+        let tblid = unsafe { ObjectID::from_slice("ttltbl1") };
+        let ksid = unsafe { ObjectID::from_slice("ttlks1") };
+        fs::create_dir_all("data/ks/ttlks1").unwrap();
+        let tbl = table_with_keys(10);
+        let kve = tbl.get_kvstore().unwrap();
+        let deadline = now_millis() + HOUR;
+        assert!(kve.expire_at_unchecked(b"key1", deadline));
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        assert!(Path::new("data/ks/ttlks1/ttltbl1.ttl").exists());
+        let ret = read_back(&ksid, &tblid);
+        assert_eq!(ret.count(), 10);
+        let ret_kve = ret.get_kvstore().unwrap();
+        assert_eq!(
+            ret_kve.get_expiry_ref().get_cloned(&b"key1"[..]),
+            Some(deadline)
+        );
+        assert_eq!(ret_kve.ttl_unchecked(b"key2"), Some(None));
+        // once no key has a TTL, the deadlines go away
+        assert!(kve.persist_unchecked(b"key1"));
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        assert!(!Path::new("data/ks/ttlks1/ttltbl1.ttl").exists());
+        assert_eq!(read_back(&ksid, &tblid).get_expiry_ref().len(), 0);
+        fs::remove_dir_all("data/ks/ttlks1").unwrap();
+    }
+    #[test]
+    fn test_expired_keys_are_dropped() {
+        let tblid = unsafe { ObjectID::from_slice("ttltbl2") };
+        let ksid = unsafe { ObjectID::from_slice("ttlks2") };
+        fs::create_dir_all("data/ks/ttlks2").unwrap();
+        let tbl = table_with_keys(10);
+        let kve = tbl.get_kvstore().unwrap();
+        // expired keys are purged before they're written
+        assert!(kve.expire_at_unchecked(b"key1", 1));
+        assert!(kve.expire_at_unchecked(b"key2", now_millis() + HOUR));
+        oneshot::flush_table(&Autoflush, &tblid, &ksid, &tbl).unwrap();
+        assert_eq!(tbl.count(), 9);
+        assert_eq!(read_back(&ksid, &tblid).count(), 9);
+        // pretend that the deadline of a key passed while the server was down
+        let deadlines = Coremap::new();
+        deadlines.upsert(SharedSlice::from("key2"), 1);
+        deadlines.upsert(SharedSlice::from("nosuchkey"), 1);
+        let mut payload = Vec::new();
+        ttl::raw_serialize_expiry(&mut payload, &deadlines).unwrap();
+        oneshot::rewrite_file("data/ks/ttlks2/ttltbl2.ttl", &payload).unwrap();
+        let ret = read_back(&ksid, &tblid);
+        assert_eq!(ret.count(), 8);
+        assert_eq!(ret.get_expiry_ref().len(), 0);
+        // the next save has to remove the key from disk too
+        assert!(ret
+            .dirty_keys()
+            .keys()
+            .unwrap()
+            .contains(&SharedSlice::from("key2")));
+        // a truncated payload is rejected
+        assert!(ttl::deserialize_expiry(&payload[..payload.len() - 1]).is_none());
+        fs::remove_dir_all("data/ks/ttlks2").unwrap();
+    }
+}
+
 mod list_tests {
     use super::iter::RawSliceIter;
     use super::{de, se};
+    use crate::corestore::zset::{Score, SortedSet};
     use crate::corestore::{htable::Coremap, SharedSlice};
-    use crate::kvengine::LockedVec;
+    use crate::kvengine::{LockedHash, LockedSet, LockedVec, LockedZSet};
     use core::ops::Deref;
     use parking_lot::RwLock;
+    use std::collections::{HashMap, HashSet};
     #[test]
     fn test_list_se_de() {
         let mylist = vec![
@@ -664,6 +1167,178 @@ mod list_tests {
         let de = de::deserialize_list_map(&v).unwrap();
         assert_eq!(de.len(), 0)
     }
+    #[test]
+    fn test_set_map_se_de() {
+        let mymap: Coremap<SharedSlice, LockedSet> = Coremap::new();
+        let key1: SharedSlice = "myset1".into();
+        let val1: HashSet<SharedSlice> = ["apples", "bananas", "carrots"]
+            .into_iter()
+            .map(SharedSlice::from)
+            .collect();
+        let key2: SharedSlice = "myset2".into();
+        let val2: HashSet<SharedSlice> = HashSet::new();
+        mymap.true_if_insert(key1.clone(), RwLock::new(val1.clone()));
+        mymap.true_if_insert(key2.clone(), RwLock::new(val2.clone()));
+        let mut v = Vec::new();
+        se::raw_serialize_set_map(&mymap, &mut v).unwrap();
+        let de = de::deserialize_set_map(&v).unwrap();
+        assert_eq!(de.len(), 2);
+        assert_eq!(de.get(&key1).unwrap().value().deref().read().clone(), val1);
+        assert_eq!(de.get(&key2).unwrap().value().deref().read().clone(), val2);
+    }
+    #[test]
+    fn test_hash_map_se_de() {
+        let mymap: Coremap<SharedSlice, LockedHash> = Coremap::new();
+        let key1: SharedSlice = "myhash1".into();
+        let val1: HashMap<SharedSlice, SharedSlice> = [("name", "sayan"), ("lang", "rust")]
+            .into_iter()
+            .map(|(f, v)| (SharedSlice::from(f), SharedSlice::from(v)))
+            .collect();
+        let key2: SharedSlice = "myhash2".into();
+        let val2: HashMap<SharedSlice, SharedSlice> = HashMap::new();
+        mymap.true_if_insert(key1.clone(), RwLock::new(val1.clone()));
+        mymap.true_if_insert(key2.clone(), RwLock::new(val2.clone()));
+        let mut v = Vec::new();
+        se::raw_serialize_hash_map(&mymap, &mut v).unwrap();
+        let de = de::deserialize_hash_map(&v).unwrap();
+        assert_eq!(de.len(), 2);
+        assert_eq!(de.get(&key1).unwrap().value().deref().read().clone(), val1);
+        assert_eq!(de.get(&key2).unwrap().value().deref().read().clone(), val2);
+    }
+    #[test]
+    fn test_zset_map_se_de() {
+        let mymap: Coremap<SharedSlice, LockedZSet> = Coremap::new();
+        let key: SharedSlice = "leaderboard".into();
+        let val: SortedSet = [
+            ("sayan", 100.0),
+            ("jotsy", -2.5),
+            ("ohsayan", f64::INFINITY),
+        ]
+        .into_iter()
+        .map(|(m, s)| (SharedSlice::from(m), Score::new(s).unwrap()))
+        .collect();
+        mymap.true_if_insert(key.clone(), RwLock::new(val.clone()));
+        let mut v = Vec::new();
+        se::raw_serialize_zset_map(&mymap, &mut v).unwrap();
+        let de = de::deserialize_zset_map(&v).unwrap();
+        assert_eq!(de.len(), 1);
+        let de_val: Vec<(SharedSlice, f64)> = de
+            .get(&key)
+            .unwrap()
+            .value()
+            .read()
+            .iter()
+            .map(|(m, s)| (m.clone(), s.get()))
+            .collect();
+        let val: Vec<(SharedSlice, f64)> = val.iter().map(|(m, s)| (m.clone(), s.get())).collect();
+        assert_eq!(de_val, val);
+    }
+}
+
+mod compression_tests {
+    use super::compress::{self, ValueExtent};
+    use super::iter::RawSliceIter;
+    use super::{de, se};
+    use crate::corestore::{htable::Coremap, SharedSlice};
+    use crate::dbnet::compression::Codec;
+
+    /// Encode every value like it would be written out
+    fn encode_with(values: &[&[u8]], settings: Option<(Codec, usize)>) -> Vec<(u64, Vec<u8>)> {
+        values
+            .iter()
+            .map(|v| {
+                let (extent, payload) = compress::encode_value_with(v, settings).unwrap();
+                (extent, payload.into_owned())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_value_roundtrip() {
+        let large = b"skytable ".repeat(128);
+        for codec in Codec::ALL {
+            let encoded = encode_with(&[&large[..], b"tiny"], Some((codec, 64)));
+            // the large value shrinks and the small one is written as it is
+            assert!(encoded[0].1.len() < large.len());
+            assert_eq!(encoded[1], (4, b"tiny".to_vec()));
+            for ((extent, payload), value) in encoded.iter().zip([&large[..], &b"tiny"[..]]) {
+                let extent = ValueExtent::decode(*extent).unwrap();
+                assert_eq!(extent.len, payload.len());
+                let decoded = compress::decode_value(extent, payload).unwrap();
+                assert_eq!(decoded.as_ref(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_incompressible_value_is_written_raw() {
+        let value: Vec<u8> = (0..=255).collect();
+        let encoded = encode_with(&[&value[..]], Some((Codec::Zstd, 1)));
+        assert_eq!(encoded[0], (value.len() as u64, value));
+    }
+
+    #[test]
+    fn test_corrupted_compressed_value() {
+        let large = b"skytable ".repeat(128);
+        let (extent, payload) = encode_with(&[&large[..]], Some((Codec::Lz4, 1))).remove(0);
+        let extent = ValueExtent::decode(extent).unwrap();
+        // unknown codec
+        let mut bad = payload.clone();
+        bad[0] = 0xFF;
+        assert!(compress::decode_value(extent, &bad).is_none());
+        // cut short
+        assert!(compress::decode_value(extent, &payload[..payload.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn test_de_compressed_map() {
+        let large = b"skytable ".repeat(128);
+        let mut v = Vec::new();
+        v.extend_from_slice(&1u64.to_ne_bytes());
+        let (extent, payload) = encode_with(&[&large[..]], Some((Codec::Lz4, 1))).remove(0);
+        v.extend_from_slice(&3u64.to_ne_bytes());
+        v.extend_from_slice(&extent.to_ne_bytes());
+        v.extend_from_slice(b"key");
+        v.extend_from_slice(&payload);
+        let de = de::deserialize_map(&v).unwrap();
+        assert_eq!(
+            de.get_cloned(b"key".as_ref()).unwrap(),
+            SharedSlice::new(&large)
+        );
+    }
+
+    #[test]
+    fn test_de_compressed_list() {
+        let large = b"skytable ".repeat(128);
+        let mut v = Vec::new();
+        v.extend_from_slice(&2u64.to_ne_bytes());
+        for (extent, payload) in encode_with(&[b"small", &large[..]], Some((Codec::Zstd, 64))) {
+            v.extend_from_slice(&extent.to_ne_bytes());
+            v.extend_from_slice(&payload);
+        }
+        let mut rawiter = RawSliceIter::new(&v);
+        let de = de::deserialize_nested_list(rawiter.get_borrowed_iter()).unwrap();
+        assert_eq!(
+            de,
+            vec![SharedSlice::from("small"), SharedSlice::new(&large)]
+        );
+    }
+
+    #[test]
+    fn test_uncompressed_map_is_unchanged() {
+        // compression is off unless it's configured, and uncompressed values are written
+        // exactly like they used to be
+        let map: Coremap<SharedSlice, SharedSlice> = Coremap::new();
+        map.upsert(SharedSlice::from("k"), SharedSlice::from("value"));
+        let mut v = Vec::new();
+        se::raw_serialize_map(&map, &mut v).unwrap();
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&1u64.to_ne_bytes());
+        expected.extend_from_slice(&1u64.to_ne_bytes());
+        expected.extend_from_slice(&5u64.to_ne_bytes());
+        expected.extend_from_slice(b"kvalue");
+        assert_eq!(v, expected);
+    }
 }
 
 mod corruption_tests {
@@ -0,0 +1,118 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Expiry deadlines
+//!
+//! The deadlines of the keys that have a TTL aren't a part of the table. Every time a table is
+//! saved, they're written to a file that sits next to the table file (`{table}.ttl`), and that
+//! file is removed once none of the keys have a TTL. Its payload looks like:
+//! ```text
+//! [8B: Number of keys]
+//! [8B: Length of the key][8B: Deadline][?B: Key] => Repeated for every key
+//! ```
+//! The integers are little endian and the deadlines are in milliseconds since the UNIX epoch.
+//! When the table is loaded (along with its [delta](super::delta)), the keys whose deadlines
+//! have passed are dropped and the rest get their deadlines back. Deadlines for keys that
+//! aren't in the table are ignored, since the file is written right after the table and a
+//! crash in between leaves the deadlines of the previous save in place.
+//!
+//! Unless `storage.keepexpired` is set, the keys whose deadlines have passed are purged
+//! before a table is saved, so they're never written at all
+
+use {
+    crate::{
+        corestore::{htable::Coremap, SharedSlice},
+        IoResult,
+    },
+    core::{
+        cmp,
+        sync::atomic::{AtomicBool, Ordering},
+    },
+    std::io::Write,
+};
+
+/// The suffix of the deadlines of a table
+pub const TTL_SUFFIX: &str = ".ttl";
+/// The size of the fixed part of an entry
+const ENTRY_PREFIX_LEN: usize = 16;
+
+static KEEP_EXPIRED: AtomicBool = AtomicBool::new(false);
+
+/// Set whether the keys whose deadlines have passed are still written to disk
+pub fn configure(keep_expired: bool) {
+    KEEP_EXPIRED.store(keep_expired, Ordering::Relaxed);
+}
+
+/// Returns true if the keys whose deadlines have passed should be purged before a table
+/// is saved
+pub fn purge_before_save() -> bool {
+    !KEEP_EXPIRED.load(Ordering::Relaxed)
+}
+
+/// Serialize the given deadlines and write them to the provided buffer
+pub fn raw_serialize_expiry<W: Write>(
+    w: &mut W,
+    expiry: &Coremap<SharedSlice, u64>,
+) -> IoResult<()> {
+    let snapshot = expiry.snapshot_with(|key, deadline| (key.clone(), *deadline));
+    w.write_all(&(snapshot.len() as u64).to_le_bytes())?;
+    for (key, deadline) in snapshot.iter() {
+        w.write_all(&(key.len() as u64).to_le_bytes())?;
+        w.write_all(&deadline.to_le_bytes())?;
+        w.write_all(key)?;
+    }
+    Ok(())
+}
+
+/// Deserialize the payload of the deadlines of a table into the keys and their deadlines
+pub fn deserialize_expiry(data: &[u8]) -> Option<Vec<(&[u8], u64)>> {
+    let (count, mut rest) = self::split_u64(data)?;
+    // don't trust the count with more room than the entries could possibly take
+    let mut entries = Vec::with_capacity(cmp::min(count as usize, rest.len() / ENTRY_PREFIX_LEN));
+    for _ in 0..count {
+        let (keylen, after_len) = self::split_u64(rest)?;
+        let (deadline, after_deadline) = self::split_u64(after_len)?;
+        if (after_deadline.len() as u64) < keylen {
+            return None;
+        }
+        let (key, after_key) = after_deadline.split_at(keylen as usize);
+        entries.push((key, deadline));
+        rest = after_key;
+    }
+    if rest.is_empty() {
+        Some(entries)
+    } else {
+        None
+    }
+}
+
+fn split_u64(data: &[u8]) -> Option<(u64, &[u8])> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (int, rest) = data.split_at(8);
+    Some((u64::from_le_bytes(int.try_into().unwrap()), rest))
+}
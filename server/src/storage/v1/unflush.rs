@@ -34,14 +34,17 @@ use {
         corestore::{
             memstore::{Keyspace, Memstore, ObjectID, SystemKeyspace, SYSTEM},
             table::{SystemTable, Table},
+            SharedSlice,
         },
         storage::v1::{
             de::DeserializeInto,
+            delta::{self, Delta},
             error::{ErrorContext, StorageEngineError, StorageEngineResult},
-            flush::Autoflush,
-            interface::DIR_KSROOT,
+            header::PayloadId,
+            interface::{DataDir, DIR_BACKUPS, DIR_KSROOT, DIR_ROOT},
+            migrate::{self, FileKind},
             preload::LoadedPartfile,
-            Coremap,
+            ttl, Coremap,
         },
         util::Wrapper,
     },
@@ -50,23 +53,34 @@ use {
 };
 
 type PreloadSet = std::collections::HashSet<ObjectID>;
-const PRELOAD_PATH: &str = "data/ks/PRELOAD";
+/// The full save that a table was loaded from, and the keys that were changed after it (in
+/// its delta)
+type SaveState = (PayloadId, Vec<SharedSlice>);
 
 /// A keyspace that can be restored from disk storage
 pub trait UnflushableKeyspace: Sized {
-    /// Unflush routine for a keyspace
-    fn unflush_keyspace(partmap: LoadedPartfile, ksid: &ObjectID) -> StorageEngineResult<Self>;
+    /// Unflush routine for a keyspace located in the given `root`
+    fn unflush_keyspace(
+        partmap: LoadedPartfile,
+        root: &str,
+        ksid: &ObjectID,
+    ) -> StorageEngineResult<Self>;
 }
 
 impl UnflushableKeyspace for Keyspace {
-    fn unflush_keyspace(partmap: LoadedPartfile, ksid: &ObjectID) -> StorageEngineResult<Self> {
+    fn unflush_keyspace(
+        partmap: LoadedPartfile,
+        root: &str,
+        ksid: &ObjectID,
+    ) -> StorageEngineResult<Self> {
         let ks: Coremap<ObjectID, Arc<Table>> = Coremap::with_capacity(partmap.len());
         for (tableid, (table_storage_type, model_code)) in partmap.into_iter() {
             if table_storage_type > 1 {
                 return Err(StorageEngineError::bad_metadata_in_table(ksid, &tableid));
             }
             let is_volatile = table_storage_type == bytemarks::BYTEMARK_STORAGE_VOLATILE;
-            let tbl = self::read_table::<Table>(ksid, &tableid, is_volatile, model_code)?;
+            let tbl =
+                self::read_table_from::<Table>(root, ksid, &tableid, is_volatile, model_code)?;
             ks.true_if_insert(tableid, Arc::new(tbl));
         }
         Ok(Keyspace::init_with_all_def_strategy(ks))
@@ -74,14 +88,24 @@ impl UnflushableKeyspace for Keyspace {
 }
 
 impl UnflushableKeyspace for SystemKeyspace {
-    fn unflush_keyspace(partmap: LoadedPartfile, ksid: &ObjectID) -> StorageEngineResult<Self> {
+    fn unflush_keyspace(
+        partmap: LoadedPartfile,
+        root: &str,
+        ksid: &ObjectID,
+    ) -> StorageEngineResult<Self> {
         let ks: Coremap<ObjectID, Wrapper<SystemTable>> = Coremap::with_capacity(partmap.len());
         for (tableid, (table_storage_type, model_code)) in partmap.into_iter() {
             if table_storage_type > 1 {
                 return Err(StorageEngineError::bad_metadata_in_table(ksid, &tableid));
             }
             let is_volatile = table_storage_type == bytemarks::BYTEMARK_STORAGE_VOLATILE;
-            let tbl = self::read_table::<SystemTable>(ksid, &tableid, is_volatile, model_code)?;
+            let tbl = self::read_table_from::<SystemTable>(
+                root,
+                ksid,
+                &tableid,
+                is_volatile,
+                model_code,
+            )?;
             ks.true_if_insert(tableid, Wrapper::new(tbl));
         }
         Ok(SystemKeyspace::new(ks))
@@ -105,21 +129,24 @@ impl UnflushableTable for Table {
         model_code: u8,
         volatile: bool,
     ) -> StorageEngineResult<Self> {
-        let ret = match model_code {
+        let (ret, state) = match model_code {
             // pure KVEBlob: [0, 3]
             x if x < 4 => {
-                let data = decode(filepath, volatile)?;
+                let (data, state) = decode_table(&filepath, volatile)?;
                 let (k_enc, v_enc) = unsafe {
                     // UNSAFE(@ohsayan): Safe because of the above match. Just a lil bitmagic
                     let key: bool = transmute(model_code >> 1);
                     let value: bool = transmute(((model_code >> 1) + (model_code & 1)) % 2);
                     (key, value)
                 };
-                Table::new_pure_kve_with_data(data, volatile, k_enc, v_enc)
+                (
+                    Table::new_pure_kve_with_data(data, volatile, k_enc, v_enc),
+                    state,
+                )
             }
             // KVExtlistmap: [4, 7]
             x if x < 8 => {
-                let data = decode(filepath, volatile)?;
+                let (data, state) = decode_table(&filepath, volatile)?;
                 let (k_enc, v_enc) = unsafe {
                     // UNSAFE(@ohsayan): Safe because of the above match. Just a lil bitmagic
                     let code = model_code - 4;
@@ -127,7 +154,55 @@ impl UnflushableTable for Table {
                     let value: bool = transmute(code % 2);
                     (key, value)
                 };
-                Table::new_kve_listmap_with_data(data, volatile, k_enc, v_enc)
+                (
+                    Table::new_kve_listmap_with_data(data, volatile, k_enc, v_enc),
+                    state,
+                )
+            }
+            // KVExtsetmap: [8, 11]
+            x if x < 12 => {
+                let (data, state) = decode_table(&filepath, volatile)?;
+                let (k_enc, v_enc) = unsafe {
+                    // UNSAFE(@ohsayan): Safe because of the above match. Just a lil bitmagic
+                    let code = model_code - 8;
+                    let key: bool = transmute(code >> 1);
+                    let value: bool = transmute(code % 2);
+                    (key, value)
+                };
+                (
+                    Table::new_kve_setmap_with_data(data, volatile, k_enc, v_enc),
+                    state,
+                )
+            }
+            // KVExthashmap: [12, 15]
+            x if x < 16 => {
+                let (data, state) = decode_table(&filepath, volatile)?;
+                let (k_enc, v_enc) = unsafe {
+                    // UNSAFE(@ohsayan): Safe because of the above match. Just a lil bitmagic
+                    let code = model_code - 12;
+                    let key: bool = transmute(code >> 1);
+                    let value: bool = transmute(code % 2);
+                    (key, value)
+                };
+                (
+                    Table::new_kve_hashmap_with_data(data, volatile, k_enc, v_enc),
+                    state,
+                )
+            }
+            // KVExtzsetmap: [16, 19]
+            x if x < 20 => {
+                let (data, state) = decode_table(&filepath, volatile)?;
+                let (k_enc, v_enc) = unsafe {
+                    // UNSAFE(@ohsayan): Safe because of the above match. Just a lil bitmagic
+                    let code = model_code - 16;
+                    let key: bool = transmute(code >> 1);
+                    let value: bool = transmute(code % 2);
+                    (key, value)
+                };
+                (
+                    Table::new_kve_zsetmap_with_data(data, volatile, k_enc, v_enc),
+                    state,
+                )
             }
             _ => {
                 return Err(StorageEngineError::BadMetadata(
@@ -135,6 +210,12 @@ impl UnflushableTable for Table {
                 ))
             }
         };
+        if let Some((base, mut dirty)) = state {
+            // the keys that were dropped have to be removed from disk on the next save too
+            let expired = self::restore_expiry(&filepath.as_ref().to_string_lossy(), &ret)?;
+            dirty.extend(expired);
+            ret.dirty_keys().restore(Some(base), dirty);
+        }
         Ok(ret)
     }
 }
@@ -166,16 +247,64 @@ fn decode<T: DeserializeInto>(
     if volatile {
         Ok(T::new_empty())
     } else {
-        let data = fs::read(filepath.as_ref()).map_err_context(format!(
-            "reading file {}",
-            filepath.as_ref().to_string_lossy()
-        ))?;
-        super::de::deserialize_into(&data).ok_or_else(|| {
-            StorageEngineError::CorruptedFile(filepath.as_ref().to_string_lossy().to_string())
-        })
+        let path = filepath.as_ref().to_string_lossy();
+        let data = fs::read(filepath.as_ref()).map_err_context(format!("reading file {path}"))?;
+        let data = migrate::read_payload(&data, FileKind::Table, &path)?;
+        super::de::deserialize_into(&data)
+            .ok_or_else(|| StorageEngineError::CorruptedFile(path.to_string()))
     }
 }
 
+/// Same as [`decode`], but also applies the delta of the table (if there is one)
+fn decode_table<T>(
+    filepath: impl AsRef<Path>,
+    volatile: bool,
+) -> StorageEngineResult<(Coremap<SharedSlice, T>, Option<SaveState>)>
+where
+    Coremap<SharedSlice, T>: DeserializeInto,
+{
+    if volatile {
+        return Ok((Coremap::new(), None));
+    }
+    let path = filepath.as_ref().to_string_lossy();
+    let data = fs::read(filepath.as_ref()).map_err_context(format!("reading file {path}"))?;
+    let payload = migrate::read_payload(&data, FileKind::Table, &path)?;
+    let table: Coremap<SharedSlice, T> = super::de::deserialize_into(&payload)
+        .ok_or_else(|| StorageEngineError::CorruptedFile(path.to_string()))?;
+    let base = PayloadId::of(&payload);
+    let delta_path = concat_str!(&path, delta::DELTA_SUFFIX);
+    let delta_data = match fs::read(&delta_path) {
+        Ok(delta_data) => delta_data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok((table, Some((base, Vec::new())))),
+        Err(e) => return Err(e).map_err_context(format!("reading file {delta_path}")),
+    };
+    let delta_payload = migrate::read_payload(&delta_data, FileKind::Delta, &delta_path)?;
+    let delta: Delta<T> = delta::deserialize_delta(&delta_payload)
+        .ok_or_else(|| StorageEngineError::CorruptedFile(delta_path.clone()))?;
+    if delta.base != base {
+        // we crashed after writing a full save but before removing the older delta
+        log::warn!("Ignoring {delta_path} since it was written for an older save of the table");
+        return Ok((table, Some((base, Vec::new()))));
+    }
+    let dirty = delta.apply_to(&table);
+    Ok((table, Some((base, dirty))))
+}
+
+/// Read the [deadlines](ttl) of the keys of the table at `path` (if it has any) and set them
+/// on `table`. Returns the keys that were dropped since their deadlines had passed
+fn restore_expiry(path: &str, table: &Table) -> StorageEngineResult<Vec<SharedSlice>> {
+    let ttl_path = concat_str!(path, ttl::TTL_SUFFIX);
+    let data = match fs::read(&ttl_path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).map_err_context(format!("reading file {ttl_path}")),
+    };
+    let payload = migrate::read_payload(&data, FileKind::Ttl, &ttl_path)?;
+    let deadlines = ttl::deserialize_expiry(&payload)
+        .ok_or_else(|| StorageEngineError::CorruptedFile(ttl_path.clone()))?;
+    Ok(table.restore_expiry(deadlines))
+}
+
 /// Read a given table into a [`Table`] object
 ///
 /// This will take care of volatility and the model_code. Just make sure that you pass the proper
@@ -186,31 +315,62 @@ pub fn read_table<T: UnflushableTable>(
     volatile: bool,
     model_code: u8,
 ) -> StorageEngineResult<T> {
-    let filepath = unsafe { concat_path!(DIR_KSROOT, ksid.as_str(), tblid.as_str()) };
+    self::read_table_from(DIR_KSROOT, ksid, tblid, volatile, model_code)
+}
+
+/// Same as [`read_table`], but reads the table from the tree located at `root`
+pub fn read_table_from<T: UnflushableTable>(
+    root: &str,
+    ksid: &ObjectID,
+    tblid: &ObjectID,
+    volatile: bool,
+    model_code: u8,
+) -> StorageEngineResult<T> {
+    let filepath = unsafe { concat_path!(root, ksid.as_str(), tblid.as_str()) };
     let tbl = T::unflush_table(filepath, model_code, volatile)?;
     Ok(tbl)
 }
 
 /// Read an entire keyspace into a Coremap. You'll need to initialize the rest
 pub fn read_keyspace<K: UnflushableKeyspace>(ksid: &ObjectID) -> StorageEngineResult<K> {
-    let partmap = self::read_partmap(ksid)?;
-    K::unflush_keyspace(partmap, ksid)
+    self::read_keyspace_from(DIR_KSROOT, ksid)
+}
+
+/// Same as [`read_keyspace`], but reads the keyspace from the tree located at `root`
+pub fn read_keyspace_from<K: UnflushableKeyspace>(
+    root: &str,
+    ksid: &ObjectID,
+) -> StorageEngineResult<K> {
+    let partmap = self::read_partmap_from(root, ksid)?;
+    K::unflush_keyspace(partmap, root, ksid)
 }
 
 /// Read the `PARTMAP` for a given keyspace
 pub fn read_partmap(ksid: &ObjectID) -> StorageEngineResult<LoadedPartfile> {
+    self::read_partmap_from(DIR_KSROOT, ksid)
+}
+
+/// Same as [`read_partmap`], but reads the `PARTMAP` from the tree located at `root`
+pub fn read_partmap_from(root: &str, ksid: &ObjectID) -> StorageEngineResult<LoadedPartfile> {
     let ksid_str = unsafe { ksid.as_str() };
-    let filepath = concat_path!(DIR_KSROOT, ksid_str, "PARTMAP");
+    let filepath = concat_path!(root, ksid_str, "PARTMAP");
     let partmap_raw = fs::read(&filepath)
         .map_err_context(format!("while reading {}", filepath.to_string_lossy()))?;
+    let partmap_raw =
+        migrate::read_payload(&partmap_raw, FileKind::Partmap, &filepath.to_string_lossy())?;
     super::de::deserialize_set_ctype_bytemark(&partmap_raw)
         .ok_or_else(|| StorageEngineError::corrupted_partmap(ksid))
 }
 
 /// Read the `PRELOAD`
 pub fn read_preload() -> StorageEngineResult<PreloadSet> {
-    let read = fs::read(PRELOAD_PATH).map_err_context("reading PRELOAD")?;
-    super::preload::read_preload_raw(read)
+    self::read_preload_from(DIR_KSROOT)
+}
+
+/// Same as [`read_preload`], but reads the `PRELOAD` from the tree located at `root`
+pub fn read_preload_from(root: &str) -> StorageEngineResult<PreloadSet> {
+    let read = fs::read(concat_path!(root, "PRELOAD")).map_err_context("reading PRELOAD")?;
+    super::preload::read_preload_raw(&migrate::read_payload(&read, FileKind::Preload, "PRELOAD")?)
 }
 
 /// Read everything and return a [`Memstore`]
@@ -218,8 +378,8 @@ pub fn read_preload() -> StorageEngineResult<PreloadSet> {
 /// If this is a new instance an empty store is returned while the directory tree
 /// is also created. If this is an already initialized instance then the store
 /// is read and returned (and any possible errors that are encountered are returned)
-pub fn read_full() -> StorageEngineResult<Memstore> {
-    if is_new_instance()? {
+pub fn read_full(dir: &DataDir) -> StorageEngineResult<Memstore> {
+    if is_new_instance(dir)? {
         log::trace!("Detected new instance. Creating data directory");
         /*
         Since the `PRELOAD` file doesn't exist -- this is a new instance
@@ -230,22 +390,30 @@ pub fn read_full() -> StorageEngineResult<Memstore> {
         */
         // init an empty store
         let store = Memstore::new_default();
-        let target = Autoflush;
         // (1) create the tree
-        super::interface::create_tree_fresh(&target, &store)?;
+        super::interface::create_tree_fresh(dir, &store)?;
         // (2) create the preload
-        super::flush::oneshot::flush_preload(&target, &store)?;
+        super::flush::oneshot::flush_preload(dir, &store)?;
         // (3) do a full flush
-        super::flush::flush_full(target, &store)?;
+        super::flush::flush_full(dir.clone(), &store)?;
         return Ok(store);
     }
-    let mut preload = self::read_preload()?;
+    let upgraded = migrate::migrate_tree(&dir.resolve(DIR_KSROOT), &dir.resolve(DIR_BACKUPS))?;
+    if upgraded != 0 {
+        log::info!("Upgraded {upgraded} files to the current format");
+    }
+    self::read_full_from(&dir.resolve(DIR_KSROOT))
+}
+
+/// Read an entire tree located at `root` (for example, a snapshot) and return a [`Memstore`]
+pub fn read_full_from(root: &str) -> StorageEngineResult<Memstore> {
+    let mut preload = self::read_preload_from(root)?;
     // HACK(@ohsayan): Pop off the preload from the serial read_keyspace list. It will fail
     assert!(preload.remove(&SYSTEM));
-    let system_keyspace = self::read_keyspace::<SystemKeyspace>(&SYSTEM)?;
+    let system_keyspace = self::read_keyspace_from::<SystemKeyspace>(root, &SYSTEM)?;
     let ksmap = Coremap::with_capacity(preload.len());
     for ksid in preload {
-        let ks = self::read_keyspace::<Keyspace>(&ksid)?;
+        let ks = self::read_keyspace_from::<Keyspace>(root, &ksid)?;
         ksmap.upsert(ksid, Arc::new(ks));
     }
     // HACK(@ohsayan): Now pop system back in here
@@ -254,8 +422,8 @@ pub fn read_full() -> StorageEngineResult<Memstore> {
 }
 
 /// Check if the `data` directory is non-empty (if not: we're on a new instance)
-pub fn is_new_instance() -> StorageEngineResult<bool> {
-    match fs::read_dir("data") {
+pub fn is_new_instance(dir: &DataDir) -> StorageEngineResult<bool> {
+    match fs::read_dir(dir.resolve(DIR_ROOT)) {
         Ok(mut dir) => Ok(dir.next().is_none()),
         Err(e) if e.kind().eq(&ErrorKind::NotFound) => Ok(true),
         Err(e) => Err(StorageEngineError::ioerror_extra(
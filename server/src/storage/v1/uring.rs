@@ -0,0 +1,191 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # io_uring flushes
+//!
+//! With the `io-uring` feature (on Linux), the files that we flush to disk are written with
+//! io_uring. Writes are buffered and submitted in large batches (instead of a `write(2)` for
+//! every few kilobytes), and the file is synced with an `fsync` submission. If the kernel
+//! doesn't support io_uring, the file is written to as usual
+
+use {
+    crate::IoResult,
+    io_uring::{opcode, squeue, types, IoUring},
+    std::{
+        fs::File,
+        io::{Error as IoError, ErrorKind, Seek, SeekFrom, Write},
+        os::unix::io::AsRawFd,
+    },
+};
+
+/// The number of bytes that are buffered before they're submitted
+const RING_BUF_CAP: usize = 1024 * 1024;
+/// The number of entries in the ring (we only ever have one operation in flight)
+const RING_ENTRIES: u32 = 2;
+
+/// A file that is written to with io_uring
+pub struct RingFile {
+    file: File,
+    /// `None` if we couldn't set up a ring (and have to write to the file as usual)
+    ring: Option<IoUring>,
+    buf: Vec<u8>,
+    /// the offset at which the next write goes
+    offset: u64,
+}
+
+impl RingFile {
+    /// Create (or truncate) the file at `path`
+    pub fn create(path: &str) -> IoResult<Self> {
+        let file = File::create(path)?;
+        let ring = match IoUring::new(RING_ENTRIES) {
+            Ok(ring) => Some(ring),
+            Err(e) => {
+                log::debug!("Failed to set up io_uring for flushing ({e}). Using write(2)");
+                None
+            }
+        };
+        Ok(Self {
+            file,
+            ring,
+            buf: Vec::new(),
+            offset: 0,
+        })
+    }
+    /// Submit an operation and wait for it to complete
+    fn run(ring: &mut IoUring, entry: squeue::Entry) -> IoResult<usize> {
+        unsafe {
+            // SAFETY: The buffer that the entry points to (if any) outlives the
+            // operation since we wait for it to complete
+            ring.submission()
+                .push(&entry)
+                .map_err(|_| IoError::new(ErrorKind::Other, "io_uring submission queue is full"))?;
+        }
+        ring.submit_and_wait(1)?;
+        let completion = ring
+            .completion()
+            .next()
+            .ok_or_else(|| IoError::new(ErrorKind::Other, "missing io_uring completion"))?;
+        match completion.result() {
+            err if err < 0 => Err(IoError::from_raw_os_error(-err)),
+            ret => Ok(ret as usize),
+        }
+    }
+    /// Write out everything that was buffered
+    fn write_buffered(&mut self) -> IoResult<()> {
+        let Self {
+            file,
+            ring,
+            buf,
+            offset,
+        } = self;
+        let ring = match ring {
+            Some(ring) => ring,
+            None => return Ok(()),
+        };
+        let fd = types::Fd(file.as_raw_fd());
+        let mut written = 0;
+        while written < buf.len() {
+            let pending = &buf[written..];
+            let len = pending.len().min(u32::MAX as usize) as u32;
+            let write = opcode::Write::new(fd, pending.as_ptr(), len)
+                .offset(*offset as _)
+                .build();
+            let n = Self::run(ring, write)?;
+            if n == 0 {
+                return Err(ErrorKind::WriteZero.into());
+            }
+            written += n;
+            *offset += n as u64;
+        }
+        buf.clear();
+        Ok(())
+    }
+    /// Write out everything that was buffered and sync the file to disk
+    pub fn sync_all(&mut self) -> IoResult<()> {
+        self.write_buffered()?;
+        match self.ring {
+            Some(ref mut ring) => {
+                let fsync = opcode::Fsync::new(types::Fd(self.file.as_raw_fd())).build();
+                Self::run(ring, fsync).map(|_| ())
+            }
+            None => self.file.sync_all(),
+        }
+    }
+}
+
+impl Write for RingFile {
+    fn write(&mut self, data: &[u8]) -> IoResult<usize> {
+        if self.ring.is_none() {
+            return self.file.write(data);
+        }
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= RING_BUF_CAP {
+            self.write_buffered()?;
+        }
+        Ok(data.len())
+    }
+    fn flush(&mut self) -> IoResult<()> {
+        self.write_buffered()
+    }
+}
+
+impl Seek for RingFile {
+    /// Only seeking from the start is supported (that's all we need to go back and write a
+    /// file's header)
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        self.write_buffered()?;
+        match (&self.ring, pos) {
+            (None, pos) => self.file.seek(pos),
+            (Some(_), SeekFrom::Start(offset)) => {
+                self.offset = offset;
+                Ok(offset)
+            }
+            (Some(_), _) => Err(IoError::new(
+                ErrorKind::Unsupported,
+                "can only seek from the start of a ring file",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::fs};
+
+    #[test]
+    fn write_and_sync() {
+        const PATH: &str = "ringfile_write_and_sync.bin";
+        let data: Vec<u8> = (0..RING_BUF_CAP * 2 + 1234).map(|i| i as u8).collect();
+        let mut file = RingFile::create(PATH).unwrap();
+        for chunk in data.chunks(8192) {
+            file.write_all(chunk).unwrap();
+        }
+        file.sync_all().unwrap();
+        drop(file);
+        assert_eq!(fs::read(PATH).unwrap(), data);
+        fs::remove_file(PATH).unwrap();
+    }
+}
@@ -0,0 +1,722 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Streams
+//!
+//! A _stream_ is an ordered log of entries that lives in a keyspace. Every entry is a list of
+//! items, and it gets an ID when it's appended: the time (in milliseconds since the UNIX epoch)
+//! at which it was appended and a sequence number that tells apart the entries appended in the
+//! same millisecond, written as `<time>-<seq>`. IDs only ever increase, even if the clock goes
+//! back. Streams are used with:
+//! - `XADD <stream> <item> [<item> ...]`: append an entry, returning its ID
+//! - `XRANGE <stream> <start> <end> [<count>]`: the entries with IDs between `start` and `end`
+//! (both included, where `-` and `+` stand for the first and the last entries)
+//! - `XREAD <stream> <after> <count> <timeout>`: up to `count` entries with IDs after `after`
+//! (where `$` stands for the last entry), waiting up to `timeout` milliseconds for an entry to
+//! be appended if there aren't any (`0` doesn't wait). Nil is returned if none were appended in
+//! time. Since this blocks the connection, it can't be queued in a transaction
+//! - `XLEN <stream>`: the number of entries in a stream
+//! - `XTRIM <stream> <maxlen>`: drop the oldest entries so that at most `maxlen` are left
+//!
+//! An ID without a sequence number stands for the first ID at that time (or the last one, for
+//! the end of a range). Entries are returned as a flat array with the ID, the number of items
+//! and the items of every entry.
+//!
+//! ## Changefeeds
+//!
+//! `XFEED ON <stream>` turns `stream` into the _changefeed_ of the current keyspace: every write
+//! to the keyspace that is recorded in the append-only log (or sent to replicas) is also
+//! appended to it, as an entry with the name of the table followed by the query. `XFEED OFF`
+//! turns it off again. This needs the `admin` permission.
+//!
+//! ## Persistence
+//!
+//! Streams are kept in a log at [`STREAMS_PATH`], which uses the same record format as the
+//! append-only log (see [`aof`]), is encrypted the same way and is synced to the disk with the
+//! same `fsync` policy (or left to the OS if the append-only log is disabled). Every change is
+//! recorded as a query: `XADD <id> <stream> <item> ...`, `XTRIM <stream> <maxlen>` and
+//! `XFEED [<stream>]`. The log is read (and compacted) on startup. It takes the place of the
+//! append-only log for streams: `XADD` and `XTRIM` are writes, but they aren't
+//! [`LOGGED`](crate::queryengine::commands::ActionFlags::LOGGED), so they're never recorded
+//! twice (or replayed against the keyspaces on startup).
+//!
+//! Note: streams are local to a node; they're neither replicated nor pushed to peers (and a
+//! replica's changefeed records the writes that it gets from its primary). Dropping a keyspace
+//! doesn't drop its streams
+
+use {
+    crate::{
+        actions::ActionResult,
+        config::FsyncPolicy,
+        corestore::memstore::ObjectID,
+        dbnet::prelude::*,
+        kvengine::now_millis,
+        storage::v1::aof::{self, Record},
+        IoResult,
+    },
+    core::{
+        fmt, str,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+    parking_lot::Mutex,
+    std::{
+        collections::{HashMap, VecDeque},
+        fs::{self, File, OpenOptions},
+        io::{ErrorKind, Write},
+        sync::Arc,
+    },
+    tokio::{
+        sync::watch,
+        time::{self, Duration},
+    },
+};
+
+#[cfg(test)]
+mod tests;
+
+/// The path to the log that holds the streams
+pub const STREAMS_PATH: &str = "data/streams";
+
+const RECORD_XADD: &[u8] = b"XADD";
+const RECORD_XTRIM: &[u8] = b"XTRIM";
+const RECORD_XFEED: &[u8] = b"XFEED";
+const FEED_ON: &[u8] = b"on";
+const FEED_OFF: &[u8] = b"off";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+/// The ID of an entry in a stream
+pub struct StreamId {
+    /// the time (in milliseconds since the UNIX epoch) at which the entry was appended
+    pub time: u64,
+    /// tells apart the entries appended in the same millisecond
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: Self = Self { time: 0, seq: 0 };
+    pub const MAX: Self = Self {
+        time: u64::MAX,
+        seq: u64::MAX,
+    };
+    pub const fn new(time: u64, seq: u64) -> Self {
+        Self { time, seq }
+    }
+    /// Returns the ID of an entry appended after this one at `now`
+    pub const fn next(self, now: u64) -> Self {
+        if now > self.time {
+            Self::new(now, 0)
+        } else if self.seq == u64::MAX {
+            Self::new(self.time + 1, 0)
+        } else {
+            Self::new(self.time, self.seq + 1)
+        }
+    }
+    /// Parse an ID written as `<time>-<seq>`, or as `<time>` (in which case the sequence number
+    /// is `seq`)
+    pub fn parse(raw: &[u8], seq: u64) -> Option<Self> {
+        let raw = str::from_utf8(raw).ok()?;
+        match raw.split_once('-') {
+            Some((time, seq)) => Some(Self::new(time.parse().ok()?, seq.parse().ok()?)),
+            None => Some(Self::new(raw.parse().ok()?, seq)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.time, self.seq)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+/// An entry in a stream
+pub struct Entry {
+    pub id: StreamId,
+    pub items: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Default)]
+/// A stream, along with the ID of the last entry that was appended to it (which is kept around
+/// even if the entry is trimmed, so that IDs never go back)
+struct Stream {
+    entries: VecDeque<Arc<Entry>>,
+    last: StreamId,
+}
+
+impl Stream {
+    fn push(&mut self, entry: Entry) {
+        self.last = entry.id;
+        self.entries.push_back(Arc::new(entry));
+    }
+    /// Returns up to `count` entries starting at the index `from`, that have an ID of at
+    /// most `end`
+    fn take_from(&self, from: usize, end: StreamId, count: usize) -> Vec<Arc<Entry>> {
+        self.entries
+            .range(from..)
+            .take_while(|entry| entry.id <= end)
+            .take(count)
+            .cloned()
+            .collect()
+    }
+    fn range(&self, start: StreamId, end: StreamId, count: usize) -> Vec<Arc<Entry>> {
+        let from = self.entries.partition_point(|entry| entry.id < start);
+        self.take_from(from, end, count)
+    }
+    fn after(&self, after: StreamId, count: usize) -> Vec<Arc<Entry>> {
+        let from = self.entries.partition_point(|entry| entry.id <= after);
+        self.take_from(from, StreamId::MAX, count)
+    }
+    fn trim(&mut self, maxlen: usize) -> usize {
+        let excess = self.entries.len().saturating_sub(maxlen);
+        self.entries.drain(..excess);
+        excess
+    }
+}
+
+#[derive(Debug, Default)]
+/// The streams of a keyspace
+struct Space {
+    streams: HashMap<Box<[u8]>, Stream>,
+    /// the stream that every write to the keyspace is appended to (if any)
+    feed: Option<Box<[u8]>>,
+}
+
+impl Space {
+    /// Apply a record from the log
+    fn apply(&mut self, args: Vec<Vec<u8>>) {
+        let mut args = args.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(kind), Some(id), Some(name)) if kind == RECORD_XADD => {
+                let stream = self.streams.entry(name.into_boxed_slice()).or_default();
+                match StreamId::parse(&id, 0) {
+                    Some(id) if id > stream.last => stream.push(Entry {
+                        id,
+                        items: args.collect(),
+                    }),
+                    _ => log::warn!("Ignoring an entry with a bad ID in the streams log"),
+                }
+            }
+            (Some(kind), Some(name), Some(maxlen)) if kind == RECORD_XTRIM => {
+                let maxlen = str::from_utf8(&maxlen)
+                    .ok()
+                    .and_then(|maxlen| maxlen.parse().ok());
+                match (self.streams.get_mut(name.as_slice()), maxlen) {
+                    (Some(stream), Some(maxlen)) => {
+                        stream.trim(maxlen);
+                    }
+                    _ => log::warn!("Ignoring a bad trim in the streams log"),
+                }
+            }
+            (Some(kind), feed, None) if kind == RECORD_XFEED => {
+                self.feed = feed.map(Vec::into_boxed_slice);
+            }
+            _ => log::warn!("Ignoring an unknown record in the streams log"),
+        }
+    }
+    /// Encode the records that recreate this keyspace's streams into `buf` (encrypting them if
+    /// files are encrypted)
+    fn encode(&self, ks: &ObjectID, buf: &mut Vec<u8>) -> IoResult<()> {
+        let mut encode = |args: &[&[u8]]| -> IoResult<()> {
+            let mut record = Vec::new();
+            aof::encode_record(
+                &mut record,
+                now_millis(),
+                Some(ks),
+                None,
+                args.iter().copied(),
+            );
+            buf.extend_from_slice(&aof::seal_record(&record)?);
+            Ok(())
+        };
+        if let Some(feed) = self.feed.as_deref() {
+            encode(&[RECORD_XFEED, feed])?;
+        }
+        for (name, stream) in self.streams.iter() {
+            let name: &[u8] = name;
+            if stream.last == StreamId::MIN {
+                // nothing was ever appended to it
+                continue;
+            }
+            if stream.entries.is_empty() {
+                // keep the last ID around
+                let last = stream.last.to_string();
+                encode(&[RECORD_XADD, last.as_bytes(), name])?;
+                encode(&[RECORD_XTRIM, name, b"0"])?;
+            }
+            for entry in stream.entries.iter() {
+                let id = entry.id.to_string();
+                let mut args = vec![RECORD_XADD, id.as_bytes(), name];
+                args.extend(entry.items.iter().map(Vec::as_slice));
+                encode(&args)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    spaces: HashMap<ObjectID, Space>,
+    /// the log (if the streams are persisted)
+    log: Option<File>,
+}
+
+/// Append a record to the log (if any), encrypting it if files are encrypted
+fn write_record(
+    log: &mut Option<File>,
+    fsync: FsyncPolicy,
+    ks: &ObjectID,
+    args: &[&[u8]],
+) -> IoResult<()> {
+    if let Some(log) = log.as_mut() {
+        let mut record = Vec::new();
+        aof::encode_record(
+            &mut record,
+            now_millis(),
+            Some(ks),
+            None,
+            args.iter().copied(),
+        );
+        log.write_all(&aof::seal_record(&record)?)?;
+        if fsync == FsyncPolicy::Always {
+            log.sync_data()?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+/// The streams of every keyspace
+pub struct Streams {
+    state: Mutex<State>,
+    fsync: FsyncPolicy,
+    /// the number of keyspaces with a changefeed
+    feeds: AtomicUsize,
+    /// signalled whenever an entry is appended to any stream
+    appended: watch::Sender<()>,
+    /// cloned by those who wait for the signal (and keeps it from failing)
+    waiter: watch::Receiver<()>,
+}
+
+impl Streams {
+    fn with_state(state: State, fsync: FsyncPolicy) -> Self {
+        let feeds = state
+            .spaces
+            .values()
+            .filter(|space| space.feed.is_some())
+            .count();
+        let (appended, waiter) = watch::channel(());
+        Self {
+            state: Mutex::new(state),
+            fsync,
+            feeds: AtomicUsize::new(feeds),
+            appended,
+            waiter,
+        }
+    }
+    /// Streams that are only kept in memory
+    pub fn new() -> Self {
+        Self::with_state(State::default(), FsyncPolicy::No)
+    }
+    /// Load the streams from the log at `path` (if it exists), compacting it, and keep
+    /// recording to it
+    pub fn open(path: &str, fsync: FsyncPolicy) -> IoResult<Self> {
+        let records = match aof::read_log(path) {
+            Ok(records) => records,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut spaces: HashMap<ObjectID, Space> = HashMap::new();
+        for Record { keyspace, args, .. } in records {
+            match keyspace {
+                Some(ks) => spaces.entry(ks).or_default().apply(args),
+                None => log::warn!("Ignoring a record without a keyspace in the streams log"),
+            }
+        }
+        // the trimmed entries (and the records that don't matter anymore) are dropped
+        let mut buf = Vec::new();
+        for (ks, space) in spaces.iter() {
+            space.encode(ks, &mut buf)?;
+        }
+        let tmp = format!("{path}.tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&buf)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        let log = OpenOptions::new().append(true).open(path)?;
+        let state = State {
+            spaces,
+            log: Some(log),
+        };
+        Ok(Self::with_state(state, fsync))
+    }
+    /// Sync the log (if any) to the disk
+    pub fn sync(&self) -> IoResult<()> {
+        match self.state.lock().log.as_ref() {
+            Some(log) => log.sync_data(),
+            None => Ok(()),
+        }
+    }
+    /// Append an entry to a stream (creating it if it doesn't exist), returning its ID
+    pub fn add(&self, ks: &ObjectID, stream: &[u8], items: Vec<Vec<u8>>) -> IoResult<StreamId> {
+        let id = {
+            let mut state = self.state.lock();
+            let State { spaces, log } = &mut *state;
+            let target = spaces
+                .entry(ks.clone())
+                .or_default()
+                .streams
+                .entry(stream.into())
+                .or_default();
+            let id = target.last.next(now_millis());
+            let encoded_id = id.to_string();
+            let mut args = vec![RECORD_XADD, encoded_id.as_bytes(), stream];
+            args.extend(items.iter().map(Vec::as_slice));
+            write_record(log, self.fsync, ks, &args)?;
+            target.push(Entry { id, items });
+            id
+        };
+        let _ = self.appended.send(());
+        Ok(id)
+    }
+    fn with_stream<T>(
+        &self,
+        ks: &ObjectID,
+        stream: &[u8],
+        f: impl FnOnce(&Stream) -> T,
+    ) -> Option<T> {
+        let state = self.state.lock();
+        state
+            .spaces
+            .get(ks)
+            .and_then(|space| space.streams.get(stream))
+            .map(f)
+    }
+    /// Returns up to `count` entries with IDs between `start` and `end` (both included)
+    pub fn range(
+        &self,
+        ks: &ObjectID,
+        stream: &[u8],
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+    ) -> Vec<Arc<Entry>> {
+        self.with_stream(ks, stream, |stream| stream.range(start, end, count))
+            .unwrap_or_default()
+    }
+    /// Returns up to `count` entries with IDs after `after`
+    pub fn read_after(
+        &self,
+        ks: &ObjectID,
+        stream: &[u8],
+        after: StreamId,
+        count: usize,
+    ) -> Vec<Arc<Entry>> {
+        self.with_stream(ks, stream, |stream| stream.after(after, count))
+            .unwrap_or_default()
+    }
+    /// Like [`Streams::read_after`], but if there are no such entries, this waits up to
+    /// `timeout` for one to be appended
+    pub async fn wait_after(
+        &self,
+        ks: &ObjectID,
+        stream: &[u8],
+        after: StreamId,
+        count: usize,
+        timeout: Duration,
+    ) -> Vec<Arc<Entry>> {
+        let deadline = time::Instant::now() + timeout;
+        let mut changed = self.waiter.clone();
+        loop {
+            let entries = self.read_after(ks, stream, after, count);
+            if !entries.is_empty() {
+                return entries;
+            }
+            if time::timeout_at(deadline, changed.changed()).await.is_err() {
+                return entries;
+            }
+        }
+    }
+    /// Returns the ID of the last entry appended to a stream
+    pub fn last_id(&self, ks: &ObjectID, stream: &[u8]) -> StreamId {
+        self.with_stream(ks, stream, |stream| stream.last)
+            .unwrap_or_default()
+    }
+    /// Returns the number of entries in a stream
+    pub fn len(&self, ks: &ObjectID, stream: &[u8]) -> usize {
+        self.with_stream(ks, stream, |stream| stream.entries.len())
+            .unwrap_or_default()
+    }
+    /// Drop the oldest entries of a stream so that at most `maxlen` are left, returning the
+    /// number of entries that were dropped
+    pub fn trim(&self, ks: &ObjectID, stream: &[u8], maxlen: usize) -> IoResult<usize> {
+        let mut state = self.state.lock();
+        let State { spaces, log } = &mut *state;
+        match spaces
+            .get_mut(ks)
+            .and_then(|space| space.streams.get_mut(stream))
+        {
+            Some(target) if target.entries.len() > maxlen => {
+                let maxlen_encoded = maxlen.to_string();
+                write_record(
+                    log,
+                    self.fsync,
+                    ks,
+                    &[RECORD_XTRIM, stream, maxlen_encoded.as_bytes()],
+                )?;
+                Ok(target.trim(maxlen))
+            }
+            _ => Ok(0),
+        }
+    }
+    /// Set (or unset) the changefeed of a keyspace
+    pub fn set_feed(&self, ks: &ObjectID, feed: Option<&[u8]>) -> IoResult<()> {
+        let mut state = self.state.lock();
+        let State { spaces, log } = &mut *state;
+        let space = spaces.entry(ks.clone()).or_default();
+        match feed {
+            Some(feed) => write_record(log, self.fsync, ks, &[RECORD_XFEED, feed])?,
+            None => write_record(log, self.fsync, ks, &[RECORD_XFEED])?,
+        }
+        match (space.feed.is_some(), feed.is_some()) {
+            (false, true) => {
+                self.feeds.fetch_add(1, Ordering::AcqRel);
+            }
+            (true, false) => {
+                self.feeds.fetch_sub(1, Ordering::AcqRel);
+            }
+            _ => {}
+        }
+        space.feed = feed.map(Into::into);
+        Ok(())
+    }
+    /// Returns true if any keyspace has a changefeed
+    pub fn has_feeds(&self) -> bool {
+        self.feeds.load(Ordering::Acquire) != 0
+    }
+    /// Append a write that was just run on `ks.table` to the changefeed of `ks` (if any)
+    pub fn record_change(&self, ks: &ObjectID, table: Option<&ObjectID>, args: &[&[u8]]) {
+        if !self.has_feeds() {
+            return;
+        }
+        let feed = match self.state.lock().spaces.get(ks) {
+            Some(Space {
+                feed: Some(feed), ..
+            }) => feed.clone(),
+            _ => return,
+        };
+        let mut items = Vec::with_capacity(args.len() + 1);
+        items.push(table.map(|table| table.to_vec()).unwrap_or_default());
+        items.extend(args.iter().map(|arg| arg.to_vec()));
+        if let Err(e) = self.add(ks, &feed, items) {
+            log::error!("Failed to append to a changefeed with error: {}", e);
+        }
+    }
+}
+
+impl Default for Streams {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the current keyspace, which every stream lives in
+fn current_keyspace<P: ProtocolSpec>(handle: &Corestore) -> ActionResult<&ObjectID> {
+    match handle.get_ids().0 {
+        Some(ks) => Ok(ks),
+        None => util::err(P::RSTRING_DEFAULT_UNSET),
+    }
+}
+
+fn parse_number<T: str::FromStr>(number: &[u8]) -> Option<T> {
+    str::from_utf8(number)
+        .ok()
+        .and_then(|number| number.parse().ok())
+}
+
+action! {
+    /// Run an `XADD <stream> <item> [<item> ...]` query
+    fn xadd(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len > 1)?;
+        if !registry::memory_okay() {
+            return util::err(P::RSTRING_MEMORY_LIMIT_EXCEEDED);
+        }
+        let ks = self::current_keyspace::<P>(handle)?;
+        let stream = unsafe {
+            // SAFETY: We have already checked that there are at least two items
+            act.next_unchecked()
+        };
+        let items = act.map(<[u8]>::to_vec).collect();
+        match handle.get_streams().add(ks, stream, items) {
+            Ok(id) => con.write_string(&id.to_string()).await?,
+            Err(e) => {
+                log::error!("Failed to append to a stream with error: {}", e);
+                return util::err(P::RCODE_SERVER_ERR);
+            }
+        }
+        Ok(())
+    }
+    /// Run an `XRANGE <stream> <start> <end> [<count>]` query
+    fn xrange(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 3 || len == 4)?;
+        let ks = self::current_keyspace::<P>(handle)?;
+        let (stream, start, end) = unsafe {
+            // SAFETY: We have already checked that there are at least three items
+            (
+                act.next_unchecked(),
+                act.next_unchecked(),
+                act.next_unchecked(),
+            )
+        };
+        let start = match start {
+            b"-" => Some(StreamId::MIN),
+            start => StreamId::parse(start, 0),
+        };
+        let end = match end {
+            b"+" => Some(StreamId::MAX),
+            end => StreamId::parse(end, u64::MAX),
+        };
+        let count = match act.next() {
+            Some(count) => self::parse_number(count),
+            None => Some(usize::MAX),
+        };
+        let (start, end, count) = match (start, end, count) {
+            (Some(start), Some(end), Some(count)) => (start, end, count),
+            _ => return util::err(P::RCODE_WRONGTYPE_ERR),
+        };
+        let entries = handle.get_streams().range(ks, stream, start, end, count);
+        self::write_entries(con, &entries).await?;
+        Ok(())
+    }
+    /// Run an `XLEN <stream>` query
+    fn xlen(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1)?;
+        let ks = self::current_keyspace::<P>(handle)?;
+        let stream = unsafe {
+            // SAFETY: We have already checked the length
+            act.next_unchecked()
+        };
+        let len = handle.get_streams().len(ks, stream);
+        con.write_usize(len).await?;
+        Ok(())
+    }
+    /// Run an `XTRIM <stream> <maxlen>` query, returning the number of entries that were
+    /// dropped
+    fn xtrim(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 2)?;
+        let ks = self::current_keyspace::<P>(handle)?;
+        let (stream, maxlen) = unsafe {
+            // SAFETY: We have already checked the length
+            (act.next_unchecked(), act.next_unchecked())
+        };
+        let maxlen = match self::parse_number(maxlen) {
+            Some(maxlen) => maxlen,
+            None => return util::err(P::RCODE_WRONGTYPE_ERR),
+        };
+        match handle.get_streams().trim(ks, stream, maxlen) {
+            Ok(dropped) => con.write_usize(dropped).await?,
+            Err(e) => {
+                log::error!("Failed to trim a stream with error: {}", e);
+                return util::err(P::RCODE_SERVER_ERR);
+            }
+        }
+        Ok(())
+    }
+    /// Run an `XFEED ON <stream>` or an `XFEED OFF` query
+    fn xfeed(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 1 || len == 2)?;
+        let ks = self::current_keyspace::<P>(handle)?;
+        let toggle = unsafe {
+            // SAFETY: We have already checked that there is at least one item
+            act.next_lowercase_unchecked()
+        };
+        let feed = match (toggle.as_ref(), act.next()) {
+            (FEED_ON, Some(stream)) => Some(stream),
+            (FEED_OFF, None) => None,
+            _ => return util::err(P::RCODE_ACTION_ERR),
+        };
+        if let Err(e) = handle.get_streams().set_feed(ks, feed) {
+            log::error!("Failed to set the changefeed with error: {}", e);
+            return util::err(P::RCODE_SERVER_ERR);
+        }
+        con._write_raw(P::RCODE_OKAY).await?;
+        Ok(())
+    }
+    /// Run an `XREAD <stream> <after> <count> <timeout>` query. This is run without holding the
+    /// transaction lock, since it can wait for a while
+    fn xread(handle: &Corestore, con: &mut Connection<C, P>, mut act: ActionIter<'a>) {
+        ensure_length::<P>(act.len(), |len| len == 4)?;
+        let ks = self::current_keyspace::<P>(handle)?;
+        let (stream, after, count, timeout) = unsafe {
+            // SAFETY: We have already checked the length
+            (
+                act.next_unchecked(),
+                act.next_unchecked(),
+                act.next_unchecked(),
+                act.next_unchecked(),
+            )
+        };
+        let streams = handle.get_streams();
+        let after = match after {
+            b"$" => Some(streams.last_id(ks, stream)),
+            after => StreamId::parse(after, 0),
+        };
+        let (after, count, timeout) = match (
+            after,
+            self::parse_number(count),
+            self::parse_number(timeout),
+        ) {
+            (Some(after), Some(count), Some(timeout)) => {
+                (after, count, Duration::from_millis(timeout))
+            }
+            _ => return util::err(P::RCODE_WRONGTYPE_ERR),
+        };
+        let entries = streams.wait_after(ks, stream, after, count, timeout).await;
+        if entries.is_empty() {
+            con._write_raw(P::RCODE_NIL).await?;
+        } else {
+            self::write_entries(con, &entries).await?;
+        }
+        Ok(())
+    }
+}
+
+action! {
+    /// Write out entries as a flat array with the ID, the number of items and the items of
+    /// every entry
+    fn write_entries(con: &mut Connection<C, P>, entries: &[Arc<Entry>]) {
+        let len = entries.iter().map(|entry| entry.items.len() + 2).sum();
+        con.write_array_header(len).await?;
+        for entry in entries {
+            con.write_string(&entry.id.to_string()).await?;
+            con.write_usize(entry.items.len()).await?;
+            for item in entry.items.iter() {
+                con.write_binary(item).await?;
+            }
+        }
+        Ok(())
+    }
+}
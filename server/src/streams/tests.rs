@@ -0,0 +1,179 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    super::{Entry, StreamId, Streams},
+    crate::{config::FsyncPolicy, corestore::memstore::DEFAULT},
+    std::{fs, sync::Arc},
+    tokio::time::Duration,
+};
+
+fn entry(time: u64, seq: u64, items: &[&[u8]]) -> Arc<Entry> {
+    Arc::new(Entry {
+        id: StreamId::new(time, seq),
+        items: items.iter().map(|item| item.to_vec()).collect(),
+    })
+}
+
+#[test]
+fn stream_ids_never_go_back() {
+    let id = StreamId::new(10, 0);
+    assert_eq!(id.next(20), StreamId::new(20, 0));
+    assert_eq!(id.next(10), StreamId::new(10, 1));
+    // the clock went back
+    assert_eq!(id.next(5), StreamId::new(10, 1));
+    assert_eq!(StreamId::new(10, u64::MAX).next(10), StreamId::new(11, 0));
+    assert_eq!(StreamId::new(10, 1).to_string(), "10-1");
+    assert_eq!(StreamId::parse(b"10-1", 0), Some(StreamId::new(10, 1)));
+    assert_eq!(StreamId::parse(b"10", 5), Some(StreamId::new(10, 5)));
+    assert_eq!(StreamId::parse(b"10-", 0), None);
+    assert_eq!(StreamId::parse(b"ten", 0), None);
+}
+
+#[test]
+fn read_ranges_and_trim() {
+    let streams = Streams::new();
+    let ids: Vec<StreamId> = (0..5)
+        .map(|i| {
+            let item = i.to_string();
+            streams
+                .add(&DEFAULT, b"events", vec![item.into_bytes()])
+                .unwrap()
+        })
+        .collect();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(streams.len(&DEFAULT, b"events"), 5);
+    assert_eq!(streams.last_id(&DEFAULT, b"events"), ids[4]);
+    let range = streams.range(&DEFAULT, b"events", ids[1], ids[3], usize::MAX);
+    assert_eq!(
+        range.iter().map(|entry| entry.id).collect::<Vec<_>>(),
+        &ids[1..4]
+    );
+    assert_eq!(range[0].items, vec![b"1".to_vec()]);
+    let range = streams.range(&DEFAULT, b"events", StreamId::MIN, StreamId::MAX, 2);
+    assert_eq!(range.len(), 2);
+    let after = streams.read_after(&DEFAULT, b"events", ids[3], usize::MAX);
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0].id, ids[4]);
+    // a stream that doesn't exist is just empty
+    assert!(streams
+        .read_after(&DEFAULT, b"nope", StreamId::MIN, 10)
+        .is_empty());
+    assert_eq!(streams.len(&DEFAULT, b"nope"), 0);
+    assert_eq!(streams.trim(&DEFAULT, b"events", 2).unwrap(), 3);
+    assert_eq!(streams.len(&DEFAULT, b"events"), 2);
+    assert_eq!(streams.trim(&DEFAULT, b"events", 2).unwrap(), 0);
+}
+
+#[test]
+fn streams_are_loaded_from_the_log() {
+    let path = std::env::temp_dir().join(format!("skyd-streams-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let (kept, trimmed) = {
+        let streams = Streams::open(path, FsyncPolicy::No).unwrap();
+        streams
+            .add(&DEFAULT, b"events", vec![b"a".to_vec()])
+            .unwrap();
+        let kept = streams
+            .add(&DEFAULT, b"events", vec![b"b".to_vec(), b"c".to_vec()])
+            .unwrap();
+        let trimmed = streams.add(&DEFAULT, b"gone", Vec::new()).unwrap();
+        streams.trim(&DEFAULT, b"events", 1).unwrap();
+        streams.trim(&DEFAULT, b"gone", 0).unwrap();
+        streams.set_feed(&DEFAULT, Some(b"events")).unwrap();
+        (kept, trimmed)
+    };
+    let streams = Streams::open(path, FsyncPolicy::No).unwrap();
+    let all = streams.range(
+        &DEFAULT,
+        b"events",
+        StreamId::MIN,
+        StreamId::MAX,
+        usize::MAX,
+    );
+    assert_eq!(
+        all,
+        vec![entry(kept.time, kept.seq, &[b"b".as_slice(), b"c"])]
+    );
+    assert!(streams.has_feeds());
+    // the ID of the last entry is kept even though the entry is gone
+    assert_eq!(streams.len(&DEFAULT, b"gone"), 0);
+    assert_eq!(streams.last_id(&DEFAULT, b"gone"), trimmed);
+    // and the log was compacted, so it loads the same way again
+    drop(streams);
+    let streams = Streams::open(path, FsyncPolicy::No).unwrap();
+    assert_eq!(streams.last_id(&DEFAULT, b"gone"), trimmed);
+    assert_eq!(streams.len(&DEFAULT, b"events"), 1);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn changefeed_records_writes() {
+    let streams = Streams::new();
+    streams.record_change(&DEFAULT, Some(&DEFAULT), &[b"SET".as_slice(), b"x", b"1"]);
+    assert_eq!(streams.len(&DEFAULT, b"changes"), 0);
+    streams.set_feed(&DEFAULT, Some(b"changes")).unwrap();
+    assert!(streams.has_feeds());
+    streams.record_change(&DEFAULT, Some(&DEFAULT), &[b"SET".as_slice(), b"x", b"1"]);
+    let changes = streams.read_after(&DEFAULT, b"changes", StreamId::MIN, usize::MAX);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        changes[0].items,
+        vec![
+            b"default".to_vec(),
+            b"SET".to_vec(),
+            b"x".to_vec(),
+            b"1".to_vec()
+        ]
+    );
+    streams.set_feed(&DEFAULT, None).unwrap();
+    assert!(!streams.has_feeds());
+    streams.record_change(&DEFAULT, Some(&DEFAULT), &[b"DEL".as_slice(), b"x"]);
+    assert_eq!(streams.len(&DEFAULT, b"changes"), 1);
+}
+
+#[tokio::test]
+async fn blocking_reads_wake_up_on_appends() {
+    let streams = Arc::new(Streams::new());
+    let after = streams.last_id(&DEFAULT, b"jobs");
+    // nothing is appended, so this times out
+    let entries = streams
+        .wait_after(&DEFAULT, b"jobs", after, 10, Duration::from_millis(10))
+        .await;
+    assert!(entries.is_empty());
+    let writer = streams.clone();
+    let append = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        writer
+            .add(&DEFAULT, b"jobs", vec![b"job".to_vec()])
+            .unwrap()
+    });
+    let entries = streams
+        .wait_after(&DEFAULT, b"jobs", after, 10, Duration::from_secs(10))
+        .await;
+    let id = append.await.unwrap();
+    assert_eq!(entries, vec![entry(id.time, id.seq, &[b"job".as_slice()])]);
+}
@@ -26,7 +26,7 @@
 
 use {
     crate::auth::provider::testsuite_data,
-    skytable::{query, Element, RespCode},
+    skytable::{query, types::Array, Element, RespCode},
 };
 
 macro_rules! assert_autherror {
@@ -136,6 +136,19 @@ async fn auth_login_rootuser_fail_bad_creds() {
     assert_auth_bad_credentials!(con, query!("auth", "login", "root", "badpass"))
 }
 
+// sys ping doesn't need a login
+#[sky_macros::dbtest_func(port = 2005, norun = true)]
+async fn sys_ping_without_login() {
+    runeq!(
+        con,
+        query!("sys", "ping"),
+        Element::String("PONG".to_owned())
+    );
+    // but everything else does
+    assert_auth_bad_credentials!(con, query!("sys", "ping", "extra"));
+    assert_auth_bad_credentials!(con, query!("sys", "info", "version"))
+}
+
 // auth adduser
 // auth adduser fail because disabled
 #[sky_macros::dbtest_func]
@@ -157,6 +170,128 @@ async fn auth_createuser_testuser_fail() {
     assert_auth_perm_error!(con, query!("auth", "adduser", "someuser"))
 }
 
+// flushdb fails because not root
+#[sky_macros::dbtest_func(port = 2005, auth_testuser = true)]
+async fn flushdb_testuser_fail() {
+    assert_auth_perm_error!(con, query!("flushdb"))
+}
+
+// setting the log level fails because not root
+#[sky_macros::dbtest_func(port = 2005, auth_testuser = true)]
+async fn loglevel_testuser_fail() {
+    assert_auth_perm_error!(con, query!("loglevel", "auth", "debug"))
+}
+
+// monitor fails because not root
+#[sky_macros::dbtest_func(port = 2005, auth_testuser = true)]
+async fn monitor_testuser_fail() {
+    assert_auth_perm_error!(con, query!("monitor"))
+}
+
+// auth grant/revoke
+/// log back in as root, so that the table can be flushed once the test is done
+macro_rules! login_as_root {
+    ($con:expr) => {
+        assert_okay!(
+            $con,
+            query!(
+                "auth",
+                "login",
+                testsuite_data::TESTSUITE_ROOT_USER,
+                testsuite_data::TESTSUITE_ROOT_TOKEN
+            )
+        )
+    };
+}
+#[sky_macros::dbtest_func(port = 2005, auth_rootuser = true)]
+async fn readonly_user_cannot_write() {
+    let key: String = con
+        .run_query(query!("auth", "adduser", "readonlyuser", "read"))
+        .await
+        .unwrap();
+    assert_okay!(con, query!("auth", "login", "readonlyuser", key));
+    runeq!(
+        con,
+        query!("get", "x"),
+        Element::RespCode(RespCode::NotFound)
+    );
+    assert_auth_perm_error!(con, query!("set", "x", "100"));
+    assert_auth_perm_error!(con, query!("mksnap"));
+    login_as_root!(con);
+}
+#[sky_macros::dbtest_func(port = 2005, auth_rootuser = true)]
+async fn grant_and_revoke_okay_because_root() {
+    let key: String = con
+        .run_query(query!("auth", "adduser", "grantuser", "read"))
+        .await
+        .unwrap();
+    assert_okay!(con, query!("auth", "grant", "grantuser", "write"));
+    assert_okay!(con, query!("auth", "revoke", "grantuser", "read"));
+    assert_okay!(con, query!("auth", "login", "grantuser", key));
+    assert_okay!(con, query!("set", "x", "100"));
+    assert_auth_perm_error!(con, query!("get", "x"));
+    login_as_root!(con);
+}
+#[sky_macros::dbtest_func(port = 2005, auth_testuser = true)]
+async fn grant_fail_because_not_root() {
+    assert_auth_perm_error!(con, query!("auth", "grant", "testuser", "admin"))
+}
+#[sky_macros::dbtest_func(port = 2005, auth_rootuser = true)]
+async fn grant_fail_because_root() {
+    assert_autherror!(
+        con,
+        query!("auth", "revoke", "root", "write"),
+        RespCode::ErrorString("err-auth-root-permissions".to_owned())
+    )
+}
+#[sky_macros::dbtest_func(port = 2005, auth_rootuser = true)]
+async fn grant_fail_because_unknown_permission() {
+    assert_autherror!(
+        con,
+        query!("auth", "grant", "testuser", "everything"),
+        RespCode::ErrorString("err-auth-unknown-permission".to_owned())
+    )
+}
+
+// auth restrict/unrestrict
+#[sky_macros::dbtest_func(port = 2005, auth_rootuser = true)]
+async fn restricted_user_stays_in_keyspace() {
+    let key: String = con
+        .run_query(query!("auth", "adduser", "tenantuser"))
+        .await
+        .unwrap();
+    assert_okay!(con, query!("auth", "restrict", "tenantuser", "testsuite"));
+    assert_okay!(con, query!("auth", "login", "tenantuser", key));
+    // we're still in a table in `testsuite`
+    assert_okay!(con, query!("set", "x", "100"));
+    assert_auth_perm_error!(con, query!("use default"));
+    assert_auth_perm_error!(con, query!("inspect space default"));
+    runeq!(
+        con,
+        query!("inspect spaces"),
+        Element::Array(Array::NonNullStr(vec!["testsuite".to_owned()]))
+    );
+    login_as_root!(con);
+}
+#[sky_macros::dbtest_func(port = 2005, auth_rootuser = true)]
+async fn restricted_user_cannot_use_other_keyspace() {
+    let key: String = con
+        .run_query(query!("auth", "adduser", "othertenant"))
+        .await
+        .unwrap();
+    assert_okay!(con, query!("auth", "restrict", "othertenant", "otherspace"));
+    assert_okay!(con, query!("auth", "login", "othertenant", key));
+    assert_auth_perm_error!(con, query!("get", "x"));
+    assert_auth_perm_error!(con, query!("lssnap"));
+    runeq!(con, query!("heya"), Element::String("HEY!".to_owned()));
+    login_as_root!(con);
+    assert_okay!(con, query!("auth", "unrestrict", "othertenant"));
+}
+#[sky_macros::dbtest_func(port = 2005, auth_testuser = true)]
+async fn restrict_fail_because_not_root() {
+    assert_auth_perm_error!(con, query!("auth", "restrict", "testuser", "testsuite"))
+}
+
 // auth logout
 // auth logout failed because auth is disabled
 #[sky_macros::dbtest_func]
@@ -255,9 +390,57 @@ async fn listuser_fail_because_not_root() {
 }
 #[sky_macros::dbtest_func(port = 2005, auth_rootuser = true)]
 async fn listuser_okay_because_root() {
-    let ret: Vec<String> = con.run_query(query!("auth", "listuser")).await.unwrap();
-    assert!(ret.contains(&"root".to_owned()));
-    assert!(ret.contains(&"testuser".to_owned()));
+    let ret = con
+        .run_query_raw(&query!("auth", "listuser"))
+        .await
+        .unwrap();
+    let users = match ret {
+        Element::Array(Array::Recursive(users)) => users,
+        other => panic!("expected an array of users, got {:?}", other),
+    };
+    // every user is listed as [name, created, last login, role]
+    let roles: Vec<(String, String)> = users
+        .into_iter()
+        .map(|user| match user {
+            Element::Array(Array::Recursive(fields)) => match fields.as_slice() {
+                [
+                    Element::String(name),
+                    Element::UnsignedInt(_),
+                    Element::UnsignedInt(_),
+                    Element::String(role),
+                ] => (name.clone(), role.clone()),
+                _ => panic!("bad user entry: {:?}", fields),
+            },
+            other => panic!("bad user entry: {:?}", other),
+        })
+        .collect();
+    assert!(roles.contains(&("root".to_owned(), "root".to_owned())));
+    assert!(roles.contains(&("testuser".to_owned(), "admin".to_owned())));
+}
+
+// auth audit
+#[sky_macros::dbtest_func]
+async fn audit_fail_because_disabled() {
+    assert_auth_disabled!(con, query!("auth", "audit"));
+}
+#[sky_macros::dbtest_func(port = 2005, auth_testuser = true)]
+async fn audit_fail_because_not_root() {
+    assert_auth_perm_error!(con, query!("auth", "audit"))
+}
+#[sky_macros::dbtest_func(port = 2005, auth_rootuser = true)]
+async fn audit_okay_because_root() {
+    let _: String = con
+        .run_query(query!("auth", "adduser", "audited"))
+        .await
+        .unwrap();
+    assert_okay!(con, query!("auth", "deluser", "audited"));
+    let ret: Vec<String> = con.run_query(query!("auth", "audit", "100")).await.unwrap();
+    assert!(ret
+        .iter()
+        .any(|entry| entry.contains("claim-user user=root") && entry.ends_with("target=audited")));
+    assert!(ret
+        .iter()
+        .any(|entry| entry.contains("delete-user user=root") && entry.ends_with("target=audited")));
 }
 
 // auth whoami
@@ -287,6 +470,48 @@ async fn auth_whoami_okay_rootuser() {
     )
 }
 
+// context
+#[sky_macros::dbtest_func]
+async fn context_without_auth() {
+    runeq!(
+        con,
+        query!("use default"),
+        Element::RespCode(RespCode::Okay)
+    );
+    runeq!(
+        con,
+        query!("context"),
+        Element::Array(Array::Str(vec![
+            None,
+            Some("admin".to_owned()),
+            Some("default".to_owned()),
+            Some("Skyhash-2.0".to_owned())
+        ]))
+    )
+}
+#[sky_macros::dbtest_func(port = 2005, norun = true, auth_testuser = true)]
+async fn context_testuser() {
+    runeq!(
+        con,
+        query!("use default"),
+        Element::RespCode(RespCode::Okay)
+    );
+    runeq!(
+        con,
+        query!("context"),
+        Element::Array(Array::Str(vec![
+            Some(testsuite_data::TESTSUITE_TEST_USER.to_owned()),
+            Some("admin".to_owned()),
+            Some("default".to_owned()),
+            Some("Skyhash-2.0".to_owned())
+        ]))
+    )
+}
+#[sky_macros::dbtest_func(port = 2005, norun = true)]
+async fn context_fail_because_anonymous() {
+    assert_auth_bad_credentials!(con, query!("context"))
+}
+
 mod syntax_checks {
     use super::{NOAUTH, ONLYAUTH};
     use crate::auth::provider::testsuite_data::{
@@ -333,11 +558,17 @@ mod syntax_checks {
     }
     #[sky_macros::dbtest_func(port = 2005, norun = true)]
     async fn adduser_aerr() {
-        assert_authn_aerr!(
-            con,
-            query!("auth", "adduser", "user", "butextradata"),
-            ONLYAUTH
-        );
+        assert_authn_aerr!(con, query!("auth", "adduser"), ONLYAUTH);
+    }
+    #[sky_macros::dbtest_func(port = 2005, norun = true)]
+    async fn grant_revoke_aerr() {
+        assert_authn_aerr!(con, query!("auth", "grant", "someuser"), ONLYAUTH);
+        assert_authn_aerr!(con, query!("auth", "revoke", "someuser"), ONLYAUTH);
+    }
+    #[sky_macros::dbtest_func(port = 2005, norun = true)]
+    async fn restrict_aerr() {
+        assert_authn_aerr!(con, query!("auth", "restrict", "someuser"), ONLYAUTH);
+        assert_authn_aerr!(con, query!("auth", "unrestrict"), ONLYAUTH);
     }
     #[sky_macros::dbtest_func(port = 2005, norun = true)]
     async fn logout_aerr() {
@@ -364,6 +595,14 @@ mod syntax_checks {
         assert_authn_aerr!(con, query!("auth", "listuser", "extra argument"), ONLYAUTH);
     }
     #[sky_macros::dbtest_func(port = 2005, norun = true)]
+    async fn audit_aerr() {
+        assert_authn_aerr!(
+            con,
+            query!("auth", "audit", "10", "extra argument"),
+            ONLYAUTH
+        );
+    }
+    #[sky_macros::dbtest_func(port = 2005, norun = true)]
     async fn whoami_aerr() {
         assert_authn_aerr!(con, query!("auth", "whoami", "extra argument"));
     }
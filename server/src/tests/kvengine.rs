@@ -35,7 +35,10 @@
 #[sky_macros::dbtest_module]
 mod __private {
     #[cfg(test)]
-    use skytable::{types::Array, Element, Query, RespCode};
+    use skytable::{
+        types::{Array, RawString},
+        Element, Query, RespCode,
+    };
     /// Test a HEYA query: The server should return HEY!
     async fn test_heya() {
         query.push("heya");
@@ -140,6 +143,48 @@ mod __private {
         );
     }
 
+    /// Test a SET query with the NX flag, which is the same as a plain SET
+    async fn test_set_nx() {
+        query.push(vec!["set", "x", "100", "NX"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["set", "x", "200", "nx"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::OverwriteError)
+        );
+    }
+
+    /// Test a SET query with the XX flag, which only updates existing keys
+    async fn test_set_xx() {
+        query.push(vec!["set", "x", "100", "XX"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+        let mut query = Query::new();
+        query.push(vec!["set", "x", "100"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["set", "x", "200", "xx"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["get", "x"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("200".to_owned())
+        );
+    }
+
     /// Test an UPDATE query: which should return code: 0
     async fn test_update_single_okay() {
         // first set the key
@@ -247,6 +292,23 @@ mod __private {
         );
     }
 
+    /// Test an MDEL query: which should return the number of keys deleted
+    async fn test_mdel_multiple() {
+        setkeys!(
+            con,
+            "x":"100",
+            "y":"200"
+        );
+        query.push("mdel");
+        query.push("x");
+        query.push("y");
+        query.push("z");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+    }
+
     /// Test a DEL query with an incorrect number of arguments
     async fn test_del_syntax_error() {
         query.push("del");
@@ -883,6 +945,50 @@ mod __private {
         );
     }
 
+    async fn test_flushdb_async() {
+        setkeys!(
+            con,
+            "x":"100",
+            "y":"200"
+        );
+        query.push(vec!["flushdb", "async"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("dbsize");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(0)
+        );
+        // the table is still usable
+        setkeys!(
+            con,
+            "x":"100"
+        );
+    }
+
+    async fn test_flushdb_fqe_async() {
+        setkeys!(
+            con,
+            "x":"100"
+        );
+        query.push("flushdb");
+        query.push(__MYENTITY__);
+        query.push("ASYNC");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("dbsize");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(0)
+        );
+    }
+
     /// Test `FLUSHDB` with an incorrect number of arguments
     async fn test_flushdb_syntax_error() {
         query.push("flushdb");
@@ -893,6 +999,13 @@ mod __private {
             con.run_query_raw(&query).await.unwrap(),
             Element::RespCode(RespCode::ActionError)
         );
+        // two arguments, but the second isn't ASYNC
+        let mut query = Query::new();
+        query.push(vec!["flushdb", "x", "y"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
     }
 
     /// Test `USET` which returns okay
@@ -965,6 +1078,78 @@ mod __private {
         );
     }
 
+    async fn test_type() {
+        query.push("set");
+        query.push("x");
+        query.push("helloworld");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("type");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("str".to_owned())
+        );
+    }
+    async fn test_type_nil() {
+        query.push("type");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_type_syntax_error() {
+        query.push("type");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+
+    async fn test_typed_str() {
+        query.push("strset");
+        query.push("x");
+        query.push("helloworld");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("strget");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("helloworld".to_owned())
+        );
+    }
+    async fn test_typed_wrong_value_type() {
+        query.push("binset");
+        query.push("x");
+        query.push("helloworld");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("wrong-value-type".to_owned()))
+        );
+        let mut query = Query::new();
+        query.push("binget");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("wrong-value-type".to_owned()))
+        );
+    }
+    async fn test_typed_syntax_error() {
+        query.push("strget");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+
     /// Test `KEYLEN` with an incorrect number of arguments
     async fn test_keylen_syntax_error_args_one() {
         query.push("keylen");
@@ -1130,6 +1315,85 @@ mod __private {
             Element::RespCode(RespCode::ActionError)
         );
     }
+    async fn test_lskeys_match() {
+        setkeys!(
+            con,
+            "user:1":"100",
+            "user:2":"200",
+            "post:1":"300"
+        );
+        query.push("lskeys");
+        query.push("match");
+        query.push("user:*");
+        query.push("limit");
+        query.push("100");
+        let ret = con.run_query_raw(&query).await.unwrap();
+        if let Element::Array(Array::NonNullStr(arr)) = ret {
+            // the first element is the cursor
+            assert_eq!(arr[0], "0");
+            assert_eq!(arr.len(), 3);
+            assert!(arr.contains(&"user:1".to_owned()));
+            assert!(arr.contains(&"user:2".to_owned()));
+        } else {
+            panic!("Expected flat string array");
+        }
+    }
+    async fn test_lskeys_cursor_pagination() {
+        setkeys!(
+            con,
+            "x":"100",
+            "y":"200",
+            "z":"300"
+        );
+        let mut cursor = "0".to_owned();
+        let mut seen = Vec::new();
+        loop {
+            let mut query = Query::new();
+            query.push("lskeys");
+            query.push("limit");
+            query.push("1");
+            query.push("cursor");
+            query.push(&cursor);
+            let ret = con.run_query_raw(&query).await.unwrap();
+            if let Element::Array(Array::NonNullStr(mut arr)) = ret {
+                cursor = arr.remove(0);
+                assert!(arr.len() <= 1);
+                seen.extend(arr);
+            } else {
+                panic!("Expected flat string array");
+            }
+            if cursor == "0" {
+                break;
+            }
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, vec!["x".to_owned(), "y".to_owned(), "z".to_owned()]);
+    }
+    async fn test_lskeys_limit_bounds_examined_keys() {
+        setkeys!(
+            con,
+            "x":"100",
+            "y":"200",
+            "z":"300"
+        );
+        query.push(vec!["lskeys", "match", "nomatch*", "limit", "1"]);
+        let ret = con.run_query_raw(&query).await.unwrap();
+        if let Element::Array(Array::NonNullStr(arr)) = ret {
+            // nothing matched, but only one key was examined so the scan isn't done
+            assert_eq!(arr.len(), 1);
+            assert_ne!(arr[0], "0");
+        } else {
+            panic!("Expected flat string array");
+        }
+    }
+    async fn test_lskeys_match_missing_pattern() {
+        query.push("lskeys");
+        query.push("match");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
     async fn test_mpop_syntax_error() {
         query.push("mpop");
         assert_eq!(
@@ -1202,4 +1466,809 @@ mod __private {
             Element::RespCode(RespCode::NotFound)
         );
     }
+    async fn test_expire_syntax_error() {
+        query.push("expire");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+        let mut query = Query::new();
+        query.push("expire");
+        query.push("x");
+        query.push("ten");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+    }
+    async fn test_expire_nil() {
+        query.push("expire");
+        query.push("x");
+        query.push("10");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_expire_ttl_persist() {
+        setkeys!(
+            con,
+            "x":"100"
+        );
+        query.push("ttl");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("no-expiry".to_owned()))
+        );
+        let mut query = Query::new();
+        query.push("expire");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("ttl");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(100)
+        );
+        let mut query = Query::new();
+        query.push("persist");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("ttl");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("no-expiry".to_owned()))
+        );
+    }
+    async fn test_randomkey() {
+        query.push("randomkey");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+        setkeys!(
+            con,
+            "x":"100"
+        );
+        let mut query = Query::new();
+        query.push("randomkey");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("x".to_owned())
+        );
+        let mut query = Query::new();
+        query.push(vec!["randomkey", "y"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_sample() {
+        query.push(vec!["sample", "10"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::Array(Array::NonNullStr(vec![]))
+        );
+        setkeys!(
+            con,
+            "x":"100",
+            "y":"20"
+        );
+        let mut query = Query::new();
+        query.push(vec!["sample", "10"]);
+        if let Element::Array(Array::NonNullStr(mut keys)) =
+            con.run_query_raw(&query).await.unwrap()
+        {
+            keys.sort_unstable();
+            assert_eq!(keys, vec!["x".to_owned(), "y".to_owned()]);
+        } else {
+            panic!("Expected a non-null string array");
+        }
+        let mut query = Query::new();
+        query.push(vec!["sample", "1"]);
+        if let Element::Array(Array::NonNullStr(keys)) = con.run_query_raw(&query).await.unwrap() {
+            assert_eq!(keys.len(), 1);
+        } else {
+            panic!("Expected a non-null string array");
+        }
+        let mut query = Query::new();
+        query.push(vec!["sample", "10", "nosizes"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+        let mut query = Query::new();
+        query.push(vec!["sample", "ten"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+    }
+    async fn test_delprefix() {
+        setkeys!(
+            con,
+            "user:1":"a",
+            "user:2":"b",
+            "item:1":"c"
+        );
+        query.push(vec!["delprefix", "user:"]);
+        let id = match con.run_query_raw(&query).await.unwrap() {
+            Element::UnsignedInt(id) => id.to_string(),
+            other => panic!("Expected a job ID, got {other:?}"),
+        };
+        loop {
+            let mut query = Query::new();
+            query.push(vec!["sys", "job", &id]);
+            let progress = match con.run_query_raw(&query).await.unwrap() {
+                Element::Array(Array::Recursive(progress)) => progress,
+                other => panic!("Expected the progress of the job, got {other:?}"),
+            };
+            if progress[0] == Element::String("running".to_owned()) {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                continue;
+            }
+            assert_eq!(
+                progress,
+                vec![
+                    Element::String("done".to_owned()),
+                    Element::UnsignedInt(2),
+                    Element::UnsignedInt(2)
+                ]
+            );
+            break;
+        }
+        let mut query = Query::new();
+        query.push(vec!["exists", "user:1", "user:2", "item:1"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+        let mut query = Query::new();
+        query.push(vec!["sys", "job", "123456789"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+        let mut query = Query::new();
+        query.push(vec!["delprefix", "glob", "item:*"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_setex() {
+        setkeys!(
+            con,
+            "x":"100"
+        );
+        // overwrites the existing value
+        query.push(vec!["setex", "x", "100", "200"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["get", "x"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("200".to_owned())
+        );
+        let mut query = Query::new();
+        query.push(vec!["ttl", "x"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(100)
+        );
+        // and creates new ones
+        let mut query = Query::new();
+        query.push(vec!["setex", "y", "0", "300"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["get", "y"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_setex_syntax_error() {
+        query.push(vec!["setex", "x", "10"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+        let mut query = Query::new();
+        query.push(vec!["setex", "x", "ten", "100"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+    }
+    async fn test_expire_elapsed() {
+        setkeys!(
+            con,
+            "x":"100"
+        );
+        query.push("expire");
+        query.push("x");
+        query.push("0");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("get");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+        let mut query = Query::new();
+        query.push("ttl");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_expired_keys_are_hidden() {
+        setkeys!(
+            con,
+            "x":"100",
+            "y":"200"
+        );
+        query.push(vec!["expire", "x", "0"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("dbsize");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+        let mut query = Query::new();
+        query.push("lskeys");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::Array(Array::NonNullStr(vec!["y".to_owned()]))
+        );
+        let mut query = Query::new();
+        query.push(vec!["lskeys", "match", "*"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::Array(Array::NonNullStr(vec!["0".to_owned(), "y".to_owned()]))
+        );
+        let mut query = Query::new();
+        query.push(vec!["keylen", "x"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_replacing_a_value_clears_ttl() {
+        setkeys!(
+            con,
+            "x":"100",
+            "y":"200"
+        );
+        query.push(vec!["expire", "x", "100"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["expire", "y", "100"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["update", "x", "300"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["uset", "y", "400"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+        for key in ["x", "y"] {
+            let mut query = Query::new();
+            query.push(vec!["ttl", key]);
+            assert_eq!(
+                con.run_query_raw(&query).await.unwrap(),
+                Element::RespCode(RespCode::ErrorString("no-expiry".to_owned()))
+            );
+        }
+    }
+    async fn test_cas_okay() {
+        setkeys!(
+            con,
+            "x":"100"
+        );
+        query.push("cas");
+        query.push("x");
+        query.push("100");
+        query.push("200");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("get");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("200".to_owned())
+        );
+    }
+    async fn test_cas_mismatch() {
+        setkeys!(
+            con,
+            "x":"100"
+        );
+        query.push("cas");
+        query.push("x");
+        query.push("300");
+        query.push("200");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("cas-mismatch".to_owned()))
+        );
+        let mut query = Query::new();
+        query.push("get");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("100".to_owned())
+        );
+    }
+    async fn test_cas_nil() {
+        query.push("cas");
+        query.push("x");
+        query.push("100");
+        query.push("200");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_cas_syntax_error() {
+        query.push("cas");
+        query.push("x");
+        query.push("100");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_incr_decr() {
+        query.push("incr");
+        query.push("counter");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("1".to_owned())
+        );
+        let mut query = Query::new();
+        query.push("incrby");
+        query.push("counter");
+        query.push("10");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("11".to_owned())
+        );
+        let mut query = Query::new();
+        query.push("decrby");
+        query.push("counter");
+        query.push("20");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("-9".to_owned())
+        );
+        let mut query = Query::new();
+        query.push("decr");
+        query.push("counter");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("-10".to_owned())
+        );
+        let mut query = Query::new();
+        query.push("get");
+        query.push("counter");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("-10".to_owned())
+        );
+    }
+    async fn test_incr_not_an_integer() {
+        setkeys!(
+            con,
+            "x":"hello"
+        );
+        query.push("incr");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("not-an-integer".to_owned()))
+        );
+        let mut query = Query::new();
+        query.push("incrby");
+        query.push("y");
+        query.push("ten");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("not-an-integer".to_owned()))
+        );
+    }
+    async fn test_incr_overflow() {
+        setkeys!(
+            con,
+            "x":"9223372036854775807"
+        );
+        query.push("incr");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("integer-overflow".to_owned()))
+        );
+        let mut query = Query::new();
+        query.push("get");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("9223372036854775807".to_owned())
+        );
+    }
+    async fn test_incr_syntax_error() {
+        query.push("incr");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_append_strlen() {
+        query.push("append");
+        query.push("x");
+        query.push("hello");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(5)
+        );
+        let mut query = Query::new();
+        query.push("append");
+        query.push("x");
+        query.push(", world");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(12)
+        );
+        let mut query = Query::new();
+        query.push("get");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("hello, world".to_owned())
+        );
+        let mut query = Query::new();
+        query.push("strlen");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(12)
+        );
+    }
+    async fn test_strlen_nil() {
+        query.push("strlen");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_append_syntax_error() {
+        query.push("append");
+        query.push("x");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_watchprefix_unwatchprefix() {
+        query.push("watchprefix");
+        query.push("user:");
+        query.push("session:");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("unwatchprefix");
+        query.push("user:");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push("unwatchprefix");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+    }
+    async fn test_watchprefix_syntax_error() {
+        query.push("watchprefix");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_sys_metric_tablestats() {
+        query.push("mset");
+        query.push(vec!["x", "1", "y", "2", "z", "3"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(3)
+        );
+        let mut query = Query::new();
+        query.push(vec!["sys", "metric", "tablestats"]);
+        match con.run_query_raw(&query).await.unwrap() {
+            Element::Array(Array::Recursive(stats)) => {
+                assert_eq!(stats.len(), 5);
+                // buckets, occupied, tombstones, longest probe, load factor
+                assert_eq!(stats[1], Element::UnsignedInt(3));
+                assert_eq!(stats[2], Element::UnsignedInt(0));
+            }
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+    async fn test_bulkload() {
+        query.push(vec!["bulkload", "begin", "100"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["bulkload", "put", "x", "1", "y", "2"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        let mut query = Query::new();
+        query.push(vec!["bulkload", "put", "x", "3"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(1)
+        );
+        let mut query = Query::new();
+        query.push(vec!["bulkload", "end"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(3)
+        );
+        let mut query = Query::new();
+        query.push(vec!["get", "x"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("3".to_owned())
+        );
+    }
+    async fn test_bulkload_not_started() {
+        query.push(vec!["bulkload", "put", "x", "1"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("bulkload-not-started".to_owned()))
+        );
+        let mut query = Query::new();
+        query.push(vec!["bulkload", "end"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("bulkload-not-started".to_owned()))
+        );
+    }
+    async fn test_bulkload_nested() {
+        query.push(vec!["bulkload", "begin"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("bulkload-in-progress".to_owned()))
+        );
+        let mut query = Query::new();
+        query.push(vec!["bulkload", "end"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(0)
+        );
+    }
+    async fn test_bulkload_syntax_error() {
+        query.push(vec!["bulkload", "begin", "many"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+        let mut query = Query::new();
+        query.push(vec!["bulkload", "begin"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        // a key without a value
+        let mut query = Query::new();
+        query.push(vec!["bulkload", "put", "x"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+    async fn test_dump_restore() {
+        query.push(vec!["setex", "x", "100", "1"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["dump", "x"]);
+        let blob = match con.run_query_raw(&query).await.unwrap() {
+            Element::Binstr(blob) => blob,
+            other => panic!("expected a blob, got {other:?}"),
+        };
+        let mut query = Query::new();
+        query.push("restore");
+        query.push("y");
+        query.push(RawString::from(blob));
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["get", "y"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("1".to_owned())
+        );
+        // the TTL went along with the value
+        let mut query = Query::new();
+        query.push(vec!["ttl", "y"]);
+        match con.run_query_raw(&query).await.unwrap() {
+            Element::UnsignedInt(ttl) => assert!(ttl > 0 && ttl <= 100),
+            other => panic!("expected a TTL, got {other:?}"),
+        }
+    }
+    async fn test_dump_nil() {
+        query.push(vec!["dump", "x"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_restore_replace() {
+        query.push(vec!["set", "x", "1"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["set", "y", "2"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["dump", "x"]);
+        let blob = match con.run_query_raw(&query).await.unwrap() {
+            Element::Binstr(blob) => blob,
+            other => panic!("expected a blob, got {other:?}"),
+        };
+        let mut query = Query::new();
+        query.push("restore");
+        query.push("y");
+        query.push(RawString::from(blob.clone()));
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::OverwriteError)
+        );
+        let mut query = Query::new();
+        query.push("restore");
+        query.push("y");
+        query.push(RawString::from(blob));
+        query.push("replace");
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["get", "y"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::String("1".to_owned())
+        );
+    }
+    async fn test_restore_bad_dump() {
+        query.push(vec!["restore", "x", "garbage"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::ErrorString("bad-dump".to_owned()))
+        );
+    }
+    async fn test_dbhash() {
+        query.push(vec!["dbhash"]);
+        let empty = con.run_query_raw(&query).await.unwrap();
+        assert_eq!(empty, Element::String("0000000000000000".to_owned()));
+        let mut query = Query::new();
+        query.push(vec!["mset", "x", "1", "y", "2"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        let mut query = Query::new();
+        query.push(vec!["dbhash"]);
+        let digest = con.run_query_raw(&query).await.unwrap();
+        assert_ne!(digest, empty);
+        // the same data written in another order has the same digest
+        let mut query = Query::new();
+        query.push(vec!["del", "x", "y"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        let mut query = Query::new();
+        query.push(vec!["mset", "y", "2", "x", "1"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(2)
+        );
+        let mut query = Query::new();
+        query.push(vec!["dbhash"]);
+        assert_eq!(con.run_query_raw(&query).await.unwrap(), digest);
+        // but a changed value doesn't
+        let mut query = Query::new();
+        query.push(vec!["update", "x", "3"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["dbhash"]);
+        assert_ne!(con.run_query_raw(&query).await.unwrap(), digest);
+    }
+    async fn test_hashrange() {
+        query.push(vec!["mset", "user:1", "a", "user:2", "b", "item:1", "c"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::UnsignedInt(3)
+        );
+        let mut query = Query::new();
+        query.push(vec!["hashrange", "user:"]);
+        let users = con.run_query_raw(&query).await.unwrap();
+        // keys outside the range don't change its digest
+        let mut query = Query::new();
+        query.push(vec!["update", "item:1", "d"]);
+        assert_eq!(
+            con.run_query_raw(&query).await.unwrap(),
+            Element::RespCode(RespCode::Okay)
+        );
+        let mut query = Query::new();
+        query.push(vec!["hashrange", "user:"]);
+        assert_eq!(con.run_query_raw(&query).await.unwrap(), users);
+    }
 }
@@ -0,0 +1,106 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest_module(table = "(string,hash<string>)")]
+mod __private {
+    use skytable::{query, types::Array, Element, RespCode};
+
+    // hset/hget
+    async fn test_hset_hget() {
+        let q = query!("hset", "user", "name", "sayan", "lang", "rust");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("hget", "user", "name");
+        runeq!(con, q, Element::String("sayan".to_owned()));
+        // updating an existing field doesn't add a field
+        let q = query!("hset", "user", "lang", "c", "editor", "vim");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("hget", "user", "lang");
+        runeq!(con, q, Element::String("c".to_owned()));
+    }
+    async fn test_hset_syntax_error() {
+        let q = query!("hset", "user", "name");
+        runeq!(con, q, Element::RespCode(RespCode::ActionError));
+    }
+    async fn test_hget_nil() {
+        let q = query!("hget", "user", "name");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+        let q = query!("hset", "user", "name", "sayan");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("hget", "user", "lang");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+
+    // hdel
+    async fn test_hdel_okay() {
+        let q = query!("hset", "user", "name", "sayan", "lang", "rust");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("hdel", "user", "lang", "editor");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("hgetall", "user");
+        assert_skyhash_arrayeq!(str, con, q, "name", "sayan");
+    }
+    async fn test_hdel_nil() {
+        let q = query!("hdel", "user", "name");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+
+    // hgetall
+    async fn test_hgetall_okay() {
+        let q = query!("hset", "user", "name", "sayan", "lang", "rust");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("hgetall", "user");
+        let mut pairs = match con.run_query_raw(&q).await.unwrap() {
+            Element::Array(Array::NonNullStr(flat)) => flat
+                .chunks(2)
+                .map(|pair| (pair[0].clone(), pair[1].clone()))
+                .collect::<Vec<_>>(),
+            other => panic!("expected a string array, got {:?}", other),
+        };
+        // hashes are unordered
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                ("lang".to_owned(), "rust".to_owned()),
+                ("name".to_owned(), "sayan".to_owned())
+            ]
+        );
+    }
+    async fn test_hgetall_nil() {
+        let q = query!("hgetall", "user");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+
+    // sanity tests
+    async fn test_get_model_error() {
+        let q = query!("get", "user");
+        runeq!(
+            con,
+            q,
+            Element::RespCode(RespCode::ErrorString("wrong-model".to_owned()))
+        );
+    }
+}
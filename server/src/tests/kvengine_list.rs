@@ -418,6 +418,94 @@ mod __private {
         runeq!(con, q, Element::RespCode(RespCode::Wrongtype));
     }
 
+    // push/pop tests
+    async fn test_rpush_creates_list() {
+        let q = query!("rpush", "mylist", "a", "b");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("rpush", "mylist", "c");
+        runeq!(con, q, Element::UnsignedInt(3));
+        let q = query!("lget", "mylist");
+        assert_skyhash_arrayeq!(str, con, q, "a", "b", "c");
+    }
+    async fn test_lpush_prepends_in_reverse() {
+        lset!(con, "mylist", "c");
+        let q = query!("lpush", "mylist", "b", "a");
+        runeq!(con, q, Element::UnsignedInt(3));
+        let q = query!("lget", "mylist");
+        assert_skyhash_arrayeq!(str, con, q, "a", "b", "c");
+    }
+    async fn test_lpush_creates_list_in_reverse() {
+        let q = query!("lpush", "mylist", "c", "b", "a");
+        runeq!(con, q, Element::UnsignedInt(3));
+        let q = query!("lget", "mylist");
+        assert_skyhash_arrayeq!(str, con, q, "a", "b", "c");
+        let q = query!("lget", "mylist", "last");
+        runeq!(con, q, Element::String("c".to_owned()));
+    }
+    async fn test_push_syntax_error() {
+        let q = query!("lpush", "mylist");
+        runeq!(con, q, Element::RespCode(RespCode::ActionError));
+    }
+    async fn test_lpop_rpop_okay() {
+        lset!(con, "mylist", "a", "b", "c");
+        let q = query!("lpop", "mylist");
+        runeq!(con, q, Element::String("a".to_owned()));
+        let q = query!("rpop", "mylist");
+        runeq!(con, q, Element::String("c".to_owned()));
+        let q = query!("lget", "mylist");
+        assert_skyhash_arrayeq!(str, con, q, "b");
+    }
+    async fn test_lpop_empty_list() {
+        lset!(con, "mylist");
+        let q = query!("lpop", "mylist");
+        runeq!(
+            con,
+            q,
+            Element::RespCode(RespCode::ErrorString("list-is-empty".to_owned()))
+        );
+    }
+    async fn test_rpop_nil() {
+        let q = query!("rpop", "mylist");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+    async fn test_blpop_brpop_okay() {
+        lset!(con, "mylist", "a", "b", "c");
+        let q = query!("blpop", "mylist", "100");
+        runeq!(con, q, Element::String("a".to_owned()));
+        let q = query!("brpop", "mylist", "100");
+        runeq!(con, q, Element::String("c".to_owned()));
+        let q = query!("lget", "mylist");
+        assert_skyhash_arrayeq!(str, con, q, "b");
+    }
+    async fn test_blocking_pop_times_out() {
+        lset!(con, "mylist");
+        let q = query!("blpop", "mylist", "10");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+        // a list that doesn't exist is waited for too
+        let q = query!("brpop", "nolist", "0");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+    async fn test_blocking_pop_bad_timeout() {
+        let q = query!("blpop", "mylist", "soon");
+        runeq!(con, q, Element::RespCode(RespCode::Wrongtype));
+    }
+    async fn test_lrange_okay() {
+        lset!(con, "mylist", "a", "b", "c", "d");
+        let q = query!("lrange", "mylist", "1", "3");
+        assert_skyhash_arrayeq!(str, con, q, "b", "c");
+        let q = query!("lrange", "mylist", "2");
+        assert_skyhash_arrayeq!(str, con, q, "c", "d");
+    }
+    async fn test_lrange_bad_index() {
+        lset!(con, "mylist", "a");
+        let q = query!("lrange", "mylist", "2", "4");
+        runeq!(
+            con,
+            q,
+            Element::RespCode(RespCode::ErrorString("bad-list-index".to_owned()))
+        );
+    }
+
     // sanity tests
     async fn test_get_model_error() {
         query.push("GET");
@@ -0,0 +1,130 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+/// Since sets are unordered, compare the returned members after sorting them
+macro_rules! assert_members_eq {
+    ($con:expr, $query:expr, $($val:expr),*) => {
+        match $con.run_query_raw(&$query).await.unwrap() {
+            skytable::Element::Array(skytable::types::Array::NonNullStr(mut members)) => {
+                members.sort();
+                let expected: Vec<String> = vec![$($val.to_owned(),)*];
+                assert_eq!(members, expected);
+            }
+            other => panic!("expected a string array, got {:?}", other),
+        }
+    };
+}
+
+#[sky_macros::dbtest_module(table = "(string,set<string>)")]
+mod __private {
+    use skytable::{query, Element, RespCode};
+
+    // sadd/srem
+    async fn test_sadd_creates_set() {
+        let q = query!("sadd", "myset", "a", "b", "a");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("sadd", "myset", "b", "c");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("scard", "myset");
+        runeq!(con, q, Element::UnsignedInt(3));
+    }
+    async fn test_sadd_syntax_error() {
+        let q = query!("sadd", "myset");
+        runeq!(con, q, Element::RespCode(RespCode::ActionError));
+    }
+    async fn test_srem_okay() {
+        let q = query!("sadd", "myset", "a", "b", "c");
+        runeq!(con, q, Element::UnsignedInt(3));
+        let q = query!("srem", "myset", "a", "c", "d");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("smembers", "myset");
+        assert_members_eq!(con, q, "b");
+    }
+    async fn test_srem_nil() {
+        let q = query!("srem", "myset", "a");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+
+    // smembers/sismember/scard
+    async fn test_smembers_okay() {
+        let q = query!("sadd", "myset", "c", "a", "b");
+        runeq!(con, q, Element::UnsignedInt(3));
+        let q = query!("smembers", "myset");
+        assert_members_eq!(con, q, "a", "b", "c");
+    }
+    async fn test_smembers_nil() {
+        let q = query!("smembers", "myset");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+    async fn test_sismember_okay() {
+        let q = query!("sadd", "myset", "a");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("sismember", "myset", "a");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("sismember", "myset", "b");
+        runeq!(con, q, Element::UnsignedInt(0));
+    }
+    async fn test_scard_nil() {
+        let q = query!("scard", "myset");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+
+    // sinter/sunion/sdiff
+    async fn test_sinter_sunion_sdiff() {
+        let q = query!("sadd", "set1", "a", "b", "c");
+        runeq!(con, q, Element::UnsignedInt(3));
+        let q = query!("sadd", "set2", "b", "c", "d");
+        runeq!(con, q, Element::UnsignedInt(3));
+        let q = query!("sinter", "set1", "set2");
+        assert_members_eq!(con, q, "b", "c");
+        let q = query!("sunion", "set1", "set2");
+        assert_members_eq!(con, q, "a", "b", "c", "d");
+        let q = query!("sdiff", "set1", "set2");
+        assert_members_eq!(con, q, "a");
+    }
+    async fn test_sinter_missing_set_is_empty() {
+        let q = query!("sadd", "set1", "a", "b");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("sinter", "set1", "nosuchset");
+        assert_members_eq!(con, q,);
+        let q = query!("sunion", "set1", "nosuchset");
+        assert_members_eq!(con, q, "a", "b");
+    }
+    async fn test_sinter_syntax_error() {
+        let q = query!("sinter");
+        runeq!(con, q, Element::RespCode(RespCode::ActionError));
+    }
+
+    // sanity tests
+    async fn test_get_model_error() {
+        let q = query!("get", "myset");
+        runeq!(
+            con,
+            q,
+            Element::RespCode(RespCode::ErrorString("wrong-model".to_owned()))
+        );
+    }
+}
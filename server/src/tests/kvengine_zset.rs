@@ -0,0 +1,107 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest_module(table = "(string,zset<string>)")]
+mod __private {
+    use skytable::{query, Element, RespCode};
+
+    // zadd
+    async fn test_zadd_okay() {
+        let q = query!("zadd", "board", "10", "alice", "20", "bob");
+        runeq!(con, q, Element::UnsignedInt(2));
+        // updating the score of an existing member doesn't add a member
+        let q = query!("zadd", "board", "30", "alice", "5", "carol");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("zrangebyscore", "board", "-inf", "+inf");
+        assert_skyhash_arrayeq!(str, con, q, "carol", "bob", "alice");
+    }
+    async fn test_zadd_bad_score() {
+        let q = query!("zadd", "board", "ten", "alice");
+        runeq!(
+            con,
+            q,
+            Element::RespCode(RespCode::ErrorString("bad-score".to_owned()))
+        );
+        let q = query!("zadd", "board", "nan", "alice");
+        runeq!(
+            con,
+            q,
+            Element::RespCode(RespCode::ErrorString("bad-score".to_owned()))
+        );
+    }
+    async fn test_zadd_syntax_error() {
+        let q = query!("zadd", "board", "10");
+        runeq!(con, q, Element::RespCode(RespCode::ActionError));
+    }
+
+    // zrangebyscore
+    async fn test_zrangebyscore_okay() {
+        let q = query!("zadd", "board", "1", "a", "2", "b", "3", "c", "4", "d");
+        runeq!(con, q, Element::UnsignedInt(4));
+        let q = query!("zrangebyscore", "board", "2", "3");
+        assert_skyhash_arrayeq!(str, con, q, "b", "c");
+        let q = query!("zrangebyscore", "board", "3.5", "+inf");
+        assert_skyhash_arrayeq!(str, con, q, "d");
+    }
+    async fn test_zrangebyscore_nil() {
+        let q = query!("zrangebyscore", "board", "0", "1");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+
+    // zrank
+    async fn test_zrank_okay() {
+        let q = query!("zadd", "board", "10", "alice", "20", "bob");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("zrank", "board", "bob");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("zrank", "board", "carol");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+
+    // zrem
+    async fn test_zrem_okay() {
+        let q = query!("zadd", "board", "10", "alice", "20", "bob");
+        runeq!(con, q, Element::UnsignedInt(2));
+        let q = query!("zrem", "board", "alice", "carol");
+        runeq!(con, q, Element::UnsignedInt(1));
+        let q = query!("zrank", "board", "bob");
+        runeq!(con, q, Element::UnsignedInt(0));
+    }
+    async fn test_zrem_nil() {
+        let q = query!("zrem", "board", "alice");
+        runeq!(con, q, Element::RespCode(RespCode::NotFound));
+    }
+
+    // sanity tests
+    async fn test_get_model_error() {
+        let q = query!("get", "board");
+        runeq!(
+            con,
+            q,
+            Element::RespCode(RespCode::ErrorString("wrong-model".to_owned()))
+        );
+    }
+}
@@ -32,13 +32,19 @@ mod macros;
 mod auth;
 mod ddl_tests;
 mod inspect_tests;
+mod issue_tests;
 mod kvengine;
 mod kvengine_encoding;
+mod kvengine_hash;
 mod kvengine_list;
+mod kvengine_set;
+mod kvengine_zset;
 mod persist;
 mod pipeline;
+mod quota;
+mod scripting;
 mod snapshot;
-mod issue_tests;
+mod transactions;
 
 mod tls {
     use skytable::{query, Element};
@@ -57,7 +63,7 @@ mod sys {
         crate::protocol::{LATEST_PROTOCOL_VERSION, LATEST_PROTOCOL_VERSIONSTRING},
         libsky::VERSION,
         sky_macros::dbtest_func as dbtest,
-        skytable::{query, Element, RespCode},
+        skytable::{query, types::Array, Element, RespCode},
     };
 
     #[dbtest]
@@ -103,6 +109,32 @@ mod sys {
         )
     }
     #[dbtest]
+    async fn sys_info_replication() {
+        runeq!(
+            con,
+            query!("sys", "info", "replication"),
+            Element::Array(Array::Recursive(vec![
+                Element::String("role".to_owned()),
+                Element::String("primary".to_owned()),
+                Element::String("replicas".to_owned()),
+                Element::UnsignedInt(0),
+            ]))
+        )
+    }
+    #[dbtest]
+    async fn sys_ping() {
+        runeq!(
+            con,
+            query!("sys", "ping"),
+            Element::String("PONG".to_owned())
+        );
+        runeq!(
+            con,
+            query!("sys", "ping", "but why this extra argument?"),
+            Element::RespCode(RespCode::ActionError)
+        )
+    }
+    #[dbtest]
     async fn sys_metric_aerr() {
         runeq!(
             con,
@@ -131,9 +163,115 @@ mod sys {
             Element::UnsignedInt
         )
     }
+    #[dbtest]
+    async fn sys_metric_counters() {
+        for metric in ["uptime", "commands", "keys", "memory"] {
+            runmatch!(con, query!("sys", "metric", metric), Element::UnsignedInt);
+        }
+        // we're connected, so there's at least one client
+        match con
+            .run_query_raw(&query!("sys", "metric", "clients"))
+            .await
+            .unwrap()
+        {
+            Element::UnsignedInt(clients) => assert!(clients >= 1),
+            other => panic!("unexpected response: {other:?}"),
+        }
+    }
+    #[dbtest]
+    async fn sys_stats() {
+        let stats = match con.run_query_raw(&query!("sys", "stats")).await.unwrap() {
+            Element::Array(Array::Recursive(stats)) => stats,
+            other => panic!("unexpected response: {other:?}"),
+        };
+        assert_eq!(stats.len() % 2, 0);
+        let names: Vec<&Element> = stats.iter().step_by(2).collect();
+        for name in [
+            "version", "health", "aof", "uptime", "clients", "commands", "hits", "misses", "keys",
+            "memory", "lastsave",
+        ] {
+            assert!(
+                names.contains(&&Element::String(name.to_owned())),
+                "missing {name}"
+            );
+        }
+        assert!(names.contains(&&Element::String("keyspace.default".to_owned())));
+    }
+    #[dbtest]
+    async fn sys_commands() {
+        let commands = match con.run_query_raw(&query!("sys", "commands")).await.unwrap() {
+            Element::Array(Array::Recursive(commands)) => commands,
+            other => panic!("unexpected response: {other:?}"),
+        };
+        assert_eq!(commands.len() % 3, 0);
+        let get = commands
+            .chunks(3)
+            .find(|spec| spec[0] == Element::String("GET".to_owned()))
+            .expect("missing GET");
+        assert_eq!(get[1], Element::UnsignedInt(1));
+        assert_eq!(get[2], Element::String("readonly".to_owned()));
+        let set = commands
+            .chunks(3)
+            .find(|spec| spec[0] == Element::String("SET".to_owned()))
+            .expect("missing SET");
+        assert_eq!(set[1], Element::UnsignedInt(2));
+        assert_eq!(set[2], Element::String("write+variadic".to_owned()));
+    }
+    #[dbtest]
+    async fn sys_stats_aerr() {
+        runeq!(
+            con,
+            query!("sys", "stats", "but why this extra argument?"),
+            Element::RespCode(RespCode::ErrorString("Unknown action".into()))
+        )
+    }
+    #[dbtest]
+    async fn sys_jobs() {
+        let id = match con
+            .run_query_raw(&query!("delprefix", "sys_jobs:"))
+            .await
+            .unwrap()
+        {
+            Element::UnsignedInt(id) => id,
+            other => panic!("Expected a job ID, got {other:?}"),
+        };
+        let jobs = match con
+            .run_query_raw(&query!("sys", "jobs", "list"))
+            .await
+            .unwrap()
+        {
+            Element::Array(Array::Recursive(jobs)) => jobs,
+            other => panic!("Expected the list of jobs, got {other:?}"),
+        };
+        let job = jobs
+            .chunks(5)
+            .find(|job| job[0] == Element::UnsignedInt(id))
+            .expect("the job isn't listed");
+        assert_eq!(job[1], Element::String("delprefix".to_owned()));
+        runeq!(
+            con,
+            query!("sys", "jobs", "kill", "123456789"),
+            Element::RespCode(RespCode::NotFound)
+        );
+        runeq!(
+            con,
+            query!("sys", "jobs", "kill", "one"),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+        runeq!(
+            con,
+            query!("sys", "jobs", "list", "all"),
+            Element::RespCode(RespCode::ActionError)
+        );
+        runeq!(
+            con,
+            query!("sys", "jobs", "stop"),
+            Element::RespCode(RespCode::ErrorString("unknown-property".into()))
+        );
+    }
 }
 
-use skytable::{query, Element, RespCode};
+use skytable::{query, types::Array, Element, RespCode};
 
 #[sky_macros::dbtest_func]
 async fn blueql_extra_args() {
@@ -143,3 +281,192 @@ async fn blueql_extra_args() {
         Element::RespCode(RespCode::ErrorString("bql-invalid-syntax".into()))
     );
 }
+
+#[sky_macros::dbtest_func]
+async fn monitor_syntax_error() {
+    runeq!(
+        con,
+        query!("monitor", "extra useless arg"),
+        Element::RespCode(RespCode::ActionError)
+    );
+    runeq!(
+        con,
+        query!("unmonitor", "extra useless arg"),
+        Element::RespCode(RespCode::ActionError)
+    );
+}
+
+#[sky_macros::dbtest_func]
+async fn unmonitor_without_monitor() {
+    runeq!(con, query!("unmonitor"), Element::RespCode(RespCode::Okay));
+}
+
+#[sky_macros::dbtest_func]
+async fn replicaof_syntax_error() {
+    runeq!(
+        con,
+        query!("replicaof", "localhost"),
+        Element::RespCode(RespCode::ActionError)
+    );
+    runeq!(
+        con,
+        query!("replicaof", "localhost", "2003", "root"),
+        Element::RespCode(RespCode::ActionError)
+    );
+    runeq!(
+        con,
+        query!("replicaof", "localhost", "notaport"),
+        Element::RespCode(RespCode::Wrongtype)
+    );
+    runeq!(
+        con,
+        query!("sync", "extra useless arg"),
+        Element::RespCode(RespCode::ActionError)
+    );
+}
+
+#[sky_macros::dbtest_func]
+async fn cluster_info_disabled() {
+    runeq!(
+        con,
+        query!("cluster", "info"),
+        Element::Array(Array::Recursive(vec![
+            Element::String("state".to_owned()),
+            Element::String("disabled".to_owned()),
+        ]))
+    );
+    runeq!(
+        con,
+        query!("cluster", "assign", "0", "100", "localhost", "2003"),
+        Element::RespCode(RespCode::ErrorString("cluster-disabled".to_owned()))
+    );
+}
+
+#[sky_macros::dbtest_func]
+async fn cluster_syntax_error() {
+    runeq!(
+        con,
+        query!("cluster"),
+        Element::RespCode(RespCode::ActionError)
+    );
+    runeq!(
+        con,
+        query!("cluster", "info", "extra"),
+        Element::RespCode(RespCode::ActionError)
+    );
+    runeq!(
+        con,
+        query!("cluster", "assign", "0", "16384", "localhost", "2003"),
+        Element::RespCode(RespCode::ErrorString("bad-slot".to_owned()))
+    );
+    runeq!(
+        con,
+        query!("cluster", "assign", "10", "0", "localhost", "2003"),
+        Element::RespCode(RespCode::ErrorString("bad-slot".to_owned()))
+    );
+    runeq!(
+        con,
+        query!("cluster", "myself", "localhost", "notaport"),
+        Element::RespCode(RespCode::Wrongtype)
+    );
+    runeq!(
+        con,
+        query!("cluster", "meet"),
+        Element::RespCode(RespCode::ErrorString("Unknown action".to_owned()))
+    );
+    runeq!(
+        con,
+        query!("cluster", "migrate", "10", "localhost"),
+        Element::RespCode(RespCode::ActionError)
+    );
+    // slots can only be moved in cluster mode
+    runeq!(
+        con,
+        query!("cluster", "migrate", "10", "localhost", "2004"),
+        Element::RespCode(RespCode::ErrorString("cluster-disabled".to_owned()))
+    );
+    runeq!(
+        con,
+        query!("cluster", "importing", "16384"),
+        Element::RespCode(RespCode::ErrorString("bad-slot".to_owned()))
+    );
+}
+
+#[sky_macros::dbtest_func]
+async fn replica_promote_on_primary() {
+    runeq!(
+        con,
+        query!("replica", "promote"),
+        Element::RespCode(RespCode::ErrorString("not-a-replica".to_owned()))
+    );
+    runeq!(
+        con,
+        query!("replica"),
+        Element::RespCode(RespCode::ActionError)
+    );
+    runeq!(
+        con,
+        query!("replica", "demote"),
+        Element::RespCode(RespCode::ErrorString("Unknown action".to_owned()))
+    );
+}
+
+mod loglevel {
+    use {
+        sky_macros::dbtest_func as dbtest,
+        skytable::{query, types::Array, Element, RespCode},
+    };
+
+    #[dbtest]
+    async fn loglevel_list() {
+        let levels = match con.run_query_raw(&query!("loglevel")).await.unwrap() {
+            Element::Array(Array::Recursive(levels)) => levels,
+            other => panic!("unexpected response: {other:?}"),
+        };
+        let names: Vec<&Element> = levels.iter().step_by(2).collect();
+        assert_eq!(
+            names,
+            ["default", "protocol", "coredb", "diskstore", "auth"]
+                .map(|name| Element::String(name.to_owned()))
+                .iter()
+                .collect::<Vec<&Element>>()
+        );
+    }
+    #[dbtest]
+    async fn loglevel_set_get() {
+        runeq!(
+            con,
+            query!("loglevel", "auth", "INFO"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            query!("loglevel", "auth"),
+            Element::String("info".to_owned())
+        );
+    }
+    #[dbtest]
+    async fn loglevel_unknown_module() {
+        runeq!(
+            con,
+            query!("loglevel", "network"),
+            Element::RespCode(RespCode::ErrorString("unknown-module".into()))
+        );
+    }
+    #[dbtest]
+    async fn loglevel_unknown_level() {
+        runeq!(
+            con,
+            query!("loglevel", "auth", "loud"),
+            Element::RespCode(RespCode::ErrorString("unknown-level".into()))
+        );
+    }
+    #[dbtest]
+    async fn loglevel_syntax_error() {
+        runeq!(
+            con,
+            query!("loglevel", "auth", "info", "extra useless arg"),
+            Element::RespCode(RespCode::ActionError)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest_module]
+mod __private {
+    use skytable::{query, types::Array, Element, RespCode};
+
+    async fn test_quota_set_get() {
+        runeq!(
+            con,
+            query!("quota", "set", "keyspace", "quotatest", "100", "0"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            query!("quota", "get", "keyspace", "quotatest"),
+            Element::Array(Array::Recursive(vec![
+                Element::String("maxkeys".to_owned()),
+                Element::UnsignedInt(100),
+                Element::String("maxbytes".to_owned()),
+                Element::UnsignedInt(0),
+                Element::String("keys".to_owned()),
+                Element::UnsignedInt(0),
+                Element::String("bytes".to_owned()),
+                Element::UnsignedInt(0),
+            ]))
+        );
+        // no limits is the same as no quota
+        runeq!(
+            con,
+            query!("quota", "set", "keyspace", "quotatest", "0", "0"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            query!("quota", "get", "keyspace", "quotatest"),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_quota_bad_limit() {
+        runeq!(
+            con,
+            query!("quota", "set", "user", "sayan", "-1", "0"),
+            Element::RespCode(RespCode::Wrongtype)
+        );
+        runeq!(
+            con,
+            query!("quota", "get", "user", "sayan"),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+}
@@ -0,0 +1,94 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest_module]
+mod __private {
+    use skytable::{query, types::Array, Element, RespCode};
+
+    macro_rules! err {
+        ($string:expr) => {
+            Element::RespCode(RespCode::ErrorString($string.to_owned()))
+        };
+    }
+
+    async fn test_eval() {
+        runeq!(con, query!("eval", "40 + 2"), Element::UnsignedInt(42));
+    }
+    async fn test_eval_writes() {
+        runeq!(
+            con,
+            query!("eval", "set(ARGS[0], ARGS[1]); get(ARGS[0])", "x", "100"),
+            Element::String("100".to_owned())
+        );
+        runeq!(con, query!("get", "x"), Element::String("100".to_owned()));
+        runeq!(
+            con,
+            query!("eval", r#"[set("x", "200"), del("x"), get("x")]"#),
+            Element::Array(Array::Recursive(vec![
+                Element::String("false".to_owned()),
+                Element::String("true".to_owned()),
+                Element::RespCode(RespCode::NotFound)
+            ]))
+        );
+    }
+    async fn test_evalsha() {
+        let sha = match con
+            .run_query_raw(&query!("script", "load", "ARGS.len()"))
+            .await
+            .unwrap()
+        {
+            Element::String(sha) => sha,
+            other => panic!("expected a SHA1, got {other:?}"),
+        };
+        runeq!(
+            con,
+            query!("evalsha", sha.as_str(), "a", "b"),
+            Element::UnsignedInt(2)
+        );
+        runeq!(
+            con,
+            query!("script", "exists", sha.as_str(), "0000"),
+            Element::Array(Array::Recursive(vec![
+                Element::UnsignedInt(1),
+                Element::UnsignedInt(0)
+            ]))
+        );
+    }
+    async fn test_evalsha_no_script() {
+        runeq!(con, query!("evalsha", "0000"), err!("no-script"));
+    }
+    async fn test_eval_script_error() {
+        runeq!(con, query!("eval", "let"), err!("script-error"));
+        runeq!(con, query!("eval", r#"throw "oops""#), err!("script-error"));
+        // scripts can't read the clock
+        runeq!(con, query!("eval", "timestamp()"), err!("script-error"));
+    }
+    async fn test_eval_inside_multi() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(con, query!("eval", "40 + 2"), err!("transaction-bad-query"));
+        runeq!(con, query!("exec"), err!("transaction-aborted"));
+    }
+}
@@ -26,10 +26,11 @@
 
 use {
     sky_macros::dbtest_func as dbtest,
-    skytable::{query, Element, RespCode},
+    skytable::{query, types::Array, Element, RespCode},
 };
 
 const SNAPSHOT_DISABLED: &str = "err-snapshot-disabled";
+const SNAPSHOT_NOT_FOUND: &str = "err-snapshot-not-found";
 
 #[dbtest]
 async fn snapshot_fail_because_local_disabled() {
@@ -87,3 +88,29 @@ async fn remote_snapshot_fail_because_already_exists() {
         }
     }
 }
+
+#[dbtest(skip_if_cfg = "persist-suite")]
+async fn lssnap_has_remote_snapshot() {
+    loop {
+        match con.run_query_raw(query!("mksnap", "listed")).await.unwrap() {
+            Element::RespCode(RespCode::Okay) => break,
+            Element::RespCode(RespCode::ErrorString(estr)) if estr.eq("err-snapshot-busy") => {}
+            x => panic!("snapshot failed: {:?}", x),
+        }
+    }
+    match con.run_query_raw(query!("lssnap")).await.unwrap() {
+        Element::Array(Array::NonNullStr(snapshots)) => {
+            assert!(snapshots.contains(&"listed".to_owned()))
+        }
+        x => panic!("Got unexpected element: {:?}", x),
+    }
+}
+
+#[dbtest]
+async fn restoresnap_fail_because_not_found() {
+    runeq!(
+        con,
+        query!("restoresnap", "doesnotexist"),
+        Element::RespCode(RespCode::ErrorString(SNAPSHOT_NOT_FOUND.to_owned()))
+    )
+}
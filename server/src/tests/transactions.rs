@@ -0,0 +1,218 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#[sky_macros::dbtest_module]
+mod __private {
+    use skytable::{query, types::Array, Element, RespCode};
+
+    macro_rules! err {
+        ($string:expr) => {
+            Element::RespCode(RespCode::ErrorString($string.to_owned()))
+        };
+    }
+
+    async fn test_txn_exec() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("set", "x", "100"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(
+            con,
+            query!("get", "x"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(
+            con,
+            query!("exec"),
+            Element::Array(Array::Recursive(vec![
+                Element::RespCode(RespCode::Okay),
+                Element::String("100".to_owned())
+            ]))
+        );
+        runeq!(con, query!("get", "x"), Element::String("100".to_owned()));
+    }
+    async fn test_txn_exec_with_action_error() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("set", "x", "100"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(
+            con,
+            query!("set", "x", "200"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(
+            con,
+            query!("exec"),
+            Element::Array(Array::Recursive(vec![
+                Element::RespCode(RespCode::Okay),
+                Element::RespCode(RespCode::OverwriteError)
+            ]))
+        );
+    }
+    async fn test_txn_discard() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("set", "x", "100"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(con, query!("discard"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("get", "x"),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_txn_not_started() {
+        runeq!(con, query!("exec"), err!("transaction-not-started"));
+        runeq!(con, query!("discard"), err!("transaction-not-started"));
+    }
+    async fn test_txn_nested() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(con, query!("multi"), err!("transaction-nested"));
+        runeq!(con, query!("discard"), Element::RespCode(RespCode::Okay));
+    }
+    async fn test_txn_bad_query_aborts() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("set", "x", "100"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(
+            con,
+            query!("create", "space", "x"),
+            err!("transaction-bad-query")
+        );
+        runeq!(con, query!("exec"), err!("transaction-aborted"));
+        runeq!(
+            con,
+            query!("get", "x"),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_txn_watch_unchanged() {
+        runeq!(
+            con,
+            query!("set", "x", "100"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, query!("watch", "x"), Element::RespCode(RespCode::Okay));
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("update", "x", "200"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(
+            con,
+            query!("exec"),
+            Element::Array(Array::Recursive(vec![Element::RespCode(RespCode::Okay)]))
+        );
+    }
+    async fn test_txn_watch_changed() {
+        runeq!(
+            con,
+            query!("set", "x", "100"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, query!("watch", "x"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("update", "x", "200"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("update", "x", "300"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(con, query!("exec"), Element::RespCode(RespCode::NotFound));
+        runeq!(con, query!("get", "x"), Element::String("200".to_owned()));
+    }
+    async fn test_txn_watch_changed_back() {
+        runeq!(
+            con,
+            query!("set", "x", "100"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, query!("watch", "x"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("update", "x", "200"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(
+            con,
+            query!("update", "x", "100"),
+            Element::RespCode(RespCode::Okay)
+        );
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("update", "x", "300"),
+            Element::String("QUEUED".to_owned())
+        );
+        // the value is the same, but it was changed in between
+        runeq!(con, query!("exec"), Element::RespCode(RespCode::NotFound));
+        runeq!(con, query!("get", "x"), Element::String("100".to_owned()));
+    }
+    async fn test_txn_wrong_arity_aborts() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("set", "x", "100"),
+            Element::String("QUEUED".to_owned())
+        );
+        runeq!(con, query!("get"), Element::RespCode(RespCode::ActionError));
+        runeq!(con, query!("exec"), err!("transaction-aborted"));
+        runeq!(
+            con,
+            query!("get", "x"),
+            Element::RespCode(RespCode::NotFound)
+        );
+    }
+    async fn test_txn_watch_inside_multi() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(con, query!("watch", "x"), err!("transaction-bad-query"));
+        runeq!(con, query!("discard"), Element::RespCode(RespCode::Okay));
+    }
+    async fn test_txn_blocking_pop_inside_multi() {
+        runeq!(con, query!("multi"), Element::RespCode(RespCode::Okay));
+        runeq!(
+            con,
+            query!("blpop", "x", "10"),
+            err!("transaction-bad-query")
+        );
+        runeq!(con, query!("exec"), err!("transaction-aborted"));
+    }
+}
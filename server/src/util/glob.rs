@@ -0,0 +1,147 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Glob matching
+//!
+//! A tiny glob matcher for byte strings that understands the following:
+//! - `*`: matches any sequence of bytes (including an empty one)
+//! - `?`: matches exactly one byte
+//! - `[abc]`, `[a-z]`: matches one byte from the set (`[^abc]` or `[!abc]` to negate)
+//! - `\x`: matches `x` literally
+
+/// Returns true if `input` matches the glob `pattern`
+pub fn glob_match(pattern: &[u8], input: &[u8]) -> bool {
+    let (mut p, mut i) = (0usize, 0usize);
+    // where to resume if we need to backtrack: (pattern position after the `*`, input position)
+    let mut backtrack: Option<(usize, usize)> = None;
+    while i < input.len() {
+        let step = match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p + 1, i));
+                p += 1;
+                continue;
+            }
+            Some(b'?') => Some(1),
+            Some(b'[') => match_class(&pattern[p..], input[i]),
+            Some(b'\\') if p + 1 < pattern.len() => {
+                if pattern[p + 1] == input[i] {
+                    Some(2)
+                } else {
+                    None
+                }
+            }
+            Some(c) if *c == input[i] => Some(1),
+            _ => None,
+        };
+        match (step, backtrack) {
+            (Some(advance), _) => {
+                p += advance;
+                i += 1;
+            }
+            (None, Some((bp, bi))) => {
+                // let the last `*` eat one more byte and try again
+                backtrack = Some((bp, bi + 1));
+                p = bp;
+                i = bi + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+    // the input is exhausted; only trailing stars may remain
+    pattern[p.min(pattern.len())..].iter().all(|c| *c == b'*')
+}
+
+/// Attempts to match `byte` against the class at the start of `pattern` (which begins with
+/// `[`). Returns the length of the class if it matched. An unterminated class is treated as
+/// a literal `[`
+fn match_class(pattern: &[u8], byte: u8) -> Option<usize> {
+    let mut pos = 1;
+    let negate = matches!(pattern.get(pos), Some(b'^') | Some(b'!'));
+    if negate {
+        pos += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match pattern.get(pos) {
+            None => {
+                // unterminated, so this is just a `[`
+                return if byte == b'[' { Some(1) } else { None };
+            }
+            Some(b']') if !first => {
+                pos += 1;
+                break;
+            }
+            Some(_) => {
+                let mut start = pattern[pos];
+                if start == b'\\' && pos + 1 < pattern.len() {
+                    pos += 1;
+                    start = pattern[pos];
+                }
+                let end = match (pattern.get(pos + 1), pattern.get(pos + 2)) {
+                    (Some(b'-'), Some(end)) if *end != b']' => {
+                        pos += 2;
+                        *end
+                    }
+                    _ => start,
+                };
+                matched |= start <= byte && byte <= end;
+                pos += 1;
+            }
+        }
+        first = false;
+    }
+    if matched != negate {
+        Some(pos)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match(b"*", b""));
+    assert!(glob_match(b"*", b"anything"));
+    assert!(glob_match(b"user:*", b"user:1"));
+    assert!(glob_match(b"user:*", b"user:"));
+    assert!(!glob_match(b"user:*", b"users"));
+    assert!(glob_match(b"*:name", b"user:1:name"));
+    assert!(glob_match(b"u*r*e", b"user:none"));
+    assert!(!glob_match(b"u*r*e", b"user:nonx"));
+    assert!(glob_match(b"h?llo", b"hello"));
+    assert!(!glob_match(b"h?llo", b"hllo"));
+    assert!(glob_match(b"h[ae]llo", b"hallo"));
+    assert!(!glob_match(b"h[ae]llo", b"hillo"));
+    assert!(glob_match(b"h[^e]llo", b"hallo"));
+    assert!(!glob_match(b"h[!e]llo", b"hello"));
+    assert!(glob_match(b"key[0-9]", b"key7"));
+    assert!(!glob_match(b"key[0-9]", b"keyx"));
+    assert!(glob_match(b"\\*star", b"*star"));
+    assert!(!glob_match(b"\\*star", b"xstar"));
+    assert!(glob_match(b"[", b"["));
+    assert!(glob_match(b"exact", b"exact"));
+    assert!(!glob_match(b"exact", b"exactly"));
+}
@@ -0,0 +1,222 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::format::Format,
+    clap::{Parser, Subcommand},
+};
+
+const HELP_TEMPLATE: &str = r#"
+{before-help}{name} {version}
+{author-with-newline}{about-with-newline}
+{usage-heading} {usage}
+
+{all-args}{after-help}
+"#;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None, help_template = HELP_TEMPLATE, arg_required_else_help = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub action: Action,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Sets the remote host to connect to",
+        default_value = "127.0.0.1",
+        value_name = "HOST"
+    )]
+    pub host: String,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Sets the remote port to connect to",
+        default_value_t = 2003,
+        value_name = "PORT"
+    )]
+    pub port: u16,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Sets the table to export from (or import into)",
+        default_value = "default.default",
+        value_name = "ENTITY"
+    )]
+    pub entity: String,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Sets the format of the file",
+        value_enum,
+        default_value_t = Format::Jsonl
+    )]
+    pub format: Format,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Sets the number of keys that are read (or written) per query",
+        default_value_t = 1000,
+        value_name = "COUNT"
+    )]
+    pub batch: usize,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Sets the user to log in as (if auth is enabled)",
+        requires = "token",
+        value_name = "USER"
+    )]
+    pub user: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Sets the token to log in with",
+        requires = "user",
+        value_name = "TOKEN"
+    )]
+    pub token: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Action {
+    /// Export the keys in a table to a file
+    Export {
+        #[arg(value_name = "FILE")]
+        file: String,
+    },
+    /// Import the keys in a file into a table
+    Import {
+        #[arg(value_name = "FILE")]
+        file: String,
+    },
+    /// Import the keys in a Redis RDB file (strings go into the table set with `--entity`)
+    ImportRdb {
+        #[arg(value_name = "FILE")]
+        file: String,
+        #[arg(
+            long,
+            help = "Sets the Redis database to import",
+            default_value_t = 0,
+            value_name = "DB"
+        )]
+        db: u64,
+        #[arg(
+            long,
+            help = "Sets the table to import lists into (lists are skipped if unset)",
+            value_name = "ENTITY"
+        )]
+        lists: Option<String>,
+        #[arg(
+            long,
+            help = "Sets the table to import sets into (sets are skipped if unset)",
+            value_name = "ENTITY"
+        )]
+        sets: Option<String>,
+        #[arg(
+            long,
+            help = "Sets the table to import hashes into (hashes are skipped if unset)",
+            value_name = "ENTITY"
+        )]
+        hashes: Option<String>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Cli};
+    use crate::format::Format;
+    use clap::Parser;
+
+    #[test]
+    fn test_export_defaults() {
+        let cli = Cli::parse_from(["sky-dump", "export", "dump.jsonl"]);
+        assert!(matches!(cli.action, Action::Export { file } if file == "dump.jsonl"));
+        assert_eq!(cli.host, "127.0.0.1");
+        assert_eq!(cli.port, 2003);
+        assert_eq!(cli.entity, "default.default");
+        assert_eq!(cli.format, Format::Jsonl);
+        assert_eq!(cli.batch, 1000);
+    }
+
+    #[test]
+    fn test_import_csv() {
+        let args = [
+            "sky-dump", "import", "dump.csv", "-f", "csv", "-e", "ks.tbl",
+        ];
+        let cli = Cli::parse_from(args);
+        assert!(matches!(cli.action, Action::Import { file } if file == "dump.csv"));
+        assert_eq!(cli.format, Format::Csv);
+        assert_eq!(cli.entity, "ks.tbl");
+    }
+
+    #[test]
+    fn test_import_rdb() {
+        let args = [
+            "sky-dump",
+            "import-rdb",
+            "dump.rdb",
+            "--db",
+            "2",
+            "--lists",
+            "ks.lists",
+        ];
+        let cli = Cli::parse_from(args);
+        match cli.action {
+            Action::ImportRdb {
+                file,
+                db,
+                lists,
+                sets,
+                hashes,
+            } => {
+                assert_eq!(file, "dump.rdb");
+                assert_eq!(db, 2);
+                assert_eq!(lists.as_deref(), Some("ks.lists"));
+                assert!(sets.is_none() && hashes.is_none());
+            }
+            other => panic!("unexpected action: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bad_args() {
+        assert!(Cli::try_parse_from(["sky-dump", "export", "f", "-b", "many"]).is_err());
+        // a user needs a token
+        assert!(Cli::try_parse_from(["sky-dump", "export", "f", "--user", "root"]).is_err());
+        assert!(Cli::try_parse_from(["sky-dump", "export", "f", "-f", "xml"]).is_err());
+    }
+}
@@ -0,0 +1,394 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    crate::{
+        error::{DumpResult, Error},
+        format::{self, Entry, Format, Value},
+        rdb::{RdbEntry, RdbReader},
+    },
+    skytable::{
+        sync::Connection,
+        types::{Array, RawString},
+        Element, Query, RespCode,
+    },
+    std::{
+        fs::File,
+        io::{BufReader, BufWriter, Write},
+        mem,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// The model of the table that is being exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Model {
+    Str,
+    List,
+    Set,
+    Hash,
+}
+
+impl Model {
+    /// Get the model from the type of a key (as returned by `TYPE`)
+    fn from_type(ty: &str) -> DumpResult<Self> {
+        match ty {
+            "str" | "binstr" => Ok(Self::Str),
+            _ if ty.starts_with("list<") => Ok(Self::List),
+            _ if ty.starts_with("set<") => Ok(Self::Set),
+            _ if ty.starts_with("hash<") => Ok(Self::Hash),
+            _ => Err(Error::Runtime(format!(
+                "exporting tables with values of type `{ty}` isn't supported"
+            ))),
+        }
+    }
+    /// The action that reads the whole value of a key
+    const fn read_action(&self) -> &'static str {
+        match self {
+            Self::Str => "GET",
+            Self::List => "LGET",
+            Self::Set => "SMEMBERS",
+            Self::Hash => "HGETALL",
+        }
+    }
+}
+
+pub fn run(con: &mut Connection, query: &Query) -> DumpResult<Element> {
+    Ok(con.run_query_raw(query)?)
+}
+
+pub fn unexpected(element: Element) -> Error {
+    Error::Server(format!("unexpected response: {:?}", element))
+}
+
+fn expect_count(element: Element) -> DumpResult<()> {
+    match element {
+        Element::UnsignedInt(_) => Ok(()),
+        other => Err(self::unexpected(other)),
+    }
+}
+
+/// Returns the elements of an array in a response (`None` for the null elements)
+fn into_array(element: Element) -> DumpResult<Vec<Option<Vec<u8>>>> {
+    match element {
+        Element::Array(Array::Bin(array)) => Ok(array),
+        Element::Array(Array::Str(array)) => Ok(array
+            .into_iter()
+            .map(|item| item.map(String::into_bytes))
+            .collect()),
+        Element::Array(Array::NonNullBin(array)) => Ok(array.into_iter().map(Some).collect()),
+        Element::Array(Array::NonNullStr(array)) => Ok(array
+            .into_iter()
+            .map(|item| Some(item.into_bytes()))
+            .collect()),
+        other => Err(self::unexpected(other)),
+    }
+}
+
+fn into_nonnull_array(element: Element) -> DumpResult<Vec<Vec<u8>>> {
+    self::into_array(element)?
+        .into_iter()
+        .map(|item| item.ok_or_else(|| Error::Server("unexpected null element".to_owned())))
+        .collect()
+}
+
+/// Find the model of the table from the type of one of `keys`
+fn detect_model(con: &mut Connection, keys: &[Vec<u8>]) -> DumpResult<Option<Model>> {
+    for key in keys {
+        let query = Query::from("TYPE").arg(RawString::from(key.clone()));
+        // anything else means that the key was removed after we listed it
+        if let Element::String(ty) = self::run(con, &query)? {
+            return Model::from_type(&ty).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Write the entries for `keys` to `w`, returning the number of entries that were written
+fn export_batch(
+    con: &mut Connection,
+    w: &mut impl Write,
+    format: Format,
+    model: Model,
+    keys: Vec<Vec<u8>>,
+) -> DumpResult<usize> {
+    let mut exported = 0;
+    if model == Model::Str {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        let mut query = Query::from("MGET");
+        keys.iter()
+            .for_each(|key| query.push(RawString::from(key.clone())));
+        let values = self::into_array(self::run(con, &query)?)?;
+        // a null value means that the key was removed after we listed it
+        for (key, value) in keys.into_iter().zip(values) {
+            if let Some(value) = value {
+                let value = Value::Str(value);
+                format::write_entry(w, format, &Entry { key, value })?;
+                exported += 1;
+            }
+        }
+        return Ok(exported);
+    }
+    for key in keys {
+        let query = Query::from(model.read_action()).arg(RawString::from(key.clone()));
+        let items = match self::run(con, &query)? {
+            Element::RespCode(RespCode::NotFound) => continue,
+            element => self::into_nonnull_array(element)?,
+        };
+        let value = match model {
+            Model::List => Value::List(items),
+            Model::Set => Value::Set(items),
+            Model::Hash => {
+                if items.len() % 2 != 0 {
+                    return Err(Error::Server("expected field/value pairs".to_owned()));
+                }
+                let mut items = items.into_iter();
+                let mut pairs = Vec::with_capacity(items.len() / 2);
+                while let (Some(field), Some(value)) = (items.next(), items.next()) {
+                    pairs.push((field, value));
+                }
+                Value::Hash(pairs)
+            }
+            Model::Str => unreachable!(),
+        };
+        format::write_entry(w, format, &Entry { key, value })?;
+        exported += 1;
+    }
+    Ok(exported)
+}
+
+/// Export every key in the current table to `file`, returning the number of exported keys.
+/// The keys are listed and read `batch` at a time, and are written out as they're read
+pub fn export(con: &mut Connection, file: &str, format: Format, batch: usize) -> DumpResult<usize> {
+    let mut writer = BufWriter::new(File::create(file)?);
+    let mut model = None;
+    let mut cursor = b"0".to_vec();
+    let mut exported = 0;
+    loop {
+        let query = Query::from("LSKEYS")
+            .arg("LIMIT")
+            .arg(batch.to_string())
+            .arg("CURSOR")
+            .arg(RawString::from(cursor));
+        let mut keys = self::into_nonnull_array(self::run(con, &query)?)?.into_iter();
+        cursor = keys
+            .next()
+            .ok_or_else(|| Error::Server("missing cursor".to_owned()))?;
+        let keys: Vec<Vec<u8>> = keys.collect();
+        if model.is_none() {
+            model = self::detect_model(con, &keys)?;
+            if format == Format::Csv && !matches!(model, None | Some(Model::Str)) {
+                return Err(Error::Runtime(
+                    "only key/value tables can be exported to CSV. Use JSON lines instead"
+                        .to_owned(),
+                ));
+            }
+        }
+        if let Some(model) = model {
+            exported += self::export_batch(con, &mut writer, format, model, keys)?;
+        }
+        if cursor == b"0" {
+            break;
+        }
+    }
+    writer.flush()?;
+    Ok(exported)
+}
+
+/// Returns the current time in milliseconds since the epoch
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Writes entries to the current table of a connection
+pub struct Importer {
+    con: Connection,
+    batch: usize,
+    /// the strings that haven't been written yet
+    uset: Query,
+    pending: usize,
+    /// the expiry times of the pending strings
+    expiries: Vec<(Vec<u8>, u64)>,
+    imported: usize,
+}
+
+impl Importer {
+    pub fn new(con: Connection, batch: usize) -> Self {
+        Self {
+            con,
+            batch,
+            uset: Query::from("USET"),
+            pending: 0,
+            expiries: Vec::new(),
+            imported: 0,
+        }
+    }
+    /// Import an entry that expires at `expires_at` (in milliseconds since the epoch), if
+    /// at all. Strings are upserted `batch` at a time, while lists, sets and hashes replace
+    /// the current value of their key
+    pub fn import(&mut self, entry: Entry, expires_at: Option<u64>) -> DumpResult<()> {
+        let Entry { key, value } = entry;
+        match value {
+            Value::Str(value) => {
+                if let Some(at) = expires_at {
+                    self.expiries.push((key.clone(), at));
+                }
+                self.uset.push(RawString::from(key));
+                self.uset.push(RawString::from(value));
+                self.pending += 1;
+                if self.pending == self.batch {
+                    self.flush()?;
+                }
+            }
+            Value::List(items) => self.replace("LSET", key, items, expires_at)?,
+            Value::Set(members) => self.replace("SADD", key, members, expires_at)?,
+            Value::Hash(pairs) => {
+                let args = pairs
+                    .into_iter()
+                    .flat_map(|(field, value)| [field, value])
+                    .collect();
+                self.replace("HSET", key, args, expires_at)?
+            }
+        }
+        self.imported += 1;
+        Ok(())
+    }
+    /// Write the pending strings
+    fn flush(&mut self) -> DumpResult<()> {
+        let uset = mem::replace(&mut self.uset, Query::from("USET"));
+        self::expect_count(self::run(&mut self.con, &uset)?)?;
+        self.pending = 0;
+        for (key, at) in mem::take(&mut self.expiries) {
+            self.expire(key, at)?;
+        }
+        Ok(())
+    }
+    fn expire(&mut self, key: Vec<u8>, at: u64) -> DumpResult<()> {
+        // round up, so that the key doesn't expire early
+        let seconds = ((at.saturating_sub(self::now_millis()) + 999) / 1000).max(1);
+        let query = Query::from("EXPIRE")
+            .arg(RawString::from(key))
+            .arg(seconds.to_string());
+        match self::run(&mut self.con, &query)? {
+            Element::RespCode(RespCode::Okay) => Ok(()),
+            other => Err(self::unexpected(other)),
+        }
+    }
+    /// Replace the value of `key` with a new one that is created by running `action`
+    fn replace(
+        &mut self,
+        action: &str,
+        key: Vec<u8>,
+        args: Vec<Vec<u8>>,
+        expires_at: Option<u64>,
+    ) -> DumpResult<()> {
+        let query = Query::from("DEL").arg(RawString::from(key.clone()));
+        self::expect_count(self::run(&mut self.con, &query)?)?;
+        // unlike lists, sets and hashes can't be created empty
+        if args.is_empty() && action != "LSET" {
+            return Ok(());
+        }
+        let mut query = Query::from(action).arg(RawString::from(key.clone()));
+        args.into_iter()
+            .for_each(|arg| query.push(RawString::from(arg)));
+        match self::run(&mut self.con, &query)? {
+            Element::RespCode(RespCode::Okay) | Element::UnsignedInt(_) => {}
+            other => return Err(self::unexpected(other)),
+        }
+        match expires_at {
+            Some(at) => self.expire(key, at),
+            None => Ok(()),
+        }
+    }
+    /// Write everything that's pending, returning the number of imported entries
+    pub fn finish(mut self) -> DumpResult<usize> {
+        if self.pending != 0 {
+            self.flush()?;
+        }
+        Ok(self.imported)
+    }
+}
+
+/// Import the entries in `file`, returning the number of imported entries. The file is read
+/// as the entries are imported
+pub fn import(mut importer: Importer, file: &str, format: Format) -> DumpResult<usize> {
+    let reader = BufReader::new(File::open(file)?);
+    for entry in format::read_entries(reader, format) {
+        importer.import(entry?, None)?;
+    }
+    importer.finish()
+}
+
+/// The tables that the keys of each type in an RDB file are imported into. The keys of the
+/// types that don't have a table are skipped
+pub struct RdbTargets {
+    pub strings: Importer,
+    pub lists: Option<Importer>,
+    pub sets: Option<Importer>,
+    pub hashes: Option<Importer>,
+}
+
+/// Import the keys in the Redis database numbered `db` from the RDB file at `file`. Keys that
+/// have already expired are ignored. Returns the number of imported and skipped keys
+pub fn import_rdb(file: &str, db: u64, mut targets: RdbTargets) -> DumpResult<(usize, usize)> {
+    let now = self::now_millis();
+    let mut skipped = 0;
+    for entry in RdbReader::new(BufReader::new(File::open(file)?))? {
+        let RdbEntry {
+            db: entry_db,
+            expires_at,
+            key,
+            value,
+        } = entry?;
+        if entry_db != db || expires_at.map_or(false, |at| at <= now) {
+            continue;
+        }
+        let target = match &value {
+            Ok(Value::Str(_)) => Some(&mut targets.strings),
+            Ok(Value::List(_)) => targets.lists.as_mut(),
+            Ok(Value::Set(_)) => targets.sets.as_mut(),
+            Ok(Value::Hash(_)) => targets.hashes.as_mut(),
+            Err(_) => None,
+        };
+        match (target, value) {
+            (Some(target), Ok(value)) => target.import(Entry { key, value }, expires_at)?,
+            _ => skipped += 1,
+        }
+    }
+    let mut imported = targets.strings.finish()?;
+    for target in [targets.lists, targets.sets, targets.hashes]
+        .into_iter()
+        .flatten()
+    {
+        imported += target.finish()?;
+    }
+    Ok((imported, skipped))
+}
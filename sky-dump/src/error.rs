@@ -0,0 +1,79 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+use {
+    skytable::error::Error as SkyError,
+    std::{fmt, io::Error as IoError},
+};
+
+pub type DumpResult<T> = Result<T, Error>;
+
+/// Dump tool errors
+pub enum Error {
+    /// An error originating from the Skytable client
+    Client(SkyError),
+    /// An I/O error while reading or writing the file
+    Io(IoError),
+    /// A bad entry in the file that is being imported
+    BadEntry(usize, String),
+    /// A corrupted (or unsupported) RDB file
+    BadRdb(String),
+    /// An unexpected response from the server
+    Server(String),
+    /// A runtime error
+    Runtime(String),
+}
+
+impl From<SkyError> for Error {
+    fn from(e: SkyError) -> Self {
+        Self::Client(e)
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(e: IoError) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Client(e) => write!(f, "client error: {}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::BadEntry(line, e) => write!(f, "bad entry on line {}: {}", line, e),
+            Error::BadRdb(e) => write!(f, "bad RDB file: {}", e),
+            Error::Server(e) => write!(f, "server error: {}", e),
+            Error::Runtime(e) => write!(f, "runtime error: {}", e),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
@@ -0,0 +1,465 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Dump formats
+//!
+//! Entries are written one at a time and read back one at a time, so neither an export nor an
+//! import needs to hold the whole table in memory. The supported formats are:
+//! - JSON lines: one object per entry that looks like
+//!   `{"key": <bytes>, "type": "string" | "list" | "set" | "hash", "value": <value>}`. The
+//!   value is `<bytes>` for a string, an array of `<bytes>` for a list or set and an array of
+//!   `[<field>, <value>]` pairs for a hash. `<bytes>` is a JSON string if the data is valid
+//!   UTF-8 and a `{"base64": "..."}` object otherwise
+//! - CSV: `<key>,<value>` rows with RFC 4180 quoting and no header. Since a row holds a single
+//!   value, this only works with key/value tables. The fields are written as is, so binary data
+//!   is kept intact
+
+use {
+    crate::error::{DumpResult, Error},
+    clap::ValueEnum,
+    serde_json::{json, Value as Json},
+    std::{
+        io::{BufRead, Error as IoError, Write},
+        mem, str,
+    },
+};
+
+/// The formats that tables can be exported to (and imported from)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line (works with every model)
+    Jsonl,
+    /// `<key>,<value>` rows (only works with key/value tables)
+    Csv,
+}
+
+/// The value of an entry, which depends on the model of the table that it's from
+#[derive(Debug, PartialEq, Eq)]
+pub enum Value {
+    Str(Vec<u8>),
+    List(Vec<Vec<u8>>),
+    Set(Vec<Vec<u8>>),
+    Hash(Vec<(Vec<u8>, Vec<u8>)>),
+}
+
+impl Value {
+    const fn type_name(&self) -> &'static str {
+        match self {
+            Self::Str(_) => "string",
+            Self::List(_) => "list",
+            Self::Set(_) => "set",
+            Self::Hash(_) => "hash",
+        }
+    }
+}
+
+/// A key and its value
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub key: Vec<u8>,
+    pub value: Value,
+}
+
+/// Write `entry` to `w` in the given format
+pub fn write_entry(w: &mut impl Write, format: Format, entry: &Entry) -> DumpResult<()> {
+    match format {
+        Format::Jsonl => self::write_jsonl(w, entry),
+        Format::Csv => self::write_csv(w, entry),
+    }
+}
+
+/// Returns an iterator over the entries in `reader`
+pub fn read_entries<'a, R: BufRead + 'a>(
+    reader: R,
+    format: Format,
+) -> Box<dyn Iterator<Item = DumpResult<Entry>> + 'a> {
+    match format {
+        Format::Jsonl => Box::new(JsonlReader {
+            reader,
+            line: 0,
+            buf: String::new(),
+        }),
+        Format::Csv => Box::new(CsvReader {
+            reader,
+            line: 0,
+            buf: Vec::new(),
+        }),
+    }
+}
+
+// JSON lines
+
+fn bytes_to_json(bytes: &[u8]) -> Json {
+    match str::from_utf8(bytes) {
+        Ok(string) => Json::String(string.to_owned()),
+        Err(_) => json!({ "base64": base64::encode(bytes) }),
+    }
+}
+
+fn bytes_from_json(json: Json) -> Result<Vec<u8>, String> {
+    match json {
+        Json::String(string) => Ok(string.into_bytes()),
+        Json::Object(mut obj) if obj.len() == 1 => match obj.remove("base64") {
+            Some(Json::String(b64)) => base64::decode(b64).map_err(|e| format!("bad base64: {e}")),
+            _ => Err("expected a `base64` string".to_owned()),
+        },
+        _ => Err("expected a string or a `base64` object".to_owned()),
+    }
+}
+
+fn array_from_json(json: Json) -> Result<Vec<Json>, String> {
+    match json {
+        Json::Array(array) => Ok(array),
+        _ => Err("expected an array".to_owned()),
+    }
+}
+
+fn bytes_array_from_json(json: Json) -> Result<Vec<Vec<u8>>, String> {
+    self::array_from_json(json)?
+        .into_iter()
+        .map(self::bytes_from_json)
+        .collect()
+}
+
+fn write_jsonl(w: &mut impl Write, entry: &Entry) -> DumpResult<()> {
+    let value = match &entry.value {
+        Value::Str(value) => self::bytes_to_json(value),
+        Value::List(items) | Value::Set(items) => {
+            Json::Array(items.iter().map(|item| self::bytes_to_json(item)).collect())
+        }
+        Value::Hash(pairs) => Json::Array(
+            pairs
+                .iter()
+                .map(|(field, value)| {
+                    json!([self::bytes_to_json(field), self::bytes_to_json(value)])
+                })
+                .collect(),
+        ),
+    };
+    let line = json!({
+        "key": self::bytes_to_json(&entry.key),
+        "type": entry.value.type_name(),
+        "value": value,
+    });
+    serde_json::to_writer(&mut *w, &line).map_err(IoError::from)?;
+    w.write_all(b"\n")?;
+    Ok(())
+}
+
+fn parse_jsonl(line: &str) -> Result<Entry, String> {
+    let mut obj = match serde_json::from_str(line).map_err(|e| e.to_string())? {
+        Json::Object(obj) => obj,
+        _ => return Err("expected an object".to_owned()),
+    };
+    let key = self::bytes_from_json(obj.remove("key").ok_or("missing `key`")?)?;
+    let value = obj.remove("value").ok_or("missing `value`")?;
+    let value = match obj.get("type").and_then(Json::as_str) {
+        Some("string") => Value::Str(self::bytes_from_json(value)?),
+        Some("list") => Value::List(self::bytes_array_from_json(value)?),
+        Some("set") => Value::Set(self::bytes_array_from_json(value)?),
+        Some("hash") => {
+            let mut pairs = Vec::new();
+            for pair in self::array_from_json(value)? {
+                match <[Vec<u8>; 2]>::try_from(self::bytes_array_from_json(pair)?) {
+                    Ok([field, value]) => pairs.push((field, value)),
+                    Err(_) => return Err("expected a `[<field>, <value>]` pair".to_owned()),
+                }
+            }
+            Value::Hash(pairs)
+        }
+        _ => return Err("`type` should be one of string, list, set or hash".to_owned()),
+    };
+    Ok(Entry { key, value })
+}
+
+struct JsonlReader<R> {
+    reader: R,
+    line: usize,
+    buf: String,
+}
+
+impl<R: BufRead> Iterator for JsonlReader<R> {
+    type Item = DumpResult<Entry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => self.line += 1,
+                Err(e) => return Some(Err(e.into())),
+            }
+            let line = self.buf.trim();
+            if !line.is_empty() {
+                return Some(self::parse_jsonl(line).map_err(|e| Error::BadEntry(self.line, e)));
+            }
+        }
+    }
+}
+
+// CSV
+
+fn write_csv_field(w: &mut impl Write, field: &[u8]) -> DumpResult<()> {
+    if field
+        .iter()
+        .any(|b| matches!(b, b',' | b'"' | b'\n' | b'\r'))
+    {
+        w.write_all(b"\"")?;
+        for (i, chunk) in field.split(|b| *b == b'"').enumerate() {
+            if i != 0 {
+                // every quote is escaped by doubling it
+                w.write_all(b"\"\"")?;
+            }
+            w.write_all(chunk)?;
+        }
+        w.write_all(b"\"")?;
+    } else {
+        w.write_all(field)?;
+    }
+    Ok(())
+}
+
+fn write_csv(w: &mut impl Write, entry: &Entry) -> DumpResult<()> {
+    let value = match &entry.value {
+        Value::Str(value) => value,
+        _ => {
+            return Err(Error::Runtime(
+                "only key/value tables can be exported to CSV. Use JSON lines instead".to_owned(),
+            ))
+        }
+    };
+    self::write_csv_field(w, &entry.key)?;
+    w.write_all(b",")?;
+    self::write_csv_field(w, value)?;
+    w.write_all(b"\n")?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CsvState {
+    /// at the start of a field
+    Start,
+    /// in a field that isn't quoted
+    Unquoted,
+    /// in a quoted field
+    Quoted,
+    /// just saw a quote in a quoted field (it either ends the field or escapes a quote)
+    QuotedQuote,
+}
+
+struct CsvReader<R> {
+    reader: R,
+    line: usize,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    /// Read the next record, which can span multiple lines if a quoted field has newlines
+    fn read_record(&mut self) -> DumpResult<Option<Vec<Vec<u8>>>> {
+        let mut fields = Vec::new();
+        let mut field = Vec::new();
+        let mut state = CsvState::Start;
+        let mut started = false;
+        loop {
+            self.buf.clear();
+            if self.reader.read_until(b'\n', &mut self.buf)? == 0 {
+                // EOF
+                return match state {
+                    CsvState::Quoted => Err(Error::BadEntry(
+                        self.line,
+                        "unterminated quoted field".to_owned(),
+                    )),
+                    _ if !started => Ok(None),
+                    _ => {
+                        fields.push(field);
+                        Ok(Some(fields))
+                    }
+                };
+            }
+            self.line += 1;
+            started = true;
+            let mut bytes = self.buf.iter().copied().peekable();
+            while let Some(byte) = bytes.next() {
+                match (state, byte) {
+                    (CsvState::Quoted, b'"') => state = CsvState::QuotedQuote,
+                    (CsvState::Quoted, byte) => field.push(byte),
+                    (CsvState::QuotedQuote, b'"') => {
+                        field.push(b'"');
+                        state = CsvState::Quoted;
+                    }
+                    (_, b'\r') if bytes.peek() == Some(&b'\n') => {}
+                    (_, b'\n') => {
+                        fields.push(field);
+                        return Ok(Some(fields));
+                    }
+                    (_, b',') => {
+                        fields.push(mem::take(&mut field));
+                        state = CsvState::Start;
+                    }
+                    (CsvState::Start, b'"') => state = CsvState::Quoted,
+                    (CsvState::QuotedQuote, _) => {
+                        return Err(Error::BadEntry(
+                            self.line,
+                            "unexpected character after a closing quote".to_owned(),
+                        ))
+                    }
+                    (_, byte) => {
+                        field.push(byte);
+                        state = CsvState::Unquoted;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CsvReader<R> {
+    type Item = DumpResult<Entry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let fields = match self.read_record() {
+                Ok(Some(fields)) => fields,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            match <[Vec<u8>; 2]>::try_from(fields) {
+                Ok([key, value]) => {
+                    return Some(Ok(Entry {
+                        key,
+                        value: Value::Str(value),
+                    }))
+                }
+                // skip blank lines
+                Err(fields) if fields.len() == 1 && fields[0].is_empty() => continue,
+                Err(_) => {
+                    return Some(Err(Error::BadEntry(
+                        self.line,
+                        "expected a `<key>,<value>` row".to_owned(),
+                    )))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_entries, write_entry, Entry, Format, Value};
+
+    fn roundtrip(format: Format, entries: &[Entry]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for entry in entries {
+            write_entry(&mut buf, format, entry).unwrap();
+        }
+        let read: Vec<Entry> = read_entries(buf.as_slice(), format)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(read, entries);
+        buf
+    }
+
+    #[test]
+    fn jsonl_roundtrip() {
+        let entries = [
+            Entry {
+                key: b"name".to_vec(),
+                value: Value::Str(b"sayan".to_vec()),
+            },
+            Entry {
+                key: vec![0xff, 0x00],
+                value: Value::Str(vec![0xfe]),
+            },
+            Entry {
+                key: b"list".to_vec(),
+                value: Value::List(vec![b"a".to_vec(), b"b\n".to_vec()]),
+            },
+            Entry {
+                key: b"set".to_vec(),
+                value: Value::Set(vec![]),
+            },
+            Entry {
+                key: b"hash".to_vec(),
+                value: Value::Hash(vec![(b"f".to_vec(), b"v".to_vec())]),
+            },
+        ];
+        let buf = roundtrip(Format::Jsonl, &entries);
+        let first_line = buf.split(|b| *b == b'\n').next().unwrap();
+        assert_eq!(
+            first_line,
+            br#"{"key":"name","type":"string","value":"sayan"}"#
+        );
+    }
+
+    #[test]
+    fn csv_roundtrip() {
+        let entries = [
+            Entry {
+                key: b"plain".to_vec(),
+                value: Value::Str(b"value".to_vec()),
+            },
+            Entry {
+                key: b"with,comma".to_vec(),
+                value: Value::Str(b"say \"hello\"\r\nand bye".to_vec()),
+            },
+            Entry {
+                key: b"empty".to_vec(),
+                value: Value::Str(vec![]),
+            },
+        ];
+        let buf = roundtrip(Format::Csv, &entries);
+        assert!(buf.starts_with(b"plain,value\n\"with,comma\",\"say \"\"hello\"\"\r\nand bye\"\n"));
+    }
+
+    #[test]
+    fn csv_only_holds_strings() {
+        let entry = Entry {
+            key: b"list".to_vec(),
+            value: Value::List(vec![]),
+        };
+        assert!(write_entry(&mut Vec::new(), Format::Csv, &entry).is_err());
+    }
+
+    #[test]
+    fn bad_entries() {
+        let bad_jsonl: [&[u8]; 4] = [
+            b"[1, 2]",
+            br#"{"key": "k", "type": "zset", "value": []}"#,
+            br#"{"key": {"base64": "!!"}, "type": "string", "value": "v"}"#,
+            br#"{"key": "k", "type": "hash", "value": [["f"]]}"#,
+        ];
+        for line in bad_jsonl {
+            assert!(read_entries(line, Format::Jsonl).next().unwrap().is_err());
+        }
+        let bad_csv: [&[u8]; 3] = [b"key\n", b"\"key\"x,value\n", b"\"key,value\n"];
+        for row in bad_csv {
+            assert!(read_entries(row, Format::Csv).next().unwrap().is_err());
+        }
+        // blank lines are skipped
+        assert!(read_entries(b"\n\n".as_ref(), Format::Csv).next().is_none());
+        assert!(read_entries(b"\n  \n".as_ref(), Format::Jsonl)
+            .next()
+            .is_none());
+    }
+}
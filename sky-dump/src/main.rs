@@ -0,0 +1,138 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # `sky-dump`
+//!
+//! Exports the keys in a table to a portable format (JSON lines or CSV) and imports them
+//! back. See the [`format`] module for the formats. Keys can also be imported from the RDB
+//! files of Redis (see the [`rdb`] module)
+
+use {
+    crate::{
+        cli::{Action, Cli},
+        dump::{Importer, RdbTargets},
+        error::{DumpResult, Error},
+    },
+    clap::Parser,
+    env_logger::Builder,
+    skytable::{sync::Connection, Element, Query, RespCode},
+    std::{env, process},
+};
+
+#[macro_use]
+extern crate log;
+
+mod cli;
+mod dump;
+mod error;
+mod format;
+mod rdb;
+
+fn main() {
+    Builder::new()
+        .parse_filters(&env::var("SKYDUMP_LOG").unwrap_or_else(|_| "info".to_owned()))
+        .init();
+    if let Err(e) = run() {
+        error!("sky-dump exited with error: {}", e);
+        process::exit(0x01);
+    }
+}
+
+fn expect_okay(con: &mut Connection, query: Query) -> DumpResult<()> {
+    match dump::run(con, &query)? {
+        Element::RespCode(RespCode::Okay) => Ok(()),
+        other => Err(dump::unexpected(other)),
+    }
+}
+
+/// Connect to the server and select `entity`
+fn connect(cli: &Cli, entity: &str) -> DumpResult<Connection> {
+    let mut con = Connection::new(&cli.host, cli.port)?;
+    if let (Some(user), Some(token)) = (&cli.user, &cli.token) {
+        expect_okay(
+            &mut con,
+            Query::from("AUTH").arg("LOGIN").arg(user).arg(token),
+        )?;
+    }
+    let use_entity = format!("USE {}", entity);
+    expect_okay(&mut con, Query::from(use_entity.as_str()))?;
+    Ok(con)
+}
+
+fn run() -> DumpResult<()> {
+    let cli = Cli::parse();
+    if cli.batch == 0 {
+        return Err(Error::Runtime("the batch size can't be zero".to_owned()));
+    }
+    let con = connect(&cli, &cli.entity)?;
+    match &cli.action {
+        Action::Export { file } => {
+            let mut con = con;
+            let exported = dump::export(&mut con, file, cli.format, cli.batch)?;
+            info!("Exported {} keys from {} to {}", exported, cli.entity, file);
+        }
+        Action::Import { file } => {
+            let imported = dump::import(Importer::new(con, cli.batch), file, cli.format)?;
+            info!(
+                "Imported {} keys from {} into {}",
+                imported, file, cli.entity
+            );
+        }
+        Action::ImportRdb {
+            file,
+            db,
+            lists,
+            sets,
+            hashes,
+        } => {
+            let importer = |entity: &Option<String>| {
+                entity
+                    .as_deref()
+                    .map(|entity| connect(&cli, entity).map(|con| Importer::new(con, cli.batch)))
+                    .transpose()
+            };
+            let targets = RdbTargets {
+                strings: Importer::new(con, cli.batch),
+                lists: importer(lists)?,
+                sets: importer(sets)?,
+                hashes: importer(hashes)?,
+            };
+            let (imported, skipped) = dump::import_rdb(file, *db, targets)?;
+            info!(
+                "Imported {} keys from database {} of {}",
+                imported, db, file
+            );
+            if skipped != 0 {
+                warn!(
+                    "Skipped {} keys of types without a table (sorted sets, streams and module \
+                     types can't be imported)",
+                    skipped
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,754 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+//! # Redis RDB files
+//!
+//! A streaming reader for the RDB files that Redis saves its data in (up to RDB version 12,
+//! which is used by Redis 7.2). Strings, lists, sets and hashes are decoded in every encoding
+//! that Redis uses for them (including ziplists, listpacks, intsets and zipmaps). Sorted sets,
+//! streams and module types are read past, since they have no Skytable equivalent
+
+use {
+    crate::{
+        error::{DumpResult, Error},
+        format::Value,
+    },
+    std::io::{self, Read},
+};
+
+const RDB_MAGIC: &[u8] = b"REDIS";
+const RDB_MAX_VERSION: u32 = 12;
+
+// opcodes
+const OP_SLOT_INFO: u8 = 0xF4;
+const OP_FUNCTION2: u8 = 0xF5;
+const OP_MODULE_AUX: u8 = 0xF7;
+const OP_IDLE: u8 = 0xF8;
+const OP_FREQ: u8 = 0xF9;
+const OP_AUX: u8 = 0xFA;
+const OP_RESIZEDB: u8 = 0xFB;
+const OP_EXPIRETIME_MS: u8 = 0xFC;
+const OP_EXPIRETIME: u8 = 0xFD;
+const OP_SELECTDB: u8 = 0xFE;
+const OP_EOF: u8 = 0xFF;
+
+// value types
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_MODULE_2: u8 = 7;
+const TYPE_HASH_ZIPMAP: u8 = 9;
+const TYPE_LIST_ZIPLIST: u8 = 10;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+const TYPE_HASH_ZIPLIST: u8 = 13;
+const TYPE_LIST_QUICKLIST: u8 = 14;
+const TYPE_STREAM_LISTPACKS: u8 = 15;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+const TYPE_STREAM_LISTPACKS_2: u8 = 19;
+const TYPE_SET_LISTPACK: u8 = 20;
+const TYPE_STREAM_LISTPACKS_3: u8 = 21;
+
+/// A quicklist node that holds a single element as is (instead of a listpack)
+const QUICKLIST_NODE_PLAIN: u64 = 1;
+
+// the opcodes in the data of a module type
+const MODULE_OP_EOF: u64 = 0;
+const MODULE_OP_SINT: u64 = 1;
+const MODULE_OP_UINT: u64 = 2;
+const MODULE_OP_FLOAT: u64 = 3;
+const MODULE_OP_DOUBLE: u64 = 4;
+const MODULE_OP_STRING: u64 = 5;
+
+fn bad(e: impl ToString) -> Error {
+    Error::BadRdb(e.to_string())
+}
+
+fn int_string(int: impl ToString) -> Vec<u8> {
+    int.to_string().into_bytes()
+}
+
+/// A key in an RDB file
+#[derive(Debug, PartialEq)]
+pub struct RdbEntry {
+    /// the number of the database that the key is in
+    pub db: u64,
+    /// the time the key expires at, in milliseconds since the epoch
+    pub expires_at: Option<u64>,
+    pub key: Vec<u8>,
+    /// the value, or the name of its type if it can't be imported
+    pub value: Result<Value, &'static str>,
+}
+
+/// The length of an object, or the encoding of a string that is stored in a special format
+enum Length {
+    Len(u64),
+    Encoded(u8),
+}
+
+/// Reads the keys in an RDB file in order
+pub struct RdbReader<R> {
+    reader: R,
+    db: u64,
+    done: bool,
+}
+
+impl<R: Read> RdbReader<R> {
+    /// Read the header of an RDB file
+    pub fn new(mut reader: R) -> DumpResult<Self> {
+        let mut header = [0u8; 9];
+        reader.read_exact(&mut header)?;
+        if !header.starts_with(RDB_MAGIC) {
+            return Err(bad("not an RDB file"));
+        }
+        let version = std::str::from_utf8(&header[RDB_MAGIC.len()..])
+            .ok()
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or_else(|| bad("bad version"))?;
+        if version == 0 || version > RDB_MAX_VERSION {
+            return Err(bad(format!("unsupported version {version}")));
+        }
+        Ok(Self {
+            reader,
+            db: 0,
+            done: false,
+        })
+    }
+    fn u8(&mut self) -> DumpResult<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+    fn array<const N: usize>(&mut self) -> DumpResult<[u8; N]> {
+        let mut array = [0u8; N];
+        self.reader.read_exact(&mut array)?;
+        Ok(array)
+    }
+    fn bytes(&mut self, len: u64) -> DumpResult<Vec<u8>> {
+        // don't trust the length with a preallocation, since the file might be corrupted
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(bad("unexpected end of file"));
+        }
+        Ok(bytes)
+    }
+    fn skip(&mut self, len: u64) -> DumpResult<()> {
+        if io::copy(&mut (&mut self.reader).take(len), &mut io::sink())? != len {
+            return Err(bad("unexpected end of file"));
+        }
+        Ok(())
+    }
+    fn length_or_encoding(&mut self) -> DumpResult<Length> {
+        let first = self.u8()?;
+        let len = match first >> 6 {
+            0 => (first & 0x3F) as u64,
+            1 => ((first as u64 & 0x3F) << 8) | self.u8()? as u64,
+            2 => match first {
+                0x80 => u32::from_be_bytes(self.array()?) as u64,
+                0x81 => u64::from_be_bytes(self.array()?),
+                _ => return Err(bad(format!("bad length encoding {first:#x}"))),
+            },
+            _ => return Ok(Length::Encoded(first & 0x3F)),
+        };
+        Ok(Length::Len(len))
+    }
+    fn length(&mut self) -> DumpResult<u64> {
+        match self.length_or_encoding()? {
+            Length::Len(len) => Ok(len),
+            Length::Encoded(_) => Err(bad("expected a length")),
+        }
+    }
+    fn string(&mut self) -> DumpResult<Vec<u8>> {
+        match self.length_or_encoding()? {
+            Length::Len(len) => self.bytes(len),
+            Length::Encoded(0) => Ok(int_string(self.u8()? as i8)),
+            Length::Encoded(1) => Ok(int_string(i16::from_le_bytes(self.array()?))),
+            Length::Encoded(2) => Ok(int_string(i32::from_le_bytes(self.array()?))),
+            Length::Encoded(3) => {
+                let compressed_len = self.length()?;
+                let len = self.length()?;
+                let compressed = self.bytes(compressed_len)?;
+                self::lzf_decompress(&compressed, len)
+            }
+            Length::Encoded(enc) => Err(bad(format!("unknown string encoding {enc}"))),
+        }
+    }
+    /// Read past a sequence of module opcodes (up to the EOF opcode)
+    fn skip_module_data(&mut self) -> DumpResult<()> {
+        loop {
+            match self.length()? {
+                MODULE_OP_EOF => return Ok(()),
+                MODULE_OP_SINT | MODULE_OP_UINT => {
+                    self.length()?;
+                }
+                MODULE_OP_FLOAT => self.skip(4)?,
+                MODULE_OP_DOUBLE => self.skip(8)?,
+                MODULE_OP_STRING => {
+                    self.string()?;
+                }
+                op => return Err(bad(format!("unknown module opcode {op}"))),
+            }
+        }
+    }
+    fn skip_stream(&mut self, ty: u8) -> DumpResult<()> {
+        // the listpacks (keyed by their master ID)
+        for _ in 0..self.length()? {
+            self.string()?;
+            self.string()?;
+        }
+        // the length and the last ID (and the first ID, the max deleted ID and the number of
+        // added entries from v2)
+        let metadata = if ty == TYPE_STREAM_LISTPACKS { 3 } else { 8 };
+        for _ in 0..metadata {
+            self.length()?;
+        }
+        for _ in 0..self.length()? {
+            // the name and the last delivered ID of the consumer group (and the number of
+            // read entries from v2)
+            self.string()?;
+            let ids = if ty == TYPE_STREAM_LISTPACKS { 2 } else { 3 };
+            for _ in 0..ids {
+                self.length()?;
+            }
+            // the pending entries: a raw ID, the delivery time and the delivery count
+            for _ in 0..self.length()? {
+                self.skip(16 + 8)?;
+                self.length()?;
+            }
+            // the consumers: the name, the seen time (and the active time from v3) and the
+            // IDs of the pending entries
+            for _ in 0..self.length()? {
+                self.string()?;
+                self.skip(if ty == TYPE_STREAM_LISTPACKS_3 { 16 } else { 8 })?;
+                let pending = self.length()?;
+                self.skip(pending.saturating_mul(16))?;
+            }
+        }
+        Ok(())
+    }
+    fn strings(&mut self) -> DumpResult<Vec<Vec<u8>>> {
+        (0..self.length()?).map(|_| self.string()).collect()
+    }
+    fn value(&mut self, ty: u8) -> DumpResult<Result<Value, &'static str>> {
+        let value = match ty {
+            TYPE_STRING => Value::Str(self.string()?),
+            TYPE_LIST => Value::List(self.strings()?),
+            TYPE_SET => Value::Set(self.strings()?),
+            TYPE_HASH => {
+                let len = self.length()?;
+                let pairs = (0..len)
+                    .map(|_| Ok((self.string()?, self.string()?)))
+                    .collect::<DumpResult<_>>()?;
+                Value::Hash(pairs)
+            }
+            TYPE_HASH_ZIPMAP => Value::Hash(self::pairs(self::zipmap(&self.string()?)?)?),
+            TYPE_LIST_ZIPLIST => Value::List(self::ziplist(&self.string()?)?),
+            TYPE_SET_INTSET => Value::Set(self::intset(&self.string()?)?),
+            TYPE_HASH_ZIPLIST => Value::Hash(self::pairs(self::ziplist(&self.string()?)?)?),
+            TYPE_LIST_QUICKLIST => {
+                let mut items = Vec::new();
+                for _ in 0..self.length()? {
+                    items.extend(self::ziplist(&self.string()?)?);
+                }
+                Value::List(items)
+            }
+            TYPE_HASH_LISTPACK => Value::Hash(self::pairs(self::listpack(&self.string()?)?)?),
+            TYPE_LIST_QUICKLIST_2 => {
+                let mut items = Vec::new();
+                for _ in 0..self.length()? {
+                    let container = self.length()?;
+                    let node = self.string()?;
+                    if container == QUICKLIST_NODE_PLAIN {
+                        items.push(node);
+                    } else {
+                        items.extend(self::listpack(&node)?);
+                    }
+                }
+                Value::List(items)
+            }
+            TYPE_SET_LISTPACK => Value::Set(self::listpack(&self.string()?)?),
+            TYPE_ZSET => {
+                for _ in 0..self.length()? {
+                    self.string()?;
+                    // the score is a string, except for NaN and the infinities
+                    let len = self.u8()?;
+                    if len < 253 {
+                        self.skip(len as u64)?;
+                    }
+                }
+                return Ok(Err("zset"));
+            }
+            TYPE_ZSET_2 => {
+                for _ in 0..self.length()? {
+                    self.string()?;
+                    self.skip(8)?;
+                }
+                return Ok(Err("zset"));
+            }
+            TYPE_ZSET_ZIPLIST | TYPE_ZSET_LISTPACK => {
+                self.string()?;
+                return Ok(Err("zset"));
+            }
+            TYPE_STREAM_LISTPACKS | TYPE_STREAM_LISTPACKS_2 | TYPE_STREAM_LISTPACKS_3 => {
+                self.skip_stream(ty)?;
+                return Ok(Err("stream"));
+            }
+            TYPE_MODULE_2 => {
+                // the module ID
+                self.length()?;
+                self.skip_module_data()?;
+                return Ok(Err("module"));
+            }
+            _ => return Err(bad(format!("unsupported value type {ty}"))),
+        };
+        Ok(Ok(value))
+    }
+    fn next_entry(&mut self) -> DumpResult<Option<RdbEntry>> {
+        let mut expires_at = None;
+        loop {
+            match self.u8()? {
+                // this is followed by a checksum, which we don't verify
+                OP_EOF => return Ok(None),
+                OP_SELECTDB => self.db = self.length()?,
+                OP_RESIZEDB => {
+                    self.length()?;
+                    self.length()?;
+                }
+                OP_AUX => {
+                    self.string()?;
+                    self.string()?;
+                }
+                OP_EXPIRETIME_MS => expires_at = Some(u64::from_le_bytes(self.array()?)),
+                OP_EXPIRETIME => {
+                    expires_at = Some(u32::from_le_bytes(self.array()?) as u64 * 1000);
+                }
+                OP_FREQ => {
+                    self.u8()?;
+                }
+                OP_IDLE => {
+                    self.length()?;
+                }
+                OP_MODULE_AUX => {
+                    // the module ID, the when opcode and when
+                    for _ in 0..3 {
+                        self.length()?;
+                    }
+                    self.skip_module_data()?;
+                }
+                OP_FUNCTION2 => {
+                    self.string()?;
+                }
+                OP_SLOT_INFO => {
+                    for _ in 0..3 {
+                        self.length()?;
+                    }
+                }
+                ty => {
+                    let key = self.string()?;
+                    let value = self.value(ty)?;
+                    return Ok(Some(RdbEntry {
+                        db: self.db,
+                        expires_at,
+                        key,
+                        value,
+                    }));
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for RdbReader<R> {
+    type Item = DumpResult<RdbEntry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let next = self.next_entry().transpose();
+        self.done = !matches!(next, Some(Ok(_)));
+        next
+    }
+}
+
+/// Decompress LZF compressed data that decompresses to `len` bytes
+fn lzf_decompress(input: &[u8], len: u64) -> DumpResult<Vec<u8>> {
+    let truncated = || bad("truncated compressed string");
+    let mut out: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < input.len() {
+        let ctrl = input[i] as usize;
+        i += 1;
+        if ctrl < 32 {
+            // a run of `ctrl + 1` literal bytes
+            let run = input.get(i..i + ctrl + 1).ok_or_else(truncated)?;
+            out.extend_from_slice(run);
+            i += run.len();
+        } else {
+            // a back reference
+            let mut ref_len = ctrl >> 5;
+            if ref_len == 7 {
+                ref_len += *input.get(i).ok_or_else(truncated)? as usize;
+                i += 1;
+            }
+            ref_len += 2;
+            let offset = ((ctrl & 0x1F) << 8) + *input.get(i).ok_or_else(truncated)? as usize + 1;
+            i += 1;
+            if offset > out.len() {
+                return Err(bad("bad back reference in compressed string"));
+            }
+            // the reference can overlap with the bytes that it produces
+            for _ in 0..ref_len {
+                out.push(out[out.len() - offset]);
+            }
+        }
+    }
+    if out.len() as u64 != len {
+        return Err(bad("bad length of compressed string"));
+    }
+    Ok(out)
+}
+
+/// A cursor over the bytes of an encoded value
+struct Blob<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Blob<'a> {
+    fn take(&mut self, len: usize) -> DumpResult<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(bad("truncated encoded value"));
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+    fn array<const N: usize>(&mut self) -> DumpResult<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+    fn u8(&mut self) -> DumpResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+    fn i24(&mut self) -> DumpResult<i32> {
+        let [a, b, c] = self.array()?;
+        // shifting back sign extends it
+        Ok(i32::from_le_bytes([0, a, b, c]) >> 8)
+    }
+    fn bytes(&mut self, len: usize) -> DumpResult<Vec<u8>> {
+        self.take(len).map(<[u8]>::to_vec)
+    }
+}
+
+/// Split flattened field/value pairs
+fn pairs(flat: Vec<Vec<u8>>) -> DumpResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    if flat.len() % 2 != 0 {
+        return Err(bad("hash field without a value"));
+    }
+    let mut flat = flat.into_iter();
+    let mut pairs = Vec::with_capacity(flat.len() / 2);
+    while let (Some(field), Some(value)) = (flat.next(), flat.next()) {
+        pairs.push((field, value));
+    }
+    Ok(pairs)
+}
+
+/// Decode the entries of a ziplist
+fn ziplist(data: &[u8]) -> DumpResult<Vec<Vec<u8>>> {
+    let mut blob = Blob { data };
+    // the size in bytes, the offset of the tail and the number of entries
+    blob.take(10)?;
+    let mut entries = Vec::new();
+    loop {
+        // the length of the previous entry
+        match blob.u8()? {
+            0xFF => return Ok(entries),
+            0xFE => {
+                blob.take(4)?;
+            }
+            _ => {}
+        }
+        let enc = blob.u8()?;
+        let entry = match enc >> 6 {
+            0 => blob.bytes((enc & 0x3F) as usize)?,
+            1 => {
+                let len = ((enc as usize & 0x3F) << 8) | blob.u8()? as usize;
+                blob.bytes(len)?
+            }
+            2 => {
+                let len = u32::from_be_bytes(blob.array()?) as usize;
+                blob.bytes(len)?
+            }
+            _ => match enc {
+                0xC0 => int_string(i16::from_le_bytes(blob.array()?)),
+                0xD0 => int_string(i32::from_le_bytes(blob.array()?)),
+                0xE0 => int_string(i64::from_le_bytes(blob.array()?)),
+                0xF0 => int_string(blob.i24()?),
+                0xFE => int_string(blob.u8()? as i8),
+                // an immediate integer in 0..=12
+                0xF1..=0xFD => int_string((enc & 0x0F) - 1),
+                _ => return Err(bad(format!("unknown ziplist encoding {enc:#x}"))),
+            },
+        };
+        entries.push(entry);
+    }
+}
+
+/// Decode the entries of a listpack
+fn listpack(data: &[u8]) -> DumpResult<Vec<Vec<u8>>> {
+    let mut blob = Blob { data };
+    // the size in bytes and the number of entries
+    blob.take(6)?;
+    let mut entries = Vec::new();
+    loop {
+        let enc = blob.u8()?;
+        if enc == 0xFF {
+            return Ok(entries);
+        }
+        // the entry, and the number of bytes it took
+        let (entry, len) = if enc & 0x80 == 0 {
+            (int_string(enc & 0x7F), 1)
+        } else if enc & 0xC0 == 0x80 {
+            let len = (enc & 0x3F) as usize;
+            (blob.bytes(len)?, 1 + len)
+        } else if enc & 0xE0 == 0xC0 {
+            // a 13 bit two's complement integer
+            let uint = ((enc as i32 & 0x1F) << 8) | blob.u8()? as i32;
+            let int = if uint >= 1 << 12 {
+                uint - (1 << 13)
+            } else {
+                uint
+            };
+            (int_string(int), 2)
+        } else if enc & 0xF0 == 0xE0 {
+            let len = ((enc as usize & 0x0F) << 8) | blob.u8()? as usize;
+            (blob.bytes(len)?, 2 + len)
+        } else {
+            match enc {
+                0xF0 => {
+                    let len = u32::from_le_bytes(blob.array()?) as usize;
+                    (blob.bytes(len)?, 5 + len)
+                }
+                0xF1 => (int_string(i16::from_le_bytes(blob.array()?)), 3),
+                0xF2 => (int_string(blob.i24()?), 4),
+                0xF3 => (int_string(i32::from_le_bytes(blob.array()?)), 5),
+                0xF4 => (int_string(i64::from_le_bytes(blob.array()?)), 9),
+                _ => return Err(bad(format!("unknown listpack encoding {enc:#x}"))),
+            }
+        };
+        entries.push(entry);
+        // the length of the entry is repeated after it (7 bits per byte), so that the
+        // listpack can be walked backwards
+        let backlen = match len {
+            0..=127 => 1,
+            128..=16383 => 2,
+            16384..=2097151 => 3,
+            2097152..=268435455 => 4,
+            _ => 5,
+        };
+        blob.take(backlen)?;
+    }
+}
+
+/// Decode the members of an intset
+fn intset(data: &[u8]) -> DumpResult<Vec<Vec<u8>>> {
+    let mut blob = Blob { data };
+    let width = u32::from_le_bytes(blob.array()?);
+    let len = u32::from_le_bytes(blob.array()?);
+    (0..len)
+        .map(|_| match width {
+            2 => Ok(int_string(i16::from_le_bytes(blob.array()?))),
+            4 => Ok(int_string(i32::from_le_bytes(blob.array()?))),
+            8 => Ok(int_string(i64::from_le_bytes(blob.array()?))),
+            _ => Err(bad(format!("bad intset encoding {width}"))),
+        })
+        .collect()
+}
+
+/// Decode the flattened field/value pairs of a zipmap
+fn zipmap(data: &[u8]) -> DumpResult<Vec<Vec<u8>>> {
+    fn len(blob: &mut Blob) -> DumpResult<Option<usize>> {
+        match blob.u8()? {
+            0xFF => Ok(None),
+            0xFE => Ok(Some(u32::from_le_bytes(blob.array()?) as usize)),
+            len => Ok(Some(len as usize)),
+        }
+    }
+    let mut blob = Blob { data };
+    // the number of pairs (if it fits in a byte)
+    blob.take(1)?;
+    let mut flat = Vec::new();
+    while let Some(field_len) = len(&mut blob)? {
+        flat.push(blob.bytes(field_len)?);
+        let value_len = len(&mut blob)?.ok_or_else(|| bad("zipmap field without a value"))?;
+        // the number of unused bytes after the value
+        let free = blob.u8()? as usize;
+        flat.push(blob.bytes(value_len)?);
+        blob.take(free)?;
+    }
+    Ok(flat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RdbEntry, RdbReader};
+    use crate::format::Value;
+
+    fn string(s: &[u8]) -> Vec<u8> {
+        assert!(s.len() < 64);
+        [&[s.len() as u8][..], s].concat()
+    }
+
+    /// A listpack made of encoded entries that are less than 128 bytes long
+    fn listpack(entries: &[&[u8]]) -> Vec<u8> {
+        let mut lp = vec![0; 6];
+        for entry in entries {
+            lp.extend_from_slice(entry);
+            lp.push(entry.len() as u8);
+        }
+        lp.push(0xFF);
+        lp
+    }
+
+    fn read(rdb: &[u8]) -> Vec<RdbEntry> {
+        RdbReader::new(rdb)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn bytes(items: &[&str]) -> Vec<Vec<u8>> {
+        items.iter().map(|item| item.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_read_rdb() {
+        let mut rdb = b"REDIS0011".to_vec();
+        // aux fields and the database
+        rdb.push(0xFA);
+        rdb.extend(string(b"redis-ver"));
+        rdb.extend(string(b"7.2.0"));
+        rdb.extend([0xFE, 0x00, 0xFB, 0x05, 0x01]);
+        // a plain string
+        rdb.push(0x00);
+        rdb.extend(string(b"name"));
+        rdb.extend(string(b"sayan"));
+        // an integer encoded string that expires
+        rdb.push(0xFC);
+        rdb.extend(1234u64.to_le_bytes());
+        rdb.push(0x00);
+        rdb.extend(string(b"temp"));
+        rdb.extend([0xC0, 0x85]);
+        // a quicklist of a listpack with a string, a 7 bit integer and a 13 bit integer
+        rdb.push(18);
+        rdb.extend(string(b"list"));
+        rdb.extend([0x01, 0x02]);
+        rdb.extend(string(&listpack(&[b"\x81a", &[0x05], &[0xDF, 0xFF]])));
+        // an intset
+        rdb.push(11);
+        rdb.extend(string(b"set"));
+        rdb.extend(string(&[2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0xFF, 0xFF]));
+        // a listpack hash
+        rdb.push(16);
+        rdb.extend(string(b"hash"));
+        rdb.extend(string(&listpack(&[b"\x81f", b"\x81v"])));
+        // a listpack zset, which is skipped
+        rdb.push(17);
+        rdb.extend(string(b"zset"));
+        rdb.extend(string(&listpack(&[b"\x81m", &[0x01]])));
+        // another database
+        rdb.extend([0xFE, 0x01, 0x00]);
+        rdb.extend(string(b"other"));
+        rdb.extend(string(b"db1"));
+        rdb.push(0xFF);
+        rdb.extend([0; 8]);
+        let entry = |db, expires_at, key: &str, value| RdbEntry {
+            db,
+            expires_at,
+            key: key.as_bytes().to_vec(),
+            value,
+        };
+        assert_eq!(
+            read(&rdb),
+            vec![
+                entry(0, None, "name", Ok(Value::Str(b"sayan".to_vec()))),
+                entry(0, Some(1234), "temp", Ok(Value::Str(b"-123".to_vec()))),
+                entry(0, None, "list", Ok(Value::List(bytes(&["a", "5", "-1"])))),
+                entry(0, None, "set", Ok(Value::Set(bytes(&["1", "-1"])))),
+                entry(
+                    0,
+                    None,
+                    "hash",
+                    Ok(Value::Hash(vec![(b"f".to_vec(), b"v".to_vec())]))
+                ),
+                entry(0, None, "zset", Err("zset")),
+                entry(1, None, "other", Ok(Value::Str(b"db1".to_vec()))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_ziplist_and_lzf() {
+        let mut rdb = b"REDIS0006".to_vec();
+        // a ziplist with a string, an immediate integer and a 16 bit integer
+        let mut zl = vec![0; 10];
+        zl.extend([0x00, 0x01, b'a', 0x03, 0xF6, 0x02, 0xC0, 0x2C, 0x01, 0xFF]);
+        rdb.push(10);
+        rdb.extend(string(b"list"));
+        rdb.extend(string(&zl));
+        // "abcabc" and "aaaaa", compressed with back references
+        rdb.push(0x00);
+        rdb.extend(string(b"lzf"));
+        rdb.extend([0xC3, 0x06, 0x06, 0x02, b'a', b'b', b'c', 0x20, 0x02]);
+        rdb.push(0x00);
+        rdb.extend(string(b"overlap"));
+        rdb.extend([0xC3, 0x04, 0x05, 0x00, b'a', 0x40, 0x00]);
+        rdb.push(0xFF);
+        let values: Vec<_> = read(&rdb).into_iter().map(|e| e.value).collect();
+        assert_eq!(
+            values,
+            vec![
+                Ok(Value::List(bytes(&["a", "5", "300"]))),
+                Ok(Value::Str(b"abcabc".to_vec())),
+                Ok(Value::Str(b"aaaaa".to_vec())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bad_rdb() {
+        assert!(RdbReader::new(&b"REDIX0011"[..]).is_err());
+        assert!(RdbReader::new(&b"REDIS0099"[..]).is_err());
+        // a truncated value stops the reader
+        let mut reader = RdbReader::new(&b"REDIS0011\x00\x04name\x05sa"[..]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,61 @@
+/*
+ * Created on Fri Oct 16 2026
+ *
+ * This file is a part of Skytable
+ * Skytable (formerly known as TerrabaseDB or Skybase) is a free and open-source
+ * NoSQL database written by Sayan Nandan ("the Author") with the
+ * vision to provide flexibility in data modelling without compromising
+ * on performance, queryability or scalability.
+ *
+ * Copyright (c) 2026, Sayan Nandan <ohsayan@outlook.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+ *
+*/
+
+#![deny(unused_crate_dependencies)]
+#![deny(unused_imports)]
+
+//! # Skytable core
+//!
+//! Skytable's storage engine as a library, so that applications can use Skytable in-process
+//! (like sled) without running the network server. The data directory uses the same format
+//! as `skyd`, so it can be served by the server later on.
+//!
+//! ```no_run
+//! use skytable_core::Database;
+//!
+//! let db = Database::open("mydb")?;
+//! db.set("hello", "world");
+//! assert_eq!(db.get("hello").as_deref(), Some(&b"world"[..]));
+//! // scan through all the keys, a few at a time
+//! let mut cursor = 0;
+//! loop {
+//!     let (pairs, next) = db.scan(cursor, 100);
+//!     for (key, value) in pairs {
+//!         println!("{key:?} => {value:?}");
+//!     }
+//!     if next == 0 {
+//!         break;
+//!     }
+//!     cursor = next;
+//! }
+//! // also done when the database is dropped
+//! db.flush()?;
+//! # Ok::<(), skytable_core::Error>(())
+//! ```
+//!
+//! See [`Database`] for the caveats.
+
+pub use skyd::embedded::{Database, Error, Result};